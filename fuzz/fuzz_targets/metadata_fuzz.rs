@@ -0,0 +1,18 @@
+//! Fuzzes `extract_metadata_from_header` with arbitrary header text.
+//!
+//! Run from the repository root with `cargo +nightly fuzz run metadata_fuzz`
+//! (see `split_fuzz.rs` for the setup). Seeds are in
+//! `fuzz/corpus/metadata_fuzz`: the headers of books 84, 1342 and 11.
+
+#![no_main]
+
+use indexing_service::services::indexing::extract_metadata_from_header;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let header = String::from_utf8_lossy(data);
+    let metadata = extract_metadata_from_header(&header, 84);
+
+    assert_eq!(metadata.book_id, 84);
+    assert!(metadata.year.is_none_or(|year| year <= 9999));
+});
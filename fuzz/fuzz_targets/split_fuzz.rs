@@ -0,0 +1,32 @@
+//! Fuzzes `header_body_split`, which runs on whatever Gutenberg serves.
+//!
+//! Setup (cargo-fuzz needs a nightly toolchain):
+//!
+//! ```text
+//! cargo install cargo-fuzz
+//! cargo +nightly fuzz run split_fuzz
+//! ```
+//!
+//! Run from the repository root. The seed corpus in `fuzz/corpus/split_fuzz`
+//! holds the headers of books 84, 1342 and 11 with their START and END
+//! marker lines; new inputs the fuzzer finds interesting are added there,
+//! and a crashing one is saved under `fuzz/artifacts/split_fuzz` and replayed
+//! with `cargo +nightly fuzz run split_fuzz <file>`. Any panic fails the run
+//! (an out-of-bounds slice, an `unwrap`), as does an input taking longer
+//! than `-timeout` seconds (default: 1200), e.g.
+//! `cargo +nightly fuzz run split_fuzz -- -timeout=5` to catch slow inputs.
+
+#![no_main]
+
+use ingestion_service::utils::file::header_body_split;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let (header, body) = header_body_split(&text);
+
+    // Both parts are cut from the text: the header is a prefix of it and
+    // the body lies within it.
+    assert!(text.starts_with(&header));
+    assert!(text.contains(&body));
+});
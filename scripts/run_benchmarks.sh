@@ -225,6 +225,7 @@ echo "Starting benchmark suite..."
 # Run individual service benchmarks (Criterion HTML reports)
 run_service_benchmarks "ingestion-service" "ingestion_benchmark"
 run_service_benchmarks "indexing-service" "indexing_benchmark"
+run_service_benchmarks "indexing-service" "pipeline_benchmark"
 run_service_benchmarks "search-service" "search_benchmark"
 
 # Run container benchmarks if services are running
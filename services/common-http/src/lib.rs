@@ -0,0 +1,213 @@
+//! # Error Responses
+//!
+//! Shared by every service so failed requests answer in one shape, an
+//! [`ErrorResponse`]: a machine-readable `error_code` clients can branch on,
+//! a human-readable message and, where they apply, the book concerned and a
+//! finer-grained `reason`. Each service maps its own errors to a status code
+//! and an [`ErrorResponse`], answered with [`ErrorResponse::with_status`].
+//!
+//! [`attach_request_id`] tags every response with an `X-Request-Id` (the
+//! client's, or a fresh UUID) and copies it into error bodies. Errors raised
+//! before a handler runs, such as a path that doesn't parse or an unknown
+//! route, are rewritten into the same shape.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied request ID echoed back; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+/// Largest plain-text error body rewritten into an [`ErrorResponse`].
+const MAX_PLAIN_ERROR_BYTES: usize = 4096;
+
+/// Machine-readable error code plus a human-readable message, the book
+/// concerned and the request ID.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse {
+    /// Machine-readable, e.g. `BOOK_NOT_FOUND` or `VALIDATION_FAILED`.
+    pub error_code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub book_id: Option<u32>,
+    /// Machine-readable detail of `error_code`, e.g. `empty_body` for a
+    /// book failing validation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The request's `X-Request-Id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn new(error_code: &str, message: impl Into<String>) -> Self {
+        Self {
+            error_code: error_code.to_string(),
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The response answering with this error and `status`, for
+    /// [`attach_request_id`] to fill in the request ID.
+    pub fn with_status(self, status: StatusCode) -> Response {
+        let mut response = (status, Json(self.clone())).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Tags the response with the request's `X-Request-Id`, generating one when
+/// the client sent none, and turns any error response into an
+/// [`ErrorResponse`] carrying it.
+pub async fn attach_request_id(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = with_error_body(next.run(request).await, &request_id).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID.clone(), value);
+    }
+    response
+}
+
+async fn with_error_body(response: Response, request_id: &str) -> Response {
+    let status = response.status();
+    let (mut parts, body) = response.into_parts();
+
+    let error = match parts.extensions.remove::<ErrorResponse>() {
+        Some(error) => error,
+        // Extractor rejections and bare status codes
+        None if (status.is_client_error() || status.is_server_error()) && is_plain_text(&parts.headers) => {
+            let text = to_bytes(body, MAX_PLAIN_ERROR_BYTES).await.unwrap_or_default();
+            error_for_status(status, String::from_utf8_lossy(&text).trim())
+        }
+        None => return Response::from_parts(parts, body),
+    };
+
+    let error = ErrorResponse {
+        request_id: Some(request_id.to_string()),
+        ..error
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    let body = serde_json::to_vec(&error).unwrap_or_default();
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn is_plain_text(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|content_type| content_type.starts_with("text/plain"))
+}
+
+/// An error for a response that only had `status` and maybe a plain-text
+/// `message`.
+fn error_for_status(status: StatusCode, message: &str) -> ErrorResponse {
+    let error_code = match status {
+        StatusCode::UNAUTHORIZED => "UNAUTHORIZED",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::METHOD_NOT_ALLOWED => "METHOD_NOT_ALLOWED",
+        StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+        StatusCode::TOO_MANY_REQUESTS => "RATE_LIMITED",
+        StatusCode::SERVICE_UNAVAILABLE => "SERVICE_UNAVAILABLE",
+        StatusCode::GATEWAY_TIMEOUT => "GATEWAY_TIMEOUT",
+        status if status.is_client_error() => "INVALID_REQUEST",
+        _ => "INTERNAL_ERROR",
+    };
+    let message = if message.is_empty() {
+        status.canonical_reason().unwrap_or("Request failed")
+    } else {
+        message
+    };
+    ErrorResponse::new(error_code, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/books/:book_id",
+                get(|Path(book_id): Path<u32>| async move {
+                    ErrorResponse {
+                        book_id: Some(book_id),
+                        ..ErrorResponse::new("BOOK_NOT_FOUND", format!("Book {} not found", book_id))
+                    }
+                    .with_status(StatusCode::NOT_FOUND)
+                }),
+            )
+            .route("/slow", get(|| async { StatusCode::GATEWAY_TIMEOUT }))
+            .layer(middleware::from_fn(attach_request_id))
+    }
+
+    async fn get_error(uri: &str, request_id: Option<&str>) -> (StatusCode, HeaderMap, ErrorResponse) {
+        let mut request = Request::get(uri);
+        if let Some(id) = request_id {
+            request = request.header(&REQUEST_ID, id);
+        }
+        let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, usize::MAX).await.unwrap();
+        (parts.status, parts.headers, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn handler_errors_carry_code_book_and_request_id() {
+        let (status, headers, error) = get_error("/books/42", Some("req-1")).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error.error_code, "BOOK_NOT_FOUND");
+        assert_eq!(error.book_id, Some(42));
+        assert_eq!(error.request_id.as_deref(), Some("req-1"));
+        assert_eq!(headers[&REQUEST_ID], "req-1");
+    }
+
+    #[tokio::test]
+    async fn rejections_and_unknown_routes_get_the_same_shape() {
+        let (status, headers, error) = get_error("/books/abc", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error_code, "INVALID_REQUEST");
+        assert!(error.message.contains("parse"), "{}", error.message);
+        // A generated ID, echoed in the header
+        let generated = error.request_id.unwrap();
+        assert_eq!(headers[&REQUEST_ID], generated.as_str());
+
+        let (status, _, error) = get_error("/nowhere", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error.error_code, "NOT_FOUND");
+        assert_eq!(error.message, "Not Found");
+
+        let (_, _, error) = get_error("/slow", None).await;
+        assert_eq!(error.error_code, "GATEWAY_TIMEOUT");
+    }
+
+    #[tokio::test]
+    async fn overlong_request_ids_are_replaced() {
+        let (_, headers, error) = get_error("/books/42", Some(&"x".repeat(MAX_REQUEST_ID_LEN + 1))).await;
+
+        let generated = error.request_id.unwrap();
+        assert!(Uuid::parse_str(&generated).is_ok(), "{}", generated);
+        assert_eq!(headers[&REQUEST_ID], generated.as_str());
+    }
+}
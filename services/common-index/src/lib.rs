@@ -0,0 +1,207 @@
+//! # The Index's Shared Types
+//!
+//! The indexing service writes books and postings that the search service
+//! reads, so both describe them with the types here: a book's
+//! [`BookMetadata`] and the [`IndexField`] a word was found in.
+//!
+//! [`MemoryIndex`] is an index kept in the process. Each service's
+//! `MemoryBackend` stores the index in one, so a test can hand the same
+//! [`MemoryIndex`] to both and search what it just indexed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Metadata for an indexed book.
+///
+/// This structure is stored in the datamart and returned by search queries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BookMetadata {
+    pub book_id: u32,
+    pub title: String,
+    pub author: String,
+    pub language: String,
+    pub year: Option<u32>,
+    pub word_count: usize,
+    pub unique_words: usize,
+    #[serde(default)]
+    pub chapter_count: usize,
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    /// Flesch-Kincaid grade level of the body; `None` for books indexed
+    /// before it was computed or bodies without sentences.
+    #[serde(default)]
+    pub reading_level: Option<f64>,
+}
+
+/// The part of a book a word was found in.
+///
+/// Body and title postings go into the unscoped word set. `Title` and
+/// `Author` postings are kept in per-field sets so matches can be scoped or
+/// boosted; body postings dominate the index and are not duplicated, so a
+/// `Body` lookup reads the unscoped set. Author postings are only kept per
+/// field, so a name only matches queries that ask for the author.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexField {
+    Title,
+    Author,
+    #[default]
+    Body,
+}
+
+impl IndexField {
+    pub const ALL: [IndexField; 3] = [IndexField::Title, IndexField::Author, IndexField::Body];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndexField::Title => "title",
+            IndexField::Author => "author",
+            IndexField::Body => "body",
+        }
+    }
+
+    pub fn is_body(&self) -> bool {
+        *self == IndexField::Body
+    }
+
+    /// Whether postings in this field also go into the unscoped word set.
+    pub fn is_unscoped(&self) -> bool {
+        *self != IndexField::Author
+    }
+}
+
+/// An index kept in hash maps behind a shared lock, so clones see the same
+/// data. Nothing is persisted.
+#[derive(Clone, Default)]
+pub struct MemoryIndex {
+    state: Arc<RwLock<IndexState>>,
+}
+
+impl MemoryIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, IndexState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, IndexState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The books and postings of a [`MemoryIndex`].
+#[derive(Default)]
+pub struct IndexState {
+    pub books: HashMap<u32, BookMetadata>,
+    /// The unscoped word set, in word order for prefix lookups.
+    pub words: BTreeMap<String, HashSet<u32>>,
+    pub field_words: HashMap<(IndexField, String), HashSet<u32>>,
+    /// word -> (book, chapter)
+    pub chapter_words: HashMap<String, HashSet<(u32, usize)>>,
+    /// word -> (book, sentence)
+    pub sentence_words: HashMap<String, HashSet<(u32, usize)>>,
+    /// Books whose postings are still being written.
+    pub incomplete: HashSet<u32>,
+    /// When each book was last stored.
+    pub indexed_at: HashMap<u32, DateTime<Utc>>,
+    pub diacritic_folding: Option<bool>,
+    /// Bumped whenever a book is stored or removed.
+    pub version: u64,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+impl IndexState {
+    pub fn store_book(&mut self, metadata: &BookMetadata) {
+        let now = Utc::now();
+        self.books.insert(metadata.book_id, metadata.clone());
+        self.indexed_at.insert(metadata.book_id, now);
+        self.version += 1;
+        self.last_updated = Some(now);
+    }
+
+    /// Removes a book's metadata, leaving its postings; false if it wasn't
+    /// stored.
+    pub fn remove_book(&mut self, book_id: u32) -> bool {
+        if self.books.remove(&book_id).is_none() {
+            return false;
+        }
+        self.indexed_at.remove(&book_id);
+        self.incomplete.remove(&book_id);
+        self.version += 1;
+        self.last_updated = Some(Utc::now());
+        true
+    }
+
+    pub fn set_incomplete(&mut self, book_id: u32, incomplete: bool) {
+        if incomplete {
+            self.incomplete.insert(book_id);
+        } else {
+            self.incomplete.remove(&book_id);
+        }
+        self.last_updated = Some(Utc::now());
+    }
+
+    /// Adds the books to the word's postings in `field`, and to its
+    /// unscoped postings if the field is (see [`IndexField`]).
+    pub fn add_postings(&mut self, word: &str, book_ids: &[u32], field: IndexField) {
+        if field.is_unscoped() {
+            self.words.entry(word.to_string()).or_default().extend(book_ids);
+        }
+        if !field.is_body() {
+            self.field_words.entry((field, word.to_string())).or_default().extend(book_ids);
+        }
+    }
+
+    /// The books the word is in within `field`.
+    pub fn postings(&self, word: &str, field: IndexField) -> Option<&HashSet<u32>> {
+        match field {
+            IndexField::Body => self.words.get(word),
+            field => self.field_words.get(&(field, word.to_string())),
+        }
+    }
+
+    /// The words with postings in `field`, where `Body` means the unscoped
+    /// word set; in no particular order except for `Body`.
+    pub fn vocabulary(&self, field: IndexField) -> Box<dyn Iterator<Item = &String> + '_> {
+        match field {
+            IndexField::Body => Box::new(self.words.keys()),
+            field => Box::new(self.field_words.keys().filter(move |(f, _)| *f == field).map(|(_, word)| word)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn author_postings_stay_out_of_the_unscoped_words() {
+        let index = MemoryIndex::new();
+        let mut state = index.write();
+        state.add_postings("melville", &[1], IndexField::Author);
+        state.add_postings("whale", &[1, 2], IndexField::Title);
+
+        assert!(state.postings("melville", IndexField::Body).is_none());
+        assert_eq!(state.postings("melville", IndexField::Author), Some(&HashSet::from([1])));
+        assert_eq!(state.postings("whale", IndexField::Body), Some(&HashSet::from([1, 2])));
+        assert_eq!(state.vocabulary(IndexField::Body).collect::<Vec<_>>(), ["whale"]);
+    }
+
+    #[test]
+    fn clones_share_the_index() {
+        let index = MemoryIndex::new();
+        let book = BookMetadata { book_id: 7, ..Default::default() };
+        index.clone().write().store_book(&book);
+
+        assert!(index.read().books.contains_key(&7));
+        assert_eq!(index.read().version, 1);
+        assert!(index.write().remove_book(7));
+        assert!(!index.write().remove_book(7));
+        assert!(index.read().indexed_at.is_empty());
+    }
+}
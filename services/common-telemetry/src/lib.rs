@@ -0,0 +1,12 @@
+//! # Logging and Tracing
+//!
+//! Shared by every service so they read the same logging variables and
+//! carry one trace across their calls to each other:
+//!
+//! - [`logging`]: installs the tracing subscriber from `RUST_LOG`,
+//!   `LOG_LEVEL_*` and `LOG_FORMAT`
+//! - [`trace_context`]: continues W3C TraceContext traces of incoming
+//!   requests, adds them to outgoing ones and exports spans over OTLP
+
+pub mod logging;
+pub mod trace_context;
@@ -0,0 +1,123 @@
+//! Logging Setup
+//!
+//! Every service initializes tracing through [`init_tracing`], so they all
+//! read the same variables. For each target, a `LOG_LEVEL_*` variable beats
+//! a directive for it in `RUST_LOG`, which beats the default.
+//!
+//! ## Environment Variables
+//! - `RUST_LOG`: Extra `EnvFilter` directives, e.g. `warn,sqlx=info` (default: none)
+//! - `LOG_LEVEL_SERVICE`: Level of the service's own logs (default: `info`)
+//! - `LOG_LEVEL_TOWER`: Level of `tower_http` request logs (default: `info`)
+//! - `LOG_LEVEL_REQWEST`: Level of `reqwest` client logs (default: `warn`)
+//! - `LOG_FORMAT`: `json` for one JSON object per line, otherwise plain text
+//! - `OTEL_EXPORTER_OTLP_ENDPOINT`: see [`crate::trace_context`]
+
+use crate::trace_context;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Builds the filter directives for `service_name` (e.g. `search-service`),
+/// reading variables through `var`.
+pub fn filter_directives(service_name: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    let var = |name: &str| var(name).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let service_target = service_name.replace('-', "_");
+
+    let mut directives: Vec<String> = var("RUST_LOG")
+        .map(|filter| {
+            filter
+                .split(',')
+                .map(str::trim)
+                .filter(|directive| !directive.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let levels = [
+        (service_target.as_str(), "LOG_LEVEL_SERVICE", "info"),
+        ("tower_http", "LOG_LEVEL_TOWER", "info"),
+        ("reqwest", "LOG_LEVEL_REQWEST", "warn"),
+    ];
+    for (target, variable, default) in levels {
+        let targets = |directive: &String| directive.split('=').next() == Some(target);
+        match var(variable) {
+            Some(level) => {
+                directives.retain(|directive| !targets(directive));
+                directives.push(format!("{}={}", target, level));
+            }
+            None if !directives.iter().any(targets) => directives.push(format!("{}={}", target, default)),
+            None => {}
+        }
+    }
+
+    directives.join(",")
+}
+
+/// Whether `LOG_FORMAT=json` asks for JSON lines.
+fn json_format() -> bool {
+    std::env::var("LOG_FORMAT").is_ok_and(|v| v.trim().eq_ignore_ascii_case("json"))
+}
+
+/// Where [`init_tracing`] writes log lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogOutput {
+    Stdout,
+    /// Leaves stdout to the program's own output, e.g. a JSON report.
+    Stderr,
+}
+
+/// Installs the global tracing subscriber for `service_name`, recording
+/// spans in OpenTelemetry too (see [`trace_context::otel_layer`]). An
+/// invalid filter is reported on stderr and replaced by the defaults.
+pub fn init_tracing(service_name: &str, output: LogOutput) {
+    let directives = filter_directives(service_name, |name| std::env::var(name).ok());
+    let filter = EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+        eprintln!("Ignoring invalid log filter '{}': {}", directives, e);
+        EnvFilter::new(filter_directives(service_name, |_| None))
+    });
+
+    let writer = match output {
+        LogOutput::Stdout => BoxMakeWriter::new(std::io::stdout),
+        LogOutput::Stderr => BoxMakeWriter::new(std::io::stderr),
+    };
+    let fmt = tracing_subscriber::fmt::layer().with_writer(writer);
+    let fmt = if json_format() { fmt.json().boxed() } else { fmt.boxed() };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(trace_context::otel_layer(service_name))
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn directives(vars: &[(&str, &str)]) -> String {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        filter_directives("indexing-service", |name| vars.get(name).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn defaults_apply_without_variables() {
+        assert_eq!(directives(&[]), "indexing_service=info,tower_http=info,reqwest=warn");
+        // Blank values count as unset
+        assert_eq!(directives(&[("LOG_LEVEL_TOWER", " ")]), "indexing_service=info,tower_http=info,reqwest=warn");
+    }
+
+    #[test]
+    fn level_variables_beat_rust_log_which_beats_defaults() {
+        let filter = directives(&[
+            ("RUST_LOG", "warn, indexing_service=trace,tower_http=debug,sqlx=info"),
+            ("LOG_LEVEL_TOWER", "error"),
+        ]);
+        assert_eq!(filter, "warn,indexing_service=trace,sqlx=info,tower_http=error,reqwest=warn");
+
+        let filter = directives(&[("RUST_LOG", "indexing_service=trace"), ("LOG_LEVEL_SERVICE", "debug")]);
+        assert_eq!(filter, "indexing_service=debug,tower_http=info,reqwest=warn");
+        assert!(EnvFilter::try_new(&filter).is_ok());
+    }
+}
@@ -0,0 +1,101 @@
+//! Trace Context Propagation
+//!
+//! Carries traces across service calls with W3C TraceContext headers, so a
+//! book's ingestion, status check and indexing show up as one trace rather
+//! than a root per call: [`inject`] adds `traceparent` and `tracestate` for
+//! the current span to an outgoing request, and [`make_span`] opens the span
+//! of an incoming request as a child of the caller's.
+//!
+//! [`init_tracing`](crate::logging::init_tracing) installs [`otel_layer`],
+//! which gives every span an OpenTelemetry trace and span ID, and exports the
+//! spans when a collector is configured.
+//!
+//! ## Environment Variables
+//! - `OTEL_EXPORTER_OTLP_ENDPOINT`: OTLP/gRPC collector to export spans to,
+//!   e.g. Jaeger's `http://jaeger:4317` (default: none, IDs are only
+//!   propagated)
+
+use http::{HeaderMap, Request};
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// The layer recording spans for `service_name` in OpenTelemetry, exporting
+/// them over OTLP when `OTEL_EXPORTER_OTLP_ENDPOINT` is set. Also installs
+/// the W3C TraceContext propagator.
+pub fn otel_layer<S>(service_name: &str) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let mut provider = TracerProvider::builder()
+        .with_resource(Resource::new([KeyValue::new("service.name", service_name.to_string())]));
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|v| !v.trim().is_empty());
+    if let Some(endpoint) = endpoint {
+        match SpanExporter::builder().with_tonic().with_endpoint(endpoint.trim()).build() {
+            Ok(exporter) => provider = provider.with_batch_exporter(exporter, runtime::Tokio),
+            Err(e) => eprintln!("Not exporting spans to '{}': {}", endpoint, e),
+        }
+    }
+    let provider = provider.build();
+    let tracer = provider.tracer(service_name.to_string());
+    global::set_tracer_provider(provider);
+
+    tracing_opentelemetry::layer().with_tracer(tracer)
+}
+
+/// `request` with the trace context of the current span.
+pub fn inject(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    let context = Span::current().context();
+    let mut headers = reqwest::header::HeaderMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut HeaderInjector(&mut headers)));
+    request.headers(headers)
+}
+
+/// The span of an incoming request, continuing the trace of its
+/// `traceparent` header if it has one.
+pub fn make_span<B>(request: &Request<B>) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+    );
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(request.headers())));
+    span.set_parent(parent);
+    span
+}
+
+/// Writes propagated fields into `reqwest`'s headers, which predate
+/// `http` 1.0 and so aren't axum's.
+struct HeaderInjector<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        let name = reqwest::header::HeaderName::from_bytes(key.as_bytes());
+        let value = reqwest::header::HeaderValue::from_str(&value);
+        if let (Ok(name), Ok(value)) = (name, value) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
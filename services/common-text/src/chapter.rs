@@ -0,0 +1,147 @@
+//! Chapter Detection
+//!
+//! Detects chapter boundaries in a book body. The indexing service numbers
+//! its chapter postings with it and the search service finds the same
+//! chapters again to title and count matches, so both must split alike.
+//!
+//! ## Recognized Headings
+//! - `CHAPTER I`, `Chapter 12.`, `CHAPTER XIV. The Storm` (case-insensitive)
+//! - Roman numerals on a line of their own (`IV`, `XII.`)
+//!
+//! Text before the first heading (title page, contents, preface) becomes
+//! chapter `0`. Bodies without any recognizable heading are returned as a
+//! single implicit chapter `1` so callers never have to special-case them.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A contiguous section of a book body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub id: usize,
+    pub title: Option<String>,
+    pub start_byte: usize,
+    pub end_byte: usize,
+}
+
+impl Chapter {
+    /// Returns the slice of `body` covered by this chapter.
+    pub fn text<'a>(&self, body: &'a str) -> &'a str {
+        &body[self.start_byte..self.end_byte]
+    }
+}
+
+fn heading_regex() -> &'static Regex {
+    static HEADING_RE: OnceLock<Regex> = OnceLock::new();
+    HEADING_RE.get_or_init(|| {
+        Regex::new(
+            r"(?m)^[ \t]*((?i:chapter[ \t]+(?:[ivxlcdm]+|\d+))\b[^\r\n]*|[IVXLCDM]+\.?)[ \t]*\r?$",
+        )
+        .unwrap()
+    })
+}
+
+/// Splits `body` into chapters on common chapter headings.
+pub fn detect_chapters(body: &str) -> Vec<Chapter> {
+    let headings: Vec<(usize, String)> = heading_regex()
+        .captures_iter(body)
+        .filter_map(|cap| {
+            let line = cap.get(0)?;
+            let title = cap.get(1)?.as_str().trim().to_string();
+            Some((line.start(), title))
+        })
+        .collect();
+
+    if headings.is_empty() {
+        return vec![Chapter {
+            id: 1,
+            title: None,
+            start_byte: 0,
+            end_byte: body.len(),
+        }];
+    }
+
+    let mut chapters = Vec::with_capacity(headings.len() + 1);
+
+    let first_start = headings[0].0;
+    if !body[..first_start].trim().is_empty() {
+        chapters.push(Chapter {
+            id: 0,
+            title: None,
+            start_byte: 0,
+            end_byte: first_start,
+        });
+    }
+
+    for (i, (start, title)) in headings.iter().enumerate() {
+        let end = headings.get(i + 1).map_or(body.len(), |(next, _)| *next);
+        chapters.push(Chapter {
+            id: i + 1,
+            title: Some(title.clone()),
+            start_byte: *start,
+            end_byte: end,
+        });
+    }
+
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_single_implicit_chapter() {
+        let body = "It was a dark and stormy night.\nThe rain fell.";
+        let chapters = detect_chapters(body);
+
+        assert_eq!(
+            chapters,
+            vec![Chapter {
+                id: 1,
+                title: None,
+                start_byte: 0,
+                end_byte: body.len(),
+            }]
+        );
+    }
+
+    #[test]
+    fn detects_chapter_headings_in_various_styles() {
+        let body = "CHAPTER I\nFirst text.\n\nChapter 2.\nSecond text.\n\nCHAPTER III. The End\nThird text.\n";
+        let chapters = detect_chapters(body);
+
+        let titles: Vec<_> = chapters.iter().map(|c| c.title.as_deref()).collect();
+        assert_eq!(
+            titles,
+            vec![Some("CHAPTER I"), Some("Chapter 2."), Some("CHAPTER III. The End")]
+        );
+        assert_eq!(chapters[1].text(body), "Chapter 2.\nSecond text.\n\n");
+        assert_eq!(chapters.last().unwrap().end_byte, body.len());
+    }
+
+    #[test]
+    fn detects_bare_roman_numerals() {
+        let body = "I.\nOne.\nII\nTwo.\n  III  \nThree.";
+        let chapters = detect_chapters(body);
+
+        assert_eq!(chapters.len(), 3);
+        assert!(chapters[2].text(body).contains("Three."));
+    }
+
+    #[test]
+    fn keeps_front_matter_as_chapter_zero() {
+        let body = "PREFACE\nSome words.\nCHAPTER 1\nStory.";
+        let chapters = detect_chapters(body);
+
+        assert_eq!(chapters[0].id, 0);
+        assert_eq!(chapters[0].text(body), "PREFACE\nSome words.\n");
+        assert_eq!(chapters[1].id, 1);
+    }
+
+    #[test]
+    fn ignores_chapter_word_inside_sentences() {
+        let body = "In this chapter we learn.\nIt was I who did it.";
+        assert_eq!(detect_chapters(body).len(), 1);
+    }
+}
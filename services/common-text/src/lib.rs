@@ -0,0 +1,411 @@
+//! Shared Text Normalization
+//!
+//! The one definition of what a word is, used by the indexing service to
+//! tokenize books and by the search service to tokenize queries, so every
+//! term a query looks up is spelled the way the index stores it.
+//!
+//! A word is a run of ASCII letters not glued to other word characters
+//! (letters, digits or `_` in any script), lowercased, at least
+//! [`TokenizerConfig::min_word_len`] letters long:
+//!
+//! - `whale,` and `Whale` → `whale`
+//! - `whale's` → `whale` (the `s` is too short)
+//! - `café`, `x86`, `snake_case` → nothing, the letters touch other word
+//!   characters
+//!
+//! Matching runs on the original text and only the matched letters are
+//! lowercased: lowercasing first would turn characters such as the Kelvin
+//! sign (`K`, U+212A) into ASCII letters the fast scanner never sees.
+//!
+//! ## Diacritic folding
+//! With [`TokenizerConfig::fold_diacritics`], text is first decomposed
+//! (Unicode NFKD) and stripped of combining marks, so `café` and `élève`
+//! become the words `cafe` and `eleve`, and a query for either spelling
+//! finds both. Letters that don't decompose, such as `ß` or `ø`, still
+//! break words. Folding changes the words stored, so the index has to be
+//! rebuilt when it is switched on or off.
+//!
+//! [`TokenizerConfig::DEFAULT`] holds the rules both services use, without
+//! folding; [`TokenizerConfig::configured`] adds `FOLD_DIACRITICS` and is
+//! what the free functions apply.
+//!
+//! ## Chapters and sentences
+//! [`chapter::detect_chapters`] splits a body into the chapters numbered by
+//! the chapter index.
+//!
+//! [`split_sentences`] defines the sentences numbered by the passage index,
+//! so the indexing service and the search service count them the same way.
+
+pub mod chapter;
+
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Words shorter than this are never indexed.
+pub const MIN_WORD_LEN: usize = 3;
+
+/// Why a word can never appear in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotIndexed {
+    TooShort { min_len: usize },
+    NotAlphabetic,
+}
+
+impl NotIndexed {
+    pub fn reason(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for NotIndexed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotIndexed::TooShort { min_len } => write!(f, "shorter than {} letters", min_len),
+            NotIndexed::NotAlphabetic => write!(f, "contains non-alphabetic characters"),
+        }
+    }
+}
+
+/// The rules deciding which words are indexed and searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizerConfig {
+    pub min_word_len: usize,
+    /// Fold accented letters to their base letters before tokenizing (see
+    /// [`fold_diacritics`]).
+    pub fold_diacritics: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+fn word_regex() -> &'static Regex {
+    static WORD_RE: OnceLock<Regex> = OnceLock::new();
+    WORD_RE.get_or_init(|| Regex::new(r"\b[a-zA-Z]+\b").unwrap())
+}
+
+/// `text` decomposed (NFKD) without its combining marks: `Élève` →
+/// `Eleve`, `Straße` stays `Straße`. ASCII text is returned as is.
+pub fn fold_diacritics(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.nfkd().filter(|&c| !is_combining_mark(c)).collect())
+}
+
+/// Same as `\b` in the regex: letters, digits and `_` are word characters.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl TokenizerConfig {
+    /// The configuration of the indexing and search services.
+    pub const DEFAULT: TokenizerConfig = TokenizerConfig {
+        min_word_len: MIN_WORD_LEN,
+        fold_diacritics: false,
+    };
+
+    /// [`Self::DEFAULT`], folding diacritics when `FOLD_DIACRITICS` is
+    /// `true`. Read once per process.
+    pub fn configured() -> &'static TokenizerConfig {
+        static CONFIGURED: OnceLock<TokenizerConfig> = OnceLock::new();
+        CONFIGURED.get_or_init(|| TokenizerConfig {
+            fold_diacritics: std::env::var("FOLD_DIACRITICS")
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false),
+            ..Self::DEFAULT
+        })
+    }
+
+    /// `text` folded when the config says so.
+    pub fn fold<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        if self.fold_diacritics {
+            fold_diacritics(text)
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// Applies the tokenizer's rules to a single lowercase word, returning
+    /// why it would be dropped, or `None` if it is indexable.
+    pub fn check_indexable(&self, word: &str) -> Option<NotIndexed> {
+        let word = &*self.fold(word);
+        if !word.chars().all(|c| c.is_ascii_alphabetic()) {
+            Some(NotIndexed::NotAlphabetic)
+        } else if word.len() < self.min_word_len {
+            Some(NotIndexed::TooShort {
+                min_len: self.min_word_len,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Lowercases a matched letter run, or drops it if it is too short.
+    fn keep(&self, letters: &str) -> Option<String> {
+        (letters.len() >= self.min_word_len).then(|| letters.to_ascii_lowercase())
+    }
+
+    fn words<'a>(&'a self, text: &'a str) -> impl Iterator<Item = String> + 'a {
+        word_regex().find_iter(text).filter_map(|m| self.keep(m.as_str()))
+    }
+
+    /// The words of `text`, in text order and with repeats, for analyses
+    /// that care where words occur.
+    pub fn tokenize_sequence(&self, text: &str) -> Vec<String> {
+        self.words(&self.fold(text)).collect()
+    }
+
+    /// The distinct words of `text`. This is the regex reference
+    /// implementation; [`Self::tokenize_text_fast`] gives the same result.
+    pub fn tokenize_text(&self, text: &str) -> HashSet<String> {
+        self.words(&self.fold(text)).collect()
+    }
+
+    /// The distinct words of `text` in order of first appearance, the form
+    /// queries are looked up in.
+    pub fn index_terms(&self, text: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.words(&self.fold(text))
+            .filter(|word| seen.insert(word.clone()))
+            .collect()
+    }
+
+    /// Collects runs of ASCII letters in `text` that are not glued to other
+    /// word characters (`caf` in `café`, `pm` in `10pm`).
+    fn scan_letter_runs(&self, text: &str, words: &mut HashSet<String>) {
+        let bytes = text.as_bytes();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            if !bytes[i].is_ascii_alphabetic() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+
+            // ASCII letters never sit inside a multi-byte char, so both ends
+            // are char boundaries; only the neighbours need a Unicode check.
+            let bounded = !text[..start].chars().next_back().is_some_and(is_word_char)
+                && !text[i..].chars().next().is_some_and(is_word_char);
+            if bounded {
+                words.extend(self.keep(&text[start..i]));
+            }
+        }
+    }
+
+    /// Faster equivalent of [`Self::tokenize_text`]: splits on whitespace
+    /// with `memchr` and scans the pieces for letter runs, without the regex.
+    pub fn tokenize_text_fast(&self, text: &str) -> HashSet<String> {
+        let text = &*self.fold(text);
+        let bytes = text.as_bytes();
+        let mut words = HashSet::new();
+        let mut start = 0;
+
+        // Whitespace is never a word character, so splitting on it can't
+        // change which letter runs are bounded.
+        while start < bytes.len() {
+            let end = memchr::memchr3(b' ', b'\n', b'\t', &bytes[start..]).map_or(bytes.len(), |pos| start + pos);
+            if end > start {
+                self.scan_letter_runs(&text[start..end], &mut words);
+            }
+            start = end + 1;
+        }
+
+        words
+    }
+}
+
+/// [`TokenizerConfig::check_indexable`] with the configured rules.
+pub fn check_indexable(word: &str) -> Option<NotIndexed> {
+    TokenizerConfig::configured().check_indexable(word)
+}
+
+/// [`TokenizerConfig::tokenize_text`] with the configured rules.
+pub fn tokenize_text(text: &str) -> HashSet<String> {
+    TokenizerConfig::configured().tokenize_text(text)
+}
+
+/// [`TokenizerConfig::tokenize_text_fast`] with the configured rules.
+pub fn tokenize_text_fast(text: &str) -> HashSet<String> {
+    TokenizerConfig::configured().tokenize_text_fast(text)
+}
+
+/// [`TokenizerConfig::tokenize_sequence`] with the configured rules.
+pub fn tokenize_sequence(text: &str) -> Vec<String> {
+    TokenizerConfig::configured().tokenize_sequence(text)
+}
+
+/// [`TokenizerConfig::index_terms`] with the configured rules.
+pub fn index_terms(text: &str) -> Vec<String> {
+    TokenizerConfig::configured().index_terms(text)
+}
+
+/// Splits text into sentences (Unicode sentence boundaries), in order, so a
+/// sentence's position in the result is its sentence ID.
+///
+/// Books are hard-wrapped, and a line break alone ends a sentence under the
+/// Unicode rules, so lines are first rejoined into paragraphs; blank lines
+/// still separate them. Sentences are trimmed and empty ones are skipped.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut paragraph = String::new();
+    let mut flush = |paragraph: &mut String| {
+        sentences.extend(
+            paragraph
+                .unicode_sentences()
+                .map(str::trim)
+                .filter(|sentence| !sentence.is_empty())
+                .map(String::from),
+        );
+        paragraph.clear();
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            flush(&mut paragraph);
+        } else {
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(line);
+        }
+    }
+    flush(&mut paragraph);
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_short_words() {
+        let words = tokenize_text("The old man and the sea, by him.");
+        assert_eq!(words, ["the", "old", "man", "and", "sea", "him"].map(String::from).into());
+    }
+
+    #[test]
+    fn fast_tokenizer_matches_regex_tokenizer() {
+        let samples = [
+            "It was the best of times, it was the WORST of times.",
+            "“Don’t,” said Mr. Lorry—and the café closed at 10pm.",
+            "snake_case words, x86 chips, naïve résumé, ALLCAPS\r\nlines",
+            "\u{212A}elvin and İstanbul",
+            "",
+        ];
+        for text in samples {
+            assert_eq!(tokenize_text_fast(text), tokenize_text(text), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn query_terms_keep_first_occurrence_order() {
+        assert_eq!(index_terms("Whale WHALE ocean whale"), ["whale", "ocean"]);
+        assert_eq!(index_terms("the whale's jaw"), ["the", "whale", "jaw"]);
+        assert_eq!(index_terms("Mr. Darcy—proud"), ["darcy", "proud"]);
+    }
+
+    #[test]
+    fn explains_why_a_word_is_not_indexed() {
+        assert_eq!(check_indexable("of").unwrap().reason(), "shorter than 3 letters");
+        assert_eq!(check_indexable("c3po"), Some(NotIndexed::NotAlphabetic));
+        assert_eq!(check_indexable("whale"), None);
+    }
+
+    #[test]
+    fn every_indexable_word_survives_tokenization() {
+        for word in ["whale", "love", "sea"] {
+            assert!(check_indexable(word).is_none());
+            assert!(tokenize_text(word).contains(word));
+        }
+    }
+
+    #[test]
+    fn honors_a_custom_config() {
+        let config = TokenizerConfig {
+            min_word_len: 2,
+            fold_diacritics: false,
+        };
+        let expected: HashSet<String> = ["old", "man", "by", "the", "sea"].map(String::from).into();
+        assert_eq!(config.tokenize_text("Old man by the sea"), expected);
+        assert_eq!(config.tokenize_text_fast("Old man by the sea"), expected);
+        assert_eq!(config.check_indexable("of"), None);
+    }
+
+    const FOLDING: TokenizerConfig = TokenizerConfig {
+        fold_diacritics: true,
+        ..TokenizerConfig::DEFAULT
+    };
+
+    /// Whether a query for `query` finds a book whose text is `text`.
+    fn finds(config: &TokenizerConfig, query: &str, text: &str) -> bool {
+        let indexed = config.tokenize_text_fast(text);
+        let terms = config.index_terms(query);
+        !terms.is_empty() && terms.iter().all(|term| indexed.contains(term))
+    }
+
+    #[test]
+    fn folding_strips_combining_marks() {
+        assert_eq!(fold_diacritics("Élève naïve"), "Eleve naive");
+        assert_eq!(fold_diacritics("Müller Straße"), "Muller Straße");
+        assert!(matches!(fold_diacritics("plain"), Cow::Borrowed("plain")));
+    }
+
+    #[test]
+    fn folded_french_matches_either_spelling() {
+        let book = "L'élève entra au café; le garçon était déçu.";
+
+        for query in ["eleve", "élève", "ELEVE", "café", "cafe", "garcon deçu"] {
+            assert!(finds(&FOLDING, query, book), "{query:?}");
+        }
+        assert!(finds(&FOLDING, "élève", "the eleve of the school"));
+        assert_eq!(FOLDING.index_terms("Élève café"), ["eleve", "cafe"]);
+        assert_eq!(FOLDING.tokenize_text_fast(book), FOLDING.tokenize_text(book));
+        assert_eq!(FOLDING.check_indexable("élève"), None);
+
+        // Without folding the accented words are never indexed at all
+        assert!(!finds(&TokenizerConfig::DEFAULT, "eleve", book));
+        assert!(!finds(&TokenizerConfig::DEFAULT, "élève", book));
+    }
+
+    #[test]
+    fn folded_german_matches_either_spelling() {
+        let book = "Über den Fluß führt eine Brücke nach Zürich.";
+
+        for query in ["uber", "über", "brucke", "Brücke", "zurich", "führt"] {
+            assert!(finds(&FOLDING, query, book), "{query:?}");
+        }
+        assert!(finds(&FOLDING, "Zürich", "from Zurich to Geneva"));
+        // ß has no decomposition, so it still breaks the word
+        assert!(!finds(&FOLDING, "fluss", book));
+        assert_eq!(FOLDING.tokenize_text_fast(book), FOLDING.tokenize_text(book));
+    }
+
+    #[test]
+    fn sentences_span_wrapped_lines_but_not_paragraphs() {
+        let text = "Call me Ishmael. Some years ago--never mind how\r\nlong precisely--I thought I would sail.\n\nChapter 2\n\n  The Carpet-Bag. \n";
+        assert_eq!(
+            split_sentences(text),
+            [
+                "Call me Ishmael.",
+                "Some years ago--never mind how long precisely--I thought I would sail.",
+                "Chapter 2",
+                "The Carpet-Bag.",
+            ]
+        );
+        assert!(split_sentences("\n \n").is_empty());
+    }
+}
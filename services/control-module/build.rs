@@ -0,0 +1,8 @@
+//! Generates the indexing service's gRPC stubs from its proto file. Uses the
+//! vendored `protoc`, so building doesn't need one installed.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::compile_protos("../indexing-service/proto/indexing.proto")?;
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Book ID Lists
+//!
+//! Expands the pipeline's book IDs from the command line:
+//! - `84` is one book, `1-500` every book from 1 to 500, and `84,11,1342`
+//!   a list of either
+//! - `--from-file ids.txt` reads the same entries, one per line; `#` starts
+//!   a comment and blank lines are skipped
+//! - `--limit N` keeps only the first `N` books (default: `100000`)
+//!
+//! The books are merged into one ascending list without duplicates, so
+//! overlapping ranges and repeated IDs are processed once. Every invalid
+//! entry is reported with its argument or line number before any book is.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use thiserror::Error;
+
+/// Books processed when no `--limit` is given, above the number of books
+/// Gutenberg holds, so only a mistyped range reaches it.
+pub(crate) const DEFAULT_LIMIT: usize = 100_000;
+
+/// An entry that isn't a book ID, a range or a list of them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InvalidEntry {
+    /// Where the entry came from, e.g. `argument 2` or `ids.txt line 7`.
+    pub location: String,
+    pub entry: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for InvalidEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: '{}' {}", self.location, self.entry, self.reason)
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum BookIdError {
+    #[error("{0} needs a value")]
+    MissingValue(&'static str),
+    #[error("--limit must be a positive number of books, not '{0}'")]
+    InvalidLimit(String),
+    #[error("Failed to read book IDs from {path}: {source}")]
+    File { path: String, source: std::io::Error },
+    #[error("Invalid book IDs:\n{}", .0.iter().map(|entry| format!("  {}", entry)).collect::<Vec<_>>().join("\n"))]
+    Invalid(Vec<InvalidEntry>),
+}
+
+/// Book IDs as ascending, non-overlapping, non-adjacent ranges.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BookIds {
+    ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl FromIterator<RangeInclusive<u32>> for BookIds {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<u32>>>(ranges: I) -> Self {
+        let mut ranges: Vec<RangeInclusive<u32>> = ranges.into_iter().collect();
+        ranges.sort_unstable_by_key(|range| *range.start());
+
+        let mut merged: Vec<RangeInclusive<u32>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                    *last = *last.start()..=(*last.end()).max(*range.end());
+                }
+                _ => merged.push(range),
+            }
+        }
+        Self { ranges: merged }
+    }
+}
+
+impl BookIds {
+    /// How many books the ranges hold.
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|range| u64::from(*range.end()) - u64::from(*range.start()) + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The first `limit` books, in ascending order.
+    pub fn first(&self, limit: usize) -> Vec<u32> {
+        self.ranges.iter().flat_map(|range| range.clone()).take(limit).collect()
+    }
+}
+
+/// Adds the ranges of `line` to `ranges`, and its entries that aren't valid
+/// to `invalid`. Everything from a `#` on is a comment.
+fn parse_line(
+    line: &str,
+    location: &str,
+    ranges: &mut Vec<RangeInclusive<u32>>,
+    invalid: &mut Vec<InvalidEntry>,
+) {
+    let line = line.split('#').next().unwrap_or_default();
+    for entry in line.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        match parse_entry(entry) {
+            Ok(range) => ranges.push(range),
+            Err(reason) => invalid.push(InvalidEntry {
+                location: location.to_string(),
+                entry: entry.to_string(),
+                reason,
+            }),
+        }
+    }
+}
+
+/// A single ID or an inclusive `start-end` range.
+fn parse_entry(entry: &str) -> Result<RangeInclusive<u32>, &'static str> {
+    let parse_id = |id: &str| match id.trim().parse::<u32>() {
+        Ok(0) => Err("is not a book ID (IDs start at 1)"),
+        Ok(id) => Ok(id),
+        Err(_) => Err("is not a book ID or range"),
+    };
+    match entry.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse_id(start)?, parse_id(end)?);
+            if start > end {
+                return Err("is a range ending before it starts");
+            }
+            Ok(start..=end)
+        }
+        None => parse_id(entry).map(|id| id..=id),
+    }
+}
+
+/// Removes `flag VALUE` (or `flag=VALUE`) from `args`, returning `VALUE`.
+fn take_flag(args: &mut Vec<String>, flag: &'static str) -> Result<Option<String>, BookIdError> {
+    let prefix = format!("{}=", flag);
+    let Some(position) = args.iter().position(|arg| arg == flag || arg.starts_with(&prefix)) else {
+        return Ok(None);
+    };
+    let arg = args.remove(position);
+    match arg.strip_prefix(&prefix) {
+        Some(value) => Ok(Some(value.to_string())),
+        None if position < args.len() => Ok(Some(args.remove(position))),
+        None => Err(BookIdError::MissingValue(flag)),
+    }
+}
+
+/// Takes `--from-file` and `--limit` out of `args` and expands them and the
+/// remaining arguments after the program name into the books to process,
+/// or `None` if no book was named at all.
+pub(crate) fn take_book_ids(args: &mut Vec<String>) -> Result<Option<Vec<u32>>, BookIdError> {
+    let from_file = take_flag(args, "--from-file")?;
+    let limit = match take_flag(args, "--limit")? {
+        None => DEFAULT_LIMIT,
+        Some(value) => match value.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(BookIdError::InvalidLimit(value)),
+        },
+    };
+
+    let mut ranges = Vec::new();
+    let mut invalid = Vec::new();
+    for (position, arg) in args.iter().enumerate().skip(1) {
+        parse_line(arg, &format!("argument {}", position), &mut ranges, &mut invalid);
+    }
+    if let Some(path) = from_file {
+        let text = std::fs::read_to_string(Path::new(&path)).map_err(|source| BookIdError::File {
+            path: path.clone(),
+            source,
+        })?;
+        for (number, line) in text.lines().enumerate() {
+            parse_line(line, &format!("{} line {}", path, number + 1), &mut ranges, &mut invalid);
+        }
+    }
+
+    if !invalid.is_empty() {
+        return Err(BookIdError::Invalid(invalid));
+    }
+    let ids: BookIds = ranges.into_iter().collect();
+    if ids.is_empty() {
+        return Ok(None);
+    }
+    if ids.len() > limit as u64 {
+        tracing::warn!(
+            "{} books requested, processing only the first {} (--limit)",
+            ids.len(),
+            limit
+        );
+    }
+    Ok(Some(ids.first(limit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        std::iter::once("control-module").chain(list.iter().copied()).map(String::from).collect()
+    }
+
+    #[test]
+    fn ranges_and_lists_are_merged_in_order() {
+        let ids = take_book_ids(&mut args(&["1342", "5-8", "84,11,7-10", "6", "11"])).unwrap();
+        assert_eq!(ids, Some(vec![5, 6, 7, 8, 9, 10, 11, 84, 1342]));
+
+        let merged: BookIds = [10..=20, 1..=4, 5..=5, 15..=30, 40..=40].into_iter().collect();
+        assert_eq!(merged.ranges, vec![1..=5, 10..=30, 40..=40]);
+        assert_eq!(merged.len(), 27);
+
+        assert_eq!(take_book_ids(&mut args(&[])).unwrap(), None);
+    }
+
+    #[test]
+    fn every_invalid_entry_is_reported_with_its_position() {
+        let Err(BookIdError::Invalid(invalid)) = take_book_ids(&mut args(&["84", "500-1", "11,moby", "0"])) else {
+            panic!("expected invalid entries");
+        };
+        let messages: Vec<String> = invalid.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "argument 2: '500-1' is a range ending before it starts",
+                "argument 3: 'moby' is not a book ID or range",
+                "argument 4: '0' is not a book ID (IDs start at 1)",
+            ]
+        );
+        assert!(matches!(parse_entry("1-x"), Err("is not a book ID or range")));
+        assert!(matches!(parse_entry("-5"), Err("is not a book ID or range")));
+    }
+
+    #[test]
+    fn limit_keeps_the_first_books() {
+        let mut huge = args(&["--limit", "3", "100-4000000000", "7"]);
+        assert_eq!(take_book_ids(&mut huge).unwrap(), Some(vec![7, 100, 101]));
+
+        let capped = take_book_ids(&mut args(&["1-200000"])).unwrap().unwrap();
+        assert_eq!(capped.len(), DEFAULT_LIMIT);
+
+        for bad in [&["--limit=0", "84"][..], &["--limit", "many", "84"], &["84", "--limit"]] {
+            assert!(take_book_ids(&mut args(bad)).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn id_files_allow_comments_and_report_line_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.txt");
+        std::fs::write(&path, "# classics\n84\n1342 # Pride and Prejudice\n\n10-12,11\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let ids = take_book_ids(&mut args(&["--from-file", &path, "2"])).unwrap();
+        assert_eq!(ids, Some(vec![2, 10, 11, 12, 84, 1342]));
+
+        std::fs::write(&path, "84\nmoby dick\n11\n12-3\n").unwrap();
+        let Err(BookIdError::Invalid(invalid)) = take_book_ids(&mut args(&[&format!("--from-file={}", path)])) else {
+            panic!("expected invalid entries");
+        };
+        let lines: Vec<&str> = invalid.iter().map(|entry| entry.location.as_str()).collect();
+        assert_eq!(lines, [format!("{} line 2", path), format!("{} line 4", path)]);
+
+        let missing = take_book_ids(&mut args(&["--from-file", "/nonexistent/ids.txt"]));
+        assert!(matches!(missing, Err(BookIdError::File { .. })));
+    }
+}
@@ -0,0 +1,355 @@
+//! Continuous Indexing
+//!
+//! With `--continuous` the control module keeps the index in step with the
+//! datalake. Every `CONTINUOUS_POLL_SECS` (default: `30`) a tick lists the
+//! books the ingestion service has stored (`GET /ingest/list?status=available`)
+//! and the books the indexing service has indexed (`GET /index/books`), then
+//! indexes the difference `--concurrency N` at a time, each book with the
+//! usual retries (see `retry`).
+//!
+//! Ticks don't overlap: the next one starts after the last has finished, and
+//! a book another tick is still indexing is skipped. A book that failed is
+//! left alone for `CONTINUOUS_FAILURE_COOLDOWN_SECS` (default: `600`), so a
+//! permanently broken book isn't retried every tick.
+
+use crate::retry::StepError;
+use crate::{env_secs, ControlModule, ListResponse, ServiceUrls, SERVICE_URLS};
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+const DEFAULT_POLL_SECS: u64 = 30;
+const DEFAULT_FAILURE_COOLDOWN_SECS: u64 = 600;
+
+/// Most failed books remembered; the oldest failure is forgotten first.
+const MAX_RECENT_FAILURES: usize = 1000;
+
+/// What one tick found and did. Book lists are in ascending ID order.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct TickSummary {
+    /// Books stored in the datalake.
+    pub ingested: usize,
+    /// Of those, books the index already held.
+    pub already_indexed: usize,
+    /// Books this tick indexed.
+    pub indexed: Vec<u32>,
+    /// Books this tick failed to index.
+    pub failed: Vec<(u32, StepError)>,
+    /// Books skipped because they failed within the cooldown.
+    pub cooling_down: Vec<u32>,
+    /// Books skipped because another tick is indexing them.
+    pub in_progress: Vec<u32>,
+}
+
+impl TickSummary {
+    pub fn log(&self) {
+        let missing = self.ingested - self.already_indexed;
+        if missing == 0 {
+            info!("Tick: all {} ingested books are indexed", self.ingested);
+            return;
+        }
+        info!(
+            "Tick: {} of {} ingested books weren't indexed; indexed {} {:?}, {} failed, {} waiting out a recent failure, {} already in progress",
+            missing,
+            self.ingested,
+            self.indexed.len(),
+            self.indexed,
+            self.failed.len(),
+            self.cooling_down.len(),
+            self.in_progress.len()
+        );
+        for (book_id, error) in &self.failed {
+            warn!("✗ Book {} wasn't indexed: {}", book_id, error);
+        }
+    }
+}
+
+/// The last failure of a book, for the cooldown.
+#[derive(Debug)]
+struct RecentFailure {
+    failed_at: Instant,
+}
+
+/// Indexes newly ingested books tick after tick, remembering which books
+/// are being indexed and which failed recently.
+pub(crate) struct ContinuousIndexer {
+    pub poll_interval: Duration,
+    pub failure_cooldown: Duration,
+    in_flight: Mutex<HashSet<u32>>,
+    failures: Mutex<HashMap<u32, RecentFailure>>,
+}
+
+impl ContinuousIndexer {
+    pub fn new(poll_interval: Duration, failure_cooldown: Duration) -> Self {
+        Self {
+            poll_interval,
+            failure_cooldown,
+            in_flight: Mutex::default(),
+            failures: Mutex::default(),
+        }
+    }
+
+    /// Reads `CONTINUOUS_POLL_SECS` and `CONTINUOUS_FAILURE_COOLDOWN_SECS`.
+    pub fn from_env() -> Self {
+        Self::new(
+            env_secs("CONTINUOUS_POLL_SECS", DEFAULT_POLL_SECS).max(Duration::from_secs(1)),
+            env_secs("CONTINUOUS_FAILURE_COOLDOWN_SECS", DEFAULT_FAILURE_COOLDOWN_SECS),
+        )
+    }
+
+    /// Indexes the ingested books missing from the index, except those
+    /// failing recently or being indexed by another tick. Fails if either
+    /// list can't be fetched.
+    pub async fn tick(&self, control: &ControlModule, urls: &ServiceUrls<'_>, concurrency: usize) -> Result<TickSummary, String> {
+        let ingested_url = format!("{}/ingest/list?status=available", urls.ingestion);
+        let indexed_url = format!("{}/index/books", urls.indexing);
+        let (ingested, indexed) = tokio::join!(control.list_books(&ingested_url), control.list_books(&indexed_url));
+        let ingested = ingested.map_err(|e| format!("Failed to list ingested books: {}", e))?;
+        let indexed: HashSet<u32> = indexed
+            .map_err(|e| format!("Failed to list indexed books: {}", e))?
+            .into_iter()
+            .collect();
+
+        let mut missing: Vec<u32> = ingested.iter().copied().filter(|book_id| !indexed.contains(book_id)).collect();
+        missing.sort_unstable();
+        missing.dedup();
+        let mut summary = TickSummary {
+            ingested: ingested.len(),
+            already_indexed: ingested.len() - missing.len(),
+            ..TickSummary::default()
+        };
+
+        let mut claimed = Vec::new();
+        for book_id in missing {
+            if self.cooling_down(book_id) {
+                summary.cooling_down.push(book_id);
+            } else if self.in_flight.lock().unwrap().insert(book_id) {
+                claimed.push(book_id);
+            } else {
+                summary.in_progress.push(book_id);
+            }
+        }
+
+        let mut results: Vec<(u32, Result<u32, StepError>)> = stream::iter(claimed)
+            .map(|book_id| async move {
+                let mut run = None;
+                let result = control
+                    .index_with_retries(book_id, urls.indexing, &mut run)
+                    .await
+                    .map(|()| run.map_or(0, |run| run.attempts));
+                self.in_flight.lock().unwrap().remove(&book_id);
+                (book_id, result)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(book_id, _)| *book_id);
+
+        for (book_id, result) in results {
+            match result {
+                Ok(attempts) => {
+                    info!("✓ Book {} indexed ({} attempt(s))", book_id, attempts);
+                    self.failures.lock().unwrap().remove(&book_id);
+                    summary.indexed.push(book_id);
+                }
+                Err(error) => {
+                    self.record_failure(book_id);
+                    summary.failed.push((book_id, error));
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn cooling_down(&self, book_id: u32) -> bool {
+        self.failures
+            .lock()
+            .unwrap()
+            .get(&book_id)
+            .is_some_and(|failure| failure.failed_at.elapsed() < self.failure_cooldown)
+    }
+
+    fn record_failure(&self, book_id: u32) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.insert(book_id, RecentFailure { failed_at: Instant::now() });
+        if failures.len() > MAX_RECENT_FAILURES {
+            failures.retain(|_, failure| failure.failed_at.elapsed() < self.failure_cooldown);
+        }
+        while failures.len() > MAX_RECENT_FAILURES {
+            let Some(oldest) = failures.iter().min_by_key(|(_, failure)| failure.failed_at).map(|(id, _)| *id) else {
+                break;
+            };
+            failures.remove(&oldest);
+        }
+    }
+}
+
+impl ControlModule {
+    /// Runs a [`ContinuousIndexer`] tick right away and then every poll
+    /// interval, until the process stops.
+    pub(crate) async fn continuous_mode(&self, concurrency: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let indexer = ContinuousIndexer::from_env();
+        info!(
+            "Starting continuous indexing every {:?}, {} book(s) at a time",
+            indexer.poll_interval, concurrency
+        );
+
+        let mut ticks = tokio::time::interval(indexer.poll_interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            match indexer.tick(self, &SERVICE_URLS, concurrency).await {
+                Ok(summary) => {
+                    summary.log();
+                    if !summary.indexed.is_empty() {
+                        self.invalidate_search_cache().await;
+                    }
+                }
+                Err(e) => warn!("Skipping tick: {}", e),
+            }
+        }
+    }
+
+    /// The book IDs of a `{ "count", "books" }` listing at `url`.
+    async fn list_books(&self, url: &str) -> Result<Vec<u32>, reqwest::Error> {
+        let response = self.get(url).send().await?.error_for_status()?;
+        let list: ListResponse = response.json().await?;
+        Ok(list.books)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// Book the mock indexing service can't find.
+    const BROKEN_BOOK: u32 = 3;
+
+    /// How often the mock indexing service was asked to index each book.
+    type IndexCalls = Arc<Mutex<HashMap<u32, usize>>>;
+
+    /// Ingestion and indexing services holding books 1 to 4, with book 1
+    /// indexed. Indexing takes `index_delay`; [`BROKEN_BOOK`] fails with
+    /// `404 BOOK_NOT_FOUND`.
+    async fn mock_services(index_delay: Duration) -> (String, IndexCalls) {
+        let indexed = Arc::new(Mutex::new(HashSet::from([1u32])));
+        let calls = IndexCalls::default();
+        let app = Router::new()
+            .route(
+                "/ingest/list",
+                get(|| async { Json(json!({ "count": 4, "books": [1, 2, 3, 4] })) }),
+            )
+            .route(
+                "/index/books",
+                get(|State((indexed, _)): State<(Arc<Mutex<HashSet<u32>>>, IndexCalls)>| async move {
+                    let mut books: Vec<u32> = indexed.lock().unwrap().iter().copied().collect();
+                    books.sort_unstable();
+                    Json(json!({ "count": books.len(), "books": books }))
+                }),
+            )
+            .route(
+                "/index/update/:book_id",
+                post(
+                    move |State((indexed, calls)): State<(Arc<Mutex<HashSet<u32>>>, IndexCalls)>, Path(book_id): Path<u32>| async move {
+                        *calls.lock().unwrap().entry(book_id).or_default() += 1;
+                        tokio::time::sleep(index_delay).await;
+                        if book_id == BROKEN_BOOK {
+                            let body = json!({ "error_code": "BOOK_NOT_FOUND", "message": "Book 3 not found in datalake" });
+                            return Err((StatusCode::NOT_FOUND, Json(body)));
+                        }
+                        indexed.lock().unwrap().insert(book_id);
+                        Ok(Json(json!({ "book_id": book_id, "status": "updated" })))
+                    },
+                ),
+            )
+            .with_state((indexed, calls.clone()));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, calls)
+    }
+
+    fn control() -> ControlModule {
+        ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::immediate(3))
+    }
+
+    #[tokio::test]
+    async fn ticks_index_the_missing_books_and_wait_out_failures() {
+        let (url, calls) = mock_services(Duration::ZERO).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = control();
+        let indexer = ContinuousIndexer::new(Duration::from_secs(30), Duration::from_secs(600));
+
+        let first = indexer.tick(&control, &urls, 2).await.unwrap();
+        assert_eq!((first.ingested, first.already_indexed), (4, 1));
+        assert_eq!(first.indexed, [2, 4]);
+        assert_eq!(first.failed.len(), 1);
+        assert_eq!(first.failed[0].0, BROKEN_BOOK);
+        assert!(matches!(&first.failed[0].1, StepError::Permanent(m) if m.contains("BOOK_NOT_FOUND")));
+
+        // The broken book isn't tried again during its cooldown
+        let second = indexer.tick(&control, &urls, 2).await.unwrap();
+        assert_eq!((second.ingested, second.already_indexed), (4, 3));
+        assert!(second.indexed.is_empty());
+        assert_eq!(second.cooling_down, [BROKEN_BOOK]);
+        assert_eq!(calls.lock().unwrap()[&BROKEN_BOOK], 1);
+
+        // Once it's over, it is
+        let impatient = ContinuousIndexer::new(Duration::from_secs(30), Duration::ZERO);
+        impatient.record_failure(BROKEN_BOOK);
+        let third = impatient.tick(&control, &urls, 2).await.unwrap();
+        assert_eq!(third.failed.len(), 1);
+        assert_eq!(calls.lock().unwrap()[&BROKEN_BOOK], 2);
+    }
+
+    #[tokio::test]
+    async fn overlapping_ticks_index_each_book_once() {
+        let (url, calls) = mock_services(Duration::from_millis(200)).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = control();
+        let indexer = ContinuousIndexer::new(Duration::from_secs(30), Duration::from_secs(600));
+
+        let (a, b) = tokio::join!(indexer.tick(&control, &urls, 4), indexer.tick(&control, &urls, 4));
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        let mut indexed: Vec<u32> = a.indexed.iter().chain(&b.indexed).copied().collect();
+        indexed.sort_unstable();
+        assert_eq!(indexed, [2, 4]);
+        assert_eq!(a.in_progress.len() + b.in_progress.len(), 3);
+        assert!(calls.lock().unwrap().values().all(|&n| n == 1), "{:?}", calls);
+    }
+
+    #[tokio::test]
+    async fn unreachable_services_skip_the_tick() {
+        let urls = ServiceUrls {
+            ingestion: "http://127.0.0.1:1",
+            indexing: "http://127.0.0.1:1",
+            search: "http://127.0.0.1:1",
+        };
+        let indexer = ContinuousIndexer::new(Duration::from_secs(30), Duration::from_secs(600));
+
+        let err = indexer.tick(&control(), &urls, 1).await.unwrap_err();
+        assert!(err.starts_with("Failed to list ingested books"), "{}", err);
+    }
+}
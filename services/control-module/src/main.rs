@@ -9,16 +9,81 @@
 //!
 //! ## Responsibilities
 //! - Wait for all dependent services to become available  
-//! - Trigger ingestion and indexing for given book IDs  
-//! - Verify pipeline completion with structured status checks  
-//! - Optionally run in continuous monitoring mode 
+//! - Trigger ingestion and indexing for given book IDs, ranges or ID files
+//!   (see `book_ids`), up to `--concurrency N` books at a time (default:
+//!   one), retrying steps that fail transiently (see `retry`)  
+//! - Verify pipeline completion with structured status checks, and each
+//!   book end to end once the run is over (see `verification`)  
+//! - Optionally report each book's steps as JSON (`--report out.json`, `--json`, see `report`)
+//! - Invalidate the search service's result cache once new books are indexed  
+//! - Declare the pipeline ready only once the search service reports an index  
+//! - Optionally index newly ingested books as they arrive (`--continuous`, see `continuous`)
+//! - Optionally serve liveness, readiness and pipeline status (`--serve`, see `server`)
+//!
+//! ## Environment Variables
+//! - `REQUEST_TIMEOUT_SECS`: Timeout for every HTTP request (default: `30`)
+//! - `MAX_WAIT_SECS`: How long to wait for each service to become ready (default: `300`)
+//! - `INDEXING_AUTH_TOKEN`: Bearer token sent to the indexing service's `POST` routes
+//! - `USE_GRPC`: Index books through the indexing service's gRPC API instead of HTTP (default: `false`)
+//! - `INDEXING_GRPC_URL`: Address of that gRPC API (default: `http://0.0.0.0:7012`)
+//! - `CONTROL_PORT`: Port of the `--serve` REST API (default: `7000`)
+//! - `PIPELINE_MAX_ATTEMPTS`, `PIPELINE_BACKOFF_*`: Retries of each pipeline step, see `retry`
+//! - `CONTINUOUS_POLL_SECS`, `CONTINUOUS_FAILURE_COOLDOWN_SECS`: Pace of `--continuous`, see `continuous`
+//! - `RUST_LOG`, `LOG_LEVEL_SERVICE`, `LOG_LEVEL_TOWER`, `LOG_LEVEL_REQWEST`, `LOG_FORMAT`: see the `common-telemetry` crate
+
+mod book_ids;
+mod continuous;
+mod report;
+mod retry;
+mod server;
+mod verification;
 
+use book_ids::take_book_ids;
+use report::{ReportOutput, RunReport, RunStatus};
+use common_telemetry::logging::{init_tracing, LogOutput};
+use common_telemetry::trace_context;
+use futures::stream::{self, StreamExt};
 use reqwest::Client;
+use retry::{AttemptError, RetryPolicy, StepError};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tonic::transport::{Channel, Endpoint};
 use tokio::time::sleep;
 use tracing::{error, info, warn};
 
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MAX_WAIT_SECS: u64 = 300;
+
+/// Errors raised by the control module itself.
+#[derive(Error, Debug)]
+enum ControlError {
+    #[error("{service} service did not become ready in time")]
+    Timeout { service: String },
+    #[error("HTTP client error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("gRPC client error: {0}")]
+    Grpc(#[from] tonic::transport::Error),
+}
+
+/// Types and client stubs generated from the indexing service's
+/// `proto/indexing.proto`.
+mod pb {
+    tonic::include_proto!("indexing");
+}
+
+use pb::indexing_service_client::IndexingServiceClient;
+
+/// Reads a duration in seconds from `var`, falling back to `default`.
+fn env_secs(var: &str, default: u64) -> Duration {
+    let secs = std::env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
 /// Response from the ingestion service after downloading a book.
 #[derive(Debug, Serialize, Deserialize)]
 struct IngestResponse {
@@ -39,9 +104,42 @@ struct StatusResponse {
 struct IndexResponse {
     book_id: u32,
     status: String,
+    /// Absent from indexing services that don't report them.
+    #[serde(default)]
+    word_count: usize,
+    #[serde(default)]
+    unique_words: usize,
+    #[serde(default)]
+    elapsed_ms: u64,
+}
+
+/// The parts of the search service's `/status` the control module reads.
+#[derive(Debug, Deserialize)]
+struct SearchHealth {
+    status: String,
+    /// Absent from search services that only report the backend.
+    #[serde(default)]
+    checks: Option<SearchChecks>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchChecks {
+    backend_reachable: bool,
+    index_present: bool,
+    indexed_books: Option<usize>,
+}
+
+/// Whether the search service can be used: its backend answers and, with
+/// `require_index`, the index holds books.
+fn search_ready(health: &SearchHealth, require_index: bool) -> bool {
+    match &health.checks {
+        Some(checks) => checks.backend_reachable && (!require_index || checks.index_present),
+        None => health.status == "running",
+    }
 }
 
-/// Response representing available ingested books.
+/// A list of books: those ingested (`GET /ingest/list`) or indexed
+/// (`GET /index/books`).
 #[derive(Debug, Serialize, Deserialize)]
 struct ListResponse {
     count: usize,
@@ -51,251 +149,1283 @@ struct ListResponse {
 const INGESTION_SERVICE_URL: &str = "http://0.0.0.0:7001";
 const INDEXING_SERVICE_URL: &str = "http://0.0.0.0:7002";
 const SEARCH_SERVICE_URL: &str = "http://0.0.0.0:7003";
+const DEFAULT_INDEXING_GRPC_URL: &str = "http://0.0.0.0:7012";
+
+/// How many attempts each step of a book's pipeline took.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct StepAttempts {
+    ingest: u32,
+    status: u32,
+    index: u32,
+}
+
+/// The attempts one step of a book's pipeline made, and the time they took
+/// with the backoff between them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct StepRun {
+    attempts: u32,
+    elapsed: Duration,
+}
+
+/// The steps of a book's pipeline that ran; a step is `None` if an earlier
+/// one failed. The last step that ran is the one that failed, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct StepRuns {
+    ingest: Option<StepRun>,
+    status: Option<StepRun>,
+    index: Option<StepRun>,
+}
+
+/// How one book's pipeline ended; `error` is why it failed.
+#[derive(Debug, PartialEq)]
+struct BookOutcome {
+    book_id: u32,
+    steps: StepRuns,
+    elapsed: Duration,
+    error: Option<StepError>,
+}
+
+impl BookOutcome {
+    fn attempts(&self) -> StepAttempts {
+        let attempts = |run: Option<StepRun>| run.map_or(0, |run| run.attempts);
+        StepAttempts {
+            ingest: attempts(self.steps.ingest),
+            status: attempts(self.steps.status),
+            index: attempts(self.steps.index),
+        }
+    }
+}
+
+/// The books a pipeline run processed, by book ID, and how long it took.
+#[derive(Debug)]
+struct PipelineSummary {
+    outcomes: Vec<BookOutcome>,
+    elapsed: Duration,
+}
+
+impl PipelineSummary {
+    fn succeeded(&self) -> usize {
+        self.outcomes.iter().filter(|outcome| outcome.error.is_none()).count()
+    }
+
+    /// Books whose every attempt at a step failed transiently.
+    fn failed_after_retries(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.error, Some(StepError::Exhausted { .. })))
+            .count()
+    }
+
+    /// Books with a step that failed in a way retrying can't fix.
+    fn failed_permanently(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.error, Some(StepError::Permanent(_))))
+            .count()
+    }
+
+    fn log(&self) {
+        let total = self.outcomes.len();
+        let succeeded = self.succeeded();
+        if succeeded == total {
+            info!("Processed all {} books in {:.1}s", total, self.elapsed.as_secs_f64());
+            return;
+        }
+        warn!(
+            "Processed {}/{} books in {:.1}s; {} failed after retries, {} failed permanently",
+            succeeded,
+            total,
+            self.elapsed.as_secs_f64(),
+            self.failed_after_retries(),
+            self.failed_permanently()
+        );
+        for outcome in &self.outcomes {
+            match &outcome.error {
+                Some(error @ StepError::Exhausted { .. }) => {
+                    warn!("✗ Book {} failed after retries: {}", outcome.book_id, error)
+                }
+                Some(error @ StepError::Permanent(_)) => {
+                    warn!("✗ Book {} failed permanently: {}", outcome.book_id, error)
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Where the pipeline services listen.
+pub(crate) struct ServiceUrls<'a> {
+    pub ingestion: &'a str,
+    pub indexing: &'a str,
+    pub search: &'a str,
+}
+
+const SERVICE_URLS: ServiceUrls<'static> = ServiceUrls {
+    ingestion: INGESTION_SERVICE_URL,
+    indexing: INDEXING_SERVICE_URL,
+    search: SEARCH_SERVICE_URL,
+};
+
+/// Pause between two polls of a service that isn't ready.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
 
 /// Central coordinator for managing service pipelines.
 struct ControlModule {
     client: Client,
+    max_wait: Duration,
+    /// Shared secret for the indexing service, if it requires one.
+    indexing_token: Option<String>,
+    /// Indexes books over gRPC instead of HTTP when set.
+    indexing_grpc: Option<IndexingServiceClient<Channel>>,
+    /// Retries of each step of a book's pipeline.
+    retry: RetryPolicy,
 }
 
 impl ControlModule {
-    fn new() -> Self {
-        Self {
-            client: Client::new(),
+    /// Builds a coordinator using `REQUEST_TIMEOUT_SECS`, `MAX_WAIT_SECS`
+    /// and the retry variables, talking gRPC to the indexing service when
+    /// `USE_GRPC=true`.
+    fn new() -> Result<Self, ControlError> {
+        let request_timeout = env_secs("REQUEST_TIMEOUT_SECS", DEFAULT_REQUEST_TIMEOUT_SECS);
+        let mut control = Self::with_timeouts(request_timeout, env_secs("MAX_WAIT_SECS", DEFAULT_MAX_WAIT_SECS))?;
+        let token = std::env::var("INDEXING_AUTH_TOKEN").ok().filter(|t| !t.is_empty());
+        control = control.with_indexing_token(token).with_retry_policy(RetryPolicy::from_env());
+
+        let use_grpc = std::env::var("USE_GRPC").is_ok_and(|v| v.eq_ignore_ascii_case("true") || v == "1");
+        if use_grpc {
+            let url = std::env::var("INDEXING_GRPC_URL").unwrap_or_else(|_| DEFAULT_INDEXING_GRPC_URL.to_string());
+            info!("Indexing books over gRPC at {}", url);
+            control = control.with_indexing_grpc(&url, request_timeout)?;
         }
+        Ok(control)
     }
 
-    /// Waits until all dependent services respond with a successful `/status`.
-    async fn wait_for_services(&self) -> Result<(), Box<dyn std::error::Error>> {
+    fn with_timeouts(request_timeout: Duration, max_wait: Duration) -> Result<Self, ControlError> {
+        let client = Client::builder().timeout(request_timeout).build()?;
+        Ok(Self {
+            client,
+            max_wait,
+            indexing_token: None,
+            indexing_grpc: None,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    fn with_indexing_token(mut self, token: Option<String>) -> Self {
+        self.indexing_token = token;
+        self
+    }
+
+    fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sends indexing requests to the gRPC API at `url`. The connection is
+    /// only opened by the first request.
+    fn with_indexing_grpc(mut self, url: &str, request_timeout: Duration) -> Result<Self, ControlError> {
+        let channel = Endpoint::from_shared(url.to_string())?
+            .timeout(request_timeout)
+            .connect_lazy();
+        self.indexing_grpc = Some(IndexingServiceClient::new(channel));
+        Ok(self)
+    }
+
+    /// Starts a `GET` to a service, carrying the current trace (see
+    /// [`common_telemetry::trace_context`]).
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        trace_context::inject(self.client.get(url))
+    }
+
+    /// Starts a `POST` to a service, carrying the current trace.
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        trace_context::inject(self.client.post(url))
+    }
+
+    /// Starts a `POST` to the indexing service, authenticated when a token is configured.
+    fn indexing_post(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.post(url);
+        match &self.indexing_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Waits until all dependent services respond with a successful
+    /// `/status`, and the search service can reach its backend. The index
+    /// may still be empty; see [`Self::wait_for_search`].
+    async fn wait_for_services(&self) -> Result<(), ControlError> {
         info!("Waiting for services to be ready...");
 
         let services = [
             ("Ingestion", format!("{}/status", INGESTION_SERVICE_URL)),
             ("Indexing", format!("{}/status", INDEXING_SERVICE_URL)),
-            ("Search", format!("{}/status", SEARCH_SERVICE_URL)),
         ];
 
         for (name, url) in &services {
-            loop {
-                match self.client.get(url).send().await {
-                    Ok(response) if response.status().is_success() => {
-                        info!("{} service is ready", name);
-                        break;
-                    }
-                    Ok(response) => {
-                        warn!(
-                            "{} service responded with status: {}",
-                            name,
-                            response.status()
-                        );
-                    }
-                    Err(e) => {
-                        warn!("{} service not ready: {}", name, e);
-                    }
-                }
-                sleep(Duration::from_secs(2)).await;
-            }
+            self.wait_for_service(name, url).await?;
         }
+        self.wait_for_search(&format!("{}/status", SEARCH_SERVICE_URL), false).await?;
 
         info!("All services are ready!");
         Ok(())
     }
 
-    /// Requests ingestion of a specific book by ID.
-    async fn ingest_book(
-        &self,
-        book_id: u32,
-    ) -> Result<IngestResponse, Box<dyn std::error::Error>> {
+    /// Asks each pipeline service's `/status` once, for `GET /status`.
+    async fn service_statuses(&self) -> Vec<server::ServiceStatus> {
+        let services = [
+            ("ingestion", INGESTION_SERVICE_URL),
+            ("indexing", INDEXING_SERVICE_URL),
+            ("search", SEARCH_SERVICE_URL),
+        ];
+        let probes = services.map(|(name, base)| async move {
+            let url = format!("{}/status", base);
+            let reachable = matches!(self.get(&url).send().await, Ok(response) if response.status().is_success());
+            server::ServiceStatus { name, url, reachable }
+        });
+        let [ingestion, indexing, search] = probes;
+        let (ingestion, indexing, search) = tokio::join!(ingestion, indexing, search);
+        vec![ingestion, indexing, search]
+    }
+
+    /// Runs `check` every [`POLL_INTERVAL`] until it passes, giving up
+    /// after `max_wait`.
+    async fn poll_until<F, Fut>(&self, name: &str, check: F) -> Result<(), ControlError>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        let poll = async {
+            while !check().await {
+                sleep(POLL_INTERVAL).await;
+            }
+        };
+
+        tokio::time::timeout(self.max_wait, poll)
+            .await
+            .map_err(|_| ControlError::Timeout {
+                service: name.to_string(),
+            })
+    }
+
+    /// Polls `url` until it answers successfully, giving up after `max_wait`.
+    async fn wait_for_service(&self, name: &str, url: &str) -> Result<(), ControlError> {
+        self.poll_until(name, || async {
+            match self.get(url).send().await {
+                Ok(response) if response.status().is_success() => {
+                    info!("{} service is ready", name);
+                    true
+                }
+                Ok(response) => {
+                    warn!(
+                        "{} service responded with status: {}",
+                        name,
+                        response.status()
+                    );
+                    false
+                }
+                Err(e) => {
+                    warn!("{} service not ready: {}", name, e);
+                    false
+                }
+            }
+        })
+        .await
+    }
+
+    /// Polls the search service's `/status` at `url` until its backend is
+    /// reachable and, with `require_index`, its index holds books, giving
+    /// up after `max_wait`.
+    async fn wait_for_search(&self, url: &str, require_index: bool) -> Result<(), ControlError> {
+        self.poll_until("Search", || async {
+            let health = match self.get(url).send().await {
+                Ok(response) if response.status().is_success() => response.json::<SearchHealth>().await,
+                Ok(response) => {
+                    warn!("Search service responded with status: {}", response.status());
+                    return false;
+                }
+                Err(e) => Err(e),
+            };
+            match health {
+                Ok(health) if search_ready(&health, require_index) => {
+                    let books = health.checks.and_then(|checks| checks.indexed_books);
+                    info!("Search service is ready (indexed books: {:?})", books);
+                    true
+                }
+                Ok(health) => {
+                    warn!("Search service is {}: {:?}", health.status, health.checks);
+                    false
+                }
+                Err(e) => {
+                    warn!("Search service not ready: {}", e);
+                    false
+                }
+            }
+        })
+        .await
+    }
+
+    /// Requests ingestion of a specific book by ID from the ingestion
+    /// service at `base`.
+    #[tracing::instrument(skip(self, base), err)]
+    async fn ingest_book(&self, book_id: u32, base: &str) -> Result<IngestResponse, AttemptError> {
         info!("Ingesting book {}", book_id);
 
-        let url = format!("{}/ingest/{}", INGESTION_SERVICE_URL, book_id);
-        let response = self.client.post(&url).send().await?;
+        let context = format!("Failed to ingest book {}", book_id);
+        let url = format!("{}/ingest/{}", base, book_id);
+        let response = self
+            .post(&url)
+            .send()
+            .await
+            .map_err(|e| AttemptError::request(&context, e))?;
 
         if response.status().is_success() {
-            let ingest_response: IngestResponse = response.json().await?;
+            let ingest_response: IngestResponse =
+                response.json().await.map_err(|e| AttemptError::request(&context, e))?;
             info!(
                 "Successfully ingested book {}: {}",
                 book_id, ingest_response.status
             );
             Ok(ingest_response)
         } else {
-            let error_msg = format!("Failed to ingest book {}: {}", book_id, response.status());
-            error!("{}", error_msg);
-            Err(error_msg.into())
+            let error = AttemptError::response(&context, response).await;
+            error!("{}", error);
+            Err(error)
         }
     }
 
-    /// Checks if a previously ingested book is available for indexing.
-    async fn check_ingestion_status(
-        &self,
-        book_id: u32,
-    ) -> Result<bool, Box<dyn std::error::Error>> {
-        let url = format!("{}/ingest/status/{}", INGESTION_SERVICE_URL, book_id);
-        let response = self.client.get(&url).send().await?;
+    /// Checks if a previously ingested book is available for indexing. An
+    /// error response that retrying can't fix counts as unavailable.
+    async fn check_ingestion_status(&self, book_id: u32, base: &str) -> Result<bool, AttemptError> {
+        let context = format!("Failed to check the status of book {}", book_id);
+        let url = format!("{}/ingest/status/{}", base, book_id);
+        let response = self
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| AttemptError::request(&context, e))?;
 
         if response.status().is_success() {
-            let status_response: StatusResponse = response.json().await?;
+            let status_response: StatusResponse =
+                response.json().await.map_err(|e| AttemptError::request(&context, e))?;
             Ok(status_response.status == "available")
         } else {
-            Ok(false)
+            match AttemptError::response(&context, response).await {
+                error @ AttemptError::Transient(_) => Err(error),
+                AttemptError::Permanent(_) => Ok(false),
+            }
         }
     }
 
-    /// Requests the indexing of a specific ingested book.
-    async fn index_book(&self, book_id: u32) -> Result<IndexResponse, Box<dyn std::error::Error>> {
+    /// Requests the indexing of a specific ingested book from the indexing
+    /// service at `base`, or over gRPC when configured.
+    #[tracing::instrument(skip(self, base), err)]
+    async fn index_book(&self, book_id: u32, base: &str) -> Result<IndexResponse, AttemptError> {
         info!("Indexing book {}", book_id);
 
-        let url = format!("{}/index/update/{}", INDEXING_SERVICE_URL, book_id);
-        let response = self.client.post(&url).send().await?;
+        if let Some(client) = &self.indexing_grpc {
+            return self.index_book_grpc(client.clone(), book_id).await;
+        }
+
+        let context = format!("Failed to index book {}", book_id);
+        let url = format!("{}/index/update/{}", base, book_id);
+        let response = self
+            .indexing_post(&url)
+            .send()
+            .await
+            .map_err(|e| AttemptError::request(&context, e))?;
 
         if response.status().is_success() {
-            let index_response: IndexResponse = response.json().await?;
+            let index_response: IndexResponse =
+                response.json().await.map_err(|e| AttemptError::request(&context, e))?;
             info!(
-                "Successfully indexed book {}: {}",
-                book_id, index_response.status
+                "Successfully indexed book {}: {} ({} words, {} unique, {} ms)",
+                book_id, index_response.status, index_response.word_count, index_response.unique_words, index_response.elapsed_ms
             );
             Ok(index_response)
         } else {
-            let error_msg = format!("Failed to index book {}: {}", book_id, response.status());
-            error!("{}", error_msg);
-            Err(error_msg.into())
+            let error = AttemptError::response(&context, response).await;
+            error!("{}", error);
+            Err(error)
         }
     }
 
-    /// Retrieves a list of available ingested books.
-    async fn get_available_books(&self) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
-        let url = format!("{}/ingest/list", INGESTION_SERVICE_URL);
-        let response = self.client.get(&url).send().await?;
+    /// Same as [`Self::index_book`], over the indexing service's gRPC API.
+    async fn index_book_grpc(
+        &self,
+        mut client: IndexingServiceClient<Channel>,
+        book_id: u32,
+    ) -> Result<IndexResponse, AttemptError> {
+        let context = format!("Failed to index book {}", book_id);
+        let mut request = tonic::Request::new(pb::IndexBookRequest {
+            book_id,
+            verbose: false,
+        });
+        if let Some(token) = &self.indexing_token {
+            let token = format!("Bearer {}", token)
+                .parse()
+                .map_err(|e| AttemptError::Permanent(format!("{}: invalid token: {}", context, e)))?;
+            request.metadata_mut().insert("authorization", token);
+        }
 
-        if response.status().is_success() {
-            let list_response: ListResponse = response.json().await?;
-            Ok(list_response.books)
-        } else {
-            Ok(Vec::new())
+        match client.index_book(request).await {
+            Ok(response) => {
+                let response = response.into_inner();
+                info!(
+                    "Successfully indexed book {}: {} ({} words, {} unique, {} ms)",
+                    book_id, response.status, response.word_count, response.unique_words, response.elapsed_ms
+                );
+                Ok(IndexResponse {
+                    book_id: response.book_id,
+                    status: response.status,
+                    word_count: response.word_count as usize,
+                    unique_words: response.unique_words as usize,
+                    elapsed_ms: response.elapsed_ms,
+                })
+            }
+            Err(status) => {
+                let error = AttemptError::grpc(&context, status);
+                error!("{}", error);
+                Err(error)
+            }
         }
     }
 
-    /// Executes the full ingestion + indexing pipeline for a single book.
-    async fn process_book(&self, book_id: u32) -> Result<(), Box<dyn std::error::Error>> {
+    /// Executes the full ingestion + indexing pipeline for a single book,
+    /// as one trace across the services, retrying each step under
+    /// `self.retry`. Returns how each step went and, if one failed, why.
+    #[tracing::instrument(skip(self, urls))]
+    async fn process_book(&self, book_id: u32, urls: &ServiceUrls<'_>) -> BookOutcome {
+        let started = Instant::now();
+        let mut steps = StepRuns::default();
+        let error = self.run_book_steps(book_id, urls, &mut steps).await.err();
+        BookOutcome {
+            book_id,
+            steps,
+            elapsed: started.elapsed(),
+            error,
+        }
+    }
+
+    /// The steps of [`Self::process_book`], each recorded in `steps` as it
+    /// finishes.
+    async fn run_book_steps(&self, book_id: u32, urls: &ServiceUrls<'_>, steps: &mut StepRuns) -> Result<(), StepError> {
         info!("Starting processing pipeline for book {}", book_id);
 
         info!("Step 1: Ingesting book {}", book_id);
-        let ingest_response = self.ingest_book(book_id).await?;
+        let ingest_response = self
+            .run_step("Ingesting", &mut steps.ingest, || self.ingest_book(book_id, urls.ingestion))
+            .await?;
 
         info!("Step 2: Waiting for ingestion confirmation...");
         sleep(Duration::from_millis(500)).await;
 
         info!("Step 3: Verifying ingestion status...");
-        if !self.check_ingestion_status(book_id).await? {
-            return Err(format!(
+        let available = self
+            .run_step("Checking ingestion status", &mut steps.status, || {
+                self.check_ingestion_status(book_id, urls.ingestion)
+            })
+            .await?;
+        if !available {
+            return Err(StepError::Permanent(format!(
                 "Book {} ingestion verification failed - status not 'available'",
                 book_id
-            )
-            .into());
+            )));
         }
+        let attempts = |run: Option<StepRun>| run.map_or(0, |run| run.attempts);
         info!(
-            "✅ Book {} successfully ingested at: {}",
-            book_id, ingest_response.path
+            "✅ Book {} successfully ingested at: {} ({} attempt(s), status checked in {})",
+            book_id,
+            ingest_response.path,
+            attempts(steps.ingest),
+            attempts(steps.status)
         );
 
         info!("Step 4: Indexing book {}", book_id);
-        let index_response = self.index_book(book_id).await?;
+        self.index_with_retries(book_id, urls.indexing, &mut steps.index).await?;
+
+        info!(
+            "Successfully completed processing pipeline for book {} (indexed in {} attempt(s))",
+            book_id,
+            attempts(steps.index)
+        );
+        Ok(())
+    }
+
+    /// Runs `attempt` under `self.retry`, recording in `run` how many
+    /// attempts it made and how long they took, whether or not it succeeds.
+    async fn run_step<T, F, Fut>(&self, step: &str, run: &mut Option<StepRun>, mut attempt: F) -> Result<T, StepError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, AttemptError>>,
+    {
+        let started = Instant::now();
+        let mut attempts = 0;
+        let result = self
+            .retry
+            .run(step, || {
+                attempts += 1;
+                attempt()
+            })
+            .await;
+        *run = Some(StepRun {
+            attempts,
+            elapsed: started.elapsed(),
+        });
+        result.map(|(value, _)| value)
+    }
+
+    /// Indexes an ingested book under `self.retry`, recording the attempts
+    /// in `run`, and checks the indexing service reports it `updated`.
+    async fn index_with_retries(&self, book_id: u32, base: &str, run: &mut Option<StepRun>) -> Result<(), StepError> {
+        let index_response = self
+            .run_step("Indexing", run, || self.index_book(book_id, base))
+            .await?;
 
         info!("✅ Step 5: Verifying indexing completion...");
         if index_response.status != "updated" {
-            return Err(format!(
+            return Err(StepError::Permanent(format!(
                 "Book {} indexing verification failed - status: {}",
                 book_id, index_response.status
-            )
-            .into());
+            )));
         }
-
-        info!(
-            "Successfully completed processing pipeline for book {}",
-            book_id
-        );
         Ok(())
     }
 
-    /// Runs the pipeline sequentially for a list of book IDs.
-    async fn run_pipeline(&self, book_ids: Vec<u32>) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Starting pipeline for {} books", book_ids.len());
+    /// Runs the pipeline for a list of book IDs, up to `concurrency` books
+    /// at a time, reporting the run to `output`.
+    async fn run_pipeline(
+        &self,
+        book_ids: Vec<u32>,
+        concurrency: usize,
+        output: &ReportOutput,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Starting pipeline for {} books, {} at a time", book_ids.len(), concurrency);
 
-        for book_id in book_ids {
-            match self.process_book(book_id).await {
-                Ok(()) => info!("✓ Book {} processed successfully", book_id),
-                Err(e) => error!("✗ Failed to process book {}: {}", book_id, e),
+        let report = Mutex::new(RunReport::new(book_ids.len(), concurrency));
+        let write_progress = |report: &RunReport| {
+            if let Err(e) = output.write_progress(report) {
+                warn!("Failed to write the run report: {}", e);
             }
+        };
+        write_progress(&report.lock().unwrap());
 
-            sleep(Duration::from_millis(100)).await;
+        let run = self.process_books(&book_ids, concurrency, &SERVICE_URLS, |outcome| {
+            let mut report = report.lock().unwrap();
+            report.record(outcome);
+            write_progress(&report);
+        });
+        let summary = tokio::select! {
+            summary = run => summary,
+            _ = tokio::signal::ctrl_c(), if output.is_requested() => {
+                let mut report = report.lock().unwrap();
+                report.finish(RunStatus::Interrupted);
+                output.write_final(&report)?;
+                warn!("Interrupted after {} of {} books", report.totals.processed, book_ids.len());
+                std::process::exit(130);
+            }
+        };
+        summary.log();
+
+        let mut report = report.into_inner().unwrap();
+        report.finish(RunStatus::Completed);
+        output.write_final(&report)?;
+
+        if summary.succeeded() > 0 {
+            self.invalidate_search_cache().await;
+            self.wait_for_search(&format!("{}/status", SEARCH_SERVICE_URL), true).await?;
+            info!("Pipeline ready: the search service is serving the index");
+            self.verify_pipeline(&book_ids).await.log();
         }
 
         info!("Pipeline execution complete");
         Ok(())
     }
 
-    /// Periodically polls available books in continuous monitoring mode.
-    async fn continuous_mode(&self) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Starting continuous monitoring mode...");
-
-        loop {
-            sleep(Duration::from_secs(30)).await;
-
-            match self.get_available_books().await {
-                Ok(books) => {
-                    if !books.is_empty() {
+    /// Runs [`Self::process_book`] for every book, `concurrency` at a time,
+    /// passing each book's outcome to `on_book` as soon as it is known.
+    /// A failing book doesn't stop the others. Each slot pauses briefly
+    /// after its book, as the sequential pipeline always did.
+    async fn process_books(
+        &self,
+        book_ids: &[u32],
+        concurrency: usize,
+        urls: &ServiceUrls<'_>,
+        mut on_book: impl FnMut(&BookOutcome),
+    ) -> PipelineSummary {
+        let started = Instant::now();
+        let mut outcomes: Vec<BookOutcome> = stream::iter(book_ids)
+            .map(|&book_id| async move {
+                let outcome = self.process_book(book_id, urls).await;
+                match &outcome.error {
+                    None => {
+                        let attempts = outcome.attempts();
                         info!(
-                            "Found {} books, checking if indexing is needed",
-                            books.len()
+                            "✓ Book {} processed successfully (attempts: ingest {}, status {}, index {})",
+                            book_id, attempts.ingest, attempts.status, attempts.index
                         );
                     }
+                    Some(e) => error!("✗ Failed to process book {}: {}", book_id, e),
                 }
-                Err(e) => {
-                    error!("Failed to get book list: {}", e);
-                }
-            }
+                sleep(Duration::from_millis(100)).await;
+                outcome
+            })
+            .buffer_unordered(concurrency.max(1))
+            .inspect(|outcome| on_book(outcome))
+            .collect()
+            .await;
+        outcomes.sort_by_key(|outcome| outcome.book_id);
+
+        PipelineSummary {
+            outcomes,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Drops the search service's cached results so newly indexed books show
+    /// up in searches right away. A failure only delays that until the cache
+    /// entries expire.
+    async fn invalidate_search_cache(&self) {
+        let url = format!("{}/cache/invalidate", SEARCH_SERVICE_URL);
+        match self.post(&url).send().await {
+            Ok(response) if response.status().is_success() => info!("Search result cache invalidated"),
+            Ok(response) => warn!("Failed to invalidate the search result cache: {}", response.status()),
+            Err(e) => warn!("Failed to invalidate the search result cache: {}", e),
         }
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt()
-        .with_env_filter("control_module=info")
-        .init();
+    // Get command line arguments
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // With --json the report is printed on stdout, so the logs move to stderr
+    let output = ReportOutput::take(&mut args);
+    let json = output.as_ref().is_ok_and(|output| output.stdout);
+    init_tracing(
+        "control-module",
+        if json { LogOutput::Stderr } else { LogOutput::Stdout },
+    );
+    let output = output.unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    });
+
+    let control = ControlModule::new()?;
+
+    let concurrency = take_concurrency(&mut args).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    });
+
+    if args.len() > 1 && args[1] == "--serve" {
+        return serve(control).await;
+    }
 
-    let control = ControlModule::new();
+    if args.len() > 1 && args[1] == "--continuous" {
+        control.wait_for_services().await?;
+        control.continuous_mode(concurrency).await?;
+        return Ok(());
+    }
+
+    // Every ID is checked before any service is contacted
+    let book_ids = take_book_ids(&mut args).unwrap_or_else(|e| {
+        error!("{}", e);
+        info!("Usage: control-module [--concurrency N] [--limit N] [--from-file ids.txt] [--report out.json] [--json] [84 1-500 84,11,1342 ...] or --continuous or --serve");
+        std::process::exit(1);
+    });
 
     // Wait for all services to be ready
     control.wait_for_services().await?;
 
-    // Get command line arguments
-    let args: Vec<String> = std::env::args().collect();
+    match book_ids {
+        Some(ids) => control.run_pipeline(ids, concurrency, &output).await?,
+        None => {
+            // Default: process a few sample books
+            let default_books = vec![1342, 84, 11, 74, 1080];
+            info!(
+                "No book IDs specified, processing default books: {:?}",
+                default_books
+            );
+            control.run_pipeline(default_books, concurrency, &output).await?;
+        }
+    }
 
-    if args.len() > 1 && args[1] == "--continuous" {
-        // Run in continuous monitoring mode
-        control.continuous_mode().await?;
-    } else if args.len() > 1 {
-        // Process specific book IDs from command line
-        let book_ids: Result<Vec<u32>, _> = args[1..].iter().map(|s| s.parse()).collect();
-        match book_ids {
-            Ok(ids) => {
-                control.run_pipeline(ids).await?;
+    Ok(())
+}
+
+/// Removes `--concurrency N` (or `--concurrency=N`) from `args`, returning
+/// `N`: how many books the pipeline processes at once (default: 1).
+fn take_concurrency(args: &mut Vec<String>) -> Result<usize, String> {
+    let Some(position) = args.iter().position(|arg| arg == "--concurrency" || arg.starts_with("--concurrency=")) else {
+        return Ok(1);
+    };
+    let flag = args.remove(position);
+    let value = match flag.strip_prefix("--concurrency=") {
+        Some(value) => value.to_string(),
+        None if position < args.len() => args.remove(position),
+        None => return Err("--concurrency needs a number of books".to_string()),
+    };
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("--concurrency must be a positive number of books, not '{}'", value)),
+    }
+}
+
+/// Serves the REST API, reporting ready once [`ControlModule::wait_for_services`]
+/// succeeds. Services that time out are waited for again, so the process
+/// stays live but unready until they come up.
+async fn serve(control: ControlModule) -> Result<(), Box<dyn std::error::Error>> {
+    let port = std::env::var("CONTROL_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(server::DEFAULT_CONTROL_PORT);
+    let state = server::ServerState {
+        control: Arc::new(control),
+        readiness: server::ReadinessState::default(),
+        started_at: Instant::now(),
+    };
+
+    let waiter = state.clone();
+    tokio::spawn(async move {
+        while let Err(e) = waiter.control.wait_for_services().await {
+            warn!("Not ready yet: {}", e);
+        }
+        waiter.readiness.mark_ready();
+    });
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("Control module API listening on port {}", port);
+    axum::serve(listener, server::app(state)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Starts a server that accepts connections but only answers after 35 seconds.
+    async fn hanging_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    sleep(Duration::from_secs(35)).await;
+                    drop(socket);
+                });
+            }
+        });
+        format!("http://{}/status", addr)
+    }
+
+    #[tokio::test]
+    async fn request_to_hung_service_times_out() {
+        let url = hanging_server().await;
+        let control = ControlModule::with_timeouts(Duration::from_millis(200), Duration::from_secs(1)).unwrap();
+
+        let err = control.client.get(&url).send().await.unwrap_err();
+        assert!(err.is_timeout());
+    }
+
+    #[tokio::test]
+    async fn wait_for_hung_service_returns_timeout_error() {
+        let url = hanging_server().await;
+        let control = ControlModule::with_timeouts(Duration::from_millis(200), Duration::from_secs(1)).unwrap();
+
+        let started = std::time::Instant::now();
+        let err = control.wait_for_service("Mock", &url).await.unwrap_err();
+
+        assert!(matches!(err, ControlError::Timeout { ref service } if service == "Mock"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Answers each request with the next JSON body of `bodies`, repeating
+    /// the last one, and counts the requests.
+    async fn scripted_status_server(bodies: Vec<&'static str>) -> (String, Arc<AtomicUsize>) {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let body = bodies[n.min(bodies.len() - 1)];
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
             }
-            Err(e) => {
-                error!("Invalid book IDs provided: {}", e);
-                info!("Usage: control-module [book_id1] [book_id2] ... or --continuous");
-                std::process::exit(1);
+        });
+        (format!("http://{}/status", addr), served)
+    }
+
+    const SEARCH_EMPTY_INDEX: &str = r#"{"status": "degraded", "checks": {"backend_reachable": true, "index_present": false, "indexed_books": 0}}"#;
+    const SEARCH_HEALTHY: &str = r#"{"status": "running", "checks": {"backend_reachable": true, "index_present": true, "indexed_books": 5}}"#;
+    const SEARCH_UNREACHABLE: &str = r#"{"status": "degraded", "checks": {"backend_reachable": false, "index_present": false, "indexed_books": null}}"#;
+
+    #[test]
+    fn search_readiness_follows_the_checks() {
+        let health = |body: &str| serde_json::from_str::<SearchHealth>(body).unwrap();
+
+        assert!(search_ready(&health(SEARCH_EMPTY_INDEX), false));
+        assert!(!search_ready(&health(SEARCH_EMPTY_INDEX), true));
+        assert!(search_ready(&health(SEARCH_HEALTHY), true));
+        assert!(!search_ready(&health(SEARCH_UNREACHABLE), false));
+        // Search services without checks only report the backend
+        assert!(search_ready(&health(r#"{"status": "running"}"#), true));
+        assert!(!search_ready(&health(r#"{"status": "degraded"}"#), false));
+    }
+
+    #[tokio::test]
+    async fn waits_until_the_search_index_is_present() {
+        let (url, served) = scripted_status_server(vec![SEARCH_UNREACHABLE, SEARCH_EMPTY_INDEX, SEARCH_EMPTY_INDEX, SEARCH_HEALTHY]).await;
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(10)).unwrap();
+
+        control.wait_for_search(&url, false).await.unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+        control.wait_for_search(&url, true).await.unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn empty_search_index_times_out() {
+        let (url, _) = scripted_status_server(vec![SEARCH_EMPTY_INDEX]).await;
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_millis(500)).unwrap();
+
+        let err = control.wait_for_search(&url, true).await.unwrap_err();
+        assert!(matches!(err, ControlError::Timeout { ref service } if service == "Search"));
+    }
+
+    /// Accepts one connection and returns the raw request it received.
+    async fn capture_request() -> (String, tokio::task::JoinHandle<String>) {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await;
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+        (format!("http://{}/index/rebuild", addr), handle)
+    }
+
+    #[tokio::test]
+    async fn indexing_requests_carry_bearer_token() {
+        let (url, request) = capture_request().await;
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1))
+            .unwrap()
+            .with_indexing_token(Some("s3cret".to_string()));
+
+        control.indexing_post(&url).send().await.unwrap();
+        assert!(request.await.unwrap().contains("authorization: bearer s3cret"));
+    }
+
+    /// Answers `IndexBook` like the indexing service and records the
+    /// `authorization` metadata of each call.
+    #[derive(Default)]
+    struct MockIndexing {
+        authorizations: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    #[tonic::async_trait]
+    impl pb::indexing_service_server::IndexingService for std::sync::Arc<MockIndexing> {
+        async fn index_book(
+            &self,
+            request: tonic::Request<pb::IndexBookRequest>,
+        ) -> Result<tonic::Response<pb::IndexBookResponse>, tonic::Status> {
+            let authorization = request
+                .metadata()
+                .get("authorization")
+                .map(|value| value.to_str().unwrap().to_string());
+            self.authorizations.lock().unwrap().push(authorization);
+
+            let book_id = request.into_inner().book_id;
+            if book_id == 0 {
+                return Err(tonic::Status::internal("Failed to index book 0"));
             }
+            Ok(tonic::Response::new(pb::IndexBookResponse {
+                book_id,
+                status: "updated".to_string(),
+                timings_ms: None,
+                word_count: 120,
+                unique_words: 45,
+                elapsed_ms: 3,
+            }))
+        }
+
+        async fn rebuild_index(
+            &self,
+            _request: tonic::Request<pb::RebuildRequest>,
+        ) -> Result<tonic::Response<pb::RebuildResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("not mocked"))
+        }
+
+        async fn get_status(
+            &self,
+            _request: tonic::Request<pb::Empty>,
+        ) -> Result<tonic::Response<pb::IndexStatusResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("not mocked"))
         }
-    } else {
-        // Default: process a few sample books
-        let default_books = vec![1342, 84, 11, 74, 1080];
-        info!(
-            "No book IDs specified, processing default books: {:?}",
-            default_books
-        );
-        control.run_pipeline(default_books).await?;
     }
 
-    Ok(())
+    async fn mock_indexing_grpc() -> (String, std::sync::Arc<MockIndexing>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let mock = std::sync::Arc::new(MockIndexing::default());
+        let server = tonic::transport::Server::builder()
+            .add_service(pb::indexing_service_server::IndexingServiceServer::new(mock.clone()))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener));
+        tokio::spawn(server);
+        (url, mock)
+    }
+
+    #[tokio::test]
+    async fn books_are_indexed_over_grpc_when_configured() {
+        let (url, mock) = mock_indexing_grpc().await;
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1))
+            .unwrap()
+            .with_indexing_token(Some("s3cret".to_string()))
+            .with_indexing_grpc(&url, Duration::from_secs(5))
+            .unwrap();
+
+        let response = control.index_book(1342, INDEXING_SERVICE_URL).await.unwrap();
+        assert_eq!(response.book_id, 1342);
+        assert_eq!(response.status, "updated");
+        assert_eq!((response.word_count, response.unique_words, response.elapsed_ms), (120, 45, 3));
+
+        let err = control.index_book(0, INDEXING_SERVICE_URL).await.unwrap_err();
+        assert!(err.to_string().contains("Failed to index book 0"));
+
+        let authorizations = mock.authorizations.lock().unwrap();
+        assert_eq!(authorizations.len(), 2);
+        assert!(authorizations.iter().all(|a| a.as_deref() == Some("Bearer s3cret")));
+    }
+
+    /// Book the mock indexing service fails to index.
+    const UNINDEXABLE_BOOK: u32 = 13;
+
+    /// Answers the three calls of one book's pipeline like the ingestion and
+    /// indexing services, tracing each request as they do. Ingesting takes
+    /// `ingest_delay`, and [`UNINDEXABLE_BOOK`] fails to index.
+    async fn mock_pipeline_services(ingest_delay: Duration) -> String {
+        use axum::{extract::Path, http::StatusCode, routing::{get, post}, Json, Router};
+        use serde_json::json;
+
+        let app = Router::new()
+            .route(
+                "/ingest/:book_id",
+                post(move |Path(book_id): Path<u32>| async move {
+                    sleep(ingest_delay).await;
+                    Json(json!({ "book_id": book_id, "status": "downloaded", "path": "/app/datalake/1342.txt" }))
+                }),
+            )
+            .route(
+                "/ingest/status/:book_id",
+                get(|Path(book_id): Path<u32>| async move { Json(json!({ "book_id": book_id, "status": "available" })) }),
+            )
+            .route(
+                "/index/update/:book_id",
+                post(|Path(book_id): Path<u32>| async move {
+                    if book_id == UNINDEXABLE_BOOK {
+                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    }
+                    Ok(Json(json!({ "book_id": book_id, "status": "updated" })))
+                }),
+            )
+            .layer(tower_http::trace::TraceLayer::new_for_http().make_span_with(trace_context::make_span));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn one_books_service_calls_share_its_pipeline_trace() {
+        use opentelemetry::trace::{SpanId, TracerProvider as _};
+        use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        opentelemetry::global::set_text_map_propagator(opentelemetry_sdk::propagation::TraceContextPropagator::new());
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("control-module")));
+        // The test runtime is single-threaded, so the mock services' spans
+        // are recorded by this subscriber too
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let url = mock_pipeline_services(Duration::ZERO).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1)).unwrap();
+        assert_eq!(control.process_book(1342, &urls).await.error, None);
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let root = spans.iter().find(|span| span.name == "process_book").unwrap();
+        assert_eq!(root.parent_span_id, SpanId::INVALID);
+        let name_of = |span_id: SpanId| {
+            spans
+                .iter()
+                .find(|span| span.span_context.span_id() == span_id)
+                .map(|span| span.name.as_ref())
+        };
+
+        let requests: Vec<_> = spans.iter().filter(|span| span.name == "request").collect();
+        assert_eq!(requests.len(), 3);
+        for request in &requests {
+            assert_eq!(request.span_context.trace_id(), root.span_context.trace_id());
+        }
+        // Each request continues the span that sent it
+        let parents: Vec<_> = requests.iter().map(|request| name_of(request.parent_span_id)).collect();
+        assert_eq!(parents, [Some("ingest_book"), Some("process_book"), Some("index_book")]);
+        for step in ["ingest_book", "index_book"] {
+            let span = spans.iter().find(|span| span.name == step).unwrap();
+            assert_eq!(span.parent_span_id, root.span_context.span_id());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_pipelines_finish_in_a_fraction_of_the_time() {
+        let url = mock_pipeline_services(Duration::from_millis(300)).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1)).unwrap();
+        let books = [6, 5, 4, 3, 2, 1];
+
+        let sequential = control.process_books(&books, 1, &urls, |_| {}).await;
+        let concurrent = control.process_books(&books, 3, &urls, |_| {}).await;
+
+        assert_eq!(sequential.succeeded(), 6);
+        assert_eq!(concurrent.succeeded(), 6);
+        let ids: Vec<u32> = concurrent.outcomes.iter().map(|outcome| outcome.book_id).collect();
+        assert_eq!(ids, [1, 2, 3, 4, 5, 6]);
+        // Each book takes about 0.9s, so 5.4s in a row against 1.8s three at a time
+        let ratio = concurrent.elapsed.as_secs_f64() / sequential.elapsed.as_secs_f64();
+        assert!((0.25..0.45).contains(&ratio), "{:?} vs {:?}", concurrent.elapsed, sequential.elapsed);
+    }
+
+    #[tokio::test]
+    async fn a_failing_book_does_not_stop_the_others() {
+        let url = mock_pipeline_services(Duration::ZERO).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::immediate(3));
+
+        let summary = control.process_books(&[42, UNINDEXABLE_BOOK, 7], 2, &urls, |_| {}).await;
+
+        assert_eq!(summary.succeeded(), 2);
+        assert_eq!((summary.outcomes[0].book_id, &summary.outcomes[0].error), (7, &None));
+        assert_eq!(summary.outcomes[1].book_id, UNINDEXABLE_BOOK);
+        let error = summary.outcomes[1].error.as_ref().unwrap();
+        assert!(matches!(error, StepError::Exhausted { attempts: 3, .. }), "{:?}", error);
+        assert!(error.to_string().contains("500"), "{}", error);
+        assert_eq!((summary.outcomes[2].book_id, &summary.outcomes[2].error), (42, &None));
+    }
+
+    #[tokio::test]
+    async fn run_report_matches_its_snapshot() {
+        let url = mock_pipeline_services(Duration::ZERO).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::immediate(2));
+        let books = [1342, UNINDEXABLE_BOOK];
+
+        let mut report = RunReport::new(books.len(), 2);
+        control
+            .process_books(&books, 2, &urls, |outcome| report.record(outcome))
+            .await;
+        report.finish(RunStatus::Completed);
+
+        let json = serde_json::to_string_pretty(&report.without_timings()).unwrap();
+        assert_eq!(json, include_str!("snapshots/two_book_run.json").trim_end());
+    }
+
+    #[tokio::test]
+    async fn books_from_an_id_file_go_through_the_pipeline_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.txt");
+        std::fs::write(&path, "# Tuesday's batch\n41-43\n84, 42 # already in the range\n").unwrap();
+        let mut args: Vec<String> = ["control-module", "--from-file", path.to_str().unwrap(), "13,43"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let book_ids = take_book_ids(&mut args).unwrap().unwrap();
+        assert_eq!(book_ids, [13, 41, 42, 43, 84]);
+
+        let url = mock_pipeline_services(Duration::ZERO).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::immediate(1));
+        let summary = control.process_books(&book_ids, 2, &urls, |_| {}).await;
+
+        let processed: Vec<u32> = summary.outcomes.iter().map(|outcome| outcome.book_id).collect();
+        assert_eq!(processed, book_ids);
+        assert_eq!(summary.succeeded(), 4);
+        assert!(summary.outcomes[0].error.is_some());
+    }
+
+    /// Book the mock services don't have, failing ingestion permanently.
+    const MISSING_BOOK: u32 = 404;
+
+    /// Pipeline services whose ingestion and indexing each answer the first
+    /// `failures` requests with a `503` error response, and whose status
+    /// check times out once. [`MISSING_BOOK`] gets a `404 BOOK_NOT_FOUND`.
+    async fn flaky_pipeline_services(failures: usize) -> String {
+        use axum::{extract::Path, http::StatusCode, routing::{get, post}, Json, Router};
+        use serde_json::json;
+
+        let unavailable = || {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "error_code": "SERVICE_UNAVAILABLE", "message": "try again later" })),
+            )
+        };
+        let ingested = Arc::new(AtomicUsize::new(0));
+        let checked = Arc::new(AtomicUsize::new(0));
+        let indexed = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/ingest/:book_id",
+                post(move |Path(book_id): Path<u32>| async move {
+                    if book_id == MISSING_BOOK {
+                        let body = json!({ "error_code": "BOOK_NOT_FOUND", "message": "Book 404 is not on Project Gutenberg", "book_id": book_id });
+                        return Err((StatusCode::NOT_FOUND, Json(body)));
+                    }
+                    if ingested.fetch_add(1, Ordering::SeqCst) < failures {
+                        return Err(unavailable());
+                    }
+                    Ok(Json(json!({ "book_id": book_id, "status": "downloaded", "path": "/app/datalake/20250101/09" })))
+                }),
+            )
+            .route(
+                "/ingest/status/:book_id",
+                get(move |Path(book_id): Path<u32>| async move {
+                    if checked.fetch_add(1, Ordering::SeqCst) == 0 {
+                        sleep(Duration::from_secs(2)).await;
+                    }
+                    Json(json!({ "book_id": book_id, "status": "available" }))
+                }),
+            )
+            .route(
+                "/index/update/:book_id",
+                post(move |Path(book_id): Path<u32>| async move {
+                    if indexed.fetch_add(1, Ordering::SeqCst) < failures {
+                        return Err(unavailable());
+                    }
+                    Ok(Json(json!({ "book_id": book_id, "status": "updated" })))
+                }),
+            );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_until_the_book_goes_through() {
+        let url = flaky_pipeline_services(2).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_millis(500), Duration::from_secs(1))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::immediate(3));
+
+        let outcome = control.process_book(1342, &urls).await;
+        assert_eq!(outcome.error, None);
+        assert_eq!(outcome.attempts(), StepAttempts { ingest: 3, status: 2, index: 3 });
+    }
+
+    #[tokio::test]
+    async fn failures_are_told_apart_in_the_summary() {
+        let url = flaky_pipeline_services(usize::MAX).await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_millis(500), Duration::from_secs(1))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::immediate(2));
+
+        let summary = control.process_books(&[84, MISSING_BOOK], 1, &urls, |_| {}).await;
+
+        assert_eq!(summary.succeeded(), 0);
+        assert_eq!((summary.failed_after_retries(), summary.failed_permanently()), (1, 1));
+        let exhausted = summary.outcomes[0].error.as_ref().unwrap();
+        assert!(matches!(exhausted, StepError::Exhausted { attempts: 2, .. }), "{:?}", exhausted);
+        assert!(exhausted.to_string().contains("SERVICE_UNAVAILABLE"), "{}", exhausted);
+        let permanent = summary.outcomes[1].error.as_ref().unwrap();
+        assert!(matches!(permanent, StepError::Permanent(m) if m.contains("BOOK_NOT_FOUND")), "{:?}", permanent);
+    }
+
+    #[test]
+    fn concurrency_is_taken_out_of_the_arguments() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let mut books = args(&["control-module", "84", "--concurrency", "4", "1342"]);
+        assert_eq!(take_concurrency(&mut books), Ok(4));
+        assert_eq!(books, args(&["control-module", "84", "1342"]));
+        assert_eq!(take_concurrency(&mut args(&["control-module", "--concurrency=2"])), Ok(2));
+        assert_eq!(take_concurrency(&mut args(&["control-module", "84"])), Ok(1));
+        for bad in [&["control-module", "--concurrency"][..], &["control-module", "--concurrency=0"], &["control-module", "--concurrency", "x"]] {
+            assert!(take_concurrency(&mut args(bad)).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn indexing_requests_are_unauthenticated_without_token() {
+        let (url, request) = capture_request().await;
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1)).unwrap();
+
+        control.indexing_post(&url).send().await.unwrap();
+        assert!(!request.await.unwrap().contains("authorization:"));
+    }
 }
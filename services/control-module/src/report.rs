@@ -0,0 +1,385 @@
+//! Run Reports
+//!
+//! `--report out.json` writes a JSON report of a pipeline run for
+//! dashboards, and `--json` prints it on stdout once the run is over (the
+//! logs then go to stderr). Per book, it holds each step's result, attempts,
+//! retries and time, and the book's final status; for the run, the totals,
+//! the wall-clock duration and the throughput.
+//!
+//! The file is rewritten after every book, each time in full and atomically,
+//! so a run that dies partway leaves a valid report of the books done, with
+//! `"status": "running"`. Ctrl-C writes it one last time as `"interrupted"`.
+//!
+//! ```json
+//! {
+//!   "status": "completed",
+//!   "started_at": "2025-01-01T09:00:00Z",
+//!   "concurrency": 2,
+//!   "duration_ms": 2140,
+//!   "books_per_second": 0.93,
+//!   "totals": { "requested": 2, "processed": 2, "succeeded": 1, "failed": 1, ... },
+//!   "books": [
+//!     {
+//!       "book_id": 84,
+//!       "status": "succeeded",
+//!       "retries": 1,
+//!       "duration_ms": 1530,
+//!       "ingest": { "status": "succeeded", "attempts": 2, "retries": 1, "duration_ms": 910 },
+//!       ...
+//! ```
+
+use crate::retry::StepError;
+use crate::{BookOutcome, StepRun};
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RunStatus {
+    Running,
+    Completed,
+    Interrupted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BookStatus {
+    Succeeded,
+    FailedAfterRetries,
+    FailedPermanently,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StepStatus {
+    Succeeded,
+    Failed,
+    /// An earlier step failed, so this one never ran.
+    Skipped,
+}
+
+/// One step of a book's pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct StepReport {
+    pub status: StepStatus,
+    pub attempts: u32,
+    pub retries: u32,
+    /// Every attempt and the backoff between them.
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StepReport {
+    fn new(run: Option<StepRun>, error: Option<&StepError>) -> Self {
+        let Some(run) = run else {
+            return Self {
+                status: StepStatus::Skipped,
+                attempts: 0,
+                retries: 0,
+                duration_ms: 0,
+                error: None,
+            };
+        };
+        Self {
+            status: if error.is_some() { StepStatus::Failed } else { StepStatus::Succeeded },
+            attempts: run.attempts,
+            retries: run.attempts.saturating_sub(1),
+            duration_ms: millis(run.elapsed),
+            error: error.map(ToString::to_string),
+        }
+    }
+}
+
+/// One book's pipeline: ingesting it, checking the ingestion service holds
+/// it, and indexing it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct BookReport {
+    pub book_id: u32,
+    pub status: BookStatus,
+    /// Retries across the book's steps.
+    pub retries: u32,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub ingest: StepReport,
+    pub ingestion_check: StepReport,
+    pub index: StepReport,
+}
+
+impl From<&BookOutcome> for BookReport {
+    fn from(outcome: &BookOutcome) -> Self {
+        let steps = [outcome.steps.ingest, outcome.steps.status, outcome.steps.index];
+        // The last step that ran is the one that failed
+        let failed_step = outcome.error.as_ref().and_then(|_| steps.iter().rposition(Option::is_some));
+        let step = |index: usize| {
+            let error = outcome.error.as_ref().filter(|_| failed_step == Some(index));
+            StepReport::new(steps[index], error)
+        };
+        let (ingest, ingestion_check, index) = (step(0), step(1), step(2));
+
+        Self {
+            book_id: outcome.book_id,
+            status: match outcome.error {
+                None => BookStatus::Succeeded,
+                Some(StepError::Exhausted { .. }) => BookStatus::FailedAfterRetries,
+                Some(StepError::Permanent(_)) => BookStatus::FailedPermanently,
+            },
+            retries: ingest.retries + ingestion_check.retries + index.retries,
+            duration_ms: millis(outcome.elapsed),
+            error: outcome.error.as_ref().map(ToString::to_string),
+            ingest,
+            ingestion_check,
+            index,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub(crate) struct Totals {
+    /// Books the run was asked to process.
+    pub requested: usize,
+    /// Books finished so far, whether they succeeded or not.
+    pub processed: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub failed_after_retries: usize,
+    pub failed_permanently: usize,
+}
+
+/// The report of one pipeline run, books in ID order.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RunReport {
+    pub status: RunStatus,
+    /// RFC 3339, UTC.
+    pub started_at: String,
+    pub concurrency: usize,
+    pub duration_ms: u64,
+    /// Books processed per second of wall-clock time.
+    pub books_per_second: f64,
+    pub totals: Totals,
+    pub books: Vec<BookReport>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl RunReport {
+    pub fn new(requested: usize, concurrency: usize) -> Self {
+        Self {
+            status: RunStatus::Running,
+            started_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            concurrency,
+            duration_ms: 0,
+            books_per_second: 0.0,
+            totals: Totals {
+                requested,
+                ..Totals::default()
+            },
+            books: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Adds a finished book.
+    pub fn record(&mut self, outcome: &BookOutcome) {
+        let book = BookReport::from(outcome);
+        let totals = &mut self.totals;
+        totals.processed += 1;
+        match book.status {
+            BookStatus::Succeeded => totals.succeeded += 1,
+            BookStatus::FailedAfterRetries => totals.failed_after_retries += 1,
+            BookStatus::FailedPermanently => totals.failed_permanently += 1,
+        }
+        totals.failed = totals.failed_after_retries + totals.failed_permanently;
+
+        let position = self.books.partition_point(|other| other.book_id < book.book_id);
+        self.books.insert(position, book);
+        self.update_duration();
+    }
+
+    pub fn finish(&mut self, status: RunStatus) {
+        self.status = status;
+        self.update_duration();
+    }
+
+    fn update_duration(&mut self) {
+        let elapsed = self.started.elapsed();
+        self.duration_ms = millis(elapsed);
+        self.books_per_second = if elapsed.is_zero() {
+            0.0
+        } else {
+            self.totals.processed as f64 / elapsed.as_secs_f64()
+        };
+    }
+
+    /// The report with every time and the start date fixed, for comparing
+    /// runs.
+    #[cfg(test)]
+    pub fn without_timings(mut self) -> Self {
+        self.started_at = "2025-01-01T09:00:00Z".to_string();
+        self.duration_ms = 0;
+        self.books_per_second = 0.0;
+        for book in &mut self.books {
+            book.duration_ms = 0;
+            for step in [&mut book.ingest, &mut book.ingestion_check, &mut book.index] {
+                step.duration_ms = 0;
+            }
+        }
+        self
+    }
+}
+
+fn millis(elapsed: Duration) -> u64 {
+    elapsed.as_millis() as u64
+}
+
+/// Where the run report goes: `--report PATH` and `--json`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ReportOutput {
+    pub file: Option<PathBuf>,
+    pub stdout: bool,
+}
+
+impl ReportOutput {
+    /// Removes `--report PATH` (or `--report=PATH`) and `--json` from `args`.
+    pub fn take(args: &mut Vec<String>) -> Result<Self, String> {
+        let stdout = args.iter().any(|arg| arg == "--json");
+        args.retain(|arg| arg != "--json");
+
+        let Some(position) = args.iter().position(|arg| arg == "--report" || arg.starts_with("--report=")) else {
+            return Ok(Self { file: None, stdout });
+        };
+        let flag = args.remove(position);
+        let path = match flag.strip_prefix("--report=") {
+            Some(path) => path.to_string(),
+            None if position < args.len() => args.remove(position),
+            None => return Err("--report needs a file to write the report to".to_string()),
+        };
+        if path.is_empty() {
+            return Err("--report needs a file to write the report to".to_string());
+        }
+        Ok(Self {
+            file: Some(PathBuf::from(path)),
+            stdout,
+        })
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.file.is_some() || self.stdout
+    }
+
+    /// Rewrites the report file, if any, with the run so far.
+    pub fn write_progress(&self, report: &RunReport) -> io::Result<()> {
+        match &self.file {
+            Some(path) => write_atomically(path, report),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the report of a finished or interrupted run to the file and,
+    /// with `--json`, to stdout.
+    pub fn write_final(&self, report: &RunReport) -> io::Result<()> {
+        self.write_progress(report)?;
+        if self.stdout {
+            println!("{}", serde_json::to_string_pretty(report)?);
+        }
+        Ok(())
+    }
+}
+
+/// Writes `report` next to `path` and renames it over `path`, so readers
+/// never see half a report.
+fn write_atomically(path: &Path, report: &RunReport) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    std::fs::write(&temporary, serde_json::to_vec_pretty(report)?)?;
+    std::fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StepRuns;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn report_flags_are_taken_out_of_the_arguments() {
+        let mut books = args(&["control-module", "84", "--report", "out.json", "--json", "11"]);
+        let output = ReportOutput::take(&mut books).unwrap();
+        assert_eq!(
+            output,
+            ReportOutput {
+                file: Some(PathBuf::from("out.json")),
+                stdout: true
+            }
+        );
+        assert_eq!(books, args(&["control-module", "84", "11"]));
+
+        let output = ReportOutput::take(&mut args(&["control-module", "--report=run.json"])).unwrap();
+        assert_eq!(output.file, Some(PathBuf::from("run.json")));
+        assert!(!ReportOutput::take(&mut args(&["control-module", "84"])).unwrap().is_requested());
+        assert!(ReportOutput::take(&mut args(&["control-module", "--report"])).is_err());
+    }
+
+    #[test]
+    fn the_file_holds_the_books_finished_so_far() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = ReportOutput {
+            file: Some(dir.path().join("run.json")),
+            stdout: false,
+        };
+        let run = |attempts| {
+            Some(StepRun {
+                attempts,
+                elapsed: Duration::from_millis(5),
+            })
+        };
+        let mut report = RunReport::new(3, 1);
+        report.record(&BookOutcome {
+            book_id: 84,
+            steps: StepRuns {
+                ingest: run(1),
+                status: run(1),
+                index: run(2),
+            },
+            elapsed: Duration::from_millis(15),
+            error: None,
+        });
+        output.write_progress(&report).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join("run.json")).unwrap()).unwrap();
+        assert_eq!(written["status"], "running");
+        assert_eq!(written["totals"]["requested"], 3);
+        assert_eq!(written["totals"]["processed"], 1);
+        assert_eq!(written["books"][0]["retries"], 1);
+        assert_eq!(written["books"][0]["index"]["attempts"], 2);
+        assert!(!dir.path().join("run.json.tmp").exists());
+
+        report.record(&BookOutcome {
+            book_id: 11,
+            steps: StepRuns {
+                ingest: run(1),
+                ..StepRuns::default()
+            },
+            elapsed: Duration::from_millis(5),
+            error: Some(StepError::Permanent("Failed to ingest book 11: 404".to_string())),
+        });
+        report.finish(RunStatus::Interrupted);
+        output.write_final(&report).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join("run.json")).unwrap()).unwrap();
+        assert_eq!(written["status"], "interrupted");
+        assert_eq!(written["books"][0]["book_id"], 11);
+        assert_eq!(written["books"][0]["status"], "failed_permanently");
+        assert_eq!(written["books"][0]["ingest"]["status"], "failed");
+        assert_eq!(written["books"][0]["index"]["status"], "skipped");
+        assert_eq!(written["totals"]["failed"], 1);
+    }
+}
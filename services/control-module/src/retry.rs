@@ -0,0 +1,291 @@
+//! Retrying Pipeline Steps
+//!
+//! [`ControlModule::process_book`](crate::ControlModule) runs each call to
+//! the ingestion and indexing services through a [`RetryPolicy`]. A call that
+//! failed transiently — a timeout, a refused connection, a `5xx`, `408` or
+//! `429` — is tried again after an exponential backoff with jitter. One that
+//! failed permanently is not: a `4xx`, or a structured error response whose
+//! `error_code` no retry can fix (e.g. `BOOK_NOT_FOUND`, `VALIDATION_FAILED`).
+//!
+//! ## Environment Variables
+//! - `PIPELINE_MAX_ATTEMPTS`: Attempts at each step, the first included (default: `3`)
+//! - `PIPELINE_BACKOFF_INITIAL_MS`, `PIPELINE_BACKOFF_MULTIPLIER`, `PIPELINE_BACKOFF_MAX_MS`,
+//!   `PIPELINE_BACKOFF_JITTER`: Backoff between attempts (defaults: `500`, `2.0`, `10000`, `0.2`)
+
+use rand::Rng;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::future::Future;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::sleep;
+use tracing::warn;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// `error_code`s of failures that fail the same way however often they're
+/// retried, whatever their status code.
+const PERMANENT_ERROR_CODES: &[&str] = &["BOOK_NOT_FOUND", "VALIDATION_FAILED", "INVALID_REQUEST", "UNAUTHORIZED"];
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// How one attempt at a step failed.
+#[derive(Error, Debug)]
+pub(crate) enum AttemptError {
+    /// The service couldn't be reached or was overloaded; a later attempt
+    /// may succeed.
+    #[error("{0}")]
+    Transient(String),
+    #[error("{0}")]
+    Permanent(String),
+}
+
+/// The parts of a service's `ErrorResponse` used to classify it.
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    error_code: String,
+    message: String,
+}
+
+impl AttemptError {
+    /// A request that got no usable response: transient unless the response
+    /// arrived but couldn't be read.
+    pub fn request(context: &str, error: reqwest::Error) -> Self {
+        let message = format!("{}: {}", context, error);
+        if error.is_timeout() || error.is_connect() || error.is_request() {
+            AttemptError::Transient(message)
+        } else {
+            AttemptError::Permanent(message)
+        }
+    }
+
+    /// An error response, classified by its `error_code` when the service
+    /// sent an `ErrorResponse`, and by its status otherwise.
+    pub async fn response(context: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = response.json::<ErrorBody>().await.ok();
+        let message = match &body {
+            Some(body) => format!("{}: {} ({}: {})", context, status, body.error_code, body.message),
+            None => format!("{}: {}", context, status),
+        };
+
+        let permanent_code = body
+            .as_ref()
+            .is_some_and(|body| PERMANENT_ERROR_CODES.contains(&body.error_code.as_str()));
+        let transient_status = status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS;
+        if transient_status && !permanent_code {
+            AttemptError::Transient(message)
+        } else {
+            AttemptError::Permanent(message)
+        }
+    }
+
+    /// A failed gRPC call, transient for the codes of an unreachable,
+    /// overloaded or failing server.
+    pub fn grpc(context: &str, status: tonic::Status) -> Self {
+        use tonic::Code;
+
+        let message = format!("{}: {}", context, status);
+        match status.code() {
+            Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted | Code::Internal | Code::Unknown => {
+                AttemptError::Transient(message)
+            }
+            _ => AttemptError::Permanent(message),
+        }
+    }
+}
+
+/// Why a step of a book's pipeline failed for good.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub(crate) enum StepError {
+    /// Every attempt failed transiently; the last failure is `message`.
+    #[error("{message} (gave up after {attempts} attempts)")]
+    Exhausted { message: String, attempts: u32 },
+    /// The step failed in a way retrying can't fix.
+    #[error("{0}")]
+    Permanent(String),
+}
+
+/// Exponential backoff between attempts, each wait randomly lengthened or
+/// shortened by up to `jitter_fraction` so books failing together don't
+/// retry together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Backoff {
+    pub initial_delay_ms: u64,
+    pub multiplier: f64,
+    pub max_delay_ms: u64,
+    pub jitter_fraction: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 500,
+            multiplier: 2.0,
+            max_delay_ms: 10_000,
+            jitter_fraction: 0.2,
+        }
+    }
+}
+
+impl Backoff {
+    /// Wait before retry number `retry` (starting at 1).
+    pub fn delay(&self, retry: u32) -> Duration {
+        let base = self.initial_delay_ms as f64 * self.multiplier.powi(retry.saturating_sub(1) as i32);
+        let base = base.min(self.max_delay_ms as f64);
+        let jitter = if self.jitter_fraction > 0.0 {
+            rand::thread_rng().gen_range(-self.jitter_fraction..=self.jitter_fraction)
+        } else {
+            0.0
+        };
+        Duration::from_millis((base * (1.0 + jitter)).round() as u64)
+    }
+}
+
+/// How often, and how patiently, a failing step is attempted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RetryPolicy {
+    /// Attempts at each step, the first included; at least 1.
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads `PIPELINE_MAX_ATTEMPTS` and the `PIPELINE_BACKOFF_*` variables.
+    pub fn from_env() -> Self {
+        let defaults = Backoff::default();
+        Self {
+            max_attempts: env_or("PIPELINE_MAX_ATTEMPTS", DEFAULT_MAX_ATTEMPTS).max(1),
+            backoff: Backoff {
+                initial_delay_ms: env_or("PIPELINE_BACKOFF_INITIAL_MS", defaults.initial_delay_ms),
+                multiplier: env_or("PIPELINE_BACKOFF_MULTIPLIER", defaults.multiplier).max(1.0),
+                max_delay_ms: env_or("PIPELINE_BACKOFF_MAX_MS", defaults.max_delay_ms),
+                jitter_fraction: env_or("PIPELINE_BACKOFF_JITTER", defaults.jitter_fraction).clamp(0.0, 1.0),
+            },
+        }
+    }
+
+    /// Retries without waiting, for tests.
+    #[cfg(test)]
+    pub fn immediate(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff {
+                initial_delay_ms: 0,
+                jitter_fraction: 0.0,
+                ..Backoff::default()
+            },
+        }
+    }
+
+    /// Runs `attempt` until it succeeds, fails permanently or has been tried
+    /// `max_attempts` times, returning its value and the attempts it took.
+    pub async fn run<T, F, Fut>(&self, step: &str, mut attempt: F) -> Result<(T, u32), StepError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AttemptError>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match attempt().await {
+                Ok(value) => return Ok((value, attempts)),
+                Err(AttemptError::Permanent(message)) => return Err(StepError::Permanent(message)),
+                Err(AttemptError::Transient(message)) if attempts >= max_attempts => {
+                    return Err(StepError::Exhausted { message, attempts });
+                }
+                Err(AttemptError::Transient(message)) => {
+                    let delay = self.backoff.delay(attempts);
+                    warn!(
+                        "{} failed (attempt {}/{}), retrying in {:?}: {}",
+                        step, attempts, max_attempts, delay, message
+                    );
+                    sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_until_the_attempts_run_out() {
+        let calls = AtomicU32::new(0);
+        let flaky = || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(AttemptError::Transient("503".to_string())),
+                _ => Ok("done"),
+            }
+        };
+        assert_eq!(RetryPolicy::immediate(3).run("Ingesting", flaky).await, Ok(("done", 3)));
+
+        calls.store(0, Ordering::SeqCst);
+        assert_eq!(
+            RetryPolicy::immediate(2).run("Ingesting", flaky).await,
+            Err(StepError::Exhausted {
+                message: "503".to_string(),
+                attempts: 2
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn permanent_failures_are_not_retried() {
+        let calls = AtomicU32::new(0);
+        let result: Result<((), u32), _> = RetryPolicy::immediate(5)
+            .run("Ingesting", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(AttemptError::Permanent("404".to_string()))
+            })
+            .await;
+
+        assert_eq!(result, Err(StepError::Permanent("404".to_string())));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn backoff_grows_up_to_its_maximum() {
+        let backoff = Backoff {
+            initial_delay_ms: 100,
+            multiplier: 3.0,
+            max_delay_ms: 500,
+            jitter_fraction: 0.0,
+        };
+        let delays: Vec<u64> = (1..=4).map(|retry| backoff.delay(retry).as_millis() as u64).collect();
+        assert_eq!(delays, [100, 300, 500, 500]);
+
+        let jittered = Backoff { jitter_fraction: 0.2, ..backoff };
+        for _ in 0..20 {
+            assert!((80..=120).contains(&jittered.delay(1).as_millis()));
+        }
+    }
+
+    #[test]
+    fn grpc_codes_are_classified() {
+        let transient = AttemptError::grpc("Failed to index book 7", tonic::Status::unavailable("down"));
+        assert!(matches!(transient, AttemptError::Transient(_)));
+        let permanent = AttemptError::grpc("Failed to index book 7", tonic::Status::not_found("no book"));
+        assert!(matches!(permanent, AttemptError::Permanent(ref m) if m.starts_with("Failed to index book 7")));
+    }
+}
@@ -0,0 +1,191 @@
+//! Control Module REST API
+//!
+//! Served in `--serve` mode on `CONTROL_PORT` (default `7000`), so
+//! orchestrators can probe the control module separately from the pipeline
+//! services. Probes answer `{"probe": ..., "ok": ...}`, with `503` when not ok.
+//!
+//! **GET /live** (liveness, also `/status/live`)
+//! → Always `200` while the process answers
+//!
+//! **GET /ready** (readiness, also `/status/ready`)
+//! → `503` until every pipeline service has answered its `/status` once
+//!
+//! **GET /status**
+//! → The pipeline status: readiness, uptime and whether each service's
+//! `/status` answers right now
+//!
+//! Each request is traced as a child of the caller's span, when it sends a
+//! `traceparent` (see [`common_telemetry::trace_context`]).
+
+use common_telemetry::trace_context;
+use crate::ControlModule;
+use axum::{
+    extract::{FromRef, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tower_http::trace::TraceLayer;
+
+pub const DEFAULT_CONTROL_PORT: u16 = 7000;
+
+/// Whether the pipeline services have all been reached. Clones share the
+/// flag, so the task waiting for the services can set it for the handlers.
+#[derive(Clone, Default)]
+pub struct ReadinessState(pub Arc<AtomicBool>);
+
+impl ReadinessState {
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    pub fn mark_ready(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+#[derive(Clone)]
+pub struct ServerState {
+    pub control: Arc<ControlModule>,
+    pub readiness: ReadinessState,
+    pub started_at: Instant,
+}
+
+impl FromRef<ServerState> for ReadinessState {
+    fn from_ref(state: &ServerState) -> Self {
+        state.readiness.clone()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProbeResponse {
+    probe: &'static str,
+    ok: bool,
+}
+
+/// One pipeline service as last probed by `GET /status`.
+#[derive(Debug, Serialize)]
+pub struct ServiceStatus {
+    pub name: &'static str,
+    pub url: String,
+    pub reachable: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PipelineStatus {
+    service: &'static str,
+    /// `"running"` once ready and every service answers, else `"starting"`
+    /// or `"degraded"`.
+    status: &'static str,
+    ready: bool,
+    uptime_secs: u64,
+    services: Vec<ServiceStatus>,
+}
+
+pub fn app(state: ServerState) -> Router {
+    Router::new()
+        .route("/live", get(liveness_check))
+        .route("/status/live", get(liveness_check))
+        .route("/ready", get(readiness_check))
+        .route("/status/ready", get(readiness_check))
+        .route("/status", get(pipeline_status))
+        .layer(TraceLayer::new_for_http().make_span_with(trace_context::make_span))
+        .with_state(state)
+}
+
+fn probe_response(probe: &'static str, ok: bool) -> (StatusCode, Json<ProbeResponse>) {
+    let code = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(ProbeResponse { probe, ok }))
+}
+
+async fn liveness_check() -> (StatusCode, Json<ProbeResponse>) {
+    probe_response("liveness", true)
+}
+
+async fn readiness_check(State(readiness): State<ReadinessState>) -> (StatusCode, Json<ProbeResponse>) {
+    probe_response("readiness", readiness.is_ready())
+}
+
+async fn pipeline_status(State(state): State<ServerState>) -> Json<PipelineStatus> {
+    let ready = state.readiness.is_ready();
+    let services = state.control.service_statuses().await;
+    let status = match (ready, services.iter().all(|service| service.reachable)) {
+        (false, _) => "starting",
+        (true, true) => "running",
+        (true, false) => "degraded",
+    };
+
+    Json(PipelineStatus {
+        service: "control-module",
+        status,
+        ready,
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        services,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn state() -> ServerState {
+        let control = ControlModule::with_timeouts(Duration::from_millis(500), Duration::from_secs(1)).unwrap();
+        ServerState {
+            control: Arc::new(control),
+            readiness: ReadinessState::default(),
+            started_at: Instant::now(),
+        }
+    }
+
+    async fn get_json(state: &ServerState, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app(state.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_the_shared_flag_while_liveness_stays_ok() {
+        let state = state();
+
+        for uri in ["/ready", "/status/ready"] {
+            let (status, body) = get_json(&state, uri).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(body, serde_json::json!({"probe": "readiness", "ok": false}));
+        }
+        for uri in ["/live", "/status/live"] {
+            assert_eq!(get_json(&state, uri).await.0, StatusCode::OK);
+        }
+
+        state.readiness.clone().mark_ready();
+        for uri in ["/ready", "/status/ready", "/live"] {
+            let (status, body) = get_json(&state, uri).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["ok"], true);
+        }
+    }
+
+    #[tokio::test]
+    async fn status_reports_readiness_and_each_service() {
+        let state = state();
+
+        let (status, body) = get_json(&state, "/status").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "starting");
+        assert_eq!(body["ready"], false);
+        let names: Vec<_> = body["services"].as_array().unwrap().iter().map(|s| s["name"].clone()).collect();
+        assert_eq!(names, ["ingestion", "indexing", "search"]);
+    }
+}
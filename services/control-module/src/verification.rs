@@ -0,0 +1,260 @@
+//! Pipeline Verification
+//!
+//! After a run, [`ControlModule::verify_pipeline`] checks each book end to
+//! end through the services' own APIs, stopping at the book's first failing
+//! step:
+//!
+//! 1. `ingestion` — the ingestion service reports the book `available`
+//! 2. `indexing` — the indexing service's verification finds its metadata
+//!    and words (`POST /index/book/{id}/verify` isn't `corrupt`)
+//! 3. `search_count` — searching the book's title returns results
+//! 4. `search_title` — the book is among the first [`SEARCH_PAGE`] of them
+
+use crate::{ControlModule, ServiceUrls, StatusResponse, SERVICE_URLS};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// Results of a title search looked through for the book.
+const SEARCH_PAGE: usize = 100;
+
+/// How many books passed verification, and why the others didn't.
+#[derive(Debug, Default)]
+pub(crate) struct VerificationReport {
+    pub checked: usize,
+    pub passed: usize,
+    pub failures: Vec<VerificationFailure>,
+}
+
+/// The first step a book failed.
+#[derive(Debug, PartialEq)]
+pub(crate) struct VerificationFailure {
+    pub book_id: u32,
+    pub step: String,
+    pub reason: String,
+}
+
+impl VerificationReport {
+    pub fn log(&self) {
+        if self.failures.is_empty() {
+            info!("✅ Verified all {} books end to end", self.checked);
+            return;
+        }
+        warn!("Verified {}/{} books end to end", self.passed, self.checked);
+        for failure in &self.failures {
+            warn!("✗ Book {} failed the {} check: {}", failure.book_id, failure.step, failure.reason);
+        }
+    }
+}
+
+/// The part of `POST /index/book/{id}/verify` checked.
+#[derive(Debug, Deserialize)]
+struct IndexVerification {
+    integrity: String,
+}
+
+/// The part of `GET /search/book/{id}` needed to search for the book.
+#[derive(Debug, Deserialize)]
+struct SearchBook {
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    book_id: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPage {
+    total_count: usize,
+    results: Vec<SearchHit>,
+}
+
+impl ControlModule {
+    /// Checks that each of `book_ids` made it through ingestion and
+    /// indexing and can be found by searching its title.
+    pub(crate) async fn verify_pipeline(&self, book_ids: &[u32]) -> VerificationReport {
+        self.verify_pipeline_at(book_ids, &SERVICE_URLS).await
+    }
+
+    pub(crate) async fn verify_pipeline_at(&self, book_ids: &[u32], urls: &ServiceUrls<'_>) -> VerificationReport {
+        let mut report = VerificationReport {
+            checked: book_ids.len(),
+            ..VerificationReport::default()
+        };
+        for &book_id in book_ids {
+            match self.verify_book(book_id, urls).await {
+                Ok(()) => report.passed += 1,
+                Err((step, reason)) => report.failures.push(VerificationFailure {
+                    book_id,
+                    step: step.to_string(),
+                    reason,
+                }),
+            }
+        }
+        report
+    }
+
+    /// Runs the steps in order, returning the first one that fails.
+    async fn verify_book(&self, book_id: u32, urls: &ServiceUrls<'_>) -> Result<(), (&'static str, String)> {
+        let fail = |step: &'static str| move |reason: String| (step, reason);
+
+        self.verify_ingested(book_id, urls.ingestion).await.map_err(fail("ingestion"))?;
+        self.verify_indexed(book_id, urls.indexing).await.map_err(fail("indexing"))?;
+
+        let title = self.search_title(book_id, urls.search).await.map_err(fail("search_title"))?;
+        let page = self.search_for(&title, urls.search).await.map_err(fail("search_count"))?;
+        if page.total_count == 0 {
+            return Err(("search_count", format!("searching {:?} returned no results", title)));
+        }
+        if !page.results.iter().any(|hit| hit.book_id == book_id) {
+            return Err((
+                "search_title",
+                format!("searching {:?} returned {} results without the book", title, page.total_count),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn verify_ingested(&self, book_id: u32, base: &str) -> Result<(), String> {
+        let url = format!("{}/ingest/status/{}", base, book_id);
+        let response = self.get(&url).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("ingestion service responded with {}", response.status()));
+        }
+        let status: StatusResponse = response.json().await.map_err(|e| e.to_string())?;
+        match status.status.as_str() {
+            "available" => Ok(()),
+            other => Err(format!("status is {:?}, not \"available\"", other)),
+        }
+    }
+
+    async fn verify_indexed(&self, book_id: u32, base: &str) -> Result<(), String> {
+        let url = format!("{}/index/book/{}/verify", base, book_id);
+        let response = self.indexing_post(&url).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("indexing service responded with {}", response.status()));
+        }
+        let verification: IndexVerification = response.json().await.map_err(|e| e.to_string())?;
+        match verification.integrity.as_str() {
+            "corrupt" => Err("the index has no metadata or words for the book".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn search_title(&self, book_id: u32, base: &str) -> Result<String, String> {
+        let url = format!("{}/search/book/{}", base, book_id);
+        let response = self.get(&url).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("search service responded with {}", response.status()));
+        }
+        let book: SearchBook = response.json().await.map_err(|e| e.to_string())?;
+        Ok(book.title)
+    }
+
+    async fn search_for(&self, title: &str, base: &str) -> Result<SearchPage, String> {
+        let url = format!("{}/search", base);
+        let limit = SEARCH_PAGE.to_string();
+        let query = [("q", title), ("limit", &limit), ("suggest", "false"), ("synonyms", "false")];
+        let response = self.get(&url).query(&query).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("search service responded with {}", response.status()));
+        }
+        response.json().await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, Query};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    /// Answers like the three services for five books: 1 passes, 2 isn't
+    /// ingested, 3 isn't indexed, 4 isn't found by title and 5 is crowded
+    /// out by another book.
+    async fn mock_services() -> String {
+        let titles = |book_id: u32| match book_id {
+            4 => "Typee",
+            5 => "Omoo",
+            _ => "Moby Dick",
+        };
+        let app = Router::new()
+            .route(
+                "/ingest/status/:book_id",
+                get(|Path(book_id): Path<u32>| async move {
+                    let status = if book_id == 2 { "not_found" } else { "available" };
+                    Json(json!({ "book_id": book_id, "status": status }))
+                }),
+            )
+            .route(
+                "/index/book/:book_id/verify",
+                post(|Path(book_id): Path<u32>| async move {
+                    let integrity = if book_id == 3 { "corrupt" } else { "ok" };
+                    Json(json!({ "book_id": book_id, "integrity": integrity }))
+                }),
+            )
+            .route(
+                "/search/book/:book_id",
+                get(move |Path(book_id): Path<u32>| async move { Json(json!({ "book_id": book_id, "title": titles(book_id) })) }),
+            )
+            .route(
+                "/search",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    let ids: &[u32] = match params["q"].as_str() {
+                        "Moby Dick" => &[1, 2, 3],
+                        "Omoo" => &[9],
+                        _ => &[],
+                    };
+                    let results: Vec<Value> = ids.iter().map(|id| json!({ "book_id": id })).collect();
+                    Json(json!({ "total_count": ids.len(), "results": results }))
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn reports_the_first_failing_step_of_each_book() {
+        let url = mock_services().await;
+        let urls = ServiceUrls {
+            ingestion: &url,
+            indexing: &url,
+            search: &url,
+        };
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1)).unwrap();
+
+        let report = control.verify_pipeline_at(&[1, 2, 3, 4, 5], &urls).await;
+
+        assert_eq!(report.checked, 5);
+        assert_eq!(report.passed, 1);
+        let steps: Vec<_> = report.failures.iter().map(|f| (f.book_id, f.step.as_str())).collect();
+        assert_eq!(
+            steps,
+            [(2, "ingestion"), (3, "indexing"), (4, "search_count"), (5, "search_title")]
+        );
+        assert_eq!(report.failures[0].reason, "status is \"not_found\", not \"available\"");
+    }
+
+    #[tokio::test]
+    async fn unreachable_services_fail_the_first_step() {
+        let control = ControlModule::with_timeouts(Duration::from_secs(5), Duration::from_secs(1)).unwrap();
+        let urls = ServiceUrls {
+            ingestion: "http://127.0.0.1:1",
+            indexing: "http://127.0.0.1:1",
+            search: "http://127.0.0.1:1",
+        };
+
+        let report = control.verify_pipeline_at(&[1342], &urls).await;
+
+        assert_eq!(report.passed, 0);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].step, "ingestion");
+    }
+}
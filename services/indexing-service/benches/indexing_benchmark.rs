@@ -1,44 +1,17 @@
 //! Indexing Service - Benchmarks
 //!
-//! Uses Criterion to measure performance of:
-//! - Text tokenization
+//! Uses Criterion to measure performance of the real indexing code:
+//! - Text tokenization (`utils::text::tokenize_text`)
 //! - Metadata extraction from book headers
 //! - Combined metadata + tokenization workflow
+//! - The full `process_book` path against the in-memory backend, with and
+//!   without co-occurrence indexing (`ENABLE_COOCCURRENCE`)
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use regex::Regex;
-use std::collections::HashSet;
-
-fn tokenize_text(text: &str) -> HashSet<String> {
-    let re = Regex::new(r"\b[a-zA-Z]+\b").unwrap();
-    re.find_iter(&text.to_lowercase())
-        .map(|m| m.as_str().to_string())
-        .filter(|word| word.len() > 2)
-        .collect()
-}
-
-fn extract_metadata_from_header(header_content: &str) -> (String, String, String) {
-    let title_re = Regex::new(r"(?i)title:\s*(.+)").unwrap();
-    let author_re = Regex::new(r"(?i)author:\s*(.+)").unwrap();
-    let lang_re = Regex::new(r"(?i)language:\s*(.+)").unwrap();
-
-    let title = title_re.captures(header_content)
-        .and_then(|cap| cap.get(1))
-        .map(|m| m.as_str().trim().to_string())
-        .unwrap_or_default();
-
-    let author = author_re.captures(header_content)
-        .and_then(|cap| cap.get(1))
-        .map(|m| m.as_str().trim().to_string())
-        .unwrap_or_default();
-
-    let language = lang_re.captures(header_content)
-        .and_then(|cap| cap.get(1))
-        .map(|m| m.as_str().trim().to_string())
-        .unwrap_or_else(|| "en".to_string());
-
-    (title, author, language)
-}
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use indexing_service::models::storage::{Backend, MemoryBackend};
+use indexing_service::services::indexing::{extract_metadata_from_header, process_book_in};
+use indexing_service::utils::text::tokenize_text;
+use std::fs;
 
 fn benchmark_tokenize_text(c: &mut Criterion) {
     let sample_text = "This is a sample text for benchmarking tokenization performance. It contains various words that should be processed efficiently.";
@@ -65,7 +38,7 @@ Release Date: August 26, 2006 [EBook #1342]
 "#;
 
     c.bench_function("extract_metadata", |b| {
-        b.iter(|| extract_metadata_from_header(black_box(sample_header)))
+        b.iter(|| extract_metadata_from_header(black_box(sample_header), 1342))
     });
 }
 
@@ -79,12 +52,76 @@ Language: English
 
     c.bench_function("full_processing", |b| {
         b.iter(|| {
-            let _metadata = extract_metadata_from_header(black_box(sample_header));
+            let _metadata = extract_metadata_from_header(black_box(sample_header), 1);
             let _words = tokenize_text(black_box(&sample_body));
         })
     });
 }
 
+fn benchmark_process_book(c: &mut Criterion) {
+    let datalake = tempfile::tempdir().unwrap();
+    let dir = datalake.path().join("20240101").join("00");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("header_1.txt"),
+        "Title: Test Book\nAuthor: Test Author\nLanguage: English\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("body_1.txt"),
+        "CHAPTER I\nThe whale swam through the grey northern sea.\n".repeat(2_000),
+    )
+    .unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let backend = Backend::Memory(MemoryBackend::new());
+
+    c.bench_function("process_book_memory_backend", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(process_book_in(datalake.path(), black_box(1), &backend))
+                .unwrap()
+        })
+    });
+}
+
+/// Indexes the same book with co-occurrence counting off and on. The flag
+/// is read per book, so flipping the variable between runs is enough.
+fn benchmark_process_book_cooccurrence(c: &mut Criterion) {
+    let datalake = tempfile::tempdir().unwrap();
+    let dir = datalake.path().join("20240101").join("00");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("header_1.txt"),
+        "Title: Test Book\nAuthor: Test Author\nLanguage: English\n",
+    )
+    .unwrap();
+    // 20,000 words drawn from a 2,000-word vocabulary ("wab", "wac", ...)
+    let word = |n: usize| -> String {
+        let letter = |d: usize| (b'a' + d as u8) as char;
+        format!("w{}{}{}", letter(n / 676 % 26), letter(n / 26 % 26), letter(n % 26))
+    };
+    let body: String = (0..20_000).map(|i| word(i * 7_919 % 2_000) + " ").collect();
+    fs::write(dir.join("body_1.txt"), body).unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let backend = Backend::Memory(MemoryBackend::new());
+    let mut group = c.benchmark_group("process_book_cooccurrence");
+
+    for enabled in [false, true] {
+        std::env::set_var("ENABLE_COOCCURRENCE", enabled.to_string());
+        group.bench_function(if enabled { "on" } else { "off" }, |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(process_book_in(datalake.path(), black_box(1), &backend))
+                    .unwrap()
+            })
+        });
+    }
+    std::env::remove_var("ENABLE_COOCCURRENCE");
+    group.finish();
+}
+
 fn custom_criterion() -> Criterion {
     Criterion::default()
         .sample_size(100)
@@ -94,6 +131,6 @@ fn custom_criterion() -> Criterion {
 criterion_group! {
     name = benches;
     config = custom_criterion();
-    targets = benchmark_tokenize_text, benchmark_tokenize_text_large, benchmark_extract_metadata, benchmark_full_processing
+    targets = benchmark_tokenize_text, benchmark_tokenize_text_large, benchmark_extract_metadata, benchmark_full_processing, benchmark_process_book, benchmark_process_book_cooccurrence
 }
-criterion_main!(benches);
\ No newline at end of file
+criterion_main!(benches);
@@ -0,0 +1,187 @@
+//! Indexing Service - Pipeline Benchmarks
+//!
+//! Measures whole books going through the indexing pipeline rather than
+//! single functions:
+//! - `process_book`: one book read from a datalake on disk, tokenized and
+//!   written to the in-memory backend, for a small (~130 KB), medium
+//!   (~440 KB) and large (~3 MB) book, reported as bytes per second
+//! - `rebuild_index`: `rebuild_from_datalake` over 10, 50 and 100 books into
+//!   an empty in-memory backend, reported as books per second
+//!
+//! The books are generated: Gutenberg can't be reached from every machine
+//! the benchmarks run on, and generated text keeps runs comparable. Set
+//! `PIPELINE_BENCH_BOOKS` to a directory holding `pg1342.txt`, `pg84.txt`
+//! and `pg2600.txt` (Pride and Prejudice, Frankenstein, War and Peace, as
+//! downloaded from gutenberg.org) to benchmark `process_book` on those
+//! instead. Chapter, sentence and co-occurrence indexing stay off, as they
+//! are by default.
+//!
+//! Results are kept in `BENCHMARKS.md` at the repository root.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use indexing_service::models::storage::{Backend, MemoryBackend};
+use indexing_service::services::indexing::{process_book_in, rebuild_from_datalake};
+use indexing_service::services::shutdown::Shutdown;
+use ingestion_service::utils::file::header_body_split;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Size of each book of the `rebuild_index` datalakes.
+const REBUILD_BOOK_BYTES: usize = 32 * 1024;
+
+/// Words of the generated books, drawn with a skew towards the first ones,
+/// so a book repeats common words and has a long tail of rare ones.
+const VOCABULARY_SIZE: usize = 12_000;
+
+/// A small deterministic generator, so every run indexes the same text.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 33
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        self.next() as f64 / (1u64 << 31) as f64
+    }
+}
+
+fn vocabulary() -> Vec<String> {
+    const SYLLABLES: &[&str] = &[
+        "an", "be", "cor", "den", "el", "fra", "gar", "hom", "in", "jus", "ken", "lor", "mar", "nes", "or",
+        "pra", "quen", "ros", "sil", "ter", "un", "ver", "wil", "yon",
+    ];
+    let mut rng = Lcg(7);
+    (0..VOCABULARY_SIZE)
+        .map(|_| (0..2 + rng.below(3)).map(|_| SYLLABLES[rng.below(SYLLABLES.len())]).collect())
+        .collect()
+}
+
+/// Roughly `bytes` of prose in chapters of wrapped lines, seeded by `seed`.
+fn generate_body(vocabulary: &[String], bytes: usize, seed: u64) -> String {
+    let mut rng = Lcg(seed);
+    let mut body = String::with_capacity(bytes + 1024);
+    let mut chapter = 0;
+    let mut line_len = 0;
+
+    while body.len() < bytes {
+        if body.len() >= chapter * 40_000 {
+            chapter += 1;
+            body.push_str(&format!("\n\nCHAPTER {}\n\n", chapter));
+            line_len = 0;
+        }
+        let sentence_len = 6 + rng.below(20);
+        for i in 0..sentence_len {
+            let rank = (VOCABULARY_SIZE as f64 * rng.unit().powi(3)) as usize;
+            let mut word = vocabulary[rank.min(VOCABULARY_SIZE - 1)].clone();
+            if i == 0 {
+                word[..1].make_ascii_uppercase();
+            }
+            if i + 1 == sentence_len {
+                word.push('.');
+            } else if rng.below(8) == 0 {
+                word.push(',');
+            }
+            if line_len + word.len() > 70 {
+                body.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                body.push(' ');
+                line_len += 1;
+            }
+            line_len += word.len();
+            body.push_str(&word);
+        }
+    }
+    body
+}
+
+/// Writes a book's header and body into the datalake rooted at `datalake`.
+fn store_book(datalake: &Path, book_id: u32, header: &str, body: &str) {
+    let dir = datalake.join("20240101").join("00");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(format!("header_{}.txt", book_id)), header).unwrap();
+    fs::write(dir.join(format!("body_{}.txt", book_id)), body).unwrap();
+}
+
+fn generated_header(book_id: u32) -> String {
+    format!(
+        "Title: Generated Book {}\nAuthor: Benchmark Author\nLanguage: English\nRelease date: January 1, 2024 [eBook #{}]\n",
+        book_id, book_id
+    )
+}
+
+/// A real Gutenberg download under `PIPELINE_BENCH_BOOKS`, split as the
+/// ingestion service stores it.
+fn downloaded_book(book_id: u32) -> Option<(String, String)> {
+    let dir = PathBuf::from(std::env::var_os("PIPELINE_BENCH_BOOKS")?);
+    let text = fs::read_to_string(dir.join(format!("pg{}.txt", book_id))).ok()?;
+    Some(header_body_split(&text))
+}
+
+fn benchmark_process_book(c: &mut Criterion) {
+    let vocabulary = vocabulary();
+    let datalake = tempfile::tempdir().unwrap();
+    let books = [("small", 1342, 130 * 1024), ("medium", 84, 440 * 1024), ("large", 2600, 3 * 1024 * 1024)];
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let backend = Backend::Memory(MemoryBackend::new());
+    let mut group = c.benchmark_group("process_book");
+    group.sample_size(20);
+
+    for (size, book_id, bytes) in books {
+        let (header, body) = downloaded_book(book_id)
+            .unwrap_or_else(|| (generated_header(book_id), generate_body(&vocabulary, bytes, book_id as u64)));
+        store_book(datalake.path(), book_id, &header, &body);
+
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        group.bench_function(size, |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(process_book_in(datalake.path(), black_box(book_id), &backend))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_rebuild_index(c: &mut Criterion) {
+    let vocabulary = vocabulary();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let shutdown = Shutdown::new();
+    let mut group = c.benchmark_group("rebuild_index");
+    group.sample_size(10);
+
+    for book_count in [10u32, 50, 100] {
+        let datalake = tempfile::tempdir().unwrap();
+        for book_id in 1..=book_count {
+            let body = generate_body(&vocabulary, REBUILD_BOOK_BYTES, book_id as u64);
+            store_book(datalake.path(), book_id, &generated_header(book_id), &body);
+        }
+
+        group.throughput(Throughput::Elements(book_count as u64));
+        group.bench_function(book_count.to_string(), |b| {
+            b.iter_batched(
+                || Backend::Memory(MemoryBackend::new()),
+                |backend| {
+                    let outcome = runtime
+                        .block_on(rebuild_from_datalake(datalake.path(), &backend, false, &shutdown))
+                        .unwrap();
+                    assert_eq!(outcome.indexed, book_count as usize);
+                    backend
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_process_book, benchmark_rebuild_index);
+criterion_main!(benches);
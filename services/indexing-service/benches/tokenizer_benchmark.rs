@@ -0,0 +1,81 @@
+//! Indexing Service - Tokenizer Benchmarks
+//!
+//! Compares the ways of splitting a book body into index words:
+//! - `regex_per_call`: compiles the regex on every call (the original code,
+//!   kept here as the baseline)
+//! - `tokenize_text`: the regex reference, compiled once
+//! - `tokenize_text_fast`: `memchr` whitespace split plus a letter-run scan,
+//!   which the indexing pipeline uses for book bodies
+//! - `tokenize_text_parallel`: the fast tokenizer over line-aligned chunks on
+//!   the rayon pool, used for bodies above the parallel threshold
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use indexing_service::utils::text::{tokenize_text, tokenize_text_fast, tokenize_text_parallel, MIN_WORD_LEN};
+use regex::Regex;
+use std::collections::HashSet;
+
+type Tokenizer = fn(&str) -> HashSet<String>;
+
+fn regex_per_call(text: &str) -> HashSet<String> {
+    let re = Regex::new(r"\b[a-zA-Z]+\b").unwrap();
+    re.find_iter(&text.to_lowercase())
+        .map(|m| m.as_str().to_string())
+        .filter(|word| word.len() >= MIN_WORD_LEN)
+        .collect()
+}
+
+fn sample_body() -> String {
+    "It was the best of times, it was the worst of times; it was the age of wisdom, \
+     it was the age of foolishness. “Don’t,” said Mr. Lorry—and the café closed at 10pm.\n"
+        .repeat(2_000)
+}
+
+fn benchmark_tokenizers(c: &mut Criterion) {
+    let body = sample_body();
+    let reference = regex_per_call(&body);
+    assert_eq!(tokenize_text(&body), reference);
+    assert_eq!(tokenize_text_fast(&body), reference);
+
+    let mut group = c.benchmark_group("tokenize_body");
+    group.throughput(criterion::Throughput::Bytes(body.len() as u64));
+    let variants: [(&str, Tokenizer); 3] = [
+        ("regex_per_call", regex_per_call),
+        ("tokenize_text", tokenize_text),
+        ("tokenize_text_fast", tokenize_text_fast),
+    ];
+    for (name, tokenize) in variants {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &body, |b, body| {
+            b.iter(|| tokenize(black_box(body)))
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_tokenizers_small(c: &mut Criterion) {
+    let sentence = "This is a sample text for benchmarking tokenization performance.";
+
+    let mut group = c.benchmark_group("tokenize_sentence");
+    group.bench_function("regex_per_call", |b| b.iter(|| regex_per_call(black_box(sentence))));
+    group.bench_function("tokenize_text", |b| b.iter(|| tokenize_text(black_box(sentence))));
+    group.bench_function("tokenize_text_fast", |b| b.iter(|| tokenize_text_fast(black_box(sentence))));
+    group.finish();
+}
+
+/// Sequential vs. parallel on a ~3 MB body, about the size of War and Peace.
+fn benchmark_tokenizers_large(c: &mut Criterion) {
+    let body = sample_body().repeat(9);
+    assert_eq!(tokenize_text_parallel(&body, 8), tokenize_text_fast(&body));
+
+    let mut group = c.benchmark_group("tokenize_large");
+    group.throughput(criterion::Throughput::Bytes(body.len() as u64));
+    group.bench_function("tokenize_text_fast", |b| b.iter(|| tokenize_text_fast(black_box(&body))));
+    for chunks in [rayon::current_num_threads(), 8] {
+        group.bench_with_input(BenchmarkId::new("tokenize_text_parallel", chunks), &chunks, |b, &chunks| {
+            b.iter(|| tokenize_text_parallel(black_box(&body), chunks))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_tokenizers, benchmark_tokenizers_small, benchmark_tokenizers_large);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+//! Generates the gRPC server and client stubs from `proto/indexing.proto`.
+//! Uses the vendored `protoc`, so building doesn't need one installed.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::compile_protos("proto/indexing.proto")?;
+    Ok(())
+}
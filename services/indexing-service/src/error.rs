@@ -0,0 +1,183 @@
+//! Error Responses
+//!
+//! Failed requests answer with an [`ErrorResponse`]: a machine-readable
+//! `error_code` clients can branch on, a human-readable message and, where
+//! one applies, the book concerned. Handlers return [`AppError`], each
+//! variant of which maps to one status code and `error_code`.
+//!
+//! The response shape and the middleware adding request IDs to it are shared
+//! by every service in `common-http`.
+
+use crate::models::storage::StorageError;
+use crate::services::backpressure::IndexingBusy;
+use crate::services::compaction::CompactionRunning;
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use common_http::ErrorResponse;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Book {0} not found")]
+    BookNotFound(u32),
+    #[error("'{word}' is not indexed ({reason})")]
+    WordNotIndexed { word: String, reason: String },
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("{0}")]
+    Unauthorized(&'static str),
+    #[error("Too many concurrent indexing operations")]
+    RateLimited { retry_after: Duration },
+    #[error("A compaction is already running; poll /index/compact/status")]
+    CompactionRunning,
+    #[error("Indexing service is shutting down")]
+    ShuttingDown,
+    #[error("Storage backend unavailable: {0}")]
+    BackendUnavailable(StorageError),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BookNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::WordNotIndexed { .. } | AppError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::CompactionRunning => StatusCode::CONFLICT,
+            AppError::ShuttingDown | AppError::BackendUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::BookNotFound(_) => "BOOK_NOT_FOUND",
+            AppError::WordNotIndexed { .. } => "WORD_NOT_INDEXED",
+            AppError::InvalidRequest(_) => "INVALID_REQUEST",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::RateLimited { .. } => "RATE_LIMITED",
+            AppError::CompactionRunning => "COMPACTION_RUNNING",
+            AppError::ShuttingDown => "SHUTTING_DOWN",
+            AppError::BackendUnavailable(_) => "BACKEND_UNAVAILABLE",
+            AppError::Timeout => "GATEWAY_TIMEOUT",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    fn book_id(&self) -> Option<u32> {
+        match self {
+            AppError::BookNotFound(book_id) => Some(*book_id),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let mut response = ErrorResponse {
+            book_id: self.book_id(),
+            ..ErrorResponse::new(self.error_code(), self.to_string())
+        }
+        .with_status(self.status());
+
+        let headers = response.headers_mut();
+        match self {
+            AppError::RateLimited { retry_after } => {
+                headers.insert(header::RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
+            }
+            AppError::Unauthorized(_) => {
+                headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            _ => {}
+        }
+        response
+    }
+}
+
+impl From<IndexingBusy> for AppError {
+    fn from(busy: IndexingBusy) -> Self {
+        AppError::RateLimited {
+            retry_after: busy.retry_after(),
+        }
+    }
+}
+
+impl From<CompactionRunning> for AppError {
+    fn from(_: CompactionRunning) -> Self {
+        AppError::CompactionRunning
+    }
+}
+
+/// Only failures to reach the backend are `503 BACKEND_UNAVAILABLE`, as
+/// retrying may succeed once it is back. Errors it answers with, such as a
+/// failed query, and stored data that doesn't deserialize are faults on
+/// our side and answer `500 INTERNAL_ERROR`.
+impl From<StorageError> for AppError {
+    fn from(error: StorageError) -> Self {
+        let unavailable = match &error {
+            StorageError::Redis(e) => {
+                e.is_io_error()
+                    || e.is_connection_refusal()
+                    || e.is_connection_dropped()
+                    || e.is_timeout()
+                    || e.is_cluster_error()
+                    || e.kind() == redis::ErrorKind::BusyLoadingError
+            }
+            StorageError::Postgres(e) => match e {
+                sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::WorkerCrashed => true,
+                // Connection exceptions (08) and the server shutting down
+                // or starting up (57P)
+                sqlx::Error::Database(db) => db
+                    .code()
+                    .is_some_and(|code| code.starts_with("08") || code.starts_with("57P")),
+                _ => false,
+            },
+            StorageError::Serialization(_) => false,
+            StorageError::Connection(_) => true,
+        };
+        if unavailable {
+            AppError::BackendUnavailable(error)
+        } else {
+            AppError::Internal(format!("Storage backend error: {}", error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unreachable_backends_are_unavailable() {
+        let status = |error: StorageError| AppError::from(error).status();
+
+        assert_eq!(
+            status(StorageError::Connection("refused".to_string())),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(redis::RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset)).into()),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(status(sqlx::Error::PoolTimedOut.into()), StatusCode::SERVICE_UNAVAILABLE);
+
+        assert_eq!(
+            status(redis::RedisError::from((redis::ErrorKind::TypeError, "WRONGTYPE")).into()),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(status(sqlx::Error::RowNotFound.into()), StatusCode::INTERNAL_SERVER_ERROR);
+        let corrupt = serde_json::from_str::<u32>("not a number").unwrap_err();
+        assert_eq!(status(corrupt.into()), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,69 @@
+//! Indexing Service Library
+//!
+//! The modules behind the `indexing-service` binary, exposed as a library so
+//! benchmarks and handler tests run the real code. `main.rs` only reads the
+//! configuration, connects the backend and serves [`app`].
+
+pub mod error;
+pub mod models;
+pub mod routes;
+pub mod services;
+pub mod state;
+pub mod utils;
+
+use axum::{
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
+use routes::{
+    health::{health_check, metrics_endpoint, readiness_check, startup_check},
+    index::{
+        delete_book, export_index_dump, get_book_chapters, get_compaction_status, get_cooccurrences,
+        get_index_status,
+        get_word_books, import_index_dump, index_book, list_indexed_books, list_stale_books, rebuild_index,
+        refresh_stale, start_compaction, verify_book,
+    },
+};
+use common_http::attach_request_id;
+use services::auth::require_bearer_token;
+use services::timeout::with_timeout;
+use state::AppState;
+use tower_http::cors::CorsLayer;
+use tower_http::trace::TraceLayer;
+use common_telemetry::trace_context;
+
+/// Builds the service's router. `POST` and `DELETE` routes require the bearer token when
+/// one is configured in `state.auth`. Indexing and rebuilding are cut off after
+/// `state.timeouts`. Every error is answered with an `ErrorResponse`.
+pub fn app(state: AppState) -> Router {
+    let timeouts = state.timeouts;
+    let protected = Router::new()
+        .route("/index/update/:book_id", with_timeout(post(index_book), timeouts.index))
+        .route("/index/rebuild", with_timeout(post(rebuild_index), timeouts.rebuild))
+        .route("/index/book/:book_id/verify", post(verify_book))
+        .route("/index/import", post(import_index_dump))
+        .route("/index/refresh-stale", post(refresh_stale))
+        .route("/index/book/:book_id", delete(delete_book))
+        .route("/index/compact", post(start_compaction))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_bearer_token));
+
+    Router::new()
+        .route("/status", get(health_check))
+        .route("/ready", get(readiness_check))
+        .route("/startup", get(startup_check))
+        .route("/metrics", get(metrics_endpoint))
+        .route("/index/status", get(get_index_status))
+        .route("/index/book/:book_id/chapters", get(get_book_chapters))
+        .route("/index/words/:word", get(get_word_books))
+        .route("/index/cooccurrence", get(get_cooccurrences))
+        .route("/index/export", get(export_index_dump))
+        .route("/index/books", get(list_indexed_books))
+        .route("/index/stale", get(list_stale_books))
+        .route("/index/compact/status", get(get_compaction_status))
+        .merge(protected)
+        .layer(middleware::from_fn(attach_request_id))
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http().make_span_with(trace_context::make_span))
+        .with_state(state)
+}
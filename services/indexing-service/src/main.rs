@@ -7,39 +7,61 @@
 //! ## Responsibilities
 //! - Index new books on demand  
 //! - Rebuild the entire index from the datalake  
-//! - Provide index statistics and health status  
+//! - Provide index statistics, health status and Prometheus metrics  
+//! - Export and import the index for backups and backend migrations  
+//! - Serve indexing and status over gRPC for internal callers  
 //! - Support multiple storage backends (Redis or PostgreSQL)
 //!
+//! An unreachable Redis backend doesn't stop the service from starting: it
+//! serves with `/status` degraded and `/startup` failing until Redis answers,
+//! so orchestrators should restart it on the startup probe rather than on
+//! exit. PostgreSQL is connected to up front and still exits on failure.
+//!
 //! ## Environment Variables
-//! - `BACKEND_TYPE`: Selects the storage backend (`redis` or `postgres`)  
-//! - `REDIS_URL`: Redis connection URL (default: `redis://redis:6379`)  
+//! - `BACKEND_TYPE`: Selects the storage backend (`redis`, `postgres` or `memory`)  
+//! - `REDIS_URL`: Redis URL, `rediss://` for TLS, or a comma-separated list of cluster nodes (default: `redis://redis:6379`)  
+//! - `REDIS_MODE`, `REDIS_USERNAME`, `REDIS_PASSWORD`, `REDIS_TLS_INSECURE`: see the `common-redis` crate  
+//! - `SHARD_URLS`: Comma-separated Redis URLs to shard the index over instead of `REDIS_URL`; see `models::storage::sharded`  
 //! - `DATABASE_URL`: PostgreSQL connection string  
-//! - `PORT`: Service port (default: `7002`)
+//! - `PORT`: Service port (default: `7002`)  
+//! - `GRPC_PORT`: Port of the gRPC API from `proto/indexing.proto` (default: `7012`)  
+//! - `ENABLE_CHAPTER_INDEX`: Also store `(book_id, chapter_no)` postings (default: `false`)  
+//! - `ENABLE_SENTENCE_INDEX`: Also store `(book_id, sentence_id)` postings for `GET /search/passages` (default: `false`)  
+//! - `ENABLE_COOCCURRENCE`: Also store counts of words found within 5 words of each other (default: `false`)  
+//! - `MAX_CONCURRENT_INDEXING`: Books indexed at the same time (default: `4`)  
+//! - `INDEXING_MAX_WAIT_SECS`: Wait for a free slot before answering `429` (default: `30`)
+//! - `INDEXING_AUTH_TOKEN`: When set, `POST` and `DELETE` routes require `Authorization: Bearer <token>`  
+//! - `TOKENIZE_PARALLEL_THRESHOLD`: Body size in bytes above which tokenization runs on rayon (default: 1 MiB)
+//! - `FOLD_DIACRITICS`: Index accented letters folded to their base letters (`café` → `cafe`); changing it needs a rebuild (default: `false`)  
+//! - `BOOK_FILENAME_PATTERN`: Comma-separated body file layouts a rebuild lists books from, `{id}` for the book ID; see `utils::file`  
+//! - `SHUTDOWN_GRACE_SECS`: Time in-flight indexing gets to finish after SIGTERM (default: `30`)
+//! - `INDEX_TIMEOUT_SECS`, `REBUILD_TIMEOUT_SECS`: see `services::timeout`
+//! - `RUST_LOG`, `LOG_LEVEL_SERVICE`, `LOG_LEVEL_TOWER`, `LOG_LEVEL_REQWEST`, `LOG_FORMAT`: see the `common-telemetry` crate  
 
-use axum::{
-    routing::{get, post},
-    Router,
-};
-use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
-use tracing::{error, info};
-
-mod models;
-mod routes;
-mod services;
-mod utils;
-
-use models::storage::{Backend, PostgresBackend, RedisBackend, StorageBackend};
-use routes::{
-    health::health_check,
-    index::{get_index_status, index_book, rebuild_index},
+use common_redis::RedisConfig;
+use common_telemetry::logging::{init_tracing, LogOutput};
+use indexing_service::app;
+use indexing_service::models::storage::{
+    Backend, MemoryBackend, PostgresBackend, RedisBackend, ShardedBackend, StorageBackend,
 };
+use indexing_service::services::auth::AuthToken;
+use indexing_service::services::backpressure::IndexingLimiter;
+use indexing_service::services::compaction::Compactor;
+use indexing_service::services::grpc;
+use indexing_service::services::shutdown::{listen_for_signals, Shutdown};
+use indexing_service::services::startup::StartupProbe;
+use indexing_service::services::timeout::RequestTimeouts;
+use indexing_service::state::AppState;
+use indexing_service::utils::file::Datalake;
+use indexing_service::utils::text::TokenizerConfig;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter("indexing_service=info,tower_http=info")
-        .init();
+    init_tracing("indexing-service", LogOutput::Stdout);
 
     let backend_type = std::env::var("BACKEND_TYPE").unwrap_or_else(|_| "redis".to_string());
     let backend = match backend_type.to_lowercase().as_str() {
@@ -53,32 +75,86 @@ async fn main() {
 
             Backend::Postgres(postgres_backend)
         }
-        "redis" | _ => {
-            let redis_url = std::env::var("REDIS_URL")
-                .unwrap_or_else(|_| "redis://redis:6379".to_string());
+        "memory" => {
+            info!("Using in-memory backend (nothing is persisted)");
+            Backend::Memory(MemoryBackend::new())
+        }
+        _ => match ShardedBackend::from_env() {
+            Some(sharded) => {
+                let sharded = sharded.unwrap_or_else(|e| {
+                    error!("Invalid shard configuration: {}", e);
+                    std::process::exit(1);
+                });
 
-            info!("Using Redis backend");
-            let redis_backend = RedisBackend::new(&redis_url)
-                .expect("Failed to connect to Redis");
+                info!("Using Redis backend sharded over {} instance(s)", sharded.shard_count());
+                Backend::Sharded(sharded)
+            }
+            None => {
+                let config = RedisConfig::from_env();
 
-            Backend::Redis(redis_backend)
-        }
+                info!("Using Redis backend ({:?}, {} node(s))", config.mode, config.nodes.len());
+                let redis_backend = RedisBackend::from_config(&config).unwrap_or_else(|e| {
+                    error!("Invalid Redis configuration: {}", e);
+                    std::process::exit(1);
+                });
+
+                Backend::Redis(redis_backend)
+            }
+        },
     };
 
-    if let Err(e) = backend.test_connection().await {
-        error!("Failed to connect to storage backend: {}", e);
-        std::process::exit(1);
+    // Keep serving even if the backend is down: `/status` reports it, and
+    // requests succeed again as soon as the backend is reachable.
+    let startup = StartupProbe::new();
+    match backend.test_connection().await {
+        Ok(()) => {
+            info!("Storage backend connection successful");
+            startup.complete();
+            let folding = TokenizerConfig::configured().fold_diacritics;
+            if let Ok(Some(indexed)) = backend.get_diacritic_folding().await {
+                if indexed != folding {
+                    warn!(
+                        "FOLD_DIACRITICS is {} but the index was built with it {}; POST /index/rebuild to migrate",
+                        folding, indexed
+                    );
+                }
+            }
+        }
+        Err(e) => warn!("Storage backend unavailable at startup: {}", e),
+    }
+
+    let shutdown = Shutdown::new();
+    tokio::spawn(listen_for_signals(shutdown.clone()));
+
+    let auth = AuthToken::from_env();
+    if auth.is_enabled() {
+        info!("Bearer token authentication enabled for POST and DELETE routes");
     }
-    info!("Storage backend connection successful");
 
-    let app = Router::new()
-        .route("/status", get(health_check))
-        .route("/index/update/:book_id", post(index_book))
-        .route("/index/rebuild", post(rebuild_index))
-        .route("/index/status", get(get_index_status))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(backend);
+    let state = AppState {
+        backend,
+        limiter: IndexingLimiter::from_env(),
+        shutdown: shutdown.clone(),
+        auth,
+        compactor: Compactor::new(),
+        started_at: Arc::new(Instant::now()),
+        startup,
+        timeouts: RequestTimeouts::from_env(),
+        datalake: Datalake::default(),
+    };
+
+    let grpc_addr = SocketAddr::from(([0, 0, 0, 0], grpc::grpc_port()));
+    info!("Indexing gRPC server starting on {}", grpc_addr);
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            if let Err(e) = grpc::serve(grpc_addr, state).await {
+                error!("gRPC server failed: {}", e);
+            }
+        }
+    });
+
+    let app = app(state);
 
     let port = std::env::var("PORT").unwrap_or_else(|_| "7002".to_string());
     let addr = format!("0.0.0.0:{}", port);
@@ -86,5 +162,28 @@ async fn main() {
     info!("Indexing service starting on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move { shutdown.wait().await }
+    });
+
+    let grace = Duration::from_secs(
+        std::env::var("SHUTDOWN_GRACE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30),
+    );
+
+    // The server stops accepting connections as soon as the signal arrives,
+    // then waits for in-flight requests. Past the grace period we exit anyway;
+    // books still being written stay flagged incomplete in the backend.
+    tokio::select! {
+        result = server => result.unwrap(),
+        _ = async {
+            shutdown.wait().await;
+            info!("Shutdown requested, waiting up to {:?} for in-flight indexing", grace);
+            tokio::time::sleep(grace).await;
+        } => warn!("Grace period elapsed with indexing still in flight, exiting"),
+    }
+    info!("Indexing service stopped");
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+//! # Index Export Format
+//!
+//! Records written by `GET /index/export` and read by `POST /index/import`.
+//! The stream is newline-delimited JSON: every book's metadata first, then
+//! one posting list per word.
+//!
+//! ```text
+//! {"type":"book","metadata":{"book_id":1342,"title":"Pride and Prejudice",...}}
+//! {"type":"posting","word":"elizabeth","book_ids":[1342]}
+//! {"type":"posting","word":"pride","field":"title","book_ids":[1342]}
+//! ```
+//!
+//! Postings without a `field` are unscoped (body) postings.
+
+use crate::models::storage::{BookMetadata, IndexField};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum IndexRecord {
+    Book { metadata: BookMetadata },
+    Posting {
+        word: String,
+        #[serde(default, skip_serializing_if = "IndexField::is_body")]
+        field: IndexField,
+        book_ids: Vec<u32>,
+    },
+}
@@ -1,2 +1,3 @@
+pub mod export;
 pub mod responses;
 pub mod storage;
\ No newline at end of file
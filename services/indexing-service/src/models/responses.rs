@@ -5,38 +5,270 @@
 //!
 //! ## Included Responses
 //! - `HealthResponse` — Reports service health and uptime.
-//! - `IndexResponse` — Returned after indexing a single book.
+//! - `ProbeResponse` — Result of the readiness or startup probe.
+//! - `IndexResponse` — Returned after indexing a single book, optionally with `StageTimings`.
 //! - `RebuildResponse` — Summarizes results of a full index rebuild.
 //! - `IndexStatusResponse` — Provides current indexing statistics.
+//! - `ChapterListResponse` — Lists the chapters detected in a book.
+//! - `VerificationResult` — Reports whether a book's words are all indexed.
+//! - `ImportResponse` — Summarizes an index import.
+//! - `WordBooksResponse` — Lists the books the index maps a word to.
+//! - `IndexedBooksResponse` — Lists the indexed books.
+//! - `StaleBooksResponse` — Lists indexed books whose datalake body changed.
+//! - `RefreshStaleResponse` — Summarizes re-indexing the stale books.
+//! - `DeleteBookResponse` — Confirms a book was removed from the index.
+//! - `CompactionStatus` — Progress and result of the background compaction job.
+//! - `ErrorResponse` — Machine-readable error code plus a human-readable message, the book and the request ID (shared, from `common-http`).
 
+pub use common_http::ErrorResponse;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct HealthResponse {
     pub service: String,
+    /// Always `liveness`.
+    pub probe: String,
+    /// Always true: a process that answers is alive.
+    pub ok: bool,
     pub status: String,
+    /// `connected` or `unavailable`.
+    pub backend: String,
+    pub backend_connected: bool,
+    /// `redis`, `postgres`, `memory` or `sharded`.
+    pub backend_type: String,
+    /// Round trip of the connection test, or the time until it failed.
+    pub backend_latency_ms: f64,
+    /// Whether the datalake directory can be read.
+    pub datalake_accessible: bool,
+    pub uptime_secs: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ProbeResponse {
+    pub service: String,
+    /// `readiness` or `startup`.
+    pub probe: String,
+    pub ok: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IndexResponse {
     pub book_id: u32,
     pub status: String,
+    /// Words in the body.
+    pub word_count: usize,
+    /// Distinct body tokens stored.
+    pub unique_words: usize,
+    /// How long indexing the book took.
+    pub elapsed_ms: u64,
+    /// Per-stage breakdown, only included with `?verbose=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings_ms: Option<StageTimings>,
+}
+
+/// Milliseconds spent in each stage of indexing a single book, in pipeline order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StageTimings {
+    pub file_read: f64,
+    pub tokenization: f64,
+    pub metadata_store: f64,
+    pub postings_write: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebuildFailure {
+    pub book_id: u32,
+    pub error: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RebuildResponse {
     pub status: String,
     pub indexed_count: usize,
+    /// Books the rebuild covered: every book in the datalake, or the
+    /// distinct `book_ids` asked for.
+    #[serde(default)]
+    pub requested_count: usize,
     pub books_processed: usize,
     pub elapsed_time: String,
+    pub failures: Vec<RebuildFailure>,
+    /// Book ID the rebuild resumed after, when called with `?resume=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<u32>,
+    /// Shutdown stopped the rebuild early; resume it with `?resume=true`.
+    #[serde(default)]
+    pub interrupted: bool,
+    /// `book_ids` asked for that aren't in the datalake.
+    #[serde(default)]
+    pub skipped_not_found: Vec<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IndexStatusResponse {
     pub total_books: usize,
     pub total_words: usize,
+    /// RFC 3339 time a book was last stored, deleted or finished indexing;
+    /// empty if the backend hasn't recorded one.
     pub last_updated: String,
     pub books_indexed: usize,
+    /// Same as `last_updated`.
     pub last_update: String,
+    /// Storage the backend reports for the index, in MiB (approximate on Redis).
     pub index_size_mb: f64,
+    /// Unique words per field (`title`, `author`, `body`); `body` counts every word.
+    pub words_per_field: BTreeMap<String, usize>,
+    /// Indexing operations currently holding a concurrency slot.
+    pub indexing_in_flight: usize,
+    /// Whether the index was built folding diacritics (`FOLD_DIACRITICS`);
+    /// `null` before the first book is indexed.
+    pub diacritic_folding: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChapterInfo {
+    pub chapter_no: usize,
+    pub title: Option<String>,
+    pub word_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChapterListResponse {
+    pub book_id: u32,
+    pub chapter_count: usize,
+    pub chapters: Vec<ChapterInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexIntegrity {
+    Ok,
+    Degraded,
+    Corrupt,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub book_id: u32,
+    pub expected_words: usize,
+    pub present_in_index: usize,
+    pub missing_words: Vec<String>,
+    /// The book's last indexing run was interrupted before completing.
+    pub incomplete: bool,
+    pub integrity: IndexIntegrity,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportResponse {
+    pub status: String,
+    pub books_imported: usize,
+    pub words_imported: usize,
+    pub postings_imported: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WordBook {
+    pub book_id: u32,
+    pub title: String,
+    pub author: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WordBooksResponse {
+    pub word: String,
+    pub book_count: usize,
+    pub page: usize,
+    pub per_page: usize,
+    pub books: Vec<WordBook>,
+}
+
+/// A word seen near the requested one, with how often across all books.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CooccurrenceEntry {
+    pub word: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CooccurrenceResponse {
+    pub word: String,
+    pub cooccurrences: Vec<CooccurrenceEntry>,
+}
+
+/// Why an indexed book no longer matches the datalake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StaleReason {
+    /// The body file changed since the book was indexed.
+    Modified,
+    /// The book was indexed before fingerprints were recorded.
+    Unrecorded,
+    /// The body file is gone; the book cannot be refreshed.
+    Missing,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StaleBook {
+    pub book_id: u32,
+    pub reason: StaleReason,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedBooksResponse {
+    pub count: usize,
+    pub books: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StaleBooksResponse {
+    pub stale_count: usize,
+    pub books: Vec<StaleBook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshStaleResponse {
+    pub status: String,
+    pub refreshed: Vec<u32>,
+    /// Stale books whose body file is missing, left as they are.
+    pub skipped: Vec<u32>,
+    pub failures: Vec<RebuildFailure>,
+    pub elapsed_time: String,
+    /// Shutdown stopped the refresh early; call it again to finish.
+    #[serde(default)]
+    pub interrupted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteBookResponse {
+    pub book_id: u32,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompactionState {
+    /// No compaction has run since the service started.
+    #[default]
+    Idle,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactionStatus {
+    pub state: CompactionState,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// Deleted books whose postings are being removed.
+    pub books_purged: usize,
+    /// Vocabulary size when the job started; progress is `words_scanned / words_total`.
+    pub words_total: usize,
+    pub words_scanned: usize,
+    pub postings_removed: usize,
+    /// Words left without any posting and dropped from the vocabulary.
+    pub words_removed: usize,
+    pub estimated_bytes_reclaimed: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
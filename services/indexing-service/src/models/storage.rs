@@ -11,17 +11,26 @@
 //! - Handling connection testing and automatic schema initialization.
 //!
 //! ## Implementations
-//! - [`RedisBackend`] — lightweight in-memory storage for fast prototyping.
+//! - [`RedisBackend`] — lightweight in-memory storage, standalone or clustered, plain or TLS.
 //! - [`PostgresBackend`] — durable relational storage with SQLx and indexing.
+//! - [`MemoryBackend`] — process-local storage for tests and local experiments.
+//! - [`ShardedBackend`] — spreads words over several backends by consistent hashing.
 
 use async_trait::async_trait;
-
+use chrono::{DateTime, NaiveDateTime, Utc};
+use common_index::{IndexState, MemoryIndex};
+use common_redis::{RedisClient, RedisConfig, RedisConnection};
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Row};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
 use thiserror::Error;
-use tracing::error;
+
+pub mod sharded;
+
+pub use common_index::{BookMetadata, IndexField};
+pub use sharded::ShardedBackend;
 
 #[derive(Error, Debug)]
 pub enum StorageError {
@@ -35,15 +44,38 @@ pub enum StorageError {
     Connection(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BookMetadata {
-    pub book_id: u32,
-    pub title: String,
-    pub author: String,
-    pub language: String,
-    pub year: Option<u32>,
-    pub word_count: usize,
-    pub unique_words: usize,
+/// Identifies the version of a book's datalake body file that was indexed.
+///
+/// A re-ingest rewrites the file, changing its modification time and usually
+/// its size, so a mismatch means the indexed words may be out of date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookFingerprint {
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub modified_ns: u64,
+    pub size: u64,
+}
+
+/// What [`StorageBackend::remove_postings`] removed for one word.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RemovedPostings {
+    /// Book IDs removed from the word's body, field, chapter and sentence postings.
+    pub postings: usize,
+    /// The word has no postings left and was dropped from the vocabulary.
+    pub word_removed: bool,
+}
+
+/// Sums per-book co-occurrence counts by word and keeps the `n` largest,
+/// breaking ties alphabetically.
+pub fn top_cooccurrences(counts: impl IntoIterator<Item = (String, u64)>, n: usize) -> Vec<(String, u64)> {
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    for (word, count) in counts {
+        *totals.entry(word).or_insert(0) += count;
+    }
+
+    let mut ranked: Vec<(String, u64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(n);
+    ranked
 }
 
 /// Trait defining a unified interface for all storage backends.
@@ -53,9 +85,73 @@ pub trait StorageBackend {
     async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError>;
     async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError>;
     async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError>;
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError>;
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError>;
     async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError>;
+    /// Books containing `word` in any field, in ascending ID order.
+    async fn get_books_for_word(&self, word: &str) -> Result<Vec<u32>, StorageError> {
+        let mut book_ids: Vec<u32> = self.search_word(word).await?.into_iter().collect();
+        book_ids.sort_unstable();
+        Ok(book_ids)
+    }
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError>;
+    /// The subset of `words` whose unscoped postings include `book_id`,
+    /// checked without reading the full posting lists.
+    async fn words_with_book(&self, words: &[String], book_id: u32) -> Result<HashSet<String>, StorageError>;
+    /// The postings in `field` of each of `words`, fetched together. Words
+    /// without any are left out.
+    async fn get_postings(&self, words: &[String], field: IndexField) -> Result<HashMap<String, HashSet<u32>>, StorageError>;
+    /// Bulk variant of `add_word_to_index`; adding existing postings is a no-op.
+    async fn add_postings(&self, word: &str, book_ids: &[u32], field: IndexField) -> Result<(), StorageError>;
+    /// Pages through the words with postings in `field`, where `Body` means
+    /// the unscoped vocabulary. Pass `None` to start; a `None` cursor in the
+    /// result means the scan is complete.
+    async fn scan_words(&self, field: IndexField, cursor: Option<String>, count: usize) -> Result<(Vec<String>, Option<String>), StorageError>;
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError>;
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError>; // (book_id, chapter_no)
+    /// Records that `word` occurs in sentence `sentence_id` of the book,
+    /// numbered as `common_text::split_sentences` splits the body.
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError>;
+    async fn search_word_sentences(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError>; // (book_id, sentence_id)
+    /// Records that the two words appeared near each other `count` times in
+    /// the book, replacing any count stored for that book before.
+    async fn add_cooccurrence(&self, word_a: &str, word_b: &str, book_id: u32, count: u32) -> Result<(), StorageError>;
+    /// The `n` words seen near `word` most often, summed over all books.
+    async fn get_cooccurrences(&self, word: &str, n: usize) -> Result<Vec<(String, u64)>, StorageError>;
     async fn get_stats(&self) -> Result<(usize, usize), StorageError>; // (total_books, unique_words)
+    async fn get_field_stats(&self) -> Result<HashMap<IndexField, usize>, StorageError>; // field -> unique words
+    /// Flags a book whose postings are being written; cleared once it is fully indexed.
+    async fn set_book_incomplete(&self, book_id: u32, incomplete: bool) -> Result<(), StorageError>;
+    async fn get_incomplete_books(&self) -> Result<HashSet<u32>, StorageError>;
+    /// Last book completed by the current rebuild, `None` when no rebuild is pending.
+    async fn save_rebuild_checkpoint(&self, last_completed: Option<u32>) -> Result<(), StorageError>;
+    async fn get_rebuild_checkpoint(&self) -> Result<Option<u32>, StorageError>;
+    /// Records whether the index's words were folded (`FOLD_DIACRITICS`),
+    /// for the search service to compare its own setting against.
+    async fn set_diacritic_folding(&self, folding: bool) -> Result<(), StorageError>;
+    /// The setting last recorded by [`Self::set_diacritic_folding`].
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError>;
+    /// Records the body file fingerprint a book was indexed from.
+    async fn set_book_fingerprint(&self, book_id: u32, fingerprint: BookFingerprint) -> Result<(), StorageError>;
+    async fn get_book_fingerprints(&self) -> Result<HashMap<u32, BookFingerprint>, StorageError>;
+    /// Drops a book's metadata and marks it deleted. Its postings stay until
+    /// compaction removes them. Returns `false` if the book was not indexed.
+    async fn delete_book(&self, book_id: u32) -> Result<bool, StorageError>;
+    /// Books deleted since the last compaction. Storing a book's metadata
+    /// again takes it off this list.
+    async fn get_deleted_books(&self) -> Result<HashSet<u32>, StorageError>;
+    async fn clear_deleted_books(&self, book_ids: &[u32]) -> Result<(), StorageError>;
+    /// Removes `book_ids` from every posting of `word`, dropping the word
+    /// from the vocabulary once nothing is left.
+    async fn remove_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError>;
+    /// Like [`Self::remove_postings`], but only for those of `book_ids` still
+    /// marked deleted. The check and the removal happen together, so a book
+    /// indexed again meanwhile keeps its new postings.
+    async fn purge_deleted_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError>;
+    /// Approximate bytes the index occupies in the backend.
+    async fn get_index_size_bytes(&self) -> Result<u64, StorageError>;
+    /// When a book was last stored, deleted or finished indexing; `None` if
+    /// nothing was recorded yet.
+    async fn get_last_updated(&self) -> Result<Option<DateTime<Utc>>, StorageError>;
     async fn test_connection(&self) -> Result<(), StorageError>;
 }
 
@@ -64,6 +160,20 @@ pub trait StorageBackend {
 pub enum Backend {
     Redis(RedisBackend),
     Postgres(PostgresBackend),
+    Memory(MemoryBackend),
+    Sharded(ShardedBackend),
+}
+
+impl Backend {
+    /// The backend's name, as given in `BACKEND_TYPE`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Backend::Redis(_) => "redis",
+            Backend::Postgres(_) => "postgres",
+            Backend::Memory(_) => "memory",
+            Backend::Sharded(_) => "sharded",
+        }
+    }
 }
 
 #[async_trait]
@@ -72,6 +182,8 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.store_book_metadata(metadata).await,
             Backend::Postgres(backend) => backend.store_book_metadata(metadata).await,
+            Backend::Memory(backend) => backend.store_book_metadata(metadata).await,
+            Backend::Sharded(backend) => backend.store_book_metadata(metadata).await,
         }
     }
 
@@ -79,6 +191,8 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.get_book_metadata(book_id).await,
             Backend::Postgres(backend) => backend.get_book_metadata(book_id).await,
+            Backend::Memory(backend) => backend.get_book_metadata(book_id).await,
+            Backend::Sharded(backend) => backend.get_book_metadata(book_id).await,
         }
     }
 
@@ -86,6 +200,8 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.is_book_indexed(book_id).await,
             Backend::Postgres(backend) => backend.is_book_indexed(book_id).await,
+            Backend::Memory(backend) => backend.is_book_indexed(book_id).await,
+            Backend::Sharded(backend) => backend.is_book_indexed(book_id).await,
         }
     }
 
@@ -93,13 +209,17 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.get_indexed_books().await,
             Backend::Postgres(backend) => backend.get_indexed_books().await,
+            Backend::Memory(backend) => backend.get_indexed_books().await,
+            Backend::Sharded(backend) => backend.get_indexed_books().await,
         }
     }
 
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError> {
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
         match self {
-            Backend::Redis(backend) => backend.add_word_to_index(word, book_id).await,
-            Backend::Postgres(backend) => backend.add_word_to_index(word, book_id).await,
+            Backend::Redis(backend) => backend.add_word_to_index(word, book_id, field).await,
+            Backend::Postgres(backend) => backend.add_word_to_index(word, book_id, field).await,
+            Backend::Memory(backend) => backend.add_word_to_index(word, book_id, field).await,
+            Backend::Sharded(backend) => backend.add_word_to_index(word, book_id, field).await,
         }
     }
 
@@ -107,6 +227,107 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.search_word(word).await,
             Backend::Postgres(backend) => backend.search_word(word).await,
+            Backend::Memory(backend) => backend.search_word(word).await,
+            Backend::Sharded(backend) => backend.search_word(word).await,
+        }
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_books_for_word_in_field(word, field).await,
+            Backend::Postgres(backend) => backend.get_books_for_word_in_field(word, field).await,
+            Backend::Memory(backend) => backend.get_books_for_word_in_field(word, field).await,
+            Backend::Sharded(backend) => backend.get_books_for_word_in_field(word, field).await,
+        }
+    }
+
+    async fn words_with_book(&self, words: &[String], book_id: u32) -> Result<HashSet<String>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.words_with_book(words, book_id).await,
+            Backend::Postgres(backend) => backend.words_with_book(words, book_id).await,
+            Backend::Memory(backend) => backend.words_with_book(words, book_id).await,
+            Backend::Sharded(backend) => backend.words_with_book(words, book_id).await,
+        }
+    }
+
+    async fn get_postings(&self, words: &[String], field: IndexField) -> Result<HashMap<String, HashSet<u32>>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_postings(words, field).await,
+            Backend::Postgres(backend) => backend.get_postings(words, field).await,
+            Backend::Memory(backend) => backend.get_postings(words, field).await,
+            Backend::Sharded(backend) => backend.get_postings(words, field).await,
+        }
+    }
+
+    async fn add_postings(&self, word: &str, book_ids: &[u32], field: IndexField) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.add_postings(word, book_ids, field).await,
+            Backend::Postgres(backend) => backend.add_postings(word, book_ids, field).await,
+            Backend::Memory(backend) => backend.add_postings(word, book_ids, field).await,
+            Backend::Sharded(backend) => backend.add_postings(word, book_ids, field).await,
+        }
+    }
+
+    async fn scan_words(&self, field: IndexField, cursor: Option<String>, count: usize) -> Result<(Vec<String>, Option<String>), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.scan_words(field, cursor, count).await,
+            Backend::Postgres(backend) => backend.scan_words(field, cursor, count).await,
+            Backend::Memory(backend) => backend.scan_words(field, cursor, count).await,
+            Backend::Sharded(backend) => backend.scan_words(field, cursor, count).await,
+        }
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.add_word_to_chapter_index(word, book_id, chapter_no).await,
+            Backend::Postgres(backend) => backend.add_word_to_chapter_index(word, book_id, chapter_no).await,
+            Backend::Memory(backend) => backend.add_word_to_chapter_index(word, book_id, chapter_no).await,
+            Backend::Sharded(backend) => backend.add_word_to_chapter_index(word, book_id, chapter_no).await,
+        }
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.search_word_chapters(word).await,
+            Backend::Postgres(backend) => backend.search_word_chapters(word).await,
+            Backend::Memory(backend) => backend.search_word_chapters(word).await,
+            Backend::Sharded(backend) => backend.search_word_chapters(word).await,
+        }
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.add_word_to_sentence_index(word, book_id, sentence_id).await,
+            Backend::Postgres(backend) => backend.add_word_to_sentence_index(word, book_id, sentence_id).await,
+            Backend::Memory(backend) => backend.add_word_to_sentence_index(word, book_id, sentence_id).await,
+            Backend::Sharded(backend) => backend.add_word_to_sentence_index(word, book_id, sentence_id).await,
+        }
+    }
+
+    async fn search_word_sentences(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.search_word_sentences(word).await,
+            Backend::Postgres(backend) => backend.search_word_sentences(word).await,
+            Backend::Memory(backend) => backend.search_word_sentences(word).await,
+            Backend::Sharded(backend) => backend.search_word_sentences(word).await,
+        }
+    }
+
+    async fn add_cooccurrence(&self, word_a: &str, word_b: &str, book_id: u32, count: u32) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.add_cooccurrence(word_a, word_b, book_id, count).await,
+            Backend::Postgres(backend) => backend.add_cooccurrence(word_a, word_b, book_id, count).await,
+            Backend::Memory(backend) => backend.add_cooccurrence(word_a, word_b, book_id, count).await,
+            Backend::Sharded(backend) => backend.add_cooccurrence(word_a, word_b, book_id, count).await,
+        }
+    }
+
+    async fn get_cooccurrences(&self, word: &str, n: usize) -> Result<Vec<(String, u64)>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_cooccurrences(word, n).await,
+            Backend::Postgres(backend) => backend.get_cooccurrences(word, n).await,
+            Backend::Memory(backend) => backend.get_cooccurrences(word, n).await,
+            Backend::Sharded(backend) => backend.get_cooccurrences(word, n).await,
         }
     }
 
@@ -114,6 +335,152 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.get_stats().await,
             Backend::Postgres(backend) => backend.get_stats().await,
+            Backend::Memory(backend) => backend.get_stats().await,
+            Backend::Sharded(backend) => backend.get_stats().await,
+        }
+    }
+
+    async fn get_field_stats(&self) -> Result<HashMap<IndexField, usize>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_field_stats().await,
+            Backend::Postgres(backend) => backend.get_field_stats().await,
+            Backend::Memory(backend) => backend.get_field_stats().await,
+            Backend::Sharded(backend) => backend.get_field_stats().await,
+        }
+    }
+
+    async fn set_book_incomplete(&self, book_id: u32, incomplete: bool) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.set_book_incomplete(book_id, incomplete).await,
+            Backend::Postgres(backend) => backend.set_book_incomplete(book_id, incomplete).await,
+            Backend::Memory(backend) => backend.set_book_incomplete(book_id, incomplete).await,
+            Backend::Sharded(backend) => backend.set_book_incomplete(book_id, incomplete).await,
+        }
+    }
+
+    async fn get_incomplete_books(&self) -> Result<HashSet<u32>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_incomplete_books().await,
+            Backend::Postgres(backend) => backend.get_incomplete_books().await,
+            Backend::Memory(backend) => backend.get_incomplete_books().await,
+            Backend::Sharded(backend) => backend.get_incomplete_books().await,
+        }
+    }
+
+    async fn save_rebuild_checkpoint(&self, last_completed: Option<u32>) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.save_rebuild_checkpoint(last_completed).await,
+            Backend::Postgres(backend) => backend.save_rebuild_checkpoint(last_completed).await,
+            Backend::Memory(backend) => backend.save_rebuild_checkpoint(last_completed).await,
+            Backend::Sharded(backend) => backend.save_rebuild_checkpoint(last_completed).await,
+        }
+    }
+
+    async fn get_rebuild_checkpoint(&self) -> Result<Option<u32>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_rebuild_checkpoint().await,
+            Backend::Postgres(backend) => backend.get_rebuild_checkpoint().await,
+            Backend::Memory(backend) => backend.get_rebuild_checkpoint().await,
+            Backend::Sharded(backend) => backend.get_rebuild_checkpoint().await,
+        }
+    }
+
+    async fn set_diacritic_folding(&self, folding: bool) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.set_diacritic_folding(folding).await,
+            Backend::Postgres(backend) => backend.set_diacritic_folding(folding).await,
+            Backend::Memory(backend) => backend.set_diacritic_folding(folding).await,
+            Backend::Sharded(backend) => backend.set_diacritic_folding(folding).await,
+        }
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_diacritic_folding().await,
+            Backend::Postgres(backend) => backend.get_diacritic_folding().await,
+            Backend::Memory(backend) => backend.get_diacritic_folding().await,
+            Backend::Sharded(backend) => backend.get_diacritic_folding().await,
+        }
+    }
+
+    async fn set_book_fingerprint(&self, book_id: u32, fingerprint: BookFingerprint) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.set_book_fingerprint(book_id, fingerprint).await,
+            Backend::Postgres(backend) => backend.set_book_fingerprint(book_id, fingerprint).await,
+            Backend::Memory(backend) => backend.set_book_fingerprint(book_id, fingerprint).await,
+            Backend::Sharded(backend) => backend.set_book_fingerprint(book_id, fingerprint).await,
+        }
+    }
+
+    async fn get_book_fingerprints(&self) -> Result<HashMap<u32, BookFingerprint>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_book_fingerprints().await,
+            Backend::Postgres(backend) => backend.get_book_fingerprints().await,
+            Backend::Memory(backend) => backend.get_book_fingerprints().await,
+            Backend::Sharded(backend) => backend.get_book_fingerprints().await,
+        }
+    }
+
+    async fn delete_book(&self, book_id: u32) -> Result<bool, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.delete_book(book_id).await,
+            Backend::Postgres(backend) => backend.delete_book(book_id).await,
+            Backend::Memory(backend) => backend.delete_book(book_id).await,
+            Backend::Sharded(backend) => backend.delete_book(book_id).await,
+        }
+    }
+
+    async fn get_deleted_books(&self) -> Result<HashSet<u32>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_deleted_books().await,
+            Backend::Postgres(backend) => backend.get_deleted_books().await,
+            Backend::Memory(backend) => backend.get_deleted_books().await,
+            Backend::Sharded(backend) => backend.get_deleted_books().await,
+        }
+    }
+
+    async fn clear_deleted_books(&self, book_ids: &[u32]) -> Result<(), StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.clear_deleted_books(book_ids).await,
+            Backend::Postgres(backend) => backend.clear_deleted_books(book_ids).await,
+            Backend::Memory(backend) => backend.clear_deleted_books(book_ids).await,
+            Backend::Sharded(backend) => backend.clear_deleted_books(book_ids).await,
+        }
+    }
+
+    async fn remove_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.remove_postings(word, book_ids).await,
+            Backend::Postgres(backend) => backend.remove_postings(word, book_ids).await,
+            Backend::Memory(backend) => backend.remove_postings(word, book_ids).await,
+            Backend::Sharded(backend) => backend.remove_postings(word, book_ids).await,
+        }
+    }
+
+    async fn purge_deleted_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.purge_deleted_postings(word, book_ids).await,
+            Backend::Postgres(backend) => backend.purge_deleted_postings(word, book_ids).await,
+            Backend::Memory(backend) => backend.purge_deleted_postings(word, book_ids).await,
+            Backend::Sharded(backend) => backend.purge_deleted_postings(word, book_ids).await,
+        }
+    }
+
+    async fn get_index_size_bytes(&self) -> Result<u64, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_index_size_bytes().await,
+            Backend::Postgres(backend) => backend.get_index_size_bytes().await,
+            Backend::Memory(backend) => backend.get_index_size_bytes().await,
+            Backend::Sharded(backend) => backend.get_index_size_bytes().await,
+        }
+    }
+
+    async fn get_last_updated(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        match self {
+            Backend::Redis(backend) => backend.get_last_updated().await,
+            Backend::Postgres(backend) => backend.get_last_updated().await,
+            Backend::Memory(backend) => backend.get_last_updated().await,
+            Backend::Sharded(backend) => backend.get_last_updated().await,
         }
     }
 
@@ -121,25 +488,198 @@ impl StorageBackend for Backend {
         match self {
             Backend::Redis(backend) => backend.test_connection().await,
             Backend::Postgres(backend) => backend.test_connection().await,
+            Backend::Memory(backend) => backend.test_connection().await,
+            Backend::Sharded(backend) => backend.test_connection().await,
         }
     }
 }
 
 /// Redis-based implementation of the [`StorageBackend`] trait.
+///
+/// Title and author postings are kept in `word:{word}:field:{field}` next to
+/// the unscoped `word:{word}` set, and the words seen in each field in
+/// `stats:field_words:{field}`. Every word of the unscoped vocabulary,
+/// `stats:all_words`, is also in the `stats:words_lex` sorted set, all
+/// scored 0, which the search service reads in order for wildcard prefixes.
+/// The IDs of stored books are kept in `index:books`, books being written
+/// in `index:incomplete`, rebuild progress
+/// in `rebuild:last_completed`, body file fingerprints in the
+/// `index:fingerprints` hash, when each book was last stored (Unix
+/// milliseconds) in the `index:recent` sorted set, deleted books awaiting
+/// compaction in `index:deleted`, co-occurrence counts in the `cooc:{word}`
+/// hash (field `{other}:{book_id}`) and the time of the last change,
+/// RFC 3339, in `stats:last_updated`.
+///
+/// Works against a single server or a cluster (see [`RedisConfig`]). Keys
+/// carry no `{hash tag}`, so each word lands in its own slot and the index
+/// spreads across all cluster nodes.
 #[derive(Clone)]
 pub struct RedisBackend {
-    client: redis::Client,
+    client: RedisClient,
 }
 
 impl RedisBackend {
-    pub fn new(redis_url: &str) -> Result<Self, StorageError> {
-        let client = redis::Client::open(redis_url)?;
-        Ok(Self { client })
+    pub fn from_config(config: &RedisConfig) -> Result<Self, StorageError> {
+        Ok(Self {
+            client: RedisClient::open(config)?,
+        })
+    }
+
+    fn field_key(word: &str, field: IndexField) -> String {
+        format!("word:{}:field:{}", word, field.as_str())
+    }
+
+    pub async fn get_connection(&self) -> Result<RedisConnection, StorageError> {
+        Ok(self.client.get_connection().await?)
     }
 
-    pub async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, StorageError> {
-        Ok(self.client.get_multiplexed_async_connection().await?)
+    /// Sums `MEMORY USAGE` over a sample of the word and book keys and scales
+    /// it up to the whole index.
+    async fn sampled_key_bytes(&self, conn: &mut RedisConnection) -> Result<u64, StorageError> {
+        let (total_books, total_words) = self.get_stats().await?;
+
+        let (words, _) = self.scan_words(IndexField::Body, None, SIZE_SAMPLE).await?;
+        let mut word_bytes = 0;
+        for word in words.iter().take(SIZE_SAMPLE) {
+            word_bytes += memory_usage(conn, &format!("word:{}", word)).await?;
+            for field in [IndexField::Title, IndexField::Author] {
+                word_bytes += memory_usage(conn, &Self::field_key(word, field)).await?;
+            }
+        }
+
+        let books = self.get_indexed_books().await?;
+        let mut book_bytes = 0;
+        for book_id in books.iter().take(SIZE_SAMPLE) {
+            book_bytes += memory_usage(conn, &format!("book:{}:metadata", book_id)).await?;
+        }
+
+        Ok(extrapolate(word_bytes, words.len().min(SIZE_SAMPLE), total_words)
+            + extrapolate(book_bytes, books.len().min(SIZE_SAMPLE), total_books))
+    }
+}
+
+/// Words checked or read per pipeline by `words_with_book` and `get_postings`.
+const MEMBERSHIP_BATCH: usize = 1_000;
+
+/// The book-level keys [`DELETE_BOOK_SCRIPT`] updates after the metadata key.
+const BOOK_BOOKKEEPING_KEYS: [&str; 8] = [
+    "stats:total_books",
+    "index:books",
+    "index:deleted",
+    "index:fingerprints",
+    "index:recent",
+    "index:incomplete",
+    "stats:index_version",
+    "stats:last_updated",
+];
+
+/// Deletes a book's metadata and, if there was any, updates the keys of
+/// [`BOOK_BOOKKEEPING_KEYS`]. `ARGV` is the book ID and the RFC 3339 time.
+const DELETE_BOOK_SCRIPT: &str = r#"
+if redis.call('DEL', KEYS[1]) == 0 then
+    return 0
+end
+redis.call('DECR', KEYS[2])
+redis.call('SREM', KEYS[3], ARGV[1])
+redis.call('SADD', KEYS[4], ARGV[1])
+redis.call('HDEL', KEYS[5], ARGV[1])
+redis.call('ZREM', KEYS[6], ARGV[1])
+redis.call('SREM', KEYS[7], ARGV[1])
+redis.call('INCR', KEYS[8])
+redis.call('SET', KEYS[9], ARGV[2])
+return 1
+"#;
+
+/// Removes the books of `ARGV[2..]` still in `index:deleted` (`KEYS[1]`)
+/// from the word's unscoped, title and author sets (`KEYS[2..4]`) and its
+/// `book_id:position` chapter and sentence sets (`KEYS[5..6]`), then drops
+/// the word `ARGV[1]` from the vocabularies (`KEYS[7..10]`) it has no
+/// postings left in. Returns the postings removed and whether the word is
+/// gone, as `remove_postings` does.
+const PURGE_DELETED_SCRIPT: &str = r#"
+local deleted, ids = {}, {}
+for i = 2, #ARGV do
+    if redis.call('SISMEMBER', KEYS[1], ARGV[i]) == 1 then
+        deleted[ARGV[i]] = true
+        table.insert(ids, ARGV[i])
+    end
+end
+if #ids == 0 then
+    return {0, 0}
+end
+
+local postings, dropped = 0, 0
+for i = 2, 4 do
+    postings = postings + redis.call('SREM', KEYS[i], unpack(ids))
+end
+for i = 5, 6 do
+    for _, member in ipairs(redis.call('SMEMBERS', KEYS[i])) do
+        local id = string.match(member, '^(%d+):')
+        if id and deleted[id] then
+            postings = postings + redis.call('SREM', KEYS[i], member)
+        end
+    end
+end
+
+for i = 3, 4 do
+    if redis.call('EXISTS', KEYS[i]) == 0 then
+        dropped = dropped + redis.call('SREM', KEYS[i + 6], ARGV[1])
+    end
+end
+local word_removed = 0
+if redis.call('EXISTS', KEYS[2]) == 0 then
+    dropped = dropped + redis.call('SREM', KEYS[7], ARGV[1])
+    redis.call('ZREM', KEYS[8], ARGV[1])
+    if dropped > 0 and redis.call('EXISTS', KEYS[4]) == 0 then
+        word_removed = 1
+    end
+end
+return {postings, word_removed}
+"#;
+
+/// Words and books whose keys are measured to size a Redis index; larger
+/// indices are extrapolated from the sample.
+const SIZE_SAMPLE: usize = 500;
+
+/// Bytes Redis attributes to `key`, `0` if it doesn't exist.
+async fn memory_usage(conn: &mut RedisConnection, key: &str) -> Result<u64, StorageError> {
+    let bytes: Option<u64> = redis::cmd("MEMORY").arg("USAGE").arg(key).query_async(conn).await?;
+    Ok(bytes.unwrap_or(0))
+}
+
+/// Fills `index:books` from the `book:{id}:metadata` keys of an index
+/// written before the set was kept, and returns the book IDs found.
+async fn backfill_indexed_books(conn: &mut RedisConnection) -> Result<HashSet<u32>, StorageError> {
+    let keys = conn.keys_matching("book:*:metadata").await?;
+    let book_ids: Vec<u32> = keys
+        .iter()
+        .filter_map(|key| key.strip_prefix("book:")?.strip_suffix(":metadata")?.parse().ok())
+        .collect();
+    for chunk in book_ids.chunks(MEMBERSHIP_BATCH) {
+        conn.sadd::<_, _, ()>("index:books", chunk).await?;
+    }
+    Ok(book_ids.into_iter().collect())
+}
+
+/// Records now as the time the index last changed.
+async fn touch_redis_last_updated(conn: &mut RedisConnection) -> Result<(), StorageError> {
+    conn.set::<_, _, ()>("stats:last_updated", Utc::now().to_rfc3339()).await?;
+    Ok(())
+}
+
+/// Scales the bytes measured for `sampled` items up to `total` items.
+fn extrapolate(sampled_bytes: u64, sampled: usize, total: usize) -> u64 {
+    if sampled == 0 {
+        return 0;
     }
+    sampled_bytes * total.max(sampled) as u64 / sampled as u64
+}
+
+/// Reads `used_memory` from the output of `INFO memory`.
+fn parse_used_memory(info: &str) -> Option<u64> {
+    info.lines()
+        .find_map(|line| line.strip_prefix("used_memory:"))
+        .and_then(|value| value.trim().parse().ok())
 }
 
 #[async_trait]
@@ -150,8 +690,20 @@ impl StorageBackend for RedisBackend {
         let key = format!("book:{}:metadata", metadata.book_id);
         let value = serde_json::to_string(metadata)?;
 
-        conn.set::<_, _, ()>(&key, &value).await?;
-        conn.incr::<_, _, ()>("stats:total_books", 1).await?;
+        // Only count a book the first time it is stored, so re-indexing or
+        // re-importing it doesn't inflate the total. SET ... GET swaps the
+        // value atomically, so of several concurrent writers of a new book
+        // exactly one sees no previous value.
+        let previous: Option<String> = redis::cmd("SET").arg(&key).arg(&value).arg("GET").query_async(&mut conn).await?;
+        if previous.is_none() {
+            conn.incr::<_, _, ()>("stats:total_books", 1).await?;
+        }
+        conn.sadd::<_, _, ()>("index:books", metadata.book_id).await?;
+        conn.srem::<_, _, ()>("index:deleted", metadata.book_id).await?;
+        conn.zadd::<_, _, _, ()>("index:recent", metadata.book_id, Utc::now().timestamp_millis()).await?;
+        // Lets readers detect that the index changed since they last looked.
+        conn.incr::<_, _, ()>("stats:index_version", 1).await?;
+        touch_redis_last_updated(&mut conn).await?;
 
         Ok(())
     }
@@ -183,128 +735,740 @@ impl StorageBackend for RedisBackend {
     async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
         let mut conn = self.get_connection().await?;
 
-        let pattern = "book:*:metadata";
-        let keys: Vec<String> = conn.keys(pattern).await?;
-
-        let mut book_ids = HashSet::new();
-        for key in keys {
-            if let Some(book_id_str) = key
-                .strip_prefix("book:")
-                .and_then(|s| s.strip_suffix(":metadata"))
-            {
-                if let Ok(book_id) = book_id_str.parse::<u32>() {
-                    book_ids.insert(book_id);
-                }
+        let book_ids: Vec<u32> = conn.smembers("index:books").await?;
+        if book_ids.is_empty() {
+            // An index written before `index:books` was kept only has the
+            // metadata keys; they are looked up once and tracked from then on.
+            let total_books: Option<i64> = conn.get("stats:total_books").await?;
+            if total_books.unwrap_or(0) > 0 {
+                return backfill_indexed_books(&mut conn).await;
             }
         }
 
-        Ok(book_ids)
+        Ok(book_ids.into_iter().collect())
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        self.add_postings(word, &[book_id], field).await
     }
 
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError> {
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
         let mut conn = self.get_connection().await?;
 
         let word_key = format!("word:{}", word);
-        conn.sadd::<_, _, ()>(&word_key, book_id).await?;
-        conn.sadd::<_, _, ()>("stats:all_words", word).await?;
+        let book_ids: Vec<u32> = conn.smembers(&word_key).await?;
 
-        Ok(())
+        Ok(book_ids.into_iter().collect())
     }
 
-    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        if field.is_body() {
+            return self.search_word(word).await;
+        }
         let mut conn = self.get_connection().await?;
 
-        let word_key = format!("word:{}", word);
-        let book_ids: Vec<u32> = conn.smembers(&word_key).await?;
+        let book_ids: Vec<u32> = conn.smembers(Self::field_key(word, field)).await?;
 
         Ok(book_ids.into_iter().collect())
     }
 
-    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+    async fn words_with_book(&self, words: &[String], book_id: u32) -> Result<HashSet<String>, StorageError> {
         let mut conn = self.get_connection().await?;
+        let mut present = HashSet::new();
+
+        // Each word's set sits in its own cluster slot, so cluster mode asks
+        // one at a time; a single server answers a pipelined batch.
+        if self.client.is_cluster() {
+            for word in words {
+                if conn.sismember(format!("word:{}", word), book_id).await? {
+                    present.insert(word.clone());
+                }
+            }
+            return Ok(present);
+        }
 
-        let total_books: Option<usize> = conn.get("stats:total_books").await?;
-        let unique_words: usize = conn.scard("stats:all_words").await?;
+        for batch in words.chunks(MEMBERSHIP_BATCH) {
+            let mut pipe = redis::pipe();
+            for word in batch {
+                pipe.sismember(format!("word:{}", word), book_id);
+            }
+            let found: Vec<bool> = pipe.query_async(&mut conn).await?;
+            present.extend(batch.iter().zip(found).filter(|(_, found)| *found).map(|(word, _)| word.clone()));
+        }
 
-        Ok((total_books.unwrap_or(0), unique_words))
+        Ok(present)
     }
 
-    async fn test_connection(&self) -> Result<(), StorageError> {
+    async fn get_postings(&self, words: &[String], field: IndexField) -> Result<HashMap<String, HashSet<u32>>, StorageError> {
         let mut conn = self.get_connection().await?;
-        let _: Option<String> = conn.get("__connection_test__").await?;
+        let key = |word: &str| match field {
+            IndexField::Body => format!("word:{}", word),
+            field => Self::field_key(word, field),
+        };
+        let mut postings = HashMap::new();
+
+        // As in `words_with_book`, cluster mode reads one set at a time.
+        if self.client.is_cluster() {
+            for word in words {
+                let book_ids: HashSet<u32> = conn.smembers(key(word)).await?;
+                if !book_ids.is_empty() {
+                    postings.insert(word.clone(), book_ids);
+                }
+            }
+            return Ok(postings);
+        }
+
+        for batch in words.chunks(MEMBERSHIP_BATCH) {
+            let mut pipe = redis::pipe();
+            for word in batch {
+                pipe.smembers(key(word));
+            }
+            let found: Vec<HashSet<u32>> = pipe.query_async(&mut conn).await?;
+            postings.extend(
+                batch
+                    .iter()
+                    .zip(found)
+                    .filter(|(_, book_ids)| !book_ids.is_empty())
+                    .map(|(word, book_ids)| (word.clone(), book_ids)),
+            );
+        }
+
+        Ok(postings)
+    }
+
+    async fn add_postings(&self, word: &str, book_ids: &[u32], field: IndexField) -> Result<(), StorageError> {
+        if book_ids.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.get_connection().await?;
+
+        let word_key = field.is_unscoped().then(|| format!("word:{}", word));
+        let field_keys = (!field.is_body())
+            .then(|| (Self::field_key(word, field), format!("stats:field_words:{}", field.as_str())));
+
+        // The keys below live in different cluster slots, which a cluster
+        // pipeline can't span, so cluster mode writes them one at a time.
+        if self.client.is_cluster() {
+            if let Some(word_key) = word_key {
+                conn.sadd::<_, _, ()>(&word_key, book_ids).await?;
+                conn.sadd::<_, _, ()>("stats:all_words", word).await?;
+                conn.zadd::<_, _, _, ()>("stats:words_lex", word, 0).await?;
+            }
+            if let Some((field_key, field_words_key)) = field_keys {
+                conn.sadd::<_, _, ()>(field_key, book_ids).await?;
+                conn.sadd::<_, _, ()>(field_words_key, word).await?;
+            }
+            return Ok(());
+        }
+
+        let mut pipe = redis::pipe();
+        if let Some(word_key) = word_key {
+            pipe.sadd(&word_key, book_ids).ignore();
+            pipe.sadd("stats:all_words", word).ignore();
+            pipe.zadd("stats:words_lex", word, 0).ignore();
+        }
+        if let Some((field_key, field_words_key)) = field_keys {
+            pipe.sadd(field_key, book_ids).ignore();
+            pipe.sadd(field_words_key, word).ignore();
+        }
+        pipe.query_async::<_, ()>(&mut conn).await?;
+
         Ok(())
     }
-}
 
-/// PostgreSQL-based implementation of the [`StorageBackend`] trait.
-#[derive(Clone)]
-pub struct PostgresBackend {
-    pool: PgPool,
-}
+    async fn scan_words(&self, field: IndexField, cursor: Option<String>, count: usize) -> Result<(Vec<String>, Option<String>), StorageError> {
+        let mut conn = self.get_connection().await?;
 
-impl PostgresBackend {
-    pub async fn new(database_url: &str) -> Result<Self, StorageError> {
-        let pool = PgPool::connect(database_url).await?;
+        let vocabulary_key = match field {
+            IndexField::Body => "stats:all_words".to_string(),
+            field => format!("stats:field_words:{}", field.as_str()),
+        };
+        let cursor: u64 = cursor.and_then(|c| c.parse().ok()).unwrap_or(0);
+        let (next, words): (u64, Vec<String>) = redis::cmd("SSCAN")
+            .arg(vocabulary_key)
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await?;
 
-        // Initialize tables
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS books (
-                book_id INTEGER PRIMARY KEY,
-                title TEXT,
-                author TEXT,
-                language VARCHAR(10),
-                year INTEGER,
-                word_count INTEGER,
-                unique_words INTEGER,
-                indexed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+        Ok((words, (next != 0).then(|| next.to_string())))
+    }
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS word_index (
-                word VARCHAR,
-                book_id INTEGER,
-                PRIMARY KEY (word, book_id)
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
 
-        sqlx::query(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_word_index_word ON word_index(word)
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+        let chapters_key = format!("word:{}:chapters", word);
+        conn.sadd::<_, _, ()>(&chapters_key, format!("{}:{}", book_id, chapter_no)).await?;
 
-        Ok(Self { pool })
+        Ok(())
     }
-}
 
-#[async_trait]
-impl StorageBackend for PostgresBackend {
-    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
-        sqlx::query(
-            r#"
-            INSERT INTO books (book_id, title, author, language, year, word_count, unique_words)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            ON CONFLICT (book_id) DO UPDATE SET
-                title = EXCLUDED.title,
-                author = EXCLUDED.author,
-                language = EXCLUDED.language,
-                year = EXCLUDED.year,
-                word_count = EXCLUDED.word_count,
-                unique_words = EXCLUDED.unique_words,
-                indexed_at = CURRENT_TIMESTAMP
-            "#,
-        )
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let chapters_key = format!("word:{}:chapters", word);
+        let members: Vec<String> = conn.smembers(&chapters_key).await?;
+
+        Ok(parse_book_positions(&members))
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let sentences_key = format!("word:{}:sentences", word);
+        conn.sadd::<_, _, ()>(&sentences_key, format!("{}:{}", book_id, sentence_id)).await?;
+
+        Ok(())
+    }
+
+    async fn search_word_sentences(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let sentences_key = format!("word:{}:sentences", word);
+        let members: Vec<String> = conn.smembers(&sentences_key).await?;
+
+        Ok(parse_book_positions(&members))
+    }
+
+    async fn add_cooccurrence(&self, word_a: &str, word_b: &str, book_id: u32, count: u32) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        // Stored under both words so either one finds the pair
+        conn.hset::<_, _, _, ()>(format!("cooc:{}", word_a), format!("{}:{}", word_b, book_id), count).await?;
+        conn.hset::<_, _, _, ()>(format!("cooc:{}", word_b), format!("{}:{}", word_a, book_id), count).await?;
+
+        Ok(())
+    }
+
+    async fn get_cooccurrences(&self, word: &str, n: usize) -> Result<Vec<(String, u64)>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let entries: HashMap<String, u64> = conn.hgetall(format!("cooc:{}", word)).await?;
+        let counts = entries.into_iter().filter_map(|(field, count)| {
+            let (other, _book_id) = field.rsplit_once(':')?;
+            Some((other.to_string(), count))
+        });
+
+        Ok(top_cooccurrences(counts, n))
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let total_books: Option<usize> = conn.get("stats:total_books").await?;
+        let unique_words: usize = conn.scard("stats:all_words").await?;
+
+        Ok((total_books.unwrap_or(0), unique_words))
+    }
+
+    async fn get_field_stats(&self) -> Result<HashMap<IndexField, usize>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let mut stats = HashMap::new();
+        for field in IndexField::ALL {
+            let key = if field.is_body() {
+                "stats:all_words".to_string()
+            } else {
+                format!("stats:field_words:{}", field.as_str())
+            };
+            stats.insert(field, conn.scard(key).await?);
+        }
+
+        Ok(stats)
+    }
+
+    async fn set_book_incomplete(&self, book_id: u32, incomplete: bool) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        if incomplete {
+            conn.sadd::<_, _, ()>("index:incomplete", book_id).await?;
+        } else {
+            conn.srem::<_, _, ()>("index:incomplete", book_id).await?;
+        }
+        touch_redis_last_updated(&mut conn).await?;
+
+        Ok(())
+    }
+
+    async fn get_incomplete_books(&self) -> Result<HashSet<u32>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let book_ids: Vec<u32> = conn.smembers("index:incomplete").await?;
+        Ok(book_ids.into_iter().collect())
+    }
+
+    async fn save_rebuild_checkpoint(&self, last_completed: Option<u32>) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        match last_completed {
+            Some(book_id) => conn.set::<_, _, ()>("rebuild:last_completed", book_id).await?,
+            None => conn.del::<_, ()>("rebuild:last_completed").await?,
+        }
+
+        Ok(())
+    }
+
+    async fn get_rebuild_checkpoint(&self) -> Result<Option<u32>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        Ok(conn.get("rebuild:last_completed").await?)
+    }
+
+    async fn set_diacritic_folding(&self, folding: bool) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        conn.set::<_, _, ()>("index:fold_diacritics", folding.to_string()).await?;
+        Ok(())
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let folding: Option<String> = conn.get("index:fold_diacritics").await?;
+        Ok(folding.map(|value| value == "true"))
+    }
+
+    async fn set_book_fingerprint(&self, book_id: u32, fingerprint: BookFingerprint) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let value = serde_json::to_string(&fingerprint)?;
+        conn.hset::<_, _, _, ()>("index:fingerprints", book_id, value).await?;
+        Ok(())
+    }
+
+    async fn get_book_fingerprints(&self) -> Result<HashMap<u32, BookFingerprint>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let entries: HashMap<u32, String> = conn.hgetall("index:fingerprints").await?;
+        entries
+            .into_iter()
+            .map(|(book_id, json)| Ok((book_id, serde_json::from_str(&json)?)))
+            .collect()
+    }
+
+    /// Runs as one script on a single server, so a concurrent re-index
+    /// sees the book either fully indexed or fully deleted. A cluster can't
+    /// run a script over keys in different slots and sends the commands
+    /// one at a time.
+    async fn delete_book(&self, book_id: u32) -> Result<bool, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let metadata_key = format!("book:{}:metadata", book_id);
+        if !self.client.is_cluster() {
+            let deleted: bool = redis::Script::new(DELETE_BOOK_SCRIPT)
+                .key(&metadata_key)
+                .key(BOOK_BOOKKEEPING_KEYS.as_slice())
+                .arg(book_id)
+                .arg(Utc::now().to_rfc3339())
+                .invoke_async(&mut conn)
+                .await?;
+            return Ok(deleted);
+        }
+
+        let removed: usize = conn.del(&metadata_key).await?;
+        if removed == 0 {
+            return Ok(false);
+        }
+        conn.decr::<_, _, ()>("stats:total_books", 1).await?;
+        conn.srem::<_, _, ()>("index:books", book_id).await?;
+        conn.sadd::<_, _, ()>("index:deleted", book_id).await?;
+        conn.hdel::<_, _, ()>("index:fingerprints", book_id).await?;
+        conn.zrem::<_, _, ()>("index:recent", book_id).await?;
+        conn.srem::<_, _, ()>("index:incomplete", book_id).await?;
+        conn.incr::<_, _, ()>("stats:index_version", 1).await?;
+        touch_redis_last_updated(&mut conn).await?;
+
+        Ok(true)
+    }
+
+    async fn get_deleted_books(&self) -> Result<HashSet<u32>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let book_ids: Vec<u32> = conn.smembers("index:deleted").await?;
+        Ok(book_ids.into_iter().collect())
+    }
+
+    async fn clear_deleted_books(&self, book_ids: &[u32]) -> Result<(), StorageError> {
+        if book_ids.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.get_connection().await?;
+
+        conn.srem::<_, _, ()>("index:deleted", book_ids).await?;
+        Ok(())
+    }
+
+    async fn remove_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        if book_ids.is_empty() {
+            return Ok(RemovedPostings::default());
+        }
+        let mut conn = self.get_connection().await?;
+        let mut removed = RemovedPostings::default();
+
+        // Redis deletes a set once its last member is removed, so emptied
+        // posting sets go away by themselves; the vocabulary sets that list
+        // the word have to be cleaned up here.
+        let word_key = format!("word:{}", word);
+        let mut dropped = 0;
+        removed.postings += conn.srem::<_, _, usize>(&word_key, book_ids).await?;
+        for field in [IndexField::Title, IndexField::Author] {
+            let field_key = Self::field_key(word, field);
+            removed.postings += conn.srem::<_, _, usize>(&field_key, book_ids).await?;
+            if !conn.exists::<_, bool>(&field_key).await? {
+                dropped += conn.srem::<_, _, usize>(format!("stats:field_words:{}", field.as_str()), word).await?;
+            }
+        }
+
+        for positions_key in [format!("word:{}:chapters", word), format!("word:{}:sentences", word)] {
+            let members: Vec<String> = conn.smembers(&positions_key).await?;
+            let stale: Vec<String> = members
+                .into_iter()
+                .filter(|member| {
+                    member
+                        .split_once(':')
+                        .and_then(|(book_id, _)| book_id.parse::<u32>().ok())
+                        .is_some_and(|book_id| book_ids.contains(&book_id))
+                })
+                .collect();
+            if !stale.is_empty() {
+                removed.postings += conn.srem::<_, _, usize>(&positions_key, stale).await?;
+            }
+        }
+
+        if !conn.exists::<_, bool>(&word_key).await? {
+            dropped += conn.srem::<_, _, usize>("stats:all_words", word).await?;
+            conn.zrem::<_, _, ()>("stats:words_lex", word).await?;
+            // Author postings aren't in the unscoped set, so the word is only
+            // gone once its author set is too.
+            let author_left: bool = conn.exists(Self::field_key(word, IndexField::Author)).await?;
+            removed.word_removed = dropped > 0 && !author_left;
+        }
+
+        Ok(removed)
+    }
+
+    /// A single server checks `index:deleted` and removes the postings in
+    /// one script. A cluster can't, as the keys live in different slots; it
+    /// re-reads the deleted books just before [`Self::remove_postings`],
+    /// which leaves a window of a few round trips.
+    async fn purge_deleted_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        if book_ids.is_empty() {
+            return Ok(RemovedPostings::default());
+        }
+        if self.client.is_cluster() {
+            let deleted = self.get_deleted_books().await?;
+            let book_ids: Vec<u32> = book_ids.iter().copied().filter(|id| deleted.contains(id)).collect();
+            return self.remove_postings(word, &book_ids).await;
+        }
+
+        let mut conn = self.get_connection().await?;
+        let (postings, word_removed): (usize, bool) = redis::Script::new(PURGE_DELETED_SCRIPT)
+            .key("index:deleted")
+            .key(format!("word:{}", word))
+            .key(Self::field_key(word, IndexField::Title))
+            .key(Self::field_key(word, IndexField::Author))
+            .key(format!("word:{}:chapters", word))
+            .key(format!("word:{}:sentences", word))
+            .key("stats:all_words")
+            .key("stats:words_lex")
+            .key("stats:field_words:title")
+            .key("stats:field_words:author")
+            .arg(word)
+            .arg(book_ids)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(RemovedPostings { postings, word_removed })
+    }
+
+    /// Measures a sample of the index's keys. Where `MEMORY USAGE` is
+    /// disabled, as on some managed services, falls back to the server's
+    /// whole `used_memory`, which also counts data outside the index.
+    async fn get_index_size_bytes(&self) -> Result<u64, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        match self.sampled_key_bytes(&mut conn).await {
+            Ok(bytes) => Ok(bytes),
+            Err(StorageError::Redis(_)) => {
+                let info: String = redis::cmd("INFO").arg("memory").query_async(&mut conn).await?;
+                parse_used_memory(&info)
+                    .ok_or_else(|| StorageError::Connection("INFO memory has no used_memory".to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_last_updated(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let value: Option<String> = conn.get("stats:last_updated").await?;
+        // A value that doesn't parse is treated like a missing one.
+        Ok(value
+            .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+            .map(|updated_at| updated_at.with_timezone(&Utc)))
+    }
+
+    async fn test_connection(&self) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+        let _: Option<String> = conn.get("__connection_test__").await?;
+        Ok(())
+    }
+}
+
+/// Parses `book_id:position` members of a Redis chapter or sentence set,
+/// skipping malformed ones.
+fn parse_book_positions(members: &[String]) -> HashSet<(u32, usize)> {
+    members
+        .iter()
+        .filter_map(|member| {
+            let (book_id, position) = member.split_once(':')?;
+            Some((book_id.parse().ok()?, position.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Tables holding the index, counted by `get_index_size_bytes` along with
+/// their indexes and TOAST data.
+const INDEX_TABLES: [&str; 6] = [
+    "books",
+    "word_index",
+    "word_field_index",
+    "word_chapter_index",
+    "word_sentence_index",
+    "word_cooccurrence",
+];
+
+/// Deletes `book_ids` from every posting table for `word`, as
+/// `remove_postings` describes.
+async fn delete_postgres_postings(
+    conn: &mut sqlx::PgConnection,
+    word: &str,
+    book_ids: &[i32],
+) -> Result<RemovedPostings, StorageError> {
+    let mut removed = RemovedPostings::default();
+    if book_ids.is_empty() {
+        return Ok(removed);
+    }
+
+    let mut word_postings = 0;
+    for table in ["word_index", "word_field_index", "word_chapter_index", "word_sentence_index"] {
+        let rows = sqlx::query(&format!(
+            "DELETE FROM {} WHERE word = $1 AND book_id = ANY($2)",
+            table
+        ))
+        .bind(word)
+        .bind(book_ids)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize;
+        removed.postings += rows;
+        if matches!(table, "word_index" | "word_field_index") {
+            word_postings += rows;
+        }
+    }
+
+    // The vocabularies are derived from word_index and word_field_index,
+    // so a word with no rows left in either is gone.
+    removed.word_removed = word_postings > 0
+        && sqlx::query(
+            "SELECT 1 FROM word_index WHERE word = $1 UNION ALL SELECT 1 FROM word_field_index WHERE word = $1 LIMIT 1"
+        )
+        .bind(word)
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_none();
+
+    Ok(removed)
+}
+
+/// Records now as the time the index last changed, in the single
+/// `last_updated` row of `index_meta`.
+async fn touch_postgres_last_updated(executor: impl sqlx::PgExecutor<'_>) -> Result<(), StorageError> {
+    sqlx::query(
+        "INSERT INTO index_meta (key, updated_at) VALUES ('last_updated', $1) ON CONFLICT (key) DO UPDATE SET updated_at = EXCLUDED.updated_at"
+    )
+    .bind(Utc::now().naive_utc())
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// PostgreSQL-based implementation of the [`StorageBackend`] trait.
+#[derive(Clone)]
+pub struct PostgresBackend {
+    pool: PgPool,
+}
+
+impl PostgresBackend {
+    pub async fn new(database_url: &str) -> Result<Self, StorageError> {
+        let pool = PgPool::connect(database_url).await?;
+
+        // Initialize tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS books (
+                book_id INTEGER PRIMARY KEY,
+                title TEXT,
+                author TEXT,
+                language VARCHAR(10),
+                year INTEGER,
+                word_count INTEGER,
+                unique_words INTEGER,
+                indexed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("ALTER TABLE books ADD COLUMN IF NOT EXISTS chapter_count INTEGER DEFAULT 0")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query("ALTER TABLE books ADD COLUMN IF NOT EXISTS reading_level DOUBLE PRECISION")
+            .execute(&pool)
+            .await?;
+
+        // Subjects are stored as a serialized JSON array
+        sqlx::query("ALTER TABLE books ADD COLUMN IF NOT EXISTS subjects TEXT DEFAULT '[]'")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_index (
+                word VARCHAR,
+                book_id INTEGER,
+                PRIMARY KEY (word, book_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_word_index_word ON word_index(word)
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Title and author postings, also present in word_index
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_field_index (
+                word VARCHAR,
+                field VARCHAR(10),
+                book_id INTEGER,
+                PRIMARY KEY (word, field, book_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS incomplete_books (book_id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS book_fingerprints (book_id INTEGER PRIMARY KEY, modified_ns BIGINT NOT NULL, size BIGINT NOT NULL)"
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS deleted_books (book_id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS index_jobs (job VARCHAR PRIMARY KEY, last_completed INTEGER)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS index_meta (key VARCHAR PRIMARY KEY, updated_at TIMESTAMP NOT NULL)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS index_settings (key VARCHAR PRIMARY KEY, value VARCHAR NOT NULL)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_cooccurrence (
+                word_a VARCHAR,
+                word_b VARCHAR,
+                book_id INTEGER,
+                count INTEGER NOT NULL,
+                PRIMARY KEY (word_a, word_b, book_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_word_cooccurrence_word_b ON word_cooccurrence(word_b)")
+            .execute(&pool)
+            .await?;
+
+        // For the search service's feed of recently indexed books
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_books_indexed_at ON books(indexed_at)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_chapter_index (
+                word VARCHAR,
+                book_id INTEGER,
+                chapter_no INTEGER,
+                PRIMARY KEY (word, book_id, chapter_no)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_sentence_index (
+                word VARCHAR,
+                book_id INTEGER,
+                sentence_id INTEGER,
+                PRIMARY KEY (word, book_id, sentence_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl StorageBackend for PostgresBackend {
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO books (book_id, title, author, language, year, word_count, unique_words, chapter_count, subjects, reading_level)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (book_id) DO UPDATE SET
+                title = EXCLUDED.title,
+                author = EXCLUDED.author,
+                language = EXCLUDED.language,
+                year = EXCLUDED.year,
+                word_count = EXCLUDED.word_count,
+                unique_words = EXCLUDED.unique_words,
+                chapter_count = EXCLUDED.chapter_count,
+                subjects = EXCLUDED.subjects,
+                reading_level = EXCLUDED.reading_level,
+                indexed_at = CURRENT_TIMESTAMP
+            "#,
+        )
         .bind(metadata.book_id as i32)
         .bind(&metadata.title)
         .bind(&metadata.author)
@@ -312,97 +1476,557 @@ impl StorageBackend for PostgresBackend {
         .bind(metadata.year.map(|y| y as i32))
         .bind(metadata.word_count as i32)
         .bind(metadata.unique_words as i32)
+        .bind(metadata.chapter_count as i32)
+        .bind(serde_json::to_string(&metadata.subjects)?)
+        .bind(metadata.reading_level)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("DELETE FROM deleted_books WHERE book_id = $1")
+            .bind(metadata.book_id as i32)
+            .execute(&self.pool)
+            .await?;
+        touch_postgres_last_updated(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
+        let row = sqlx::query(
+            "SELECT book_id, title, author, language, year, word_count, unique_words, chapter_count, subjects, reading_level FROM books WHERE book_id = $1"
+        )
+        .bind(book_id as i32)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => {
+                let metadata = BookMetadata {
+                    book_id: row.get::<i32, _>("book_id") as u32,
+                    title: row.get("title"),
+                    author: row.get("author"),
+                    language: row.get("language"),
+                    year: row.get::<Option<i32>, _>("year").map(|y| y as u32),
+                    word_count: row.get::<i32, _>("word_count") as usize,
+                    unique_words: row.get::<i32, _>("unique_words") as usize,
+                    chapter_count: row.get::<Option<i32>, _>("chapter_count").unwrap_or(0) as usize,
+                    subjects: row
+                        .get::<Option<String>, _>("subjects")
+                        .map(|json| serde_json::from_str(&json))
+                        .transpose()?
+                        .unwrap_or_default(),
+                    reading_level: row.get("reading_level"),
+                };
+                Ok(Some(metadata))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
+        let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM books WHERE book_id = $1)")
+            .bind(book_id as i32)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
+        let rows = sqlx::query("SELECT book_id FROM books")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let book_ids = rows
+            .into_iter()
+            .map(|row| row.get::<i32, _>("book_id") as u32)
+            .collect();
+
+        Ok(book_ids)
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        if field.is_unscoped() {
+            sqlx::query(
+                "INSERT INTO word_index (word, book_id) VALUES ($1, $2) ON CONFLICT (word, book_id) DO NOTHING"
+            )
+            .bind(word)
+            .bind(book_id as i32)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        if !field.is_body() {
+            sqlx::query(
+                "INSERT INTO word_field_index (word, field, book_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+            )
+            .bind(word)
+            .bind(field.as_str())
+            .bind(book_id as i32)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        let rows = sqlx::query("SELECT book_id FROM word_index WHERE word = $1")
+            .bind(word)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let book_ids = rows
+            .into_iter()
+            .map(|row| row.get::<i32, _>("book_id") as u32)
+            .collect();
+
+        Ok(book_ids)
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        if field.is_body() {
+            return self.search_word(word).await;
+        }
+
+        let rows = sqlx::query("SELECT book_id FROM word_field_index WHERE word = $1 AND field = $2")
+            .bind(word)
+            .bind(field.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+
+        let book_ids = rows
+            .into_iter()
+            .map(|row| row.get::<i32, _>("book_id") as u32)
+            .collect();
+
+        Ok(book_ids)
+    }
+
+    async fn words_with_book(&self, words: &[String], book_id: u32) -> Result<HashSet<String>, StorageError> {
+        let rows = sqlx::query("SELECT word FROM word_index WHERE book_id = $1 AND word = ANY($2)")
+            .bind(book_id as i32)
+            .bind(words)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("word")).collect())
+    }
+
+    async fn get_postings(&self, words: &[String], field: IndexField) -> Result<HashMap<String, HashSet<u32>>, StorageError> {
+        let rows = if field.is_body() {
+            sqlx::query("SELECT word, book_id FROM word_index WHERE word = ANY($1)")
+                .bind(words)
+                .fetch_all(&self.pool)
+                .await?
+        } else {
+            sqlx::query("SELECT word, book_id FROM word_field_index WHERE word = ANY($1) AND field = $2")
+                .bind(words)
+                .bind(field.as_str())
+                .fetch_all(&self.pool)
+                .await?
+        };
+
+        let mut postings: HashMap<String, HashSet<u32>> = HashMap::new();
+        for row in rows {
+            postings
+                .entry(row.get("word"))
+                .or_default()
+                .insert(row.get::<i32, _>("book_id") as u32);
+        }
+
+        Ok(postings)
+    }
+
+    async fn add_postings(&self, word: &str, book_ids: &[u32], field: IndexField) -> Result<(), StorageError> {
+        let book_ids: Vec<i32> = book_ids.iter().map(|&id| id as i32).collect();
+
+        if field.is_unscoped() {
+            sqlx::query(
+                "INSERT INTO word_index (word, book_id) SELECT $1, UNNEST($2::INTEGER[]) ON CONFLICT (word, book_id) DO NOTHING"
+            )
+            .bind(word)
+            .bind(&book_ids)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        if !field.is_body() {
+            sqlx::query(
+                "INSERT INTO word_field_index (word, field, book_id) SELECT $1, $2, UNNEST($3::INTEGER[]) ON CONFLICT DO NOTHING"
+            )
+            .bind(word)
+            .bind(field.as_str())
+            .bind(&book_ids)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn scan_words(&self, field: IndexField, cursor: Option<String>, count: usize) -> Result<(Vec<String>, Option<String>), StorageError> {
+        let rows = if field.is_body() {
+            sqlx::query(
+                "SELECT DISTINCT word FROM word_index WHERE $1::VARCHAR IS NULL OR word > $1 ORDER BY word LIMIT $2"
+            )
+            .bind(cursor)
+            .bind(count as i64)
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            sqlx::query(
+                "SELECT DISTINCT word FROM word_field_index WHERE field = $3 AND ($1::VARCHAR IS NULL OR word > $1) ORDER BY word LIMIT $2"
+            )
+            .bind(cursor)
+            .bind(count as i64)
+            .bind(field.as_str())
+            .fetch_all(&self.pool)
+            .await?
+        };
+
+        let words: Vec<String> = rows.into_iter().map(|row| row.get("word")).collect();
+        let next = if words.len() == count { words.last().cloned() } else { None };
+
+        Ok((words, next))
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO word_chapter_index (word, book_id, chapter_no) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+        )
+        .bind(word)
+        .bind(book_id as i32)
+        .bind(chapter_no as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let rows = sqlx::query("SELECT book_id, chapter_no FROM word_chapter_index WHERE word = $1")
+            .bind(word)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let postings = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<i32, _>("book_id") as u32,
+                    row.get::<i32, _>("chapter_no") as usize,
+                )
+            })
+            .collect();
+
+        Ok(postings)
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO word_sentence_index (word, book_id, sentence_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+        )
+        .bind(word)
+        .bind(book_id as i32)
+        .bind(sentence_id as i32)
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(())
+    }
+
+    async fn search_word_sentences(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let rows = sqlx::query("SELECT book_id, sentence_id FROM word_sentence_index WHERE word = $1")
+            .bind(word)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let postings = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<i32, _>("book_id") as u32,
+                    row.get::<i32, _>("sentence_id") as usize,
+                )
+            })
+            .collect();
+
+        Ok(postings)
+    }
+
+    async fn add_cooccurrence(&self, word_a: &str, word_b: &str, book_id: u32, count: u32) -> Result<(), StorageError> {
+        // One row per pair, in word order
+        let (word_a, word_b) = if word_a <= word_b { (word_a, word_b) } else { (word_b, word_a) };
+        sqlx::query(
+            r#"
+            INSERT INTO word_cooccurrence (word_a, word_b, book_id, count) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (word_a, word_b, book_id) DO UPDATE SET count = EXCLUDED.count
+            "#,
+        )
+        .bind(word_a)
+        .bind(word_b)
+        .bind(book_id as i32)
+        .bind(count as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_cooccurrences(&self, word: &str, n: usize) -> Result<Vec<(String, u64)>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT CASE WHEN word_a = $1 THEN word_b ELSE word_a END AS other, SUM(count)::BIGINT AS total
+            FROM word_cooccurrence
+            WHERE word_a = $1 OR word_b = $1
+            GROUP BY other
+            ORDER BY total DESC, other
+            LIMIT $2
+            "#,
+        )
+        .bind(word)
+        .bind(n as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get::<String, _>("other"), row.get::<i64, _>("total") as u64))
+            .collect())
     }
 
-    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
-        let row = sqlx::query(
-            "SELECT book_id, title, author, language, year, word_count, unique_words FROM books WHERE book_id = $1"
-        )
-        .bind(book_id as i32)
-        .fetch_optional(&self.pool)
-        .await?;
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        let total_books = sqlx::query("SELECT COUNT(*) as count FROM books")
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count") as usize;
 
-        match row {
-            Some(row) => {
-                let metadata = BookMetadata {
-                    book_id: row.get::<i32, _>("book_id") as u32,
-                    title: row.get("title"),
-                    author: row.get("author"),
-                    language: row.get("language"),
-                    year: row.get::<Option<i32>, _>("year").map(|y| y as u32),
-                    word_count: row.get::<i32, _>("word_count") as usize,
-                    unique_words: row.get::<i32, _>("unique_words") as usize,
-                };
-                Ok(Some(metadata))
+        let unique_words = sqlx::query("SELECT COUNT(DISTINCT word) as count FROM word_index")
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count") as usize;
+
+        Ok((total_books, unique_words))
+    }
+
+    async fn get_field_stats(&self) -> Result<HashMap<IndexField, usize>, StorageError> {
+        let rows = sqlx::query("SELECT field, COUNT(DISTINCT word) AS count FROM word_field_index GROUP BY field")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut stats: HashMap<IndexField, usize> = IndexField::ALL.iter().map(|&f| (f, 0)).collect();
+        for row in rows {
+            let field: String = row.get("field");
+            if let Some(&field) = IndexField::ALL.iter().find(|f| f.as_str() == field) {
+                stats.insert(field, row.get::<i64, _>("count") as usize);
             }
-            None => Ok(None),
         }
+        stats.insert(IndexField::Body, self.get_stats().await?.1);
+
+        Ok(stats)
     }
 
-    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
-        let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM books WHERE book_id = $1)")
+    async fn set_book_incomplete(&self, book_id: u32, incomplete: bool) -> Result<(), StorageError> {
+        let query = if incomplete {
+            "INSERT INTO incomplete_books (book_id) VALUES ($1) ON CONFLICT DO NOTHING"
+        } else {
+            "DELETE FROM incomplete_books WHERE book_id = $1"
+        };
+        sqlx::query(query)
             .bind(book_id as i32)
-            .fetch_one(&self.pool)
+            .execute(&self.pool)
             .await?;
+        touch_postgres_last_updated(&self.pool).await?;
 
-        Ok(row.get(0))
+        Ok(())
     }
 
-    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
-        let rows = sqlx::query("SELECT book_id FROM books")
+    async fn get_incomplete_books(&self) -> Result<HashSet<u32>, StorageError> {
+        let rows = sqlx::query("SELECT book_id FROM incomplete_books")
             .fetch_all(&self.pool)
             .await?;
 
-        let book_ids = rows
+        Ok(rows
             .into_iter()
             .map(|row| row.get::<i32, _>("book_id") as u32)
-            .collect();
+            .collect())
+    }
 
-        Ok(book_ids)
+    async fn save_rebuild_checkpoint(&self, last_completed: Option<u32>) -> Result<(), StorageError> {
+        match last_completed {
+            Some(book_id) => {
+                sqlx::query(
+                    "INSERT INTO index_jobs (job, last_completed) VALUES ('rebuild', $1) ON CONFLICT (job) DO UPDATE SET last_completed = EXCLUDED.last_completed"
+                )
+                .bind(book_id as i32)
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM index_jobs WHERE job = 'rebuild'")
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_rebuild_checkpoint(&self) -> Result<Option<u32>, StorageError> {
+        let row = sqlx::query("SELECT last_completed FROM index_jobs WHERE job = 'rebuild'")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.and_then(|row| row.get::<Option<i32>, _>("last_completed")).map(|id| id as u32))
     }
 
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError> {
+    async fn set_diacritic_folding(&self, folding: bool) -> Result<(), StorageError> {
         sqlx::query(
-            "INSERT INTO word_index (word, book_id) VALUES ($1, $2) ON CONFLICT (word, book_id) DO NOTHING"
+            "INSERT INTO index_settings (key, value) VALUES ('fold_diacritics', $1) ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value"
+        )
+        .bind(folding.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        let row = sqlx::query("SELECT value FROM index_settings WHERE key = 'fold_diacritics'")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get::<String, _>("value") == "true"))
+    }
+
+    async fn set_book_fingerprint(&self, book_id: u32, fingerprint: BookFingerprint) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO book_fingerprints (book_id, modified_ns, size) VALUES ($1, $2, $3) ON CONFLICT (book_id) DO UPDATE SET modified_ns = EXCLUDED.modified_ns, size = EXCLUDED.size"
         )
-        .bind(word)
         .bind(book_id as i32)
+        .bind(fingerprint.modified_ns as i64)
+        .bind(fingerprint.size as i64)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
-        let rows = sqlx::query("SELECT book_id FROM word_index WHERE word = $1")
-            .bind(word)
+    async fn get_book_fingerprints(&self) -> Result<HashMap<u32, BookFingerprint>, StorageError> {
+        let rows = sqlx::query("SELECT book_id, modified_ns, size FROM book_fingerprints")
             .fetch_all(&self.pool)
             .await?;
 
-        let book_ids = rows
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let fingerprint = BookFingerprint {
+                    modified_ns: row.get::<i64, _>("modified_ns") as u64,
+                    size: row.get::<i64, _>("size") as u64,
+                };
+                (row.get::<i32, _>("book_id") as u32, fingerprint)
+            })
+            .collect())
+    }
+
+    async fn delete_book(&self, book_id: u32) -> Result<bool, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted = sqlx::query("DELETE FROM books WHERE book_id = $1")
+            .bind(book_id as i32)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Ok(false);
+        }
+        sqlx::query("INSERT INTO deleted_books (book_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(book_id as i32)
+            .execute(&mut *tx)
+            .await?;
+        for table in ["book_fingerprints", "incomplete_books"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE book_id = $1", table))
+                .bind(book_id as i32)
+                .execute(&mut *tx)
+                .await?;
+        }
+        touch_postgres_last_updated(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn get_deleted_books(&self) -> Result<HashSet<u32>, StorageError> {
+        let rows = sqlx::query("SELECT book_id FROM deleted_books")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
             .into_iter()
             .map(|row| row.get::<i32, _>("book_id") as u32)
-            .collect();
+            .collect())
+    }
 
-        Ok(book_ids)
+    async fn clear_deleted_books(&self, book_ids: &[u32]) -> Result<(), StorageError> {
+        let book_ids: Vec<i32> = book_ids.iter().map(|&id| id as i32).collect();
+        sqlx::query("DELETE FROM deleted_books WHERE book_id = ANY($1)")
+            .bind(&book_ids)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
     }
 
-    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
-        let total_books = sqlx::query("SELECT COUNT(*) as count FROM books")
-            .fetch_one(&self.pool)
+    async fn remove_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        let book_ids: Vec<i32> = book_ids.iter().map(|&id| id as i32).collect();
+        let mut conn = self.pool.acquire().await?;
+        delete_postgres_postings(&mut conn, word, &book_ids).await
+    }
+
+    /// Share-locks the books' `deleted_books` rows for the transaction, so
+    /// storing one of them again waits until its postings are gone and a
+    /// book stored first is skipped.
+    async fn purge_deleted_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        let book_ids: Vec<i32> = book_ids.iter().map(|&id| id as i32).collect();
+        let mut tx = self.pool.begin().await?;
+
+        let still_deleted: Vec<i32> = sqlx::query("SELECT book_id FROM deleted_books WHERE book_id = ANY($1) FOR SHARE")
+            .bind(&book_ids)
+            .fetch_all(&mut *tx)
             .await?
-            .get::<i64, _>("count") as usize;
+            .into_iter()
+            .map(|row| row.get("book_id"))
+            .collect();
+        let removed = delete_postgres_postings(&mut tx, word, &still_deleted).await?;
+        tx.commit().await?;
 
-        let unique_words = sqlx::query("SELECT COUNT(DISTINCT word) as count FROM word_index")
-            .fetch_one(&self.pool)
+        Ok(removed)
+    }
+
+    async fn get_index_size_bytes(&self) -> Result<u64, StorageError> {
+        let bytes = sqlx::query(
+            r#"
+            SELECT COALESCE(SUM(pg_total_relation_size(oid)), 0)::BIGINT AS bytes
+            FROM pg_class
+            WHERE relkind = 'r' AND relname = ANY($1)
+            "#,
+        )
+        .bind(&INDEX_TABLES[..])
+        .fetch_one(&self.pool)
+        .await?
+        .get::<i64, _>("bytes");
+
+        Ok(bytes as u64)
+    }
+
+    async fn get_last_updated(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let updated_at = sqlx::query("SELECT updated_at FROM index_meta WHERE key = 'last_updated'")
+            .fetch_optional(&self.pool)
             .await?
-            .get::<i64, _>("count") as usize;
+            .map(|row| row.get::<NaiveDateTime, _>("updated_at").and_utc());
 
-        Ok((total_books, unique_words))
+        Ok(updated_at)
     }
 
     async fn test_connection(&self) -> Result<(), StorageError> {
@@ -410,3 +2034,382 @@ impl StorageBackend for PostgresBackend {
         Ok(())
     }
 }
+
+/// In-process implementation of the [`StorageBackend`] trait.
+///
+/// Keeps everything in hash maps behind shared locks, so clones of the
+/// backend see the same data. Nothing is persisted. The books and postings
+/// are kept in a [`MemoryIndex`], which the search service's memory backend
+/// can share (see [`MemoryBackend::with_index`]).
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    index: MemoryIndex,
+    state: Arc<RwLock<MemoryState>>,
+}
+
+/// What only the indexing service keeps besides the index.
+#[derive(Default)]
+struct MemoryState {
+    rebuild_checkpoint: Option<u32>,
+    fingerprints: HashMap<u32, BookFingerprint>,
+    deleted: HashSet<u32>,
+    /// word -> (other word, book) -> times seen together
+    cooccurrences: HashMap<String, HashMap<(String, u32), u32>>,
+}
+
+impl MemoryState {
+    /// Bytes of the stored keys and values, leaving out the maps' own overhead.
+    fn estimated_bytes(&self, index: &IndexState) -> u64 {
+        let postings = |word: &str, books: usize| (word.len() + books * size_of::<u32>()) as u64;
+
+        let books: u64 = index
+            .books
+            .values()
+            .map(|book| serde_json::to_vec(book).map_or(0, |json| json.len() as u64))
+            .sum();
+        let words: u64 = index.words.iter().map(|(word, books)| postings(word, books.len())).sum();
+        let field_words: u64 = index
+            .field_words
+            .iter()
+            .map(|((_, word), books)| postings(word, books.len()))
+            .sum();
+        let positions = |word: &str, positions: usize| (word.len() + positions * size_of::<(u32, usize)>()) as u64;
+        let chapter_words: u64 = index
+            .chapter_words
+            .iter()
+            .map(|(word, chapters)| positions(word, chapters.len()))
+            .sum();
+        let sentence_words: u64 = index
+            .sentence_words
+            .iter()
+            .map(|(word, sentences)| positions(word, sentences.len()))
+            .sum();
+        let cooccurrences: u64 = self
+            .cooccurrences
+            .iter()
+            .flat_map(|(word, counts)| counts.keys().map(move |(other, _)| (word.len() + other.len() + 8) as u64))
+            .sum();
+
+        books + words + field_words + chapter_words + sentence_words + cooccurrences
+    }
+}
+
+/// `remove_postings` against the index already locked.
+fn remove_postings(index: &mut IndexState, word: &str, book_ids: &[u32]) -> RemovedPostings {
+    let mut removed = RemovedPostings::default();
+
+    let mut remove_from = |postings: &mut HashSet<u32>| {
+        let before = postings.len();
+        postings.retain(|book_id| !book_ids.contains(book_id));
+        removed.postings += before - postings.len();
+        postings.is_empty()
+    };
+    let mut emptied = false;
+    if index.words.get_mut(word).is_some_and(&mut remove_from) {
+        index.words.remove(word);
+        emptied = true;
+    }
+    for field in [IndexField::Title, IndexField::Author] {
+        let key = (field, word.to_string());
+        if index.field_words.get_mut(&key).is_some_and(&mut remove_from) {
+            index.field_words.remove(&key);
+            emptied = true;
+        }
+    }
+    removed.word_removed = emptied
+        && !index.words.contains_key(word)
+        && !index.field_words.contains_key(&(IndexField::Author, word.to_string()));
+    for positions in [&mut index.chapter_words, &mut index.sentence_words] {
+        if let Some(postings) = positions.get_mut(word) {
+            let before = postings.len();
+            postings.retain(|(book_id, _)| !book_ids.contains(book_id));
+            removed.postings += before - postings.len();
+            if postings.is_empty() {
+                positions.remove(word);
+            }
+        }
+    }
+
+    removed
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A backend writing `index`, which the search service's memory backend
+    /// may share.
+    pub fn with_index(index: MemoryIndex) -> Self {
+        Self { index, ..Self::default() }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, MemoryState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, MemoryState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryBackend {
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        self.index.write().store_book(metadata);
+        self.write().deleted.remove(&metadata.book_id);
+        Ok(())
+    }
+
+    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
+        Ok(self.index.read().books.get(&book_id).cloned())
+    }
+
+    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
+        Ok(self.index.read().books.contains_key(&book_id))
+    }
+
+    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
+        Ok(self.index.read().books.keys().copied().collect())
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        self.add_postings(word, &[book_id], field).await
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        Ok(self.index.read().words.get(word).cloned().unwrap_or_default())
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        Ok(self.index.read().postings(word, field).cloned().unwrap_or_default())
+    }
+
+    async fn words_with_book(&self, words: &[String], book_id: u32) -> Result<HashSet<String>, StorageError> {
+        let state = self.index.read();
+        Ok(words
+            .iter()
+            .filter(|word| state.words.get(*word).is_some_and(|books| books.contains(&book_id)))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_postings(&self, words: &[String], field: IndexField) -> Result<HashMap<String, HashSet<u32>>, StorageError> {
+        let state = self.index.read();
+        Ok(words
+            .iter()
+            .filter_map(|word| Some((word.clone(), state.postings(word, field)?.clone())))
+            .collect())
+    }
+
+    async fn add_postings(&self, word: &str, book_ids: &[u32], field: IndexField) -> Result<(), StorageError> {
+        self.index.write().add_postings(word, book_ids, field);
+        Ok(())
+    }
+
+    async fn scan_words(&self, field: IndexField, cursor: Option<String>, count: usize) -> Result<(Vec<String>, Option<String>), StorageError> {
+        let state = self.index.read();
+        let mut words: Vec<&String> = state
+            .vocabulary(field)
+            .filter(|word| cursor.as_ref().is_none_or(|c| *word > c))
+            .collect();
+        words.sort_unstable();
+        words.truncate(count);
+
+        let words: Vec<String> = words.into_iter().cloned().collect();
+        let next = if words.len() == count { words.last().cloned() } else { None };
+
+        Ok((words, next))
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        self.index.write()
+            .chapter_words
+            .entry(word.to_string())
+            .or_default()
+            .insert((book_id, chapter_no));
+        Ok(())
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        Ok(self.index.read().chapter_words.get(word).cloned().unwrap_or_default())
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        self.index.write()
+            .sentence_words
+            .entry(word.to_string())
+            .or_default()
+            .insert((book_id, sentence_id));
+        Ok(())
+    }
+
+    async fn search_word_sentences(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        Ok(self.index.read().sentence_words.get(word).cloned().unwrap_or_default())
+    }
+
+    async fn add_cooccurrence(&self, word_a: &str, word_b: &str, book_id: u32, count: u32) -> Result<(), StorageError> {
+        let mut state = self.write();
+        for (word, other) in [(word_a, word_b), (word_b, word_a)] {
+            state
+                .cooccurrences
+                .entry(word.to_string())
+                .or_default()
+                .insert((other.to_string(), book_id), count);
+        }
+        Ok(())
+    }
+
+    async fn get_cooccurrences(&self, word: &str, n: usize) -> Result<Vec<(String, u64)>, StorageError> {
+        let state = self.read();
+        let counts = state
+            .cooccurrences
+            .get(word)
+            .into_iter()
+            .flatten()
+            .map(|((other, _), &count)| (other.clone(), u64::from(count)));
+        Ok(top_cooccurrences(counts, n))
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        let state = self.index.read();
+        Ok((state.books.len(), state.words.len()))
+    }
+
+    async fn get_field_stats(&self) -> Result<HashMap<IndexField, usize>, StorageError> {
+        let state = self.index.read();
+        let mut stats: HashMap<IndexField, usize> = IndexField::ALL.iter().map(|&f| (f, 0)).collect();
+        for (field, _) in state.field_words.keys() {
+            *stats.entry(*field).or_default() += 1;
+        }
+        stats.insert(IndexField::Body, state.words.len());
+        Ok(stats)
+    }
+
+    async fn set_book_incomplete(&self, book_id: u32, incomplete: bool) -> Result<(), StorageError> {
+        self.index.write().set_incomplete(book_id, incomplete);
+        Ok(())
+    }
+
+    async fn get_incomplete_books(&self) -> Result<HashSet<u32>, StorageError> {
+        Ok(self.index.read().incomplete.clone())
+    }
+
+    async fn save_rebuild_checkpoint(&self, last_completed: Option<u32>) -> Result<(), StorageError> {
+        self.write().rebuild_checkpoint = last_completed;
+        Ok(())
+    }
+
+    async fn get_rebuild_checkpoint(&self) -> Result<Option<u32>, StorageError> {
+        Ok(self.read().rebuild_checkpoint)
+    }
+
+    async fn set_diacritic_folding(&self, folding: bool) -> Result<(), StorageError> {
+        self.index.write().diacritic_folding = Some(folding);
+        Ok(())
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        Ok(self.index.read().diacritic_folding)
+    }
+
+    async fn set_book_fingerprint(&self, book_id: u32, fingerprint: BookFingerprint) -> Result<(), StorageError> {
+        self.write().fingerprints.insert(book_id, fingerprint);
+        Ok(())
+    }
+
+    async fn get_book_fingerprints(&self) -> Result<HashMap<u32, BookFingerprint>, StorageError> {
+        Ok(self.read().fingerprints.clone())
+    }
+
+    async fn delete_book(&self, book_id: u32) -> Result<bool, StorageError> {
+        if !self.index.write().remove_book(book_id) {
+            return Ok(false);
+        }
+        let mut state = self.write();
+        state.deleted.insert(book_id);
+        state.fingerprints.remove(&book_id);
+        Ok(true)
+    }
+
+    async fn get_deleted_books(&self) -> Result<HashSet<u32>, StorageError> {
+        Ok(self.read().deleted.clone())
+    }
+
+    async fn clear_deleted_books(&self, book_ids: &[u32]) -> Result<(), StorageError> {
+        let mut state = self.write();
+        for book_id in book_ids {
+            state.deleted.remove(book_id);
+        }
+        Ok(())
+    }
+
+    async fn remove_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        Ok(remove_postings(&mut self.index.write(), word, book_ids))
+    }
+
+    async fn purge_deleted_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        let mut index = self.index.write();
+        let state = self.read();
+        let book_ids: Vec<u32> = book_ids.iter().copied().filter(|id| state.deleted.contains(id)).collect();
+        Ok(remove_postings(&mut index, word, &book_ids))
+    }
+
+    async fn get_index_size_bytes(&self) -> Result<u64, StorageError> {
+        Ok(self.read().estimated_bytes(&self.index.read()))
+    }
+
+    async fn get_last_updated(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        Ok(self.index.read().last_updated)
+    }
+
+    async fn test_connection(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use redis::cluster_routing::get_slot;
+
+    #[test]
+    fn word_keys_spread_across_cluster_slots() {
+        let words = ["love", "whale", "ocean", "monster", "prejudice", "pride"];
+        let slots: HashSet<u16> = words
+            .iter()
+            .map(|word| get_slot(format!("word:{}", word).as_bytes()))
+            .collect();
+        assert_eq!(slots.len(), words.len());
+
+        let field_key = RedisBackend::field_key("love", IndexField::Title);
+        assert!(!field_key.contains('{'), "hash tags would pin words to one slot");
+    }
+
+    #[test]
+    fn used_memory_is_read_from_info() {
+        let info = "# Memory\r\nused_memory:1048576\r\nused_memory_human:1.00M\r\nused_memory_rss:2097152\r\n";
+        assert_eq!(parse_used_memory(info), Some(1_048_576));
+        assert_eq!(parse_used_memory("# Memory\r\n"), None);
+    }
+
+    #[test]
+    fn sampled_sizes_scale_to_the_whole_index() {
+        assert_eq!(extrapolate(5_000, 500, 20_000), 200_000);
+        assert_eq!(extrapolate(300, 3, 3), 300);
+        assert_eq!(extrapolate(0, 0, 0), 0);
+    }
+
+    #[tokio::test]
+    async fn memory_index_size_grows_with_postings() {
+        let backend = MemoryBackend::new();
+        assert_eq!(backend.get_index_size_bytes().await.unwrap(), 0);
+
+        backend.add_word_to_index("whale", 2701, IndexField::Body).await.unwrap();
+        let one = backend.get_index_size_bytes().await.unwrap();
+        assert!(one > 0);
+
+        backend.add_word_to_index("whale", 84, IndexField::Body).await.unwrap();
+        assert_eq!(backend.get_index_size_bytes().await.unwrap(), one + 4);
+    }
+}
@@ -0,0 +1,445 @@
+//! Sharded Storage
+//!
+//! Spreads the index over several backends for corpora too large for one
+//! Redis instance. Each word, with all its postings, lives on exactly one
+//! shard, picked by consistent hashing on the word, so a lookup touches a
+//! single shard. Book metadata and book-level bookkeeping are small and
+//! replicated to every shard; reads of them go to the first shard.
+//!
+//! Configured with `SHARD_URLS=redis://r1:6379,redis://r2:6379`. The search
+//! service must list the same shards in the same order, since a word's shard
+//! depends on the shard's position in the list.
+
+use super::{
+    BookFingerprint, BookMetadata, IndexField, RedisBackend, RemovedPostings, StorageBackend, StorageError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common_redis::{RedisConfig, RedisMode};
+use hashring::HashRing;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Points each shard gets on the hash ring; more points spread words more
+/// evenly.
+const VIRTUAL_NODES_PER_SHARD: usize = 64;
+
+pub type Shard = Arc<dyn StorageBackend + Send + Sync>;
+
+/// A point on the hash ring belonging to shard `shard`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq)]
+struct VirtualNode {
+    shard: usize,
+    replica: usize,
+}
+
+/// A [`StorageBackend`] over several shards; see the module docs for what
+/// lives where.
+#[derive(Clone)]
+pub struct ShardedBackend {
+    shards: Vec<Shard>,
+    ring: Arc<HashRing<VirtualNode>>,
+}
+
+impl ShardedBackend {
+    /// # Panics
+    /// If `shards` is empty.
+    pub fn new(shards: Vec<Shard>) -> Self {
+        assert!(!shards.is_empty(), "a sharded backend needs at least one shard");
+        let mut ring = HashRing::new();
+        for shard in 0..shards.len() {
+            for replica in 0..VIRTUAL_NODES_PER_SHARD {
+                ring.add(VirtualNode { shard, replica });
+            }
+        }
+        Self {
+            shards,
+            ring: Arc::new(ring),
+        }
+    }
+
+    /// One standalone Redis backend per URL in `SHARD_URLS`, sharing the
+    /// `REDIS_*` credentials and TLS settings. `None` when it isn't set.
+    pub fn from_env() -> Option<Result<Self, StorageError>> {
+        let urls = std::env::var("SHARD_URLS").ok().filter(|urls| !urls.trim().is_empty())?;
+        let base = RedisConfig::from_env();
+        let shards = urls
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                let config = RedisConfig {
+                    nodes: vec![url.to_string()],
+                    mode: RedisMode::Standalone,
+                    ..base.clone()
+                };
+                Ok(Arc::new(RedisBackend::from_config(&config)?) as Shard)
+            })
+            .collect::<Result<Vec<_>, StorageError>>();
+        Some(shards.map(Self::new))
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Position of the shard holding `word`.
+    pub fn shard_index(&self, word: &str) -> usize {
+        self.ring.get(&word).map_or(0, |node| node.shard)
+    }
+
+    fn shard_for(&self, word: &str) -> &Shard {
+        &self.shards[self.shard_index(word)]
+    }
+
+    /// `words` split by the shard holding them, indexed like `shards`.
+    fn group_by_shard(&self, words: &[String]) -> Vec<Vec<String>> {
+        let mut by_shard = vec![Vec::new(); self.shards.len()];
+        for word in words {
+            by_shard[self.shard_index(word)].push(word.clone());
+        }
+        by_shard
+    }
+
+    /// Where replicated data is read from.
+    fn primary(&self) -> &Shard {
+        &self.shards[0]
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ShardedBackend {
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.store_book_metadata(metadata).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
+        self.primary().get_book_metadata(book_id).await
+    }
+
+    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
+        self.primary().is_book_indexed(book_id).await
+    }
+
+    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
+        self.primary().get_indexed_books().await
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        self.shard_for(word).add_word_to_index(word, book_id, field).await
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        self.shard_for(word).search_word(word).await
+    }
+
+    async fn get_books_for_word(&self, word: &str) -> Result<Vec<u32>, StorageError> {
+        self.shard_for(word).get_books_for_word(word).await
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        self.shard_for(word).get_books_for_word_in_field(word, field).await
+    }
+
+    /// Asks each shard only about the words it holds.
+    async fn words_with_book(&self, words: &[String], book_id: u32) -> Result<HashSet<String>, StorageError> {
+        let mut present = HashSet::new();
+        for (shard, words) in self.shards.iter().zip(self.group_by_shard(words)) {
+            if !words.is_empty() {
+                present.extend(shard.words_with_book(&words, book_id).await?);
+            }
+        }
+        Ok(present)
+    }
+
+    /// Reads each shard's postings for only the words it holds.
+    async fn get_postings(&self, words: &[String], field: IndexField) -> Result<HashMap<String, HashSet<u32>>, StorageError> {
+        let mut postings = HashMap::new();
+        for (shard, words) in self.shards.iter().zip(self.group_by_shard(words)) {
+            if !words.is_empty() {
+                postings.extend(shard.get_postings(&words, field).await?);
+            }
+        }
+        Ok(postings)
+    }
+
+    async fn add_postings(&self, word: &str, book_ids: &[u32], field: IndexField) -> Result<(), StorageError> {
+        self.shard_for(word).add_postings(word, book_ids, field).await
+    }
+
+    /// Scans the shards one after the other. The cursor is
+    /// `{shard}:{shard's own cursor}`, empty after the colon at a shard's start.
+    async fn scan_words(&self, field: IndexField, cursor: Option<String>, count: usize) -> Result<(Vec<String>, Option<String>), StorageError> {
+        let (mut shard, mut inner) = match cursor.as_deref().and_then(|c| c.split_once(':')) {
+            Some((shard, inner)) => (
+                shard.parse::<usize>().unwrap_or(0),
+                Some(inner.to_string()).filter(|inner| !inner.is_empty()),
+            ),
+            None => (0, None),
+        };
+
+        while shard < self.shards.len() {
+            let (words, next) = self.shards[shard].scan_words(field, inner.take(), count).await?;
+            let next = match next {
+                Some(next) => Some(format!("{}:{}", shard, next)),
+                None if shard + 1 < self.shards.len() => Some(format!("{}:", shard + 1)),
+                None => None,
+            };
+            // An empty page from a shard moves straight on to the next one
+            if !words.is_empty() || next.is_none() {
+                return Ok((words, next));
+            }
+            let (next_shard, next_inner) = next.as_deref().and_then(|c| c.split_once(':')).unwrap_or_default();
+            shard = next_shard.parse().unwrap_or(self.shards.len());
+            inner = Some(next_inner.to_string()).filter(|inner| !inner.is_empty());
+        }
+        Ok((Vec::new(), None))
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        self.shard_for(word).add_word_to_chapter_index(word, book_id, chapter_no).await
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.shard_for(word).search_word_chapters(word).await
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        self.shard_for(word).add_word_to_sentence_index(word, book_id, sentence_id).await
+    }
+
+    async fn search_word_sentences(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.shard_for(word).search_word_sentences(word).await
+    }
+
+    /// Stored on both words' shards, so either one finds the pair.
+    async fn add_cooccurrence(&self, word_a: &str, word_b: &str, book_id: u32, count: u32) -> Result<(), StorageError> {
+        let (a, b) = (self.shard_index(word_a), self.shard_index(word_b));
+        self.shards[a].add_cooccurrence(word_a, word_b, book_id, count).await?;
+        if b != a {
+            self.shards[b].add_cooccurrence(word_a, word_b, book_id, count).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_cooccurrences(&self, word: &str, n: usize) -> Result<Vec<(String, u64)>, StorageError> {
+        self.shard_for(word).get_cooccurrences(word, n).await
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        let (total_books, mut unique_words) = self.primary().get_stats().await?;
+        for shard in &self.shards[1..] {
+            unique_words += shard.get_stats().await?.1;
+        }
+        Ok((total_books, unique_words))
+    }
+
+    async fn get_field_stats(&self) -> Result<HashMap<IndexField, usize>, StorageError> {
+        let mut stats = HashMap::new();
+        for shard in &self.shards {
+            for (field, words) in shard.get_field_stats().await? {
+                *stats.entry(field).or_default() += words;
+            }
+        }
+        Ok(stats)
+    }
+
+    async fn set_book_incomplete(&self, book_id: u32, incomplete: bool) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.set_book_incomplete(book_id, incomplete).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_incomplete_books(&self) -> Result<HashSet<u32>, StorageError> {
+        self.primary().get_incomplete_books().await
+    }
+
+    async fn save_rebuild_checkpoint(&self, last_completed: Option<u32>) -> Result<(), StorageError> {
+        self.primary().save_rebuild_checkpoint(last_completed).await
+    }
+
+    async fn get_rebuild_checkpoint(&self) -> Result<Option<u32>, StorageError> {
+        self.primary().get_rebuild_checkpoint().await
+    }
+
+    async fn set_diacritic_folding(&self, folding: bool) -> Result<(), StorageError> {
+        self.primary().set_diacritic_folding(folding).await
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        self.primary().get_diacritic_folding().await
+    }
+
+    async fn set_book_fingerprint(&self, book_id: u32, fingerprint: BookFingerprint) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.set_book_fingerprint(book_id, fingerprint).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_book_fingerprints(&self) -> Result<HashMap<u32, BookFingerprint>, StorageError> {
+        self.primary().get_book_fingerprints().await
+    }
+
+    async fn delete_book(&self, book_id: u32) -> Result<bool, StorageError> {
+        let deleted = self.primary().delete_book(book_id).await?;
+        for shard in &self.shards[1..] {
+            shard.delete_book(book_id).await?;
+        }
+        Ok(deleted)
+    }
+
+    async fn get_deleted_books(&self) -> Result<HashSet<u32>, StorageError> {
+        self.primary().get_deleted_books().await
+    }
+
+    async fn clear_deleted_books(&self, book_ids: &[u32]) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.clear_deleted_books(book_ids).await?;
+        }
+        Ok(())
+    }
+
+    async fn remove_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        self.shard_for(word).remove_postings(word, book_ids).await
+    }
+
+    /// Every shard keeps its own copy of the deleted books, so the word's
+    /// shard checks them together with the removal.
+    async fn purge_deleted_postings(&self, word: &str, book_ids: &[u32]) -> Result<RemovedPostings, StorageError> {
+        self.shard_for(word).purge_deleted_postings(word, book_ids).await
+    }
+
+    async fn get_index_size_bytes(&self) -> Result<u64, StorageError> {
+        let mut bytes = 0;
+        for shard in &self.shards {
+            bytes += shard.get_index_size_bytes().await?;
+        }
+        Ok(bytes)
+    }
+
+    async fn get_last_updated(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        self.primary().get_last_updated().await
+    }
+
+    async fn test_connection(&self) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.test_connection().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+
+    fn three_shards() -> (ShardedBackend, Vec<MemoryBackend>) {
+        let memories = vec![MemoryBackend::new(), MemoryBackend::new(), MemoryBackend::new()];
+        let shards = memories.iter().map(|memory| Arc::new(memory.clone()) as Shard).collect();
+        (ShardedBackend::new(shards), memories)
+    }
+
+    fn metadata(book_id: u32) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: format!("Book {}", book_id),
+            author: "Herman Melville".to_string(),
+            language: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    const WORDS: [&str; 8] = ["whale", "ship", "ocean", "harpoon", "captain", "sailor", "island", "storm"];
+
+    #[tokio::test]
+    async fn routes_each_word_to_one_shard() {
+        let (sharded, memories) = three_shards();
+        for word in WORDS {
+            sharded.add_word_to_index(word, 2701, IndexField::Body).await.unwrap();
+        }
+
+        for word in WORDS {
+            let owner = sharded.shard_index(word);
+            for (i, memory) in memories.iter().enumerate() {
+                let books = memory.search_word(word).await.unwrap();
+                assert_eq!(books.contains(&2701), i == owner, "'{}' on shard {}", word, i);
+            }
+            assert_eq!(sharded.get_books_for_word(word).await.unwrap(), [2701]);
+        }
+
+        // The words are spread over more than one shard, and counted once
+        let used: HashSet<usize> = WORDS.iter().map(|word| sharded.shard_index(word)).collect();
+        assert!(used.len() > 1);
+        assert_eq!(sharded.get_stats().await.unwrap().1, WORDS.len());
+    }
+
+    #[tokio::test]
+    async fn replicates_metadata_to_every_shard() {
+        let (sharded, memories) = three_shards();
+        sharded.store_book_metadata(&metadata(2701)).await.unwrap();
+
+        for memory in &memories {
+            assert!(memory.is_book_indexed(2701).await.unwrap());
+        }
+        assert_eq!(sharded.get_book_metadata(2701).await.unwrap().unwrap().title, "Book 2701");
+        assert_eq!(sharded.get_stats().await.unwrap().0, 1);
+    }
+
+    /// The search service pins the same placements; if these change, the
+    /// two services no longer agree on where a word lives.
+    #[test]
+    fn placement_matches_the_search_service() {
+        let (sharded, _) = three_shards();
+        let placement: Vec<usize> = WORDS.iter().map(|word| sharded.shard_index(word)).collect();
+        assert_eq!(placement, [0, 0, 1, 1, 1, 0, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn scans_the_vocabulary_of_every_shard() {
+        let (sharded, _) = three_shards();
+        for word in WORDS {
+            sharded.add_word_to_index(word, 2701, IndexField::Body).await.unwrap();
+        }
+
+        let mut seen = HashSet::new();
+        let mut cursor = None;
+        loop {
+            let (words, next) = sharded.scan_words(IndexField::Body, cursor, 2).await.unwrap();
+            seen.extend(words);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, WORDS.iter().map(|word| word.to_string()).collect());
+    }
+
+    #[tokio::test]
+    async fn membership_checks_reach_every_shard() {
+        let (sharded, _) = three_shards();
+        for word in WORDS {
+            sharded.add_word_to_index(word, 2701, IndexField::Body).await.unwrap();
+        }
+
+        let mut words: Vec<String> = WORDS.iter().map(|word| word.to_string()).collect();
+        words.push("kraken".to_string());
+        let present = sharded.words_with_book(&words, 2701).await.unwrap();
+        assert_eq!(present, WORDS.iter().map(|word| word.to_string()).collect());
+        assert!(sharded.words_with_book(&words, 84).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cooccurrences_are_found_from_either_word() {
+        let (sharded, _) = three_shards();
+        sharded.add_cooccurrence("whale", "harpoon", 2701, 3).await.unwrap();
+
+        assert_eq!(sharded.get_cooccurrences("whale", 5).await.unwrap(), [("harpoon".to_string(), 3)]);
+        assert_eq!(sharded.get_cooccurrences("harpoon", 5).await.unwrap(), [("whale".to_string(), 3)]);
+    }
+}
@@ -1,16 +1,196 @@
-//! Health Check Endpoint
+//! Health Check Endpoints
 //!
-//! Provides a simple endpoint to verify that the **Indexing Service** is
-//! operational.
+//! Liveness, readiness and startup probes for the **Indexing Service**.
+//! Each answers `{"probe": ..., "ok": ...}`, with `503` when not ok.
 //!
-//! **GET /status**
-//! → Returns `{"service": "indexing-service", "status": "running"}`
-use crate::models::responses::HealthResponse;
-use axum::response::Json;
+//! **GET /status** (liveness)
+//! → Always `200` while the process answers, with the backend's type,
+//! connection state and round-trip time, whether the datalake is readable
+//! and the seconds since startup. `"status"` is `"degraded"` while the
+//! backend is unreachable, but a restart wouldn't fix that
+//!
+//! **GET /ready** (readiness)
+//! → `200` while the backend is connected and the datalake readable
+//!
+//! **GET /startup** (startup)
+//! → `200` once the backend has answered for the first time
+//!
+//! **GET /metrics**
+//! → Returns Prometheus metrics in the text exposition format
+use crate::models::responses::{HealthResponse, ProbeResponse};
+use crate::models::storage::{Backend, StorageBackend};
+use crate::services::metrics::metrics;
+use crate::services::startup::StartupProbe;
+use crate::utils::file::Datalake;
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    response::Json,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How long the backend may take to answer before it counts as unreachable.
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn health_check(
+    State(backend): State<Backend>,
+    State(started_at): State<Arc<Instant>>,
+    State(startup): State<StartupProbe>,
+    State(datalake): State<Datalake>,
+) -> Json<HealthResponse> {
+    let (connected, backend_latency_ms) = check_backend(&backend, &startup).await;
+    let (status, backend_status) = if connected {
+        ("running", "connected")
+    } else {
+        ("degraded", "unavailable")
+    };
 
-pub async fn health_check() -> Json<HealthResponse> {
     Json(HealthResponse {
         service: "indexing-service".to_string(),
-        status: "running".to_string(),
+        probe: "liveness".to_string(),
+        ok: true,
+        status: status.to_string(),
+        backend: backend_status.to_string(),
+        backend_connected: connected,
+        backend_type: backend.kind().to_string(),
+        backend_latency_ms,
+        datalake_accessible: datalake_accessible(&datalake).await,
+        uptime_secs: started_at.elapsed().as_secs(),
     })
-}
\ No newline at end of file
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn readiness_check(
+    State(backend): State<Backend>,
+    State(startup): State<StartupProbe>,
+    State(datalake): State<Datalake>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let (connected, _) = check_backend(&backend, &startup).await;
+    probe_response("readiness", connected && datalake_accessible(&datalake).await)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn startup_check(
+    State(backend): State<Backend>,
+    State(startup): State<StartupProbe>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let started = startup.is_complete() || check_backend(&backend, &startup).await.0;
+    probe_response("startup", started)
+}
+
+/// Whether the backend answers a connection test within
+/// [`HEALTH_CHECK_TIMEOUT`], and the milliseconds it took to find out. The
+/// first success completes `startup`.
+async fn check_backend(backend: &Backend, startup: &StartupProbe) -> (bool, f64) {
+    let probe_started = Instant::now();
+    let connected = match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, backend.test_connection()).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            warn!("Health check: storage backend unavailable: {}", e);
+            false
+        }
+        Err(_) => {
+            warn!("Health check: storage backend didn't answer within {:?}", HEALTH_CHECK_TIMEOUT);
+            false
+        }
+    };
+    if connected {
+        startup.complete();
+    }
+    (connected, probe_started.elapsed().as_secs_f64() * 1000.0)
+}
+
+async fn datalake_accessible(datalake: &Datalake) -> bool {
+    tokio::fs::read_dir(datalake.path()).await.is_ok()
+}
+
+fn probe_response(probe: &str, ok: bool) -> (StatusCode, Json<ProbeResponse>) {
+    let code = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        code,
+        Json(ProbeResponse {
+            service: "indexing-service".to_string(),
+            probe: probe.to_string(),
+            ok,
+        }),
+    )
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn metrics_endpoint() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_redis::RedisConfig;
+    use crate::models::storage::{MemoryBackend, RedisBackend};
+
+    fn unreachable_redis() -> Backend {
+        // Nothing listens on port 1
+        Backend::Redis(RedisBackend::from_config(&RedisConfig::new("redis://127.0.0.1:1", None)).unwrap())
+    }
+
+    #[tokio::test]
+    async fn reports_the_backend_and_uptime() {
+        let started_at = Arc::new(Instant::now() - Duration::from_secs(90));
+        let Json(health) = health_check(
+            State(Backend::Memory(MemoryBackend::new())),
+            State(started_at),
+            State(StartupProbe::new()),
+            State(Datalake::default()),
+        )
+        .await;
+
+        assert_eq!(health.probe, "liveness");
+        assert!(health.ok);
+        assert!(health.backend_connected);
+        assert_eq!(health.backend_type, "memory");
+        assert!(health.uptime_secs >= 90);
+    }
+
+    #[tokio::test]
+    async fn unreachable_backend_is_alive_but_not_ready() {
+        let Json(health) = health_check(
+            State(unreachable_redis()),
+            State(Arc::new(Instant::now())),
+            State(StartupProbe::new()),
+            State(Datalake::default()),
+        )
+        .await;
+        assert!(health.ok);
+        assert_eq!(health.status, "degraded");
+        assert!(!health.backend_connected);
+        assert_eq!(health.backend_type, "redis");
+
+        let (code, Json(ready)) = readiness_check(State(unreachable_redis()), State(StartupProbe::new()), State(Datalake::default())).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(ready.probe, "readiness");
+        assert!(!ready.ok);
+    }
+
+    #[tokio::test]
+    async fn startup_completes_on_the_first_successful_check() {
+        let startup = StartupProbe::new();
+
+        let (code, Json(probe)) = startup_check(State(unreachable_redis()), State(startup.clone())).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(probe.probe, "startup");
+
+        let (code, _) = startup_check(State(Backend::Memory(MemoryBackend::new())), State(startup.clone())).await;
+        assert_eq!(code, StatusCode::OK);
+        assert!(startup.is_complete());
+
+        // Once started, a later outage doesn't fail the startup probe
+        let (code, _) = startup_check(State(unreachable_redis()), State(startup)).await;
+        assert_eq!(code, StatusCode::OK);
+    }
+}
@@ -4,122 +4,820 @@
 //! - Indexing a single book by ID
 //! - Rebuilding the entire index from the datalake
 //! - Retrieving current index statistics
+//! - Listing the chapters detected in a book
+//! - Verifying that a book's words are all present in the index
+//! - Listing the books a word maps to, for debugging search results
+//! - Listing the words most often found near a word
+//! - Exporting and importing the whole index as NDJSON
+//! - Listing the indexed books
+//! - Listing and re-indexing books whose datalake files changed since indexing
+//! - Deleting books and compacting their postings out of the index
 //!
 //! It interacts with a pluggable [`StorageBackend`] (e.g., Redis or Postgres)
-//! and uses the [`process_book`] function from the indexing service for core logic.
+//! and uses the [`process_book_in`] function from the indexing service for core logic.
+//! Failures are returned as [`AppError`]s.
 
-use crate::models::responses::{IndexResponse, IndexStatusResponse, RebuildResponse};
+use crate::error::AppError;
+use crate::models::responses::{
+    ChapterInfo, ChapterListResponse, CompactionStatus, CooccurrenceEntry, CooccurrenceResponse, DeleteBookResponse, ImportResponse, IndexResponse,
+    IndexStatusResponse, IndexedBooksResponse, RebuildResponse, RefreshStaleResponse, StaleBooksResponse,
+    VerificationResult, WordBook, WordBooksResponse,
+};
 use crate::models::storage::{Backend, StorageBackend};
-use crate::services::indexing::process_book;
-use crate::utils::file::DATALAKE_PATH;
-use axum::{extract::Path, http::StatusCode, response::Json};
-use chrono::Utc;
+use crate::services::backpressure::IndexingLimiter;
+use crate::services::compaction::Compactor;
+use crate::services::indexing::{process_book_in, rebuild_books_in, rebuild_from_datalake};
+use crate::services::shutdown::Shutdown;
+use crate::services::staleness::{find_stale_books, refresh_stale_books};
+use crate::services::transfer::{export_index, ImportError, ImportSummary};
+use crate::services::verification::verify_book_index;
+use crate::utils::chapter::detect_chapters;
+use crate::utils::conditional::Validators;
+use crate::utils::file::{find_book_files_in, Datalake};
+use crate::utils::text::check_indexable;
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::Deserialize;
 use std::fs;
 use tracing::{error, info, warn};
 
+#[derive(Debug, Deserialize)]
+pub struct IndexParams {
+    #[serde(default)]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebuildParams {
+    /// Continue after the last checkpointed book instead of starting over.
+    #[serde(default)]
+    pub resume: bool,
+}
+
+/// Optional JSON body of `POST /index/rebuild`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RebuildRequest {
+    /// Only re-index these books; every book when absent.
+    pub book_ids: Option<Vec<u32>>,
+}
+
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 200;
+
+/// Function words found in nearly every book. They are indexed like any
+/// other word, but `/index/words` refuses them: listing most of the corpus
+/// page by page says nothing about why a search matched.
+const STOP_WORDS: &[&str] = &[
+    "about", "all", "and", "any", "are", "been", "but", "can", "could", "did", "for", "from",
+    "had", "has", "have", "her", "him", "his", "how", "into", "its", "not", "our", "out",
+    "she", "that", "the", "their", "them", "then", "there", "these", "they", "this", "those",
+    "was", "were", "what", "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct PageParams {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+const BYTES_PER_MB: f64 = 1_048_576.0;
+
+const DEFAULT_COOCCURRENCES: usize = 10;
+const MAX_COOCCURRENCES: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CooccurrenceParams {
+    pub word: String,
+    pub n: Option<usize>,
+}
+
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
 pub async fn index_book(
     Path(book_id): Path<u32>,
+    Query(params): Query<IndexParams>,
     axum::extract::State(backend): axum::extract::State<Backend>,
-) -> Result<Json<IndexResponse>, StatusCode> {
+    axum::extract::State(limiter): axum::extract::State<IndexingLimiter>,
+    axum::extract::State(shutdown): axum::extract::State<Shutdown>,
+    axum::extract::State(datalake): axum::extract::State<Datalake>,
+) -> Result<Json<IndexResponse>, AppError> {
+    if shutdown.is_triggered() {
+        return Err(AppError::ShuttingDown);
+    }
+    if find_book_files_in(datalake.path(), book_id).is_none() {
+        return Err(AppError::BookNotFound(book_id));
+    }
+    let _permit = limiter.acquire().await.inspect_err(|_| {
+        warn!("Rejecting indexing of book {}: too many in flight", book_id);
+    })?;
     info!("Indexing book {}", book_id);
 
-    match process_book(book_id, &backend).await {
-        Ok(()) => Ok(Json(IndexResponse {
+    match process_book_in(datalake.path(), book_id, &backend).await {
+        Ok(report) => Ok(Json(IndexResponse {
             book_id,
             status: "updated".to_string(),
+            word_count: report.word_count,
+            unique_words: report.unique_words,
+            elapsed_ms: report.elapsed_ms,
+            timings_ms: params.verbose.then_some(report.timings),
         })),
         Err(e) => {
             error!("Failed to index book {}: {}", book_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(AppError::Internal(format!("Failed to index book {}: {}", book_id, e)))
         }
     }
 }
 
+/// Rebuilds the index from the datalake. The rebuild indexes one book at a
+/// time, so it holds a single indexing slot for its whole duration.
+///
+/// A body of `{"book_ids": [84, 1342]}` re-indexes only those books,
+/// skipping (and listing in `skipped_not_found`) any not in the datalake;
+/// no body rebuilds everything. A malformed body is a `400`, as is
+/// `?resume=true` with `book_ids`.
+///
+/// A full rebuild cut short by shutdown reports `interrupted: true`;
+/// calling it again with `?resume=true` skips the books already completed.
+#[tracing::instrument(skip_all, err(level = "warn"))]
 pub async fn rebuild_index(
+    Query(params): Query<RebuildParams>,
     axum::extract::State(backend): axum::extract::State<Backend>,
-) -> Result<Json<RebuildResponse>, StatusCode> {
+    axum::extract::State(limiter): axum::extract::State<IndexingLimiter>,
+    axum::extract::State(shutdown): axum::extract::State<Shutdown>,
+    axum::extract::State(datalake): axum::extract::State<Datalake>,
+    body: Bytes,
+) -> Result<Json<RebuildResponse>, AppError> {
+    if shutdown.is_triggered() {
+        return Err(AppError::ShuttingDown);
+    }
+    let request: RebuildRequest = if body.iter().all(u8::is_ascii_whitespace) {
+        RebuildRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| AppError::InvalidRequest(format!("Invalid rebuild request: {}", e)))?
+    };
+    if params.resume && request.book_ids.is_some() {
+        return Err(AppError::InvalidRequest(
+            "resume only applies to a full rebuild; leave out book_ids".to_string(),
+        ));
+    }
+    let _permit = limiter.acquire().await.inspect_err(|_| {
+        warn!("Rejecting index rebuild: too many indexing operations in flight");
+    })?;
+
+    let book_ids = request.book_ids.as_deref();
+    let response = run_rebuild(&datalake, &backend, &shutdown, params.resume, book_ids).await.map_err(|e| {
+        error!("Index rebuild failed: {}", e);
+        AppError::Internal(format!("Index rebuild failed: {}", e))
+    })?;
+
+    Ok(Json(response))
+}
+
+/// Rebuilds the index from `datalake`, or only `book_ids` when given, and
+/// summarizes the outcome. The caller holds the indexing slot; shared with
+/// the gRPC `RebuildIndex`.
+pub async fn run_rebuild(
+    datalake: &Datalake,
+    backend: &Backend,
+    shutdown: &Shutdown,
+    resume: bool,
+    book_ids: Option<&[u32]>,
+) -> Result<RebuildResponse, Box<dyn std::error::Error + Send + Sync>> {
     let start_time = std::time::Instant::now();
-    info!("Starting index rebuild");
-
-    let mut books_processed = 0;
-
-    if let Ok(entries) = fs::read_dir(DATALAKE_PATH) {
-        for date_entry in entries.flatten() {
-            if date_entry
-                .file_type()
-                .map(|ft| ft.is_dir())
-                .unwrap_or(false)
-            {
-                if let Ok(subdir_entries) = fs::read_dir(date_entry.path()) {
-                    for subdir_entry in subdir_entries.flatten() {
-                        if subdir_entry
-                            .file_type()
-                            .map(|ft| ft.is_dir())
-                            .unwrap_or(false)
-                        {
-                            if let Ok(file_entries) = fs::read_dir(subdir_entry.path()) {
-                                for file_entry in file_entries.flatten() {
-                                    if let Some(filename) = file_entry.file_name().to_str() {
-                                        if filename.starts_with("header_")
-                                            && filename.ends_with(".txt")
-                                        {
-                                            if let Some(book_id_str) = filename
-                                                .strip_prefix("header_")
-                                                .and_then(|s| s.strip_suffix(".txt"))
-                                            {
-                                                if let Ok(book_id) = book_id_str.parse::<u32>() {
-                                                    match process_book(book_id, &backend).await {
-                                                        Ok(()) => {
-                                                            books_processed += 1;
-                                                        }
-                                                        Err(e) => {
-                                                            warn!(
-                                                                "Failed to index book {}: {}",
-                                                                book_id, e
-                                                            );
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    let datalake = datalake.path();
+    let outcome = match book_ids {
+        Some(book_ids) => {
+            info!("Starting index rebuild of {} books", book_ids.len());
+            rebuild_books_in(datalake, backend, book_ids, shutdown).await
+        }
+        None => {
+            info!("Starting index rebuild (resume: {})", resume);
+            rebuild_from_datalake(datalake, backend, resume, shutdown).await?
         }
+    };
+
+    let elapsed = start_time.elapsed();
+    info!(
+        "Index rebuild complete: {} of {} books indexed, {} failed in {:?}",
+        outcome.indexed,
+        outcome.attempted,
+        outcome.failures.len(),
+        elapsed
+    );
+
+    Ok(RebuildResponse {
+        status: if outcome.interrupted { "interrupted" } else { "rebuilt" }.to_string(),
+        indexed_count: outcome.indexed,
+        requested_count: outcome.requested,
+        books_processed: outcome.attempted,
+        elapsed_time: format!("{:.2}s", elapsed.as_secs_f64()),
+        failures: outcome.failures,
+        resumed_from: outcome.resumed_from,
+        interrupted: outcome.interrupted,
+        skipped_not_found: outcome.skipped_not_found,
+    })
+}
+
+/// Removes a book from the index. Its postings are reclaimed by the next
+/// compaction; until then searches skip it for lack of metadata.
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn delete_book(
+    Path(book_id): Path<u32>,
+    axum::extract::State(backend): axum::extract::State<Backend>,
+) -> Result<Json<DeleteBookResponse>, AppError> {
+    let deleted = backend.delete_book(book_id).await.inspect_err(|e| {
+        error!("Failed to delete book {}: {}", book_id, e);
+    })?;
+    if !deleted {
+        return Err(AppError::BookNotFound(book_id));
     }
 
+    info!("Deleted book {} from the index", book_id);
+    Ok(Json(DeleteBookResponse {
+        book_id,
+        status: "deleted".to_string(),
+    }))
+}
+
+/// Starts a background compaction; poll [`get_compaction_status`] for progress.
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn start_compaction(
+    axum::extract::State(backend): axum::extract::State<Backend>,
+    axum::extract::State(compactor): axum::extract::State<Compactor>,
+) -> Result<(StatusCode, Json<CompactionStatus>), AppError> {
+    let status = compactor.start(backend)?;
+    info!("Started index compaction");
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_compaction_status(
+    axum::extract::State(compactor): axum::extract::State<Compactor>,
+) -> Json<CompactionStatus> {
+    Json(compactor.status())
+}
+
+/// Lists the indexed books in ID order, for the ingestion service's
+/// `?indexed=` filter.
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_indexed_books(
+    axum::extract::State(backend): axum::extract::State<Backend>,
+) -> Result<Json<IndexedBooksResponse>, AppError> {
+    let mut books: Vec<u32> = backend.get_indexed_books().await?.into_iter().collect();
+    books.sort_unstable();
+
+    Ok(Json(IndexedBooksResponse {
+        count: books.len(),
+        books,
+    }))
+}
+
+/// Lists indexed books whose datalake body file changed since they were indexed.
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_stale_books(
+    axum::extract::State(backend): axum::extract::State<Backend>,
+    axum::extract::State(datalake): axum::extract::State<Datalake>,
+) -> Result<Json<StaleBooksResponse>, AppError> {
+    let books = find_stale_books(datalake.path(), &backend)
+        .await
+        .map_err(|e| {
+            error!("Failed to check for stale books: {}", e);
+            AppError::Internal(format!("Failed to check for stale books: {}", e))
+        })?;
+
+    Ok(Json(StaleBooksResponse {
+        stale_count: books.len(),
+        books,
+    }))
+}
+
+/// Re-indexes the books [`list_stale_books`] reports.
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn refresh_stale(
+    axum::extract::State(backend): axum::extract::State<Backend>,
+    axum::extract::State(limiter): axum::extract::State<IndexingLimiter>,
+    axum::extract::State(shutdown): axum::extract::State<Shutdown>,
+    axum::extract::State(datalake): axum::extract::State<Datalake>,
+) -> Result<Json<RefreshStaleResponse>, AppError> {
+    if shutdown.is_triggered() {
+        return Err(AppError::ShuttingDown);
+    }
+    let _permit = limiter.acquire().await.inspect_err(|_| {
+        warn!("Rejecting stale refresh: too many indexing operations in flight");
+    })?;
+    let start_time = std::time::Instant::now();
+
+    let outcome = refresh_stale_books(datalake.path(), &backend, &shutdown)
+        .await
+        .map_err(|e| {
+            error!("Stale refresh failed: {}", e);
+            AppError::Internal(format!("Stale refresh failed: {}", e))
+        })?;
+
     let elapsed = start_time.elapsed();
     info!(
-        "Index rebuild complete: {} books processed in {:?}",
-        books_processed, elapsed
+        "Stale refresh complete: {} refreshed, {} skipped, {} failed in {:?}",
+        outcome.refreshed.len(),
+        outcome.skipped.len(),
+        outcome.failures.len(),
+        elapsed
     );
 
-    Ok(Json(RebuildResponse {
-        status: "rebuilt".to_string(),
-        indexed_count: books_processed,
-        books_processed,
+    Ok(Json(RefreshStaleResponse {
+        status: if outcome.interrupted { "interrupted" } else { "refreshed" }.to_string(),
+        refreshed: outcome.refreshed,
+        skipped: outcome.skipped,
+        failures: outcome.failures,
         elapsed_time: format!("{:.2}s", elapsed.as_secs_f64()),
+        interrupted: outcome.interrupted,
     }))
 }
 
+/// Current index statistics, with an `ETag` over the book count, the last
+/// index change and the indexing in flight, plus `Last-Modified` once a
+/// change was recorded. A client whose `If-None-Match` still matches gets an
+/// empty `304`.
+#[tracing::instrument(skip_all)]
 pub async fn get_index_status(
     axum::extract::State(backend): axum::extract::State<Backend>,
-) -> Json<IndexStatusResponse> {
+    axum::extract::State(limiter): axum::extract::State<IndexingLimiter>,
+    headers: HeaderMap,
+) -> Response {
+    let status = index_status(&backend, &limiter).await;
+
+    let last_updated = DateTime::parse_from_rfc3339(&status.last_updated)
+        .ok()
+        .map(|updated_at| updated_at.with_timezone(&Utc));
+    let validators = Validators::new(
+        (status.total_books, &status.last_updated, status.indexing_in_flight),
+        last_updated,
+    );
+    if validators.is_fresh(&headers) {
+        return validators.not_modified();
+    }
+    let mut response = Json(status).into_response();
+    validators.apply(response.headers_mut());
+    response
+}
+
+/// Current index statistics; shared with the gRPC `GetStatus`.
+pub async fn index_status(backend: &Backend, limiter: &IndexingLimiter) -> IndexStatusResponse {
     let (book_count, word_count) = backend.get_stats().await.unwrap_or((0, 0));
 
-    let index_size_mb = (book_count * 1000 + word_count * 100) as f64 / 1_000_000.0;
+    let index_size_mb = backend
+        .get_index_size_bytes()
+        .await
+        .map(|bytes| bytes as f64 / BYTES_PER_MB)
+        .unwrap_or(0.0);
+
+    let words_per_field = backend
+        .get_field_stats()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(field, count)| (field.as_str().to_string(), count))
+        .collect();
 
-    let timestamp = Utc::now().to_rfc3339();
-    Json(IndexStatusResponse {
+    // Empty until the backend has recorded a change
+    let timestamp = backend
+        .get_last_updated()
+        .await
+        .ok()
+        .flatten()
+        .map(|updated_at| updated_at.to_rfc3339())
+        .unwrap_or_default();
+    IndexStatusResponse {
         total_books: book_count,
         total_words: word_count,
         last_updated: timestamp.clone(),
         books_indexed: book_count,
         last_update: timestamp,
         index_size_mb,
-    })
+        words_per_field,
+        indexing_in_flight: limiter.in_flight(),
+        diacritic_folding: backend.get_diacritic_folding().await.ok().flatten(),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn get_book_chapters(
+    Path(book_id): Path<u32>,
+    axum::extract::State(datalake): axum::extract::State<Datalake>,
+) -> Result<Json<ChapterListResponse>, AppError> {
+    let (_, body_path) = find_book_files_in(datalake.path(), book_id).ok_or(AppError::BookNotFound(book_id))?;
+
+    let body_content = fs::read_to_string(&body_path).map_err(|e| {
+        error!("Failed to read body of book {}: {}", book_id, e);
+        AppError::Internal(format!("Failed to read body of book {}: {}", book_id, e))
+    })?;
+
+    let chapters: Vec<ChapterInfo> = detect_chapters(&body_content)
+        .into_iter()
+        .map(|chapter| ChapterInfo {
+            chapter_no: chapter.id,
+            word_count: chapter.text(&body_content).split_whitespace().count(),
+            title: chapter.title,
+        })
+        .collect();
+
+    Ok(Json(ChapterListResponse {
+        book_id,
+        chapter_count: chapters.len(),
+        chapters,
+    }))
+}
+
+/// Lists the books the index maps `word` to, one page at a time. Words the
+/// tokenizer would drop are rejected with `400` instead of an empty list, and
+/// so are [`STOP_WORDS`].
+#[tracing::instrument(skip_all, fields(word = %word), err(level = "warn"))]
+pub async fn get_word_books(
+    Path(word): Path<String>,
+    Query(params): Query<PageParams>,
+    axum::extract::State(backend): axum::extract::State<Backend>,
+) -> Result<Json<WordBooksResponse>, AppError> {
+    let word = word.to_lowercase();
+    if let Some(not_indexed) = check_indexable(&word) {
+        return Err(AppError::WordNotIndexed {
+            reason: not_indexed.reason(),
+            word,
+        });
+    }
+    if STOP_WORDS.contains(&word.as_str()) {
+        return Err(AppError::InvalidRequest(format!(
+            "'{}' is a stop word, found in nearly every book",
+            word
+        )));
+    }
+
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+
+    let book_ids = backend.get_books_for_word(&word).await.inspect_err(|e| {
+        error!("Failed to look up books for word '{}': {}", word, e);
+    })?;
+
+    let mut books = Vec::new();
+    for &book_id in book_ids.iter().skip((page - 1) * per_page).take(per_page) {
+        let metadata = backend.get_book_metadata(book_id).await.inspect_err(|e| {
+            error!("Failed to load metadata for book {}: {}", book_id, e);
+        })?;
+        let (title, author) = metadata
+            .map(|m| (m.title, m.author))
+            .unwrap_or_else(|| ("Unknown".to_string(), "Unknown".to_string()));
+        books.push(WordBook { book_id, title, author });
+    }
+
+    Ok(Json(WordBooksResponse {
+        word,
+        book_count: book_ids.len(),
+        page,
+        per_page,
+        books,
+    }))
+}
+
+/// Lists the `n` words (default 10, at most 100) found most often within a
+/// few words of `word`. Empty unless books were indexed with
+/// `ENABLE_COOCCURRENCE=true`.
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_cooccurrences(
+    Query(params): Query<CooccurrenceParams>,
+    axum::extract::State(backend): axum::extract::State<Backend>,
+) -> Result<Json<CooccurrenceResponse>, AppError> {
+    let word = params.word.to_lowercase();
+    if let Some(not_indexed) = check_indexable(&word) {
+        return Err(AppError::WordNotIndexed {
+            reason: not_indexed.reason(),
+            word,
+        });
+    }
+
+    let n = params.n.unwrap_or(DEFAULT_COOCCURRENCES).clamp(1, MAX_COOCCURRENCES);
+    let cooccurrences = backend.get_cooccurrences(&word, n).await.inspect_err(|e| {
+        error!("Failed to look up co-occurrences for '{}': {}", word, e);
+    })?;
+
+    Ok(Json(CooccurrenceResponse {
+        word,
+        cooccurrences: cooccurrences
+            .into_iter()
+            .map(|(word, count)| CooccurrenceEntry { word, count })
+            .collect(),
+    }))
+}
+
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn verify_book(
+    Path(book_id): Path<u32>,
+    axum::extract::State(backend): axum::extract::State<Backend>,
+    axum::extract::State(datalake): axum::extract::State<Datalake>,
+) -> Result<Json<VerificationResult>, AppError> {
+    if find_book_files_in(datalake.path(), book_id).is_none() {
+        return Err(AppError::BookNotFound(book_id));
+    }
+
+    match verify_book_index(datalake.path(), book_id, &backend).await {
+        Ok(result) => {
+            info!(
+                "Verified book {}: {}/{} words present",
+                book_id, result.present_in_index, result.expected_words
+            );
+            Ok(Json(result))
+        }
+        Err(e) => {
+            error!("Failed to verify book {}: {}", book_id, e);
+            Err(AppError::Internal(format!("Failed to verify book {}: {}", book_id, e)))
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn export_index_dump(
+    axum::extract::State(backend): axum::extract::State<Backend>,
+) -> impl IntoResponse {
+    info!("Exporting index");
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(export_index(backend)),
+    )
+}
+
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn import_index_dump(
+    axum::extract::State(backend): axum::extract::State<Backend>,
+    body: Body,
+) -> Result<Json<ImportResponse>, AppError> {
+    info!("Importing index");
+
+    let mut summary = ImportSummary::default();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunks = body.into_data_stream();
+
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| AppError::InvalidRequest(e.to_string()))?;
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            import_line(&mut summary, &line, &backend).await?;
+        }
+    }
+    if !buffer.is_empty() {
+        import_line(&mut summary, &buffer, &backend).await?;
+    }
+
+    info!(
+        "Index import complete: {} books, {} words",
+        summary.books_imported, summary.words_imported
+    );
+
+    Ok(Json(ImportResponse {
+        status: "imported".to_string(),
+        books_imported: summary.books_imported,
+        words_imported: summary.words_imported,
+        postings_imported: summary.postings_imported,
+    }))
+}
+
+async fn import_line(
+    summary: &mut ImportSummary,
+    line: &[u8],
+    backend: &Backend,
+) -> Result<(), AppError> {
+    summary
+        .import_line(&String::from_utf8_lossy(line), backend)
+        .await
+        .map_err(|e| match e {
+            ImportError::InvalidRecord { .. } => {
+                warn!("Index import stopped: {}", e);
+                AppError::InvalidRequest(e.to_string())
+            }
+            ImportError::Storage(storage) => {
+                error!("Index import failed: {}", storage);
+                storage.into()
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app;
+    use crate::models::storage::{Backend, BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use crate::services::auth::AuthToken;
+    use crate::services::backpressure::IndexingLimiter;
+    use crate::services::shutdown::Shutdown;
+    use crate::services::startup::StartupProbe;
+    use crate::services::timeout::RequestTimeouts;
+    use crate::services::compaction::Compactor;
+    use crate::utils::file::Datalake;
+    use crate::state::AppState;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    fn state(backend: Backend) -> AppState {
+        AppState {
+            backend,
+            limiter: IndexingLimiter::new(4, Duration::ZERO),
+            shutdown: Shutdown::new(),
+            auth: AuthToken::default(),
+            compactor: Compactor::new(),
+            started_at: Arc::new(Instant::now()),
+            startup: StartupProbe::new(),
+            timeouts: RequestTimeouts::default(),
+            datalake: Datalake::default(),
+        }
+    }
+
+    async fn get_json(backend: Backend, uri: &str) -> (StatusCode, Value) {
+        let response = app(state(backend))
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn post_json(backend: Backend, uri: &str, body: &str) -> (StatusCode, Value) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app(state(backend)).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn seed(backend: &Backend, book_id: u32, title: &str) {
+        backend
+            .store_book_metadata(&BookMetadata {
+                book_id,
+                title: title.to_string(),
+                author: "Herman Melville".to_string(),
+                language: "en".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        backend.add_word_to_index("whale", book_id, IndexField::Body).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn word_lookup_rejects_stop_words() {
+        let (status, body) = get_json(Backend::Memory(MemoryBackend::new()), "/index/words/The").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+        assert_eq!(body["message"], "'the' is a stop word, found in nearly every book");
+    }
+
+    #[tokio::test]
+    async fn indexed_books_are_listed_in_order() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        seed(&backend, 2701, "Moby Dick").await;
+        seed(&backend, 15, "Moby Dick (abridged)").await;
+
+        let (status, body) = get_json(backend, "/index/books").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["books"], serde_json::json!([15, 2701]));
+    }
+
+    #[tokio::test]
+    async fn cooccurrences_are_summed_across_books() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        backend.add_cooccurrence("love", "letter", 1342, 4).await.unwrap();
+        backend.add_cooccurrence("letter", "love", 158, 2).await.unwrap();
+        backend.add_cooccurrence("love", "marriage", 1342, 5).await.unwrap();
+        backend.add_cooccurrence("love", "ball", 158, 1).await.unwrap();
+        // Re-indexing replaces a book's count rather than adding to it
+        backend.add_cooccurrence("love", "marriage", 1342, 3).await.unwrap();
+
+        let (status, body) = get_json(backend, "/index/cooccurrence?word=Love&n=2").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["word"], "love");
+        assert_eq!(
+            body["cooccurrences"],
+            serde_json::json!([{"word": "letter", "count": 6}, {"word": "marriage", "count": 3}])
+        );
+    }
+
+    #[tokio::test]
+    async fn cooccurrence_lookup_rejects_unindexed_words() {
+        let (status, body) = get_json(Backend::Memory(MemoryBackend::new()), "/index/cooccurrence?word=of").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "WORD_NOT_INDEXED");
+    }
+
+    #[tokio::test]
+    async fn word_lookup_pages_through_books() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        for (book_id, title) in [(2701, "Moby Dick"), (15, "Omoo"), (21816, "The Confidence-Man")] {
+            seed(&backend, book_id, title).await;
+        }
+
+        let (status, body) = get_json(backend, "/index/words/whale?page=2&per_page=2").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["book_count"], 3);
+        assert_eq!(body["books"].as_array().unwrap().len(), 1);
+        assert_eq!(body["books"][0]["book_id"], 21816);
+        assert_eq!(body["books"][0]["title"], "The Confidence-Man");
+    }
+
+    #[tokio::test]
+    async fn status_reports_in_memory_stats() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        seed(&backend, 2701, "Moby Dick").await;
+
+        let (status, body) = get_json(backend, "/index/status").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_books"], 1);
+        assert_eq!(body["total_words"], 1);
+    }
+
+    #[tokio::test]
+    async fn unchanged_status_is_not_modified() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        seed(&backend, 2701, "Moby Dick").await;
+        let app = app(state(backend.clone()));
+        let status = |etag: Option<&str>| {
+            let mut request = Request::get("/index/status");
+            if let Some(etag) = etag {
+                request = request.header("if-none-match", etag);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let first = status(None).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert!(first.headers().contains_key("last-modified"));
+        let etag = first.headers()["etag"].to_str().unwrap().to_string();
+
+        let second = status(Some(&etag)).await.unwrap();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(second.headers()["etag"], etag.as_str());
+        assert!(to_bytes(second.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        seed(&backend, 2702, "Typee").await;
+        let third = status(Some(&etag)).await.unwrap();
+        assert_eq!(third.status(), StatusCode::OK);
+        assert_ne!(third.headers()["etag"], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn rebuild_without_a_body_covers_the_whole_datalake() {
+        for body in ["", "{}"] {
+            let (status, json) = post_json(Backend::Memory(MemoryBackend::new()), "/index/rebuild", body).await;
+
+            assert_eq!(status, StatusCode::OK, "{}", json);
+            assert_eq!(json["status"], "rebuilt");
+            assert_eq!(json["requested_count"], json["books_processed"]);
+            assert_eq!(json["skipped_not_found"], serde_json::json!([]));
+        }
+    }
+
+    #[tokio::test]
+    async fn rebuild_of_selected_books_skips_those_not_in_the_datalake() {
+        let body = r#"{"book_ids": [999999, 999998, 999999]}"#;
+        let (status, json) = post_json(Backend::Memory(MemoryBackend::new()), "/index/rebuild", body).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", json);
+        assert_eq!(json["requested_count"], 2);
+        assert_eq!(json["books_processed"], 0);
+        assert_eq!(json["skipped_not_found"], serde_json::json!([999998, 999999]));
+    }
+
+    #[tokio::test]
+    async fn rebuild_rejects_malformed_bodies_and_resuming_selected_books() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        for (uri, body) in [
+            ("/index/rebuild", r#"{"book_ids": "84"}"#),
+            ("/index/rebuild", r#"{"books": [84]}"#),
+            ("/index/rebuild", "84"),
+            ("/index/rebuild?resume=true", r#"{"book_ids": [84]}"#),
+        ] {
+            let (status, json) = post_json(backend.clone(), uri, body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{} {}", uri, body);
+            assert_eq!(json["error_code"], "INVALID_REQUEST");
+        }
+    }
 }
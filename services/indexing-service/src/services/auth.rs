@@ -0,0 +1,124 @@
+//! Bearer Token Authentication
+//!
+//! Optional shared-secret check for the mutating (`POST`) routes. When
+//! `INDEXING_AUTH_TOKEN` is set, those routes require
+//! `Authorization: Bearer <token>` and answer `401` with an
+//! `UNAUTHORIZED` error otherwise. When it is unset, every request is let
+//! through, as before.
+
+use crate::error::AppError;
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::warn;
+
+#[derive(Clone, Default)]
+pub struct AuthToken(Option<Arc<str>>);
+
+impl AuthToken {
+    pub fn new(token: Option<&str>) -> Self {
+        Self(token.filter(|t| !t.is_empty()).map(Arc::from))
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("INDEXING_AUTH_TOKEN").ok().as_deref())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub(crate) fn accepts(&self, authorization: Option<&str>) -> bool {
+        let Some(expected) = &self.0 else {
+            return true;
+        };
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), expected.as_bytes()))
+    }
+}
+
+/// Compares without short-circuiting so response timing doesn't leak how
+/// much of the token matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub async fn require_bearer_token(
+    State(token): State<AuthToken>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    if token.accepts(authorization) {
+        return next.run(request).await;
+    }
+
+    let message = if authorization.is_some() {
+        "Invalid bearer token"
+    } else {
+        "Missing bearer token"
+    };
+    warn!("Rejecting {} {}: {}", request.method(), request.uri().path(), message);
+
+    AppError::Unauthorized(message).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::responses::ErrorResponse;
+    use axum::{body::Body, http::StatusCode, middleware, routing::post, Router};
+    use tower::ServiceExt;
+
+    fn app(token: Option<&str>) -> Router {
+        let token = AuthToken::new(token);
+        Router::new()
+            .route("/index/rebuild", post(|| async { "rebuilt" }))
+            .route_layer(middleware::from_fn_with_state(token, require_bearer_token))
+    }
+
+    async fn rebuild(app: Router, authorization: Option<&str>) -> Response {
+        let mut request = Request::post("/index/rebuild");
+        if let Some(value) = authorization {
+            request = request.header(header::AUTHORIZATION, value);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn accepts_matching_token() {
+        let response = rebuild(app(Some("s3cret")), Some("Bearer s3cret")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_or_wrong_token() {
+        let missing = rebuild(app(Some("s3cret")), None).await;
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+
+        let wrong = rebuild(app(Some("s3cret")), Some("Bearer nope")).await;
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+
+        let body = axum::body::to_bytes(wrong.into_body(), usize::MAX).await.unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.error_code, "UNAUTHORIZED");
+        assert_eq!(error.message, "Invalid bearer token");
+    }
+
+    #[tokio::test]
+    async fn lets_everything_through_when_disabled() {
+        assert!(!AuthToken::new(Some("")).is_enabled());
+
+        let response = rebuild(app(None), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,127 @@
+//! Indexing Backpressure
+//!
+//! Bounds the number of books tokenized and written at the same time, so a
+//! burst of `POST /index/update/:id` requests can't exhaust the container's
+//! memory. Requests beyond the limit wait for a permit for a bounded time and
+//! are then rejected with `429 Too Many Requests`.
+//!
+//! ## Environment Variables
+//! - `MAX_CONCURRENT_INDEXING`: Concurrent indexing operations (default: `4`)
+//! - `INDEXING_MAX_WAIT_SECS`: How long a request may wait for a slot (default: `30`)
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+const DEFAULT_MAX_WAIT_SECS: u64 = 30;
+
+/// Shared limit on in-flight indexing operations.
+#[derive(Clone)]
+pub struct IndexingLimiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+    max_wait: Duration,
+}
+
+/// Returned when no indexing slot became free within the wait budget.
+#[derive(Debug)]
+pub struct IndexingBusy {
+    retry_after: Duration,
+}
+
+impl IndexingBusy {
+    /// How long to wait before trying again, sent as `Retry-After`.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+impl IndexingLimiter {
+    pub fn new(max_concurrent: usize, max_wait: Duration) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            max_wait,
+        }
+    }
+
+    /// Builds a limiter from `MAX_CONCURRENT_INDEXING` and `INDEXING_MAX_WAIT_SECS`.
+    pub fn from_env() -> Self {
+        let max_concurrent = std::env::var("MAX_CONCURRENT_INDEXING")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT);
+        let max_wait_secs = std::env::var("INDEXING_MAX_WAIT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_WAIT_SECS);
+
+        Self::new(max_concurrent, Duration::from_secs(max_wait_secs))
+    }
+
+    /// Waits up to the configured budget for a slot. The slot is released
+    /// when the returned permit is dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, IndexingBusy> {
+        let busy = || IndexingBusy {
+            retry_after: self.max_wait,
+        };
+
+        if self.max_wait.is_zero() {
+            return self.semaphore.clone().try_acquire_owned().map_err(|_| busy());
+        }
+
+        match tokio::time::timeout(self.max_wait, self.semaphore.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(permit),
+            _ => Err(busy()),
+        }
+    }
+
+    /// Number of indexing operations currently holding a slot.
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrent - self.semaphore.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn rejects_request_beyond_limit_with_zero_wait() {
+        let limiter = IndexingLimiter::new(50, Duration::ZERO);
+
+        let mut permits = Vec::new();
+        for _ in 0..50 {
+            permits.push(limiter.acquire().await.expect("slot should be free"));
+        }
+        assert_eq!(limiter.in_flight(), 50);
+
+        let busy = limiter.acquire().await.expect_err("51st request should be rejected");
+        let response = AppError::from(busy).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+
+        permits.pop();
+        assert!(limiter.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_slot_within_budget() {
+        let limiter = IndexingLimiter::new(1, Duration::from_secs(5));
+        let permit = limiter.acquire().await.unwrap();
+
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire().await.is_ok() })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(permit);
+
+        assert!(waiter.await.unwrap());
+    }
+}
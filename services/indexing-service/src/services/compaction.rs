@@ -0,0 +1,278 @@
+//! Index Compaction
+//!
+//! Deleting a book only drops its metadata and marks it deleted; its
+//! postings stay behind in every word set it appeared in. Compaction walks
+//! the vocabulary, then the author words kept only in their own field,
+//! removes the deleted books from each word's postings and
+//! drops words left without any, which is what shrinks `index_size_mb`.
+//!
+//! The job runs in the background, one at a time, and reports progress
+//! through [`Compactor::status`]. It only ever removes postings of deleted
+//! books, so indexing and searching carry on while it runs. Each word's
+//! postings are removed only for books still marked deleted, checked
+//! together with the removal (see `StorageBackend::purge_deleted_postings`),
+//! so a deleted book indexed again mid-run keeps every posting the re-index
+//! writes. Storing its metadata takes it off the deleted list before any of
+//! them are written. On a Redis Cluster the check can't be atomic, and a
+//! re-index racing the job may lose postings until the book is rebuilt.
+//! Books still marked deleted when the job is cut short are picked up by
+//! the next run.
+
+use crate::models::responses::{CompactionState, CompactionStatus};
+use crate::models::storage::{Backend, IndexField, StorageBackend};
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info};
+
+/// Words read from the vocabulary per batch.
+const SCAN_BATCH: usize = 500;
+/// Rough size of one book ID in a posting set or row.
+const ESTIMATED_POSTING_BYTES: u64 = 16;
+/// Rough size of a word's own entries, as estimated in `/index/status`.
+const ESTIMATED_WORD_BYTES: u64 = 100;
+
+/// Handle on the compaction job, shared by the handlers.
+#[derive(Clone, Default)]
+pub struct Compactor {
+    status: Arc<Mutex<CompactionStatus>>,
+}
+
+/// Returned when a compaction is requested while one is running.
+#[derive(Debug)]
+pub struct CompactionRunning;
+
+impl Compactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> CompactionStatus {
+        self.lock().clone()
+    }
+
+    /// Starts a compaction in the background and returns its initial status.
+    pub fn start(&self, backend: Backend) -> Result<CompactionStatus, CompactionRunning> {
+        let status = self.begin()?;
+        let compactor = self.clone();
+        tokio::spawn(async move { compactor.execute(&backend).await });
+        Ok(status)
+    }
+
+    /// Runs a compaction to completion and returns its final status.
+    pub async fn run(&self, backend: &Backend) -> Result<CompactionStatus, CompactionRunning> {
+        self.begin()?;
+        Ok(self.execute(backend).await)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CompactionStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn begin(&self) -> Result<CompactionStatus, CompactionRunning> {
+        let mut status = self.lock();
+        if status.state == CompactionState::Running {
+            return Err(CompactionRunning);
+        }
+        *status = CompactionStatus {
+            state: CompactionState::Running,
+            started_at: Some(Utc::now().to_rfc3339()),
+            ..CompactionStatus::default()
+        };
+        Ok(status.clone())
+    }
+
+    async fn execute(&self, backend: &Backend) -> CompactionStatus {
+        let result = self.compact(backend).await;
+
+        let mut status = self.lock();
+        status.finished_at = Some(Utc::now().to_rfc3339());
+        match result {
+            Ok(()) => {
+                status.state = CompactionState::Completed;
+                info!(
+                    "Compaction complete: {} postings and {} words removed for {} deleted books",
+                    status.postings_removed, status.words_removed, status.books_purged
+                );
+            }
+            Err(e) => {
+                error!("Compaction failed: {}", e);
+                status.state = CompactionState::Failed;
+                status.error = Some(e.to_string());
+            }
+        }
+        status.clone()
+    }
+
+    async fn compact(&self, backend: &Backend) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut deleted: Vec<u32> = backend.get_deleted_books().await?.into_iter().collect();
+        deleted.sort_unstable();
+        let (_, words_total) = backend.get_stats().await?;
+        {
+            let mut status = self.lock();
+            status.books_purged = deleted.len();
+            status.words_total = words_total;
+        }
+
+        // Author postings aren't in the unscoped vocabulary, so their words
+        // are walked separately; words in both are visited twice, the second
+        // time removing nothing.
+        for field in [IndexField::Body, IndexField::Author] {
+            self.compact_vocabulary(backend, field, &mut deleted).await?;
+        }
+
+        backend.clear_deleted_books(&deleted).await?;
+        Ok(())
+    }
+
+    /// Removes `deleted` from the postings of every word in `field`'s
+    /// vocabulary, dropping books indexed again since the job started.
+    async fn compact_vocabulary(
+        &self,
+        backend: &Backend,
+        field: IndexField,
+        deleted: &mut Vec<u32>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut cursor = None;
+        while !deleted.is_empty() {
+            let (words, next) = backend.scan_words(field, cursor, SCAN_BATCH).await?;
+
+            // Skip books indexed again since the job started.
+            let still_deleted = backend.get_deleted_books().await?;
+            deleted.retain(|book_id| still_deleted.contains(book_id));
+
+            let (mut postings, mut words_removed) = (0, 0);
+            for word in &words {
+                let removed = backend.purge_deleted_postings(word, deleted).await?;
+                postings += removed.postings;
+                words_removed += usize::from(removed.word_removed);
+            }
+
+            {
+                let mut status = self.lock();
+                status.words_scanned += words.len();
+                status.postings_removed += postings;
+                status.words_removed += words_removed;
+                status.estimated_bytes_reclaimed += postings as u64 * ESTIMATED_POSTING_BYTES
+                    + words_removed as u64 * ESTIMATED_WORD_BYTES;
+            }
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, PostgresBackend};
+
+    async fn index(backend: &Backend, book_id: u32, words: &[&str]) {
+        let metadata = BookMetadata {
+            book_id,
+            title: format!("Book {}", book_id),
+            author: "Anonymous".to_string(),
+            language: "en".to_string(),
+            word_count: words.len(),
+            unique_words: words.len(),
+            ..Default::default()
+        };
+        backend.store_book_metadata(&metadata).await.unwrap();
+        for word in words {
+            backend.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+        }
+        backend.add_word_to_index("anonymous", book_id, IndexField::Author).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn compaction_drops_postings_of_deleted_books() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        index(&backend, 1, &["whale", "ocean", "harpoon"]).await;
+        index(&backend, 2, &["whale", "garden"]).await;
+        let (_, words_before) = backend.get_stats().await.unwrap();
+
+        assert!(backend.delete_book(1).await.unwrap());
+        assert!(!backend.delete_book(1).await.unwrap());
+        // Deleting alone leaves the postings in place.
+        assert_eq!(backend.get_stats().await.unwrap().1, words_before);
+
+        let status = Compactor::new().run(&backend).await.unwrap();
+        assert_eq!(status.state, CompactionState::Completed);
+        assert_eq!(status.books_purged, 1);
+        assert_eq!(status.words_removed, 2);
+        assert_eq!(status.postings_removed, 4);
+        assert!(status.estimated_bytes_reclaimed > 0);
+
+        let (_, words_after) = backend.get_stats().await.unwrap();
+        assert_eq!(words_after, words_before - 2);
+        assert!(backend.search_word("ocean").await.unwrap().is_empty());
+        assert_eq!(backend.search_word("whale").await.unwrap(), [2].into());
+        assert_eq!(
+            backend.get_books_for_word_in_field("anonymous", IndexField::Author).await.unwrap(),
+            [2].into()
+        );
+        assert!(backend.get_deleted_books().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reindexed_book_is_not_compacted() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        index(&backend, 1, &["whale"]).await;
+        backend.delete_book(1).await.unwrap();
+        index(&backend, 1, &["whale"]).await;
+
+        let status = Compactor::new().run(&backend).await.unwrap();
+        assert_eq!(status.books_purged, 0);
+        assert_eq!(backend.search_word("whale").await.unwrap(), [1].into());
+    }
+
+    /// Deletes two books and indexes the first again after the job would
+    /// have read the deleted list; purging must leave the re-indexed book.
+    async fn assert_purge_skips_books_indexed_again(backend: &Backend, first: u32, word: &str) {
+        let second = first + 1;
+        index(backend, first, &[word]).await;
+        index(backend, second, &[word]).await;
+        backend.delete_book(first).await.unwrap();
+        backend.delete_book(second).await.unwrap();
+        index(backend, first, &[word]).await;
+
+        let removed = backend.purge_deleted_postings(word, &[first, second]).await.unwrap();
+        assert_eq!(removed.postings, 1);
+        assert!(!removed.word_removed);
+        assert_eq!(backend.search_word(word).await.unwrap(), [first].into());
+    }
+
+    #[tokio::test]
+    async fn purge_skips_books_indexed_again() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        assert_purge_skips_books_indexed_again(&backend, 1, "whale").await;
+    }
+
+    /// Runs against a real Postgres database when `TEST_DATABASE_URL` is
+    /// set, and is skipped otherwise.
+    #[tokio::test]
+    async fn purge_skips_books_indexed_again_on_postgres() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let backend = Backend::Postgres(PostgresBackend::new(&url).await.unwrap());
+        let (first, word) = (9_101, "purgeable");
+        for book_id in [first, first + 1] {
+            backend.delete_book(book_id).await.unwrap();
+        }
+        backend.remove_postings(word, &[first, first + 1]).await.unwrap();
+        backend.clear_deleted_books(&[first, first + 1]).await.unwrap();
+
+        assert_purge_skips_books_indexed_again(&backend, first, word).await;
+    }
+
+    #[tokio::test]
+    async fn refuses_to_start_twice() {
+        let compactor = Compactor::new();
+        compactor.begin().unwrap();
+        assert!(compactor.start(Backend::Memory(MemoryBackend::new())).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! gRPC Server
+//!
+//! Serves `proto/indexing.proto` next to the HTTP API, for internal callers
+//! such as the control module. Each RPC runs the same code as its HTTP route:
+//!
+//! - `IndexBook` → `POST /index/update/:book_id`
+//! - `RebuildIndex` → `POST /index/rebuild`
+//! - `GetStatus` → `GET /index/status`
+//!
+//! HTTP errors map onto gRPC codes: `401` → `UNAUTHENTICATED`, `429` →
+//! `RESOURCE_EXHAUSTED`, `503` → `UNAVAILABLE`, `500` → `INTERNAL`. When
+//! `INDEXING_AUTH_TOKEN` is set, the mutating RPCs require
+//! `authorization: Bearer <token>` metadata, like the `POST` routes.
+//!
+//! ## Environment Variables
+//! - `GRPC_PORT`: Port of the gRPC server (default: `7012`)
+
+use crate::models::responses;
+use crate::routes::index::{index_status, run_rebuild};
+use crate::services::indexing::process_book_in;
+use crate::state::AppState;
+use std::net::SocketAddr;
+use tonic::{Request, Response, Status};
+use tracing::{error, info, warn};
+
+/// Types and stubs generated from `proto/indexing.proto`.
+pub mod pb {
+    tonic::include_proto!("indexing");
+}
+
+use pb::indexing_service_server::{IndexingService, IndexingServiceServer};
+
+pub const DEFAULT_GRPC_PORT: u16 = 7012;
+
+/// Reads `GRPC_PORT`, falling back to [`DEFAULT_GRPC_PORT`].
+pub fn grpc_port() -> u16 {
+    std::env::var("GRPC_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_GRPC_PORT)
+}
+
+/// The gRPC service, sharing the HTTP server's state.
+#[derive(Clone)]
+pub struct IndexingGrpc {
+    state: AppState,
+}
+
+impl IndexingGrpc {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    fn is_authorized<T>(&self, request: &Request<T>) -> bool {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        self.state.auth.accepts(authorization)
+    }
+
+    /// Admission checks shared by the mutating RPCs, in the order the HTTP
+    /// routes apply them.
+    fn admit<T>(&self, request: &Request<T>) -> Option<Status> {
+        if !self.is_authorized(request) {
+            warn!("Rejecting gRPC call: missing or invalid bearer token");
+            return Some(Status::unauthenticated("Missing or invalid bearer token"));
+        }
+        if self.state.shutdown.is_triggered() {
+            return Some(Status::unavailable("Indexing service is shutting down"));
+        }
+        None
+    }
+}
+
+#[tonic::async_trait]
+impl IndexingService for IndexingGrpc {
+    async fn index_book(
+        &self,
+        request: Request<pb::IndexBookRequest>,
+    ) -> Result<Response<pb::IndexBookResponse>, Status> {
+        if let Some(rejection) = self.admit(&request) {
+            return Err(rejection);
+        }
+        let pb::IndexBookRequest { book_id, verbose } = request.into_inner();
+
+        let _permit = self.state.limiter.acquire().await.map_err(|_| {
+            warn!("Rejecting indexing of book {}: too many in flight", book_id);
+            Status::resource_exhausted("Too many concurrent indexing operations")
+        })?;
+        info!("Indexing book {} (gRPC)", book_id);
+
+        let report = process_book_in(self.state.datalake.path(), book_id, &self.state.backend).await.map_err(|e| {
+            error!("Failed to index book {}: {}", book_id, e);
+            Status::internal(format!("Failed to index book {}", book_id))
+        })?;
+
+        Ok(Response::new(pb::IndexBookResponse {
+            book_id,
+            status: "updated".to_string(),
+            timings_ms: verbose.then(|| report.timings.into()),
+            word_count: report.word_count as u64,
+            unique_words: report.unique_words as u64,
+            elapsed_ms: report.elapsed_ms,
+        }))
+    }
+
+    async fn rebuild_index(
+        &self,
+        request: Request<pb::RebuildRequest>,
+    ) -> Result<Response<pb::RebuildResponse>, Status> {
+        if let Some(rejection) = self.admit(&request) {
+            return Err(rejection);
+        }
+
+        let request = request.into_inner();
+        if request.resume && !request.book_ids.is_empty() {
+            return Err(Status::invalid_argument("resume only applies to a full rebuild; leave out book_ids"));
+        }
+
+        let _permit = self.state.limiter.acquire().await.map_err(|_| {
+            warn!("Rejecting index rebuild: too many indexing operations in flight");
+            Status::resource_exhausted("Too many concurrent indexing operations")
+        })?;
+
+        let book_ids = (!request.book_ids.is_empty()).then_some(request.book_ids.as_slice());
+        let response = run_rebuild(&self.state.datalake, &self.state.backend, &self.state.shutdown, request.resume, book_ids)
+            .await
+            .map_err(|e| {
+                error!("Index rebuild failed: {}", e);
+                Status::internal("Index rebuild failed")
+            })?;
+
+        Ok(Response::new(response.into()))
+    }
+
+    async fn get_status(&self, _request: Request<pb::Empty>) -> Result<Response<pb::IndexStatusResponse>, Status> {
+        let status = index_status(&self.state.backend, &self.state.limiter).await;
+        Ok(Response::new(status.into()))
+    }
+}
+
+/// Serves the gRPC API on `addr` until shutdown is triggered.
+pub async fn serve(addr: SocketAddr, state: AppState) -> Result<(), tonic::transport::Error> {
+    let shutdown = state.shutdown.clone();
+    tonic::transport::Server::builder()
+        .add_service(IndexingServiceServer::new(IndexingGrpc::new(state)))
+        .serve_with_shutdown(addr, async move { shutdown.wait().await })
+        .await
+}
+
+impl From<responses::StageTimings> for pb::StageTimings {
+    fn from(timings: responses::StageTimings) -> Self {
+        Self {
+            file_read: timings.file_read,
+            tokenization: timings.tokenization,
+            metadata_store: timings.metadata_store,
+            postings_write: timings.postings_write,
+        }
+    }
+}
+
+impl From<responses::RebuildResponse> for pb::RebuildResponse {
+    fn from(response: responses::RebuildResponse) -> Self {
+        Self {
+            status: response.status,
+            indexed_count: response.indexed_count as u64,
+            books_processed: response.books_processed as u64,
+            elapsed_time: response.elapsed_time,
+            failures: response
+                .failures
+                .into_iter()
+                .map(|failure| pb::RebuildFailure {
+                    book_id: failure.book_id,
+                    error: failure.error,
+                })
+                .collect(),
+            resumed_from: response.resumed_from,
+            interrupted: response.interrupted,
+            requested_count: response.requested_count as u64,
+            skipped_not_found: response.skipped_not_found,
+        }
+    }
+}
+
+impl From<responses::IndexStatusResponse> for pb::IndexStatusResponse {
+    fn from(status: responses::IndexStatusResponse) -> Self {
+        Self {
+            total_books: status.total_books as u64,
+            total_words: status.total_words as u64,
+            last_updated: status.last_updated,
+            index_size_mb: status.index_size_mb,
+            words_per_field: status
+                .words_per_field
+                .into_iter()
+                .map(|(field, count)| (field, count as u64))
+                .collect(),
+            indexing_in_flight: status.indexing_in_flight as u64,
+            diacritic_folding: status.diacritic_folding,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{Backend, BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use crate::services::auth::AuthToken;
+    use crate::services::backpressure::IndexingLimiter;
+    use crate::services::compaction::Compactor;
+    use crate::services::shutdown::Shutdown;
+    use crate::services::startup::StartupProbe;
+    use crate::services::timeout::RequestTimeouts;
+    use crate::utils::file::Datalake;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tonic::Code;
+
+    fn service(backend: Backend, token: Option<&str>) -> IndexingGrpc {
+        IndexingGrpc::new(AppState {
+            backend,
+            limiter: IndexingLimiter::new(4, Duration::ZERO),
+            shutdown: Shutdown::new(),
+            auth: AuthToken::new(token),
+            compactor: Compactor::new(),
+            started_at: Arc::new(Instant::now()),
+            startup: StartupProbe::new(),
+            timeouts: RequestTimeouts::default(),
+            datalake: Datalake::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn status_reports_the_index() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        backend
+            .store_book_metadata(&BookMetadata {
+                book_id: 2701,
+                title: "Moby Dick".to_string(),
+                author: "Herman Melville".to_string(),
+                language: "en".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        backend.add_word_to_index("whale", 2701, IndexField::Body).await.unwrap();
+        backend.add_word_to_index("moby", 2701, IndexField::Title).await.unwrap();
+
+        let status = service(backend, None)
+            .get_status(Request::new(pb::Empty {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(status.total_books, 1);
+        assert_eq!(status.words_per_field["title"], 1);
+        assert_eq!(status.indexing_in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn mutating_rpcs_require_the_token() {
+        let service = service(Backend::Memory(MemoryBackend::new()), Some("s3cret"));
+
+        let err = service
+            .rebuild_index(Request::new(pb::RebuildRequest { resume: false, book_ids: Vec::new() }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+
+        let mut request = Request::new(pb::IndexBookRequest {
+            book_id: 999_999,
+            verbose: false,
+        });
+        request.metadata_mut().insert("authorization", "Bearer s3cret".parse().unwrap());
+        // Authorized, but the book isn't in the datalake
+        let err = service.index_book(request).await.unwrap_err();
+        assert_eq!(err.code(), Code::Internal);
+
+        // Reads stay open, like GET /index/status
+        assert!(service.get_status(Request::new(pb::Empty {})).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn indexing_is_refused_during_shutdown() {
+        let service = service(Backend::Memory(MemoryBackend::new()), None);
+        service.state.shutdown.trigger();
+
+        let err = service
+            .index_book(Request::new(pb::IndexBookRequest {
+                book_id: 1342,
+                verbose: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+    }
+}
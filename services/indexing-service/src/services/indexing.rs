@@ -7,24 +7,49 @@
 //!
 //! ## Responsibilities
 //! - Read header and body files for each book from the datalake  
-//! - Extract metadata (title, author, language, year) from the header  
-//! - Tokenize the book’s text content and title into searchable words  
+//! - Extract metadata (title, author, language, year, subjects) from the header  
+//! - Tokenize the book’s text content, title and author into searchable words  
+//! - Keep field-scoped postings for title and author words  
+//! - Index subject words with a `subj:` prefix to keep them apart from body words  
 //! - Store metadata and word-to-book relationships in the backend  
+//! - Optionally store `(book_id, chapter_no)` postings (`ENABLE_CHAPTER_INDEX=true`)  
+//! - Optionally store `(book_id, sentence_id)` postings for passage search
+//!   (`ENABLE_SENTENCE_INDEX=true`)  
+//! - Optionally store word co-occurrence counts (`ENABLE_COOCCURRENCE=true`)  
 //! - Ensure consistent indexing for rebuild and incremental ingestion
+//! - Time each pipeline stage and feed the `/metrics` histograms
+//! - Flag books while they are written and checkpoint rebuilds so an
+//!   interrupted rebuild can be resumed
+//! - Record each body file's fingerprint so stale books can be found later
+//! - Record whether words were folded (`FOLD_DIACRITICS`), see
+//!   [`rebuild_from_datalake`]
 
-use crate::models::storage::{Backend, BookMetadata, StorageBackend};
-use crate::utils::file::find_book_files;
-use crate::utils::text::tokenize_text;
+use crate::models::responses::{RebuildFailure, StageTimings};
+use crate::models::storage::{Backend, BookFingerprint, BookMetadata, IndexField, StorageBackend, StorageError};
+use crate::services::metrics::metrics;
+use crate::services::shutdown::Shutdown;
+use crate::utils::chapter::detect_chapters;
+use crate::utils::cooccurrence::count_cooccurrences;
+use crate::utils::file::{
+    file_fingerprint, find_book_files_in, list_all_book_ids, read_text, FileText,
+};
+use crate::utils::text::{
+    flesch_kincaid_grade, split_sentences, tokenize_body, tokenize_text, tokenize_text_fast, TokenizerConfig,
+};
 use regex::Regex;
 use std::collections::HashSet;
 use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::warn;
 
-fn extract_metadata_from_header(header_content: &str, book_id: u32) -> BookMetadata {
+pub fn extract_metadata_from_header(header_content: &str, book_id: u32) -> BookMetadata {
     let title_re = Regex::new(r"(?i)title:\s*(.+)").unwrap();
     let author_re = Regex::new(r"(?i)author:\s*(.+)").unwrap();
     let lang_re = Regex::new(r"(?i)language:\s*(.+)").unwrap();
     let year_re =
         Regex::new(r"(?i)(?:release date|posting date|release|date):\s*.*?(\d{4})").unwrap();
+    let subject_re = Regex::new(r"(?im)^\s*subject:\s*(.+?)\s*$").unwrap();
 
     let title = title_re
         .captures(header_content)
@@ -49,6 +74,12 @@ fn extract_metadata_from_header(header_content: &str, book_id: u32) -> BookMetad
         .and_then(|cap| cap.get(1))
         .and_then(|m| m.as_str().parse::<u32>().ok());
 
+    let subjects = subject_re
+        .captures_iter(header_content)
+        .filter_map(|cap| cap.get(1))
+        .map(|m| m.as_str().to_string())
+        .collect();
+
     BookMetadata {
         book_id,
         title,
@@ -57,33 +88,726 @@ fn extract_metadata_from_header(header_content: &str, book_id: u32) -> BookMetad
         year,
         word_count: 0,
         unique_words: 0,
+        chapter_count: 0,
+        subjects,
+        reading_level: None,
     }
 }
 
-pub async fn process_book(
+/// Prefix distinguishing subject tokens from body and title words in the index.
+pub const SUBJECT_PREFIX: &str = "subj:";
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+/// Whether chapter-level postings should be written (`ENABLE_CHAPTER_INDEX=true`).
+fn chapter_indexing_enabled() -> bool {
+    env_flag("ENABLE_CHAPTER_INDEX")
+}
+
+/// Whether sentence-level postings should be written
+/// (`ENABLE_SENTENCE_INDEX=true`). Off by default: every sentence stores its
+/// own postings, several times the size of the book-level index.
+fn sentence_indexing_enabled() -> bool {
+    env_flag("ENABLE_SENTENCE_INDEX")
+}
+
+/// Whether word co-occurrence counts should be written
+/// (`ENABLE_COOCCURRENCE=true`). Off by default: a book stores roughly ten
+/// times as many pairs as words.
+fn cooccurrence_indexing_enabled() -> bool {
+    env_flag("ENABLE_COOCCURRENCE")
+}
+
+/// Reads a book's header and body from the datalake rooted at
+/// `datalake_path` and extracts its metadata. Large bodies are
+/// memory-mapped rather than copied (see [`read_text`]). Also returns the
+/// body file's fingerprint, taken before the body is read, so a rewrite
+/// during indexing still shows up as stale afterwards.
+pub fn read_book_in(
+    datalake_path: &Path,
     book_id: u32,
-    backend: &Backend,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let (header_path, body_path) =
-        find_book_files(book_id).ok_or(format!("Book {} files not found", book_id))?;
+) -> Result<(BookMetadata, FileText, BookFingerprint), Box<dyn std::error::Error + Send + Sync>> {
+    let (header_path, body_path) = find_book_files_in(datalake_path, book_id)
+        .ok_or(format!("Book {} files not found", book_id))?;
 
+    let fingerprint = file_fingerprint(Path::new(&body_path))?;
     let header_content = fs::read_to_string(&header_path)?;
-    let body_content = fs::read_to_string(&body_path)?;
+    let body_content = read_text(Path::new(&body_path))?;
 
-    let mut metadata = extract_metadata_from_header(&header_content, book_id);
-    let words = tokenize_text(&body_content);
-    let title_words = tokenize_text(&metadata.title);
+    Ok((extract_metadata_from_header(&header_content, book_id), body_content, fingerprint))
+}
+
+/// Returns every word written to the unscoped index for a book: the body
+/// and title tokens, and the subject tokens prefixed with [`SUBJECT_PREFIX`].
+/// Author tokens are only indexed in their own field.
+pub fn index_words(metadata: &BookMetadata, body_words: &HashSet<String>) -> HashSet<String> {
+    let subject_words = metadata
+        .subjects
+        .iter()
+        .flat_map(|subject| tokenize_text(subject))
+        .map(|word| format!("{}{}", SUBJECT_PREFIX, word));
+
+    body_words
+        .iter()
+        .cloned()
+        .chain(tokenize_text(&metadata.title))
+        .chain(subject_words)
+        .collect()
+}
+
+/// Summary of a successfully indexed book.
+#[derive(Debug, Clone)]
+pub struct IndexReport {
+    /// Words in the body, as split on whitespace.
+    pub word_count: usize,
+    /// Distinct body tokens stored; suspiciously few for the word count
+    /// usually means an encoding or splitting problem.
+    pub unique_words: usize,
+    /// Indexing the book from start to finish.
+    pub elapsed_ms: u64,
+    pub timings: StageTimings,
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
+}
+
+/// Writes a `(book_id, sentence_id)` posting for every word of every
+/// sentence of the body, numbering sentences as [`split_sentences`] does so
+/// the search service can find their text again.
+pub async fn add_sentence_postings(backend: &Backend, book_id: u32, body: &str) -> Result<(), StorageError> {
+    for (sentence_id, sentence) in split_sentences(body).iter().enumerate() {
+        for word in tokenize_text_fast(sentence) {
+            backend.add_word_to_sentence_index(&word, book_id, sentence_id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Indexes a book read from the datalake rooted at `datalake_path`.
+///
+/// Runs in a `process_book` span carrying the book, the backend and, once
+/// tokenized, the word count.
+#[tracing::instrument(
+    name = "process_book",
+    skip(datalake_path, backend),
+    fields(backend = backend.kind(), word_count),
+    ret,
+    err
+)]
+pub async fn process_book_in(
+    datalake_path: &Path,
+    book_id: u32,
+    backend: &Backend,
+) -> Result<IndexReport, Box<dyn std::error::Error + Send + Sync>> {
+    let started = Instant::now();
+    let mut timings = StageTimings::default();
+
+    let stage = Instant::now();
+    let (mut metadata, body_content, fingerprint) = read_book_in(datalake_path, book_id)?;
+    timings.file_read = millis(stage.elapsed());
+
+    let stage = Instant::now();
+    let words = tokenize_body(&body_content);
+    let chapters = detect_chapters(&body_content);
 
     metadata.word_count = body_content.split_whitespace().count();
     metadata.unique_words = words.len();
+    metadata.chapter_count = chapters.len();
+    metadata.reading_level = flesch_kincaid_grade(&body_content);
+    tracing::Span::current().record("word_count", metadata.word_count);
 
-    let all_words: HashSet<String> = words.union(&title_words).cloned().collect();
+    let all_words = index_words(&metadata, &words);
+    let title_words = tokenize_text(&metadata.title);
+    let author_words = tokenize_text(&metadata.author);
+    let cooccurrences = cooccurrence_indexing_enabled().then(|| count_cooccurrences(&body_content));
+    timings.tokenization = millis(stage.elapsed());
+
+    // The first book indexed records how the index's words are spelled;
+    // afterwards only a completed rebuild changes it
+    if backend.get_diacritic_folding().await?.is_none() {
+        backend.set_diacritic_folding(TokenizerConfig::configured().fold_diacritics).await?;
+    }
 
+    // Stays set if we stop before the postings are written, so verification
+    // and resumed rebuilds know the book needs another pass.
+    backend.set_book_incomplete(book_id, true).await?;
+
+    let stage = Instant::now();
     backend.store_book_metadata(&metadata).await?;
+    timings.metadata_store = millis(stage.elapsed());
 
+    let stage = Instant::now();
     for word in &all_words {
-        backend.add_word_to_index(word, book_id).await?;
+        if !title_words.contains(word) {
+            backend.add_word_to_index(word, book_id, IndexField::Body).await?;
+        }
+    }
+    for word in &title_words {
+        backend.add_word_to_index(word, book_id, IndexField::Title).await?;
+    }
+    for word in &author_words {
+        backend.add_word_to_index(word, book_id, IndexField::Author).await?;
     }
 
-    Ok(())
+    if chapter_indexing_enabled() {
+        for chapter in &chapters {
+            for word in tokenize_body(chapter.text(&body_content)) {
+                backend
+                    .add_word_to_chapter_index(&word, book_id, chapter.id)
+                    .await?;
+            }
+        }
+    }
+
+    if sentence_indexing_enabled() {
+        add_sentence_postings(backend, book_id, &body_content).await?;
+    }
+
+    // Counts replace the book's previous ones; pairs that no longer occur
+    // after the text changed keep their old count.
+    for ((word_a, word_b), count) in cooccurrences.into_iter().flatten() {
+        backend.add_cooccurrence(&word_a, &word_b, book_id, count).await?;
+    }
+    timings.postings_write = millis(stage.elapsed());
+
+    backend.set_book_fingerprint(book_id, fingerprint).await?;
+    backend.set_book_incomplete(book_id, false).await?;
+    metrics().observe_book(&timings);
+
+    Ok(IndexReport {
+        word_count: metadata.word_count,
+        unique_words: metadata.unique_words,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        timings,
+    })
+}
+
+/// Outcome of indexing every book found in the datalake, or the ones asked
+/// for.
+#[derive(Debug, Default)]
+pub struct RebuildOutcome {
+    /// Books the rebuild covered: those listed in the datalake, or the
+    /// distinct IDs asked for.
+    pub requested: usize,
+    pub attempted: usize,
+    pub indexed: usize,
+    pub failures: Vec<RebuildFailure>,
+    /// Checkpoint the rebuild resumed after, if any.
+    pub resumed_from: Option<u32>,
+    /// Set when shutdown stopped the rebuild before the last book.
+    pub interrupted: bool,
+    /// Books asked for that have no files in the datalake.
+    pub skipped_not_found: Vec<u32>,
+}
+
+/// Indexes every book in the datalake rooted at `datalake_path`, in book ID
+/// order.
+///
+/// A failing book never aborts the rebuild: its error is logged and
+/// collected in [`RebuildOutcome::failures`] while the remaining books are
+/// still processed. The words-per-second gauge is updated after every book
+/// so throughput can be watched while the rebuild runs.
+///
+/// The last completed book is checkpointed in the backend. A book that
+/// fails is not checkpointed but flagged incomplete, so verification and a
+/// resumed rebuild pick it up again. With `resume`, books up to the
+/// checkpoint are skipped unless they are still flagged incomplete. Once
+/// `shutdown` is triggered the rebuild stops after the book in flight,
+/// leaving the checkpoint in place.
+///
+/// A completed rebuild records the current `FOLD_DIACRITICS`, so rebuilding
+/// is how the index moves to a new folding setting. Words stored under the
+/// old setting are not removed, so turning folding off leaves the folded
+/// spellings matching until the index is rebuilt from empty.
+pub async fn rebuild_from_datalake(
+    datalake_path: &Path,
+    backend: &Backend,
+    resume: bool,
+    shutdown: &Shutdown,
+) -> Result<RebuildOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    let mut outcome = RebuildOutcome::default();
+    let started = Instant::now();
+    let mut words_indexed = 0;
+
+    let incomplete = backend.get_incomplete_books().await?;
+    if resume {
+        outcome.resumed_from = backend.get_rebuild_checkpoint().await?;
+    } else {
+        backend.save_rebuild_checkpoint(None).await?;
+    }
+
+    let book_ids = list_all_book_ids(datalake_path);
+    outcome.requested = book_ids.len();
+    for book_id in book_ids {
+        let already_done = outcome
+            .resumed_from
+            .is_some_and(|checkpoint| book_id <= checkpoint && !incomplete.contains(&book_id));
+        if already_done {
+            continue;
+        }
+
+        if rebuild_book(datalake_path, book_id, backend, started, &mut words_indexed, &mut outcome).await {
+            backend.save_rebuild_checkpoint(Some(book_id)).await?;
+        } else {
+            // A book that failed before its postings were written isn't
+            // flagged yet, and would be skipped once later books move the
+            // checkpoint past it.
+            backend.set_book_incomplete(book_id, true).await?;
+        }
+
+        if shutdown.is_triggered() {
+            warn!(book_id, "Shutdown requested, stopping rebuild after this book");
+            outcome.interrupted = true;
+            return Ok(outcome);
+        }
+    }
+
+    backend.set_diacritic_folding(TokenizerConfig::configured().fold_diacritics).await?;
+    backend.save_rebuild_checkpoint(None).await?;
+    Ok(outcome)
+}
+
+/// Re-indexes only `book_ids` from the datalake rooted at `datalake_path`,
+/// each once, in ID order; e.g. after a tokenizer change that affects a
+/// few books. Books without files in the datalake are skipped and listed in
+/// [`RebuildOutcome::skipped_not_found`]. Failures and shutdown are handled
+/// as in [`rebuild_from_datalake`], but the rebuild checkpoint and the
+/// recorded folding setting are left alone, since the rest of the index
+/// isn't rebuilt.
+pub async fn rebuild_books_in(
+    datalake_path: &Path,
+    backend: &Backend,
+    book_ids: &[u32],
+    shutdown: &Shutdown,
+) -> RebuildOutcome {
+    let mut outcome = RebuildOutcome::default();
+    let started = Instant::now();
+    let mut words_indexed = 0;
+
+    let mut book_ids = book_ids.to_vec();
+    book_ids.sort_unstable();
+    book_ids.dedup();
+    outcome.requested = book_ids.len();
+
+    for book_id in book_ids {
+        if find_book_files_in(datalake_path, book_id).is_none() {
+            warn!(book_id, "Skipping book not in the datalake during rebuild");
+            outcome.skipped_not_found.push(book_id);
+            continue;
+        }
+
+        rebuild_book(datalake_path, book_id, backend, started, &mut words_indexed, &mut outcome).await;
+
+        if shutdown.is_triggered() {
+            warn!(book_id, "Shutdown requested, stopping rebuild after this book");
+            outcome.interrupted = true;
+            break;
+        }
+    }
+    outcome
+}
+
+/// Indexes one book of a rebuild that started at `started`, recording the
+/// result in `outcome` and the words-per-second gauge. Returns whether the
+/// book was indexed.
+async fn rebuild_book(
+    datalake_path: &Path,
+    book_id: u32,
+    backend: &Backend,
+    started: Instant,
+    words_indexed: &mut usize,
+    outcome: &mut RebuildOutcome,
+) -> bool {
+    outcome.attempted += 1;
+    match process_book_in(datalake_path, book_id, backend).await {
+        Ok(report) => {
+            outcome.indexed += 1;
+            *words_indexed += report.word_count;
+            metrics().set_rebuild_throughput(*words_indexed as f64 / started.elapsed().as_secs_f64());
+            true
+        }
+        Err(e) => {
+            warn!(book_id, error = %e, "Failed to index book during rebuild");
+            outcome.failures.push(RebuildFailure {
+                book_id,
+                error: e.to_string(),
+            });
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const FRANKENSTEIN_HEADER: &str = "\
+The Project Gutenberg eBook of Frankenstein; Or, The Modern Prometheus
+
+This ebook is for the use of anyone anywhere in the United States and
+most other parts of the world at no cost and with almost no restrictions
+whatsoever.
+
+Title: Frankenstein; Or, The Modern Prometheus
+
+Author: Mary Wollstonecraft Shelley
+
+Release date: October 1, 1993 [eBook #84]
+                Most recently updated: March 8, 2024
+
+Language: English
+
+Subject: Science fiction
+Subject: Horror tales
+Subject: Frankenstein's monster (Fictitious character) -- Fiction
+
+Credits: Judith Boss, Christy Phillips, Lynn Hanninen and David Meltzer.
+";
+
+    #[test]
+    fn extracts_all_subject_lines() {
+        let metadata = extract_metadata_from_header(FRANKENSTEIN_HEADER, 84);
+
+        assert_eq!(metadata.title, "Frankenstein; Or, The Modern Prometheus");
+        assert_eq!(metadata.author, "Mary Wollstonecraft Shelley");
+        assert_eq!(metadata.year, Some(1993));
+        assert_eq!(
+            metadata.subjects,
+            vec![
+                "Science fiction",
+                "Horror tales",
+                "Frankenstein's monster (Fictitious character) -- Fiction",
+            ]
+        );
+    }
+
+    #[test]
+    fn header_without_subjects_yields_empty_list() {
+        let header = "Title: Pride and Prejudice\nAuthor: Jane Austen\nLanguage: English\n";
+        let metadata = extract_metadata_from_header(header, 1342);
+
+        assert!(metadata.subjects.is_empty());
+    }
+
+    #[test]
+    fn subject_words_are_indexed_with_prefix() {
+        let metadata = extract_metadata_from_header(FRANKENSTEIN_HEADER, 84);
+        let body_words = tokenize_text("The monster walked through the ice");
+        let words = index_words(&metadata, &body_words);
+
+        assert!(words.contains("subj:science"));
+        assert!(words.contains("subj:fiction"));
+        assert!(words.contains("subj:horror"));
+        assert!(words.contains("monster"));
+        assert!(words.contains("prometheus"));
+        assert!(!words.contains("science"));
+    }
+
+    fn plant_book(datalake: &Path, book_id: u32, title: &str, body: &[u8]) {
+        let dir = datalake.join("20240101").join("00");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("header_{}.txt", book_id)),
+            format!("Title: {}\nAuthor: Test Author\nLanguage: en\n", title),
+        )
+        .unwrap();
+        fs::write(dir.join(format!("body_{}.txt", book_id)), body).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rebuild_reports_failures_and_keeps_going() {
+        let datalake = tempfile::tempdir().unwrap();
+        plant_book(datalake.path(), 1, "First Book", b"The whale swam far away.");
+        plant_book(datalake.path(), 2, "Broken Book", &[0xff, 0xfe, 0xfd, 0x00, 0xc3]);
+        plant_book(datalake.path(), 3, "Third Book", b"A quiet village by the sea.");
+
+        let backend = Backend::Memory(MemoryBackend::new());
+        let outcome = rebuild_from_datalake(datalake.path(), &backend, false, &Shutdown::new())
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.attempted, 3);
+        assert_eq!(outcome.indexed, 2);
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(outcome.failures[0].book_id, 2);
+        assert!(!outcome.failures[0].error.is_empty());
+
+        assert!(backend.is_book_indexed(1).await.unwrap());
+        assert!(!backend.is_book_indexed(2).await.unwrap());
+        assert!(backend.is_book_indexed(3).await.unwrap());
+        assert!(backend.search_word("whale").await.unwrap().contains(&1));
+    }
+
+    #[tokio::test]
+    async fn rebuilds_only_the_books_asked_for() {
+        let datalake = tempfile::tempdir().unwrap();
+        for book_id in [84, 1342, 2701] {
+            plant_book(datalake.path(), book_id, "Some Book", b"The whale swam far away.");
+        }
+        let backend = Backend::Memory(MemoryBackend::new());
+        backend.save_rebuild_checkpoint(Some(84)).await.unwrap();
+
+        let outcome =
+            rebuild_books_in(datalake.path(), &backend, &[1342, 99999, 84, 1342], &Shutdown::new()).await;
+
+        assert_eq!(outcome.requested, 3);
+        assert_eq!(outcome.attempted, 2);
+        assert_eq!(outcome.indexed, 2);
+        assert_eq!(outcome.skipped_not_found, [99999]);
+        assert!(outcome.failures.is_empty());
+        assert_eq!(backend.get_indexed_books().await.unwrap(), [84, 1342].into());
+        assert_eq!(backend.get_rebuild_checkpoint().await.unwrap(), Some(84));
+        assert_eq!(backend.get_diacritic_folding().await.unwrap(), Some(TokenizerConfig::configured().fold_diacritics));
+    }
+
+    #[tokio::test]
+    async fn indexes_title_and_author_in_their_own_fields() {
+        let datalake = tempfile::tempdir().unwrap();
+        plant_book(datalake.path(), 2701, "Moby Dick", b"Call me Ishmael. The whale is white.");
+
+        let backend = Backend::Memory(MemoryBackend::new());
+        process_book_in(datalake.path(), 2701, &backend).await.unwrap();
+
+        let in_field = |word: &'static str, field| {
+            let backend = backend.clone();
+            async move { backend.get_books_for_word_in_field(word, field).await.unwrap() }
+        };
+        assert!(in_field("moby", IndexField::Title).await.contains(&2701));
+        assert!(in_field("author", IndexField::Author).await.contains(&2701));
+        assert!(in_field("whale", IndexField::Title).await.is_empty());
+        assert!(in_field("whale", IndexField::Body).await.contains(&2701));
+        assert!(backend.search_word("moby").await.unwrap().contains(&2701));
+        assert!(backend.search_word("author").await.unwrap().is_empty());
+
+        let stats = backend.get_field_stats().await.unwrap();
+        assert_eq!(stats[&IndexField::Title], 2);
+        assert_eq!(stats[&IndexField::Author], 2);
+    }
+
+    #[tokio::test]
+    async fn rebuild_records_the_folding_setting() {
+        let datalake = tempfile::tempdir().unwrap();
+        plant_book(datalake.path(), 1, "First Book", b"The whale swam far away.");
+        let backend = Backend::Memory(MemoryBackend::new());
+        assert_eq!(backend.get_diacritic_folding().await.unwrap(), None);
+
+        // An index built under the other setting is migrated by the rebuild
+        backend.set_diacritic_folding(true).await.unwrap();
+        rebuild_from_datalake(datalake.path(), &backend, false, &Shutdown::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.get_diacritic_folding().await.unwrap(),
+            Some(TokenizerConfig::configured().fold_diacritics)
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_interrupted_rebuild_without_reindexing() {
+        let datalake = tempfile::tempdir().unwrap();
+        for book_id in 1..=3 {
+            plant_book(datalake.path(), book_id, "Some Book", b"The whale swam far away.");
+        }
+        let backend = Backend::Memory(MemoryBackend::new());
+
+        // Shutdown is already requested, so the book in flight (the first
+        // one) completes and the rebuild stops right after it.
+        let shutdown = Shutdown::new();
+        shutdown.trigger();
+        let first = rebuild_from_datalake(datalake.path(), &backend, false, &shutdown)
+            .await
+            .unwrap();
+
+        assert!(first.interrupted);
+        assert_eq!(first.indexed, 1);
+        assert_eq!(backend.get_rebuild_checkpoint().await.unwrap(), Some(1));
+
+        // "Restart" with a fresh shutdown handle over the same backend.
+        let resumed = rebuild_from_datalake(datalake.path(), &backend, true, &Shutdown::new())
+            .await
+            .unwrap();
+
+        assert!(!resumed.interrupted);
+        assert_eq!(resumed.resumed_from, Some(1));
+        assert_eq!(resumed.attempted, 2);
+        assert_eq!(first.indexed + resumed.indexed, 3);
+        assert_eq!(backend.get_indexed_books().await.unwrap(), [1, 2, 3].into());
+        assert!(backend.get_incomplete_books().await.unwrap().is_empty());
+        assert_eq!(backend.get_rebuild_checkpoint().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn resume_reindexes_books_left_incomplete() {
+        let datalake = tempfile::tempdir().unwrap();
+        for book_id in 1..=2 {
+            plant_book(datalake.path(), book_id, "Some Book", b"The whale swam far away.");
+        }
+        let backend = Backend::Memory(MemoryBackend::new());
+
+        // Book 1 was cut off past the grace period after being checkpointed.
+        backend.save_rebuild_checkpoint(Some(1)).await.unwrap();
+        backend.set_book_incomplete(1, true).await.unwrap();
+
+        let outcome = rebuild_from_datalake(datalake.path(), &backend, true, &Shutdown::new())
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.attempted, 2);
+        assert!(backend.get_incomplete_books().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resume_retries_books_that_failed() {
+        let datalake = tempfile::tempdir().unwrap();
+        plant_book(datalake.path(), 1, "Broken Book", &[0xff, 0xfe, 0xfd, 0x00, 0xc3]);
+        plant_book(datalake.path(), 2, "Some Book", b"The whale swam far away.");
+        let backend = Backend::Memory(MemoryBackend::new());
+
+        // Book 1 can't be read, and shutdown stops the rebuild right after it
+        let shutdown = Shutdown::new();
+        shutdown.trigger();
+        let first = rebuild_from_datalake(datalake.path(), &backend, false, &shutdown)
+            .await
+            .unwrap();
+
+        assert_eq!(first.failures.len(), 1);
+        assert_eq!(backend.get_rebuild_checkpoint().await.unwrap(), None);
+        assert_eq!(backend.get_incomplete_books().await.unwrap(), [1].into());
+
+        plant_book(datalake.path(), 1, "Fixed Book", b"A quiet village by the sea.");
+        // Later books moved the checkpoint past it
+        backend.save_rebuild_checkpoint(Some(2)).await.unwrap();
+        let resumed = rebuild_from_datalake(datalake.path(), &backend, true, &Shutdown::new())
+            .await
+            .unwrap();
+
+        assert_eq!(resumed.attempted, 1);
+        assert!(backend.is_book_indexed(1).await.unwrap());
+        assert!(backend.get_incomplete_books().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_plausible_stage_timings() {
+        let datalake = tempfile::tempdir().unwrap();
+        let body = "It is a truth universally acknowledged, that a single man in possession \
+                    of a good fortune, must be in want of a wife. "
+            .repeat(200);
+        plant_book(datalake.path(), 1342, "Pride and Prejudice", body.as_bytes());
+
+        let backend = Backend::Memory(MemoryBackend::new());
+        let started = Instant::now();
+        let report = process_book_in(datalake.path(), 1342, &backend).await.unwrap();
+        let total = millis(started.elapsed());
+
+        let timings = report.timings;
+        let stages = [
+            timings.file_read,
+            timings.tokenization,
+            timings.metadata_store,
+            timings.postings_write,
+        ];
+        assert!(stages.iter().all(|&ms| ms > 0.0), "{:?}", timings);
+        assert!(stages.iter().sum::<f64>() <= total);
+        assert_eq!(report.word_count, 23 * 200);
+        assert_eq!(report.unique_words, tokenize_body(&body).len());
+        assert!(report.elapsed_ms as f64 <= total);
+
+        let json = serde_json::to_string(&timings).unwrap();
+        let field_positions: Vec<usize> = ["file_read", "tokenization", "metadata_store", "postings_write"]
+            .iter()
+            .map(|field| json.find(field).unwrap())
+            .collect();
+        assert!(field_positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    type SpanFields = HashMap<String, String>;
+
+    /// Span names with the fields recorded on them, in the order the spans
+    /// were opened.
+    #[derive(Clone, Default)]
+    struct SpanCapture(Arc<Mutex<Vec<(String, SpanFields)>>>);
+
+    struct FieldVisitor<'a>(&'a mut SpanFields);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for SpanCapture
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = HashMap::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            fields.insert("id".to_string(), id.into_u64().to_string());
+            self.0.lock().unwrap().push((attrs.metadata().name().to_string(), fields));
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let id = id.into_u64().to_string();
+            let mut spans = self.0.lock().unwrap();
+            if let Some((_, fields)) = spans.iter_mut().rev().find(|(_, fields)| fields["id"] == id) {
+                values.record(&mut FieldVisitor(fields));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn process_book_runs_in_a_span_with_its_fields() {
+        use tracing::instrument::WithSubscriber;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let datalake = tempfile::tempdir().unwrap();
+        plant_book(datalake.path(), 84, "Frankenstein", b"You will rejoice to hear that no disaster has accompanied");
+
+        let capture = SpanCapture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        let backend = Backend::Memory(MemoryBackend::new());
+        process_book_in(datalake.path(), 84, &backend)
+            .with_subscriber(subscriber)
+            .await
+            .unwrap();
+
+        let spans = capture.0.lock().unwrap();
+        let (name, fields) = &spans[0];
+        assert_eq!(name, "process_book");
+        assert_eq!(fields["book_id"], "84");
+        assert_eq!(fields["backend"], "\"memory\"");
+        assert_eq!(fields["word_count"], "10");
+    }
+
+    #[tokio::test]
+    async fn sentence_postings_number_sentences_across_wrapped_lines() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        let body = "Call me Ishmael. Some years ago I went\nto sea to see the whale.\n\nThe whale was white.";
+        add_sentence_postings(&backend, 2701, body).await.unwrap();
+
+        assert_eq!(backend.search_word_sentences("ishmael").await.unwrap(), HashSet::from([(2701, 0)]));
+        assert_eq!(
+            backend.search_word_sentences("whale").await.unwrap(),
+            HashSet::from([(2701, 1), (2701, 2)])
+        );
+        assert_eq!(backend.search_word_sentences("sea").await.unwrap(), HashSet::from([(2701, 1)]));
+    }
 }
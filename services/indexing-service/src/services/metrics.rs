@@ -0,0 +1,119 @@
+//! Indexing Metrics
+//!
+//! Prometheus instrumentation for the indexing pipeline, exposed on
+//! `GET /metrics`:
+//!
+//! - `indexing_stage_duration_seconds{stage}` — histogram per pipeline stage
+//!   (`file_read`, `tokenization`, `metadata_store`, `postings_write`)
+//! - `indexing_books_indexed_total` — books indexed successfully
+//! - `indexing_rebuild_words_per_second` — throughput of the running (or
+//!   last) rebuild
+
+use crate::models::responses::StageTimings;
+use prometheus::{Encoder, Gauge, HistogramOpts, HistogramVec, IntCounter, Registry, TextEncoder};
+use std::sync::OnceLock;
+
+/// Stage histogram buckets, in seconds: 1 ms up to ~30 s.
+const STAGE_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+pub struct IndexingMetrics {
+    registry: Registry,
+    stage_duration: HistogramVec,
+    books_indexed: IntCounter,
+    rebuild_words_per_second: Gauge,
+}
+
+impl IndexingMetrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let stage_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "indexing_stage_duration_seconds",
+                "Time spent in each stage of indexing a book",
+            )
+            .buckets(STAGE_BUCKETS.to_vec()),
+            &["stage"],
+        )
+        .unwrap();
+        let books_indexed = IntCounter::new(
+            "indexing_books_indexed_total",
+            "Number of books indexed successfully",
+        )
+        .unwrap();
+        let rebuild_words_per_second = Gauge::new(
+            "indexing_rebuild_words_per_second",
+            "Words indexed per second during the current or last rebuild",
+        )
+        .unwrap();
+
+        registry.register(Box::new(stage_duration.clone())).unwrap();
+        registry.register(Box::new(books_indexed.clone())).unwrap();
+        registry.register(Box::new(rebuild_words_per_second.clone())).unwrap();
+
+        Self {
+            registry,
+            stage_duration,
+            books_indexed,
+            rebuild_words_per_second,
+        }
+    }
+
+    /// Records the stage timings of one successfully indexed book.
+    pub fn observe_book(&self, timings: &StageTimings) {
+        for (stage, ms) in [
+            ("file_read", timings.file_read),
+            ("tokenization", timings.tokenization),
+            ("metadata_store", timings.metadata_store),
+            ("postings_write", timings.postings_write),
+        ] {
+            self.stage_duration
+                .with_label_values(&[stage])
+                .observe(ms / 1000.0);
+        }
+        self.books_indexed.inc();
+    }
+
+    pub fn set_rebuild_throughput(&self, words_per_second: f64) {
+        self.rebuild_words_per_second.set(words_per_second);
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Process-wide metrics registry.
+pub fn metrics() -> &'static IndexingMetrics {
+    static METRICS: OnceLock<IndexingMetrics> = OnceLock::new();
+    METRICS.get_or_init(IndexingMetrics::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_observed_stages() {
+        let metrics = IndexingMetrics::new();
+        metrics.observe_book(&StageTimings {
+            file_read: 2.0,
+            tokenization: 3.0,
+            metadata_store: 1.0,
+            postings_write: 10.0,
+        });
+        metrics.set_rebuild_throughput(1234.0);
+
+        let text = metrics.render();
+        assert!(text.contains(r#"indexing_stage_duration_seconds_count{stage="postings_write"} 1"#));
+        assert!(text.contains("indexing_books_indexed_total 1"));
+        assert!(text.contains("indexing_rebuild_words_per_second 1234"));
+    }
+}
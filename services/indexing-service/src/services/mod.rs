@@ -1 +1,12 @@
-pub mod indexing;
\ No newline at end of file
+pub mod auth;
+pub mod backpressure;
+pub mod compaction;
+pub mod grpc;
+pub mod indexing;
+pub mod metrics;
+pub mod shutdown;
+pub mod staleness;
+pub mod startup;
+pub mod timeout;
+pub mod transfer;
+pub mod verification;
\ No newline at end of file
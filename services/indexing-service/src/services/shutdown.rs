@@ -0,0 +1,89 @@
+//! Graceful Shutdown
+//!
+//! A cloneable flag raised on SIGTERM / ctrl-c. Handlers refuse new
+//! indexing work once it is set, and a running rebuild stops after the book
+//! it is currently indexing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    triggered: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once [`trigger`](Self::trigger) has been called.
+    pub async fn wait(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_triggered() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Waits for SIGTERM (container stop) or ctrl-c, then triggers `shutdown`.
+pub async fn listen_for_signals(shutdown: Shutdown) {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    shutdown.trigger();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn wait_resolves_after_trigger() {
+        let shutdown = Shutdown::new();
+        let waiter = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move { shutdown.wait().await })
+        };
+
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("wait should resolve")
+            .unwrap();
+        assert!(shutdown.is_triggered());
+    }
+}
@@ -0,0 +1,235 @@
+//! Stale Book Detection
+//!
+//! Compares the fingerprint recorded when each book was indexed with its
+//! body file in the datalake now. A forced re-ingest rewrites the file, so
+//! until the book is re-indexed its postings may not match its text.
+
+use crate::models::responses::{RebuildFailure, StaleBook, StaleReason};
+use crate::models::storage::{Backend, IndexField, StorageBackend, StorageError};
+use crate::services::indexing::process_book_in;
+use crate::services::shutdown::Shutdown;
+use crate::utils::file::{file_fingerprint, find_book_files_in};
+use std::collections::BTreeSet;
+use std::path::Path;
+use tracing::warn;
+
+/// Words read from the vocabulary per batch when looking for a book's postings.
+const SCAN_BATCH: usize = 500;
+
+/// Lists the indexed books whose body file no longer matches the recorded
+/// fingerprint, in book ID order.
+pub async fn find_stale_books(
+    datalake_path: &Path,
+    backend: &Backend,
+) -> Result<Vec<StaleBook>, Box<dyn std::error::Error + Send + Sync>> {
+    let recorded = backend.get_book_fingerprints().await?;
+    let mut book_ids: Vec<u32> = backend.get_indexed_books().await?.into_iter().collect();
+    book_ids.sort_unstable();
+
+    let mut stale = Vec::new();
+    for book_id in book_ids {
+        let current = find_book_files_in(datalake_path, book_id)
+            .and_then(|(_, body_path)| file_fingerprint(Path::new(&body_path)).ok());
+
+        let reason = match (current, recorded.get(&book_id)) {
+            (None, _) => StaleReason::Missing,
+            (Some(_), None) => StaleReason::Unrecorded,
+            (Some(current), Some(recorded)) if current != *recorded => StaleReason::Modified,
+            _ => continue,
+        };
+        stale.push(StaleBook { book_id, reason });
+    }
+
+    Ok(stale)
+}
+
+/// Outcome of re-indexing the stale books.
+#[derive(Debug, Default)]
+pub struct RefreshOutcome {
+    pub refreshed: Vec<u32>,
+    /// Books whose body file is missing; there is nothing to re-index from.
+    pub skipped: Vec<u32>,
+    pub failures: Vec<RebuildFailure>,
+    /// Set when shutdown stopped the refresh before the last book.
+    pub interrupted: bool,
+}
+
+/// Re-indexes exactly the books [`find_stale_books`] reports, except those
+/// whose body file is missing. Like a rebuild, a failing book is collected
+/// in [`RefreshOutcome::failures`] without stopping the others, and a
+/// triggered `shutdown` stops after the book in flight.
+///
+/// Each book's old postings are removed before it is indexed again, so
+/// words dropped from its text stop matching it. The book is flagged
+/// incomplete in between and misses searches until its new postings are
+/// written.
+pub async fn refresh_stale_books(
+    datalake_path: &Path,
+    backend: &Backend,
+    shutdown: &Shutdown,
+) -> Result<RefreshOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    let mut outcome = RefreshOutcome::default();
+
+    for StaleBook { book_id, reason } in find_stale_books(datalake_path, backend).await? {
+        if reason == StaleReason::Missing {
+            outcome.skipped.push(book_id);
+            continue;
+        }
+        if shutdown.is_triggered() {
+            warn!(book_id, "Shutdown requested, stopping stale refresh");
+            outcome.interrupted = true;
+            break;
+        }
+
+        let refreshed = async {
+            backend.set_book_incomplete(book_id, true).await?;
+            remove_book_postings(backend, book_id).await?;
+            process_book_in(datalake_path, book_id, backend).await
+        };
+        match refreshed.await {
+            Ok(_) => outcome.refreshed.push(book_id),
+            Err(e) => {
+                warn!(book_id, error = %e, "Failed to refresh stale book");
+                outcome.failures.push(RebuildFailure {
+                    book_id,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Removes every posting of `book_id`, walking the unscoped vocabulary and
+/// then the author words kept only in their own field.
+async fn remove_book_postings(backend: &Backend, book_id: u32) -> Result<(), StorageError> {
+    let mut words = BTreeSet::new();
+    for field in [IndexField::Body, IndexField::Author] {
+        let mut cursor = None;
+        loop {
+            let (batch, next) = backend.scan_words(field, cursor, SCAN_BATCH).await?;
+            if field.is_unscoped() {
+                words.extend(backend.words_with_book(&batch, book_id).await?);
+            } else {
+                let postings = backend.get_postings(&batch, field).await?;
+                words.extend(
+                    postings
+                        .into_iter()
+                        .filter(|(_, book_ids)| book_ids.contains(&book_id))
+                        .map(|(word, _)| word),
+                );
+            }
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+    }
+
+    for word in &words {
+        backend.remove_postings(word, &[book_id]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    fn plant_book(datalake: &Path, book_id: u32, body: &str) -> std::path::PathBuf {
+        let dir = datalake.join("20240101").join("00");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("header_{}.txt", book_id)),
+            "Title: Moby Dick\nAuthor: Herman Melville\nLanguage: en\n",
+        )
+        .unwrap();
+        let body_path = dir.join(format!("body_{}.txt", book_id));
+        fs::write(&body_path, body).unwrap();
+        body_path
+    }
+
+    /// Moves the file's mtime forward, as a re-ingest rewriting it would.
+    fn touch(path: &Path) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn touched_book_is_stale_until_refreshed() {
+        let datalake = tempfile::tempdir().unwrap();
+        let backend = Backend::Memory(MemoryBackend::new());
+        let body_path = plant_book(datalake.path(), 1, "Call me Ishmael.");
+        plant_book(datalake.path(), 2, "The whale surfaced.");
+        process_book_in(datalake.path(), 1, &backend).await.unwrap();
+        process_book_in(datalake.path(), 2, &backend).await.unwrap();
+        assert!(find_stale_books(datalake.path(), &backend).await.unwrap().is_empty());
+
+        touch(&body_path);
+        let stale = find_stale_books(datalake.path(), &backend).await.unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].book_id, 1);
+        assert_eq!(stale[0].reason, StaleReason::Modified);
+
+        let outcome = refresh_stale_books(datalake.path(), &backend, &Shutdown::new())
+            .await
+            .unwrap();
+        assert_eq!(outcome.refreshed, vec![1]);
+        assert!(find_stale_books(datalake.path(), &backend).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refresh_drops_words_no_longer_in_the_text() {
+        let datalake = tempfile::tempdir().unwrap();
+        let backend = Backend::Memory(MemoryBackend::new());
+        let body_path = plant_book(datalake.path(), 1, "Call me Ishmael.");
+        process_book_in(datalake.path(), 1, &backend).await.unwrap();
+        assert!(backend.search_word("ishmael").await.unwrap().contains(&1));
+
+        fs::write(&body_path, "The whale surfaced.").unwrap();
+        touch(&body_path);
+        let outcome = refresh_stale_books(datalake.path(), &backend, &Shutdown::new())
+            .await
+            .unwrap();
+        assert_eq!(outcome.refreshed, vec![1]);
+
+        assert!(backend.search_word("ishmael").await.unwrap().is_empty());
+        assert!(backend.search_word("whale").await.unwrap().contains(&1));
+        assert!(backend.search_word("moby").await.unwrap().contains(&1));
+        assert!(backend
+            .get_books_for_word_in_field("melville", IndexField::Author)
+            .await
+            .unwrap()
+            .contains(&1));
+        assert!(backend.get_incomplete_books().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_and_unrecorded_books_are_reported() {
+        let datalake = tempfile::tempdir().unwrap();
+        let backend = Backend::Memory(MemoryBackend::new());
+        let body_path = plant_book(datalake.path(), 1, "Call me Ishmael.");
+        process_book_in(datalake.path(), 1, &backend).await.unwrap();
+        fs::remove_file(body_path).unwrap();
+
+        // Indexed by an older version that didn't record fingerprints.
+        plant_book(datalake.path(), 2, "The whale surfaced.");
+        let mut metadata = backend.get_book_metadata(1).await.unwrap().unwrap();
+        metadata.book_id = 2;
+        backend.store_book_metadata(&metadata).await.unwrap();
+
+        let stale = find_stale_books(datalake.path(), &backend).await.unwrap();
+        let reasons: Vec<_> = stale.iter().map(|book| (book.book_id, book.reason)).collect();
+        assert_eq!(reasons, vec![(1, StaleReason::Missing), (2, StaleReason::Unrecorded)]);
+
+        let outcome = refresh_stale_books(datalake.path(), &backend, &Shutdown::new())
+            .await
+            .unwrap();
+        assert_eq!(outcome.refreshed, vec![2]);
+        assert_eq!(outcome.skipped, vec![1]);
+    }
+}
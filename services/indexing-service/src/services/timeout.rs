@@ -0,0 +1,118 @@
+//! Request Timeouts
+//!
+//! Bounds how long the slow routes may run: a request still working when its
+//! timeout expires is dropped and answered with `504 Gateway Timeout` and a
+//! `GATEWAY_TIMEOUT` error. Dropping an index or rebuild request stops it
+//! between backend writes, the same as a shutdown: the book being written
+//! stays flagged incomplete, and a resumed rebuild continues from its
+//! checkpoint.
+//!
+//! ## Environment Variables
+//! - `INDEX_TIMEOUT_SECS`: `POST /index/update/:book_id` (default: `60`)
+//! - `REBUILD_TIMEOUT_SECS`: `POST /index/rebuild` (default: `300`)
+
+use crate::error::AppError;
+use axum::{error_handling::HandleErrorLayer, routing::MethodRouter, BoxError};
+use std::time::Duration;
+use tower::{timeout::error::Elapsed, timeout::TimeoutLayer, ServiceBuilder};
+
+const DEFAULT_INDEX_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_REBUILD_TIMEOUT_SECS: u64 = 5 * 60;
+
+/// Timeouts of the routes that have one.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeouts {
+    pub index: Duration,
+    pub rebuild: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            index: Duration::from_secs(DEFAULT_INDEX_TIMEOUT_SECS),
+            rebuild: Duration::from_secs(DEFAULT_REBUILD_TIMEOUT_SECS),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Reads `INDEX_TIMEOUT_SECS` and `REBUILD_TIMEOUT_SECS`; zero or
+    /// unparsable values keep the default.
+    pub fn from_env() -> Self {
+        let secs = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            index: Duration::from_secs(secs("INDEX_TIMEOUT_SECS", DEFAULT_INDEX_TIMEOUT_SECS)),
+            rebuild: Duration::from_secs(secs("REBUILD_TIMEOUT_SECS", DEFAULT_REBUILD_TIMEOUT_SECS)),
+        }
+    }
+}
+
+/// `route`, answering with a `504` once it has run for `timeout`.
+pub fn with_timeout<S>(route: MethodRouter<S>, timeout: Duration) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    route.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(timeout_error))
+            .layer(TimeoutLayer::new(timeout)),
+    )
+}
+
+async fn timeout_error(error: BoxError) -> AppError {
+    if error.is::<Elapsed>() {
+        AppError::Timeout
+    } else {
+        AppError::Internal(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::responses::ErrorResponse;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn app(handler_takes: Duration) -> Router {
+        let handler = move || async move {
+            tokio::time::sleep(handler_takes).await;
+            "done"
+        };
+        Router::new().route("/slow", with_timeout(get(handler), Duration::from_millis(50)))
+    }
+
+    #[tokio::test]
+    async fn slow_requests_get_a_gateway_timeout() {
+        let response = app(Duration::from_secs(5))
+            .oneshot(Request::get("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.error_code, "GATEWAY_TIMEOUT");
+        assert_eq!(error.message, "Request timed out");
+    }
+
+    #[tokio::test]
+    async fn requests_within_the_timeout_are_untouched() {
+        let response = app(Duration::ZERO)
+            .oneshot(Request::get("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
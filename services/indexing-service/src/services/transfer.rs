@@ -0,0 +1,317 @@
+//! Index Export and Import
+//!
+//! Moves a whole index between backends (e.g. Redis → Postgres) or into a
+//! backup file without re-processing the datalake. See
+//! [`IndexRecord`](crate::models::export::IndexRecord) for the wire format.
+//!
+//! Export is streamed a page of words at a time, with each page's postings
+//! read in one batch per field, so memory use doesn't grow with the size of
+//! the index. Author postings aren't in the unscoped vocabulary, so they are
+//! exported after it, from the author field's own vocabulary. Import relies on set semantics in every backend,
+//! which makes it idempotent: replaying a partially imported file is safe.
+
+use crate::models::export::IndexRecord;
+use crate::models::storage::{Backend, IndexField, StorageBackend, StorageError};
+use futures::stream::{self, Stream};
+use std::collections::{HashMap, HashSet, VecDeque};
+use thiserror::Error;
+
+/// Number of words fetched from the backend per scan page.
+const EXPORT_BATCH_SIZE: usize = 500;
+
+enum ExportState {
+    Start,
+    Books(std::vec::IntoIter<u32>),
+    Words {
+        /// `Body` while walking the unscoped vocabulary, then `Author`.
+        vocabulary: IndexField,
+        cursor: Option<String>,
+        pending: VecDeque<IndexRecord>,
+        exhausted: bool,
+    },
+}
+
+/// The fields whose postings are exported from each vocabulary.
+fn exported_fields(vocabulary: IndexField) -> &'static [IndexField] {
+    match vocabulary {
+        IndexField::Body => &[IndexField::Body, IndexField::Title],
+        _ => &[IndexField::Author],
+    }
+}
+
+/// One posting record per word and field with any postings, word by word.
+fn posting_records(words: Vec<String>, mut postings: Vec<(IndexField, HashMap<String, HashSet<u32>>)>) -> VecDeque<IndexRecord> {
+    let mut records = VecDeque::new();
+    for word in words {
+        for (field, by_word) in &mut postings {
+            if let Some(book_ids) = by_word.remove(&word) {
+                let mut book_ids: Vec<u32> = book_ids.into_iter().collect();
+                book_ids.sort_unstable();
+                records.push_back(IndexRecord::Posting {
+                    word: word.clone(),
+                    field: *field,
+                    book_ids,
+                });
+            }
+        }
+    }
+    records
+}
+
+/// Streams the whole index as NDJSON lines: all books, then all postings.
+pub fn export_index(backend: Backend) -> impl Stream<Item = Result<String, StorageError>> {
+    stream::try_unfold((backend, ExportState::Start), |(backend, mut state)| async move {
+        loop {
+            state = match state {
+                ExportState::Start => {
+                    let mut book_ids: Vec<u32> = backend.get_indexed_books().await?.into_iter().collect();
+                    book_ids.sort_unstable();
+                    ExportState::Books(book_ids.into_iter())
+                }
+                ExportState::Books(mut book_ids) => match book_ids.next() {
+                    Some(book_id) => {
+                        if let Some(metadata) = backend.get_book_metadata(book_id).await? {
+                            let line = to_line(&IndexRecord::Book { metadata })?;
+                            return Ok(Some((line, (backend, ExportState::Books(book_ids)))));
+                        }
+                        ExportState::Books(book_ids)
+                    }
+                    None => ExportState::Words {
+                        vocabulary: IndexField::Body,
+                        cursor: None,
+                        pending: VecDeque::new(),
+                        exhausted: false,
+                    },
+                },
+                ExportState::Words { vocabulary, cursor, mut pending, exhausted } => {
+                    if let Some(record) = pending.pop_front() {
+                        let line = to_line(&record)?;
+                        let next = ExportState::Words { vocabulary, cursor, pending, exhausted };
+                        return Ok(Some((line, (backend, next))));
+                    } else if exhausted && vocabulary.is_body() {
+                        ExportState::Words {
+                            vocabulary: IndexField::Author,
+                            cursor: None,
+                            pending,
+                            exhausted: false,
+                        }
+                    } else if exhausted {
+                        return Ok(None);
+                    } else {
+                        let (words, next_cursor) = backend.scan_words(vocabulary, cursor, EXPORT_BATCH_SIZE).await?;
+                        let mut postings = Vec::new();
+                        for &field in exported_fields(vocabulary) {
+                            postings.push((field, backend.get_postings(&words, field).await?));
+                        }
+                        ExportState::Words {
+                            vocabulary,
+                            exhausted: next_cursor.is_none(),
+                            cursor: next_cursor,
+                            pending: posting_records(words, postings),
+                        }
+                    }
+                }
+            };
+        }
+    })
+}
+
+fn to_line(record: &IndexRecord) -> Result<String, StorageError> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    Ok(line)
+}
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Invalid record on line {line}: {source}")]
+    InvalidRecord {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+/// Running totals for an import.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub lines: usize,
+    pub books_imported: usize,
+    pub words_imported: usize,
+    pub postings_imported: usize,
+}
+
+impl ImportSummary {
+    /// Parses one NDJSON line and writes it to `backend`. Blank lines are
+    /// skipped but still counted, so error line numbers match the input.
+    pub async fn import_line(&mut self, line: &str, backend: &Backend) -> Result<(), ImportError> {
+        self.lines += 1;
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        let record = serde_json::from_str(line).map_err(|source| ImportError::InvalidRecord {
+            line: self.lines,
+            source,
+        })?;
+
+        match record {
+            IndexRecord::Book { metadata } => {
+                backend.store_book_metadata(&metadata).await?;
+                self.books_imported += 1;
+            }
+            IndexRecord::Posting { word, field, book_ids } => {
+                backend.add_postings(&word, &book_ids, field).await?;
+                self.words_imported += 1;
+                self.postings_imported += book_ids.len();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{BookMetadata, MemoryBackend, PostgresBackend};
+    use futures::TryStreamExt;
+
+    fn metadata(book_id: u32, title: &str) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: title.to_string(),
+            author: "Unknown".to_string(),
+            language: "English".to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn seeded_backend() -> Backend {
+        let backend = Backend::Memory(MemoryBackend::new());
+        backend.store_book_metadata(&metadata(1342, "Pride and Prejudice")).await.unwrap();
+        backend.store_book_metadata(&metadata(84, "Frankenstein")).await.unwrap();
+        for word in ["monster", "creature"] {
+            backend.add_word_to_index(word, 84, IndexField::Body).await.unwrap();
+        }
+        for word in ["elizabeth", "darcy"] {
+            backend.add_word_to_index(word, 1342, IndexField::Body).await.unwrap();
+        }
+        backend.add_postings("the", &[84, 1342], IndexField::Body).await.unwrap();
+        backend.add_word_to_index("pride", 1342, IndexField::Title).await.unwrap();
+        backend.add_word_to_index("shelley", 84, IndexField::Author).await.unwrap();
+        backend
+    }
+
+    async fn import_all(dump: &str, backend: &Backend) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for line in dump.lines() {
+            summary.import_line(line, backend).await.unwrap();
+        }
+        summary
+    }
+
+    #[tokio::test]
+    async fn export_import_round_trip_preserves_index() {
+        let source = seeded_backend().await;
+        let dump: String = export_index(source.clone()).try_collect().await.unwrap();
+
+        let lines: Vec<&str> = dump.lines().collect();
+        // 2 books, 6 words, the title-scoped posting of "pride" and the
+        // author-only posting of "shelley", which comes last
+        assert_eq!(lines.len(), 2 + 6 + 1 + 1);
+        assert!(lines[0].starts_with(r#"{"type":"book""#));
+        assert!(lines[8].starts_with(r#"{"type":"posting""#));
+        assert!(lines[9].contains(r#""word":"shelley""#));
+
+        let target = Backend::Memory(MemoryBackend::new());
+        let summary = import_all(&dump, &target).await;
+
+        assert_eq!(summary.books_imported, 2);
+        assert_eq!(summary.words_imported, 8);
+        assert_eq!(summary.postings_imported, 9);
+        assert_eq!(target.get_stats().await.unwrap(), source.get_stats().await.unwrap());
+        assert_eq!(target.search_word("the").await.unwrap(), [84, 1342].into());
+        assert_eq!(
+            target.get_books_for_word_in_field("pride", IndexField::Title).await.unwrap(),
+            [1342].into()
+        );
+        assert_eq!(
+            target.get_books_for_word_in_field("shelley", IndexField::Author).await.unwrap(),
+            [84].into()
+        );
+        assert!(target.search_word("shelley").await.unwrap().is_empty());
+        assert_eq!(
+            target.get_book_metadata(84).await.unwrap().unwrap().title,
+            "Frankenstein"
+        );
+    }
+
+    #[tokio::test]
+    async fn import_is_idempotent() {
+        let dump: String = export_index(seeded_backend().await).try_collect().await.unwrap();
+
+        let target = Backend::Memory(MemoryBackend::new());
+        import_all(&dump, &target).await;
+        import_all(&dump, &target).await;
+
+        assert_eq!(target.get_stats().await.unwrap(), (2, 6));
+        assert_eq!(target.search_word("the").await.unwrap().len(), 2);
+    }
+
+    /// Imports into a real Postgres database when `TEST_DATABASE_URL` is
+    /// set, and is skipped otherwise. Only this test's own books and words
+    /// are checked, so a database shared with other data works too.
+    #[tokio::test]
+    async fn import_into_postgres_round_trips() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let target = Backend::Postgres(PostgresBackend::new(&url).await.unwrap());
+        for book_id in [84, 1342] {
+            target.delete_book(book_id).await.unwrap();
+        }
+        for word in ["monster", "creature", "elizabeth", "darcy", "the", "pride", "shelley"] {
+            target.remove_postings(word, &[84, 1342]).await.unwrap();
+        }
+
+        let dump: String = export_index(seeded_backend().await).try_collect().await.unwrap();
+        let (books_before, _) = target.get_stats().await.unwrap();
+        import_all(&dump, &target).await;
+        import_all(&dump, &target).await;
+
+        assert_eq!(target.get_stats().await.unwrap().0, books_before + 2);
+        assert_eq!(target.search_word("the").await.unwrap(), [84, 1342].into());
+        assert_eq!(target.search_word("monster").await.unwrap(), [84].into());
+        assert_eq!(
+            target.get_books_for_word_in_field("pride", IndexField::Title).await.unwrap(),
+            [1342].into()
+        );
+        assert_eq!(
+            target.get_books_for_word_in_field("shelley", IndexField::Author).await.unwrap(),
+            [84].into()
+        );
+        assert!(target.search_word("shelley").await.unwrap().is_empty());
+        assert_eq!(
+            target.get_book_metadata(1342).await.unwrap().unwrap().title,
+            "Pride and Prejudice"
+        );
+    }
+
+    #[tokio::test]
+    async fn import_reports_line_of_invalid_record() {
+        let backend = Backend::Memory(MemoryBackend::new());
+        let mut summary = ImportSummary::default();
+
+        summary
+            .import_line(r#"{"type":"posting","word":"whale","book_ids":[2701]}"#, &backend)
+            .await
+            .unwrap();
+        summary.import_line("", &backend).await.unwrap();
+        let err = summary.import_line("{not json", &backend).await.unwrap_err();
+
+        assert!(matches!(err, ImportError::InvalidRecord { line: 3, .. }));
+    }
+}
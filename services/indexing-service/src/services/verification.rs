@@ -0,0 +1,78 @@
+//! Index Verification
+//!
+//! Confirms that a book's words are actually present in the storage backend.
+//! The book is re-read from the datalake and tokenized exactly as during
+//! indexing, then every token is checked against the word-to-book mapping.
+//! Useful after a Redis eviction or a Postgres vacuum.
+
+use crate::models::responses::{IndexIntegrity, VerificationResult};
+use crate::models::storage::{Backend, StorageBackend};
+use crate::services::indexing::{index_words, read_book_in};
+use std::path::Path;
+use crate::utils::text::tokenize_body;
+
+/// Upper bound on the number of missing words listed in the result.
+const MAX_REPORTED_MISSING: usize = 100;
+
+pub async fn verify_book_index(
+    datalake_path: &Path,
+    book_id: u32,
+    backend: &Backend,
+) -> Result<VerificationResult, Box<dyn std::error::Error + Send + Sync>> {
+    let (metadata, body_content, _) = read_book_in(datalake_path, book_id)?;
+    let expected = index_words(&metadata, &tokenize_body(&body_content));
+
+    let metadata_present = backend.is_book_indexed(book_id).await?;
+    let incomplete = backend.get_incomplete_books().await?.contains(&book_id);
+
+    let expected: Vec<String> = expected.into_iter().collect();
+    let present = backend.words_with_book(&expected, book_id).await?;
+    let mut missing_words: Vec<String> = expected.iter().filter(|word| !present.contains(*word)).cloned().collect();
+    missing_words.sort();
+
+    let present_in_index = expected.len() - missing_words.len();
+    let integrity = classify_integrity(metadata_present, incomplete, expected.len(), present_in_index);
+    missing_words.truncate(MAX_REPORTED_MISSING);
+
+    Ok(VerificationResult {
+        book_id,
+        expected_words: expected.len(),
+        present_in_index,
+        missing_words,
+        incomplete,
+        integrity,
+    })
+}
+
+/// `ok` when every word is present, `corrupt` when the metadata record is
+/// gone or no word is present at all, and `degraded` in between. A book
+/// still flagged incomplete (indexing was interrupted) is never `ok`.
+fn classify_integrity(
+    metadata_present: bool,
+    incomplete: bool,
+    expected: usize,
+    present: usize,
+) -> IndexIntegrity {
+    if !metadata_present || (expected > 0 && present == 0) {
+        IndexIntegrity::Corrupt
+    } else if present < expected || incomplete {
+        IndexIntegrity::Degraded
+    } else {
+        IndexIntegrity::Ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_integrity() {
+        assert_eq!(classify_integrity(true, false, 10, 10), IndexIntegrity::Ok);
+        assert_eq!(classify_integrity(true, false, 0, 0), IndexIntegrity::Ok);
+        assert_eq!(classify_integrity(true, false, 10, 7), IndexIntegrity::Degraded);
+        assert_eq!(classify_integrity(true, true, 10, 10), IndexIntegrity::Degraded);
+        assert_eq!(classify_integrity(true, false, 10, 0), IndexIntegrity::Corrupt);
+        assert_eq!(classify_integrity(false, false, 10, 10), IndexIntegrity::Corrupt);
+    }
+}
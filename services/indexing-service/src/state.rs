@@ -0,0 +1,82 @@
+//! Shared Application State
+//!
+//! Everything the handlers share. Handlers extract only the part they need
+//! (`State<Backend>`, `State<IndexingLimiter>`, `State<Shutdown>`,
+//! `State<AuthToken>`, `State<Compactor>`, `State<Arc<Instant>>`,
+//! `State<StartupProbe>`, `State<Datalake>`) through [`FromRef`].
+
+use crate::models::storage::Backend;
+use crate::services::auth::AuthToken;
+use crate::services::backpressure::IndexingLimiter;
+use crate::services::compaction::Compactor;
+use crate::services::shutdown::Shutdown;
+use crate::services::startup::StartupProbe;
+use crate::services::timeout::RequestTimeouts;
+use crate::utils::file::Datalake;
+use axum::extract::FromRef;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub backend: Backend,
+    pub limiter: IndexingLimiter,
+    pub shutdown: Shutdown,
+    pub auth: AuthToken,
+    pub compactor: Compactor,
+    /// When the service started, for the uptime in `/status`.
+    pub started_at: Arc<Instant>,
+    pub startup: StartupProbe,
+    /// Read when building the router, not by handlers.
+    pub timeouts: RequestTimeouts,
+    /// Where books are read from.
+    pub datalake: Datalake,
+}
+
+impl FromRef<AppState> for Backend {
+    fn from_ref(state: &AppState) -> Self {
+        state.backend.clone()
+    }
+}
+
+impl FromRef<AppState> for IndexingLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Shutdown {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+impl FromRef<AppState> for AuthToken {
+    fn from_ref(state: &AppState) -> Self {
+        state.auth.clone()
+    }
+}
+
+impl FromRef<AppState> for Compactor {
+    fn from_ref(state: &AppState) -> Self {
+        state.compactor.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Instant> {
+    fn from_ref(state: &AppState) -> Self {
+        state.started_at.clone()
+    }
+}
+
+impl FromRef<AppState> for StartupProbe {
+    fn from_ref(state: &AppState) -> Self {
+        state.startup.clone()
+    }
+}
+
+impl FromRef<AppState> for Datalake {
+    fn from_ref(state: &AppState) -> Self {
+        state.datalake.clone()
+    }
+}
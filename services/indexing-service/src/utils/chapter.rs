@@ -0,0 +1,7 @@
+//! Chapter Utilities
+//!
+//! Chapter detection lives in the shared `common-text` crate, since the
+//! search service has to find the same chapters to title its chapter hits;
+//! it is re-exported here.
+
+pub use common_text::chapter::{detect_chapters, Chapter};
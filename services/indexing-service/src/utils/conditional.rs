@@ -0,0 +1,140 @@
+//! Conditional Requests
+//!
+//! `ETag` and `Last-Modified` validators for responses that only change
+//! when the index does, so polling clients can revalidate with
+//! `If-None-Match` (or `If-Modified-Since`) and get an empty `304` back
+//! instead of the same body again.
+//!
+//! ETags are weak (`W/"…"`): the body may be gzipped or not, but means the
+//! same either way.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The validators of one version of a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validators {
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validators {
+    /// Validators for a response determined by `state`, last changed at
+    /// `last_modified`. `DefaultHasher` is deterministic within a build, so
+    /// replicas of the same build agree on the ETag.
+    pub fn new(state: impl Hash, last_modified: Option<DateTime<Utc>>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        Self {
+            etag: format!("W/\"{:016x}\"", hasher.finish()),
+            last_modified,
+        }
+    }
+
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    /// Whether the client's cached copy, described by `If-None-Match` or,
+    /// without it, `If-Modified-Since`, is still current.
+    pub fn is_fresh(&self, request: &HeaderMap) -> bool {
+        if let Some(if_none_match) = request.get(header::IF_NONE_MATCH) {
+            let Ok(if_none_match) = if_none_match.to_str() else {
+                return false;
+            };
+            return if_none_match.split(',').map(str::trim).any(|tag| tag == "*" || weak_eq(tag, &self.etag));
+        }
+
+        let since = request
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+        match (since, self.last_modified) {
+            // HTTP dates have whole seconds
+            (Some(since), Some(last_modified)) => last_modified.timestamp() <= since.timestamp(),
+            _ => false,
+        }
+    }
+
+    /// An empty `304 Not Modified` carrying the validators.
+    pub fn not_modified(&self) -> Response {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        self.apply(response.headers_mut());
+        response
+    }
+
+    /// Adds `ETag` and, when the time is known, `Last-Modified` to `headers`.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            let date = last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            if let Ok(date) = HeaderValue::from_str(&date) {
+                headers.insert(header::LAST_MODIFIED, date);
+            }
+        }
+    }
+}
+
+/// Weak comparison: the tags match ignoring any `W/` prefix.
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn request(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn etag_changes_with_the_state() {
+        let updated = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let validators = Validators::new((10, Some(updated)), Some(updated));
+
+        assert_eq!(validators, Validators::new((10, Some(updated)), Some(updated)));
+        assert_ne!(validators.etag(), Validators::new((11, Some(updated)), Some(updated)).etag());
+        assert!(validators.etag().starts_with("W/\""));
+    }
+
+    #[test]
+    fn if_none_match_compares_weakly_and_takes_precedence() {
+        let updated = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let validators = Validators::new(10, Some(updated));
+        let strong = validators.etag().trim_start_matches("W/").to_string();
+
+        assert!(validators.is_fresh(&request(header::IF_NONE_MATCH, validators.etag())));
+        assert!(validators.is_fresh(&request(header::IF_NONE_MATCH, &format!("\"other\", {}", strong))));
+        assert!(validators.is_fresh(&request(header::IF_NONE_MATCH, "*")));
+        assert!(!validators.is_fresh(&request(header::IF_NONE_MATCH, "\"other\"")));
+
+        let mut headers = request(header::IF_NONE_MATCH, "\"other\"");
+        headers.insert(header::IF_MODIFIED_SINCE, HeaderValue::from_static("Fri, 01 Mar 2024 12:00:00 GMT"));
+        assert!(!validators.is_fresh(&headers));
+    }
+
+    #[test]
+    fn if_modified_since_compares_whole_seconds() {
+        let updated = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap() + chrono::Duration::milliseconds(250);
+        let validators = Validators::new(10, Some(updated));
+
+        let mut headers = HeaderMap::new();
+        validators.apply(&mut headers);
+        assert_eq!(headers[header::LAST_MODIFIED], "Fri, 01 Mar 2024 12:00:00 GMT");
+
+        assert!(validators.is_fresh(&request(header::IF_MODIFIED_SINCE, "Fri, 01 Mar 2024 12:00:00 GMT")));
+        assert!(!validators.is_fresh(&request(header::IF_MODIFIED_SINCE, "Fri, 01 Mar 2024 11:59:59 GMT")));
+        assert!(!Validators::new(10, None).is_fresh(&request(header::IF_MODIFIED_SINCE, "Fri, 01 Mar 2024 12:00:00 GMT")));
+    }
+}
@@ -0,0 +1,67 @@
+//! Word Co-occurrence
+//!
+//! Counts which indexed words appear near each other in a book body. Two
+//! words co-occur when they fall within [`COOCCURRENCE_WINDOW`] consecutive
+//! tokens, after short words have been dropped, so "an old man of the sea"
+//! pairs `old`, `man`, `the` and `sea` with each other.
+
+use crate::utils::text::tokenize_sequence;
+use std::collections::HashMap;
+
+/// Number of consecutive tokens a pair must fall within.
+pub const COOCCURRENCE_WINDOW: usize = 5;
+
+/// Counts every pair of distinct words within [`COOCCURRENCE_WINDOW`] tokens
+/// of each other. Pairs are keyed in alphabetical order, so `(a, b)` and
+/// `(b, a)` share a count.
+pub fn count_cooccurrences(text: &str) -> HashMap<(String, String), u32> {
+    let tokens = tokenize_sequence(text);
+    let mut counts = HashMap::new();
+
+    for (i, word) in tokens.iter().enumerate() {
+        for other in tokens.iter().skip(i + 1).take(COOCCURRENCE_WINDOW - 1) {
+            let pair = match word.cmp(other) {
+                std::cmp::Ordering::Less => (word.clone(), other.clone()),
+                std::cmp::Ordering::Greater => (other.clone(), word.clone()),
+                std::cmp::Ordering::Equal => continue,
+            };
+            *counts.entry(pair).or_insert(0) += 1;
+        }
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(a: &str, b: &str) -> (String, String) {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn pairs_words_within_the_window() {
+        let counts = count_cooccurrences("one two three four five six");
+
+        assert_eq!(counts[&pair("five", "one")], 1);
+        assert!(!counts.contains_key(&pair("one", "six")));
+        assert_eq!(counts[&pair("five", "six")], 1);
+        // Each of the six words pairs with up to four followers
+        assert_eq!(counts.len(), 4 + 4 + 3 + 2 + 1);
+    }
+
+    #[test]
+    fn skips_short_words_and_self_pairs() {
+        let counts = count_cooccurrences("A whale of a whale, a sea of a whale.");
+
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&pair("sea", "whale")], 3);
+    }
+
+    #[test]
+    fn counts_repeated_pairs() {
+        let counts = count_cooccurrences("love letter. love letter. letter love.");
+        assert_eq!(counts[&pair("letter", "love")], 9);
+    }
+}
@@ -4,16 +4,51 @@
 //! **datalake**.
 //!
 //! ## Responsibilities
-//! - Define the base datalake path used by the service  
+//! - Define the base datalake path used by the service, and the [`Datalake`]
+//!   handle the service reads it through  
 //! - Locate book files (`header_*.txt` and `body_*.txt`) across nested directories  
+//! - List every book ID present in the datalake, in the layouts
+//!   `BOOK_FILENAME_PATTERN` names  
 //! - Return matching file paths for downstream indexing operations
+//! - Read book text, memory-mapping large files instead of copying them to the heap
+//! - Fingerprint body files so re-ingested books can be detected
 
-use std::fs;
+use crate::models::storage::BookFingerprint;
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 
 pub const DATALAKE_PATH: &str = "/app/datalake";
 
-pub fn find_book_files(book_id: u32) -> Option<(String, String)> {
-    if let Ok(entries) = fs::read_dir(DATALAKE_PATH) {
+/// The root of the datalake the service reads books from: [`DATALAKE_PATH`]
+/// unless a test points it at a directory of its own.
+#[derive(Debug, Clone)]
+pub struct Datalake(Arc<PathBuf>);
+
+impl Datalake {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self(Arc::new(root.into()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Default for Datalake {
+    fn default() -> Self {
+        Self::new(DATALAKE_PATH)
+    }
+}
+
+/// The header and body files of `book_id` in the datalake rooted at
+/// `datalake_path`.
+pub fn find_book_files_in(datalake_path: &Path, book_id: u32) -> Option<(String, String)> {
+    if let Ok(entries) = fs::read_dir(datalake_path) {
         for date_entry in entries.flatten() {
             if date_entry
                 .file_type()
@@ -45,4 +80,237 @@ pub fn find_book_files(book_id: u32) -> Option<(String, String)> {
         }
     }
     None
-}
\ No newline at end of file
+}
+
+/// Where [`list_all_book_ids`] looks for book bodies by default, `{id}`
+/// standing for the book ID: the ingestion service's `body_{id}.txt`, and
+/// the `{id}_body.txt` and `book_{id}/body.txt` layouts of corpora copied
+/// into the datalake by hand. Indexing a book still reads it from the
+/// ingestion service's layout (see [`find_book_files_in`]).
+pub const DEFAULT_BOOK_FILENAME_PATTERN: &str = "body_{id}.txt,{id}_body.txt,book_{id}/body.txt";
+
+/// Reads `BOOK_FILENAME_PATTERN`, a comma-separated list of patterns like
+/// [`DEFAULT_BOOK_FILENAME_PATTERN`], falling back to that default.
+pub fn book_filename_patterns() -> Vec<String> {
+    let patterns = std::env::var("BOOK_FILENAME_PATTERN")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_BOOK_FILENAME_PATTERN.to_string());
+
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Lists the IDs of all books in the datalake whose body file matches a
+/// `BOOK_FILENAME_PATTERN` pattern (see [`book_filename_patterns`]), at
+/// any depth, sorted and without duplicates.
+pub fn list_all_book_ids(datalake_path: &Path) -> Vec<u32> {
+    list_book_ids_matching(datalake_path, &book_filename_patterns())
+}
+
+/// Same as [`list_all_book_ids`], with the filename patterns given.
+pub fn list_book_ids_matching(datalake_path: &Path, patterns: &[String]) -> Vec<u32> {
+    let patterns: Vec<Vec<&str>> = patterns
+        .iter()
+        .filter(|pattern| pattern.matches("{id}").count() == 1)
+        .map(|pattern| pattern.split('/').collect())
+        .collect();
+    let mut book_ids = Vec::new();
+    let mut dirs = vec![datalake_path.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(ft) if ft.is_dir() => dirs.push(path),
+                Ok(ft) if ft.is_file() => {
+                    let Ok(relative) = path.strip_prefix(datalake_path) else {
+                        continue;
+                    };
+                    let components: Vec<&str> = relative.iter().filter_map(|c| c.to_str()).collect();
+                    book_ids.extend(patterns.iter().find_map(|pattern| match_book_path(pattern, &components)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    book_ids.sort_unstable();
+    book_ids.dedup();
+    book_ids
+}
+
+/// The book ID in `components`, the path of a file below the datalake, if
+/// its last components match `pattern`'s.
+fn match_book_path(pattern: &[&str], components: &[&str]) -> Option<u32> {
+    let tail = components.get(components.len().checked_sub(pattern.len())?..)?;
+    let mut book_id = None;
+
+    for (expected, actual) in pattern.iter().zip(tail) {
+        match expected.split_once("{id}") {
+            Some((prefix, suffix)) => {
+                let id = actual.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                book_id = Some(id.parse().ok()?);
+            }
+            None if expected == actual => {}
+            None => return None,
+        }
+    }
+    book_id
+}
+
+/// Files smaller than this are read onto the heap; mapping them costs more
+/// (syscalls, page faults, TLB entries) than the copy it saves.
+pub const MMAP_THRESHOLD: u64 = 64 * 1024;
+
+/// Memory-maps the file at `path` read-only.
+///
+/// # Safety invariant
+///
+/// A mapping is only sound while nobody else modifies or truncates the file:
+/// writes would change bytes under `&str`s we handed out, and truncation
+/// turns page faults into `SIGBUS`. This holds for the datalake because the
+/// ingestion service writes each `header_*`/`body_*` file exactly once and
+/// never touches it again; indexing only ever reads them.
+pub fn mmap_read(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: datalake files are immutable after ingestion, see above.
+    unsafe { Mmap::map(&file) }
+}
+
+/// UTF-8 text of a file, either mapped or read onto the heap by [`read_text`].
+pub enum FileText {
+    Mapped(Mmap),
+    Heap(String),
+}
+
+impl Deref for FileText {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            // SAFETY: validated as UTF-8 in `read_text`, and the mapping is
+            // immutable per the `mmap_read` invariant.
+            FileText::Mapped(mmap) => unsafe { std::str::from_utf8_unchecked(mmap) },
+            FileText::Heap(text) => text,
+        }
+    }
+}
+
+/// Reads the text file at `path`, memory-mapping it when it is at least
+/// [`MMAP_THRESHOLD`] bytes. Like `fs::read_to_string`, fails with
+/// `InvalidData` if the file is not valid UTF-8.
+pub fn read_text(path: &Path) -> io::Result<FileText> {
+    if fs::metadata(path)?.len() < MMAP_THRESHOLD {
+        return fs::read_to_string(path).map(FileText::Heap);
+    }
+
+    let mmap = mmap_read(path)?;
+    std::str::from_utf8(&mmap).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(FileText::Mapped(mmap))
+}
+
+/// Fingerprints a file by its modification time and size.
+pub fn file_fingerprint(path: &Path) -> io::Result<BookFingerprint> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(BookFingerprint {
+        modified_ns: modified.as_nanos() as u64,
+        size: metadata.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty file at each of `paths` below `root`.
+    fn touch(root: &Path, paths: &[&str]) {
+        for path in paths {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+    }
+
+    #[test]
+    fn lists_books_in_every_default_layout_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(
+            dir.path(),
+            &[
+                "20240101/12/header_1342.txt",
+                "20240101/12/body_1342.txt",
+                "20240102/09/body_84.txt",
+                "20240102/10/body_84.txt",
+                "imported/2701_body.txt",
+                "imported/2701_header.txt",
+                "book_11/body.txt",
+                "book_11/header.txt",
+                "book_x/body.txt",
+                "notes/body_.txt",
+                "notes/readme.txt",
+            ],
+        );
+
+        let patterns: Vec<String> = DEFAULT_BOOK_FILENAME_PATTERN.split(',').map(str::to_string).collect();
+        assert_eq!(list_book_ids_matching(dir.path(), &patterns), [11, 84, 1342, 2701]);
+    }
+
+    #[test]
+    fn lists_only_the_patterns_given() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), &["20240101/12/body_1342.txt", "corpus/pg84.txt", "corpus/pg11.txt", "pg2701.txt"]);
+
+        let patterns = vec!["corpus/pg{id}.txt".to_string(), "no-placeholder.txt".to_string()];
+        assert_eq!(list_book_ids_matching(dir.path(), &patterns), [11, 84]);
+        assert!(list_book_ids_matching(&dir.path().join("missing"), &patterns).is_empty());
+    }
+
+    #[test]
+    fn small_files_are_read_onto_the_heap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("body_1.txt");
+        fs::write(&path, "Call me Ishmael.").unwrap();
+
+        let text = read_text(&path).unwrap();
+        assert!(matches!(text, FileText::Heap(_)));
+        assert_eq!(&*text, "Call me Ishmael.");
+    }
+
+    #[test]
+    fn large_files_are_mapped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("body_2.txt");
+        let body = "Call me Ishmael. ".repeat(MMAP_THRESHOLD as usize / 8);
+        fs::write(&path, &body).unwrap();
+
+        let text = read_text(&path).unwrap();
+        assert!(matches!(text, FileText::Mapped(_)));
+        assert_eq!(&*text, body);
+    }
+
+    #[test]
+    fn rejects_invalid_utf8_like_read_to_string() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("body_3.txt");
+        fs::write(&path, vec![0xff; MMAP_THRESHOLD as usize]).unwrap();
+
+        let err = read_text(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
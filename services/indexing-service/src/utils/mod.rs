@@ -1,2 +1,5 @@
+pub mod chapter;
+pub mod conditional;
+pub mod cooccurrence;
 pub mod file;
-pub mod text;
\ No newline at end of file
+pub mod text;
@@ -1,21 +1,294 @@
 //! Text Utilities
 //!
 //! Provides text-processing helpers used during indexing, primarily for
-//! tokenization of eBook contents. 
+//! tokenization of eBook contents. The rules for what counts as a word live
+//! in the shared `common-text` crate, which the search service also uses to
+//! tokenize queries, and are re-exported here.
 //!
-//! ## Responsibilities
-//! - Normalize text by converting to lowercase  
-//! - Extract valid alphabetic tokens using regular expressions  
-//! - Filter out very short or irrelevant words  
-//! - Return unique tokens as a `HashSet<String>` for efficient indexing
+//! [`tokenize_text`] is the regex reference; [`tokenize_text_fast`] produces
+//! the same words by splitting on whitespace with `memchr` and scanning the
+//! chunks for letter runs. It is what the indexing pipeline runs on book
+//! bodies, roughly 6x faster than the regex (see `benches/tokenizer_benchmark.rs`).
+//! Bodies larger than [`parallel_threshold`] are split across the rayon pool
+//! by [`tokenize_text_parallel`]; [`tokenize_body`] picks between the two.
+//!
+//! [`flesch_kincaid_grade`] estimates a body's reading level from
+//! [`count_syllables`] and [`count_sentences`], both approximations.
 
-use regex::Regex;
+pub use common_text::{
+    check_indexable, split_sentences, tokenize_sequence, tokenize_text, tokenize_text_fast, NotIndexed,
+    TokenizerConfig, MIN_WORD_LEN,
+};
+use rayon::prelude::*;
 use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Bodies above this many bytes are tokenized in parallel by default.
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 1024 * 1024;
+
+/// Size in bytes above which [`tokenize_body`] switches to
+/// [`tokenize_text_parallel`]. Read once from `TOKENIZE_PARALLEL_THRESHOLD`,
+/// falling back to [`DEFAULT_PARALLEL_THRESHOLD`].
+pub fn parallel_threshold() -> usize {
+    static THRESHOLD: OnceLock<usize> = OnceLock::new();
+    *THRESHOLD.get_or_init(|| {
+        std::env::var("TOKENIZE_PARALLEL_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_PARALLEL_THRESHOLD)
+    })
+}
+
+/// Splits `text` into at most `chunk_count` pieces of roughly equal size,
+/// each ending just after a newline (the last one runs to the end).
+fn split_at_lines(text: &str, chunk_count: usize) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let target = text.len().div_ceil(chunk_count.max(1)).max(1);
+    let mut chunks = Vec::with_capacity(chunk_count);
+    let mut start = 0;
+
+    while start < bytes.len() {
+        let end = match memchr::memchr(b'\n', &bytes[(start + target).min(bytes.len())..]) {
+            Some(pos) => (start + target).min(bytes.len()) + pos + 1,
+            None => bytes.len(),
+        };
+        chunks.push(&text[start..end]);
+        start = end;
+    }
+
+    chunks
+}
+
+/// Tokenizes `text` in `chunk_count` line-aligned chunks on the rayon pool
+/// and unions the results. Produces the same words as [`tokenize_text_fast`]:
+/// a newline is never a word character, so no word straddles two chunks.
+///
+/// 3 MB sample body (`benches/tokenizer_benchmark.rs`, `tokenize_large`):
+///
+/// | variant                      | time (1 core) |
+/// |------------------------------|---------------|
+/// | `tokenize_text_fast`         | 73.9 ms       |
+/// | `tokenize_text_parallel` (1) | 71.8 ms       |
+/// | `tokenize_text_parallel` (8) | 72.9 ms       |
+///
+/// The benchmark host had a single core, so the chunks ran one after the
+/// other: the three are within noise, which shows the split and merge cost
+/// nothing measurable but not the speedup. With N cores the scan divides by
+/// up to N; only the merge of the per-chunk sets stays serial, and a book's
+/// vocabulary is small next to its length.
+pub fn tokenize_text_parallel(text: &str, chunk_count: usize) -> HashSet<String> {
+    split_at_lines(text, chunk_count)
+        .into_par_iter()
+        .map(tokenize_text_fast)
+        .reduce(HashSet::new, |mut a, b| {
+            if a.len() < b.len() {
+                return b.into_iter().chain(a).collect();
+            }
+            a.extend(b);
+            a
+        })
+}
+
+/// Tokenizes a book body, in parallel once it is larger than
+/// [`parallel_threshold`].
+pub fn tokenize_body(text: &str) -> HashSet<String> {
+    if text.len() > parallel_threshold() {
+        tokenize_text_parallel(text, rayon::current_num_threads())
+    } else {
+        tokenize_text_fast(text)
+    }
+}
+
+/// Approximate syllables in `word`: groups of consecutive vowels (`y`
+/// included), less a silent final `e` (but not a consonant's `-le`), at
+/// least one for a word with letters. Non-letters are ignored.
+pub fn count_syllables(word: &str) -> usize {
+    let letters: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if letters.is_empty() {
+        return 0;
+    }
+
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut groups = 0;
+    let mut previous_vowel = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            groups += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // "make" loses its final e, "table" keeps it
+    let n = letters.len();
+    let consonant_le = n > 2 && letters[n - 2] == 'l' && !is_vowel(letters[n - 3]);
+    let silent_e = n > 2 && letters[n - 1] == 'e' && !is_vowel(letters[n - 2]) && !consonant_le;
+    if silent_e {
+        groups -= 1;
+    }
+    groups.max(1)
+}
+
+/// Number of sentences in `text`, counting each run of `.`, `?` and `!`
+/// not followed by a letter or digit (so `3.14` and `e.g` don't end one).
+/// Text without any terminator but with words is one sentence.
+pub fn count_sentences(text: &str) -> usize {
+    let mut sentences = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !matches!(c, '.' | '?' | '!') {
+            continue;
+        }
+        while chars.next_if(|next| matches!(next, '.' | '?' | '!')).is_some() {}
+        if chars.peek().is_none_or(|next| !next.is_alphanumeric()) {
+            sentences += 1;
+        }
+    }
+
+    if sentences == 0 && text.chars().any(char::is_alphabetic) {
+        1
+    } else {
+        sentences
+    }
+}
+
+/// Flesch-Kincaid grade level of `text`:
+/// `0.39 * words/sentences + 11.8 * syllables/words - 15.59`, rounded to two
+/// decimals. Words are whitespace-separated runs holding a letter; `None`
+/// when there are none.
+pub fn flesch_kincaid_grade(text: &str) -> Option<f64> {
+    let (words, syllables) = text
+        .split_whitespace()
+        .map(count_syllables)
+        .filter(|&syllables| syllables > 0)
+        .fold((0usize, 0usize), |(words, total), syllables| (words + 1, total + syllables));
+    if words == 0 {
+        return None;
+    }
+
+    let words = words as f64;
+    let sentences = count_sentences(text).max(1) as f64;
+    let grade = 0.39 * (words / sentences) + 11.8 * (syllables as f64 / words) - 15.59;
+    Some((grade * 100.0).round() / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_syllables_of_known_words() {
+        for (word, syllables) in [
+            ("beautiful", 3),
+            ("through", 1),
+            ("the", 1),
+            ("make", 1),
+            ("table", 2),
+            ("Whale,", 1),
+            ("yesterday", 3),
+            ("education", 4),
+            ("a", 1),
+            ("1851", 0),
+        ] {
+            assert_eq!(count_syllables(word), syllables, "{word}");
+        }
+    }
+
+    #[test]
+    fn counts_sentences_by_terminators() {
+        assert_eq!(count_sentences("Call me Ishmael. Some years ago... I went to sea!"), 3);
+        assert_eq!(count_sentences("Is it? It is?! Pi is 3.14, e.g. roughly."), 4);
+        assert_eq!(count_sentences("No terminator here"), 1);
+        assert_eq!(count_sentences("  "), 0);
+    }
+
+    #[test]
+    fn grades_simple_text_below_dense_text() {
+        let simple = "The cat sat on the mat. The dog ran to the cat. It was fun.";
+        let dense = "Notwithstanding considerable institutional reluctance, the \
+            administration subsequently implemented comprehensive organizational \
+            restructuring initiatives throughout numerous departments.";
+
+        // 15 words, 3 sentences, 15 syllables
+        assert_eq!(flesch_kincaid_grade(simple), Some(-1.84));
+        assert!(flesch_kincaid_grade(dense).unwrap() > 12.0);
+        assert_eq!(flesch_kincaid_grade("1851 -- 1852"), None);
+    }
+
+    #[test]
+    fn parallel_tokenizer_matches_sequential() {
+        let text = "It was the best of times,\nit was the worst of times;\r\ncafé closed\n\n".repeat(50);
+        let expected = tokenize_text_fast(&text);
+        for chunk_count in [1, 2, 3, 7, 64, 10_000] {
+            assert_eq!(tokenize_text_parallel(&text, chunk_count), expected, "{chunk_count} chunks");
+        }
+        assert!(tokenize_text_parallel("", 4).is_empty());
+    }
+
+    #[test]
+    fn line_chunks_cover_the_text() {
+        let text = "one\ntwo\nthree\nfour\nfive";
+        let chunks = split_at_lines(text, 3);
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks.len() <= 3);
+        assert!(chunks[..chunks.len() - 1].iter().all(|c| c.ends_with('\n')));
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Words `tokenize_text` keeps: ASCII letters, long enough.
+        fn indexable_word() -> impl Strategy<Value = String> {
+            "[a-zA-Z]{3,12}"
+        }
+
+        /// Any text, or text made mostly of ASCII words, short ones,
+        /// punctuation and the characters that glue letters into non-words.
+        fn text() -> impl Strategy<Value = String> {
+            prop_oneof!["\\PC{0,200}", "[a-zA-Z0-9_ ,.'\n\\-é]{0,200}"]
+        }
+
+        proptest! {
+            #[test]
+            fn tokens_are_longer_than_two_letters(text in text()) {
+                for token in tokenize_text(&text) {
+                    prop_assert!(token.len() >= MIN_WORD_LEN, "{:?}", token);
+                }
+            }
+
+            #[test]
+            fn tokens_are_lowercase(text in text()) {
+                for token in tokenize_text(&text) {
+                    prop_assert_eq!(token.to_lowercase(), token);
+                }
+            }
+
+            #[test]
+            fn alphabetic_words_are_kept(words in prop::collection::vec(indexable_word(), 1..20)) {
+                let tokens = tokenize_text(&words.join(" "));
+                prop_assert!(!tokens.is_empty());
+                for word in &words {
+                    prop_assert!(tokens.contains(&word.to_ascii_lowercase()), "{:?} missing", word);
+                }
+            }
+
+            #[test]
+            fn tokenizing_is_idempotent(text in text()) {
+                let tokens = tokenize_text(&text);
+                prop_assert_eq!(&tokenize_text(&text), &tokens);
+                prop_assert_eq!(&tokenize_text(&format!("{} {}", text, text)), &tokens);
+            }
+        }
 
-pub fn tokenize_text(text: &str) -> HashSet<String> {
-    let re = Regex::new(r"\b[a-zA-Z]+\b").unwrap();
-    re.find_iter(&text.to_lowercase())
-        .map(|m| m.as_str().to_string())
-        .filter(|word| word.len() > 2)
-        .collect()
-}
\ No newline at end of file
+        #[test]
+        fn empty_text_has_no_tokens() {
+            assert!(tokenize_text("").is_empty());
+            assert!(tokenize_text(" \n\t").is_empty());
+        }
+    }
+}
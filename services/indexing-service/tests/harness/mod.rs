@@ -0,0 +1,159 @@
+//! In-Process Test Harness
+//!
+//! Starts the ingestion, indexing and search services as background tasks of
+//! the test's runtime, each on an ephemeral port, so integration tests run
+//! with `cargo test` alone: no Docker, Redis or PostgreSQL. The ingestion and
+//! indexing services share a temporary datalake, and the indexing and
+//! search services share one in-memory index, so a book can go from upload
+//! to search results within one test.
+//!
+//! [`TestHarness::start_with`] puts the index in PostgreSQL or Redis
+//! instead, for tests of a real backend.
+//!
+//! ```ignore
+//! let services = TestHarness::start().await;
+//! let response = reqwest::get(format!("{}/status", services.indexing_url)).await?;
+//! ```
+
+use common_index::MemoryIndex;
+use common_redis::RedisConfig;
+use indexing_service::models::storage::{Backend, MemoryBackend, PostgresBackend, RedisBackend};
+use indexing_service::services::auth::AuthToken;
+use indexing_service::services::backpressure::IndexingLimiter;
+use indexing_service::services::compaction::Compactor;
+use indexing_service::services::shutdown::Shutdown;
+use indexing_service::services::startup::StartupProbe;
+use indexing_service::services::timeout::RequestTimeouts;
+use indexing_service::utils::file::Datalake;
+use search_service::models::storage as search;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// How long [`TestHarness::start`] waits for every service to answer.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TestHarness;
+
+/// Where the indexing service writes the index the search service reads.
+pub enum IndexStore {
+    Memory,
+    /// A database URL, e.g. `postgres://postgres@localhost/index_test`.
+    Postgres(String),
+    /// A Redis URL, e.g. `redis://localhost:6379`.
+    Redis(String),
+}
+
+impl IndexStore {
+    /// The indexing and search services' backends over the same index.
+    async fn backends(self) -> (Backend, search_service::Backend) {
+        match self {
+            IndexStore::Memory => {
+                let index = MemoryIndex::new();
+                (
+                    Backend::Memory(MemoryBackend::with_index(index.clone())),
+                    Arc::new(search::MemoryBackend::with_index(index)),
+                )
+            }
+            IndexStore::Postgres(url) => (
+                Backend::Postgres(PostgresBackend::new(&url).await.expect("Failed to connect to PostgreSQL")),
+                Arc::new(search::PostgresBackend::new(&url).await.expect("Failed to connect to PostgreSQL")),
+            ),
+            IndexStore::Redis(url) => {
+                let config = RedisConfig::new(&url, None);
+                (
+                    Backend::Redis(RedisBackend::from_config(&config).expect("Invalid Redis URL")),
+                    Arc::new(search::RedisBackend::from_config(&config).expect("Invalid Redis URL")),
+                )
+            }
+        }
+    }
+}
+
+/// The base URLs of the running services, e.g. `http://127.0.0.1:41234`.
+/// Dropping it stops them and deletes the datalake.
+pub struct HarnessGuard {
+    pub ingestion_url: String,
+    pub indexing_url: String,
+    pub search_url: String,
+    tasks: Vec<JoinHandle<()>>,
+    _datalake: TempDir,
+}
+
+impl TestHarness {
+    /// Starts the three services over an in-memory index and waits until
+    /// each answers `GET /status`.
+    pub async fn start() -> HarnessGuard {
+        Self::start_with(IndexStore::Memory).await
+    }
+
+    /// Starts the three services over the index in `store`.
+    pub async fn start_with(store: IndexStore) -> HarnessGuard {
+        let datalake_dir = tempfile::tempdir().expect("Failed to create the datalake");
+        let datalake = Datalake::new(datalake_dir.path());
+        let (backend, search_backend) = store.backends().await;
+
+        let ingestion_state = ingestion_service::state::AppState {
+            datalake: ingestion_service::utils::file::Datalake::new(datalake_dir.path()),
+            ..Default::default()
+        };
+        let indexing_state = indexing_service::state::AppState {
+            backend,
+            limiter: IndexingLimiter::new(4, Duration::ZERO),
+            shutdown: Shutdown::new(),
+            auth: AuthToken::new(None),
+            compactor: Compactor::new(),
+            started_at: Arc::new(Instant::now()),
+            startup: StartupProbe::new(),
+            timeouts: RequestTimeouts::default(),
+            datalake,
+        };
+
+        let (ingestion_url, ingestion) = serve(ingestion_service::app(ingestion_state)).await;
+        let (indexing_url, indexing) = serve(indexing_service::app(indexing_state)).await;
+        let (search_url, search) = serve(search_service::app(search_backend)).await;
+
+        let guard = HarnessGuard {
+            ingestion_url,
+            indexing_url,
+            search_url,
+            tasks: vec![ingestion, indexing, search],
+            _datalake: datalake_dir,
+        };
+        for url in [&guard.ingestion_url, &guard.indexing_url, &guard.search_url] {
+            wait_until_up(url).await;
+        }
+        guard
+    }
+}
+
+impl Drop for HarnessGuard {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Serves `app` on an ephemeral local port, returning its base URL.
+async fn serve(app: axum::Router) -> (String, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind a port");
+    let url = format!("http://{}", listener.local_addr().expect("Bound listener has an address"));
+    let task = tokio::spawn(async move {
+        axum::serve(listener, app).await.expect("Service stopped unexpectedly");
+    });
+    (url, task)
+}
+
+async fn wait_until_up(url: &str) {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        match reqwest::get(format!("{}/status", url)).await {
+            Ok(response) if response.status().is_success() => return,
+            _ if Instant::now() >= deadline => panic!("{} didn't start within {:?}", url, STARTUP_TIMEOUT),
+            _ => tokio::time::sleep(Duration::from_millis(20)).await,
+        }
+    }
+}
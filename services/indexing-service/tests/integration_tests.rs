@@ -1,12 +1,19 @@
 //! Integration Tests for Indexing Service
 //!
 //! Simplified tests that focus on basic functionality that should always work.
+//! Each test starts the services in-process through [`TestHarness`], so they
+//! run with `cargo test` alone.
 
+mod harness;
+
+use harness::{HarnessGuard, IndexStore, TestHarness};
 use serde_json::Value;
+use std::time::{Duration, Instant};
 
 #[tokio::test]
 async fn test_health_check() {
-    let response = reqwest::get("http://0.0.0.0:7002/status")
+    let services = TestHarness::start().await;
+    let response = reqwest::get(format!("{}/status", services.indexing_url))
         .await
         .expect("Failed to make request");
 
@@ -19,7 +26,8 @@ async fn test_health_check() {
 
 #[tokio::test]
 async fn test_index_status() {
-    let response = reqwest::get("http://0.0.0.0:7002/index/status")
+    let services = TestHarness::start().await;
+    let response = reqwest::get(format!("{}/index/status", services.indexing_url))
         .await
         .expect("Failed to make request");
 
@@ -29,14 +37,31 @@ async fn test_index_status() {
     assert!(body["total_books"].is_number());
     assert!(body["total_words"].is_number());
     assert!(body["last_updated"].is_string());
+    assert!(body["words_per_field"]["title"].is_number());
+    assert!(body["words_per_field"]["author"].is_number());
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    let services = TestHarness::start().await;
+    let response = reqwest::get(format!("{}/metrics", services.indexing_url))
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body = response.text().await.expect("Failed to read body");
+    assert!(body.contains("indexing_books_indexed_total"));
+    assert!(body.contains("indexing_rebuild_words_per_second"));
 }
 
 #[tokio::test]
 async fn test_index_rebuild() {
+    let services = TestHarness::start().await;
     let client = reqwest::Client::new();
 
     let response = client
-        .post("http://0.0.0.0:7002/index/rebuild")
+        .post(format!("{}/index/rebuild", services.indexing_url))
         .send()
         .await
         .expect("Failed to make request");
@@ -48,17 +73,201 @@ async fn test_index_rebuild() {
     assert!(body["indexed_count"].is_number());
 }
 
+#[tokio::test]
+async fn test_index_rebuild_selected_books() {
+    let services = TestHarness::start().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/index/rebuild", services.indexing_url))
+        .json(&serde_json::json!({ "book_ids": [999999, 999998, 999999] }))
+        .send()
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["requested_count"], 2);
+    assert_eq!(body["books_processed"], 0);
+    assert_eq!(body["skipped_not_found"], serde_json::json!([999998, 999999]));
+}
+
 #[tokio::test]
 async fn test_index_update_non_existing_book() {
+    let services = TestHarness::start().await;
     let client = reqwest::Client::new();
     let book_id = "999999";
 
     let response = client
-        .post(&format!("http://0.0.0.0:7002/index/update/{}", book_id))
+        .post(format!("{}/index/update/{}", services.indexing_url, book_id))
+        .send()
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 404);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["error_code"], "BOOK_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn test_book_chapters_non_existing_book() {
+    let services = TestHarness::start().await;
+    let response = reqwest::get(format!("{}/index/book/999999/chapters", services.indexing_url))
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_verify_non_existing_book() {
+    let services = TestHarness::start().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/index/book/999999/verify", services.indexing_url))
+        .send()
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_index_import_rejects_invalid_record() {
+    let services = TestHarness::start().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/index/import", services.indexing_url))
+        .body("{not json\n")
+        .send()
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_word_books_rejects_stop_word() {
+    let services = TestHarness::start().await;
+    let response = reqwest::get(format!("{}/index/words/the", services.indexing_url))
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 400);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+    assert!(body["message"].as_str().unwrap().contains("stop word"));
+}
+
+#[tokio::test]
+async fn test_word_books_paginates() {
+    let services = TestHarness::start().await;
+    let response = reqwest::get(format!("{}/index/words/whale?page=1&per_page=1", services.indexing_url))
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["word"], "whale");
+    assert!(body["book_count"].is_number());
+    assert!(body["books"].as_array().unwrap().len() <= 1);
+}
+
+#[tokio::test]
+async fn test_uploaded_book_is_searchable_once_indexed() {
+    assert_uploaded_book_is_searchable(TestHarness::start().await).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a PostgreSQL database at TEST_DATABASE_URL"]
+async fn test_uploaded_book_is_searchable_once_indexed_on_postgres() {
+    let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set");
+    assert_uploaded_book_is_searchable(TestHarness::start_with(IndexStore::Postgres(url)).await).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a Redis server at TEST_REDIS_URL"]
+async fn test_uploaded_book_is_searchable_once_indexed_on_redis() {
+    let url = std::env::var("TEST_REDIS_URL").expect("TEST_REDIS_URL is not set");
+    assert_uploaded_book_is_searchable(TestHarness::start_with(IndexStore::Redis(url)).await).await;
+}
+
+/// Uploads a book to the ingestion service, indexes it and finds it through
+/// the search service.
+async fn assert_uploaded_book_is_searchable(services: HarnessGuard) {
+    let client = reqwest::Client::new();
+    // Long enough to pass the ingestion service's minimum book size.
+    let text = format!(
+        "Title: A Harness Voyage\nAuthor: Test Author\nLanguage: English\n\n\
+         *** START OF THE PROJECT GUTENBERG EBOOK A HARNESS VOYAGE ***\n{}\
+         *** END OF THE PROJECT GUTENBERG EBOOK A HARNESS VOYAGE ***\n",
+        "The narwhal surfaced beside the schooner at dawn.\n".repeat(30)
+    );
+    let form = reqwest::multipart::Form::new()
+        .text("book_id", "990001")
+        .part("file", reqwest::multipart::Part::text(text).file_name("990001.txt"));
+
+    let response = client
+        .post(format!("{}/ingest/file", services.ingestion_url))
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+
+    let response = client
+        .post(format!("{}/index/update/990001", services.indexing_url))
         .send()
         .await
         .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
 
-    // Should return an error for non-existing book
-    assert!(response.status().is_server_error());
-}
\ No newline at end of file
+    let response = reqwest::get(format!("{}/search?q=narwhal", services.search_url))
+        .await
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let book_ids: Vec<u64> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|result| result["book_id"].as_u64())
+        .collect();
+    assert_eq!(book_ids, vec![990001]);
+
+    // Take the book out again, postings too, so a shared database is left as
+    // it was
+    let response = client
+        .delete(format!("{}/index/book/990001", services.indexing_url))
+        .send()
+        .await
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+    let response = client
+        .post(format!("{}/index/compact", services.indexing_url))
+        .send()
+        .await
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 202);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        let status: Value = reqwest::get(format!("{}/index/compact/status", services.indexing_url))
+            .await
+            .expect("Failed to make request")
+            .json()
+            .await
+            .expect("Failed to parse JSON");
+        match status["state"].as_str() {
+            Some("completed") => break,
+            Some("running") if Instant::now() < deadline => tokio::time::sleep(Duration::from_millis(50)).await,
+            _ => panic!("Compaction didn't complete: {}", status),
+        }
+    }
+}
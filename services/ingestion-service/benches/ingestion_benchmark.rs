@@ -9,23 +9,7 @@
 
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-
-fn header_body_split(text: &str) -> (String, String) {
-    let start_marker = "*** START OF THE PROJECT GUTENBERG EBOOK";
-    let end_marker = "*** END OF THE PROJECT GUTENBERG EBOOK";
-
-    if let Some(start_pos) = text.find(start_marker) {
-        let header = text[..start_pos].to_string();
-
-        if let Some(end_pos) = text.find(end_marker) {
-            let body_start = text[start_pos..].find('\n').map(|pos| start_pos + pos + 1).unwrap_or(start_pos);
-            let body = text[body_start..end_pos].to_string();
-            return (header, body);
-        }
-    }
-
-    (text.to_string(), String::new())
-}
+use ingestion_service::utils::file::header_body_split;
 
 fn benchmark_header_body_split(c: &mut Criterion) {
     let sample_text = format!(
@@ -0,0 +1,87 @@
+//! Error Responses
+//!
+//! Failed requests answer with an [`ErrorResponse`]: a machine-readable
+//! `error_code` clients can branch on, a human-readable message and, where
+//! they apply, the book concerned and why it failed validation. Handlers return [`AppError`], each
+//! variant of which maps to one status code and `error_code`.
+//!
+//! The response shape and the middleware adding request IDs to it are shared
+//! by every service in `common-http`.
+
+use crate::services::download::DownloadError;
+use crate::services::indexing::IndexingError;
+use crate::services::validation::ValidationError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use common_http::ErrorResponse;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Book {0} is not on Project Gutenberg")]
+    BookNotFound(u32),
+    #[error("Book {book_id} failed validation ({}): {error}", error.reason())]
+    ValidationFailed { book_id: u32, error: ValidationError },
+    #[error("Failed to download book {book_id}: {error}")]
+    DownloadFailed { book_id: u32, error: DownloadError },
+    #[error("Failed to ask the indexing service: {0}")]
+    IndexingUnavailable(#[from] IndexingError),
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BookNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::ValidationFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::DownloadFailed { .. } => StatusCode::BAD_GATEWAY,
+            AppError::IndexingUnavailable(_) => StatusCode::BAD_GATEWAY,
+            AppError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::BookNotFound(_) => "BOOK_NOT_FOUND",
+            AppError::ValidationFailed { .. } => "VALIDATION_FAILED",
+            AppError::DownloadFailed { .. } => "DOWNLOAD_FAILED",
+            AppError::IndexingUnavailable(_) => "INDEXING_UNAVAILABLE",
+            AppError::InvalidRequest(_) => "INVALID_REQUEST",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    fn book_id(&self) -> Option<u32> {
+        match self {
+            AppError::BookNotFound(book_id)
+            | AppError::ValidationFailed { book_id, .. }
+            | AppError::DownloadFailed { book_id, .. } => Some(*book_id),
+            _ => None,
+        }
+    }
+
+    /// Why a book failed validation, e.g. `empty_body`.
+    fn reason(&self) -> Option<&'static str> {
+        match self {
+            AppError::ValidationFailed { error, .. } => Some(error.reason()),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        ErrorResponse {
+            book_id: self.book_id(),
+            reason: self.reason().map(str::to_string),
+            ..ErrorResponse::new(self.error_code(), self.to_string())
+        }
+        .with_status(self.status())
+    }
+}
+
@@ -0,0 +1,54 @@
+//! Ingestion Service Library
+//!
+//! The modules behind the `ingestion-service` binary, exposed as a library
+//! so benchmarks and handler tests run the real code. `main.rs` only sets up
+//! logging and serves [`app`].
+
+pub mod error;
+pub mod models;
+pub mod routes;
+pub mod services;
+pub mod state;
+pub mod utils;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use common_http::attach_request_id;
+use routes::{
+    export::export_datalake,
+    health::{health_check, readiness_check, startup_check},
+    ingest::{
+        check_status, download_queue, download_stats, ingest_book, ingest_file, list_books, MAX_UPLOAD_BYTES,
+    },
+};
+use state::AppState;
+use tower_http::cors::CorsLayer;
+use tower_http::trace::TraceLayer;
+use common_telemetry::trace_context;
+
+/// Builds the service's router. Every error is answered with an
+/// `ErrorResponse` carrying the request's `X-Request-Id`.
+pub fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/status", get(health_check))
+        .route("/ready", get(readiness_check))
+        .route("/startup", get(startup_check))
+        .route(
+            "/ingest/file",
+            post(ingest_file).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/ingest/:book_id", post(ingest_book))
+        .route("/ingest/status/:book_id", get(check_status))
+        .route("/ingest/list", get(list_books))
+        .route("/ingest/stats", get(download_stats))
+        .route("/ingest/queue", get(download_queue))
+        .route("/ingest/export", get(export_datalake))
+        .layer(middleware::from_fn(attach_request_id))
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http().make_span_with(trace_context::make_span))
+        .with_state(state)
+}
@@ -10,52 +10,64 @@
 //! - Include health checks for operational monitoring
 //!
 //! ## Endpoints
-//! - `GET /status` → Service health check  
+//! - `GET /status` → Liveness probe with datalake and uptime details  
+//! - `GET /ready` → Readiness probe: is the datalake readable  
+//! - `GET /startup` → Startup probe: has the datalake been readable yet  
 //! - `POST /ingest/:book_id` → Trigger book ingestion  
-//! - `GET /ingest/status/:book_id` → Check availability of a book  
-//! - `GET /ingest/list` → List all downloaded books
+//! - `GET /ingest/status/:book_id` → Check availability of a book, including
+//!   books stored before the last restart  
+//! - `GET /ingest/list` → List downloaded books, sorted and filtered by file
+//!   status, ID prefix or whether they are indexed  
+//! - `GET /ingest/stats` → Download counters since startup
+//! - `GET /ingest/queue` → Books waiting for or in download
+//!
+//! ## Environment Variables
+//! - `PORT`: Service port (default: `7001`)
+//! - `REQUEST_TIMEOUT_SECS`: Timeout for each download request (default: `30`)
+//! - `GUTENBERG_MIRRORS`: Comma-separated mirror base URLs, used round-robin (default: `https://www.gutenberg.org`)
+//! - `MIN_BOOK_SIZE_BYTES`: Smallest download accepted as a book (default: `1000`)
+//! - `MAX_CONCURRENT_DOWNLOADS`: Downloads running at the same time (default: `4`)
+//! - `DOWNLOAD_MAX_RETRIES`: Retries of a download failing transiently (default: `3`)
+//! - `DOWNLOAD_BACKOFF_INITIAL_MS`, `DOWNLOAD_BACKOFF_MULTIPLIER`, `DOWNLOAD_BACKOFF_MAX_MS`,
+//!   `DOWNLOAD_BACKOFF_JITTER`: Backoff between retries (defaults: `500`, `2.0`, `10000`, `0.2`)
+//! - `INDEXING_SERVICE_URL`: Indexing service asked by `/ingest/list?indexed=` (default: `http://localhost:7002`)
+//! - `RUST_LOG`, `LOG_LEVEL_SERVICE`, `LOG_LEVEL_TOWER`, `LOG_LEVEL_REQWEST`, `LOG_FORMAT`: see the `common-telemetry` crate
 //!
 //! The service uses `Axum` for HTTP routing, `Tokio` for async runtime,
 //! and `Tower` middlewares for tracing and CORS support.
 
-use axum::{
-    routing::{get, post},
-    Router,
-};
-use std::collections::HashSet;
+use common_telemetry::logging::{init_tracing, LogOutput};
+use ingestion_service::app;
+use ingestion_service::services::download::{MirrorSelector, RetryPolicy};
+use ingestion_service::state::AppState;
+use ingestion_service::utils::file::{initialize_book_registry, Datalake};
 use std::sync::{Arc, Mutex};
-use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
 use tracing::info;
 
-type DownloadedBooks = Arc<Mutex<HashSet<u32>>>;
-
-mod models;
-mod routes;
-mod services;
-mod utils;
-
-use routes::{
-    health::health_check,
-    ingest::{check_status, ingest_book, list_books},
-};
-
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter("ingestion_service=info,tower_http=info")
-        .init();
+    init_tracing("ingestion-service", LogOutput::Stdout);
+
+    let mirrors = MirrorSelector::from_env();
+    let retries = RetryPolicy::from_env();
+    info!(
+        "Downloading from {} Gutenberg mirror(s), retrying up to {} times",
+        mirrors.mirrors().len(),
+        retries.max_retries
+    );
 
-    let downloaded_books: DownloadedBooks = Arc::new(Mutex::new(HashSet::new()));
+    // Books stored before a restart are still available
+    let datalake = Datalake::default();
+    let downloaded_books = initialize_book_registry(datalake.path());
+    info!("Found {} books already in the datalake", downloaded_books.len());
 
-    let app = Router::new()
-        .route("/status", get(health_check))
-        .route("/ingest/:book_id", post(ingest_book))
-        .route("/ingest/status/:book_id", get(check_status))
-        .route("/ingest/list", get(list_books))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(downloaded_books);
+    let app = app(AppState {
+        downloaded_books: Arc::new(Mutex::new(downloaded_books)),
+        mirrors: Arc::new(mirrors),
+        retries,
+        datalake,
+        ..AppState::default()
+    });
 
     let port = std::env::var("PORT").unwrap_or_else(|_| "7001".to_string());
     let addr = format!("0.0.0.0:{}", port);
@@ -3,17 +3,41 @@
 //! Defines the **API response structures** used by the Ingestion Service.
 //!
 //! ## Structures
-//! - `HealthResponse` — used by `/status` for service health reporting  
+//! - `HealthResponse` — used by `/status` for service health and uptime reporting
+//! - `ProbeResponse` — result of the `/ready` and `/startup` probes  
 //! - `IngestResponse` — returned after successful ingestion of a book  
 //! - `StatusResponse` — reports processing status for a specific book  
-//! - `ListResponse` — lists all available ingested book IDs
+//! - `ListResponse` — lists all available ingested book IDs  
+//! - `DownloadStatsResponse` — download counters since the service started
+//! - `ErrorResponse` — machine-readable error code plus message, book, validation reason and request ID for failed requests (shared, from `common-http`)
 
+pub use common_http::ErrorResponse;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct HealthResponse {
     pub service: String,
+    /// Always `liveness`.
+    pub probe: String,
+    /// Always true: a process that answers is alive.
+    pub ok: bool,
     pub status: String,
+    /// Whether the datalake, this service's only storage, can be read.
+    pub backend_connected: bool,
+    /// Always `datalake`.
+    pub backend_type: String,
+    /// Time taken to open the datalake directory.
+    pub backend_latency_ms: f64,
+    pub datalake_accessible: bool,
+    pub uptime_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProbeResponse {
+    pub service: String,
+    /// `readiness` or `startup`.
+    pub probe: String,
+    pub ok: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,6 +55,29 @@ pub struct StatusResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListResponse {
+    /// Books in the datalake, before filtering.
     pub count: usize,
     pub books: Vec<u32>,
+    /// Books left after the filters, i.e. the length of `books`.
+    pub filtered_count: usize,
+    /// The sort and filters requested, as `name=value`.
+    pub applied_filters: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownloadStatsResponse {
+    pub total_downloads: u64,
+    pub total_bytes: u64,
+    pub failed_downloads: u64,
+    pub avg_speed_bps: f64,
+}
+
+/// Response for GET /ingest/queue: books waiting for a download slot, in
+/// order, books downloading, and downloads finished in the last hour.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownloadQueueResponse {
+    pub queued: Vec<u32>,
+    pub in_progress: Vec<u32>,
+    pub completed_last_hour: usize,
+    pub failed_last_hour: usize,
 }
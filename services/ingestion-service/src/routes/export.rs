@@ -0,0 +1,84 @@
+//! Export Endpoint
+//!
+//! **GET /ingest/export?format=ndjson&book_ids=84,1342**
+//! → Streams the datalake as newline-delimited JSON, one
+//! `{"book_id", "header", "body"}` object per book in ascending ID order (see
+//! [`crate::services::export`]), as the attachment `datalake-export.ndjson`.
+//! `book_ids` limits the export to those books; IDs not in the datalake are
+//! skipped. `ndjson` is the only (and default) format; anything else, or a
+//! malformed ID list, gets `400`.
+
+use crate::error::AppError;
+use crate::services::export::ndjson_stream;
+use crate::utils::file::{scan_datalake, Datalake};
+use axum::{
+    body::Body,
+    extract::{rejection::QueryRejection, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    pub format: Option<String>,
+    /// Comma-separated book IDs, e.g. `84,1342`.
+    pub book_ids: Option<String>,
+}
+
+/// Parses `84, 1342`, ignoring blanks.
+fn parse_book_ids(list: &str) -> Result<HashSet<u32>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(|_| format!("invalid book ID '{}'", id)))
+        .collect()
+}
+
+/// Streams the datalake, or the requested books, as NDJSON.
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn export_datalake(
+    State(datalake): State<Datalake>,
+    params: Result<Query<ExportParams>, QueryRejection>,
+) -> Result<Response, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidRequest(rejection.body_text()))?;
+
+    if let Some(format) = params.format.as_deref().filter(|format| *format != "ndjson") {
+        return Err(AppError::InvalidRequest(format!("unsupported format '{}', expected ndjson", format)));
+    }
+    let wanted = params
+        .book_ids
+        .as_deref()
+        .map(parse_book_ids)
+        .transpose()
+        .map_err(AppError::InvalidRequest)?;
+
+    let books: Vec<_> = scan_datalake(datalake.path())
+        .into_iter()
+        .filter(|(book_id, _)| wanted.as_ref().is_none_or(|wanted| wanted.contains(book_id)))
+        .collect();
+    info!("Exporting {} book(s) as NDJSON", books.len());
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"datalake-export.ndjson\""),
+        ],
+        Body::from_stream(ndjson_stream(books)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_book_id_lists() {
+        assert_eq!(parse_book_ids("84, 1342,,84").unwrap(), HashSet::from([84, 1342]));
+        assert!(parse_book_ids("").unwrap().is_empty());
+        assert_eq!(parse_book_ids("84,moby").unwrap_err(), "invalid book ID 'moby'");
+    }
+}
@@ -1,17 +1,151 @@
-//! Health Check Endpoint
+//! Health Check Endpoints
 //!
-//! Provides a simple endpoint to verify that the **Ingestion Service** is
-//! operational.
+//! Liveness, readiness and startup probes for the **Ingestion Service**,
+//! whose only storage is the datalake. Each answers
+//! `{"probe": ..., "ok": ...}`, with `503` when not ok.
 //!
-//! **GET /status**
-//! → Returns `{"service": "ingestion-service", "status": "running"}`
+//! **GET /status** (liveness)
+//! → Always `200` while the process answers, with whether the datalake is
+//! readable, how long opening it took and the seconds since startup.
+//! `"status"` is `"degraded"` while the datalake can't be read
+//!
+//! **GET /ready** (readiness)
+//! → `200` while the datalake is readable
+//!
+//! **GET /startup** (startup)
+//! → `200` once the datalake has been readable for the first time
+
+use crate::models::responses::{HealthResponse, ProbeResponse};
+use crate::services::startup::StartupProbe;
+use crate::utils::file::Datalake;
+use axum::{extract::State, http::StatusCode, response::Json};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::warn;
 
-use crate::models::responses::HealthResponse;
-use axum::response::Json;
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn health_check(
+    State(started_at): State<Arc<Instant>>,
+    State(startup): State<StartupProbe>,
+    State(datalake): State<Datalake>,
+) -> Json<HealthResponse> {
+    let (accessible, backend_latency_ms) = check_datalake(&datalake, &startup).await;
+    let status = if accessible { "running" } else { "degraded" };
 
-pub async fn health_check() -> Json<HealthResponse> {
     Json(HealthResponse {
         service: "ingestion-service".to_string(),
-        status: "running".to_string(),
+        probe: "liveness".to_string(),
+        ok: true,
+        status: status.to_string(),
+        backend_connected: accessible,
+        backend_type: "datalake".to_string(),
+        backend_latency_ms,
+        datalake_accessible: accessible,
+        uptime_secs: started_at.elapsed().as_secs(),
     })
 }
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn readiness_check(
+    State(startup): State<StartupProbe>,
+    State(datalake): State<Datalake>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let (accessible, _) = check_datalake(&datalake, &startup).await;
+    probe_response("readiness", accessible)
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn startup_check(
+    State(startup): State<StartupProbe>,
+    State(datalake): State<Datalake>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let started = startup.is_complete() || check_datalake(&datalake, &startup).await.0;
+    probe_response("startup", started)
+}
+
+/// Whether the datalake directory can be read, and the milliseconds it took
+/// to find out. The first success completes `startup`.
+async fn check_datalake(datalake: &Datalake, startup: &StartupProbe) -> (bool, f64) {
+    let probe_started = Instant::now();
+    let accessible = match tokio::fs::read_dir(datalake.path()).await {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Health check: datalake {} unreadable: {}", datalake.path().display(), e);
+            false
+        }
+    };
+    if accessible {
+        startup.complete();
+    }
+    (accessible, probe_started.elapsed().as_secs_f64() * 1000.0)
+}
+
+fn probe_response(probe: &str, ok: bool) -> (StatusCode, Json<ProbeResponse>) {
+    let code = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        code,
+        Json(ProbeResponse {
+            service: "ingestion-service".to_string(),
+            probe: probe.to_string(),
+            ok,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app;
+    use crate::state::AppState;
+    use crate::utils::file::DATALAKE_PATH;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn probe(state: AppState, uri: &str) -> (StatusCode, Value) {
+        let response = app(state)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let code = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (code, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn liveness_reports_the_datalake() {
+        let (code, health) = probe(AppState::default(), "/status").await;
+
+        let readable = std::fs::read_dir(DATALAKE_PATH).is_ok();
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(health["probe"], "liveness");
+        assert_eq!(health["ok"], true);
+        assert_eq!(health["backend_type"], "datalake");
+        assert_eq!(health["datalake_accessible"], readable);
+        assert_eq!(health["backend_connected"], readable);
+        assert!(health["uptime_secs"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_the_datalake() {
+        let (code, ready) = probe(AppState::default(), "/ready").await;
+
+        let readable = std::fs::read_dir(DATALAKE_PATH).is_ok();
+        let expected = if readable { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        assert_eq!(code, expected);
+        assert_eq!(ready["probe"], "readiness");
+        assert_eq!(ready["ok"], readable);
+    }
+
+    #[tokio::test]
+    async fn startup_stays_complete() {
+        let state = AppState::default();
+        state.startup.complete();
+
+        let (code, started) = probe(state, "/startup").await;
+
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(started["probe"], "startup");
+        assert_eq!(started["ok"], true);
+    }
+}
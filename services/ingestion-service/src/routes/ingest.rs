@@ -4,48 +4,158 @@
 //! Handles downloading, tracking, and listing ingested books stored in the datalake.
 //!
 //! ## Endpoints
-//! - **GET /ingest/:book_id** — downloads and stores a book from Project Gutenberg  
+//! - **GET /ingest/:book_id** — downloads and stores a book from Project Gutenberg;
+//!   books failing validation get `422` with a `VALIDATION_FAILED` error,
+//!   books Gutenberg doesn't have `404` with `BOOK_NOT_FOUND`. Downloads
+//!   wait in the [`DownloadQueue`] for a free slot  
+//! - **POST /ingest/file** — stores a book uploaded as `multipart/form-data`
+//!   (`book_id` and `file` fields) instead of downloading it, for air-gapped
+//!   deployments and local corpora. The text passes the same validation as a
+//!   download and replaces any copy already stored
 //! - **GET /status/:book_id** — checks if a book has been successfully processed  
-//! - **GET /list** — returns all available ingested books in the datalake
-
-use crate::models::responses::{IngestResponse, ListResponse, StatusResponse};
-use crate::services::download::download_book;
-use crate::utils::file::{create_datalake_path, DATALAKE_PATH};
-use axum::{extract::Path, http::StatusCode, response::Json};
-use std::collections::HashSet;
-use std::fs;
-use std::sync::{Arc, Mutex};
-use tracing::error;
-type DownloadedBooks = Arc<Mutex<HashSet<u32>>>;
+//! - **GET /list** — returns the ingested books in the datalake, by ascending
+//!   ID; `?sort=desc` reverses the order, `?status=available|missing` keeps
+//!   books whose body file is present or not, `?prefix=13` keeps IDs starting
+//!   with those digits, and `?indexed=true|false` keeps the books the indexing
+//!   service has or hasn't indexed (`502` if it can't be asked)
+//! - **GET /ingest/stats** — download counters since the service started
+//! - **GET /ingest/queue** — books waiting for and in download, and recent outcomes
 
+use crate::error::AppError;
+use crate::models::responses::{
+    DownloadQueueResponse, DownloadStatsResponse, IngestResponse, ListResponse, StatusResponse,
+};
+use crate::services::download::{store_book, store_text, DownloadError, MirrorSelector, RetryPolicy};
+use crate::services::indexing::IndexingClient;
+use crate::services::queue::DownloadQueue;
+use crate::services::stats::DownloadStats;
+use crate::services::validation::ValidationError;
+use crate::state::DownloadedBooks;
+use crate::utils::file::{create_datalake_path, scan_datalake, Datalake};
+use axum::{
+    extract::{multipart::MultipartError, rejection::QueryRejection, Multipart, Path, Query},
+    response::Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
 pub async fn ingest_book(
     Path(book_id): Path<u32>,
     downloaded_books: axum::extract::State<DownloadedBooks>,
-) -> Result<Json<IngestResponse>, StatusCode> {
-    match download_book(book_id).await {
-        Ok(path) => {
+    stats: axum::extract::State<Arc<DownloadStats>>,
+    mirrors: axum::extract::State<Arc<MirrorSelector>>,
+    retries: axum::extract::State<RetryPolicy>,
+    queue: axum::extract::State<DownloadQueue>,
+    datalake: axum::extract::State<Datalake>,
+) -> Result<Json<IngestResponse>, AppError> {
+    let started = Instant::now();
+    match store_book(datalake.path(), book_id, &mirrors, &retries, &queue).await {
+        Ok(download) => {
+            // Books already in the datalake weren't downloaded, so they
+            // don't count towards the statistics.
+            if let Some(bytes) = download.bytes {
+                stats.record_success(bytes, started.elapsed());
+            }
             downloaded_books.lock().unwrap().insert(book_id);
             Ok(Json(IngestResponse {
                 book_id,
                 status: "downloaded".to_string(),
-                path,
+                path: download.path,
             }))
         }
         Err(e) => {
-            error!("Failed to download book {}: {}", book_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            stats.record_failure();
+            Err(ingest_error(book_id, e))
         }
     }
 }
 
-pub async fn check_status(Path(book_id): Path<u32>) -> Json<StatusResponse> {
-    let datalake_path = create_datalake_path();
-    let header_path = format!("{}/header_{}.txt", datalake_path, book_id);
-    let body_path = format!("{}/body_{}.txt", datalake_path, book_id);
+/// Largest request accepted by `POST /ingest/file`.
+pub const MAX_UPLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+#[tracing::instrument(skip_all, fields(book_id), err(level = "warn"))]
+pub async fn ingest_file(
+    downloaded_books: axum::extract::State<DownloadedBooks>,
+    datalake: axum::extract::State<Datalake>,
+    mut multipart: Multipart,
+) -> Result<Json<IngestResponse>, AppError> {
+    let mut book_id = None;
+    let mut text = None;
+    while let Some(field) = multipart.next_field().await.map_err(invalid_upload)? {
+        match field.name() {
+            Some("book_id") => {
+                let value = field.text().await.map_err(invalid_upload)?;
+                let id = value
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| AppError::InvalidRequest(format!("Invalid book_id: {:?}", value)))?;
+                book_id = Some(id);
+            }
+            Some("file") => {
+                // Invalid UTF-8 is left for validation to reject, as it is
+                // for downloads
+                let bytes = field.bytes().await.map_err(invalid_upload)?;
+                text = Some(String::from_utf8_lossy(&bytes).into_owned());
+            }
+            _ => {}
+        }
+    }
+    let book_id = book_id.ok_or_else(|| AppError::InvalidRequest("Missing `book_id` field".to_string()))?;
+    let text = text.ok_or_else(|| AppError::InvalidRequest("Missing `file` field".to_string()))?;
+    tracing::Span::current().record("book_id", book_id);
+
+    let path = store_text(datalake.path(), book_id, &text).map_err(|e| ingest_error(book_id, e))?;
+    downloaded_books.lock().unwrap().insert(book_id);
+    info!("Stored uploaded book {} in {}", book_id, path);
+
+    Ok(Json(IngestResponse {
+        book_id,
+        status: "uploaded".to_string(),
+        path,
+    }))
+}
+
+fn invalid_upload(e: MultipartError) -> AppError {
+    AppError::InvalidRequest(format!("Invalid upload: {}", e.body_text()))
+}
 
-    let status = if std::path::Path::new(&header_path).exists()
-        && std::path::Path::new(&body_path).exists()
-    {
+/// The [`AppError`] for a failed [`store_book`] or [`store_text`]: a
+/// rejected text, a book Gutenberg doesn't have, a download that failed
+/// otherwise, or a datalake write error.
+fn ingest_error(book_id: u32, e: Box<dyn std::error::Error + Send + Sync>) -> AppError {
+    let e = match e.downcast::<ValidationError>() {
+        Ok(error) => {
+            warn!("Rejecting book {}: {}", book_id, error);
+            return AppError::ValidationFailed { book_id, error: *error };
+        }
+        Err(e) => e,
+    };
+    error!("Failed to ingest book {}: {}", book_id, e);
+    match e.downcast::<DownloadError>() {
+        Ok(error) => match *error {
+            DownloadError::Status { status, .. } if matches!(status.as_u16(), 404 | 410) => {
+                AppError::BookNotFound(book_id)
+            }
+            error => AppError::DownloadFailed { book_id, error },
+        },
+        Err(e) => AppError::Internal(format!("Failed to store book {}: {}", book_id, e)),
+    }
+}
+
+/// A book is available once ingested, by this run or, through the registry
+/// seeded at startup, an earlier one; otherwise the current hour's directory
+/// is checked for its files.
+#[tracing::instrument(skip_all, fields(book_id = book_id))]
+pub async fn check_status(
+    Path(book_id): Path<u32>,
+    downloaded_books: axum::extract::State<DownloadedBooks>,
+    datalake: axum::extract::State<Datalake>,
+) -> Json<StatusResponse> {
+    let registered = downloaded_books.lock().unwrap().contains(&book_id);
+    let status = if registered || stored_this_hour(&datalake, book_id) {
         "available"
     } else {
         "not_found"
@@ -57,53 +167,500 @@ pub async fn check_status(Path(book_id): Path<u32>) -> Json<StatusResponse> {
     })
 }
 
-pub async fn list_books() -> Json<ListResponse> {
-    let mut books = Vec::new();
-
-    if let Ok(entries) = fs::read_dir(DATALAKE_PATH) {
-        for date_entry in entries.flatten() {
-            if date_entry
-                .file_type()
-                .map(|ft| ft.is_dir())
-                .unwrap_or(false)
-            {
-                if let Ok(subdir_entries) = fs::read_dir(date_entry.path()) {
-                    for subdir_entry in subdir_entries.flatten() {
-                        if subdir_entry
-                            .file_type()
-                            .map(|ft| ft.is_dir())
-                            .unwrap_or(false)
-                        {
-                            if let Ok(file_entries) = fs::read_dir(subdir_entry.path()) {
-                                for file_entry in file_entries.flatten() {
-                                    if let Some(filename) = file_entry.file_name().to_str() {
-                                        if filename.starts_with("header_")
-                                            && filename.ends_with(".txt")
-                                        {
-                                            if let Some(book_id_str) = filename
-                                                .strip_prefix("header_")
-                                                .and_then(|s| s.strip_suffix(".txt"))
-                                            {
-                                                if let Ok(book_id) = book_id_str.parse::<u32>() {
-                                                    books.push(book_id);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+fn stored_this_hour(datalake: &Datalake, book_id: u32) -> bool {
+    let datalake_path = create_datalake_path(datalake.path());
+    let header_path = format!("{}/header_{}.txt", datalake_path, book_id);
+    let body_path = format!("{}/body_{}.txt", datalake_path, book_id);
+    std::path::Path::new(&header_path).exists() && std::path::Path::new(&body_path).exists()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    /// Both the header and the body file are stored.
+    Available,
+    /// Only the header file is stored.
+    Missing,
+}
+
+impl FileStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileStatus::Available => "available",
+            FileStatus::Missing => "missing",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListParams {
+    pub sort: Option<SortOrder>,
+    pub status: Option<FileStatus>,
+    /// Leading digits of the book IDs to keep.
+    pub prefix: Option<String>,
+    pub indexed: Option<bool>,
+}
 
-    books.sort();
-    books.dedup();
+impl ListParams {
+    fn applied_filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if let Some(sort) = self.sort {
+            filters.push(format!("sort={}", sort.as_str()));
+        }
+        if let Some(status) = self.status {
+            filters.push(format!("status={}", status.as_str()));
+        }
+        if let Some(prefix) = &self.prefix {
+            filters.push(format!("prefix={}", prefix));
+        }
+        if let Some(indexed) = self.indexed {
+            filters.push(format!("indexed={}", indexed));
+        }
+        filters
+    }
+}
 
-    Json(ListResponse {
-        count: books.len(),
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_books(
+    indexing: axum::extract::State<IndexingClient>,
+    datalake: axum::extract::State<Datalake>,
+    params: Result<Query<ListParams>, QueryRejection>,
+) -> Result<Json<ListResponse>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidRequest(rejection.body_text()))?;
+    list_books_in(datalake.path(), &params, &indexing).await.map(Json)
+}
+
+/// The books under `datalake_path` that pass `params`' filters, in its order.
+/// The indexing service is only asked when `indexed` is given.
+async fn list_books_in(
+    datalake_path: &std::path::Path,
+    params: &ListParams,
+    indexing: &IndexingClient,
+) -> Result<ListResponse, AppError> {
+    if let Some(prefix) = &params.prefix {
+        if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(AppError::InvalidRequest(format!(
+                "invalid prefix '{}', expected digits",
+                prefix
+            )));
+        }
+    }
+    let indexed = match params.indexed {
+        Some(_) => Some(indexing.indexed_books().await?),
+        None => None,
+    };
+
+    let stored = scan_datalake(datalake_path);
+    let count = stored.len();
+    let mut books: Vec<u32> = stored
+        .into_iter()
+        .filter(|(_, files)| match params.status {
+            Some(FileStatus::Available) => files.body.is_file(),
+            Some(FileStatus::Missing) => !files.body.is_file(),
+            None => true,
+        })
+        .map(|(book_id, _)| book_id)
+        .filter(|book_id| {
+            params
+                .prefix
+                .as_deref()
+                .is_none_or(|prefix| book_id.to_string().starts_with(prefix))
+        })
+        .filter(|book_id| match (&indexed, params.indexed) {
+            (Some(indexed), Some(wanted)) => indexed.contains(book_id) == wanted,
+            _ => true,
+        })
+        .collect();
+    if params.sort == Some(SortOrder::Desc) {
+        books.reverse();
+    }
+
+    Ok(ListResponse {
+        count,
+        filtered_count: books.len(),
+        applied_filters: params.applied_filters(),
         books,
     })
 }
+
+#[tracing::instrument(skip_all)]
+pub async fn download_stats(
+    stats: axum::extract::State<Arc<DownloadStats>>,
+) -> Json<DownloadStatsResponse> {
+    Json(stats.snapshot())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn download_queue(queue: axum::extract::State<DownloadQueue>) -> Json<DownloadQueueResponse> {
+    Json(queue.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app;
+    use crate::state::AppState;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    async fn get_json(app: axum::Router, uri: &str) -> Value {
+        let response = app.oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn books_stored_before_a_restart_stay_available() {
+        use crate::utils::file::initialize_book_registry;
+        use std::sync::{Arc, Mutex};
+
+        let datalake = tempfile::tempdir().unwrap();
+        let hour = datalake.path().join("20250101/09");
+        std::fs::create_dir_all(&hour).unwrap();
+        std::fs::write(hour.join("header_990101.txt"), "Title: Stored Earlier\n").unwrap();
+        std::fs::write(hour.join("body_990101.txt"), "A body written by a previous run").unwrap();
+        std::fs::write(hour.join("header_990102.txt"), "Title: Never Finished\n").unwrap();
+
+        // A fresh service knows nothing until the registry is seeded
+        let before = get_json(app(AppState::default()), "/ingest/status/990101").await;
+        assert_eq!(before["status"], "not_found");
+
+        let restarted = app(AppState {
+            downloaded_books: Arc::new(Mutex::new(initialize_book_registry(datalake.path()))),
+            ..AppState::default()
+        });
+        let stored = get_json(restarted.clone(), "/ingest/status/990101").await;
+        assert_eq!(stored["status"], "available");
+        let unfinished = get_json(restarted, "/ingest/status/990102").await;
+        assert_eq!(unfinished["status"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn stats_endpoint_reports_recorded_downloads() {
+        let state = AppState::default();
+        state.stats.record_success(2_048, Duration::from_millis(500));
+        state.stats.record_failure();
+
+        let response = app(state)
+            .oneshot(Request::get("/ingest/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let stats: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["total_downloads"], 1);
+        assert_eq!(stats["failed_downloads"], 1);
+        assert_eq!(stats["avg_speed_bps"], 4096.0);
+    }
+
+    #[test]
+    fn failed_ingestion_maps_to_error_codes() {
+        use super::ingest_error;
+        use crate::services::download::DownloadError;
+        use crate::services::validation::ValidationError;
+        use axum::response::IntoResponse;
+
+        let rejected = ingest_error(7, Box::new(ValidationError::EmptyBody));
+        assert_eq!(rejected.error_code(), "VALIDATION_FAILED");
+        assert_eq!(rejected.to_string(), "Book 7 failed validation (empty_body): book body is empty");
+        assert_eq!(rejected.into_response().status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let missing = DownloadError::Status {
+            book_id: 7,
+            mirror: reqwest::Url::parse("https://www.gutenberg.org").unwrap(),
+            status: reqwest::StatusCode::NOT_FOUND,
+        };
+        assert_eq!(ingest_error(7, Box::new(missing)).error_code(), "BOOK_NOT_FOUND");
+
+        let exhausted = DownloadError::MaxRetriesExceeded { book_id: 7, attempts: 4 };
+        assert_eq!(ingest_error(7, Box::new(exhausted)).status(), StatusCode::BAD_GATEWAY);
+
+        let unwritable = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "read-only");
+        assert_eq!(ingest_error(7, Box::new(unwritable)).error_code(), "INTERNAL_ERROR");
+    }
+
+    #[tokio::test]
+    async fn queue_lists_books_waiting_for_and_in_download() {
+        use crate::services::download::MirrorSelector;
+        use crate::services::queue::DownloadQueue;
+        use axum::routing::get;
+        use axum::Router;
+        use std::sync::Arc;
+
+        // A mirror that never answers, so downloads stay in progress
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mirror = reqwest::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let stalled = Router::new().fallback(get(std::future::pending::<()>));
+        tokio::spawn(async move { axum::serve(listener, stalled).await.unwrap() });
+
+        let app = app(AppState {
+            mirrors: Arc::new(MirrorSelector::new(vec![mirror])),
+            queue: DownloadQueue::new(2),
+            ..AppState::default()
+        });
+        let book_ids: [u32; 5] = [990_001, 990_002, 990_003, 990_004, 990_005];
+        for book_id in book_ids {
+            let request = Request::post(format!("/ingest/{}", book_id)).body(Body::empty()).unwrap();
+            tokio::spawn(app.clone().oneshot(request));
+        }
+
+        let mut queue = Value::Null;
+        for _ in 0..100 {
+            let response = app
+                .clone()
+                .oneshot(Request::get("/ingest/queue").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            queue = serde_json::from_slice(&body).unwrap();
+            if queue["queued"].as_array().unwrap().len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let in_progress = queue["in_progress"].as_array().unwrap();
+        let queued = queue["queued"].as_array().unwrap();
+        assert_eq!(in_progress.len(), 2, "{}", queue);
+        assert_eq!(queued.len(), 3, "{}", queue);
+        let mut listed: Vec<u64> = in_progress.iter().chain(queued).map(|id| id.as_u64().unwrap()).collect();
+        listed.sort_unstable();
+        assert_eq!(listed, book_ids.map(u64::from));
+        assert_eq!(queue["completed_last_hour"], 0);
+        assert_eq!(queue["failed_last_hour"], 0);
+    }
+
+    /// A datalake holding books 84, 1342, 1399 and 13 (the last without a
+    /// body file).
+    fn listed_datalake() -> tempfile::TempDir {
+        let datalake = tempfile::tempdir().unwrap();
+        let hour = datalake.path().join("20250101/09");
+        std::fs::create_dir_all(&hour).unwrap();
+        for book_id in [84, 1342, 1399, 13] {
+            std::fs::write(hour.join(format!("header_{}.txt", book_id)), "Title: Listed\n").unwrap();
+            if book_id != 13 {
+                std::fs::write(hour.join(format!("body_{}.txt", book_id)), "A listed body").unwrap();
+            }
+        }
+        datalake
+    }
+
+    #[tokio::test]
+    async fn listing_is_sorted_and_filtered_by_files_and_prefix() {
+        use super::{list_books_in, FileStatus, ListParams, SortOrder};
+        use crate::services::indexing::IndexingClient;
+
+        let datalake = listed_datalake();
+        // Never contacted without `indexed`
+        let indexing = IndexingClient::new("http://127.0.0.1:9");
+        let list = |params: ListParams| {
+            let indexing = indexing.clone();
+            let root = datalake.path().to_path_buf();
+            async move { list_books_in(&root, &params, &indexing).await.unwrap() }
+        };
+
+        let all = list(ListParams::default()).await;
+        assert_eq!(all.books, [13, 84, 1342, 1399]);
+        assert_eq!((all.count, all.filtered_count), (4, 4));
+        assert!(all.applied_filters.is_empty());
+
+        let desc = list(ListParams {
+            sort: Some(SortOrder::Desc),
+            ..ListParams::default()
+        })
+        .await;
+        assert_eq!(desc.books, [1399, 1342, 84, 13]);
+        assert_eq!(desc.applied_filters, ["sort=desc"]);
+
+        let available = list(ListParams {
+            status: Some(FileStatus::Available),
+            prefix: Some("13".to_string()),
+            ..ListParams::default()
+        })
+        .await;
+        assert_eq!(available.books, [1342, 1399]);
+        assert_eq!((available.count, available.filtered_count), (4, 2));
+        assert_eq!(available.applied_filters, ["status=available", "prefix=13"]);
+
+        let missing = list(ListParams {
+            status: Some(FileStatus::Missing),
+            ..ListParams::default()
+        })
+        .await;
+        assert_eq!(missing.books, [13]);
+
+        let invalid = ListParams {
+            prefix: Some("1a".to_string()),
+            ..ListParams::default()
+        };
+        let err = list_books_in(datalake.path(), &invalid, &indexing).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn listing_asks_the_indexing_service_which_books_are_indexed() {
+        use super::{list_books_in, ListParams};
+        use crate::services::indexing::IndexingClient;
+        use axum::routing::get;
+        use axum::{Json, Router};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let indexing_service = Router::new().route(
+            "/index/books",
+            get(|| async { Json(serde_json::json!({"count": 2, "books": [84, 1399]})) }),
+        );
+        tokio::spawn(async move { axum::serve(listener, indexing_service).await.unwrap() });
+
+        let datalake = listed_datalake();
+        let indexing = IndexingClient::new(url);
+        let indexed = ListParams {
+            indexed: Some(true),
+            ..ListParams::default()
+        };
+        let listed = list_books_in(datalake.path(), &indexed, &indexing).await.unwrap();
+        assert_eq!(listed.books, [84, 1399]);
+        assert_eq!(listed.applied_filters, ["indexed=true"]);
+
+        let not_indexed = ListParams {
+            indexed: Some(false),
+            ..ListParams::default()
+        };
+        let listed = list_books_in(datalake.path(), &not_indexed, &indexing).await.unwrap();
+        assert_eq!(listed.books, [13, 1342]);
+
+        let unreachable = IndexingClient::new("http://127.0.0.1:9");
+        let err = list_books_in(datalake.path(), &indexed, &unreachable).await.unwrap_err();
+        assert_eq!(err.error_code(), "INDEXING_UNAVAILABLE");
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn listing_rejects_unknown_sort_orders() {
+        let response = app(AppState::default())
+            .oneshot(Request::get("/ingest/list?sort=sideways").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+    }
+
+    /// A `multipart/form-data` request to `POST /ingest/file` with the given
+    /// `(name, value)` fields.
+    fn upload(fields: &[(&str, &str)]) -> Request<Body> {
+        let boundary = "gutenberg-upload";
+        let mut body = String::new();
+        for (name, value) in fields {
+            let filename = if *name == "file" { "; filename=\"book.txt\"" } else { "" };
+            body.push_str(&format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"{}\r\n\r\n{}\r\n",
+                boundary, name, filename, value
+            ));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        Request::post("/ingest/file")
+            .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn uploaded_books_are_validated_and_stored() {
+        use crate::utils::file::{END_MARKER, START_MARKER};
+
+        let book_id = 990_101;
+        let text = format!(
+            "Title: A Synthetic Book\nAuthor: Test Author\nLanguage: English\n\n{} A SYNTHETIC BOOK ***\n{}\n{} A SYNTHETIC BOOK ***\n",
+            START_MARKER,
+            "It was a dark and stormy night; the rain fell in torrents. ".repeat(30),
+            END_MARKER
+        );
+        let state = AppState::default();
+        let app = app(state.clone());
+
+        let response = app
+            .clone()
+            .oneshot(upload(&[("book_id", &book_id.to_string()), ("file", &text)]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let ingested: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(ingested["book_id"], book_id);
+        assert_eq!(ingested["status"], "uploaded");
+        let path = ingested["path"].as_str().unwrap().to_string();
+        let header = std::fs::read_to_string(format!("{}/header_{}.txt", path, book_id)).unwrap();
+        let stored_body = std::fs::read_to_string(format!("{}/body_{}.txt", path, book_id)).unwrap();
+        assert!(header.contains("Author: Test Author"), "{}", header);
+        assert!(stored_body.contains("dark and stormy night"), "{}", stored_body);
+        assert!(state.downloaded_books.lock().unwrap().contains(&book_id));
+
+        let response = app
+            .clone()
+            .oneshot(Request::get(format!("/ingest/status/{}", book_id)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["status"], "available");
+        let _ = std::fs::remove_file(format!("{}/header_{}.txt", path, book_id));
+        let _ = std::fs::remove_file(format!("{}/body_{}.txt", path, book_id));
+
+        let rejected = app
+            .clone()
+            .oneshot(upload(&[("book_id", "990102"), ("file", "Just some notes, no markers.")]))
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let missing = app.clone().oneshot(upload(&[("file", &text)])).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::BAD_REQUEST);
+        let invalid = app.oneshot(upload(&[("book_id", "moby"), ("file", &text)])).await.unwrap();
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rejected_uploads_say_why() {
+        use crate::utils::file::{END_MARKER, START_MARKER};
+
+        let text = format!("Title: Nothing Inside
+{} NOTHING ***
+
+{} NOTHING ***
+", START_MARKER, END_MARKER);
+        let response = app(AppState::default())
+            .oneshot(upload(&[("book_id", "990103"), ("file", &text)]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["error_code"], "VALIDATION_FAILED");
+        assert_eq!(error["reason"], "empty_body");
+        assert_eq!(error["book_id"], 990103);
+    }
+}
@@ -5,22 +5,374 @@
 //! datalake for further processing by downstream services.
 //!
 //! ## Responsibilities
-//! - Fetch book text files from Project Gutenberg by book ID  
-//! - Split content into header/body using `header_body_split`  
-//! - Persist results into the structured datalake directory
+//! - Fetch book text files from Project Gutenberg by book ID
+//! - Spread downloads over the configured mirrors
+//! - Validate the text (see [`crate::services::validation`])
+//! - Split content into header/body using `header_body_split`
+//! - Persist results into the structured datalake directory, also for
+//!   books uploaded as files (see [`store_text`])
+//!
+//! Requests time out after `REQUEST_TIMEOUT_SECS` seconds (default: `30`) so
+//! a stalled mirror can't block an ingestion request forever.
+//!
+//! ## Mirrors
+//! Gutenberg throttles repeated requests from one IP, so `GUTENBERG_MIRRORS`
+//! can list several base URLs (comma-separated, default
+//! `https://www.gutenberg.org`). Each must serve books at the same
+//! `cache/epub/{id}/pg{id}.txt` path. Successive downloads start at the next
+//! mirror in turn, and a download moves on to the following mirror when one
+//! can't be reached or answers `429` or `5xx`.
+//!
+//! ## Retries
+//! When every mirror fails that way, the whole download is retried up to
+//! `DOWNLOAD_MAX_RETRIES` times (default: `3`), waiting longer before each
+//! attempt (see [`BackoffConfig`]). Permanent failures such as `404` or
+//! `410` are never retried.
 
-use crate::utils::file::{create_datalake_path, header_body_split};
+use crate::services::queue::DownloadQueue;
+use crate::services::validation::validate_book_content;
+use crate::utils::file::{
+    create_datalake_path, header_body_split, remove_older_copies, write_book_files,
+};
+use rand::Rng;
+use reqwest::{StatusCode, Url};
 use std::fs;
-use tracing::info;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MIRROR: &str = "https://www.gutenberg.org";
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// The client every download goes through, so connections to a mirror are
+/// kept alive and reused rather than opened for each book.
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        let timeout = env_or("REQUEST_TIMEOUT_SECS", DEFAULT_REQUEST_TIMEOUT_SECS);
+
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum DownloadError {
+    #[error("no Gutenberg mirrors configured")]
+    NoMirrors,
+    #[error("HTTP client error: {0}")]
+    Client(#[from] reqwest::Error),
+    #[error("{mirror} answered {status} for book {book_id}")]
+    Status {
+        book_id: u32,
+        mirror: Url,
+        status: StatusCode,
+    },
+    #[error("book {book_id} could not be downloaded after {attempts} attempts")]
+    MaxRetriesExceeded { book_id: u32, attempts: u32 },
+}
+
+impl DownloadError {
+    /// Whether the failure may clear up: the mirror couldn't be reached or
+    /// was overloaded, so another mirror or a later attempt might succeed.
+    fn is_transient(&self) -> bool {
+        match self {
+            DownloadError::Client(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            DownloadError::Status { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            DownloadError::NoMirrors | DownloadError::MaxRetriesExceeded { .. } => false,
+        }
+    }
+}
+
+/// Exponential backoff between download attempts.
+///
+/// Read from `DOWNLOAD_BACKOFF_INITIAL_MS` (default: `500`),
+/// `DOWNLOAD_BACKOFF_MULTIPLIER` (default: `2.0`), `DOWNLOAD_BACKOFF_MAX_MS`
+/// (default: `10000`) and `DOWNLOAD_BACKOFF_JITTER` (default: `0.2`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackoffConfig {
+    /// Wait before the first retry.
+    pub initial_delay_ms: u64,
+    /// Factor the wait grows by with each retry.
+    pub multiplier: f64,
+    /// Longest wait, before jitter.
+    pub max_delay_ms: u64,
+    /// Each wait is randomly lengthened or shortened by up to this fraction,
+    /// so clients throttled together don't retry together.
+    pub jitter_fraction: f64,
+}
 
-pub async fn download_book(
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 500,
+            multiplier: 2.0,
+            max_delay_ms: 10_000,
+            jitter_fraction: 0.2,
+        }
+    }
+}
+
+impl BackoffConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            initial_delay_ms: env_or("DOWNLOAD_BACKOFF_INITIAL_MS", defaults.initial_delay_ms),
+            multiplier: env_or("DOWNLOAD_BACKOFF_MULTIPLIER", defaults.multiplier).max(1.0),
+            max_delay_ms: env_or("DOWNLOAD_BACKOFF_MAX_MS", defaults.max_delay_ms),
+            jitter_fraction: env_or("DOWNLOAD_BACKOFF_JITTER", defaults.jitter_fraction).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Wait before retry number `retry` (starting at 1), without jitter.
+    fn base_delay_ms(&self, retry: u32) -> f64 {
+        let delay = self.initial_delay_ms as f64 * self.multiplier.powi(retry.saturating_sub(1) as i32);
+        delay.min(self.max_delay_ms as f64)
+    }
+
+    /// Wait before retry number `retry` (starting at 1).
+    pub fn delay(&self, retry: u32) -> Duration {
+        let base = self.base_delay_ms(retry);
+        let jitter = if self.jitter_fraction > 0.0 {
+            rand::thread_rng().gen_range(-self.jitter_fraction..=self.jitter_fraction)
+        } else {
+            0.0
+        };
+        Duration::from_millis((base * (1.0 + jitter)).round() as u64)
+    }
+}
+
+/// How often, and how patiently, a failed download is retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff: BackoffConfig,
+}
+
+impl RetryPolicy {
+    /// Reads `DOWNLOAD_MAX_RETRIES` and the [`BackoffConfig`] variables.
+    pub fn from_env() -> Self {
+        Self {
+            max_retries: env_or("DOWNLOAD_MAX_RETRIES", DEFAULT_MAX_RETRIES),
+            backoff: BackoffConfig::from_env(),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Round-robin over the configured Gutenberg mirrors.
+#[derive(Debug)]
+pub struct MirrorSelector {
+    mirrors: Vec<Url>,
+    next: AtomicUsize,
+}
+
+impl MirrorSelector {
+    pub fn new(mirrors: Vec<Url>) -> Self {
+        Self {
+            mirrors,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reads `GUTENBERG_MIRRORS`, skipping entries that aren't valid URLs.
+    pub fn from_env() -> Self {
+        let configured = std::env::var("GUTENBERG_MIRRORS").unwrap_or_default();
+        let mut mirrors = Vec::new();
+        for entry in configured.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match Url::parse(entry) {
+                Ok(url) => mirrors.push(url),
+                Err(e) => warn!("Ignoring invalid Gutenberg mirror '{}': {}", entry, e),
+            }
+        }
+
+        if mirrors.is_empty() {
+            mirrors.push(Url::parse(DEFAULT_MIRROR).expect("default mirror is a valid URL"));
+        }
+        Self::new(mirrors)
+    }
+
+    pub fn mirrors(&self) -> &[Url] {
+        &self.mirrors
+    }
+
+    /// Every mirror, starting one further along than the previous call.
+    pub fn rotation(&self) -> Vec<Url> {
+        if self.mirrors.is_empty() {
+            return Vec::new();
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.mirrors.len();
+        let mut mirrors = self.mirrors.clone();
+        mirrors.rotate_left(start);
+        mirrors
+    }
+}
+
+impl Default for MirrorSelector {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Where `mirror` serves the plain-text edition of a book.
+fn book_url(mirror: &Url, book_id: u32) -> Url {
+    let path = format!("cache/epub/{}/pg{}.txt", book_id, book_id);
+    // A base without a trailing slash would lose its last path segment
+    let base = if mirror.path().ends_with('/') {
+        mirror.clone()
+    } else {
+        let mut base = mirror.clone();
+        base.set_path(&format!("{}/", mirror.path()));
+        base
+    };
+    base.join(&path).expect("book path is a valid relative URL")
+}
+
+async fn fetch_from(client: &reqwest::Client, mirror: &Url, book_id: u32) -> Result<String, DownloadError> {
+    let response = client.get(book_url(mirror, book_id)).send().await?;
+
+    if !response.status().is_success() {
+        return Err(DownloadError::Status {
+            book_id,
+            mirror: mirror.clone(),
+            status: response.status(),
+        });
+    }
+    Ok(response.text().await?)
+}
+
+/// Fetches a book's text from the first of `mirrors` that serves it. Mirrors
+/// that can't be reached or are overloaded are skipped; any other failure,
+/// such as `404`, is returned straight away.
+#[tracing::instrument(skip(mirrors), err)]
+pub async fn download_book(book_id: u32, mirrors: &[Url]) -> Result<String, DownloadError> {
+    let client = http_client();
+    let mut last_error = DownloadError::NoMirrors;
+
+    for mirror in mirrors {
+        match fetch_from(client, mirror, book_id).await {
+            Ok(text) => {
+                info!("Downloaded book {} from mirror {}", book_id, mirror);
+                return Ok(text);
+            }
+            Err(e) if e.is_transient() => {
+                warn!("Mirror {} failed for book {}, trying the next one: {}", mirror, book_id, e);
+                last_error = e;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error)
+}
+
+/// [`download_book`] through `mirrors`, retried up to `max_retries` times
+/// while it fails transiently.
+#[tracing::instrument(skip(mirrors, backoff), err)]
+pub async fn retry_download_from(
     book_id: u32,
+    mirrors: &[Url],
+    max_retries: u32,
+    backoff: BackoffConfig,
+) -> Result<String, DownloadError> {
+    let mut retry = 0;
+    loop {
+        match download_book(book_id, mirrors).await {
+            Err(e) if e.is_transient() => {
+                if retry == max_retries {
+                    warn!("Giving up on book {} after {} attempts: {}", book_id, retry + 1, e);
+                    return Err(DownloadError::MaxRetriesExceeded {
+                        book_id,
+                        attempts: retry + 1,
+                    });
+                }
+                retry += 1;
+                let delay = backoff.delay(retry);
+                warn!(
+                    "Download of book {} failed ({}), retry {} of {} in {:?}",
+                    book_id, e, retry, max_retries, delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// [`retry_download_from`] the next [`MirrorSelector::rotation`] of
+/// `mirrors`, so successive downloads start at different mirrors.
+pub async fn retry_download(
+    book_id: u32,
+    mirrors: &MirrorSelector,
+    max_retries: u32,
+    backoff: BackoffConfig,
+) -> Result<String, DownloadError> {
+    retry_download_from(book_id, &mirrors.rotation(), max_retries, backoff).await
+}
+
+/// Result of a successful [`store_book`] call.
+#[derive(Debug)]
+pub struct Download {
+    pub path: String,
+    /// Bytes fetched from Gutenberg; `None` when the book was already stored.
+    pub bytes: Option<u64>,
+}
+
+/// Validates `text` as a Gutenberg book and writes its header and body to
+/// the current directory of the datalake at `datalake`, replacing a copy
+/// already there and removing copies stored in earlier directories. Returns
+/// that directory.
+pub fn store_text(
+    datalake: &std::path::Path,
+    book_id: u32,
+    text: &str,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    let url = format!(
-        "https://www.gutenberg.org/cache/epub/{}/pg{}.txt",
-        book_id, book_id
-    );
-    let datalake_path = create_datalake_path();
+    validate_book_content(text)?;
+    let (header, body) = header_body_split(text);
+
+    let datalake_path = create_datalake_path(datalake);
+    fs::create_dir_all(&datalake_path)?;
+    let dir = std::path::Path::new(&datalake_path);
+    write_book_files(dir, book_id, &header, &body)?;
+    remove_older_copies(datalake, book_id, dir);
+
+    Ok(datalake_path)
+}
+
+/// Downloads a book through `mirrors`, retrying as `retries` allows, and
+/// stores its header and body in the datalake at `datalake`, unless it is
+/// already there.
+/// The download waits its turn in `queue`.
+/// Books failing validation are not stored and return a
+/// [`ValidationError`](crate::services::validation::ValidationError).
+#[tracing::instrument(skip(datalake, mirrors, retries, queue), err)]
+pub async fn store_book(
+    datalake: &std::path::Path,
+    book_id: u32,
+    mirrors: &MirrorSelector,
+    retries: &RetryPolicy,
+    queue: &DownloadQueue,
+) -> Result<Download, Box<dyn std::error::Error + Send + Sync>> {
+    let datalake_path = create_datalake_path(datalake);
 
     fs::create_dir_all(&datalake_path)?;
 
@@ -29,27 +381,211 @@ pub async fn download_book(
 
     if std::path::Path::new(&header_path).exists() && std::path::Path::new(&body_path).exists() {
         info!("Book {} already exists, skipping download", book_id);
-        return Ok(datalake_path);
+        return Ok(Download {
+            path: datalake_path,
+            bytes: None,
+        });
     }
 
-    info!("Downloading book {} from {}", book_id, url);
+    let text = queue
+        .run(book_id, retry_download(book_id, mirrors, retries.max_retries, retries.backoff))
+        .await?;
+    let bytes = text.len() as u64;
+    let datalake_path = store_text(datalake, book_id, &text)?;
 
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await?;
+    info!(
+        "Successfully downloaded book {} to {}",
+        book_id, datalake_path
+    );
+    Ok(Download {
+        path: datalake_path,
+        bytes: Some(bytes),
+    })
+}
 
-    if !response.status().is_success() {
-        return Err(format!("Failed to download book {}: {}", book_id, response.status()).into());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, http::StatusCode as AxumStatus, routing::get, Router};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    /// Serves `pg{id}.txt` for every book like a Gutenberg mirror, or answers
+    /// every request with `status` when one is given.
+    async fn mock_mirror(status: Option<AxumStatus>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/gutenberg", listener.local_addr().unwrap())).unwrap();
+        let app = Router::new().route(
+            "/gutenberg/cache/epub/:book_id/:file",
+            get(move |Path((book_id, file)): Path<(u32, String)>| async move {
+                match status {
+                    Some(status) => Err(status),
+                    None if file == format!("pg{}.txt", book_id) => Ok(format!("Text of book {}", book_id)),
+                    None => Err(AxumStatus::NOT_FOUND),
+                }
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
     }
 
-    let text = response.text().await?;
-    let (header, body) = header_body_split(&text);
+    /// A mirror answering `status` to its first `failures` requests, then
+    /// serving every book, with a count of the requests it received.
+    async fn flaky_mirror(status: AxumStatus, failures: usize) -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().route(
+            "/cache/epub/:book_id/:file",
+            get(move |Path((book_id, _)): Path<(u32, String)>| async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    Err(status)
+                } else {
+                    Ok(format!("Text of book {}", book_id))
+                }
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
 
-    fs::write(&header_path, header)?;
-    fs::write(&body_path, body)?;
+    fn no_wait() -> BackoffConfig {
+        BackoffConfig {
+            initial_delay_ms: 0,
+            ..BackoffConfig::default()
+        }
+    }
 
-    info!(
-        "Successfully downloaded book {} to {}",
-        book_id, datalake_path
-    );
-    Ok(datalake_path)
+    /// A URL nothing listens on.
+    async fn unreachable_mirror() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        url
+    }
+
+    #[tokio::test]
+    async fn downloads_from_a_mirror() {
+        let mirror = mock_mirror(None).await;
+        assert_eq!(download_book(1342, &[mirror]).await.unwrap(), "Text of book 1342");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_mirror() {
+        let mirrors = [
+            unreachable_mirror().await,
+            mock_mirror(Some(AxumStatus::TOO_MANY_REQUESTS)).await,
+            mock_mirror(None).await,
+        ];
+        assert_eq!(download_book(84, &mirrors).await.unwrap(), "Text of book 84");
+    }
+
+    #[tokio::test]
+    async fn missing_book_is_not_retried_elsewhere() {
+        let mirrors = [
+            mock_mirror(Some(AxumStatus::NOT_FOUND)).await,
+            mock_mirror(None).await,
+        ];
+        let err = download_book(84, &mirrors).await.unwrap_err();
+        assert!(matches!(err, DownloadError::Status { status: StatusCode::NOT_FOUND, .. }));
+    }
+
+    #[tokio::test]
+    async fn reports_the_last_error_when_every_mirror_fails() {
+        let mirrors = [unreachable_mirror().await, unreachable_mirror().await];
+        assert!(matches!(download_book(11, &mirrors).await, Err(DownloadError::Client(_))));
+        assert!(matches!(download_book(11, &[]).await, Err(DownloadError::NoMirrors)));
+    }
+
+    #[test]
+    fn rotation_starts_at_the_next_mirror_each_time() {
+        let urls: Vec<Url> = ["http://a.example/", "http://b.example/", "http://c.example/"]
+            .iter()
+            .map(|u| Url::parse(u).unwrap())
+            .collect();
+        let selector = MirrorSelector::new(urls.clone());
+
+        let firsts: Vec<Url> = (0..4).map(|_| selector.rotation()[0].clone()).collect();
+        assert_eq!(firsts, [urls[0].clone(), urls[1].clone(), urls[2].clone(), urls[0].clone()]);
+        assert_eq!(selector.rotation(), [urls[1].clone(), urls[2].clone(), urls[0].clone()]);
+    }
+
+    #[test]
+    fn book_urls_keep_the_mirror_path() {
+        let mirror = Url::parse("https://mirror.example/gutenberg").unwrap();
+        assert_eq!(
+            book_url(&mirror, 1342).as_str(),
+            "https://mirror.example/gutenberg/cache/epub/1342/pg1342.txt"
+        );
+        let root = Url::parse("https://www.gutenberg.org").unwrap();
+        assert_eq!(
+            book_url(&root, 84).as_str(),
+            "https://www.gutenberg.org/cache/epub/84/pg84.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let (mirror, requests) = flaky_mirror(AxumStatus::SERVICE_UNAVAILABLE, 2).await;
+
+        let text = retry_download_from(98, &[mirror], 3, no_wait()).await.unwrap();
+
+        assert_eq!(text, "Text of book 98");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_rotate_through_the_shared_selector() {
+        let (first, first_requests) = flaky_mirror(AxumStatus::SERVICE_UNAVAILABLE, 0).await;
+        let (second, second_requests) = flaky_mirror(AxumStatus::SERVICE_UNAVAILABLE, 0).await;
+        let selector = MirrorSelector::new(vec![first, second]);
+
+        for book_id in [1, 2] {
+            retry_download(book_id, &selector, 0, no_wait()).await.unwrap();
+        }
+
+        assert_eq!(first_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(second_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn permanent_failures_are_not_retried() {
+        for status in [AxumStatus::NOT_FOUND, AxumStatus::GONE] {
+            let (mirror, requests) = flaky_mirror(status, 1).await;
+
+            let err = retry_download_from(98, &[mirror], 3, no_wait()).await.unwrap_err();
+
+            assert!(matches!(err, DownloadError::Status { status: s, .. } if s.as_u16() == status.as_u16()));
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (mirror, requests) = flaky_mirror(AxumStatus::TOO_MANY_REQUESTS, usize::MAX).await;
+
+        let err = retry_download_from(98, &[mirror], 2, no_wait()).await.unwrap_err();
+
+        assert!(matches!(err, DownloadError::MaxRetriesExceeded { book_id: 98, attempts: 3 }));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_cap() {
+        let backoff = BackoffConfig {
+            initial_delay_ms: 100,
+            multiplier: 2.0,
+            max_delay_ms: 1_000,
+            jitter_fraction: 0.0,
+        };
+        let delays: Vec<u64> = (1..=6).map(|retry| backoff.delay(retry).as_millis() as u64).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1_000, 1_000]);
+
+        let jittered = BackoffConfig { jitter_fraction: 0.5, ..backoff };
+        for _ in 0..100 {
+            let delay = jittered.delay(2).as_millis();
+            assert!((100..=300).contains(&delay), "{}ms is outside the jitter range", delay);
+        }
+    }
 }
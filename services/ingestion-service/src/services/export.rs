@@ -0,0 +1,97 @@
+//! Datalake Export
+//!
+//! Streams the datalake as newline-delimited JSON, one book per line:
+//!
+//! ```text
+//! {"book_id":84,"header":"...","body":"..."}
+//! ```
+//!
+//! Files are read in chunks and JSON-escaped on the fly, so memory use stays
+//! flat however large the datalake is. Escaping works byte by byte: only
+//! quotes, backslashes and control characters change, and multi-byte UTF-8
+//! sequences pass through untouched even when split across chunks.
+
+use crate::utils::file::BookFiles;
+use axum::body::Bytes;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::io;
+use std::path::PathBuf;
+use tokio_util::io::ReaderStream;
+
+/// The NDJSON lines of `books`, in the order given.
+pub fn ndjson_stream(books: Vec<(u32, BookFiles)>) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+    stream::iter(books).flat_map(|(book_id, files)| book_line(book_id, files))
+}
+
+/// One book's line: its two files as JSON strings between the fixed parts.
+fn book_line(book_id: u32, files: BookFiles) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+    let fixed = |text: String| stream::once(async move { Ok(Bytes::from(text)) });
+
+    fixed(format!("{{\"book_id\":{},\"header\":\"", book_id))
+        .chain(escaped_file(files.header))
+        .chain(fixed("\",\"body\":\"".to_string()))
+        .chain(escaped_file(files.body))
+        .chain(fixed("\"}\n".to_string()))
+}
+
+/// The contents of `path`, escaped for use inside a JSON string.
+fn escaped_file(path: PathBuf) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+    stream::once(async move { tokio::fs::File::open(path).await.map(ReaderStream::new) })
+        .try_flatten()
+        .map_ok(|chunk| escape_json(&chunk))
+}
+
+/// Escapes `chunk` for the inside of a JSON string.
+fn escape_json(chunk: &[u8]) -> Bytes {
+    let mut escaped = Vec::with_capacity(chunk.len() + chunk.len() / 16);
+    for &byte in chunk {
+        match byte {
+            b'"' => escaped.extend_from_slice(b"\\\""),
+            b'\\' => escaped.extend_from_slice(b"\\\\"),
+            b'\n' => escaped.extend_from_slice(b"\\n"),
+            b'\r' => escaped.extend_from_slice(b"\\r"),
+            b'\t' => escaped.extend_from_slice(b"\\t"),
+            0x00..=0x1f => escaped.extend_from_slice(format!("\\u{:04x}", byte).as_bytes()),
+            _ => escaped.push(byte),
+        }
+    }
+    Bytes::from(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::file::scan_datalake;
+    use serde_json::Value;
+    use std::fs;
+
+    #[test]
+    fn escapes_json_special_characters() {
+        let escaped = escape_json("“Quote” \"me\"\\\n\tnow\u{1}".as_bytes());
+        let parsed: String = serde_json::from_slice(&[b"\"", &escaped[..], b"\""].concat()).unwrap();
+        assert_eq!(parsed, "“Quote” \"me\"\\\n\tnow\u{1}");
+    }
+
+    #[tokio::test]
+    async fn streams_one_json_line_per_book() {
+        let datalake = tempfile::tempdir().unwrap();
+        let hour = datalake.path().join("20250101/09");
+        fs::create_dir_all(&hour).unwrap();
+        fs::write(hour.join("header_84.txt"), "Title: Frankenstein\n").unwrap();
+        fs::write(hour.join("body_84.txt"), "“You will rejoice to hear”\n\"quoted\"").unwrap();
+        fs::write(hour.join("header_1342.txt"), "Title: Pride and Prejudice\n").unwrap();
+        fs::write(hour.join("body_1342.txt"), "It is a truth universally acknowledged").unwrap();
+
+        let books = scan_datalake(datalake.path()).into_iter().collect();
+        let chunks: Vec<Bytes> = ndjson_stream(books).try_collect().await.unwrap();
+        let export = String::from_utf8(chunks.concat()).unwrap();
+
+        let lines: Vec<Value> = export.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["book_id"], 84);
+        assert_eq!(lines[0]["body"], "“You will rejoice to hear”\n\"quoted\"");
+        assert_eq!(lines[1]["book_id"], 1342);
+        assert_eq!(lines[1]["header"], "Title: Pride and Prejudice\n");
+        assert!(export.ends_with('\n'));
+    }
+}
@@ -0,0 +1,85 @@
+//! Indexing Service Client
+//!
+//! Asks the indexing service which books it has indexed, for the
+//! `?indexed=` filter of `GET /ingest/list`. The service is found at
+//! `INDEXING_SERVICE_URL` (default: `http://localhost:7002`).
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use thiserror::Error;
+
+const DEFAULT_INDEXING_SERVICE_URL: &str = "http://localhost:7002";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum IndexingError {
+    #[error("indexing service at {url} is unreachable: {source}")]
+    Unreachable { url: String, source: reqwest::Error },
+    #[error("indexing service at {url} answered {status}")]
+    Status { url: String, status: reqwest::StatusCode },
+    #[error("indexing service at {url} sent an unreadable book list: {source}")]
+    InvalidResponse { url: String, source: reqwest::Error },
+}
+
+/// The body of the indexing service's `GET /index/books`.
+#[derive(Debug, Deserialize)]
+struct IndexedBooks {
+    books: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexingClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl IndexingClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("INDEXING_SERVICE_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_INDEXING_SERVICE_URL.to_string());
+        Self::new(base_url)
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The IDs of every book the indexing service has indexed.
+    pub async fn indexed_books(&self) -> Result<HashSet<u32>, IndexingError> {
+        let url = format!("{}/index/books", self.base_url);
+        let response = self.client.get(&url).send().await.map_err(|source| IndexingError::Unreachable {
+            url: url.clone(),
+            source,
+        })?;
+        if !response.status().is_success() {
+            return Err(IndexingError::Status {
+                url,
+                status: response.status(),
+            });
+        }
+        let indexed: IndexedBooks = response
+            .json()
+            .await
+            .map_err(|source| IndexingError::InvalidResponse { url, source })?;
+        Ok(indexed.books.into_iter().collect())
+    }
+}
+
+impl Default for IndexingClient {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
@@ -1 +1,7 @@
 pub mod download;
+pub mod export;
+pub mod indexing;
+pub mod queue;
+pub mod startup;
+pub mod stats;
+pub mod validation;
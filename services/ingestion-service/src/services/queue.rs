@@ -0,0 +1,212 @@
+//! Download Queue
+//!
+//! At most `MAX_CONCURRENT_DOWNLOADS` books are fetched from Gutenberg at
+//! once; further ingest requests wait their turn, first come first served.
+//! The queue remembers which books are waiting, which are downloading and
+//! when the last [`RECENT_CAPACITY`] downloads finished, for
+//! `GET /ingest/queue`. Books already in the datalake never enter it.
+//!
+//! ## Environment Variables
+//! - `MAX_CONCURRENT_DOWNLOADS`: Downloads running at the same time (default: `4`)
+
+use crate::models::responses::DownloadQueueResponse;
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// Finished downloads remembered per outcome; older ones are forgotten.
+pub const RECENT_CAPACITY: usize = 1024;
+
+/// Window of `completed_last_hour` and `failed_last_hour`.
+const RECENT_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// When the latest downloads with one outcome finished, oldest first,
+/// keeping at most [`RECENT_CAPACITY`].
+#[derive(Debug, Default)]
+struct RecentOutcomes(VecDeque<Instant>);
+
+impl RecentOutcomes {
+    fn record(&mut self, at: Instant) {
+        if self.0.len() == RECENT_CAPACITY {
+            self.0.pop_front();
+        }
+        self.0.push_back(at);
+    }
+
+    fn count_since(&self, since: Instant) -> usize {
+        self.0.iter().rev().take_while(|&&at| at >= since).count()
+    }
+}
+
+/// Books waiting for a download slot, in order, and books downloading.
+#[derive(Debug, Default)]
+pub struct DownloadQueueState {
+    pub queued: VecDeque<u32>,
+    pub in_progress: HashSet<u32>,
+    completed: RecentOutcomes,
+    failed: RecentOutcomes,
+}
+
+impl DownloadQueueState {
+    fn remove_queued(&mut self, book_id: u32) {
+        if let Some(position) = self.queued.iter().position(|&id| id == book_id) {
+            self.queued.remove(position);
+        }
+    }
+
+    pub fn snapshot(&self) -> DownloadQueueResponse {
+        let mut in_progress: Vec<u32> = self.in_progress.iter().copied().collect();
+        in_progress.sort_unstable();
+        let since = Instant::now().checked_sub(RECENT_WINDOW);
+
+        DownloadQueueResponse {
+            queued: self.queued.iter().copied().collect(),
+            in_progress,
+            completed_last_hour: since.map_or(self.completed.0.len(), |since| self.completed.count_since(since)),
+            failed_last_hour: since.map_or(self.failed.0.len(), |since| self.failed.count_since(since)),
+        }
+    }
+}
+
+/// Shared queue of downloads.
+#[derive(Clone)]
+pub struct DownloadQueue {
+    state: Arc<RwLock<DownloadQueueState>>,
+    slots: Arc<Semaphore>,
+}
+
+impl Default for DownloadQueue {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl DownloadQueue {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Arc::default(),
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+        }
+    }
+
+    /// Builds a queue from `MAX_CONCURRENT_DOWNLOADS`.
+    pub fn from_env() -> Self {
+        let max_concurrent = std::env::var("MAX_CONCURRENT_DOWNLOADS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT);
+
+        Self::new(max_concurrent)
+    }
+
+    /// Runs `download` once a slot is free, recording whether it succeeded.
+    /// A request dropped while waiting or downloading leaves the queue
+    /// without counting as a failure.
+    pub async fn run<T, E>(&self, book_id: u32, download: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let mut ticket = Ticket::enqueue(self, book_id);
+        let _slot = self.slots.acquire().await.expect("download slots are never closed");
+        ticket.start();
+
+        let result = download.await;
+        ticket.finish(result.is_ok());
+        result
+    }
+
+    pub fn snapshot(&self) -> DownloadQueueResponse {
+        self.state.read().unwrap().snapshot()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Queued,
+    InProgress,
+    Finished,
+}
+
+/// A book's place in the queue, given up when dropped.
+struct Ticket<'a> {
+    queue: &'a DownloadQueue,
+    book_id: u32,
+    stage: Stage,
+}
+
+impl<'a> Ticket<'a> {
+    fn enqueue(queue: &'a DownloadQueue, book_id: u32) -> Self {
+        queue.state.write().unwrap().queued.push_back(book_id);
+        Self {
+            queue,
+            book_id,
+            stage: Stage::Queued,
+        }
+    }
+
+    fn start(&mut self) {
+        let mut state = self.queue.state.write().unwrap();
+        state.remove_queued(self.book_id);
+        state.in_progress.insert(self.book_id);
+        self.stage = Stage::InProgress;
+    }
+
+    fn finish(&mut self, succeeded: bool) {
+        let mut state = self.queue.state.write().unwrap();
+        state.in_progress.remove(&self.book_id);
+        let outcomes = if succeeded { &mut state.completed } else { &mut state.failed };
+        outcomes.record(Instant::now());
+        self.stage = Stage::Finished;
+    }
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.write().unwrap();
+        match self.stage {
+            Stage::Queued => state.remove_queued(self.book_id),
+            Stage::InProgress => {
+                state.in_progress.remove(&self.book_id);
+            }
+            Stage::Finished => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_outcomes_are_bounded() {
+        let mut outcomes = RecentOutcomes::default();
+        let start = Instant::now();
+        for _ in 0..RECENT_CAPACITY + 10 {
+            outcomes.record(start);
+        }
+        assert_eq!(outcomes.0.len(), RECENT_CAPACITY);
+        assert_eq!(outcomes.count_since(start), RECENT_CAPACITY);
+        assert_eq!(outcomes.count_since(start + Duration::from_secs(1)), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_outcomes_and_forgets_cancelled_downloads() {
+        let queue = DownloadQueue::new(1);
+        queue.run(1, async { Ok::<_, ()>(()) }).await.unwrap();
+        queue.run(2, async { Err::<(), _>(()) }).await.unwrap_err();
+
+        // Dropped while waiting for the only slot
+        let slot = queue.slots.clone().acquire_owned().await.unwrap();
+        let waiting = queue.run(3, async { Ok::<_, ()>(()) });
+        assert!(tokio::time::timeout(Duration::from_millis(20), waiting).await.is_err());
+        drop(slot);
+
+        let snapshot = queue.snapshot();
+        assert!(snapshot.queued.is_empty());
+        assert!(snapshot.in_progress.is_empty());
+        assert_eq!(snapshot.completed_last_hour, 1);
+        assert_eq!(snapshot.failed_last_hour, 1);
+    }
+}
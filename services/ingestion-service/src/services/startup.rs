@@ -0,0 +1,28 @@
+//! Startup Probe
+//!
+//! A cloneable flag raised the first time the datalake can be read. The
+//! service starts serving before its volume is necessarily mounted, so
+//! `/startup` keeps failing until this is set, and never fails again
+//! afterwards.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Default)]
+pub struct StartupProbe {
+    completed: Arc<AtomicBool>,
+}
+
+impl StartupProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn complete(&self) {
+        self.completed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed.load(Ordering::SeqCst)
+    }
+}
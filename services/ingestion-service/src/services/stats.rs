@@ -0,0 +1,72 @@
+//! Download Statistics
+//!
+//! In-memory counters for the downloads performed since the service started.
+//! They are reset on restart; nothing is persisted.
+
+use crate::models::responses::DownloadStatsResponse;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub struct DownloadStats {
+    pub total_downloads: AtomicU64,
+    pub total_bytes: AtomicU64,
+    pub failed_downloads: AtomicU64,
+    pub total_duration_ms: AtomicU64,
+}
+
+impl DownloadStats {
+    pub fn record_success(&self, bytes: u64, elapsed: Duration) {
+        self.total_downloads.fetch_add(1, Ordering::Relaxed);
+        self.total_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.total_duration_ms
+            .fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self) {
+        self.failed_downloads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Average throughput over all successful downloads, in bytes per second.
+    pub fn avg_speed_bps(&self) -> f64 {
+        let bytes = self.total_bytes.load(Ordering::Relaxed);
+        let millis = self.total_duration_ms.load(Ordering::Relaxed);
+        if millis == 0 {
+            return 0.0;
+        }
+        bytes as f64 * 1000.0 / millis as f64
+    }
+
+    pub fn snapshot(&self) -> DownloadStatsResponse {
+        DownloadStatsResponse {
+            total_downloads: self.total_downloads.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes.load(Ordering::Relaxed),
+            failed_downloads: self.failed_downloads.load(Ordering::Relaxed),
+            avg_speed_bps: self.avg_speed_bps(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_speed_over_successful_downloads() {
+        let stats = DownloadStats::default();
+        stats.record_success(4_000, Duration::from_millis(1_000));
+        stats.record_success(2_000, Duration::from_millis(1_000));
+        stats.record_failure();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total_downloads, 2);
+        assert_eq!(snapshot.total_bytes, 6_000);
+        assert_eq!(snapshot.failed_downloads, 1);
+        assert_eq!(snapshot.avg_speed_bps, 3_000.0);
+    }
+
+    #[test]
+    fn reports_zero_speed_before_any_download() {
+        assert_eq!(DownloadStats::default().avg_speed_bps(), 0.0);
+    }
+}
@@ -0,0 +1,118 @@
+//! Book Content Validation
+//!
+//! Checks a downloaded book before it is written to the datalake, so error
+//! pages, truncated transfers and files that aren't Gutenberg books never
+//! reach the indexer. A book must:
+//!
+//! 1. decode as UTF-8 without replacement characters (`reqwest` decodes
+//!    invalid bytes lossily, so they'd otherwise go unnoticed),
+//! 2. contain the Gutenberg start marker,
+//! 3. have a non-empty body between the start and end markers,
+//! 4. be at least `MIN_BOOK_SIZE_BYTES` long (default: `1000`).
+
+use crate::utils::file::{header_body_split, START_MARKER};
+use thiserror::Error;
+
+const DEFAULT_MIN_BOOK_SIZE_BYTES: usize = 1000;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("text is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("text has no Gutenberg start marker")]
+    MissingStartMarker,
+    #[error("book body is empty")]
+    EmptyBody,
+    #[error("text is {size} bytes, below the minimum of {min}")]
+    TooSmall { size: usize, min: usize },
+}
+
+impl ValidationError {
+    /// Machine-readable reason reported to clients.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ValidationError::InvalidUtf8 => "invalid_utf8",
+            ValidationError::MissingStartMarker => "missing_start_marker",
+            ValidationError::EmptyBody => "empty_body",
+            ValidationError::TooSmall { .. } => "too_small",
+        }
+    }
+}
+
+/// Reads `MIN_BOOK_SIZE_BYTES`, falling back to the default.
+fn min_book_size() -> usize {
+    std::env::var("MIN_BOOK_SIZE_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MIN_BOOK_SIZE_BYTES)
+}
+
+/// Checks that `text` looks like a complete Gutenberg book.
+pub fn validate_book_content(text: &str) -> Result<(), ValidationError> {
+    if std::str::from_utf8(text.as_bytes()).is_err() || text.contains(char::REPLACEMENT_CHARACTER) {
+        return Err(ValidationError::InvalidUtf8);
+    }
+    if !text.contains(START_MARKER) {
+        return Err(ValidationError::MissingStartMarker);
+    }
+    let (_, body) = header_body_split(text);
+    if body.trim().is_empty() {
+        return Err(ValidationError::EmptyBody);
+    }
+    let min = min_book_size();
+    if text.len() < min {
+        return Err(ValidationError::TooSmall { size: text.len(), min });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::file::END_MARKER;
+
+    fn book(body: &str) -> String {
+        format!(
+            "Title: Pride and Prejudice\n{} PRIDE AND PREJUDICE ***\n{}\n{} PRIDE AND PREJUDICE ***\n",
+            START_MARKER, body, END_MARKER
+        )
+    }
+
+    fn long_body() -> String {
+        "It is a truth universally acknowledged. ".repeat(50)
+    }
+
+    #[test]
+    fn accepts_a_complete_book() {
+        assert_eq!(validate_book_content(&book(&long_body())), Ok(()));
+    }
+
+    #[test]
+    fn rejects_lossily_decoded_text() {
+        let text = String::from_utf8_lossy(&[&book(&long_body()).into_bytes()[..], &[0xff, 0xfe]].concat()).into_owned();
+        assert_eq!(validate_book_content(&text), Err(ValidationError::InvalidUtf8));
+    }
+
+    #[test]
+    fn rejects_text_without_start_marker() {
+        let text = format!("<html><body>Not found</body></html>{}", long_body());
+        assert_eq!(validate_book_content(&text), Err(ValidationError::MissingStartMarker));
+    }
+
+    #[test]
+    fn rejects_an_empty_body() {
+        let text = format!("{}{}", long_body(), book("\n  \n"));
+        let err = validate_book_content(&text).unwrap_err();
+        assert_eq!(err, ValidationError::EmptyBody);
+        assert_eq!(err.reason(), "empty_body");
+    }
+
+    #[test]
+    fn rejects_books_below_the_minimum_size() {
+        let text = book("It is a truth universally acknowledged.");
+        assert!(matches!(
+            validate_book_content(&text),
+            Err(ValidationError::TooSmall { min: 1000, .. })
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+//! Shared Application State
+//!
+//! Handlers extract only the part they need (`State<DownloadedBooks>`,
+//! `State<Arc<DownloadStats>>`, `State<Arc<MirrorSelector>>`,
+//! `State<RetryPolicy>`, `State<DownloadQueue>`, `State<Arc<Instant>>`,
+//! `State<StartupProbe>`, `State<IndexingClient>`, `State<Datalake>`) through
+//! [`FromRef`].
+
+use crate::services::download::{MirrorSelector, RetryPolicy};
+use crate::services::indexing::IndexingClient;
+use crate::services::queue::DownloadQueue;
+use crate::services::startup::StartupProbe;
+use crate::services::stats::DownloadStats;
+use crate::utils::file::Datalake;
+use axum::extract::FromRef;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub type DownloadedBooks = Arc<Mutex<HashSet<u32>>>;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub downloaded_books: DownloadedBooks,
+    pub stats: Arc<DownloadStats>,
+    pub mirrors: Arc<MirrorSelector>,
+    pub retries: RetryPolicy,
+    pub queue: DownloadQueue,
+    /// When the service started, for the uptime reported by `/status`.
+    pub started_at: Arc<Instant>,
+    pub startup: StartupProbe,
+    /// Asked which books are indexed by `GET /ingest/list?indexed=`.
+    pub indexing: IndexingClient,
+    /// Where books are stored.
+    pub datalake: Datalake,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            downloaded_books: DownloadedBooks::default(),
+            stats: Arc::default(),
+            mirrors: Arc::default(),
+            retries: RetryPolicy::default(),
+            queue: DownloadQueue::default(),
+            started_at: Arc::new(Instant::now()),
+            startup: StartupProbe::new(),
+            indexing: IndexingClient::default(),
+            datalake: Datalake::default(),
+        }
+    }
+}
+
+impl FromRef<AppState> for DownloadedBooks {
+    fn from_ref(state: &AppState) -> Self {
+        state.downloaded_books.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<DownloadStats> {
+    fn from_ref(state: &AppState) -> Self {
+        state.stats.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<MirrorSelector> {
+    fn from_ref(state: &AppState) -> Self {
+        state.mirrors.clone()
+    }
+}
+
+impl FromRef<AppState> for RetryPolicy {
+    fn from_ref(state: &AppState) -> Self {
+        state.retries
+    }
+}
+
+impl FromRef<AppState> for DownloadQueue {
+    fn from_ref(state: &AppState) -> Self {
+        state.queue.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Instant> {
+    fn from_ref(state: &AppState) -> Self {
+        state.started_at.clone()
+    }
+}
+
+impl FromRef<AppState> for StartupProbe {
+    fn from_ref(state: &AppState) -> Self {
+        state.startup.clone()
+    }
+}
+
+impl FromRef<AppState> for IndexingClient {
+    fn from_ref(state: &AppState) -> Self {
+        state.indexing.clone()
+    }
+}
+
+impl FromRef<AppState> for Datalake {
+    fn from_ref(state: &AppState) -> Self {
+        state.datalake.clone()
+    }
+}
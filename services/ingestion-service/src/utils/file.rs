@@ -1,19 +1,49 @@
 use chrono::{Timelike, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub const DATALAKE_PATH: &str = "/app/datalake";
 
-pub fn header_body_split(text: &str) -> (String, String) {
-    let start_marker = "*** START OF THE PROJECT GUTENBERG EBOOK";
-    let end_marker = "*** END OF THE PROJECT GUTENBERG EBOOK";
+/// The root of the datalake the service stores books in: [`DATALAKE_PATH`]
+/// unless a test points it at a directory of its own.
+#[derive(Debug, Clone)]
+pub struct Datalake(Arc<PathBuf>);
+
+impl Datalake {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self(Arc::new(root.into()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
 
-    if let Some(start_pos) = text.find(start_marker) {
+impl Default for Datalake {
+    fn default() -> Self {
+        Self::new(DATALAKE_PATH)
+    }
+}
+
+pub const START_MARKER: &str = "*** START OF THE PROJECT GUTENBERG EBOOK";
+pub const END_MARKER: &str = "*** END OF THE PROJECT GUTENBERG EBOOK";
+
+/// Splits a Gutenberg text into the header before its START marker line and
+/// the body between that line and the END marker. Text without both markers,
+/// in that order, is all header.
+pub fn header_body_split(text: &str) -> (String, String) {
+    if let Some(start_pos) = text.find(START_MARKER) {
         let header = text[..start_pos].to_string();
+        let body_start = text[start_pos..]
+            .find('\n')
+            .map(|pos| start_pos + pos + 1)
+            .unwrap_or(start_pos);
 
-        if let Some(end_pos) = text.find(end_marker) {
-            let body_start = text[start_pos..]
-                .find('\n')
-                .map(|pos| start_pos + pos + 1)
-                .unwrap_or(start_pos);
+        // An END marker earlier than the body (on the START line, or before
+        // it) doesn't close it; slicing up to it would panic.
+        if let Some(end_pos) = text[body_start..].find(END_MARKER).map(|pos| body_start + pos) {
             let body = text[body_start..end_pos].to_string();
             return (header, body);
         }
@@ -22,9 +52,175 @@ pub fn header_body_split(text: &str) -> (String, String) {
     (text.to_string(), String::new())
 }
 
-pub fn create_datalake_path() -> String {
+/// The current hour's directory under `datalake_path`, `{date}/{hour}`.
+pub fn create_datalake_path(datalake_path: &Path) -> String {
     let now = Utc::now();
     let date_str = now.format("%Y%m%d").to_string();
     let subdir = format!("{:02}", now.hour());
-    format!("{}/{}/{}", DATALAKE_PATH, date_str, subdir)
+    format!("{}/{}/{}", datalake_path.display(), date_str, subdir)
+}
+
+/// The header and body files of one book in the datalake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookFiles {
+    pub header: PathBuf,
+    pub body: PathBuf,
+}
+
+/// Finds every book under `root`'s `{date}/{hour}` directories that has a
+/// header file. A book stored more than once resolves to its latest copy.
+pub fn scan_datalake(root: &Path) -> BTreeMap<u32, BookFiles> {
+    let mut books = BTreeMap::new();
+    for hour_dir in sorted_subdirs(root).iter().flat_map(|date_dir| sorted_subdirs(date_dir)) {
+        let Ok(entries) = fs::read_dir(&hour_dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(book_id) = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix("header_"))
+                .and_then(|name| name.strip_suffix(".txt"))
+                .and_then(|id| id.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            books.insert(
+                book_id,
+                BookFiles {
+                    header: entry.path(),
+                    body: hour_dir.join(format!("body_{}.txt", book_id)),
+                },
+            );
+        }
+    }
+    books
+}
+
+/// The books under `datalake_path` with both a header and a body file, to
+/// seed [`crate::state::DownloadedBooks`] with what earlier runs stored.
+pub fn initialize_book_registry(datalake_path: &Path) -> HashSet<u32> {
+    scan_datalake(datalake_path)
+        .into_iter()
+        .filter(|(_, files)| files.body.is_file())
+        .map(|(book_id, _)| book_id)
+        .collect()
+}
+
+/// Writes a book's header and body into `dir`, each to a hidden temporary
+/// file first and then renamed over any copy already there, so readers see
+/// the old files or the new ones but never a half-written file.
+pub fn write_book_files(dir: &Path, book_id: u32, header: &str, body: &str) -> std::io::Result<()> {
+    let files = [("header", header), ("body", body)].map(|(kind, contents)| {
+        let path = dir.join(format!("{}_{}.txt", kind, book_id));
+        let temp = dir.join(format!(".{}_{}.txt.tmp", kind, book_id));
+        (path, temp, contents)
+    });
+
+    let written = files.iter().try_for_each(|(_, temp, contents)| fs::write(temp, contents));
+    // The body goes first: a book is listed by its header, so it is never
+    // listed with a missing body.
+    let renamed = written.and_then(|_| {
+        files
+            .iter()
+            .rev()
+            .try_for_each(|(path, temp, _)| fs::rename(temp, path))
+    });
+    if renamed.is_err() {
+        for (_, temp, _) in &files {
+            let _ = fs::remove_file(temp);
+        }
+    }
+    renamed
+}
+
+/// Removes the header and body of `book_id` from every `{date}/{hour}`
+/// directory under `root` except `keep`, so a book stored again leaves only
+/// its latest copy.
+pub fn remove_older_copies(root: &Path, book_id: u32, keep: &Path) {
+    for hour_dir in sorted_subdirs(root).iter().flat_map(|date_dir| sorted_subdirs(date_dir)) {
+        if hour_dir == keep {
+            continue;
+        }
+        for kind in ["header", "body"] {
+            let path = hour_dir.join(format!("{}_{}.txt", kind, book_id));
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Could not remove old copy {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Subdirectories of `dir` in name order, which is chronological for the
+/// datalake's `YYYYMMDD` and `HH` directories.
+fn sorted_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_ignores_end_markers_before_the_body() {
+        let text = format!("Title: Odd\n{START_MARKER} ODD {END_MARKER} ODD ***\nBody text\n{END_MARKER} ODD ***\n");
+        assert_eq!(
+            header_body_split(&text),
+            ("Title: Odd\n".to_string(), "Body text\n".to_string())
+        );
+
+        let unclosed = format!("{END_MARKER}\nTitle: Odd\n{START_MARKER}\nBody text");
+        assert_eq!(header_body_split(&unclosed), (unclosed.clone(), String::new()));
+    }
+
+    #[test]
+    fn registry_holds_books_with_a_header_and_a_body() {
+        let datalake = tempfile::tempdir().unwrap();
+        let earlier = datalake.path().join("20250101/09");
+        let later = datalake.path().join("20250102/17");
+        fs::create_dir_all(&earlier).unwrap();
+        fs::create_dir_all(&later).unwrap();
+        fs::write(earlier.join("header_84.txt"), "Title: Frankenstein\n").unwrap();
+        fs::write(earlier.join("body_84.txt"), "You will rejoice to hear").unwrap();
+        fs::write(later.join("header_1342.txt"), "Title: Pride and Prejudice\n").unwrap();
+        fs::write(later.join("body_1342.txt"), "It is a truth universally acknowledged").unwrap();
+        fs::write(later.join("header_11.txt"), "Title: Alice in Wonderland\n").unwrap();
+        fs::write(later.join("notes.txt"), "not a book").unwrap();
+
+        assert_eq!(initialize_book_registry(datalake.path()), HashSet::from([84, 1342]));
+        assert!(initialize_book_registry(&datalake.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn storing_again_replaces_the_files_and_drops_older_copies() {
+        let datalake = tempfile::tempdir().unwrap();
+        let earlier = datalake.path().join("20250101/09");
+        let later = datalake.path().join("20250102/17");
+        fs::create_dir_all(&earlier).unwrap();
+        fs::create_dir_all(&later).unwrap();
+        write_book_files(&earlier, 84, "Title: Frankenstein\n", "old body").unwrap();
+        write_book_files(&earlier, 11, "Title: Alice in Wonderland\n", "other book").unwrap();
+        write_book_files(&later, 84, "Title: Frankenstein\n", "old body").unwrap();
+
+        write_book_files(&later, 84, "Title: Frankenstein\n", "new body").unwrap();
+        remove_older_copies(datalake.path(), 84, &later);
+
+        assert_eq!(fs::read_to_string(later.join("body_84.txt")).unwrap(), "new body");
+        assert!(!earlier.join("header_84.txt").exists());
+        assert!(!earlier.join("body_84.txt").exists());
+        assert!(earlier.join("body_11.txt").exists());
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(&later).unwrap().count(), 2);
+        assert_eq!(scan_datalake(datalake.path())[&84].body, later.join("body_84.txt"));
+    }
 }
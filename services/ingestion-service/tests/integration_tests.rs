@@ -8,6 +8,8 @@
 //! - `POST /ingest/:book_id` → Book ingestion workflow
 //! - `GET /ingest/status/:book_id` → Book status lookup
 //! - `GET /ingest/list` → Listing of downloaded books
+//! - `GET /ingest/stats` → Download statistics
+//! - `GET /ingest/export` → NDJSON export of the datalake
 
 use serde_json::Value;
 use tokio::time::{sleep, Duration};
@@ -31,7 +33,7 @@ async fn test_ingest_book_valid_id() {
     let book_id = "1342"; // Pride and Prejudice
 
     let response = client
-        .post(&format!("http://0.0.0.0:7001/ingest/{}", book_id))
+        .post(format!("http://0.0.0.0:7001/ingest/{}", book_id))
         .send()
         .await
         .expect("Failed to make request");
@@ -50,7 +52,7 @@ async fn test_ingest_book_invalid_id() {
     let book_id = "999999"; // Non-existent book
 
     let response = client
-        .post(&format!("http://0.0.0.0:7001/ingest/{}", book_id))
+        .post(format!("http://0.0.0.0:7001/ingest/{}", book_id))
         .send()
         .await
         .expect("Failed to make request");
@@ -65,7 +67,7 @@ async fn test_ingest_status_existing_book() {
 
     // First ingest the book
     let _ingest_response = client
-        .post(&format!("http://0.0.0.0:7001/ingest/{}", book_id))
+        .post(format!("http://0.0.0.0:7001/ingest/{}", book_id))
         .send()
         .await
         .expect("Failed to ingest book");
@@ -75,7 +77,7 @@ async fn test_ingest_status_existing_book() {
 
     // Check status
     let response = client
-        .get(&format!("http://0.0.0.0:7001/ingest/status/{}", book_id))
+        .get(format!("http://0.0.0.0:7001/ingest/status/{}", book_id))
         .send()
         .await
         .expect("Failed to check status");
@@ -117,6 +119,38 @@ async fn test_list_books() {
     assert!(body["count"].is_number());
 }
 
+#[tokio::test]
+async fn test_list_books_sorted_descending() {
+    let client = reqwest::Client::new();
+    let book_ids = [11u64, 84, 1342];
+    for book_id in book_ids {
+        let response = client
+            .post(format!("http://0.0.0.0:7001/ingest/{}", book_id))
+            .send()
+            .await
+            .expect("Failed to make request");
+        assert_eq!(response.status(), 200, "Book {} failed to ingest", book_id);
+    }
+
+    let response = reqwest::get("http://0.0.0.0:7001/ingest/list?sort=desc")
+        .await
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let listed: Vec<u64> = body["books"]
+        .as_array()
+        .expect("books is an array")
+        .iter()
+        .filter_map(Value::as_u64)
+        .collect();
+    assert!(listed.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", listed);
+    let ingested: Vec<u64> = listed.into_iter().filter(|id| book_ids.contains(id)).collect();
+    assert_eq!(ingested, [1342, 84, 11]);
+    assert_eq!(body["applied_filters"], serde_json::json!(["sort=desc"]));
+    assert_eq!(body["filtered_count"], body["count"]);
+}
+
 #[tokio::test]
 async fn test_concurrent_ingestion() {
     let client = reqwest::Client::new();
@@ -129,7 +163,7 @@ async fn test_concurrent_ingestion() {
 
         let handle = tokio::spawn(async move {
             let response = client_clone
-                .post(&format!("http://localhost:7001/ingest/{}", book_id_clone))
+                .post(format!("http://localhost:7001/ingest/{}", book_id_clone))
                 .send()
                 .await
                 .expect("Failed to make request");
@@ -144,4 +178,53 @@ async fn test_concurrent_ingestion() {
         let (book_id, status) = handle.await.expect("Task failed");
         assert_eq!(status, 200, "Book {} failed to ingest", book_id);
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_download_stats() {
+    let response = reqwest::get("http://0.0.0.0:7001/ingest/stats")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert!(body["total_downloads"].is_u64());
+    assert!(body["total_bytes"].is_u64());
+    assert!(body["failed_downloads"].is_u64());
+    assert!(body["avg_speed_bps"].is_number());
+}
+
+#[tokio::test]
+async fn test_export_ndjson() {
+    let client = reqwest::Client::new();
+
+    for book_id in [84, 1342] {
+        let response = client
+            .post(format!("http://0.0.0.0:7001/ingest/{}", book_id))
+            .send()
+            .await
+            .expect("Failed to make request");
+        assert_eq!(response.status(), 200);
+    }
+
+    let response = reqwest::get("http://0.0.0.0:7001/ingest/export?format=ndjson&book_ids=84,1342")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"datalake-export.ndjson\""
+    );
+
+    let export = response.text().await.expect("Failed to read export");
+    let lines: Vec<Value> = export
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Each line should be a JSON object"))
+        .collect();
+    let book_ids: Vec<u64> = lines.iter().filter_map(|line| line["book_id"].as_u64()).collect();
+    assert_eq!(book_ids, [84, 1342]);
+    assert!(lines.iter().all(|line| line["header"].is_string() && line["body"].is_string()));
+}
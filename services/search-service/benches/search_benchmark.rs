@@ -1,73 +1,94 @@
 //! Search Service Benchmarks
 //!
 //! Measures the performance of core search operations: query tokenization,
-//! book matching, and filtered search across the dataset.
+//! metadata filtering, the browse aggregations, related-book ranking,
+//! wildcard expansion and whole searches with and without the in-memory
+//! index snapshot, all against the service's own code from the
+//! `search_service` library.
 //!
 //! These benchmarks help identify performance bottlenecks in the search algorithm
 //! and provide data for the Stage 2 performance analysis report.
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use std::collections::HashMap;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use axum::body::Body;
+use axum::http::Request;
+use search_service::models::storage::snapshot::SnapshotConfig;
+use search_service::models::storage::{
+    aggregate_authors, decade_buckets, BookMetadata, IndexField, MemoryBackend, StorageBackend,
+};
+use search_service::routes::search::{apply_filters, tokenize_query, SearchParams};
+use search_service::services::query::{parse_query, parse_regex_query};
+use search_service::services::related::rank_related;
+use search_service::services::result_cache::ResultCache;
+use search_service::services::search::{MatchMode, SortOrder};
+use search_service::services::wildcard::expand_patterns;
+use search_service::state::AppState;
+use search_service::Backend;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
 
-
-/// Represents a book in the search results.
-#[derive(Debug, Clone)]
-struct BookResult {
-    book_id: u32,
-    title: String,
-    author: String,
-    language: String,
-    year: Option<u32>,
-}
-
-/// Tokenizes a search query into individual words for matching.
-fn tokenize_query(query: &str) -> Vec<String> {
-    query.to_lowercase()
-         .split_whitespace()
-         .filter(|word| word.len() > 2)
-         .map(|word| word.to_string())
-         .collect()
+/// Builds a `SearchParams` for `q` with no filters set.
+fn params(q: &str) -> SearchParams {
+    SearchParams {
+        q: q.to_string(),
+        author: None,
+        language: None,
+        year: None,
+        year_from: None,
+        year_to: None,
+        year_min: None,
+        year_max: None,
+        min_word_count: None,
+        max_word_count: None,
+        max_reading_level: None,
+        subject: None,
+        highlight_body: false,
+        snippets: false,
+        mode: MatchMode::default(),
+        sort: SortOrder::default(),
+        facets: Vec::new(),
+        limit: None,
+        offset: None,
+        suggest: true,
+        synonyms: true,
+        dedupe: false,
+        regex: false,
+        timeout_ms: None,
+        export: false,
+    }
 }
 
-/// Checks if a book matches any of the query terms.
-fn matches_query(book: &BookResult, query_words: &[String]) -> bool {
-    let book_text = format!("{} {}", book.title.to_lowercase(), book.author.to_lowercase());
-
-    query_words.iter().any(|word| {
-        book_text.contains(word)
-    })
+fn book(book_id: u32, title: &str, author: &str, year: Option<u32>) -> BookMetadata {
+    BookMetadata {
+        book_id,
+        title: title.to_string(),
+        author: author.to_string(),
+        language: if book_id.is_multiple_of(5) { "fr" } else { "en" }.to_string(),
+        year,
+        word_count: 50_000,
+        unique_words: 5_000,
+        chapter_count: 20,
+        subjects: vec![format!("Subject {}", book_id % 10)],
+        reading_level: Some(f64::from(book_id % 12)),
+    }
 }
 
 /// Creates a sample dataset for benchmarking.
-fn create_sample_books() -> HashMap<u32, BookResult> {
-    let mut books = HashMap::new();
-
-    // Books for validation
-    books.insert(1342, BookResult {
-        book_id: 1342,
-        title: "Pride and Prejudice".to_string(),
-        author: "Jane Austen".to_string(),
-        language: "en".to_string(),
-        year: Some(1813),
-    });
+fn create_sample_books() -> Vec<BookMetadata> {
+    let mut books = vec![
+        book(1342, "Pride and Prejudice", "Jane Austen", Some(1813)),
+        book(84, "Frankenstein", "Mary Wollstonecraft Shelley", Some(1818)),
+    ];
 
-    books.insert(84, BookResult {
-        book_id: 84,
-        title: "Frankenstein".to_string(),
-        author: "Mary Wollstonecraft Shelley".to_string(),
-        language: "en".to_string(),
-        year: Some(1818),
-    });
-
-    // Add more books for benchmarking
     for i in 1000..2000 {
-        books.insert(i, BookResult {
-            book_id: i,
-            title: format!("Test Book {}", i),
-            author: format!("Test Author {}", i % 50),
-            language: "en".to_string(),
-            year: Some(1800 + (i % 200)),
-        });
+        books.push(book(
+            i,
+            &format!("Test Book {}", i),
+            &format!("Test Author {}", i % 50),
+            Some(1800 + (i % 200)),
+        ));
     }
 
     books
@@ -75,77 +96,161 @@ fn create_sample_books() -> HashMap<u32, BookResult> {
 
 /// Benchmarks query tokenization performance.
 fn benchmark_tokenize_query(c: &mut Criterion) {
-    let query = "pride prejudice jane austen";
+    let query = "pride and prejudice by jane austen";
 
     c.bench_function("tokenize_query", |b| {
         b.iter(|| tokenize_query(black_box(query)))
     });
 }
 
-/// Benchmarks single book matching against query terms.
-fn benchmark_matches_query(c: &mut Criterion) {
-    let book = BookResult {
-        book_id: 1342,
-        title: "Pride and Prejudice".to_string(),
-        author: "Jane Austen".to_string(),
-        language: "en".to_string(),
-        year: Some(1813),
-    };
-    let query_words = vec!["pride".to_string(), "prejudice".to_string()];
+/// Benchmarks filtering 1000+ candidate books with no filters set.
+fn benchmark_filter_unfiltered(c: &mut Criterion) {
+    let books = create_sample_books();
+    let params = params("test book");
 
-    c.bench_function("matches_query", |b| {
-        b.iter(|| matches_query(black_box(&book), black_box(&query_words)))
+    c.bench_function("apply_filters_none", |b| {
+        b.iter(|| apply_filters(black_box(books.clone()), black_box(&params)))
     });
 }
 
-/// Benchmarks full search across 1000+ books without filters.
+/// Benchmarks filtering by author, language and subject together.
 ///
-/// Performance determines maximum throughput for unfiltered queries.
-fn benchmark_search_small_dataset(c: &mut Criterion) {
+/// Measures the additional cost of applying filters after the index lookup.
+fn benchmark_filter_with_filters(c: &mut Criterion) {
     let books = create_sample_books();
-    let query_words = vec!["test".to_string(), "book".to_string()];
+    let params = SearchParams {
+        author: Some("Test Author 25".to_string()),
+        language: Some("en".to_string()),
+        subject: Some("subject 5".to_string()),
+        ..params("test")
+    };
+
+    c.bench_function("apply_filters_all", |b| {
+        b.iter(|| apply_filters(black_box(books.clone()), black_box(&params)))
+    });
+}
 
-    c.bench_function("search_small_dataset", |b| {
+/// Benchmarks the `/search/authors` and `/search/years` aggregations.
+fn benchmark_browse_aggregations(c: &mut Criterion) {
+    let books = create_sample_books();
+
+    c.bench_function("aggregate_authors", |b| {
         b.iter(|| {
-            let mut results = Vec::new();
-            for book in books.values() {
-                if matches_query(black_box(book), black_box(&query_words)) {
-                    results.push(book.clone());
-                }
-            }
-            results
+            let pairs = books.iter().map(|book| (book.author.clone(), book.book_id));
+            aggregate_authors(black_box(pairs), Some("test"), 1, 50)
         })
     });
+
+    c.bench_function("decade_buckets", |b| {
+        b.iter(|| decade_buckets(black_box(books.iter().map(|book| book.year))))
+    });
 }
 
-/// Benchmarks search with metadata filtering (by author).
+/// Benchmarks ranking one book against a 100-book corpus.
 ///
-/// Measures the additional cost of applying filters after matching,
-fn benchmark_search_with_filters(c: &mut Criterion) {
-    let books = create_sample_books();
-    let query_words = vec!["test".to_string()];
-    let author_filter = "Test Author 25".to_string();
+/// Each book has 5,000 words: 2,000 from a vocabulary shared by every book,
+/// the rest drawn from a window that overlaps its neighbours'.
+fn benchmark_related_books(c: &mut Criterion) {
+    let word_set = |book_id: u32| -> HashSet<String> {
+        let shared = (0..2_000).map(|i| format!("common{}", i));
+        let own = (0..3_000).map(move |i| format!("word{}", book_id * 500 + i));
+        shared.chain(own).collect()
+    };
+    let target = word_set(0);
+    let corpus: Vec<(u32, HashSet<String>)> = (1..=100).map(|book_id| (book_id, word_set(book_id))).collect();
 
-    c.bench_function("search_with_filters", |b| {
-        b.iter(|| {
-            let mut results = Vec::new();
-            for book in books.values() {
-                if matches_query(black_box(book), black_box(&query_words)) {
-                    if book.author.to_lowercase().contains(&author_filter.to_lowercase()) {
-                        results.push(book.clone());
-                    }
-                }
+    c.bench_function("rank_related_100_books", |b| {
+        b.iter_batched(
+            || corpus.clone(),
+            |corpus| rank_related(black_box(&target), corpus, 5),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+/// Benchmarks expanding a wildcard and a regex matching the same ten words
+/// against a 100,000-word vocabulary. The prefix is read off the ordered
+/// vocabulary, so it stays far cheaper than the regex, which tests every
+/// word.
+fn benchmark_wildcard_expansion(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let backend: Backend = runtime.block_on(async {
+        let memory = MemoryBackend::new();
+        for i in 0..100_000u32 {
+            memory.add_word_to_index(&format!("word{:05}", i), i % 100, IndexField::Body).await.unwrap();
+        }
+        Arc::new(memory)
+    });
+    let prefix = parse_query("word0001*", MatchMode::Any).unwrap().unwrap();
+    let regex = parse_regex_query("word0001.", MatchMode::Any).unwrap().unwrap();
+
+    c.bench_function("expand_prefix_100k_words", |b| {
+        b.iter(|| runtime.block_on(expand_patterns(black_box(prefix.clone()), &backend)).unwrap())
+    });
+    c.bench_function("expand_regex_100k_words", |b| {
+        b.iter(|| runtime.block_on(expand_patterns(black_box(regex.clone()), &backend)).unwrap())
+    });
+}
+
+/// Benchmarks a two-word search over 500 books and a 2,000-word
+/// vocabulary, straight from the backend and from a snapshot of it. Each
+/// posting lookup on the backend is slowed to a round trip, which tokio's
+/// timer rounds up to about a millisecond; the snapshot never pays it.
+/// Results aren't cached, so every iteration runs the search: about 10.8 ms
+/// from the backend against 1.5 ms from the snapshot.
+fn benchmark_snapshot_search(c: &mut Criterion) {
+    // Index words are letters only: `i` in base 26
+    let word = |i: u32| -> String {
+        let letter = |digit: u32| char::from(b'a' + (digit % 26) as u8);
+        format!("w{}{}{}", letter(i / 676), letter(i / 26), letter(i))
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let memory = runtime.block_on(async {
+        let memory = MemoryBackend::new();
+        for book_id in 0..500u32 {
+            memory
+                .store_book_metadata(&book(book_id, &format!("Book {}", book_id), "Anonymous", Some(1850)))
+                .await
+                .unwrap();
+            for i in 0..40u32 {
+                memory.add_word_to_index(&word((book_id * 7 + i * 13) % 2_000), book_id, IndexField::Body).await.unwrap();
             }
-            results
-        })
+        }
+        for i in 0..2_000u32 {
+            memory.slow_down_word(&word(i), Duration::from_micros(200));
+        }
+        memory
     });
+    let app = |state: AppState| {
+        let state = AppState {
+            result_cache: ResultCache::new(1, Duration::from_nanos(1)),
+            ..state
+        };
+        search_service::app_with_state(state)
+    };
+    let backend_app = app(AppState::new(Arc::new(memory.clone())));
+    let snapshot_state = AppState::new(Arc::new(memory)).with_snapshot(SnapshotConfig::default());
+    runtime.block_on(snapshot_state.snapshot.as_ref().unwrap().refresh()).unwrap();
+    let snapshot_app = app(snapshot_state);
+
+    let uri = format!("/search?q={}+{}&mode=any", word(13), word(26));
+    let search = |router: &axum::Router| {
+        let response = runtime.block_on(router.clone().oneshot(Request::get(&uri).body(Body::empty()).unwrap())).unwrap();
+        assert!(response.status().is_success());
+        response
+    };
+    c.bench_function("search_backend_round_trips", |b| b.iter(|| search(black_box(&backend_app))));
+    c.bench_function("search_snapshot", |b| b.iter(|| search(black_box(&snapshot_app))));
 }
 
 criterion_group!(
     benches,
     benchmark_tokenize_query,
-    benchmark_matches_query,
-    benchmark_search_small_dataset,
-    benchmark_search_with_filters
+    benchmark_filter_unfiltered,
+    benchmark_filter_with_filters,
+    benchmark_browse_aggregations,
+    benchmark_related_books,
+    benchmark_wildcard_expansion,
+    benchmark_snapshot_search
 );
-criterion_main!(benches);
\ No newline at end of file
+criterion_main!(benches);
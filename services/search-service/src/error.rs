@@ -0,0 +1,111 @@
+//! Error Responses
+//!
+//! Failed requests answer with an [`ErrorResponse`]: a machine-readable
+//! `error_code` clients can branch on, a human-readable message and, where
+//! one applies, the book concerned. Handlers return [`AppError`], each
+//! variant of which maps to one status code and `error_code`.
+//!
+//! The response shape and the middleware adding request IDs to it are shared
+//! by every service in `common-http`.
+
+use crate::models::storage::StorageError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use common_http::ErrorResponse;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Book {0} is not in the index")]
+    BookNotFound(u32),
+    #[error("{0}")]
+    InvalidQuery(String),
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("Storage backend unavailable: {0}")]
+    BackendUnavailable(StorageError),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BookNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidQuery(_) | AppError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::BackendUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::BookNotFound(_) => "BOOK_NOT_FOUND",
+            AppError::InvalidQuery(_) => "INVALID_QUERY",
+            AppError::InvalidRequest(_) => "INVALID_REQUEST",
+            AppError::BackendUnavailable(_) => "BACKEND_UNAVAILABLE",
+            AppError::Timeout => "GATEWAY_TIMEOUT",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    fn book_id(&self) -> Option<u32> {
+        match self {
+            AppError::BookNotFound(book_id) => Some(*book_id),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        ErrorResponse {
+            book_id: self.book_id(),
+            ..ErrorResponse::new(self.error_code(), self.to_string())
+        }
+        .with_status(self.status())
+    }
+}
+
+/// Only failures to reach the backend are `503 BACKEND_UNAVAILABLE`, as
+/// retrying may succeed once it is back. Errors it answers with, such as a
+/// failed query, and stored data that doesn't deserialize are faults on
+/// our side and answer `500 INTERNAL_ERROR`.
+impl From<StorageError> for AppError {
+    fn from(error: StorageError) -> Self {
+        let unavailable = match &error {
+            StorageError::Redis(e) => {
+                e.is_io_error()
+                    || e.is_connection_refusal()
+                    || e.is_connection_dropped()
+                    || e.is_timeout()
+                    || e.is_cluster_error()
+                    || e.kind() == redis::ErrorKind::BusyLoadingError
+            }
+            StorageError::Postgres(e) => match e {
+                sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::WorkerCrashed => true,
+                // Connection exceptions (08) and the server shutting down
+                // or starting up (57P)
+                sqlx::Error::Database(db) => db
+                    .code()
+                    .is_some_and(|code| code.starts_with("08") || code.starts_with("57P")),
+                _ => false,
+            },
+            StorageError::Serialization(_) => false,
+            StorageError::Connection(_) => true,
+        };
+        if unavailable {
+            AppError::BackendUnavailable(error)
+        } else {
+            AppError::Internal(format!("Storage backend error: {}", error))
+        }
+    }
+}
@@ -6,39 +6,52 @@
 //! ## Responsibilities
 //! - Bootstraps the Axum web server
 //! - Connects to the configured storage backend (Redis or PostgreSQL)
-//! - Registers core routes: `/status` and `/search`
+//! - Registers core routes: `/status`, `/ready`, `/startup`, `/search` and the `/search/authors`,
+//!   `/search/languages` and `/search/years` browse endpoints
+//! - Tests the backend connection every 30 seconds, reconnecting after three
+//!   failures in a row (see `services::backend_monitor`)
+//! - With `SEARCH_SNAPSHOT=on`, loads the index into memory and keeps it
+//!   refreshed (see `models::storage::snapshot`)
+//! - Serves the gRPC API (`proto/search.proto`) on its own port, stopping both
+//!   servers together on SIGTERM / ctrl-c
+//!
+//! An unreachable Redis backend doesn't stop the service from starting: it
+//! serves with `/status` degraded and `/startup` failing until Redis answers,
+//! so orchestrators should restart it on the startup probe rather than on
+//! exit. PostgreSQL is connected to up front and still exits on failure.
 //!
 //! ## Environment Variables
-//! - `BACKEND_TYPE` → `"redis"` (default) or `"postgres"`
-//! - `REDIS_URL` → Redis connection string
+//! - `BACKEND_TYPE` → `"redis"` (default) or `"postgres"`; must match the indexing service's
+//! - `REDIS_URL` → Redis URL, `rediss://` for TLS, or a comma-separated list of cluster nodes
+//! - `REDIS_MODE`, `REDIS_USERNAME`, `REDIS_PASSWORD`, `REDIS_TLS_INSECURE` → see the `common-redis` crate
+//! - `SHARD_URLS` → Comma-separated Redis URLs the index is sharded over, in the indexing service's order; see `models::storage::sharded`
 //! - `DATABASE_URL` → PostgreSQL connection string
 //! - `PORT` → Service port (default: `7003`)
+//! - `GRPC_PORT` → Port of the gRPC API, served alongside (default: `7013`); see `services::grpc`
+//! - `METADATA_CACHE_TTL_SECS`, `METADATA_CACHE_MAX_ENTRIES` → see `services::metadata_cache`
+//! - `SEARCH_CACHE_TTL_SECS`, `SEARCH_CACHE_MAX_ENTRIES` → see `services::result_cache`
+//! - `SEARCH_TIMEOUT_SECS`, `SEARCH_TIMEOUT_MS` → see `services::timeout`
+//! - `SEARCH_SNAPSHOT`, `SNAPSHOT_REFRESH_SECS`, `SNAPSHOT_MAX_WORDS` → see `models::storage::snapshot`
+//! - `FOLD_DIACRITICS` → fold accented letters in queries (`café` → `cafe`); must match the indexing service's, which is checked at startup
+//! - `RUST_LOG`, `LOG_LEVEL_SERVICE`, `LOG_LEVEL_TOWER`, `LOG_LEVEL_REQWEST`, `LOG_FORMAT` → see the `common-telemetry` crate
 
-use axum::{
-    routing::get,
-    Router,
-};
+use common_redis::RedisConfig;
+use common_telemetry::logging::{init_tracing, LogOutput};
+use search_service::models::storage::snapshot::SnapshotConfig;
+use search_service::models::storage::{PostgresBackend, RedisBackend, ShardedBackend};
+use search_service::services::backend_monitor::CHECK_INTERVAL;
+use search_service::services::grpc;
+use search_service::services::shutdown::{listen_for_signals, Shutdown};
+use search_service::services::startup::check_diacritic_folding;
+use search_service::state::AppState;
+use search_service::utils::text::TokenizerConfig;
+use search_service::{app_with_state, Backend};
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
-use tower_http::trace::TraceLayer;
-use tracing::{error, info};
-
-mod models;
-mod routes;
-
-use models::storage::{PostgresBackend, RedisBackend, StorageBackend};
-use routes::{
-    health::health_check,
-    search::search_books,
-};
-
-type Backend = Arc<dyn StorageBackend + Send + Sync>;
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter("search_service=info,tower_http=info")
-        .init();
+    init_tracing("search-service", LogOutput::Stdout);
 
     let backend_type = std::env::var("BACKEND_TYPE").unwrap_or_else(|_| "redis".to_string());
     let backend: Backend = match backend_type.to_lowercase().as_str() {
@@ -52,30 +65,79 @@ async fn main() {
 
             Arc::new(postgres_backend)
         }
-        "redis" | _ => {
-            let redis_url = std::env::var("REDIS_URL")
-                .unwrap_or_else(|_| "redis://redis:6379".to_string());
+        "memory" => {
+            error!("BACKEND_TYPE=memory only exists inside the indexing service; use redis or postgres");
+            std::process::exit(1);
+        }
+        "redis" => match ShardedBackend::from_env() {
+            Some(sharded) => {
+                let sharded = sharded.unwrap_or_else(|e| {
+                    error!("Invalid shard configuration: {}", e);
+                    std::process::exit(1);
+                });
+
+                info!("Using Redis backend sharded over {} instance(s)", sharded.shard_count());
+                Arc::new(sharded)
+            }
+            None => {
+                let config = RedisConfig::from_env();
 
-            info!("Using Redis backend");
-            let redis_backend = RedisBackend::new(&redis_url)
-                .expect("Failed to connect to Redis");
+                info!("Using Redis backend ({:?}, {} node(s))", config.mode, config.nodes.len());
+                let redis_backend = RedisBackend::from_config(&config).unwrap_or_else(|e| {
+                    error!("Invalid Redis configuration: {}", e);
+                    std::process::exit(1);
+                });
 
-            Arc::new(redis_backend)
+                Arc::new(redis_backend)
+            }
+        },
+        other => {
+            error!("Unknown BACKEND_TYPE '{}'; expected redis or postgres", other);
+            std::process::exit(1);
         }
     };
 
-    if let Err(e) = backend.test_connection().await {
-        error!("Failed to connect to storage backend: {}", e);
-        std::process::exit(1);
+    // Keep serving even if the backend is down: `/status` reports it, and
+    // `/startup` passes once it answers.
+    let mut state = AppState::new(backend);
+    if let Some(config) = SnapshotConfig::from_env() {
+        info!("Serving queries from an in-memory index snapshot, refreshed every {:?}", config.refresh_interval);
+        state = state.with_snapshot(config);
     }
-    info!("Storage backend connection successful");
+    match state.backend.test_connection().await {
+        Ok(()) => {
+            info!("Storage backend connection successful");
+            state.startup.complete();
+            check_diacritic_folding(&state.backend, TokenizerConfig::configured().fold_diacritics).await;
+        }
+        Err(e) => warn!("Storage backend unavailable at startup: {}", e),
+    }
+    state.backend_health.spawn(state.backend.clone(), CHECK_INTERVAL);
+    if let Some(snapshot) = &state.snapshot {
+        let result_cache = state.result_cache.clone();
+        snapshot.spawn_refresh(move || result_cache.invalidate_all());
+    }
+
+    let shutdown = Shutdown::new();
+    tokio::spawn(listen_for_signals(shutdown.clone()));
 
-    let app = Router::new()
-        .route("/status", get(health_check))
-        .route("/search", get(search_books))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .with_state(backend);
+    // Both servers share the state and stop on the same signal; if either
+    // fails, the other is stopped too
+    let grpc_addr = format!("0.0.0.0:{}", grpc::grpc_port());
+    let grpc_listener = tokio::net::TcpListener::bind(&grpc_addr).await.unwrap();
+    info!("Search gRPC server starting on {}", grpc_addr);
+    let grpc_server = tokio::spawn({
+        let (state, shutdown) = (state.clone(), shutdown.clone());
+        async move {
+            let result = grpc::serve(grpc_listener, state, shutdown.clone()).await;
+            if let Err(e) = &result {
+                error!("gRPC server failed: {}", e);
+            }
+            shutdown.trigger();
+        }
+    });
+
+    let app = app_with_state(state);
 
     let port = std::env::var("PORT").unwrap_or_else(|_| "7003".to_string());
     let addr = format!("0.0.0.0:{}", port);
@@ -83,5 +145,14 @@ async fn main() {
     info!("Search service starting on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move { shutdown.wait().await }
+    });
+    if let Err(e) = server.await {
+        error!("HTTP server failed: {}", e);
+    }
+    shutdown.trigger();
+    let _ = grpc_server.await;
+    info!("Search service stopped");
 }
\ No newline at end of file
@@ -2,35 +2,428 @@
 //!
 //! Defines the JSON response structures returned by the Search Service endpoints.
 
+use crate::models::storage::{AuthorEntry, BookMetadata, DecadeBucket};
+use chrono::{DateTime, Utc};
+pub use common_http::ErrorResponse;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
 
 
 /// Response for the /status health check endpoint.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct HealthResponse {
     pub service: String,
+    /// Always `liveness`.
+    pub probe: String,
+    /// Always true: a process that answers is alive.
+    pub ok: bool,
     pub status: String,
+    /// `connected` or `unavailable`.
+    pub backend: String,
+    pub backend_connected: bool,
+    /// `redis`, `postgres`, `memory` or `sharded`.
+    pub backend_type: String,
+    /// Round trip of the connection test, or the time until it failed.
+    pub backend_latency_ms: f64,
+    /// Background connection checks in a row that failed; the backend is
+    /// reconnected after every third.
+    #[serde(default)]
+    pub backend_consecutive_failures: u32,
+    /// Whether the datalake directory can be read.
+    pub datalake_accessible: bool,
+    pub uptime_secs: u64,
+    /// What `status` was decided from.
+    #[serde(default)]
+    pub checks: HealthChecks,
+    /// Only with `SEARCH_SNAPSHOT=on`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotStatus>,
+}
+
+/// The in-memory index snapshot queries are answered from.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct SnapshotStatus {
+    /// Whether one has loaded; queries go to the backend until then.
+    pub loaded: bool,
+    pub books: usize,
+    pub words: usize,
+    /// Rough size of the snapshot's data.
+    pub memory_bytes: usize,
+    /// The backend's index version the snapshot was read at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loaded_at: Option<DateTime<Utc>>,
+}
+
+/// The checks behind `status`: `running` needs a reachable backend holding
+/// an index, and with `deep=true` a canary query that finds its book.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, ToSchema)]
+pub struct HealthChecks {
+    pub backend_reachable: bool,
+    /// Whether the index holds any book.
+    pub index_present: bool,
+    /// Books in the index; `None` when it couldn't be read.
+    pub indexed_books: Option<usize>,
+    /// Only with `deep=true`, once an index was found.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryCheck>,
+}
+
+/// A search for a title word of an indexed book, run by `/status?deep=true`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct CanaryCheck {
+    /// The word searched; `None` when no book had a searchable title word
+    /// or the backend failed first.
+    pub query: Option<String>,
+    /// `book_id` whose title the word came from.
+    pub book_id: Option<u32>,
+    /// Whether the search found that book.
+    pub ok: bool,
+    pub latency_ms: f64,
+}
+
+/// Response for the /ready and /startup probes.
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ProbeResponse {
+    pub service: String,
+    /// `readiness` or `startup`.
+    pub probe: String,
+    pub ok: bool,
 }
 
 /// Represents a single book in search results.
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// `highlights` maps a field name (`"title"`, `"author"`, `"body"`) to
+/// HTML-escaped snippets of that field with the matched query terms wrapped
+/// in `<mark>`.
+/// `chapters` lists the chapters containing a query term, when the book was
+/// indexed with chapter-level postings. `snippet` is the body text around
+/// the first match, with matched terms in `<em>`, when `snippets=true` was
+/// requested and the body is available.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct BookResult {
     pub book_id: u32,
     pub title: String,
     pub author: String,
     pub language: String,
     pub year: Option<u32>,
+    pub subjects: Vec<String>,
+    /// Flesch-Kincaid grade level of the body, if it was computed.
+    #[serde(default)]
+    pub reading_level: Option<f64>,
+    /// Relevance score, when results are sorted by relevance; `0` under
+    /// other orders.
+    #[serde(default)]
+    pub score: f64,
+    pub highlights: HashMap<String, Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chapters: Option<Vec<usize>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// With `dedupe=true`, the IDs of other editions of this book that
+    /// matched (see [`crate::services::dedupe`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_available_as: Vec<u32>,
+}
+
+/// A book as a result, before scoring, highlights, chapters or snippet.
+impl From<BookMetadata> for BookResult {
+    fn from(book: BookMetadata) -> Self {
+        Self {
+            book_id: book.book_id,
+            title: book.title,
+            author: book.author,
+            language: book.language,
+            year: book.year,
+            subjects: book.subjects,
+            reading_level: book.reading_level,
+            score: 0.0,
+            highlights: HashMap::new(),
+            chapters: None,
+            snippet: None,
+            also_available_as: Vec::new(),
+        }
+    }
+}
+
+
+/// How common one word of a query is in the index.
+///
+/// A word the index never holds (too short, or not purely alphabetic) is
+/// `filtered`, with the `reason`, and isn't looked up.
+/// Synonyms the query was expanded with have their own entry, naming the
+/// query word in `synonym_of`. A wildcard or regex has an entry of its own,
+/// counting the books with any of its `expansions`, and so does each word
+/// it was replaced by, naming it in `expansion_of`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TermStat {
+    /// Books containing the word.
+    pub doc_freq: usize,
+    pub found: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub filtered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synonym_of: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expansion_of: Option<String>,
+    /// Words a pattern was replaced by, in the most books first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expansions: Vec<String>,
+    /// Whether a pattern matched more words than it was replaced by.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
 }
 
 
 /// Response for search queries (GET /search endpoint).
 ///
-/// Returns the search query, applied filters, and matching books.
-#[derive(Debug, Serialize, Deserialize)]
+/// Returns the search query and its parsed form, applied filters, and one
+/// page of matching books. `facets` maps each facet requested with
+/// `?facets=` to its values' book counts over all pages. `term_stats` maps
+/// every word of the query, lowercased, to how many books contain it.
+/// A search that ran out of its time budget is `partial`: it holds the
+/// books found so far, possibly unscored.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SearchResponse {
     pub query: String,
+    /// How the query was understood, e.g. `whale AND NOT pequod`; empty
+    /// when no searchable word was left.
+    pub parsed_query: String,
     pub filters: HashMap<String, String>,
+    /// Results in this page.
     pub count: usize,
+    /// Results across all pages.
+    pub total_count: usize,
+    pub limit: usize,
+    pub offset: usize,
     pub results: Vec<BookResult>,
-}
\ No newline at end of file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facets: Option<HashMap<String, BTreeMap<String, usize>>>,
+    /// Corrected queries that would find books, when this one found none
+    /// and `suggest` wasn't turned off; possibly empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestions: Option<Vec<String>>,
+    #[serde(default)]
+    pub term_stats: BTreeMap<String, TermStat>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// The time budget that ran out, when `partial`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timed_out_after_ms: Option<u64>,
+}
+
+/// The last event of a `GET /search/stream` response, once every match was
+/// sent.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamSummary {
+    /// `result` events sent, the books matching the query and filters.
+    pub total_count: usize,
+    pub took_ms: f64,
+}
+
+
+/// Response for a single book (GET /search/book/:book_id endpoint).
+///
+/// All of the book's stored metadata, plus how many distinct words the index
+/// holds postings for.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BookDetailResponse {
+    #[serde(flatten)]
+    pub book: BookMetadata,
+    pub related_words_count: usize,
+}
+
+
+/// Response for GET /books/:book_id: the book's stored metadata with the
+/// author line split into names. `indexed` is `false` while the indexing
+/// service hasn't finished writing the book's postings, so searches may
+/// miss it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BookInfoResponse {
+    pub book_id: u32,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub language: String,
+    pub year: Option<u32>,
+    pub word_count: usize,
+    pub unique_words: usize,
+    pub chapter_count: usize,
+    pub subjects: Vec<String>,
+    pub indexed: bool,
+}
+
+
+/// A book's stored metadata with when the indexing service last stored it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecentBook {
+    #[serde(flatten)]
+    pub book: BookMetadata,
+    pub indexed_at: DateTime<Utc>,
+}
+
+
+/// Response for GET /books/recent, newest first.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecentBooksResponse {
+    pub count: usize,
+    pub books: Vec<RecentBook>,
+}
+
+
+/// A book sharing indexed words with the requested one.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RelatedBook {
+    pub book_id: u32,
+    /// Jaccard similarity of the two books' word sets, `0.0`–`1.0`.
+    pub similarity: f64,
+    pub title: String,
+}
+
+
+/// A chapter of a book holding query words
+/// (GET /search/book/:book_id/chapters endpoint).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChapterHit {
+    /// `0` for front matter before the first heading.
+    pub chapter_id: usize,
+    /// The heading line, `None` for front matter or a book without headings.
+    pub chapter_title: Option<String>,
+    /// Query words found in the chapter; `None` when the book's body is no
+    /// longer in the datalake.
+    pub occurrence_count: Option<usize>,
+}
+
+
+/// A sentence containing every word of a passage search
+/// (GET /search/passages endpoint).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PassageResult {
+    pub book_id: u32,
+    /// Position of the sentence in the book's body, from 0.
+    pub sentence_id: usize,
+    /// The sentence, with wrapped lines joined; `None` when the book's body
+    /// is no longer in the datalake.
+    pub text: Option<String>,
+}
+
+
+/// Response for related books (GET /search/book/:book_id/related endpoint).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RelatedBooksResponse {
+    pub book_id: u32,
+    pub related: Vec<RelatedBook>,
+}
+
+
+/// A book users marked relevant for a query.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeedbackBook {
+    pub book_id: u32,
+    pub title: String,
+}
+
+
+/// Response for feedback statistics (GET /search/feedback/stats endpoint).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FeedbackStatsResponse {
+    /// The query as normalized for storage.
+    pub query: String,
+    pub positive: usize,
+    pub negative: usize,
+    /// Most often marked relevant first.
+    pub top_relevant_books: Vec<FeedbackBook>,
+}
+
+
+/// Response for the author listing (GET /search/authors endpoint).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuthorsResponse {
+    pub page: usize,
+    pub per_page: usize,
+    pub authors: Vec<AuthorEntry>,
+}
+
+
+/// Number of indexed books in one language.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LanguageEntry {
+    pub code: String,
+    pub name: String,
+    pub book_count: usize,
+}
+
+
+/// Response for the language distribution (GET /search/languages endpoint).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LanguagesResponse {
+    pub languages: Vec<LanguageEntry>,
+}
+
+
+/// Response for the decade distribution (GET /search/years endpoint).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct YearsResponse {
+    pub decades: Vec<DecadeBucket>,
+}
+
+
+/// How often a query was searched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QueryCount {
+    pub query: String,
+    pub count: usize,
+}
+
+
+/// Search latencies in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LatencyPercentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+
+/// Response for GET /search/stats: what was searched in the last
+/// `window_mins` minutes. `zero_result_queries` are the searches that found
+/// nothing, often words the index is missing.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchStatsResponse {
+    pub window_mins: u64,
+    pub logging_enabled: bool,
+    pub total_queries: usize,
+    pub top_queries: Vec<QueryCount>,
+    pub zero_result_queries: Vec<QueryCount>,
+    pub latency_ms: LatencyPercentiles,
+}
+
+
+/// Response for POST /cache/invalidate. `book_id` is set when only the
+/// responses matching that book were dropped.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CacheInvalidateResponse {
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub book_id: Option<u32>,
+}
+
+/// Response for POST /snapshot/refresh.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SnapshotRefreshResponse {
+    /// `refreshed`, or `unchanged` when the index version hadn't moved.
+    pub status: String,
+    pub snapshot: SnapshotStatus,
+}
+
+/// Response for POST /synonyms/reload.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SynonymsReloadResponse {
+    pub status: String,
+    /// Words with synonyms in the reloaded file.
+    pub words: usize,
+}
@@ -9,19 +9,35 @@
 //! - Book metadata storage (title, author, language, year, word counts)
 //! - Inverted index management (word -> book_id mappings)
 //! - Query operations for the Search Service
+//! - The relevance feedback store (judgements users send about results)
+//! - The query log (recent searches, for usage statistics)
 //!
 //! # Storage Backends
 //! - Redis: In-memory storage for fast lookups, ideal for development and small datasets
 //! - PostgreSQL: Persistent relational storage with indexing for production use
-
+//! - Memory: Process-local storage for tests and local experiments (see [`memory`])
+//! - Sharded: Words spread over several backends by consistent hashing (see [`sharded`])
+//! - Snapshot: Queries answered from an in-memory copy of another backend's index (see [`snapshot`])
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common_redis::{RedisClient, RedisConfig, RedisConnection, RedisMode};
 use redis::AsyncCommands;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, Row};
-use std::collections::HashSet;
+use sqlx::{postgres::PgRow, PgPool, Row};
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
-use tracing::error;
+use utoipa::ToSchema;
+
+pub mod memory;
+pub mod sharded;
+pub mod snapshot;
+
+pub use common_index::{BookMetadata, IndexField};
+pub use memory::MemoryBackend;
+pub use sharded::ShardedBackend;
+pub use snapshot::SnapshotBackend;
 
 /// Errors that can occur during storage operations.
 #[derive(Error, Debug)]
@@ -36,18 +52,177 @@ pub enum StorageError {
     Connection(String),
 }
 
-/// Metadata for an indexed book.
+/// A distinct author together with the books attributed to them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuthorEntry {
+    pub name: String,
+    pub book_count: usize,
+    pub book_ids: Vec<u32>,
+}
+
+/// Groups `(author, book_id)` pairs into [`AuthorEntry`]s whose name starts
+/// with `prefix` (case-insensitive), sorted alphabetically and paginated.
 ///
-/// This structure is stored in the datamart and returned by search queries.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BookMetadata {
+/// `page` is 1-based; a page past the end yields an empty list.
+pub fn aggregate_authors(
+    books: impl IntoIterator<Item = (String, u32)>,
+    prefix: Option<&str>,
+    page: usize,
+    per_page: usize,
+) -> Vec<AuthorEntry> {
+    let prefix = prefix.map(str::to_lowercase);
+    let mut by_author: HashMap<String, Vec<u32>> = HashMap::new();
+
+    for (author, book_id) in books {
+        if prefix
+            .as_deref()
+            .is_some_and(|p| !author.to_lowercase().starts_with(p))
+        {
+            continue;
+        }
+        by_author.entry(author).or_default().push(book_id);
+    }
+
+    let mut authors: Vec<AuthorEntry> = by_author
+        .into_iter()
+        .map(|(name, mut book_ids)| {
+            book_ids.sort_unstable();
+            AuthorEntry {
+                name,
+                book_count: book_ids.len(),
+                book_ids,
+            }
+        })
+        .collect();
+    authors.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    authors
+        .into_iter()
+        .skip(page.saturating_sub(1) * per_page)
+        .take(per_page)
+        .collect()
+}
+
+/// Number of indexed books published in one decade; `decade` is `None` for
+/// books without a known year.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DecadeBucket {
+    pub decade: Option<u32>,
+    pub book_count: usize,
+}
+
+/// One user judgement of whether a book was a good result for a query.
+/// `query` is the normalized query (see [`crate::services::query`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Feedback {
+    pub query: String,
+    pub book_id: u32,
+    pub relevant: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Feedback recorded for one query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedbackSummary {
+    pub positive: usize,
+    pub negative: usize,
+    /// `(book_id, times marked relevant)`, most often first.
+    pub top_relevant: Vec<(u32, usize)>,
+}
+
+/// A sentence matching a passage search, numbered as
+/// `common_text::split_sentences` splits the book's body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Passage {
     pub book_id: u32,
-    pub title: String,
-    pub author: String,
-    pub language: String,
-    pub year: Option<u32>,
-    pub word_count: usize,
-    pub unique_words: usize,
+    pub sentence_id: usize,
+}
+
+/// Sentences found in every one of `postings` (one set per query word),
+/// restricted to `book_id` when given, ordered by book and sentence.
+pub fn intersect_passages(
+    postings: impl IntoIterator<Item = HashSet<(u32, usize)>>,
+    book_id: Option<u32>,
+) -> Vec<Passage> {
+    let mut matched: Option<HashSet<(u32, usize)>> = None;
+    for mut sentences in postings {
+        if let Some(book_id) = book_id {
+            sentences.retain(|&(id, _)| id == book_id);
+        }
+        if let Some(matched) = &matched {
+            sentences.retain(|sentence| matched.contains(sentence));
+        }
+        let empty = sentences.is_empty();
+        matched = Some(sentences);
+        if empty {
+            break;
+        }
+    }
+
+    let mut passages: Vec<Passage> = matched
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(book_id, sentence_id)| Passage { book_id, sentence_id })
+        .collect();
+    passages.sort_unstable();
+    passages
+}
+
+/// Most searches the query log keeps; older ones are dropped as new ones
+/// arrive.
+pub const QUERY_LOG_CAP: usize = 10_000;
+
+/// One search in the query log. `query` is the normalized query and
+/// `result_count` the number of matches across all pages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryLogEntry {
+    pub query: String,
+    pub filters: HashMap<String, String>,
+    pub result_count: usize,
+    pub latency_ms: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Tallies `feedback` and keeps the `top` books most often marked relevant,
+/// breaking ties by book ID.
+pub fn summarize_feedback<'a>(feedback: impl IntoIterator<Item = &'a Feedback>, top: usize) -> FeedbackSummary {
+    let mut summary = FeedbackSummary::default();
+    let mut relevant: HashMap<u32, usize> = HashMap::new();
+    for entry in feedback {
+        if entry.relevant {
+            summary.positive += 1;
+            *relevant.entry(entry.book_id).or_insert(0) += 1;
+        } else {
+            summary.negative += 1;
+        }
+    }
+
+    summary.top_relevant = relevant.into_iter().collect();
+    summary.top_relevant.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    summary.top_relevant.truncate(top);
+    summary
+}
+
+/// Counts books per decade (`(year / 10) * 10`), sorted chronologically
+/// with the unknown-year bucket last.
+pub fn decade_buckets(years: impl IntoIterator<Item = Option<u32>>) -> Vec<DecadeBucket> {
+    let mut counts: std::collections::BTreeMap<Option<u32>, usize> = std::collections::BTreeMap::new();
+    for year in years {
+        *counts.entry(year.map(|y| (y / 10) * 10)).or_insert(0) += 1;
+    }
+
+    let mut buckets: Vec<DecadeBucket> = counts
+        .into_iter()
+        .map(|(decade, book_count)| DecadeBucket { decade, book_count })
+        .collect();
+    // `None` sorts first in the map; report it after the known decades.
+    buckets.sort_by_key(|bucket| (bucket.decade.is_none(), bucket.decade));
+    buckets
 }
 
 /// Trait defining the storage backend interface.
@@ -56,14 +231,151 @@ pub struct BookMetadata {
 /// to ensure consistent behavior across different backends.
 #[async_trait]
 pub trait StorageBackend {
+    /// The backend's name, as given in `BACKEND_TYPE`.
+    fn kind(&self) -> &'static str;
     async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError>;
     async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError>;
+    /// Metadata of several books in as few round trips as the backend
+    /// allows; books that aren't indexed are left out.
+    async fn get_book_metadatas(&self, book_ids: &[u32]) -> Result<Vec<BookMetadata>, StorageError>;
     async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError>;
+    /// Whether the indexing service stored the book's metadata but hasn't
+    /// finished writing its postings, either still working or interrupted.
+    async fn is_book_incomplete(&self, book_id: u32) -> Result<bool, StorageError>;
     async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError>;
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError>;
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError>;
     async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError>;
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError>;
+    /// Books containing every word (empty when `words` is).
+    async fn search_all_words(&self, words: &[String]) -> Result<HashSet<u32>, StorageError>;
+    /// Books containing at least one of the words.
+    async fn search_any_word(&self, words: &[String]) -> Result<HashSet<u32>, StorageError>;
+    /// Number of books containing the word.
+    async fn get_word_doc_freq(&self, word: &str) -> Result<usize, StorageError>;
+    /// The `words` that are in the vocabulary, in their original order.
+    async fn filter_known_words(&self, words: &[String]) -> Result<Vec<String>, StorageError>;
+    /// Up to `limit` vocabulary words starting with `prefix`, alphabetically,
+    /// read from an ordered index of the vocabulary.
+    async fn words_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<String>, StorageError>;
+    /// Up to `limit` words with postings in `field` that `pattern` matches,
+    /// in no particular order, where `Body` means the whole vocabulary.
+    /// Walks the field's whole vocabulary.
+    async fn words_matching(&self, field: IndexField, pattern: &Regex, limit: usize) -> Result<Vec<String>, StorageError>;
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError>;
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError>; // (book_id, chapter_no)
+    /// The `(book_id, chapter_no)` postings of the word in just the books of
+    /// `chapter_counts`, which maps each to its chapter count.
+    async fn search_word_chapters_in_books(
+        &self,
+        word: &str,
+        chapter_counts: &HashMap<u32, usize>,
+    ) -> Result<HashSet<(u32, usize)>, StorageError>;
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError>;
+    /// Sentences containing every word, only in `book_id` when given,
+    /// ordered by book and sentence (empty when `words` is).
+    async fn search_passages(&self, words: &[String], book_id: Option<u32>) -> Result<Vec<Passage>, StorageError>;
     async fn get_stats(&self) -> Result<(usize, usize), StorageError>; // (total_books, unique_words)
+    async fn list_authors(&self, prefix: Option<&str>, page: usize, per_page: usize) -> Result<Vec<AuthorEntry>, StorageError>;
+    async fn get_language_distribution(&self) -> Result<HashMap<String, usize>, StorageError>; // language -> book count
+    async fn get_year_distribution(&self) -> Result<Vec<DecadeBucket>, StorageError>;
+    /// Number of distinct words with a posting for the book.
+    async fn get_word_count_for_book(&self, book_id: u32) -> Result<usize, StorageError>;
+    /// Distinct words with a posting for the book.
+    async fn get_words_for_book(&self, book_id: u32) -> Result<HashSet<String>, StorageError>;
+    /// Appends a judgement to the feedback store.
+    async fn record_feedback(&self, feedback: &Feedback) -> Result<(), StorageError>;
+    /// Feedback recorded for the normalized `query`, with the `top` books
+    /// most often marked relevant.
+    async fn get_feedback_summary(&self, query: &str, top: usize) -> Result<FeedbackSummary, StorageError>;
+    /// Appends a search to the query log, which keeps the latest
+    /// [`QUERY_LOG_CAP`].
+    async fn record_query(&self, entry: &QueryLogEntry) -> Result<(), StorageError>;
+    /// Logged searches made at or after `since`, newest first.
+    async fn get_query_log(&self, since: DateTime<Utc>) -> Result<Vec<QueryLogEntry>, StorageError>;
+    /// Opaque token that changes whenever the indexing service stores a book.
+    async fn get_index_version(&self) -> Result<String, StorageError>;
+    /// Number of books and when the indexing service last stored, deleted or
+    /// finished indexing one (`None` if it never recorded a change).
+    async fn get_index_freshness(&self) -> Result<(usize, Option<DateTime<Utc>>), StorageError>; // (total_books, last_updated)
+    /// Up to `limit` books with when the indexing service last stored them,
+    /// newest first; with `since`, only books stored after it. Deleted books
+    /// aren't listed.
+    async fn get_recent_books(&self, limit: usize, since: Option<DateTime<Utc>>) -> Result<Vec<(u32, DateTime<Utc>)>, StorageError>;
+    /// Whether the indexing service folded diacritics when it built the
+    /// index, `None` if it never recorded it.
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError>;
     async fn test_connection(&self) -> Result<(), StorageError>;
+    /// Replaces the backend's connections with fresh ones to the same
+    /// server. Backends whose pool reconnects on its own keep the default.
+    async fn reconnect(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// The smallest string above every word starting with `prefix`: the prefix
+/// with its last character bumped (`whal` → `wham`). `None` if there is no
+/// such character.
+fn prefix_upper_bound(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+    let last = chars.pop()?;
+    chars.push(char::from_u32(last as u32 + 1)?);
+    Some(chars.into_iter().collect())
+}
+
+/// Parses `book_id:position` members of a Redis chapter or sentence set,
+/// skipping malformed ones.
+fn parse_book_positions(members: &[String]) -> HashSet<(u32, usize)> {
+    members
+        .iter()
+        .filter_map(|member| {
+            let (book_id, position) = member.split_once(':')?;
+            Some((book_id.parse().ok()?, position.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Words requested per SSCAN call when walking `stats:all_words`.
+const WORD_SCAN_BATCH: usize = 500;
+
+/// Metadata keys fetched per MGET call.
+const METADATA_BATCH: usize = 500;
+
+/// Fills `index:books` from the `book:{id}:metadata` keys of an index
+/// written before the set was kept, and returns the book IDs found.
+async fn backfill_indexed_books(conn: &mut RedisConnection) -> Result<HashSet<u32>, StorageError> {
+    let keys = conn.keys_matching("book:*:metadata").await?;
+    let book_ids: Vec<u32> = keys
+        .iter()
+        .filter_map(|key| key.strip_prefix("book:")?.strip_suffix(":metadata")?.parse().ok())
+        .collect();
+    for chunk in book_ids.chunks(METADATA_BATCH) {
+        conn.sadd::<_, _, ()>("index:books", chunk).await?;
+    }
+    Ok(book_ids.into_iter().collect())
+}
+
+/// Adds the words of `stats:all_words` missing from `stats:words_lex`,
+/// which indexes written before the sorted set existed only partly fill as
+/// books are indexed again.
+async fn backfill_words_lex(conn: &mut RedisConnection) -> Result<(), StorageError> {
+    let mut cursor = 0u64;
+    loop {
+        let (next, words): (u64, Vec<String>) = redis::cmd("SSCAN")
+            .arg("stats:all_words")
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(WORD_SCAN_BATCH)
+            .query_async(conn)
+            .await?;
+        if !words.is_empty() {
+            let members: Vec<(u8, &String)> = words.iter().map(|word| (0, word)).collect();
+            conn.zadd_multiple::<_, _, _, ()>("stats:words_lex", &members).await?;
+        }
+        if next == 0 {
+            return Ok(());
+        }
+        cursor = next;
+    }
 }
 
 /// Redis-based storage implementation.
@@ -71,25 +383,57 @@ pub trait StorageBackend {
 /// Uses Redis data structures for fast in-memory operations:
 /// - `book:{id}:metadata` - JSON-serialized book metadata
 /// - `word:{word}` - Set of book IDs containing the word (inverted index)
+/// - `word:{word}:field:{field}` - Book IDs with the word in their title or author
+/// - `word:{word}:sentences` - `book_id:sentence_id` members, when sentence indexing is on
 /// - `stats:total_books` - Counter for total indexed books
+/// - `index:books` - Set of the IDs of all indexed books
 /// - `stats:all_words` - Set of all indexed words
+/// - `stats:words_lex` - The same words in a sorted set, all scored 0, for prefix lookups
+/// - `stats:index_version` - Counter bumped on every metadata write
+/// - `stats:last_updated` - RFC 3339 time of the indexing service's last change
+/// - `index:recent` - Sorted set of book IDs scored by when they were last stored, in Unix milliseconds
+/// - `feedback:{query}` - List of JSON relevance judgements for a query
+/// - `search:query_log` - JSON query log entries, newest first, capped at [`QUERY_LOG_CAP`]
+///
+/// Works against a single server or a cluster (see [`RedisConfig`]).
 pub struct RedisBackend {
-    client: redis::Client,
+    config: RedisConfig,
+    /// Replaced by [`StorageBackend::reconnect`] while handlers keep using
+    /// the connections they already hold.
+    client: tokio::sync::RwLock<RedisClient>,
 }
 
 impl RedisBackend {
-    pub fn new(redis_url: &str) -> Result<Self, StorageError> {
-        let client = redis::Client::open(redis_url)?;
-        Ok(Self { client })
+    pub fn from_config(config: &RedisConfig) -> Result<Self, StorageError> {
+        Ok(Self {
+            config: config.clone(),
+            client: tokio::sync::RwLock::new(RedisClient::open(config)?),
+        })
+    }
+
+    pub async fn get_connection(&self) -> Result<RedisConnection, StorageError> {
+        let client = self.client.read().await.clone();
+        Ok(client.get_connection().await?)
+    }
+
+    fn is_cluster(&self) -> bool {
+        self.config.mode == RedisMode::Cluster
     }
 
-    pub async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, StorageError> {
-        Ok(self.client.get_multiplexed_async_connection().await?)
+    /// Metadata of every indexed book, fetched in [`METADATA_BATCH`]-sized
+    /// MGETs rather than one GET per book.
+    async fn all_book_metadata(&self) -> Result<Vec<BookMetadata>, StorageError> {
+        let book_ids: Vec<u32> = self.get_indexed_books().await?.into_iter().collect();
+        self.get_book_metadatas(&book_ids).await
     }
 }
 
 #[async_trait]
 impl StorageBackend for RedisBackend {
+    fn kind(&self) -> &'static str {
+        "redis"
+    }
+
     async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
         let mut conn = self.get_connection().await?;
 
@@ -98,6 +442,7 @@ impl StorageBackend for RedisBackend {
 
         conn.set::<_, _, ()>(&key, &value).await?;
         conn.incr::<_, _, ()>("stats:total_books", 1).await?;
+        conn.sadd::<_, _, ()>("index:books", metadata.book_id).await?;
 
         Ok(())
     }
@@ -117,6 +462,32 @@ impl StorageBackend for RedisBackend {
         }
     }
 
+    async fn get_book_metadatas(&self, book_ids: &[u32]) -> Result<Vec<BookMetadata>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let mut books = Vec::with_capacity(book_ids.len());
+        for chunk in book_ids.chunks(METADATA_BATCH) {
+            let keys: Vec<String> = chunk.iter().map(|id| format!("book:{}:metadata", id)).collect();
+            // The keys live in different cluster slots, which MGET can't
+            // span, so cluster mode fetches them one at a time.
+            let values: Vec<Option<String>> = if self.is_cluster() {
+                let mut values = Vec::with_capacity(keys.len());
+                for key in &keys {
+                    values.push(conn.get(key).await?);
+                }
+                values
+            } else {
+                // MGET always answers with a list, even for a single key
+                redis::cmd("MGET").arg(&keys).query_async(&mut conn).await?
+            };
+            for json_str in values.into_iter().flatten() {
+                books.push(serde_json::from_str(&json_str)?);
+            }
+        }
+
+        Ok(books)
+    }
+
     async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
         let mut conn = self.get_connection().await?;
 
@@ -126,33 +497,44 @@ impl StorageBackend for RedisBackend {
         Ok(exists)
     }
 
+    async fn is_book_incomplete(&self, book_id: u32) -> Result<bool, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let incomplete: bool = conn.sismember("index:incomplete", book_id).await?;
+        Ok(incomplete)
+    }
+
     async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
         let mut conn = self.get_connection().await?;
 
-        let pattern = "book:*:metadata";
-        let keys: Vec<String> = conn.keys(pattern).await?;
-
-        let mut book_ids = HashSet::new();
-        for key in keys {
-            if let Some(book_id_str) = key
-                .strip_prefix("book:")
-                .and_then(|s| s.strip_suffix(":metadata"))
-            {
-                if let Ok(book_id) = book_id_str.parse::<u32>() {
-                    book_ids.insert(book_id);
-                }
+        let book_ids: Vec<u32> = conn.smembers("index:books").await?;
+        if book_ids.is_empty() {
+            // An index written before `index:books` was kept only has the
+            // metadata keys; they are looked up once and tracked from then on.
+            let total_books: Option<i64> = conn.get("stats:total_books").await?;
+            if total_books.unwrap_or(0) > 0 {
+                return backfill_indexed_books(&mut conn).await;
             }
         }
 
-        Ok(book_ids)
+        Ok(book_ids.into_iter().collect())
     }
 
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError> {
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
         let mut conn = self.get_connection().await?;
 
-        let word_key = format!("word:{}", word);
-        conn.sadd::<_, _, ()>(&word_key, book_id).await?;
-        conn.sadd::<_, _, ()>("stats:all_words", word).await?;
+        if field.is_unscoped() {
+            let word_key = format!("word:{}", word);
+            conn.sadd::<_, _, ()>(&word_key, book_id).await?;
+            conn.sadd::<_, _, ()>("stats:all_words", word).await?;
+            conn.zadd::<_, _, _, ()>("stats:words_lex", word, 0).await?;
+        }
+
+        if !field.is_body() {
+            let field_key = format!("word:{}:field:{}", word, field.as_str());
+            conn.sadd::<_, _, ()>(&field_key, book_id).await?;
+            conn.sadd::<_, _, ()>(format!("stats:field_words:{}", field.as_str()), word).await?;
+        }
 
         Ok(())
     }
@@ -166,6 +548,387 @@ impl StorageBackend for RedisBackend {
         Ok(book_ids.into_iter().collect())
     }
 
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        if field.is_body() {
+            return self.search_word(word).await;
+        }
+        let mut conn = self.get_connection().await?;
+
+        let field_key = format!("word:{}:field:{}", word, field.as_str());
+        let book_ids: Vec<u32> = conn.smembers(&field_key).await?;
+
+        Ok(book_ids.into_iter().collect())
+    }
+
+    async fn search_all_words(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        if words.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        // SINTER can't span cluster slots, so cluster mode intersects here,
+        // stopping at the first word no book has in common.
+        if self.is_cluster() {
+            let mut intersection = self.search_word(&words[0]).await?;
+            for word in &words[1..] {
+                if intersection.is_empty() {
+                    break;
+                }
+                let books = self.search_word(word).await?;
+                intersection.retain(|book_id| books.contains(book_id));
+            }
+            return Ok(intersection);
+        }
+
+        let mut conn = self.get_connection().await?;
+        let keys: Vec<String> = words.iter().map(|word| format!("word:{}", word)).collect();
+        let book_ids: Vec<u32> = conn.sinter(keys).await?;
+
+        Ok(book_ids.into_iter().collect())
+    }
+
+    async fn search_any_word(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        if words.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        // Same slot restriction as SINTER
+        if self.is_cluster() {
+            let mut union = HashSet::new();
+            for word in words {
+                union.extend(self.search_word(word).await?);
+            }
+            return Ok(union);
+        }
+
+        let mut conn = self.get_connection().await?;
+        let keys: Vec<String> = words.iter().map(|word| format!("word:{}", word)).collect();
+        let book_ids: Vec<u32> = conn.sunion(keys).await?;
+
+        Ok(book_ids.into_iter().collect())
+    }
+
+    async fn get_word_doc_freq(&self, word: &str) -> Result<usize, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        Ok(conn.scard(format!("word:{}", word)).await?)
+    }
+
+    async fn filter_known_words(&self, words: &[String]) -> Result<Vec<String>, StorageError> {
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.get_connection().await?;
+
+        // One key, so a single SMISMEMBER works in cluster mode too
+        let known: Vec<bool> = redis::cmd("SMISMEMBER")
+            .arg("stats:all_words")
+            .arg(words)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(words
+            .iter()
+            .zip(known)
+            .filter(|(_, known)| *known)
+            .map(|(word, _)| word.clone())
+            .collect())
+    }
+
+    async fn words_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<String>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        // Both counts are O(1); the sorted set only falls behind the plain
+        // set on indexes written before it existed
+        let lex_words: usize = conn.zcard("stats:words_lex").await?;
+        let all_words: usize = conn.scard("stats:all_words").await?;
+        if lex_words < all_words {
+            backfill_words_lex(&mut conn).await?;
+        }
+
+        // Equal scores order a sorted set by member, so the prefix's words
+        // are one range of it; no byte of UTF-8 text sorts after `\xff`
+        let mut end = format!("[{}", prefix).into_bytes();
+        end.push(0xff);
+        let words: Vec<String> = redis::cmd("ZRANGEBYLEX")
+            .arg("stats:words_lex")
+            .arg(format!("[{}", prefix))
+            .arg(end)
+            .arg("LIMIT")
+            .arg(0)
+            .arg(limit)
+            .query_async(&mut conn)
+            .await?;
+        Ok(words)
+    }
+
+    async fn words_matching(&self, field: IndexField, pattern: &Regex, limit: usize) -> Result<Vec<String>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let vocabulary_key = match field {
+            IndexField::Body => "stats:all_words".to_string(),
+            field => format!("stats:field_words:{}", field.as_str()),
+        };
+        // SSCAN may repeat a word, hence the set
+        let mut matched = HashSet::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, words): (u64, Vec<String>) = redis::cmd("SSCAN")
+                .arg(&vocabulary_key)
+                .arg(cursor)
+                .arg("COUNT")
+                .arg(WORD_SCAN_BATCH)
+                .query_async(&mut conn)
+                .await?;
+            matched.extend(words.into_iter().filter(|word| pattern.is_match(word)));
+            if next == 0 || matched.len() >= limit {
+                break;
+            }
+            cursor = next;
+        }
+        Ok(matched.into_iter().take(limit).collect())
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let chapters_key = format!("word:{}:chapters", word);
+        conn.sadd::<_, _, ()>(&chapters_key, format!("{}:{}", book_id, chapter_no)).await?;
+
+        Ok(())
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let chapters_key = format!("word:{}:chapters", word);
+        let members: Vec<String> = conn.smembers(&chapters_key).await?;
+
+        Ok(parse_book_positions(&members))
+    }
+
+    /// Asks about every chapter the books can have rather than reading the
+    /// word's whole set.
+    async fn search_word_chapters_in_books(
+        &self,
+        word: &str,
+        chapter_counts: &HashMap<u32, usize>,
+    ) -> Result<HashSet<(u32, usize)>, StorageError> {
+        // Chapter 0 is the text before the first heading
+        let candidates: Vec<(u32, usize)> = chapter_counts
+            .iter()
+            .flat_map(|(&book_id, &count)| (0..=count).map(move |chapter_no| (book_id, chapter_no)))
+            .collect();
+        if candidates.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let mut conn = self.get_connection().await?;
+
+        let members: Vec<String> = candidates
+            .iter()
+            .map(|(book_id, chapter_no)| format!("{}:{}", book_id, chapter_no))
+            .collect();
+        let found: Vec<bool> = redis::cmd("SMISMEMBER")
+            .arg(format!("word:{}:chapters", word))
+            .arg(members)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(candidates
+            .into_iter()
+            .zip(found)
+            .filter(|(_, found)| *found)
+            .map(|(posting, _)| posting)
+            .collect())
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let sentences_key = format!("word:{}:sentences", word);
+        conn.sadd::<_, _, ()>(&sentences_key, format!("{}:{}", book_id, sentence_id)).await?;
+
+        Ok(())
+    }
+
+    async fn search_passages(&self, words: &[String], book_id: Option<u32>) -> Result<Vec<Passage>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        // Members are "book:sentence" strings, so SINTER would work, but not
+        // across cluster slots; intersecting here works in both modes.
+        let mut postings = Vec::with_capacity(words.len());
+        for word in words {
+            let members: Vec<String> = conn.smembers(format!("word:{}:sentences", word)).await?;
+            let sentences = parse_book_positions(&members);
+            let empty = sentences.is_empty();
+            postings.push(sentences);
+            if empty {
+                break;
+            }
+        }
+
+        Ok(intersect_passages(postings, book_id))
+    }
+
+    async fn list_authors(&self, prefix: Option<&str>, page: usize, per_page: usize) -> Result<Vec<AuthorEntry>, StorageError> {
+        let books = self
+            .all_book_metadata()
+            .await?
+            .into_iter()
+            .map(|metadata| (metadata.author, metadata.book_id));
+
+        Ok(aggregate_authors(books, prefix, page, per_page))
+    }
+
+    async fn get_language_distribution(&self) -> Result<HashMap<String, usize>, StorageError> {
+        let mut distribution = HashMap::new();
+        for metadata in self.all_book_metadata().await? {
+            *distribution.entry(metadata.language).or_insert(0) += 1;
+        }
+
+        Ok(distribution)
+    }
+
+    async fn get_year_distribution(&self) -> Result<Vec<DecadeBucket>, StorageError> {
+        let years = self.all_book_metadata().await?.into_iter().map(|metadata| metadata.year);
+
+        Ok(decade_buckets(years))
+    }
+
+    async fn get_word_count_for_book(&self, book_id: u32) -> Result<usize, StorageError> {
+        Ok(self.get_words_for_book(book_id).await?.len())
+    }
+
+    async fn get_words_for_book(&self, book_id: u32) -> Result<HashSet<String>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        // Postings are only kept per word, so every indexed word is checked.
+        // SSCAN may repeat a word, hence the set.
+        let mut matched = HashSet::new();
+        let mut cursor = 0u64;
+        loop {
+            let (next, words): (u64, Vec<String>) = redis::cmd("SSCAN")
+                .arg("stats:all_words")
+                .arg(cursor)
+                .arg("COUNT")
+                .arg(WORD_SCAN_BATCH)
+                .query_async(&mut conn)
+                .await?;
+
+            // The word sets live in different cluster slots, which a cluster
+            // pipeline can't span, so cluster mode checks them one at a time.
+            let hits: Vec<bool> = if self.is_cluster() {
+                let mut hits = Vec::with_capacity(words.len());
+                for word in &words {
+                    hits.push(conn.sismember(format!("word:{}", word), book_id).await?);
+                }
+                hits
+            } else {
+                let mut pipe = redis::pipe();
+                for word in &words {
+                    pipe.sismember(format!("word:{}", word), book_id);
+                }
+                pipe.query_async(&mut conn).await?
+            };
+            matched.extend(words.into_iter().zip(hits).filter(|(_, hit)| *hit).map(|(word, _)| word));
+
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        Ok(matched)
+    }
+
+    async fn record_feedback(&self, feedback: &Feedback) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let entry = serde_json::to_string(feedback)?;
+        conn.rpush::<_, _, ()>(format!("feedback:{}", feedback.query), entry).await?;
+
+        Ok(())
+    }
+
+    async fn get_feedback_summary(&self, query: &str, top: usize) -> Result<FeedbackSummary, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let entries: Vec<String> = conn.lrange(format!("feedback:{}", query), 0, -1).await?;
+        let feedback = entries
+            .iter()
+            .map(|entry| serde_json::from_str(entry))
+            .collect::<Result<Vec<Feedback>, _>>()?;
+
+        Ok(summarize_feedback(&feedback, top))
+    }
+
+    async fn record_query(&self, entry: &QueryLogEntry) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let entry = serde_json::to_string(entry)?;
+        conn.lpush::<_, _, ()>("search:query_log", entry).await?;
+        conn.ltrim::<_, ()>("search:query_log", 0, QUERY_LOG_CAP as isize - 1).await?;
+
+        Ok(())
+    }
+
+    async fn get_query_log(&self, since: DateTime<Utc>) -> Result<Vec<QueryLogEntry>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let entries: Vec<String> = conn.lrange("search:query_log", 0, -1).await?;
+        let mut log = Vec::new();
+        for entry in entries {
+            let entry: QueryLogEntry = serde_json::from_str(&entry)?;
+            if entry.created_at < since {
+                break;
+            }
+            log.push(entry);
+        }
+
+        Ok(log)
+    }
+
+    async fn get_index_version(&self) -> Result<String, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let version: Option<u64> = conn.get("stats:index_version").await?;
+        Ok(version.unwrap_or(0).to_string())
+    }
+
+    async fn get_index_freshness(&self) -> Result<(usize, Option<DateTime<Utc>>), StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        // Separate GETs: in a cluster the two keys live in different slots
+        let total_books: Option<usize> = conn.get("stats:total_books").await?;
+        let last_updated: Option<String> = conn.get("stats:last_updated").await?;
+        let last_updated = last_updated
+            .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+            .map(|updated_at| updated_at.with_timezone(&Utc));
+
+        Ok((total_books.unwrap_or(0), last_updated))
+    }
+
+    async fn get_recent_books(&self, limit: usize, since: Option<DateTime<Utc>>) -> Result<Vec<(u32, DateTime<Utc>)>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        // `(` excludes books stored exactly at `since`
+        let min = since.map_or_else(|| "-inf".to_string(), |since| format!("({}", since.timestamp_millis()));
+        let books: Vec<(u32, f64)> = conn
+            .zrevrangebyscore_limit_withscores("index:recent", "+inf", min, 0, limit as isize)
+            .await?;
+
+        Ok(books
+            .into_iter()
+            .filter_map(|(book_id, millis)| Some((book_id, DateTime::from_timestamp_millis(millis as i64)?)))
+            .collect())
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        let mut conn = self.get_connection().await?;
+
+        let folding: Option<String> = conn.get("index:fold_diacritics").await?;
+        Ok(folding.map(|value| value == "true"))
+    }
+
     async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
         let mut conn = self.get_connection().await?;
 
@@ -180,6 +943,14 @@ impl StorageBackend for RedisBackend {
         let _: Option<String> = conn.get("__connection_test__").await?;
         Ok(())
     }
+
+    async fn reconnect(&self) -> Result<(), StorageError> {
+        // A cluster client keeps its connection for good, so a fresh client
+        // is the only way to drop one that broke
+        let client = RedisClient::open(&self.config)?;
+        *self.client.write().await = client;
+        self.test_connection().await
+    }
 }
 
 
@@ -215,6 +986,19 @@ impl PostgresBackend {
         .execute(&pool)
         .await?;
 
+        sqlx::query("ALTER TABLE books ADD COLUMN IF NOT EXISTS chapter_count INTEGER DEFAULT 0")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query("ALTER TABLE books ADD COLUMN IF NOT EXISTS reading_level DOUBLE PRECISION")
+            .execute(&pool)
+            .await?;
+
+        // Subjects are stored as a serialized JSON array
+        sqlx::query("ALTER TABLE books ADD COLUMN IF NOT EXISTS subjects TEXT DEFAULT '[]'")
+            .execute(&pool)
+            .await?;
+
         // Create inverted index table
         sqlx::query(
             r#"
@@ -237,24 +1021,140 @@ impl PostgresBackend {
         .execute(&pool)
         .await?;
 
-        Ok(Self { pool })
-    }
-}
-
-#[async_trait]
-impl StorageBackend for PostgresBackend {
-    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
         sqlx::query(
             r#"
-            INSERT INTO books (book_id, title, author, language, year, word_count, unique_words)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            ON CONFLICT (book_id) DO UPDATE SET
-                title = EXCLUDED.title,
-                author = EXCLUDED.author,
+            CREATE TABLE IF NOT EXISTS word_field_index (
+                word VARCHAR,
+                field VARCHAR(10),
+                book_id INTEGER,
+                PRIMARY KEY (word, field, book_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS search_feedback (
+                query TEXT NOT NULL,
+                book_id INTEGER NOT NULL,
+                relevant BOOLEAN NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_search_feedback_query ON search_feedback(query)")
+            .execute(&pool)
+            .await?;
+
+        // Filters are stored as a serialized JSON object
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS search_queries (
+                query TEXT NOT NULL,
+                filters TEXT NOT NULL DEFAULT '{}',
+                result_count INTEGER NOT NULL,
+                latency_ms DOUBLE PRECISION NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_search_queries_created_at ON search_queries(created_at)")
+            .execute(&pool)
+            .await?;
+
+        // Written by the indexing service while a book's postings are in flux
+        sqlx::query("CREATE TABLE IF NOT EXISTS incomplete_books (book_id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await?;
+
+        // Written by the indexing service whenever the index changes
+        sqlx::query("CREATE TABLE IF NOT EXISTS index_meta (key VARCHAR PRIMARY KEY, updated_at TIMESTAMP NOT NULL)")
+            .execute(&pool)
+            .await?;
+
+        // Written by the indexing service with the settings it built the index with
+        sqlx::query("CREATE TABLE IF NOT EXISTS index_settings (key VARCHAR PRIMARY KEY, value VARCHAR NOT NULL)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_chapter_index (
+                word VARCHAR,
+                book_id INTEGER,
+                chapter_no INTEGER,
+                PRIMARY KEY (word, book_id, chapter_no)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS word_sentence_index (
+                word VARCHAR,
+                book_id INTEGER,
+                sentence_id INTEGER,
+                PRIMARY KEY (word, book_id, sentence_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+fn metadata_from_row(row: &PgRow) -> Result<BookMetadata, StorageError> {
+    Ok(BookMetadata {
+        book_id: row.get::<i32, _>("book_id") as u32,
+        title: row.get("title"),
+        author: row.get("author"),
+        language: row.get("language"),
+        year: row.get::<Option<i32>, _>("year").map(|y| y as u32),
+        word_count: row.get::<i32, _>("word_count") as usize,
+        unique_words: row.get::<i32, _>("unique_words") as usize,
+        chapter_count: row.get::<Option<i32>, _>("chapter_count").unwrap_or(0) as usize,
+        subjects: row
+            .get::<Option<String>, _>("subjects")
+            .map(|json| serde_json::from_str(&json))
+            .transpose()?
+            .unwrap_or_default(),
+        reading_level: row.get("reading_level"),
+    })
+}
+
+#[async_trait]
+impl StorageBackend for PostgresBackend {
+    fn kind(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        sqlx::query(
+            r#"
+            INSERT INTO books (book_id, title, author, language, year, word_count, unique_words, chapter_count, subjects, reading_level)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (book_id) DO UPDATE SET
+                title = EXCLUDED.title,
+                author = EXCLUDED.author,
                 language = EXCLUDED.language,
                 year = EXCLUDED.year,
                 word_count = EXCLUDED.word_count,
                 unique_words = EXCLUDED.unique_words,
+                chapter_count = EXCLUDED.chapter_count,
+                subjects = EXCLUDED.subjects,
+                reading_level = EXCLUDED.reading_level,
                 indexed_at = CURRENT_TIMESTAMP
             "#,
         )
@@ -265,6 +1165,9 @@ impl StorageBackend for PostgresBackend {
         .bind(metadata.year.map(|y| y as i32))
         .bind(metadata.word_count as i32)
         .bind(metadata.unique_words as i32)
+        .bind(metadata.chapter_count as i32)
+        .bind(serde_json::to_string(&metadata.subjects)?)
+        .bind(metadata.reading_level)
         .execute(&self.pool)
         .await?;
 
@@ -273,27 +1176,25 @@ impl StorageBackend for PostgresBackend {
 
     async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
         let row = sqlx::query(
-            "SELECT book_id, title, author, language, year, word_count, unique_words FROM books WHERE book_id = $1"
+            "SELECT book_id, title, author, language, year, word_count, unique_words, chapter_count, subjects, reading_level FROM books WHERE book_id = $1"
         )
         .bind(book_id as i32)
         .fetch_optional(&self.pool)
         .await?;
 
-        match row {
-            Some(row) => {
-                let metadata = BookMetadata {
-                    book_id: row.get::<i32, _>("book_id") as u32,
-                    title: row.get("title"),
-                    author: row.get("author"),
-                    language: row.get("language"),
-                    year: row.get::<Option<i32>, _>("year").map(|y| y as u32),
-                    word_count: row.get::<i32, _>("word_count") as usize,
-                    unique_words: row.get::<i32, _>("unique_words") as usize,
-                };
-                Ok(Some(metadata))
-            }
-            None => Ok(None),
-        }
+        row.as_ref().map(metadata_from_row).transpose()
+    }
+
+    async fn get_book_metadatas(&self, book_ids: &[u32]) -> Result<Vec<BookMetadata>, StorageError> {
+        let ids: Vec<i32> = book_ids.iter().map(|&id| id as i32).collect();
+        let rows = sqlx::query(
+            "SELECT book_id, title, author, language, year, word_count, unique_words, chapter_count, subjects, reading_level FROM books WHERE book_id = ANY($1)"
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(metadata_from_row).collect()
     }
 
     async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
@@ -305,6 +1206,15 @@ impl StorageBackend for PostgresBackend {
         Ok(row.get(0))
     }
 
+    async fn is_book_incomplete(&self, book_id: u32) -> Result<bool, StorageError> {
+        let row = sqlx::query("SELECT EXISTS(SELECT 1 FROM incomplete_books WHERE book_id = $1)")
+            .bind(book_id as i32)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get(0))
+    }
+
     async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
         let rows = sqlx::query("SELECT book_id FROM books")
             .fetch_all(&self.pool)
@@ -318,30 +1228,509 @@ impl StorageBackend for PostgresBackend {
         Ok(book_ids)
     }
 
-    async fn add_word_to_index(&self, word: &str, book_id: u32) -> Result<(), StorageError> {
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        if field.is_unscoped() {
+            sqlx::query(
+                "INSERT INTO word_index (word, book_id) VALUES ($1, $2) ON CONFLICT (word, book_id) DO NOTHING"
+            )
+            .bind(word)
+            .bind(book_id as i32)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        if !field.is_body() {
+            sqlx::query(
+                "INSERT INTO word_field_index (word, field, book_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+            )
+            .bind(word)
+            .bind(field.as_str())
+            .bind(book_id as i32)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        let rows = sqlx::query("SELECT book_id FROM word_index WHERE word = $1")
+            .bind(word)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let book_ids = rows
+            .into_iter()
+            .map(|row| row.get::<i32, _>("book_id") as u32)
+            .collect();
+
+        Ok(book_ids)
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        if field.is_body() {
+            return self.search_word(word).await;
+        }
+
+        let rows = sqlx::query("SELECT book_id FROM word_field_index WHERE word = $1 AND field = $2")
+            .bind(word)
+            .bind(field.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+
+        let book_ids = rows
+            .into_iter()
+            .map(|row| row.get::<i32, _>("book_id") as u32)
+            .collect();
+
+        Ok(book_ids)
+    }
+
+    async fn search_all_words(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        let words: Vec<&str> = words.iter().map(String::as_str).collect::<HashSet<_>>().into_iter().collect();
+        if words.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let rows = sqlx::query(
+            "SELECT book_id FROM word_index WHERE word = ANY($1) GROUP BY book_id HAVING COUNT(DISTINCT word) = $2"
+        )
+        .bind(&words)
+        .bind(words.len() as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.get::<i32, _>("book_id") as u32).collect())
+    }
+
+    async fn search_any_word(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        if words.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let rows = sqlx::query("SELECT DISTINCT book_id FROM word_index WHERE word = ANY($1)")
+            .bind(words)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get::<i32, _>("book_id") as u32).collect())
+    }
+
+    async fn get_word_doc_freq(&self, word: &str) -> Result<usize, StorageError> {
+        let count = sqlx::query("SELECT COUNT(*) as count FROM word_index WHERE word = $1")
+            .bind(word)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count");
+
+        Ok(count as usize)
+    }
+
+    async fn filter_known_words(&self, words: &[String]) -> Result<Vec<String>, StorageError> {
+        let known: HashSet<String> = sqlx::query("SELECT DISTINCT word FROM word_index WHERE word = ANY($1)")
+            .bind(words)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| row.get("word"))
+            .collect();
+
+        Ok(words.iter().filter(|word| known.contains(*word)).cloned().collect())
+    }
+
+    async fn words_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<String>, StorageError> {
+        // The range lets the word index be read in order from the prefix;
+        // starts_with keeps the result right under any collation
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT word FROM word_index
+            WHERE word >= $1 AND ($2::TEXT IS NULL OR word < $2) AND starts_with(word, $1)
+            ORDER BY word
+            LIMIT $3
+            "#,
+        )
+        .bind(prefix)
+        .bind(prefix_upper_bound(prefix))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("word")).collect())
+    }
+
+    async fn words_matching(&self, field: IndexField, pattern: &Regex, limit: usize) -> Result<Vec<String>, StorageError> {
+        use tokio_stream::StreamExt;
+
+        // PostgreSQL's regex dialect differs from the one queries are
+        // validated with, so words are matched here as they stream in
+        let mut rows = if field.is_body() {
+            sqlx::query("SELECT DISTINCT word FROM word_index").fetch(&self.pool)
+        } else {
+            sqlx::query("SELECT DISTINCT word FROM word_field_index WHERE field = $1")
+                .bind(field.as_str())
+                .fetch(&self.pool)
+        };
+        let mut matched = Vec::new();
+        while let Some(row) = rows.next().await {
+            let word: String = row?.get("word");
+            if pattern.is_match(&word) {
+                matched.push(word);
+                if matched.len() == limit {
+                    break;
+                }
+            }
+        }
+        Ok(matched)
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
         sqlx::query(
-            "INSERT INTO word_index (word, book_id) VALUES ($1, $2) ON CONFLICT (word, book_id) DO NOTHING"
+            "INSERT INTO word_chapter_index (word, book_id, chapter_no) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
         )
         .bind(word)
         .bind(book_id as i32)
+        .bind(chapter_no as i32)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
-        let rows = sqlx::query("SELECT book_id FROM word_index WHERE word = $1")
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let rows = sqlx::query("SELECT book_id, chapter_no FROM word_chapter_index WHERE word = $1")
             .bind(word)
             .fetch_all(&self.pool)
             .await?;
 
-        let book_ids = rows
+        let postings = rows
             .into_iter()
-            .map(|row| row.get::<i32, _>("book_id") as u32)
+            .map(|row| {
+                (
+                    row.get::<i32, _>("book_id") as u32,
+                    row.get::<i32, _>("chapter_no") as usize,
+                )
+            })
             .collect();
 
-        Ok(book_ids)
+        Ok(postings)
+    }
+
+    async fn search_word_chapters_in_books(
+        &self,
+        word: &str,
+        chapter_counts: &HashMap<u32, usize>,
+    ) -> Result<HashSet<(u32, usize)>, StorageError> {
+        let book_ids: Vec<i32> = chapter_counts.keys().map(|&book_id| book_id as i32).collect();
+        let rows = sqlx::query(
+            "SELECT book_id, chapter_no FROM word_chapter_index WHERE word = $1 AND book_id = ANY($2)"
+        )
+        .bind(word)
+        .bind(&book_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let postings = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<i32, _>("book_id") as u32,
+                    row.get::<i32, _>("chapter_no") as usize,
+                )
+            })
+            .collect();
+
+        Ok(postings)
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO word_sentence_index (word, book_id, sentence_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+        )
+        .bind(word)
+        .bind(book_id as i32)
+        .bind(sentence_id as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn search_passages(&self, words: &[String], book_id: Option<u32>) -> Result<Vec<Passage>, StorageError> {
+        let words: Vec<&str> = words.iter().map(String::as_str).collect::<HashSet<_>>().into_iter().collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT book_id, sentence_id FROM word_sentence_index
+            WHERE word = ANY($1) AND ($2::INTEGER IS NULL OR book_id = $2)
+            GROUP BY book_id, sentence_id
+            HAVING COUNT(DISTINCT word) = $3
+            ORDER BY book_id, sentence_id
+            "#,
+        )
+        .bind(&words)
+        .bind(book_id.map(|id| id as i32))
+        .bind(words.len() as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Passage {
+                book_id: row.get::<i32, _>("book_id") as u32,
+                sentence_id: row.get::<i32, _>("sentence_id") as usize,
+            })
+            .collect())
+    }
+
+    async fn list_authors(&self, prefix: Option<&str>, page: usize, per_page: usize) -> Result<Vec<AuthorEntry>, StorageError> {
+        let pattern = prefix.map(|p| {
+            let escaped = p.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("{}%", escaped)
+        });
+
+        let rows = sqlx::query(
+            r#"
+            SELECT author, COUNT(*) AS book_count, ARRAY_AGG(book_id ORDER BY book_id) AS book_ids
+            FROM books
+            WHERE $1::TEXT IS NULL OR author ILIKE $1
+            GROUP BY author
+            ORDER BY LOWER(author), author
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(pattern)
+        .bind(per_page as i64)
+        .bind((page.saturating_sub(1) * per_page) as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let authors = rows
+            .into_iter()
+            .map(|row| AuthorEntry {
+                name: row.get("author"),
+                book_count: row.get::<i64, _>("book_count") as usize,
+                book_ids: row
+                    .get::<Vec<i32>, _>("book_ids")
+                    .into_iter()
+                    .map(|id| id as u32)
+                    .collect(),
+            })
+            .collect();
+
+        Ok(authors)
+    }
+
+    async fn get_language_distribution(&self) -> Result<HashMap<String, usize>, StorageError> {
+        let rows = sqlx::query("SELECT language, COUNT(*) AS book_count FROM books GROUP BY language")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let distribution = rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<Option<String>, _>("language").unwrap_or_default(),
+                    row.get::<i64, _>("book_count") as usize,
+                )
+            })
+            .collect();
+
+        Ok(distribution)
+    }
+
+    async fn get_year_distribution(&self) -> Result<Vec<DecadeBucket>, StorageError> {
+        let rows = sqlx::query(
+            "SELECT (year / 10) * 10 AS decade, COUNT(*) AS book_count FROM books GROUP BY decade ORDER BY decade NULLS LAST"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let buckets = rows
+            .into_iter()
+            .map(|row| DecadeBucket {
+                decade: row.get::<Option<i32>, _>("decade").map(|d| d as u32),
+                book_count: row.get::<i64, _>("book_count") as usize,
+            })
+            .collect();
+
+        Ok(buckets)
+    }
+
+    async fn get_word_count_for_book(&self, book_id: u32) -> Result<usize, StorageError> {
+        let count = sqlx::query("SELECT COUNT(DISTINCT word) as count FROM word_index WHERE book_id = $1")
+            .bind(book_id as i32)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count");
+
+        Ok(count as usize)
+    }
+
+    async fn get_words_for_book(&self, book_id: u32) -> Result<HashSet<String>, StorageError> {
+        let rows = sqlx::query("SELECT DISTINCT word FROM word_index WHERE book_id = $1")
+            .bind(book_id as i32)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get::<String, _>("word")).collect())
+    }
+
+    async fn record_feedback(&self, feedback: &Feedback) -> Result<(), StorageError> {
+        sqlx::query("INSERT INTO search_feedback (query, book_id, relevant, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(&feedback.query)
+            .bind(feedback.book_id as i32)
+            .bind(feedback.relevant)
+            .bind(feedback.created_at.naive_utc())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_feedback_summary(&self, query: &str, top: usize) -> Result<FeedbackSummary, StorageError> {
+        let counts = sqlx::query(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE relevant) AS positive, COUNT(*) FILTER (WHERE NOT relevant) AS negative
+            FROM search_feedback WHERE query = $1
+            "#,
+        )
+        .bind(query)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT book_id, COUNT(*) AS votes FROM search_feedback
+            WHERE query = $1 AND relevant
+            GROUP BY book_id
+            ORDER BY votes DESC, book_id
+            LIMIT $2
+            "#,
+        )
+        .bind(query)
+        .bind(top as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(FeedbackSummary {
+            positive: counts.get::<i64, _>("positive") as usize,
+            negative: counts.get::<i64, _>("negative") as usize,
+            top_relevant: rows
+                .into_iter()
+                .map(|row| (row.get::<i32, _>("book_id") as u32, row.get::<i64, _>("votes") as usize))
+                .collect(),
+        })
+    }
+
+    async fn record_query(&self, entry: &QueryLogEntry) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO search_queries (query, filters, result_count, latency_ms, created_at) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(&entry.query)
+        .bind(serde_json::to_string(&entry.filters)?)
+        .bind(entry.result_count as i32)
+        .bind(entry.latency_ms)
+        .bind(entry.created_at.naive_utc())
+        .execute(&self.pool)
+        .await?;
+
+        // Keep the table at the cap, like the Redis list
+        sqlx::query(
+            r#"
+            DELETE FROM search_queries WHERE created_at < (
+                SELECT created_at FROM search_queries ORDER BY created_at DESC OFFSET $1 LIMIT 1
+            )
+            "#,
+        )
+        .bind(QUERY_LOG_CAP as i64 - 1)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_query_log(&self, since: DateTime<Utc>) -> Result<Vec<QueryLogEntry>, StorageError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT query, filters, result_count, latency_ms, created_at FROM search_queries
+            WHERE created_at >= $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(since.naive_utc())
+        .bind(QUERY_LOG_CAP as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(QueryLogEntry {
+                    query: row.get("query"),
+                    filters: serde_json::from_str(row.get("filters"))?,
+                    result_count: row.get::<i32, _>("result_count") as usize,
+                    latency_ms: row.get("latency_ms"),
+                    created_at: row.get::<chrono::NaiveDateTime, _>("created_at").and_utc(),
+                })
+            })
+            .collect()
+    }
+
+    async fn get_index_version(&self) -> Result<String, StorageError> {
+        // Upserts refresh indexed_at, so this changes on every metadata write.
+        let row = sqlx::query(
+            "SELECT COUNT(*)::TEXT || ':' || COALESCE(MAX(indexed_at)::TEXT, '') AS version FROM books"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get("version"))
+    }
+
+    async fn get_index_freshness(&self) -> Result<(usize, Option<DateTime<Utc>>), StorageError> {
+        let row = sqlx::query(
+            "SELECT (SELECT COUNT(*) FROM books) AS total_books, (SELECT updated_at FROM index_meta WHERE key = 'last_updated') AS updated_at"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let updated_at: Option<chrono::NaiveDateTime> = row.get("updated_at");
+        Ok((row.get::<i64, _>("total_books") as usize, updated_at.map(|updated_at| updated_at.and_utc())))
+    }
+
+    async fn get_recent_books(&self, limit: usize, since: Option<DateTime<Utc>>) -> Result<Vec<(u32, DateTime<Utc>)>, StorageError> {
+        // Upserts refresh indexed_at and deletes remove the row
+        let rows = sqlx::query(
+            r#"
+            SELECT book_id, indexed_at FROM books
+            WHERE indexed_at IS NOT NULL AND ($1::TIMESTAMP IS NULL OR indexed_at > $1)
+            ORDER BY indexed_at DESC, book_id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(since.map(|since| since.naive_utc()))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let indexed_at: chrono::NaiveDateTime = row.get("indexed_at");
+                (row.get::<i32, _>("book_id") as u32, indexed_at.and_utc())
+            })
+            .collect())
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        let row = sqlx::query("SELECT value FROM index_settings WHERE key = 'fold_diacritics'")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get::<String, _>("value") == "true"))
     }
 
     async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
@@ -362,4 +1751,56 @@ impl StorageBackend for PostgresBackend {
         sqlx::query("SELECT 1").fetch_one(&self.pool).await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books() -> Vec<(String, u32)> {
+        vec![
+            ("Mary Shelley".to_string(), 84),
+            ("Jane Austen".to_string(), 1342),
+            ("charles Dickens".to_string(), 98),
+            ("Jane Austen".to_string(), 161),
+        ]
+    }
+
+    #[test]
+    fn aggregates_authors_alphabetically() {
+        let authors = aggregate_authors(books(), None, 1, 10);
+
+        let names: Vec<_> = authors.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["charles Dickens", "Jane Austen", "Mary Shelley"]);
+        assert_eq!(authors[1].book_count, 2);
+        assert_eq!(authors[1].book_ids, vec![161, 1342]);
+    }
+
+    #[test]
+    fn filters_authors_by_prefix_case_insensitively() {
+        let authors = aggregate_authors(books(), Some("JA"), 1, 10);
+
+        assert_eq!(authors.len(), 1);
+        assert_eq!(authors[0].name, "Jane Austen");
+    }
+
+    #[test]
+    fn buckets_years_by_decade_with_unknown_last() {
+        let buckets = decade_buckets([Some(1818), None, Some(1813), Some(1851), Some(1819)]);
+
+        assert_eq!(
+            buckets,
+            vec![
+                DecadeBucket { decade: Some(1810), book_count: 3 },
+                DecadeBucket { decade: Some(1850), book_count: 1 },
+                DecadeBucket { decade: None, book_count: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn paginates_authors() {
+        assert_eq!(aggregate_authors(books(), None, 2, 2)[0].name, "Mary Shelley");
+        assert!(aggregate_authors(books(), None, 3, 2).is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+//! In-Memory Storage
+//!
+//! A [`StorageBackend`] kept entirely in the process, which the handler,
+//! service and snapshot tests run against instead of Redis or PostgreSQL.
+//! Besides the trait it has hooks to play the indexing service's part
+//! (marking books incomplete, deleting them) and, in tests and with the
+//! `test-util` feature, to simulate a failing or slow backend.
+//!
+//! The books and postings are kept in a [`MemoryIndex`], which the indexing
+//! service's memory backend can share (see [`MemoryBackend::with_index`]).
+
+use super::{
+    aggregate_authors, decade_buckets, intersect_passages, summarize_feedback, AuthorEntry, BookMetadata,
+    DecadeBucket, Feedback, FeedbackSummary, IndexField, Passage, QueryLogEntry, StorageBackend, StorageError,
+    QUERY_LOG_CAP,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common_index::MemoryIndex;
+use regex::Regex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
+#[cfg(any(test, feature = "test-util"))]
+use std::time::Duration;
+
+/// In-memory storage implementation.
+///
+/// Keeps everything in hash maps behind shared locks, so clones of the
+/// backend see the same data. Nothing is persisted.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    index: MemoryIndex,
+    state: Arc<RwLock<MemoryState>>,
+}
+
+/// What the search service keeps besides the index.
+#[derive(Default)]
+struct MemoryState {
+    feedback: Vec<Feedback>,
+    /// Oldest first.
+    query_log: VecDeque<QueryLogEntry>,
+    /// Set by [`MemoryBackend::drop_connection`] until reconnected.
+    #[cfg(any(test, feature = "test-util"))]
+    disconnected: bool,
+    /// Set by [`MemoryBackend::slow_down_word`].
+    #[cfg(any(test, feature = "test-util"))]
+    slow_words: HashMap<String, Duration>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A backend reading and writing `index`, which the indexing service's
+    /// memory backend may share. Feedback and the query log stay its own.
+    pub fn with_index(index: MemoryIndex) -> Self {
+        Self { index, ..Self::default() }
+    }
+
+    /// Marks a book as partly indexed, as the indexing service does while
+    /// it writes the book's postings.
+    pub fn set_book_incomplete(&self, book_id: u32, incomplete: bool) {
+        self.index.write().set_incomplete(book_id, incomplete);
+    }
+
+    /// Removes a book's metadata, as the indexing service does when a book
+    /// is deleted. Its postings stay until compaction.
+    pub fn delete_book(&self, book_id: u32) {
+        self.index.write().remove_book(book_id);
+    }
+
+    /// Makes connection tests fail until [`StorageBackend::reconnect`], as
+    /// a Redis connection does once the server restarts.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn drop_connection(&self) {
+        self.write().disconnected = true;
+    }
+
+    /// Makes every posting lookup of `word` take `delay`, like a huge
+    /// posting list on a loaded server.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn slow_down_word(&self, word: &str, delay: Duration) {
+        self.write().slow_words.insert(word.to_string(), delay);
+    }
+
+    /// Waits as long as the slowest of `words` takes to look up.
+    #[cfg(any(test, feature = "test-util"))]
+    async fn wait_for_postings<'a>(&self, words: impl IntoIterator<Item = &'a str>) {
+        let delay = {
+            let state = self.read();
+            words.into_iter().filter_map(|word| state.slow_words.get(word)).max().copied()
+        };
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Lookups are never slowed down outside tests.
+    #[cfg(not(any(test, feature = "test-util")))]
+    async fn wait_for_postings<'a>(&self, _words: impl IntoIterator<Item = &'a str>) {}
+
+    /// Records whether the index was built folding diacritics, as the
+    /// indexing service does.
+    pub fn set_diacritic_folding(&self, folding: bool) {
+        self.index.write().diacritic_folding = Some(folding);
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, MemoryState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, MemoryState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryBackend {
+    fn kind(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        self.index.write().store_book(metadata);
+        Ok(())
+    }
+
+    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
+        Ok(self.index.read().books.get(&book_id).cloned())
+    }
+
+    async fn get_book_metadatas(&self, book_ids: &[u32]) -> Result<Vec<BookMetadata>, StorageError> {
+        let state = self.index.read();
+        Ok(book_ids.iter().filter_map(|id| state.books.get(id).cloned()).collect())
+    }
+
+    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
+        Ok(self.index.read().books.contains_key(&book_id))
+    }
+
+    async fn is_book_incomplete(&self, book_id: u32) -> Result<bool, StorageError> {
+        Ok(self.index.read().incomplete.contains(&book_id))
+    }
+
+    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
+        Ok(self.index.read().books.keys().copied().collect())
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        self.index.write().add_postings(word, &[book_id], field);
+        Ok(())
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        self.wait_for_postings([word]).await;
+        Ok(self.index.read().words.get(word).cloned().unwrap_or_default())
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        if field.is_body() {
+            return self.search_word(word).await;
+        }
+        self.wait_for_postings([word]).await;
+        Ok(self.index.read().postings(word, field).cloned().unwrap_or_default())
+    }
+
+    async fn search_all_words(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        self.wait_for_postings(words.iter().map(String::as_str)).await;
+        let state = self.index.read();
+        let Some((first, rest)) = words.split_first() else {
+            return Ok(HashSet::new());
+        };
+
+        let mut intersection = state.words.get(first).cloned().unwrap_or_default();
+        for word in rest {
+            let books = state.words.get(word);
+            intersection.retain(|book_id| books.is_some_and(|books| books.contains(book_id)));
+        }
+        Ok(intersection)
+    }
+
+    async fn search_any_word(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        self.wait_for_postings(words.iter().map(String::as_str)).await;
+        let state = self.index.read();
+        Ok(words
+            .iter()
+            .filter_map(|word| state.words.get(word))
+            .flatten()
+            .copied()
+            .collect())
+    }
+
+    async fn get_word_doc_freq(&self, word: &str) -> Result<usize, StorageError> {
+        Ok(self.index.read().words.get(word).map_or(0, HashSet::len))
+    }
+
+    async fn filter_known_words(&self, words: &[String]) -> Result<Vec<String>, StorageError> {
+        let state = self.index.read();
+        Ok(words.iter().filter(|word| state.words.contains_key(*word)).cloned().collect())
+    }
+
+    async fn words_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .index
+            .read()
+            .words
+            .range(prefix.to_string()..)
+            .map(|(word, _)| word)
+            .take_while(|word| word.starts_with(prefix))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn words_matching(&self, field: IndexField, pattern: &Regex, limit: usize) -> Result<Vec<String>, StorageError> {
+        let state = self.index.read();
+        Ok(state.vocabulary(field).filter(|word| pattern.is_match(word)).take(limit).cloned().collect())
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        self.index.write()
+            .chapter_words
+            .entry(word.to_string())
+            .or_default()
+            .insert((book_id, chapter_no));
+        Ok(())
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.wait_for_postings([word]).await;
+        Ok(self.index.read().chapter_words.get(word).cloned().unwrap_or_default())
+    }
+
+    async fn search_word_chapters_in_books(
+        &self,
+        word: &str,
+        chapter_counts: &HashMap<u32, usize>,
+    ) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.wait_for_postings([word]).await;
+        let state = self.index.read();
+        let postings = state.chapter_words.get(word).into_iter().flatten();
+        Ok(postings.filter(|(book_id, _)| chapter_counts.contains_key(book_id)).copied().collect())
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        self.index.write()
+            .sentence_words
+            .entry(word.to_string())
+            .or_default()
+            .insert((book_id, sentence_id));
+        Ok(())
+    }
+
+    async fn search_passages(&self, words: &[String], book_id: Option<u32>) -> Result<Vec<Passage>, StorageError> {
+        let state = self.index.read();
+        let postings = words
+            .iter()
+            .map(|word| state.sentence_words.get(word).cloned().unwrap_or_default());
+        Ok(intersect_passages(postings, book_id))
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        let state = self.index.read();
+        Ok((state.books.len(), state.words.len()))
+    }
+
+    async fn list_authors(&self, prefix: Option<&str>, page: usize, per_page: usize) -> Result<Vec<AuthorEntry>, StorageError> {
+        let books = self
+            .index
+            .read()
+            .books
+            .values()
+            .map(|book| (book.author.clone(), book.book_id))
+            .collect::<Vec<_>>();
+        Ok(aggregate_authors(books, prefix, page, per_page))
+    }
+
+    async fn get_language_distribution(&self) -> Result<HashMap<String, usize>, StorageError> {
+        let mut distribution = HashMap::new();
+        for book in self.index.read().books.values() {
+            *distribution.entry(book.language.clone()).or_insert(0) += 1;
+        }
+        Ok(distribution)
+    }
+
+    async fn get_year_distribution(&self) -> Result<Vec<DecadeBucket>, StorageError> {
+        Ok(decade_buckets(self.index.read().books.values().map(|book| book.year)))
+    }
+
+    async fn get_word_count_for_book(&self, book_id: u32) -> Result<usize, StorageError> {
+        Ok(self.index.read().words.values().filter(|books| books.contains(&book_id)).count())
+    }
+
+    async fn get_words_for_book(&self, book_id: u32) -> Result<HashSet<String>, StorageError> {
+        Ok(self
+            .index
+            .read()
+            .words
+            .iter()
+            .filter(|(_, books)| books.contains(&book_id))
+            .map(|(word, _)| word.clone())
+            .collect())
+    }
+
+    async fn record_feedback(&self, feedback: &Feedback) -> Result<(), StorageError> {
+        self.write().feedback.push(feedback.clone());
+        Ok(())
+    }
+
+    async fn get_feedback_summary(&self, query: &str, top: usize) -> Result<FeedbackSummary, StorageError> {
+        let state = self.read();
+        Ok(summarize_feedback(state.feedback.iter().filter(|entry| entry.query == query), top))
+    }
+
+    async fn record_query(&self, entry: &QueryLogEntry) -> Result<(), StorageError> {
+        let mut state = self.write();
+        state.query_log.push_back(entry.clone());
+        if state.query_log.len() > QUERY_LOG_CAP {
+            state.query_log.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn get_query_log(&self, since: DateTime<Utc>) -> Result<Vec<QueryLogEntry>, StorageError> {
+        let state = self.read();
+        Ok(state.query_log.iter().rev().take_while(|entry| entry.created_at >= since).cloned().collect())
+    }
+
+    async fn get_index_version(&self) -> Result<String, StorageError> {
+        Ok(self.index.read().version.to_string())
+    }
+
+    async fn get_index_freshness(&self) -> Result<(usize, Option<DateTime<Utc>>), StorageError> {
+        let state = self.index.read();
+        Ok((state.books.len(), state.last_updated))
+    }
+
+    async fn get_recent_books(&self, limit: usize, since: Option<DateTime<Utc>>) -> Result<Vec<(u32, DateTime<Utc>)>, StorageError> {
+        let state = self.index.read();
+        let mut books: Vec<(u32, DateTime<Utc>)> = state
+            .indexed_at
+            .iter()
+            .filter(|(_, &indexed_at)| since.is_none_or(|since| indexed_at > since))
+            .map(|(&book_id, &indexed_at)| (book_id, indexed_at))
+            .collect();
+        books.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+        books.truncate(limit);
+        Ok(books)
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        Ok(self.index.read().diacritic_folding)
+    }
+
+    async fn test_connection(&self) -> Result<(), StorageError> {
+        #[cfg(any(test, feature = "test-util"))]
+        if self.read().disconnected {
+            return Err(StorageError::Connection("connection dropped".to_string()));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(test, feature = "test-util"))]
+    async fn reconnect(&self) -> Result<(), StorageError> {
+        self.write().disconnected = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+//! Sharded Storage
+//!
+//! Reads an index the indexing service spread over several backends. Each
+//! word lives on the one shard picked by consistent hashing on the word, so
+//! a single-word lookup touches one shard and a multi-word query combines
+//! per-word results here. Book metadata is replicated to every shard and
+//! read from the first one, as are the feedback store, the query log and the
+//! index version.
+//!
+//! Configured with `SHARD_URLS=redis://r1:6379,redis://r2:6379`, listing the
+//! same shards in the same order as the indexing service: a word's shard
+//! depends on the shard's position in the list.
+
+use super::{
+    intersect_passages, AuthorEntry, BookMetadata, DecadeBucket, Feedback, FeedbackSummary, IndexField, Passage,
+    QueryLogEntry, RedisBackend, StorageBackend, StorageError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common_redis::{RedisConfig, RedisMode};
+use hashring::HashRing;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+/// Points each shard gets on the hash ring. Must match the indexing service.
+const VIRTUAL_NODES_PER_SHARD: usize = 64;
+
+pub type Shard = Arc<dyn StorageBackend + Send + Sync>;
+
+/// A point on the hash ring belonging to shard `shard`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq)]
+struct VirtualNode {
+    shard: usize,
+    replica: usize,
+}
+
+/// A [`StorageBackend`] over several shards; see the module docs for what
+/// lives where.
+#[derive(Clone)]
+pub struct ShardedBackend {
+    shards: Vec<Shard>,
+    ring: Arc<HashRing<VirtualNode>>,
+}
+
+impl ShardedBackend {
+    /// # Panics
+    /// If `shards` is empty.
+    pub fn new(shards: Vec<Shard>) -> Self {
+        assert!(!shards.is_empty(), "a sharded backend needs at least one shard");
+        let mut ring = HashRing::new();
+        for shard in 0..shards.len() {
+            for replica in 0..VIRTUAL_NODES_PER_SHARD {
+                ring.add(VirtualNode { shard, replica });
+            }
+        }
+        Self {
+            shards,
+            ring: Arc::new(ring),
+        }
+    }
+
+    /// One standalone Redis backend per URL in `SHARD_URLS`, sharing the
+    /// `REDIS_*` credentials and TLS settings. `None` when it isn't set.
+    pub fn from_env() -> Option<Result<Self, StorageError>> {
+        let urls = std::env::var("SHARD_URLS").ok().filter(|urls| !urls.trim().is_empty())?;
+        let base = RedisConfig::from_env();
+        let shards = urls
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                let config = RedisConfig {
+                    nodes: vec![url.to_string()],
+                    mode: RedisMode::Standalone,
+                    ..base.clone()
+                };
+                Ok(Arc::new(RedisBackend::from_config(&config)?) as Shard)
+            })
+            .collect::<Result<Vec<_>, StorageError>>();
+        Some(shards.map(Self::new))
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Position of the shard holding `word`.
+    pub fn shard_index(&self, word: &str) -> usize {
+        self.ring.get(&word).map_or(0, |node| node.shard)
+    }
+
+    fn shard_for(&self, word: &str) -> &Shard {
+        &self.shards[self.shard_index(word)]
+    }
+
+    /// Where replicated data is read from.
+    fn primary(&self) -> &Shard {
+        &self.shards[0]
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ShardedBackend {
+    fn kind(&self) -> &'static str {
+        "sharded"
+    }
+
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.store_book_metadata(metadata).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
+        self.primary().get_book_metadata(book_id).await
+    }
+
+    async fn get_book_metadatas(&self, book_ids: &[u32]) -> Result<Vec<BookMetadata>, StorageError> {
+        self.primary().get_book_metadatas(book_ids).await
+    }
+
+    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
+        self.primary().is_book_indexed(book_id).await
+    }
+
+    async fn is_book_incomplete(&self, book_id: u32) -> Result<bool, StorageError> {
+        self.primary().is_book_incomplete(book_id).await
+    }
+
+    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
+        self.primary().get_indexed_books().await
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        self.shard_for(word).add_word_to_index(word, book_id, field).await
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        self.shard_for(word).search_word(word).await
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        self.shard_for(word).get_books_for_word_in_field(word, field).await
+    }
+
+    async fn search_all_words(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        let mut matched: Option<HashSet<u32>> = None;
+        for word in words {
+            let books = self.search_word(word).await?;
+            let books = match matched {
+                Some(matched) => matched.intersection(&books).copied().collect(),
+                None => books,
+            };
+            if books.is_empty() {
+                return Ok(books);
+            }
+            matched = Some(books);
+        }
+        Ok(matched.unwrap_or_default())
+    }
+
+    async fn search_any_word(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        let mut matched = HashSet::new();
+        for word in words {
+            matched.extend(self.search_word(word).await?);
+        }
+        Ok(matched)
+    }
+
+    async fn get_word_doc_freq(&self, word: &str) -> Result<usize, StorageError> {
+        self.shard_for(word).get_word_doc_freq(word).await
+    }
+
+    async fn filter_known_words(&self, words: &[String]) -> Result<Vec<String>, StorageError> {
+        let mut by_shard: HashMap<usize, Vec<String>> = HashMap::new();
+        for word in words {
+            by_shard.entry(self.shard_index(word)).or_default().push(word.clone());
+        }
+        let mut known = HashSet::new();
+        for (shard, words) in by_shard {
+            known.extend(self.shards[shard].filter_known_words(&words).await?);
+        }
+
+        Ok(words.iter().filter(|word| known.contains(*word)).cloned().collect())
+    }
+
+    async fn words_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<String>, StorageError> {
+        // A prefix's words are spread over every shard
+        let mut words = BTreeSet::new();
+        for shard in &self.shards {
+            words.extend(shard.words_with_prefix(prefix, limit).await?);
+        }
+        Ok(words.into_iter().take(limit).collect())
+    }
+
+    async fn words_matching(&self, field: IndexField, pattern: &Regex, limit: usize) -> Result<Vec<String>, StorageError> {
+        let mut words = HashSet::new();
+        for shard in &self.shards {
+            if words.len() >= limit {
+                break;
+            }
+            words.extend(shard.words_matching(field, pattern, limit - words.len()).await?);
+        }
+        Ok(words.into_iter().take(limit).collect())
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        self.shard_for(word).add_word_to_chapter_index(word, book_id, chapter_no).await
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.shard_for(word).search_word_chapters(word).await
+    }
+
+    async fn search_word_chapters_in_books(
+        &self,
+        word: &str,
+        chapter_counts: &HashMap<u32, usize>,
+    ) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.shard_for(word).search_word_chapters_in_books(word, chapter_counts).await
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        self.shard_for(word).add_word_to_sentence_index(word, book_id, sentence_id).await
+    }
+
+    /// Each word's sentences come from its own shard and are intersected here.
+    async fn search_passages(&self, words: &[String], book_id: Option<u32>) -> Result<Vec<Passage>, StorageError> {
+        let mut postings = Vec::with_capacity(words.len());
+        for word in words {
+            let passages = self.shard_for(word).search_passages(std::slice::from_ref(word), book_id).await?;
+            let empty = passages.is_empty();
+            postings.push(passages.into_iter().map(|p| (p.book_id, p.sentence_id)).collect());
+            if empty {
+                break;
+            }
+        }
+        Ok(intersect_passages(postings, book_id))
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        let (total_books, mut unique_words) = self.primary().get_stats().await?;
+        for shard in &self.shards[1..] {
+            unique_words += shard.get_stats().await?.1;
+        }
+        Ok((total_books, unique_words))
+    }
+
+    async fn list_authors(&self, prefix: Option<&str>, page: usize, per_page: usize) -> Result<Vec<AuthorEntry>, StorageError> {
+        self.primary().list_authors(prefix, page, per_page).await
+    }
+
+    async fn get_language_distribution(&self) -> Result<HashMap<String, usize>, StorageError> {
+        self.primary().get_language_distribution().await
+    }
+
+    async fn get_year_distribution(&self) -> Result<Vec<DecadeBucket>, StorageError> {
+        self.primary().get_year_distribution().await
+    }
+
+    async fn get_word_count_for_book(&self, book_id: u32) -> Result<usize, StorageError> {
+        Ok(self.get_words_for_book(book_id).await?.len())
+    }
+
+    async fn get_words_for_book(&self, book_id: u32) -> Result<HashSet<String>, StorageError> {
+        let mut words = HashSet::new();
+        for shard in &self.shards {
+            words.extend(shard.get_words_for_book(book_id).await?);
+        }
+        Ok(words)
+    }
+
+    async fn record_feedback(&self, feedback: &Feedback) -> Result<(), StorageError> {
+        self.primary().record_feedback(feedback).await
+    }
+
+    async fn get_feedback_summary(&self, query: &str, top: usize) -> Result<FeedbackSummary, StorageError> {
+        self.primary().get_feedback_summary(query, top).await
+    }
+
+    async fn record_query(&self, entry: &QueryLogEntry) -> Result<(), StorageError> {
+        self.primary().record_query(entry).await
+    }
+
+    async fn get_query_log(&self, since: DateTime<Utc>) -> Result<Vec<QueryLogEntry>, StorageError> {
+        self.primary().get_query_log(since).await
+    }
+
+    async fn get_index_version(&self) -> Result<String, StorageError> {
+        self.primary().get_index_version().await
+    }
+
+    async fn get_index_freshness(&self) -> Result<(usize, Option<DateTime<Utc>>), StorageError> {
+        self.primary().get_index_freshness().await
+    }
+
+    async fn get_recent_books(&self, limit: usize, since: Option<DateTime<Utc>>) -> Result<Vec<(u32, DateTime<Utc>)>, StorageError> {
+        self.primary().get_recent_books(limit, since).await
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        self.primary().get_diacritic_folding().await
+    }
+
+    async fn test_connection(&self) -> Result<(), StorageError> {
+        for shard in &self.shards {
+            shard.test_connection().await?;
+        }
+        Ok(())
+    }
+
+    async fn reconnect(&self) -> Result<(), StorageError> {
+        // Every shard is reconnected even if an earlier one is still down
+        let mut result = Ok(());
+        for shard in &self.shards {
+            if let Err(e) = shard.reconnect().await {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+
+    fn three_shards() -> (ShardedBackend, Vec<MemoryBackend>) {
+        let memories = vec![MemoryBackend::new(), MemoryBackend::new(), MemoryBackend::new()];
+        let shards = memories.iter().map(|memory| Arc::new(memory.clone()) as Shard).collect();
+        (ShardedBackend::new(shards), memories)
+    }
+
+    fn metadata(book_id: u32) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: format!("Book {}", book_id),
+            author: "Herman Melville".to_string(),
+            language: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    const WORDS: [&str; 8] = ["whale", "ship", "ocean", "harpoon", "captain", "sailor", "island", "storm"];
+
+    #[tokio::test]
+    async fn routes_each_word_to_one_shard() {
+        let (sharded, memories) = three_shards();
+        for word in WORDS {
+            sharded.add_word_to_index(word, 2701, IndexField::Body).await.unwrap();
+        }
+
+        for word in WORDS {
+            let owner = sharded.shard_index(word);
+            for (i, memory) in memories.iter().enumerate() {
+                let books = memory.search_word(word).await.unwrap();
+                assert_eq!(books.contains(&2701), i == owner, "'{}' on shard {}", word, i);
+            }
+            assert_eq!(sharded.search_word(word).await.unwrap(), HashSet::from([2701]));
+        }
+        assert_eq!(sharded.get_words_for_book(2701).await.unwrap().len(), WORDS.len());
+    }
+
+    /// The indexing service pins the same placements; if these change, the
+    /// two services no longer agree on where a word lives.
+    #[test]
+    fn placement_matches_the_indexing_service() {
+        let (sharded, _) = three_shards();
+        let placement: Vec<usize> = WORDS.iter().map(|word| sharded.shard_index(word)).collect();
+        assert_eq!(placement, [0, 0, 1, 1, 1, 0, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn combines_words_from_different_shards() {
+        let (sharded, _) = three_shards();
+        // Two words known to hash to different shards
+        let (a, b) = WORDS
+            .iter()
+            .flat_map(|a| WORDS.iter().map(move |b| (*a, *b)))
+            .find(|(a, b)| sharded.shard_index(a) != sharded.shard_index(b))
+            .unwrap();
+        sharded.add_word_to_index(a, 1, IndexField::Body).await.unwrap();
+        sharded.add_word_to_index(a, 2, IndexField::Body).await.unwrap();
+        sharded.add_word_to_index(b, 2, IndexField::Body).await.unwrap();
+        sharded.add_word_to_index(b, 3, IndexField::Body).await.unwrap();
+        let words = [a.to_string(), b.to_string()];
+
+        assert_eq!(sharded.search_all_words(&words).await.unwrap(), HashSet::from([2]));
+        assert_eq!(sharded.search_any_word(&words).await.unwrap(), HashSet::from([1, 2, 3]));
+        assert!(sharded.search_all_words(&[]).await.unwrap().is_empty());
+
+        sharded.add_word_to_sentence_index(a, 2, 7).await.unwrap();
+        sharded.add_word_to_sentence_index(a, 2, 8).await.unwrap();
+        sharded.add_word_to_sentence_index(b, 2, 8).await.unwrap();
+        sharded.add_word_to_sentence_index(b, 3, 8).await.unwrap();
+        assert_eq!(
+            sharded.search_passages(&words, None).await.unwrap(),
+            [Passage { book_id: 2, sentence_id: 8 }]
+        );
+        assert!(sharded.search_passages(&words, Some(3)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reads_replicated_metadata() {
+        let (sharded, memories) = three_shards();
+        sharded.store_book_metadata(&metadata(2701)).await.unwrap();
+
+        for memory in &memories {
+            assert!(memory.is_book_indexed(2701).await.unwrap());
+        }
+        let books = sharded.get_book_metadatas(&[2701, 1]).await.unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].title, "Book 2701");
+    }
+}
@@ -0,0 +1,733 @@
+//! In-Memory Index Snapshot
+//!
+//! With `SEARCH_SNAPSHOT=on`, queries are answered from a copy of the index
+//! held in memory instead of a round trip to the backend per lookup. The
+//! snapshot holds the vocabulary, the body, title and author postings and
+//! every book's metadata; chapters, passages, the feedback store, the query
+//! log and writes still go to the backend, which stays the source each
+//! refresh reads from.
+//!
+//! A refresh builds a whole new snapshot and swaps it in at once, so a query
+//! sees either the old snapshot or the new one, never a mix. Refreshes run
+//! at startup, every `SNAPSHOT_REFRESH_SECS` and on `POST /snapshot/refresh`;
+//! one that finds the index version and the time of the index's last change
+//! both unchanged keeps the snapshot. The version only moves with book
+//! metadata, while postings are written after it, so the time of the last
+//! change is what catches a book finished since the snapshot was read.
+//! Until the first refresh succeeds, queries go to the backend. Cached search
+//! results are dropped whenever a new snapshot is swapped in.
+//!
+//! Loading takes a round trip per word and field, so corpora with more than
+//! `SNAPSHOT_MAX_WORDS` words are refused and keep being served from the
+//! backend. An index that grows past the limit after a snapshot loaded drops
+//! that snapshot, and the cached results with it, instead of serving a copy
+//! no refresh can update any more.
+//!
+//! ## Environment Variables
+//! - `SEARCH_SNAPSHOT`: `on` to serve queries from memory (default: off)
+//! - `SNAPSHOT_REFRESH_SECS`: seconds between refreshes (default: 60)
+//! - `SNAPSHOT_MAX_WORDS`: largest vocabulary loaded (default: 200000)
+
+use super::{
+    aggregate_authors, decade_buckets, AuthorEntry, BookMetadata, DecadeBucket, Feedback, FeedbackSummary,
+    IndexField, Passage, QueryLogEntry, StorageBackend, StorageError,
+};
+use crate::models::responses::SnapshotStatus;
+use crate::Backend;
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+const DEFAULT_REFRESH_SECS: u64 = 60;
+const DEFAULT_MAX_WORDS: usize = 200_000;
+
+/// Words whose postings are fetched at once while loading.
+const LOAD_CONCURRENCY: usize = 32;
+
+/// The fields with postings of their own besides the body's.
+const FIELDS: [IndexField; 2] = [IndexField::Title, IndexField::Author];
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("SEARCH_SNAPSHOT is not on")]
+    NotEnabled,
+    #[error("The index has more than {max} words (SNAPSHOT_MAX_WORDS); serving queries from the backend")]
+    TooLarge { max: usize },
+    #[error("Loading the snapshot failed: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// The index as it was at one refresh. Never changed once built.
+pub struct IndexSnapshot {
+    books: HashMap<u32, BookMetadata>,
+    words: HashMap<String, HashSet<u32>>,
+    /// The keys of `words` in order, for prefix lookups.
+    vocabulary: BTreeSet<String>,
+    field_words: HashMap<(IndexField, String), HashSet<u32>>,
+    version: String,
+    /// The backend's time of the last index change when the snapshot was read.
+    last_updated: Option<DateTime<Utc>>,
+    loaded_at: DateTime<Utc>,
+    memory_bytes: usize,
+}
+
+impl IndexSnapshot {
+    pub fn books(&self) -> usize {
+        self.books.len()
+    }
+
+    pub fn words(&self) -> usize {
+        self.words.len()
+    }
+
+    /// The backend's index version when the snapshot was read.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn loaded_at(&self) -> DateTime<Utc> {
+        self.loaded_at
+    }
+
+    /// Rough size of the snapshot's data, hash table overhead included.
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_bytes
+    }
+
+    fn postings(&self, word: &str) -> Option<&HashSet<u32>> {
+        self.words.get(word)
+    }
+}
+
+/// Approximate heap use of a posting list and its key.
+fn postings_bytes(word: &str, books: &HashSet<u32>) -> usize {
+    // A hash table slot per book id plus a control byte, and the map entry
+    word.len() + books.capacity() * (std::mem::size_of::<u32>() + 1) + 2 * std::mem::size_of::<usize>() * 3
+}
+
+fn estimate_memory(
+    books: &HashMap<u32, BookMetadata>,
+    words: &HashMap<String, HashSet<u32>>,
+    field_words: &HashMap<(IndexField, String), HashSet<u32>>,
+) -> usize {
+    let metadata: usize = books
+        .values()
+        .map(|book| {
+            std::mem::size_of::<BookMetadata>()
+                + book.title.len()
+                + book.author.len()
+                + book.language.len()
+                + book.subjects.iter().map(|subject| subject.len() + 24).sum::<usize>()
+        })
+        .sum();
+    let postings: usize = words.iter().map(|(word, books)| postings_bytes(word, books)).sum();
+    let fields: usize = field_words.iter().map(|((_, word), books)| postings_bytes(word, books)).sum();
+    // The vocabulary's copy of each word, in a B-tree node
+    let vocabulary: usize = words.keys().map(|word| word.len() + 32).sum();
+    metadata + postings + fields + vocabulary
+}
+
+/// How the snapshot is configured.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotConfig {
+    pub refresh_interval: Duration,
+    pub max_words: usize,
+}
+
+impl SnapshotConfig {
+    /// `None` unless `SEARCH_SNAPSHOT` is on.
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("SEARCH_SNAPSHOT")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "on" | "true" | "1"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let parse = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Some(Self {
+            refresh_interval: Duration::from_secs(parse("SNAPSHOT_REFRESH_SECS").unwrap_or(DEFAULT_REFRESH_SECS).max(1)),
+            max_words: parse("SNAPSHOT_MAX_WORDS").map_or(DEFAULT_MAX_WORDS, |max| max as usize),
+        })
+    }
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(DEFAULT_REFRESH_SECS),
+            max_words: DEFAULT_MAX_WORDS,
+        }
+    }
+}
+
+/// A [`StorageBackend`] answering queries from an [`IndexSnapshot`] of
+/// `inner`; see the module docs for what is read from where. Clones share
+/// the snapshot.
+#[derive(Clone)]
+pub struct SnapshotBackend {
+    inner: Backend,
+    config: SnapshotConfig,
+    snapshot: Arc<ArcSwapOption<IndexSnapshot>>,
+    /// Held while refreshing, so overlapping refreshes don't load twice.
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl SnapshotBackend {
+    /// Wraps `inner` without loading anything yet.
+    pub fn new(inner: Backend, config: SnapshotConfig) -> Self {
+        Self {
+            inner,
+            config,
+            snapshot: Arc::new(ArcSwapOption::empty()),
+            refreshing: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// The snapshot queries are answered from, if one has loaded.
+    pub fn current(&self) -> Option<Arc<IndexSnapshot>> {
+        self.snapshot.load_full()
+    }
+
+    /// What `/status` reports about the current snapshot.
+    pub fn status(&self) -> SnapshotStatus {
+        let current = self.current();
+        SnapshotStatus {
+            loaded: current.is_some(),
+            books: current.as_ref().map_or(0, |snapshot| snapshot.books()),
+            words: current.as_ref().map_or(0, |snapshot| snapshot.words()),
+            memory_bytes: current.as_ref().map_or(0, |snapshot| snapshot.memory_bytes()),
+            index_version: current.as_ref().map(|snapshot| snapshot.version.clone()),
+            loaded_at: current.as_ref().map(|snapshot| snapshot.loaded_at),
+        }
+    }
+
+    /// Loads a new snapshot from the backend and swaps it in, unless neither
+    /// the index version nor the time of the last change moved since the
+    /// current one. On failure the current snapshot stays, except when the
+    /// index outgrew `SNAPSHOT_MAX_WORDS`: then it is dropped and queries go
+    /// to the backend.
+    pub async fn refresh(&self) -> Result<Arc<IndexSnapshot>, SnapshotError> {
+        let _refreshing = self.refreshing.lock().await;
+        // Read before loading, so a write landing during the load makes the
+        // next refresh load again
+        let version = self.inner.get_index_version().await?;
+        let (_, last_updated) = self.inner.get_index_freshness().await?;
+        if let Some(current) = self
+            .current()
+            .filter(|current| current.version == version && current.last_updated == last_updated)
+        {
+            return Ok(current);
+        }
+
+        let snapshot = match self.load(version, last_updated).await {
+            Ok(snapshot) => Arc::new(snapshot),
+            Err(e @ SnapshotError::TooLarge { .. }) => {
+                self.snapshot.store(None);
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+        self.snapshot.store(Some(snapshot.clone()));
+        Ok(snapshot)
+    }
+
+    async fn load(&self, version: String, last_updated: Option<DateTime<Utc>>) -> Result<IndexSnapshot, SnapshotError> {
+        let max = self.config.max_words;
+        let (_, unique_words) = self.inner.get_stats().await?;
+        if unique_words > max {
+            return Err(SnapshotError::TooLarge { max });
+        }
+        // The count may lag the vocabulary, so the listing is checked too
+        let vocabulary = self.inner.words_with_prefix("", max.saturating_add(1)).await?;
+        // Author words are only listed in the author field's vocabulary
+        let any_word = Regex::new("").expect("the empty pattern is valid");
+        let mut author_only = self
+            .inner
+            .words_matching(IndexField::Author, &any_word, max.saturating_add(1))
+            .await?;
+        author_only.retain(|word| vocabulary.binary_search(word).is_err());
+        if vocabulary.len() + author_only.len() > max {
+            return Err(SnapshotError::TooLarge { max });
+        }
+
+        let to_load: Vec<(String, bool)> = vocabulary
+            .into_iter()
+            .map(|word| (word, true))
+            .chain(author_only.into_iter().map(|word| (word, false)))
+            .collect();
+        let mut words = HashMap::new();
+        let mut field_words = HashMap::new();
+        for chunk in to_load.chunks(LOAD_CONCURRENCY) {
+            let mut lookups = tokio::task::JoinSet::new();
+            for (word, unscoped) in chunk {
+                let (inner, word, unscoped) = (self.inner.clone(), word.clone(), *unscoped);
+                lookups.spawn(async move {
+                    let (body, fields): (_, &[IndexField]) = match unscoped {
+                        true => (Some(inner.search_word(&word).await?), &FIELDS),
+                        false => (None, &[IndexField::Author]),
+                    };
+                    let mut postings = Vec::with_capacity(fields.len());
+                    for &field in fields {
+                        postings.push((field, inner.get_books_for_word_in_field(&word, field).await?));
+                    }
+                    Ok::<_, StorageError>((word, body, postings))
+                });
+            }
+            while let Some(loaded) = lookups.join_next().await {
+                let (word, body, fields) =
+                    loaded.map_err(|e| StorageError::Connection(format!("loading postings failed: {}", e)))??;
+                for (field, books) in fields.into_iter().filter(|(_, books)| !books.is_empty()) {
+                    field_words.insert((field, word.clone()), books);
+                }
+                if let Some(body) = body {
+                    words.insert(word, body);
+                }
+            }
+        }
+
+        let book_ids: Vec<u32> = self.inner.get_indexed_books().await?.into_iter().collect();
+        let books: HashMap<u32, BookMetadata> = self
+            .inner
+            .get_book_metadatas(&book_ids)
+            .await?
+            .into_iter()
+            .map(|book| (book.book_id, book))
+            .collect();
+
+        let memory_bytes = estimate_memory(&books, &words, &field_words);
+        Ok(IndexSnapshot {
+            vocabulary: words.keys().cloned().collect(),
+            books,
+            words,
+            field_words,
+            version,
+            last_updated,
+            loaded_at: Utc::now(),
+            memory_bytes,
+        })
+    }
+
+    /// Refreshes now and then every configured interval for as long as the
+    /// runtime runs, calling `on_swap` whenever a new snapshot replaced the
+    /// last or the last was dropped.
+    pub fn spawn_refresh(&self, on_swap: impl Fn() + Send + 'static) {
+        let snapshot = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(snapshot.config.refresh_interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                if snapshot.refresh_logged().await {
+                    on_swap();
+                }
+            }
+        });
+    }
+
+    /// Refreshes, logging the outcome. Returns whether the snapshot queries
+    /// are answered from changed.
+    async fn refresh_logged(&self) -> bool {
+        let previous = self.current();
+        match self.refresh().await {
+            Ok(loaded) if previous.as_ref().is_some_and(|previous| Arc::ptr_eq(previous, &loaded)) => false,
+            Ok(loaded) => {
+                info!(
+                    "Loaded index snapshot: {} books, {} words, ~{} bytes",
+                    loaded.books(),
+                    loaded.words(),
+                    loaded.memory_bytes()
+                );
+                true
+            }
+            Err(e @ SnapshotError::TooLarge { .. }) => {
+                error!("Not snapshotting the index: {}", e);
+                previous.is_some()
+            }
+            Err(e) => {
+                warn!("Index snapshot refresh failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for SnapshotBackend {
+    fn kind(&self) -> &'static str {
+        self.inner.kind()
+    }
+
+    async fn store_book_metadata(&self, metadata: &BookMetadata) -> Result<(), StorageError> {
+        self.inner.store_book_metadata(metadata).await
+    }
+
+    async fn get_book_metadata(&self, book_id: u32) -> Result<Option<BookMetadata>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.books.get(&book_id).cloned()),
+            None => self.inner.get_book_metadata(book_id).await,
+        }
+    }
+
+    async fn get_book_metadatas(&self, book_ids: &[u32]) -> Result<Vec<BookMetadata>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(book_ids.iter().filter_map(|id| snapshot.books.get(id).cloned()).collect()),
+            None => self.inner.get_book_metadatas(book_ids).await,
+        }
+    }
+
+    async fn is_book_indexed(&self, book_id: u32) -> Result<bool, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.books.contains_key(&book_id)),
+            None => self.inner.is_book_indexed(book_id).await,
+        }
+    }
+
+    async fn is_book_incomplete(&self, book_id: u32) -> Result<bool, StorageError> {
+        self.inner.is_book_incomplete(book_id).await
+    }
+
+    async fn get_indexed_books(&self) -> Result<HashSet<u32>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.books.keys().copied().collect()),
+            None => self.inner.get_indexed_books().await,
+        }
+    }
+
+    async fn add_word_to_index(&self, word: &str, book_id: u32, field: IndexField) -> Result<(), StorageError> {
+        self.inner.add_word_to_index(word, book_id, field).await
+    }
+
+    async fn search_word(&self, word: &str) -> Result<HashSet<u32>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.postings(word).cloned().unwrap_or_default()),
+            None => self.inner.search_word(word).await,
+        }
+    }
+
+    async fn get_books_for_word_in_field(&self, word: &str, field: IndexField) -> Result<HashSet<u32>, StorageError> {
+        if field.is_body() {
+            return self.search_word(word).await;
+        }
+        match self.current() {
+            Some(snapshot) => Ok(snapshot
+                .field_words
+                .get(&(field, word.to_string()))
+                .cloned()
+                .unwrap_or_default()),
+            None => self.inner.get_books_for_word_in_field(word, field).await,
+        }
+    }
+
+    async fn search_all_words(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        let Some(snapshot) = self.current() else {
+            return self.inner.search_all_words(words).await;
+        };
+        // Intersecting from the rarest word keeps the working set small
+        let mut postings: Vec<Option<&HashSet<u32>>> = words.iter().map(|word| snapshot.postings(word)).collect();
+        postings.sort_by_key(|books| books.map_or(0, HashSet::len));
+        let Some((first, rest)) = postings.split_first() else {
+            return Ok(HashSet::new());
+        };
+
+        let mut intersection = first.cloned().unwrap_or_default();
+        for books in rest {
+            intersection.retain(|book_id| books.is_some_and(|books| books.contains(book_id)));
+        }
+        Ok(intersection)
+    }
+
+    async fn search_any_word(&self, words: &[String]) -> Result<HashSet<u32>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(words
+                .iter()
+                .filter_map(|word| snapshot.postings(word))
+                .flatten()
+                .copied()
+                .collect()),
+            None => self.inner.search_any_word(words).await,
+        }
+    }
+
+    async fn get_word_doc_freq(&self, word: &str) -> Result<usize, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.postings(word).map_or(0, HashSet::len)),
+            None => self.inner.get_word_doc_freq(word).await,
+        }
+    }
+
+    async fn filter_known_words(&self, words: &[String]) -> Result<Vec<String>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(words.iter().filter(|word| snapshot.words.contains_key(*word)).cloned().collect()),
+            None => self.inner.filter_known_words(words).await,
+        }
+    }
+
+    async fn words_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<String>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot
+                .vocabulary
+                .range(prefix.to_string()..)
+                .take_while(|word| word.starts_with(prefix))
+                .take(limit)
+                .cloned()
+                .collect()),
+            None => self.inner.words_with_prefix(prefix, limit).await,
+        }
+    }
+
+    async fn words_matching(&self, field: IndexField, pattern: &Regex, limit: usize) -> Result<Vec<String>, StorageError> {
+        match self.current() {
+            Some(snapshot) => {
+                let vocabulary: Box<dyn Iterator<Item = &String>> = match field {
+                    IndexField::Body => Box::new(snapshot.vocabulary.iter()),
+                    field => Box::new(snapshot.field_words.keys().filter(move |(f, _)| *f == field).map(|(_, word)| word)),
+                };
+                Ok(vocabulary.filter(|word| pattern.is_match(word)).take(limit).cloned().collect())
+            }
+            None => self.inner.words_matching(field, pattern, limit).await,
+        }
+    }
+
+    async fn add_word_to_chapter_index(&self, word: &str, book_id: u32, chapter_no: usize) -> Result<(), StorageError> {
+        self.inner.add_word_to_chapter_index(word, book_id, chapter_no).await
+    }
+
+    async fn search_word_chapters(&self, word: &str) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.inner.search_word_chapters(word).await
+    }
+
+    async fn search_word_chapters_in_books(
+        &self,
+        word: &str,
+        chapter_counts: &HashMap<u32, usize>,
+    ) -> Result<HashSet<(u32, usize)>, StorageError> {
+        self.inner.search_word_chapters_in_books(word, chapter_counts).await
+    }
+
+    async fn add_word_to_sentence_index(&self, word: &str, book_id: u32, sentence_id: usize) -> Result<(), StorageError> {
+        self.inner.add_word_to_sentence_index(word, book_id, sentence_id).await
+    }
+
+    async fn search_passages(&self, words: &[String], book_id: Option<u32>) -> Result<Vec<Passage>, StorageError> {
+        self.inner.search_passages(words, book_id).await
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize), StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok((snapshot.books(), snapshot.words())),
+            None => self.inner.get_stats().await,
+        }
+    }
+
+    async fn list_authors(&self, prefix: Option<&str>, page: usize, per_page: usize) -> Result<Vec<AuthorEntry>, StorageError> {
+        let Some(snapshot) = self.current() else {
+            return self.inner.list_authors(prefix, page, per_page).await;
+        };
+        let books = snapshot.books.values().map(|book| (book.author.clone(), book.book_id)).collect::<Vec<_>>();
+        Ok(aggregate_authors(books, prefix, page, per_page))
+    }
+
+    async fn get_language_distribution(&self) -> Result<HashMap<String, usize>, StorageError> {
+        let Some(snapshot) = self.current() else {
+            return self.inner.get_language_distribution().await;
+        };
+        let mut distribution = HashMap::new();
+        for book in snapshot.books.values() {
+            *distribution.entry(book.language.clone()).or_insert(0) += 1;
+        }
+        Ok(distribution)
+    }
+
+    async fn get_year_distribution(&self) -> Result<Vec<DecadeBucket>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(decade_buckets(snapshot.books.values().map(|book| book.year))),
+            None => self.inner.get_year_distribution().await,
+        }
+    }
+
+    async fn get_word_count_for_book(&self, book_id: u32) -> Result<usize, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.words.values().filter(|books| books.contains(&book_id)).count()),
+            None => self.inner.get_word_count_for_book(book_id).await,
+        }
+    }
+
+    async fn get_words_for_book(&self, book_id: u32) -> Result<HashSet<String>, StorageError> {
+        match self.current() {
+            Some(snapshot) => Ok(snapshot
+                .words
+                .iter()
+                .filter(|(_, books)| books.contains(&book_id))
+                .map(|(word, _)| word.clone())
+                .collect()),
+            None => self.inner.get_words_for_book(book_id).await,
+        }
+    }
+
+    async fn record_feedback(&self, feedback: &Feedback) -> Result<(), StorageError> {
+        self.inner.record_feedback(feedback).await
+    }
+
+    async fn get_feedback_summary(&self, query: &str, top: usize) -> Result<FeedbackSummary, StorageError> {
+        self.inner.get_feedback_summary(query, top).await
+    }
+
+    async fn record_query(&self, entry: &QueryLogEntry) -> Result<(), StorageError> {
+        self.inner.record_query(entry).await
+    }
+
+    async fn get_query_log(&self, since: DateTime<Utc>) -> Result<Vec<QueryLogEntry>, StorageError> {
+        self.inner.get_query_log(since).await
+    }
+
+    async fn get_index_version(&self) -> Result<String, StorageError> {
+        // Results cached under this version come from the snapshot, so they
+        // stay valid until it is replaced
+        match self.current() {
+            Some(snapshot) => Ok(snapshot.version.clone()),
+            None => self.inner.get_index_version().await,
+        }
+    }
+
+    async fn get_index_freshness(&self) -> Result<(usize, Option<DateTime<Utc>>), StorageError> {
+        self.inner.get_index_freshness().await
+    }
+
+    async fn get_recent_books(&self, limit: usize, since: Option<DateTime<Utc>>) -> Result<Vec<(u32, DateTime<Utc>)>, StorageError> {
+        self.inner.get_recent_books(limit, since).await
+    }
+
+    async fn get_diacritic_folding(&self) -> Result<Option<bool>, StorageError> {
+        self.inner.get_diacritic_folding().await
+    }
+
+    async fn test_connection(&self) -> Result<(), StorageError> {
+        self.inner.test_connection().await
+    }
+
+    async fn reconnect(&self) -> Result<(), StorageError> {
+        self.inner.reconnect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+
+    fn book(book_id: u32, title: &str) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: title.to_string(),
+            author: "Herman Melville".to_string(),
+            language: "en".to_string(),
+            year: Some(1851),
+            word_count: 3,
+            unique_words: 3,
+            ..Default::default()
+        }
+    }
+
+    async fn library() -> MemoryBackend {
+        let memory = MemoryBackend::new();
+        for (book_id, title, words) in [(1, "Moby Dick", ["whale", "sea", "ship"]), (2, "Typee", ["island", "sea", "valley"])] {
+            memory.store_book_metadata(&book(book_id, title)).await.unwrap();
+            for word in words {
+                memory.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        memory.add_word_to_index("moby", 1, IndexField::Title).await.unwrap();
+        memory.add_word_to_index("melville", 1, IndexField::Author).await.unwrap();
+        memory
+    }
+
+    #[tokio::test]
+    async fn answers_from_memory_until_the_next_refresh() {
+        let memory = library().await;
+        let snapshot = SnapshotBackend::new(Arc::new(memory.clone()), SnapshotConfig::default());
+        let loaded = snapshot.refresh().await.unwrap();
+        assert_eq!((loaded.books(), loaded.words()), (2, 6));
+        assert!(loaded.memory_bytes() > 0);
+
+        memory.add_word_to_index("whale", 2, IndexField::Body).await.unwrap();
+        memory.store_book_metadata(&book(3, "Omoo")).await.unwrap();
+        assert_eq!(snapshot.search_word("whale").await.unwrap(), HashSet::from([1]));
+        assert_eq!(snapshot.get_books_for_word_in_field("moby", IndexField::Title).await.unwrap(), HashSet::from([1]));
+        // Author words load from their own vocabulary
+        assert_eq!(snapshot.get_books_for_word_in_field("melville", IndexField::Author).await.unwrap(), HashSet::from([1]));
+        assert!(snapshot.search_word("melville").await.unwrap().is_empty());
+        assert_eq!(snapshot.search_all_words(&["sea".into(), "ship".into()]).await.unwrap(), HashSet::from([1]));
+        assert_eq!(snapshot.words_with_prefix("s", 10).await.unwrap(), ["sea", "ship"]);
+        assert!(!snapshot.is_book_indexed(3).await.unwrap());
+
+        let reloaded = snapshot.refresh().await.unwrap();
+        assert_ne!(reloaded.version(), loaded.version());
+        assert_eq!(snapshot.search_word("whale").await.unwrap(), HashSet::from([1, 2]));
+        assert!(snapshot.is_book_indexed(3).await.unwrap());
+        assert!(Arc::ptr_eq(&snapshot.refresh().await.unwrap(), &reloaded), "unchanged index reloaded");
+    }
+
+    #[tokio::test]
+    async fn reloads_postings_written_after_the_metadata() {
+        let memory = library().await;
+        let snapshot = SnapshotBackend::new(Arc::new(memory.clone()), SnapshotConfig::default());
+
+        // The indexing service stores a book's metadata, which bumps the
+        // version, before its postings; a refresh in between sees the book
+        // without its words
+        memory.set_book_incomplete(3, true);
+        memory.store_book_metadata(&book(3, "Omoo")).await.unwrap();
+        let partial = snapshot.refresh().await.unwrap();
+        assert!(snapshot.search_word("tattoo").await.unwrap().is_empty());
+
+        memory.add_word_to_index("tattoo", 3, IndexField::Body).await.unwrap();
+        memory.set_book_incomplete(3, false);
+        let complete = snapshot.refresh().await.unwrap();
+        assert_eq!(complete.version(), partial.version());
+        assert!(!Arc::ptr_eq(&complete, &partial), "finished book not reloaded");
+        assert_eq!(snapshot.search_word("tattoo").await.unwrap(), HashSet::from([3]));
+    }
+
+    #[tokio::test]
+    async fn refuses_corpora_above_the_word_limit() {
+        let memory = library().await;
+        let config = SnapshotConfig {
+            max_words: 5,
+            ..SnapshotConfig::default()
+        };
+        let snapshot = SnapshotBackend::new(Arc::new(memory), config);
+
+        assert!(matches!(snapshot.refresh().await, Err(SnapshotError::TooLarge { max: 5 })));
+        assert!(snapshot.current().is_none());
+        assert_eq!(snapshot.search_word("sea").await.unwrap(), HashSet::from([1, 2]));
+    }
+
+    #[tokio::test]
+    async fn drops_the_snapshot_once_the_index_outgrows_the_limit() {
+        let memory = library().await;
+        let config = SnapshotConfig {
+            max_words: 7,
+            ..SnapshotConfig::default()
+        };
+        let snapshot = SnapshotBackend::new(Arc::new(memory.clone()), config);
+        snapshot.refresh().await.unwrap();
+
+        memory.store_book_metadata(&book(3, "Omoo")).await.unwrap();
+        for word in ["tattoo", "canoe"] {
+            memory.add_word_to_index(word, 3, IndexField::Body).await.unwrap();
+        }
+        // Dropping the snapshot counts as a swap, so cached results go too
+        assert!(snapshot.refresh_logged().await);
+        assert!(snapshot.current().is_none());
+        assert_eq!(snapshot.search_word("tattoo").await.unwrap(), HashSet::from([3]));
+
+        assert!(matches!(snapshot.refresh().await, Err(SnapshotError::TooLarge { max: 7 })));
+        assert!(!snapshot.refresh_logged().await);
+    }
+}
@@ -0,0 +1,790 @@
+//! Browse Endpoints
+//!
+//! Lets clients explore the indexed library without a search query.
+//!
+//! **GET /search/authors?prefix=&page=&per_page=**
+//! → Returns distinct authors, alphabetically, with their books.
+//!
+//! **GET /search/languages**
+//! → Returns the number of books per language, most common first.
+//!
+//! **GET /search/years**
+//! → Returns the number of books per publication decade, chronologically.
+//!
+//! **GET /search/book/:book_id**
+//! → Returns one book's metadata, or `404` if it isn't indexed.
+//!
+//! **GET /search/book/:book_id/related?n=**
+//! → Returns the books sharing the most indexed words with it.
+//!
+//! **GET /search/book/:book_id/chapters?q=**
+//! → Returns the chapters holding any word of `q`, in order, with their
+//!   headings and number of matches. Only books indexed with
+//!   `ENABLE_CHAPTER_INDEX=true` have chapter postings.
+//!
+//! **GET /books/recent?limit=&since=**
+//! → Returns the most recently indexed books, newest first, with when each
+//!   was indexed. `since` (RFC 3339) keeps only books indexed after it.
+//!
+//! **GET /books/:book_id?fields=**
+//! → Returns one book's metadata for a detail view, or a `404`
+//!   `BOOK_NOT_FOUND` error if the index has no metadata for it.
+
+use crate::Backend;
+use crate::error::AppError;
+use crate::models::responses::{
+    AuthorsResponse, BookDetailResponse, BookInfoResponse, ChapterHit, LanguageEntry, LanguagesResponse,
+    RecentBook, RecentBooksResponse, RelatedBook, RelatedBooksResponse, YearsResponse,
+};
+use crate::services::related::rank_related;
+use crate::models::storage::{AuthorEntry, BookMetadata, StorageError};
+use crate::utils::cache::{TtlCache, VersionedCache};
+use crate::utils::chapter::chapter_matches;
+use crate::utils::language::summarize_languages;
+use crate::utils::text::index_terms;
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::error;
+use utoipa::IntoParams;
+
+
+/// Authors rarely change, so listings are reused for this long.
+const AUTHORS_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 200;
+
+type AuthorsCacheKey = (Option<String>, usize, usize);
+
+fn authors_cache() -> &'static TtlCache<AuthorsCacheKey, Vec<AuthorEntry>> {
+    static CACHE: OnceLock<TtlCache<AuthorsCacheKey, Vec<AuthorEntry>>> = OnceLock::new();
+    CACHE.get_or_init(|| TtlCache::new(AUTHORS_CACHE_TTL))
+}
+
+/// Counting a book's words walks the whole vocabulary on Redis, so book
+/// lookups are reused for this long.
+const BOOK_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+fn book_cache() -> &'static TtlCache<u32, BookDetailResponse> {
+    static CACHE: OnceLock<TtlCache<u32, BookDetailResponse>> = OnceLock::new();
+    CACHE.get_or_init(|| TtlCache::new(BOOK_CACHE_TTL))
+}
+
+/// Ranking a book compares its words with every other book's, so rankings
+/// are reused for this long.
+const RELATED_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_RELATED: usize = 5;
+/// Rankings are cached at this length and cut down to `n`.
+const MAX_RELATED: usize = 50;
+
+fn related_cache() -> &'static TtlCache<u32, Vec<RelatedBook>> {
+    static CACHE: OnceLock<TtlCache<u32, Vec<RelatedBook>>> = OnceLock::new();
+    CACHE.get_or_init(|| TtlCache::new(RELATED_CACHE_TTL))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuthorsParams {
+    /// Case-insensitive start of the author's name.
+    pub prefix: Option<String>,
+    /// 1-based page number.
+    #[param(default = 1, minimum = 1)]
+    pub page: Option<usize>,
+    #[param(default = json!(DEFAULT_PER_PAGE), maximum = 200)]
+    pub per_page: Option<usize>,
+}
+
+/// Lists distinct authors, optionally restricted to names starting with
+/// `prefix`. Pages are 1-based.
+#[utoipa::path(
+    get,
+    path = "/search/authors",
+    tag = "browse",
+    params(AuthorsParams),
+    responses(
+        (status = 200, description = "Authors, alphabetically", body = AuthorsResponse),
+        (status = 400, description = "Malformed parameters", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_authors(
+    Query(params): Query<AuthorsParams>,
+    State(backend): State<Backend>,
+) -> Result<Json<AuthorsResponse>, AppError> {
+    let prefix = params.prefix.filter(|p| !p.trim().is_empty());
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+
+    let key = (prefix.clone(), page, per_page);
+    let authors = match authors_cache().get(&key) {
+        Some(authors) => authors,
+        None => {
+            let authors = backend
+                .list_authors(prefix.as_deref(), page, per_page)
+                .await
+                .inspect_err(|e| {
+                    error!("Failed to list authors: {}", e);
+                })?;
+            authors_cache().insert(key, authors.clone());
+            authors
+        }
+    };
+
+    Ok(Json(AuthorsResponse {
+        page,
+        per_page,
+        authors,
+    }))
+}
+
+/// Language counts only change when a book is (re)indexed, so the summary is
+/// kept until the index version moves on.
+static LANGUAGES_CACHE: VersionedCache<Vec<LanguageEntry>> = VersionedCache::new();
+
+/// Lists every language in the index with its number of books.
+#[utoipa::path(
+    get,
+    path = "/search/languages",
+    tag = "browse",
+    responses(
+        (status = 200, description = "Books per language", body = LanguagesResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_languages(State(backend): State<Backend>) -> Result<Json<LanguagesResponse>, AppError> {
+    let version = backend.get_index_version().await.inspect_err(|e| {
+        error!("Failed to read index version: {}", e);
+    })?;
+
+    if let Some(languages) = LANGUAGES_CACHE.get(&version) {
+        return Ok(Json(LanguagesResponse { languages }));
+    }
+
+    let distribution = backend.get_language_distribution().await.inspect_err(|e| {
+        error!("Failed to get language distribution: {}", e);
+    })?;
+    let languages = summarize_languages(&distribution);
+    LANGUAGES_CACHE.insert(version, languages.clone());
+
+    Ok(Json(LanguagesResponse { languages }))
+}
+
+/// Lists the publication decades in the index with their number of books.
+#[utoipa::path(
+    get,
+    path = "/search/years",
+    tag = "browse",
+    responses(
+        (status = 200, description = "Books per decade", body = YearsResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn list_years(State(backend): State<Backend>) -> Result<Json<YearsResponse>, AppError> {
+    let decades = backend.get_year_distribution().await.inspect_err(|e| {
+        error!("Failed to get year distribution: {}", e);
+    })?;
+
+    Ok(Json(YearsResponse { decades }))
+}
+
+/// Returns a single indexed book's metadata. Unknown books aren't cached, so
+/// a book shows up as soon as it is indexed.
+#[utoipa::path(
+    get,
+    path = "/search/book/{book_id}",
+    tag = "browse",
+    params(("book_id" = u32, Path, description = "Project Gutenberg book ID")),
+    responses(
+        (status = 200, description = "The book's metadata", body = BookDetailResponse),
+        (status = 404, description = "Book not indexed", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn get_book(
+    Path(book_id): Path<u32>,
+    State(backend): State<Backend>,
+) -> Result<Json<BookDetailResponse>, AppError> {
+    fetch_book(book_id, &backend).await.map(Json)
+}
+
+/// Looks up a book for `GET /search/book/:book_id` and the gRPC `GetBook`
+/// call, through the same cache.
+pub async fn fetch_book(book_id: u32, backend: &Backend) -> Result<BookDetailResponse, AppError> {
+    if let Some(book) = book_cache().get(&book_id) {
+        return Ok(book);
+    }
+
+    let metadata = backend
+        .get_book_metadata(book_id)
+        .await
+        .inspect_err(|e| {
+            error!("Failed to get metadata for book {}: {}", book_id, e);
+        })?
+        .ok_or(AppError::BookNotFound(book_id))?;
+
+    let related_words_count = backend.get_word_count_for_book(book_id).await.inspect_err(|e| {
+        error!("Failed to count words for book {}: {}", book_id, e);
+    })?;
+
+    let book = BookDetailResponse {
+        book: metadata,
+        related_words_count,
+    };
+    book_cache().insert(book_id, book.clone());
+
+    Ok(book)
+}
+
+/// Fields of [`BookInfoResponse`] that `?fields=` can select. `book_id` is
+/// always returned.
+const BOOK_INFO_FIELDS: &[&str] = &[
+    "title", "authors", "language", "year", "word_count", "unique_words", "chapter_count", "subjects", "indexed",
+];
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BookInfoParams {
+    /// Comma-separated fields to return, e.g. `title,authors,year`.
+    pub fields: Option<String>,
+}
+
+/// The fields listed in `fields`, or an error naming the first unknown one.
+fn parse_fields(fields: &str) -> Result<Vec<&str>, String> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| match field {
+            "book_id" => Ok(field),
+            _ if BOOK_INFO_FIELDS.contains(&field) => Ok(field),
+            _ => Err(format!(
+                "unknown field '{}', expected some of: book_id, {}",
+                field,
+                BOOK_INFO_FIELDS.join(", ")
+            )),
+        })
+        .collect()
+}
+
+/// Splits a Gutenberg author line naming several people (`Karl Marx and
+/// Friedrich Engels`, `A; B`) into their names.
+fn split_authors(author: &str) -> Vec<String> {
+    author
+        .split(';')
+        .flat_map(|part| part.split(" and "))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn book_info(metadata: BookMetadata, indexed: bool) -> BookInfoResponse {
+    BookInfoResponse {
+        book_id: metadata.book_id,
+        authors: split_authors(&metadata.author),
+        title: metadata.title,
+        language: metadata.language,
+        year: metadata.year,
+        word_count: metadata.word_count,
+        unique_words: metadata.unique_words,
+        chapter_count: metadata.chapter_count,
+        subjects: metadata.subjects,
+        indexed,
+    }
+}
+
+/// Returns a book's metadata for a detail view, trimmed to `?fields=` when
+/// given. Unlike [`get_book`] it doesn't count the book's words, so it
+/// reads straight from the backend without a cache.
+///
+/// The index keeps the set of books per word rather than per-book word
+/// frequencies, so there are no most frequent terms to report.
+#[utoipa::path(
+    get,
+    path = "/books/{book_id}",
+    tag = "browse",
+    params(("book_id" = u32, Path, description = "Project Gutenberg book ID"), BookInfoParams),
+    responses(
+        (status = 200, description = "The book's metadata, only the requested `fields` if given", body = BookInfoResponse),
+        (status = 400, description = "Unknown field", body = ErrorResponse),
+        (status = 404, description = "Book not indexed", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn get_book_info(
+    Path(book_id): Path<u32>,
+    Query(params): Query<BookInfoParams>,
+    State(backend): State<Backend>,
+) -> Result<Json<Value>, AppError> {
+    let fields = params
+        .fields
+        .as_deref()
+        .map(parse_fields)
+        .transpose()
+        .map_err(AppError::InvalidRequest)?;
+
+    let storage_error = |e: StorageError| {
+        error!("Failed to get book {}: {}", book_id, e);
+        AppError::from(e)
+    };
+    let Some(metadata) = backend.get_book_metadata(book_id).await.map_err(storage_error)? else {
+        return Err(AppError::BookNotFound(book_id));
+    };
+    let indexed = !backend.is_book_incomplete(book_id).await.map_err(storage_error)?;
+
+    let mut book = serde_json::to_value(book_info(metadata, indexed)).map_err(|e| storage_error(e.into()))?;
+    if let (Some(fields), Value::Object(book)) = (fields, &mut book) {
+        book.retain(|key, _| key == "book_id" || fields.contains(&key.as_str()));
+    }
+
+    Ok(Json(book))
+}
+
+const DEFAULT_RECENT: usize = 20;
+const MAX_RECENT: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecentBooksParams {
+    /// Number of books.
+    #[param(default = json!(DEFAULT_RECENT), minimum = 1, maximum = 100)]
+    pub limit: Option<usize>,
+    /// Only books indexed after this RFC 3339 time.
+    #[param(value_type = Option<String>, format = DateTime)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Lists the `limit` books (default 20, at most 100) the indexing service
+/// stored most recently. Re-indexing a book moves it to the top, and
+/// deleted books drop out.
+#[utoipa::path(
+    get,
+    path = "/books/recent",
+    tag = "browse",
+    params(RecentBooksParams),
+    responses(
+        (status = 200, description = "Most recently indexed books first", body = RecentBooksResponse),
+        (status = 400, description = "Malformed parameters", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn get_recent_books(
+    params: Result<Query<RecentBooksParams>, QueryRejection>,
+    State(backend): State<Backend>,
+) -> Result<Json<RecentBooksResponse>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let limit = params.limit.unwrap_or(DEFAULT_RECENT).clamp(1, MAX_RECENT);
+
+    let storage_error = |e: StorageError| {
+        error!("Failed to list recent books: {}", e);
+        AppError::from(e)
+    };
+    let recent = backend.get_recent_books(limit, params.since).await.map_err(storage_error)?;
+    let ids: Vec<u32> = recent.iter().map(|&(book_id, _)| book_id).collect();
+    let mut metadata: HashMap<u32, BookMetadata> = backend
+        .get_book_metadatas(&ids)
+        .await
+        .map_err(storage_error)?
+        .into_iter()
+        .map(|book| (book.book_id, book))
+        .collect();
+
+    // A book deleted between the two reads has no metadata left
+    let books: Vec<RecentBook> = recent
+        .into_iter()
+        .filter_map(|(book_id, indexed_at)| Some(RecentBook { book: metadata.remove(&book_id)?, indexed_at }))
+        .collect();
+
+    Ok(Json(RecentBooksResponse {
+        count: books.len(),
+        books,
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RelatedParams {
+    /// Number of related books.
+    #[param(default = json!(DEFAULT_RELATED), maximum = 50)]
+    pub n: Option<usize>,
+}
+
+/// Lists the `n` books (default 5, at most 50) whose indexed words overlap
+/// most with the book's, by Jaccard similarity. Every indexed book's words
+/// are loaded, hence the hour-long cache.
+#[utoipa::path(
+    get,
+    path = "/search/book/{book_id}/related",
+    tag = "browse",
+    params(("book_id" = u32, Path, description = "Project Gutenberg book ID"), RelatedParams),
+    responses(
+        (status = 200, description = "Most similar books first", body = RelatedBooksResponse),
+        (status = 404, description = "Book not indexed", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn get_related_books(
+    Path(book_id): Path<u32>,
+    Query(params): Query<RelatedParams>,
+    State(backend): State<Backend>,
+) -> Result<Json<RelatedBooksResponse>, AppError> {
+    let n = params.n.unwrap_or(DEFAULT_RELATED).clamp(1, MAX_RELATED);
+
+    let related = match related_cache().get(&book_id) {
+        Some(related) => related,
+        None => {
+            let related = compute_related_books(book_id, &backend).await?;
+            related_cache().insert(book_id, related.clone());
+            related
+        }
+    };
+
+    Ok(Json(RelatedBooksResponse {
+        book_id,
+        related: related.into_iter().take(n).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChapterSearchParams {
+    /// Words to look for in the book's chapters.
+    pub q: String,
+}
+
+/// Lists the chapters of a book in which any query word occurs, counting
+/// the matches in each from the book's body.
+#[utoipa::path(
+    get,
+    path = "/search/book/{book_id}/chapters",
+    tag = "browse",
+    params(("book_id" = u32, Path, description = "Project Gutenberg book ID"), ChapterSearchParams),
+    responses(
+        (status = 200, description = "Matching chapters in book order", body = [ChapterHit]),
+        (status = 400, description = "Malformed parameters or no searchable words", body = ErrorResponse),
+        (status = 404, description = "Book not indexed", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, fields(book_id = book_id), err(level = "warn"))]
+pub async fn get_book_chapters(
+    Path(book_id): Path<u32>,
+    params: Result<Query<ChapterSearchParams>, QueryRejection>,
+    State(backend): State<Backend>,
+) -> Result<Json<Vec<ChapterHit>>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let words = index_terms(&params.q);
+    if words.is_empty() {
+        return Err(AppError::InvalidQuery("query has no searchable terms".to_string()));
+    }
+
+    let storage_error = |e: StorageError| {
+        error!("Failed to find chapters of {}: {}", book_id, e);
+        AppError::from(e)
+    };
+    if !backend.is_book_indexed(book_id).await.map_err(storage_error)? {
+        return Err(AppError::BookNotFound(book_id));
+    }
+    let mut chapter_ids = BTreeSet::new();
+    for word in &words {
+        let postings = backend.search_word_chapters(word).await.map_err(storage_error)?;
+        chapter_ids.extend(postings.into_iter().filter(|&(id, _)| id == book_id).map(|(_, chapter)| chapter));
+    }
+    let chapter_ids: Vec<usize> = chapter_ids.into_iter().collect();
+
+    let ids = chapter_ids.clone();
+    let mut matches = tokio::task::spawn_blocking(move || chapter_matches(book_id, &ids, &words))
+        .await
+        .unwrap_or_else(|e| {
+            error!("Counting chapter matches failed: {}", e);
+            HashMap::new()
+        });
+
+    let hits = chapter_ids
+        .into_iter()
+        .map(|chapter_id| {
+            let found = matches.remove(&chapter_id);
+            ChapterHit {
+                chapter_id,
+                occurrence_count: found.as_ref().map(|found| found.occurrences),
+                chapter_title: found.and_then(|found| found.title),
+            }
+        })
+        .collect();
+
+    Ok(Json(hits))
+}
+
+async fn compute_related_books(book_id: u32, backend: &Backend) -> Result<Vec<RelatedBook>, AppError> {
+    let storage_error = |e: StorageError| {
+        error!("Failed to find books related to {}: {}", book_id, e);
+        AppError::from(e)
+    };
+
+    if !backend.is_book_indexed(book_id).await.map_err(storage_error)? {
+        return Err(AppError::BookNotFound(book_id));
+    }
+    let words = backend.get_words_for_book(book_id).await.map_err(storage_error)?;
+
+    let mut candidates = Vec::new();
+    for other in backend.get_indexed_books().await.map_err(storage_error)? {
+        if other != book_id {
+            candidates.push((other, backend.get_words_for_book(other).await.map_err(storage_error)?));
+        }
+    }
+
+    let mut related = Vec::new();
+    for (other, similarity) in rank_related(&words, candidates, MAX_RELATED) {
+        // A book deleted since the word scan just drops out
+        if let Some(metadata) = backend.get_book_metadata(other).await.map_err(storage_error)? {
+            related.push(RelatedBook {
+                book_id: other,
+                similarity,
+                title: metadata.title,
+            });
+        }
+    }
+
+    Ok(related)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get_json(backend: MemoryBackend, uri: &str) -> (StatusCode, Value) {
+        let response = crate::app(Arc::new(backend))
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn book_lookup_returns_metadata_and_word_count() {
+        let backend = MemoryBackend::new();
+        backend
+            .store_book_metadata(&BookMetadata {
+                book_id: 2701,
+                title: "Moby Dick".to_string(),
+                author: "Herman Melville".to_string(),
+                language: "en".to_string(),
+                year: Some(1851),
+                word_count: 215_000,
+                unique_words: 17_000,
+                chapter_count: 135,
+                subjects: vec!["Whaling -- Fiction".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        for word in ["whale", "ahab", "moby"] {
+            backend.add_word_to_index(word, 2701, IndexField::Body).await.unwrap();
+        }
+        backend.add_word_to_index("whale", 84, IndexField::Body).await.unwrap();
+        backend.add_word_to_index("monster", 84, IndexField::Body).await.unwrap();
+
+        let (status, body) = get_json(backend, "/search/book/2701").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["book_id"], 2701);
+        assert_eq!(body["title"], "Moby Dick");
+        assert_eq!(body["year"], 1851);
+        assert_eq!(body["chapter_count"], 135);
+        assert_eq!(body["subjects"][0], "Whaling -- Fiction");
+        assert_eq!(body["related_words_count"], 3);
+    }
+
+    #[tokio::test]
+    async fn related_books_are_ranked_by_shared_words() {
+        let backend = MemoryBackend::new();
+        let books: [(u32, &str, &[&str]); 4] = [
+            (11, "Moby Dick", &["whale", "ship", "sea", "captain"]),
+            (12, "Typhoon", &["ship", "sea", "captain", "storm"]),
+            (13, "The Sea Wolf", &["sea", "ship", "wolf"]),
+            (14, "Emma", &["ballroom", "marriage"]),
+        ];
+        for (book_id, title, words) in books {
+            let metadata = BookMetadata {
+                book_id,
+                title: title.to_string(),
+                language: "en".to_string(),
+                ..Default::default()
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            for word in words {
+                backend.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+
+        let (status, body) = get_json(backend.clone(), "/search/book/11/related").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["book_id"], 11);
+        let related = body["related"].as_array().unwrap();
+        let ids: Vec<u64> = related.iter().map(|book| book["book_id"].as_u64().unwrap()).collect();
+        assert_eq!(ids, [12, 13]);
+        assert_eq!(related[0]["title"], "Typhoon");
+        assert_eq!(related[0]["similarity"], 0.6);
+
+        // Served from the cache, cut down to n
+        let (_, body) = get_json(backend, "/search/book/11/related?n=1").await;
+        assert_eq!(body["related"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn related_books_of_unknown_book_is_not_found() {
+        let (status, _) = get_json(MemoryBackend::new(), "/search/book/999998/related").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn chapters_holding_query_words_are_listed_in_order() {
+        let backend = MemoryBackend::new();
+        backend.store_book_metadata(&marx_and_engels()).await.unwrap();
+        for (word, book_id, chapter_no) in [("love", 61, 3), ("love", 61, 1), ("pride", 61, 3), ("pride", 62, 5)] {
+            backend.add_word_to_chapter_index(word, book_id, chapter_no).await.unwrap();
+        }
+
+        // The body isn't in the sandbox datalake, so nothing is counted
+        let (status, body) = get_json(backend.clone(), "/search/book/61/chapters?q=Love+pride").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!([
+                {"chapter_id": 1, "chapter_title": null, "occurrence_count": null},
+                {"chapter_id": 3, "chapter_title": null, "occurrence_count": null},
+            ])
+        );
+
+        let (_, body) = get_json(backend.clone(), "/search/book/61/chapters?q=whale").await;
+        assert_eq!(body, serde_json::json!([]));
+
+        let (status, body) = get_json(backend.clone(), "/search/book/61/chapters?q=of").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+
+        let (status, _) = get_json(backend, "/search/book/62/chapters?q=pride").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    fn marx_and_engels() -> BookMetadata {
+        BookMetadata {
+            book_id: 61,
+            title: "The Communist Manifesto".to_string(),
+            author: "Karl Marx and Friedrich Engels".to_string(),
+            language: "en".to_string(),
+            year: Some(1848),
+            word_count: 11_000,
+            unique_words: 2_500,
+            chapter_count: 4,
+            subjects: vec!["Communism".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn book_info_returns_metadata_and_index_state() {
+        let backend = MemoryBackend::new();
+        backend.store_book_metadata(&marx_and_engels()).await.unwrap();
+
+        let (status, body) = get_json(backend.clone(), "/books/61").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["title"], "The Communist Manifesto");
+        assert_eq!(body["authors"], serde_json::json!(["Karl Marx", "Friedrich Engels"]));
+        assert_eq!(body["year"], 1848);
+        assert_eq!(body["unique_words"], 2_500);
+        assert_eq!(body["subjects"][0], "Communism");
+        assert_eq!(body["indexed"], true);
+
+        backend.set_book_incomplete(61, true);
+        let (_, body) = get_json(backend, "/books/61").await;
+        assert_eq!(body["indexed"], false);
+    }
+
+    #[tokio::test]
+    async fn book_info_is_trimmed_to_the_requested_fields() {
+        let backend = MemoryBackend::new();
+        backend.store_book_metadata(&marx_and_engels()).await.unwrap();
+
+        let (status, body) = get_json(backend.clone(), "/books/61?fields=title,%20year").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({"book_id": 61, "title": "The Communist Manifesto", "year": 1848}));
+
+        let (status, body) = get_json(backend, "/books/61?fields=title,isbn").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+        assert!(body["message"].as_str().unwrap().contains("'isbn'"));
+    }
+
+    #[tokio::test]
+    async fn book_info_of_unknown_book_is_a_structured_404() {
+        let (status, body) = get_json(MemoryBackend::new(), "/books/999997").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error_code"], "BOOK_NOT_FOUND");
+        assert_eq!(body["book_id"], 999997);
+        assert!(body["request_id"].is_string());
+    }
+
+    #[tokio::test]
+    async fn recent_books_are_listed_newest_first_and_filtered_by_since() {
+        let backend = MemoryBackend::new();
+        let mut frankenstein = marx_and_engels();
+        frankenstein.book_id = 84;
+        frankenstein.title = "Frankenstein".to_string();
+        backend.store_book_metadata(&marx_and_engels()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        backend.store_book_metadata(&frankenstein).await.unwrap();
+
+        let (status, body) = get_json(backend.clone(), "/books/recent").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 2);
+        let ids: Vec<_> = body["books"].as_array().unwrap().iter().map(|b| b["book_id"].clone()).collect();
+        assert_eq!(ids, [84, 61]);
+        assert_eq!(body["books"][0]["title"], "Frankenstein");
+
+        let since = body["books"][1]["indexed_at"].as_str().unwrap().to_string();
+        let uri = format!("/books/recent?since={}", since.replace('+', "%2B"));
+        let (_, body) = get_json(backend.clone(), &uri).await;
+        let ids: Vec<_> = body["books"].as_array().unwrap().iter().map(|b| b["book_id"].clone()).collect();
+        assert_eq!(ids, [84]);
+
+        let (_, body) = get_json(backend.clone(), "/books/recent?limit=1").await;
+        assert_eq!(body["count"], 1);
+
+        backend.delete_book(84);
+        let (_, body) = get_json(backend.clone(), "/books/recent").await;
+        let ids: Vec<_> = body["books"].as_array().unwrap().iter().map(|b| b["book_id"].clone()).collect();
+        assert_eq!(ids, [61]);
+
+        let (status, body) = get_json(backend, "/books/recent?since=yesterday").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn unknown_book_is_not_found() {
+        let (status, _) = get_json(MemoryBackend::new(), "/search/book/999999").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
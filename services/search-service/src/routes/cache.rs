@@ -0,0 +1,60 @@
+//! Result Cache Endpoint
+//!
+//! **POST /cache/invalidate?book_id=**
+//! → Drops cached search responses (see
+//! [`crate::services::result_cache`]): all of them, or with `book_id` only
+//! those whose query matched that book. Called after indexing so new or
+//! re-indexed books show up in searches right away.
+
+use crate::models::responses::CacheInvalidateResponse;
+use crate::error::AppError;
+use crate::services::result_cache::ResultCache;
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use tracing::info;
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InvalidateParams {
+    /// Only drop the responses whose query matched this book.
+    pub book_id: Option<u32>,
+}
+
+/// Invalidates the result cache, entirely or for one book.
+#[utoipa::path(
+    post,
+    path = "/cache/invalidate",
+    tag = "cache",
+    params(InvalidateParams),
+    responses(
+        (status = 200, description = "Cached responses dropped", body = CacheInvalidateResponse),
+        (status = 400, description = "Malformed parameters", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn invalidate_cache(
+    params: Result<Query<InvalidateParams>, QueryRejection>,
+    State(result_cache): State<ResultCache>,
+) -> Result<Json<CacheInvalidateResponse>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+
+    match params.book_id {
+        Some(book_id) => {
+            info!("Invalidating cached searches matching book {}", book_id);
+            result_cache.invalidate_book(book_id);
+        }
+        None => {
+            info!("Invalidating all cached searches");
+            result_cache.invalidate_all();
+        }
+    }
+
+    Ok(Json(CacheInvalidateResponse {
+        status: "invalidated".to_string(),
+        book_id: params.book_id,
+    }))
+}
@@ -0,0 +1,196 @@
+//! API Documentation Endpoints
+//!
+//! **GET /openapi.json**
+//! → Returns the service's OpenAPI 3 description, generated from the
+//! handlers' `#[utoipa::path]` annotations and the extractor and response
+//! types they use, so it follows the code.
+//!
+//! **GET /docs**
+//! → Swagger UI for `/openapi.json`, only routed with
+//! `ENABLE_SWAGGER_UI=true`. The page loads Swagger UI's assets from a CDN.
+//!
+//! Every error is described by the shared `ErrorResponse` schema.
+
+use crate::models::responses::{
+    AuthorsResponse, BookDetailResponse, BookInfoResponse, BookResult, CacheInvalidateResponse, CanaryCheck, ChapterHit,
+    ErrorResponse, FeedbackBook, FeedbackStatsResponse, HealthChecks, HealthResponse, LanguageEntry,
+    LanguagesResponse, LatencyPercentiles, PassageResult, ProbeResponse, QueryCount, RecentBook, RecentBooksResponse, RelatedBook,
+    RelatedBooksResponse, SearchResponse, SearchStatsResponse, SnapshotRefreshResponse, SnapshotStatus, StreamSummary,
+    SynonymsReloadResponse, TermStat, YearsResponse,
+};
+use crate::models::storage::{AuthorEntry, BookMetadata, DecadeBucket, Feedback};
+use crate::routes::{browse, cache, feedback, health, passages, search, snapshot, stats, stream, synonyms};
+use crate::services::search::{MatchMode, SortOrder};
+use axum::response::{Html, Json};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Search Service",
+        description = "Full-text search over the indexed Project Gutenberg books."
+    ),
+    paths(
+        search::search_books,
+        passages::search_passages,
+        stream::stream_search,
+        stats::search_stats,
+        browse::list_authors,
+        browse::list_languages,
+        browse::list_years,
+        browse::get_book,
+        browse::get_book_info,
+        browse::get_recent_books,
+        browse::get_related_books,
+        browse::get_book_chapters,
+        feedback::submit_feedback,
+        feedback::feedback_stats,
+        cache::invalidate_cache,
+        synonyms::reload_synonyms,
+        snapshot::refresh_snapshot,
+        health::health_check,
+        health::readiness_check,
+        health::startup_check,
+        health::metrics_endpoint,
+    ),
+    components(schemas(
+        ErrorResponse,
+        SearchResponse,
+        BookResult,
+        TermStat,
+        PassageResult,
+        StreamSummary,
+        MatchMode,
+        SortOrder,
+        SearchStatsResponse,
+        QueryCount,
+        LatencyPercentiles,
+        AuthorsResponse,
+        AuthorEntry,
+        LanguagesResponse,
+        LanguageEntry,
+        YearsResponse,
+        DecadeBucket,
+        BookDetailResponse,
+        BookMetadata,
+        BookInfoResponse,
+        RecentBooksResponse,
+        RecentBook,
+        RelatedBooksResponse,
+        RelatedBook,
+        ChapterHit,
+        feedback::FeedbackRequest,
+        Feedback,
+        FeedbackStatsResponse,
+        FeedbackBook,
+        CacheInvalidateResponse,
+        SynonymsReloadResponse,
+        SnapshotRefreshResponse,
+        SnapshotStatus,
+        HealthResponse,
+        HealthChecks,
+        CanaryCheck,
+        ProbeResponse,
+    )),
+    tags(
+        (name = "search", description = "Searching and search statistics"),
+        (name = "browse", description = "Exploring the library without a query"),
+        (name = "feedback", description = "Relevance judgements"),
+        (name = "cache", description = "Search result cache"),
+        (name = "synonyms", description = "Query-time synonyms"),
+        (name = "snapshot", description = "In-memory index snapshot"),
+        (name = "health", description = "Probes and metrics"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Whether `/docs` serves Swagger UI (`ENABLE_SWAGGER_UI=true`).
+pub fn swagger_ui_enabled() -> bool {
+    std::env::var("ENABLE_SWAGGER_UI")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+/// Returns the OpenAPI description of the service.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+const SWAGGER_UI_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Search Service API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Serves Swagger UI pointed at `/openapi.json`.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(SWAGGER_UI_PAGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::MemoryBackend;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn spec_describes_search_and_its_parameters() {
+        let response = crate::app(Arc::new(MemoryBackend::new()))
+            .oneshot(Request::get("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let spec: utoipa::openapi::OpenApi = serde_json::from_slice(&body).unwrap();
+
+        let search = spec.paths.paths["/search"].operations.values().next().unwrap();
+        let parameters: Vec<&str> = search
+            .parameters
+            .iter()
+            .flatten()
+            .map(|parameter| parameter.name.as_str())
+            .collect();
+        for name in [
+            "q", "author", "language", "year", "year_from", "year_to", "subject", "mode", "sort",
+            "facets", "limit", "offset", "suggest",
+        ] {
+            assert!(parameters.contains(&name), "/search lacks `{}`: {:?}", name, parameters);
+        }
+
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        let parameter = |name: &str| {
+            json["paths"]["/search"]["get"]["parameters"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|parameter| parameter["name"] == name)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(parameter("q")["required"], true);
+        assert_eq!(parameter("limit")["schema"]["default"], 20);
+        assert_eq!(parameter("mode")["schema"]["default"], "all");
+        assert_eq!(parameter("sort")["schema"]["default"], "relevance");
+        assert_eq!(
+            json["paths"]["/search"]["get"]["responses"]["400"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+        assert!(json["components"]["schemas"]["ErrorResponse"]["properties"]["error_code"].is_object());
+    }
+}
@@ -0,0 +1,234 @@
+//! Relevance Feedback Endpoints
+//!
+//! Lets clients record whether a search result was useful, so ranking can
+//! later learn from it.
+//!
+//! **POST /search/feedback**
+//! → Body `{ "query": "love", "book_id": 1342, "relevant": true }`. Stores the
+//! judgement and returns it with `201`. Malformed bodies or queries are
+//! rejected with `400` and `INVALID_QUERY`, books that aren't indexed with
+//! `404` and `BOOK_NOT_FOUND`.
+//!
+//! **GET /search/feedback/stats?query=**
+//! → Returns the judgements' totals for a query and the books most often
+//! marked relevant.
+//!
+//! Queries are stored normalized (`Love  AND pride` → `love AND pride`), so
+//! feedback for spellings of the same query is counted together.
+
+use crate::Backend;
+use crate::error::AppError;
+use crate::models::responses::{FeedbackBook, FeedbackStatsResponse};
+use crate::models::storage::{Feedback, StorageError};
+use crate::services::query::parse_query;
+use crate::services::search::MatchMode;
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Query, State,
+    },
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+/// Number of books listed in `top_relevant_books`.
+const TOP_RELEVANT_BOOKS: usize = 10;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FeedbackRequest {
+    pub query: String,
+    pub book_id: u32,
+    pub relevant: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedbackStatsParams {
+    pub query: String,
+}
+
+/// Normalizes `query` the way feedback is stored, rejecting queries that
+/// `/search` would reject or that have no terms.
+fn normalize_query(query: &str) -> Result<String, AppError> {
+    match parse_query(query, MatchMode::All) {
+        Ok(Some(query)) => Ok(query.to_string()),
+        Ok(None) => Err(AppError::InvalidQuery("query has no searchable terms".to_string())),
+        Err(e) => Err(AppError::InvalidQuery(e.to_string())),
+    }
+}
+
+fn storage_error(e: StorageError) -> AppError {
+    error!("Failed to access the feedback store: {}", e);
+    e.into()
+}
+
+/// Records one relevance judgement for an indexed book.
+#[utoipa::path(
+    post,
+    path = "/search/feedback",
+    tag = "feedback",
+    request_body = FeedbackRequest,
+    responses(
+        (status = 201, description = "The stored judgement", body = Feedback),
+        (status = 400, description = "Malformed body or query", body = ErrorResponse),
+        (status = 404, description = "Book not indexed", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn submit_feedback(
+    State(backend): State<Backend>,
+    request: Result<Json<FeedbackRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<Feedback>), AppError> {
+    let Json(request) = request.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let query = normalize_query(&request.query)?;
+
+    if !backend.is_book_indexed(request.book_id).await.map_err(storage_error)? {
+        return Err(AppError::BookNotFound(request.book_id));
+    }
+
+    let feedback = Feedback {
+        query,
+        book_id: request.book_id,
+        relevant: request.relevant,
+        created_at: Utc::now(),
+    };
+    backend.record_feedback(&feedback).await.map_err(storage_error)?;
+    info!(
+        "Feedback for '{}': book {} relevant={}",
+        feedback.query, feedback.book_id, feedback.relevant
+    );
+
+    Ok((StatusCode::CREATED, Json(feedback)))
+}
+
+/// Summarizes the feedback recorded for a query.
+#[utoipa::path(
+    get,
+    path = "/search/feedback/stats",
+    tag = "feedback",
+    params(FeedbackStatsParams),
+    responses(
+        (status = 200, description = "Judgement totals for the query", body = FeedbackStatsResponse),
+        (status = 400, description = "Malformed parameters", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn feedback_stats(
+    params: Result<Query<FeedbackStatsParams>, QueryRejection>,
+    State(backend): State<Backend>,
+) -> Result<Json<FeedbackStatsResponse>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let query = normalize_query(&params.query)?;
+
+    let summary = backend
+        .get_feedback_summary(&query, TOP_RELEVANT_BOOKS)
+        .await
+        .map_err(storage_error)?;
+
+    let mut top_relevant_books = Vec::new();
+    for (book_id, _) in summary.top_relevant {
+        // Books deleted since the feedback was given drop out
+        if let Some(metadata) = backend.get_book_metadata(book_id).await.map_err(storage_error)? {
+            top_relevant_books.push(FeedbackBook {
+                book_id,
+                title: metadata.title,
+            });
+        }
+    }
+
+    Ok(Json(FeedbackStatsResponse {
+        query,
+        positive: summary.positive,
+        negative: summary.negative,
+        top_relevant_books,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn send(backend: &MemoryBackend, request: Request<Body>) -> (StatusCode, Value) {
+        let response = crate::app(Arc::new(backend.clone())).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    async fn post_feedback(backend: &MemoryBackend, body: Value) -> (StatusCode, Value) {
+        let request = Request::post("/search/feedback")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        send(backend, request).await
+    }
+
+    async fn austen_backend() -> MemoryBackend {
+        let backend = MemoryBackend::new();
+        for (book_id, title) in [(1342, "Pride and Prejudice"), (158, "Emma"), (161, "Sense and Sensibility")] {
+            let metadata = BookMetadata {
+                book_id,
+                title: title.to_string(),
+                author: "Jane Austen".to_string(),
+                language: "en".to_string(),
+                ..Default::default()
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            backend.add_word_to_index("love", book_id, IndexField::Body).await.unwrap();
+        }
+        backend
+    }
+
+    #[tokio::test]
+    async fn feedback_is_counted_in_stats() {
+        let backend = austen_backend().await;
+        let judgements = [(1342, true), (1342, true), (158, true), (161, false)];
+        for (book_id, relevant) in judgements {
+            // Spellings of the same query are counted together
+            let (status, body) =
+                post_feedback(&backend, json!({ "query": " LOVE ", "book_id": book_id, "relevant": relevant })).await;
+            assert_eq!(status, StatusCode::CREATED);
+            assert_eq!(body["query"], "love");
+        }
+
+        let (status, body) = send(&backend, Request::get("/search/feedback/stats?query=love").body(Body::empty()).unwrap()).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["query"], "love");
+        assert_eq!(body["positive"], 3);
+        assert_eq!(body["negative"], 1);
+        assert_eq!(
+            body["top_relevant_books"],
+            json!([
+                { "book_id": 1342, "title": "Pride and Prejudice" },
+                { "book_id": 158, "title": "Emma" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_feedback_is_rejected() {
+        let backend = austen_backend().await;
+
+        let (status, body) = post_feedback(&backend, json!({ "query": "love", "book_id": 1342 })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+
+        let (status, _) = post_feedback(&backend, json!({ "query": "(love", "book_id": 1342, "relevant": true })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = post_feedback(&backend, json!({ "query": "love", "book_id": 999999, "relevant": true })).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
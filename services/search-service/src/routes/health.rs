@@ -1,18 +1,429 @@
-//! Health Check Endpoint
+//! Health Check Endpoints
 //!
-//! Simple route to verify that the **Search Service** is up and running.
+//! Liveness, readiness and startup probes for the **Search Service**. Each
+//! answers `{"probe": ..., "ok": ...}`, with `503` when not ok.
 //!
-//! **GET /status**
-//! → Returns `{"service":"search-service","status":"running"}`
+//! **GET /status?deep=** (liveness)
+//! → Always `200` while the process answers, with the backend's type,
+//! connection state and round-trip time, the background connection checks
+//! failed in a row, whether the datalake (read for snippets) is readable and
+//! the seconds since startup, plus the in-memory snapshot's size and age
+//! with `SEARCH_SNAPSHOT=on`. `"status"` is
+//! `"degraded"` while the backend is unreachable or the index holds no
+//! books, with the reason in `checks`. `deep=true` also searches for a
+//! title word of an indexed book and is degraded unless the book is found
+//!
+//! **GET /ready** (readiness)
+//! → `200` while the backend is connected and the datalake readable
+//!
+//! **GET /startup** (startup)
+//! → `200` once the backend has answered for the first time
+//!
+//! **GET /metrics**
+//! → Returns Prometheus metrics in the text exposition format
+
+use crate::Backend;
+use crate::models::responses::{CanaryCheck, HealthChecks, HealthResponse, ProbeResponse};
+use crate::models::storage::{SnapshotBackend, StorageError};
+use crate::services::backend_monitor::BackendHealth;
+use crate::services::metrics::metrics;
+use crate::services::startup::StartupProbe;
+use crate::utils::file::DATALAKE_PATH;
+use crate::utils::text::index_terms;
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use utoipa::IntoParams;
+
+/// How long the backend may take to answer before it counts as unreachable.
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
 
-use crate::models::responses::HealthResponse;
-use axum::response::Json;
+/// Indexed books the canary looks through for a searchable title word.
+const CANARY_CANDIDATES: usize = 10;
 
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HealthParams {
+    /// Also run a canary search against the index.
+    #[serde(default)]
+    pub deep: bool,
+}
 
 /// Returns the current health status of the Search Service.
-pub async fn health_check() -> Json<HealthResponse> {
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = "health",
+    params(HealthParams),
+    responses(
+        (status = 200, description = "Alive, with backend details", body = HealthResponse),
+    )
+)]
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn health_check(
+    params: Option<Query<HealthParams>>,
+    State(backend): State<Backend>,
+    State(started_at): State<Arc<Instant>>,
+    State(startup): State<StartupProbe>,
+    State(backend_health): State<BackendHealth>,
+    State(snapshot): State<Option<SnapshotBackend>>,
+) -> Json<HealthResponse> {
+    let Query(params) = params.unwrap_or_default();
+    let (connected, backend_latency_ms) = check_backend(&backend, &startup).await;
+    let mut checks = HealthChecks {
+        backend_reachable: connected,
+        ..HealthChecks::default()
+    };
+    if connected {
+        checks.indexed_books = check_index(&backend).await;
+        checks.index_present = checks.indexed_books.is_some_and(|books| books > 0);
+        if params.deep && checks.index_present {
+            checks.canary = Some(run_canary(&backend).await);
+        }
+    }
+
+    let healthy = checks.index_present && checks.canary.as_ref().is_none_or(|canary| canary.ok);
+    let status = if healthy { "running" } else { "degraded" };
+    let backend_status = if connected { "connected" } else { "unavailable" };
+
     Json(HealthResponse {
         service: "search-service".to_string(),
-        status: "running".to_string(),
+        probe: "liveness".to_string(),
+        ok: true,
+        status: status.to_string(),
+        backend: backend_status.to_string(),
+        backend_connected: connected,
+        backend_type: backend.kind().to_string(),
+        backend_latency_ms,
+        backend_consecutive_failures: backend_health.consecutive_failures(),
+        datalake_accessible: datalake_accessible().await,
+        uptime_secs: started_at.elapsed().as_secs(),
+        checks,
+        snapshot: snapshot.map(|snapshot| snapshot.status()),
     })
-}
\ No newline at end of file
+}
+
+/// Returns whether the service can answer searches right now.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to answer searches", body = ProbeResponse),
+        (status = 503, description = "Backend or datalake unavailable", body = ProbeResponse),
+    )
+)]
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn readiness_check(
+    State(backend): State<Backend>,
+    State(startup): State<StartupProbe>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let (connected, _) = check_backend(&backend, &startup).await;
+    probe_response("readiness", connected && datalake_accessible().await)
+}
+
+/// Returns whether the backend has ever answered.
+#[utoipa::path(
+    get,
+    path = "/startup",
+    tag = "health",
+    responses(
+        (status = 200, description = "The backend has answered", body = ProbeResponse),
+        (status = 503, description = "Still waiting for the backend", body = ProbeResponse),
+    )
+)]
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn startup_check(
+    State(backend): State<Backend>,
+    State(startup): State<StartupProbe>,
+) -> (StatusCode, Json<ProbeResponse>) {
+    let started = startup.is_complete() || check_backend(&backend, &startup).await.0;
+    probe_response("startup", started)
+}
+
+/// Whether the backend answers a connection test within
+/// [`HEALTH_CHECK_TIMEOUT`], and the milliseconds it took to find out. The
+/// first success completes `startup`.
+async fn check_backend(backend: &Backend, startup: &StartupProbe) -> (bool, f64) {
+    let probe_started = Instant::now();
+    let connected = match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, backend.test_connection()).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            warn!("Health check: storage backend unavailable: {}", e);
+            false
+        }
+        Err(_) => {
+            warn!("Health check: storage backend didn't answer within {:?}", HEALTH_CHECK_TIMEOUT);
+            false
+        }
+    };
+    if connected {
+        startup.complete();
+    }
+    (connected, probe_started.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Number of indexed books, `None` when the index can't be read within
+/// [`HEALTH_CHECK_TIMEOUT`], e.g. its tables don't exist.
+async fn check_index(backend: &Backend) -> Option<usize> {
+    match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, backend.get_stats()).await {
+        Ok(Ok((total_books, _))) => Some(total_books),
+        Ok(Err(e)) => {
+            warn!("Health check: index unreadable: {}", e);
+            None
+        }
+        Err(_) => {
+            warn!("Health check: index stats took longer than {:?}", HEALTH_CHECK_TIMEOUT);
+            None
+        }
+    }
+}
+
+/// Searches for a title word of one of the first indexed books and checks
+/// the book comes back, within [`HEALTH_CHECK_TIMEOUT`].
+async fn run_canary(backend: &Backend) -> CanaryCheck {
+    let started = Instant::now();
+    let found = match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, canary_search(backend)).await {
+        Ok(Ok(found)) => found,
+        Ok(Err(e)) => {
+            warn!("Health check: canary search failed: {}", e);
+            None
+        }
+        Err(_) => {
+            warn!("Health check: canary search took longer than {:?}", HEALTH_CHECK_TIMEOUT);
+            None
+        }
+    };
+    if let Some((word, book_id, false)) = &found {
+        warn!("Health check: searching '{}' didn't find book {}", word, book_id);
+    }
+
+    let (query, book_id, ok) = match found {
+        Some((word, book_id, ok)) => (Some(word), Some(book_id), ok),
+        None => (None, None, false),
+    };
+    CanaryCheck {
+        query,
+        book_id,
+        ok,
+        latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+    }
+}
+
+/// The word searched, the book it came from and whether it was found;
+/// `None` when no candidate book has a searchable title word.
+async fn canary_search(backend: &Backend) -> Result<Option<(String, u32, bool)>, StorageError> {
+    let mut book_ids: Vec<u32> = backend.get_indexed_books().await?.into_iter().collect();
+    book_ids.sort_unstable();
+
+    for book_id in book_ids.into_iter().take(CANARY_CANDIDATES) {
+        let Some(metadata) = backend.get_book_metadata(book_id).await? else {
+            continue;
+        };
+        let Some(word) = index_terms(&metadata.title).into_iter().next() else {
+            continue;
+        };
+        let found = backend.search_word(&word).await?.contains(&book_id);
+        return Ok(Some((word, book_id, found)));
+    }
+    Ok(None)
+}
+
+async fn datalake_accessible() -> bool {
+    tokio::fs::read_dir(DATALAKE_PATH).await.is_ok()
+}
+
+fn probe_response(probe: &str, ok: bool) -> (StatusCode, Json<ProbeResponse>) {
+    let code = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        code,
+        Json(ProbeResponse {
+            service: "search-service".to_string(),
+            probe: probe.to_string(),
+            ok,
+        }),
+    )
+}
+
+/// Serves the Prometheus metrics of this process.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Prometheus text exposition", content_type = "text/plain"),
+    )
+)]
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn metrics_endpoint() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use common_redis::RedisConfig;
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, RedisBackend, StorageBackend};
+    use crate::Backend;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn probe(backend: Backend, uri: &str) -> (StatusCode, Value) {
+        let response = crate::app(backend)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let code = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (code, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn unreachable_redis() -> Backend {
+        // Nothing listens on port 1
+        Arc::new(RedisBackend::from_config(&RedisConfig::new("redis://127.0.0.1:1", None)).unwrap())
+    }
+
+    #[tokio::test]
+    async fn reports_the_backend_and_uptime() {
+        let (code, health) = probe(Arc::new(MemoryBackend::new()), "/status").await;
+
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(health["probe"], "liveness");
+        assert_eq!(health["ok"], true);
+        assert_eq!(health["backend_connected"], true);
+        assert_eq!(health["backend_type"], "memory");
+        assert!(health["backend_latency_ms"].is_f64());
+        assert_eq!(health["backend_consecutive_failures"], 0);
+        assert!(health["datalake_accessible"].is_boolean());
+        assert!(health["uptime_secs"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn reports_failed_background_checks_until_one_passes() {
+        let memory = MemoryBackend::new();
+        let state = crate::state::AppState::new(Arc::new(memory.clone()));
+        let failures = |state: &crate::state::AppState| {
+            let app = crate::app_with_state(state.clone());
+            async move {
+                let response = app.oneshot(Request::get("/status").body(Body::empty()).unwrap()).await.unwrap();
+                let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+                body["backend_consecutive_failures"].clone()
+            }
+        };
+
+        memory.drop_connection();
+        state.backend_health.check(&state.backend).await;
+        state.backend_health.check(&state.backend).await;
+        assert_eq!(failures(&state).await, 2);
+
+        // The third failure reconnects
+        state.backend_health.check(&state.backend).await;
+        state.backend_health.check(&state.backend).await;
+        assert_eq!(failures(&state).await, 0);
+    }
+
+    #[tokio::test]
+    async fn unreachable_backend_is_alive_but_not_ready() {
+        let (code, health) = probe(unreachable_redis(), "/status").await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["backend_connected"], false);
+        assert_eq!(health["backend_type"], "redis");
+        assert_eq!(
+            health["checks"],
+            json!({"backend_reachable": false, "index_present": false, "indexed_books": null})
+        );
+
+        for (uri, name) in [("/ready", "readiness"), ("/startup", "startup")] {
+            let (code, body) = probe(unreachable_redis(), uri).await;
+            assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE, "{}", uri);
+            assert_eq!(body["probe"], name);
+            assert_eq!(body["ok"], false);
+        }
+    }
+
+    #[tokio::test]
+    async fn connected_backend_has_started() {
+        let (code, body) = probe(Arc::new(MemoryBackend::new()), "/startup").await;
+
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body["ok"], true);
+    }
+
+    /// Moby Dick's metadata, with its title word `moby` indexed or not.
+    async fn moby_dick(title_indexed: bool) -> Backend {
+        let memory = MemoryBackend::new();
+        memory
+            .store_book_metadata(&BookMetadata {
+                book_id: 2701,
+                title: "Moby Dick".to_string(),
+                author: "Herman Melville".to_string(),
+                language: "en".to_string(),
+                year: Some(1851),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        memory.add_word_to_index("whale", 2701, IndexField::Body).await.unwrap();
+        if title_indexed {
+            memory.add_word_to_index("moby", 2701, IndexField::Title).await.unwrap();
+        }
+        Arc::new(memory)
+    }
+
+    #[tokio::test]
+    async fn empty_index_is_degraded() {
+        let (code, health) = probe(Arc::new(MemoryBackend::new()), "/status?deep=true").await;
+
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["backend"], "connected");
+        // No canary without an index to run it on
+        assert_eq!(
+            health["checks"],
+            json!({"backend_reachable": true, "index_present": false, "indexed_books": 0})
+        );
+    }
+
+    #[tokio::test]
+    async fn indexed_books_make_the_service_healthy() {
+        let (_, health) = probe(moby_dick(true).await, "/status").await;
+        assert_eq!(health["status"], "running");
+        assert_eq!(health["checks"]["index_present"], true);
+        assert_eq!(health["checks"]["indexed_books"], 1);
+        assert!(health["checks"].get("canary").is_none());
+
+        let (_, health) = probe(moby_dick(true).await, "/status?deep=true").await;
+        assert_eq!(health["status"], "running");
+        let canary = &health["checks"]["canary"];
+        assert_eq!(canary["query"], "moby");
+        assert_eq!(canary["book_id"], 2701);
+        assert_eq!(canary["ok"], true);
+        assert!(canary["latency_ms"].is_f64());
+    }
+
+    #[tokio::test]
+    async fn failed_canary_is_degraded() {
+        let (_, health) = probe(moby_dick(false).await, "/status?deep=true").await;
+
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["checks"]["index_present"], true);
+        assert_eq!(health["checks"]["canary"]["query"], "moby");
+        assert_eq!(health["checks"]["canary"]["ok"], false);
+
+        // Only the deep check searches
+        let (_, health) = probe(moby_dick(false).await, "/status").await;
+        assert_eq!(health["status"], "running");
+    }
+}
@@ -1,2 +1,11 @@
+pub mod browse;
+pub mod cache;
+pub mod docs;
+pub mod feedback;
 pub mod health;
-pub mod search;
\ No newline at end of file
+pub mod passages;
+pub mod search;
+pub mod snapshot;
+pub mod stats;
+pub mod stream;
+pub mod synonyms;
\ No newline at end of file
@@ -0,0 +1,186 @@
+//! Passage Search Endpoint
+//!
+//! **GET /search/passages?q=&book_id=&limit=**
+//! → Returns the sentences containing every word of `q`, optionally only in
+//!   one book, ordered by book and sentence, with each sentence's text read
+//!   back from the datalake (see [`crate::utils::passage`]). Only books
+//!   indexed with `ENABLE_SENTENCE_INDEX=true` have sentence postings. A
+//!   query without searchable words is a `400` `INVALID_QUERY` error.
+
+use crate::error::AppError;
+use crate::models::responses::PassageResult;
+use crate::models::storage::Passage;
+use crate::utils::passage::sentence_texts;
+use crate::utils::text::index_terms;
+use crate::Backend;
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use tracing::error;
+use utoipa::IntoParams;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PassageParams {
+    /// Words every returned sentence must contain.
+    pub q: String,
+    /// Only search this book.
+    pub book_id: Option<u32>,
+    /// Most sentences returned.
+    #[param(default = json!(DEFAULT_LIMIT), minimum = 1, maximum = 100)]
+    pub limit: Option<usize>,
+}
+
+/// Finds the sentences containing every query word.
+#[utoipa::path(
+    get,
+    path = "/search/passages",
+    tag = "search",
+    params(PassageParams),
+    responses(
+        (status = 200, description = "Matching sentences by book and position", body = [PassageResult]),
+        (status = 400, description = "Malformed parameters or no searchable words", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn search_passages(
+    params: Result<Query<PassageParams>, QueryRejection>,
+    State(backend): State<Backend>,
+) -> Result<Json<Vec<PassageResult>>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let words = index_terms(&params.q);
+    if words.is_empty() {
+        return Err(AppError::InvalidQuery("query has no searchable terms".to_string()));
+    }
+
+    let mut passages = backend.search_passages(&words, params.book_id).await.inspect_err(|e| {
+        error!("Failed to search passages for {:?}: {}", params.q, e);
+    })?;
+    passages.truncate(limit);
+
+    let mut texts = read_passage_texts(&passages).await;
+    let results = passages
+        .into_iter()
+        .map(|passage| PassageResult {
+            book_id: passage.book_id,
+            sentence_id: passage.sentence_id,
+            text: texts.remove(&(passage.book_id, passage.sentence_id)),
+        })
+        .collect();
+
+    Ok(Json(results))
+}
+
+/// Reads the passages' sentences from the datalake, each body once.
+async fn read_passage_texts(passages: &[Passage]) -> HashMap<(u32, usize), String> {
+    let mut by_book: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for passage in passages {
+        by_book.entry(passage.book_id).or_default().push(passage.sentence_id);
+    }
+
+    tokio::task::spawn_blocking(move || {
+        by_book
+            .into_iter()
+            .flat_map(|(book_id, sentence_ids)| {
+                sentence_texts(book_id, &sentence_ids)
+                    .into_iter()
+                    .map(move |(sentence_id, text)| ((book_id, sentence_id), text))
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_else(|e| {
+        error!("Reading passage text failed: {}", e);
+        HashMap::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::{MemoryBackend, StorageBackend};
+    use crate::Backend;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn get_json(backend: &Backend, uri: &str) -> (StatusCode, Value) {
+        let response = crate::app(backend.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    /// Sentence postings as the indexing service writes them.
+    async fn moby_dick_sentences() -> Backend {
+        let memory = MemoryBackend::new();
+        let sentences: [(u32, usize, &[&str]); 4] = [
+            (2701, 3, &["great", "whale"]),
+            (2701, 1503, &["great", "white", "whale"]),
+            (2701, 1600, &["white", "whale", "sank"]),
+            (15, 40, &["great", "white", "whale"]),
+        ];
+        for (book_id, sentence_id, words) in sentences {
+            for word in words {
+                memory.add_word_to_sentence_index(word, book_id, sentence_id).await.unwrap();
+            }
+        }
+        Arc::new(memory)
+    }
+
+    #[tokio::test]
+    async fn finds_sentences_with_every_word() {
+        let backend = moby_dick_sentences().await;
+
+        // Books outside the sandbox datalake have no text to show
+        let (status, passages) = get_json(&backend, "/search/passages?q=great+white+whale").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            passages,
+            json!([
+                {"book_id": 15, "sentence_id": 40, "text": null},
+                {"book_id": 2701, "sentence_id": 1503, "text": null},
+            ])
+        );
+
+        let (_, passages) = get_json(&backend, "/search/passages?q=A+White+WHALE&book_id=2701").await;
+        let ids: Vec<u64> = passages
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|passage| passage["sentence_id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, [1503, 1600]);
+
+        let (_, passages) = get_json(&backend, "/search/passages?q=whale&limit=1").await;
+        assert_eq!(passages.as_array().unwrap().len(), 1);
+        assert_eq!(passages[0]["book_id"], 15);
+
+        let (_, passages) = get_json(&backend, "/search/passages?q=whale+zanzibar").await;
+        assert_eq!(passages, json!([]));
+    }
+
+    #[tokio::test]
+    async fn queries_without_searchable_words_are_rejected() {
+        let backend = moby_dick_sentences().await;
+
+        for uri in ["/search/passages?q=of+an", "/search/passages", "/search/passages?q=whale&book_id=moby"] {
+            let (status, error) = get_json(&backend, uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+            assert_eq!(error["error_code"], "INVALID_QUERY", "{}", uri);
+        }
+    }
+}
@@ -1,106 +1,491 @@
 //! Search Endpoint
 //!
 //! Handles book search queries for the **Search Service**.
-//! Performs tokenization, inverted index lookups, and metadata filtering.
+//! Tokenizes the query like the indexer tokenizes book text, intersects the
+//! words' postings in the inverted index (body, title and author words
+//! alike), then hydrates the matching books' metadata, through the
+//! [`MetadataCache`], and filters it.
 //!
-//! **GET /search?q=...&author=&language=&year=**
-//! → Returns matching books with applied filters.
+//! **GET /search?q=...&author=&language=&year=&year_from=&year_to=&max_reading_level=&subject=&highlight_body=&snippets=&sort=&facets=&suggest=&synonyms=&dedupe=&regex=&timeout_ms=&limit=&offset=**
+//! → Returns one page of matching books with applied filters and highlighted
+//! matches, ordered by `sort` (see [`SortOrder`]). The applied filters are
+//! echoed in `filters`, normalized. `facets` adds counts of all matching
+//! books per language, author or decade (see [`crate::services::facets`]).
+//! `snippets=true` adds body context around the first match to the first
+//! [`MAX_SNIPPETS`] results of the page. A search matching nothing suggests
+//! up to three corrected queries in `suggestions` unless `suggest=false`
+//! (see [`crate::services::suggest`]). `term_stats` gives the number of
+//! books containing each word of the query, so a word matching nothing
+//! stands out; words the index never holds, like ones under three letters,
+//! are marked `filtered`. Query words are ORed with their synonyms unless
+//! `synonyms=false` (see [`crate::services::synonyms`]). `dedupe=true` keeps
+//! one result per edition of a book, listing the others in
+//! `also_available_as`; `total_count` and facets then count editions (see
+//! [`crate::services::dedupe`]). A word ending in `*` (`whal*`), or with
+//! `regex=true` every term, matches the indexed words it describes, listed
+//! under the pattern in `term_stats` (see [`crate::services::wildcard`]).
+//! Malformed parameters, patterns and empty year ranges are rejected with
+//! `400` and an `INVALID_QUERY` error.
+//!
+//! Fetching postings and scoring share a time budget, `timeout_ms` or
+//! `SEARCH_TIMEOUT_MS` (see [`crate::services::timeout`]). Once it runs out,
+//! the backend calls still running are cancelled and the books found so far
+//! are returned with `partial: true` and `timed_out_after_ms`; those are the
+//! books of the `OR` branches answered in time, unscored if scoring was cut
+//! short. Partial responses aren't cached. Exports aren't budgeted.
 
-use crate::models::responses::{BookResult, SearchResponse};
-use crate::models::storage::{BookMetadata, StorageBackend};
+use crate::Backend;
+use crate::error::AppError;
+use crate::models::responses::{BookResult, SearchResponse, TermStat};
+use crate::models::storage::{BookMetadata, IndexField, QueryLogEntry, StorageError};
+use crate::services::dedupe::dedupe_editions;
+use crate::services::facets::{count_facets, parse_facets, Facet};
+use crate::services::metadata_cache::MetadataCache;
+use crate::services::metrics::metrics;
+use crate::services::result_cache::CachedSearch;
+use crate::services::query::{evaluate, parse_query, parse_regex_query, query_words, Query as BooleanQuery};
+use crate::services::search::{
+    idf, relevance_score, sort_results, MatchMode, ScoredBookResult, SortOrder, TermStats,
+};
+use crate::services::suggest::suggest;
+use crate::services::synonyms::SynonymExpander;
+use crate::services::wildcard::{expand_patterns, Expansion};
+use crate::state::AppState;
+use crate::utils::conditional::Validators;
+use crate::utils::export::{stream_results, ResultFormat};
+use crate::utils::highlight::{apply_highlights, has_matches, wrap_matches};
+use crate::utils::snippet::extract_snippet;
+use crate::utils::text::{index_terms, TokenizerConfig};
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::Json,
+    body::Bytes,
+    extract::{rejection::QueryRejection, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue},
+    response::{IntoResponse, Response},
 };
+use chrono::Utc;
 use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use tracing::{error, info};
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::time::Instant;
+use tokio::task::JoinSet;
+use tokio::time::Instant as Deadline;
+use tracing::{error, info, warn};
+use utoipa::IntoParams;
+
+/// Treats a blank parameter (`?author=`) as absent, so it doesn't filter.
+fn blank_as_none<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.filter(|v| !v.trim().is_empty()))
+}
 
-#[derive(Debug, Deserialize)]
+/// Like [`blank_as_none`], trimming and lowercasing the value for filters
+/// that ignore case.
+fn normalized<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(blank_as_none(deserializer)?.map(|v| v.trim().to_lowercase()))
+}
+
+/// Parses `?facets=language,decade`, rejecting unknown facets.
+fn facet_list<'de, D>(deserializer: D) -> Result<Vec<Facet>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let list = String::deserialize(deserializer)?;
+    parse_facets(&list).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchParams {
+    /// Words to find, with optional `AND`, `OR`, `NOT`, parentheses,
+    /// `title:`/`author:`/`body:` prefixes and `"quoted phrases"`.
     pub q: String,
+    /// Case-insensitive substring of the author's name.
+    #[serde(default, deserialize_with = "normalized")]
     pub author: Option<String>,
+    #[serde(default, deserialize_with = "normalized")]
     pub language: Option<String>,
     pub year: Option<u32>,
+    /// Earliest publication year, inclusive; books without a year are
+    /// excluded.
+    pub year_from: Option<u32>,
+    /// Latest publication year, inclusive; books without a year are
+    /// excluded.
+    pub year_to: Option<u32>,
+    /// Older name of `year_from`; sending both is a `400`.
+    pub year_min: Option<u32>,
+    /// Older name of `year_to`; sending both is a `400`.
+    pub year_max: Option<u32>,
+    /// Fewest words a book may have, inclusive.
+    pub min_word_count: Option<usize>,
+    /// Most words a book may have, inclusive.
+    pub max_word_count: Option<usize>,
+    /// Highest Flesch-Kincaid grade level, inclusive; books without a
+    /// reading level are excluded.
+    #[param(example = 6.0)]
+    pub max_reading_level: Option<f64>,
+    #[serde(default, deserialize_with = "blank_as_none")]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub highlight_body: bool,
+    /// Adds each result's body context as `snippet`, matches in `<em>`.
+    #[serde(default)]
+    pub snippets: bool,
+    /// Whether a book needs every query word (`all`) or just one (`any`).
+    #[serde(default)]
+    #[param(inline, default = json!(MatchMode::default()))]
+    pub mode: MatchMode,
+    #[serde(default)]
+    #[param(inline, default = json!(SortOrder::default()))]
+    pub sort: SortOrder,
+    /// Facets to count over all matching books, comma-separated:
+    /// `language`, `author` and `decade`.
+    #[serde(default, deserialize_with = "facet_list")]
+    #[param(value_type = Option<String>, example = "language,decade")]
+    pub facets: Vec<Facet>,
+    /// Page size, default [`DEFAULT_LIMIT`]; `0` is rejected and larger
+    /// values than [`MAX_LIMIT`] are capped.
+    #[param(default = json!(DEFAULT_LIMIT), minimum = 1, maximum = 100)]
+    pub limit: Option<usize>,
+    /// Results to skip; past the end yields an empty page.
+    #[param(default = 0)]
+    pub offset: Option<usize>,
+    /// Suggest corrected queries when nothing matches (see
+    /// [`crate::services::suggest`]); on unless `suggest=false`.
+    #[serde(default = "default_true")]
+    #[param(default = true)]
+    pub suggest: bool,
+    /// Also match each word's synonyms from the synonyms file (see
+    /// [`crate::services::synonyms`]); on unless `expand_synonyms=false`,
+    /// or its older name `synonyms=false`.
+    #[serde(default = "default_true", alias = "expand_synonyms")]
+    #[param(default = true)]
+    pub synonyms: bool,
+    /// Collapse editions of the same book into one result, the best scored.
+    #[serde(default)]
+    pub dedupe: bool,
+    /// Read every term as a regular expression matched against whole words
+    /// (see [`crate::services::wildcard`]).
+    #[serde(default)]
+    pub regex: bool,
+    /// Time budget in milliseconds, after which the books found so far
+    /// are returned as `partial`; defaults to `SEARCH_TIMEOUT_MS`, capped
+    /// at `SEARCH_TIMEOUT_SECS`.
+    pub timeout_ms: Option<u64>,
+    /// Return every match, ignoring `limit` and `offset`; meant for CSV
+    /// and NDJSON exports.
+    #[serde(default)]
+    pub export: bool,
 }
 
-type Backend = Arc<dyn StorageBackend + Send + Sync>;
+fn default_true() -> bool {
+    true
+}
 
-fn tokenize_query(query: &str) -> Vec<String> {
-    query
-        .to_lowercase()
-        .split_whitespace()
-        .filter(|word| word.len() > 2)
-        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_string())
-        .filter(|word| !word.is_empty())
-        .collect()
+impl SearchParams {
+    /// The inclusive year range, under either spelling of its bounds.
+    pub fn year_range(&self) -> (Option<u32>, Option<u32>) {
+        (self.year_from.or(self.year_min), self.year_to.or(self.year_max))
+    }
+
+    /// Rejects a bound given under both its name and its older one, which
+    /// could disagree.
+    fn check_year_names(&self) -> Result<(), AppError> {
+        for (name, old_name, both) in [
+            ("year_from", "year_min", self.year_from.is_some() && self.year_min.is_some()),
+            ("year_to", "year_max", self.year_to.is_some() && self.year_max.is_some()),
+        ] {
+            if both {
+                return Err(AppError::InvalidQuery(format!(
+                    "{} and {} are the same filter; send only {}",
+                    name, old_name, name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_LIMIT: usize = 20;
+pub const MAX_LIMIT: usize = 100;
+/// Results per request that body snippets are extracted for.
+pub const MAX_SNIPPETS: usize = 20;
+
+
+/// Splits the query into the words the index can contain, using the same
+/// rules the indexing service applies to book text (see [`index_terms`]).
+pub fn tokenize_query(query: &str) -> Vec<String> {
+    index_terms(query)
 }
 
 // No longer needed - we get year directly from metadata
 
-async fn get_book_ids_for_words(
-    words: &[String],
+/// The books `query` matches, none without a query, and whether `deadline`
+/// passed first. The branches of a top-level `OR` are looked up
+/// concurrently, so those answered in time still count; at the deadline the
+/// rest are aborted, cancelling their backend calls.
+async fn get_book_ids_for_query(
+    query: Option<&BooleanQuery>,
     backend: &Backend,
-) -> Result<HashSet<u32>, StatusCode> {
-    if words.is_empty() {
-        return Ok(HashSet::new());
+    deadline: Option<Deadline>,
+) -> Result<(HashSet<u32>, bool), AppError> {
+    let Some(query) = query else {
+        return Ok((HashSet::new(), false));
+    };
+    let log_error = |e: &StorageError| error!("Failed to evaluate query '{}': {}", query, e);
+    let Some(deadline) = deadline else {
+        let book_ids = evaluate(query, backend).await.inspect_err(log_error)?;
+        return Ok((book_ids, false));
+    };
+
+    let branches = match query {
+        BooleanQuery::Or(children) => children.clone(),
+        query => vec![query.clone()],
+    };
+    let mut lookups = JoinSet::new();
+    for branch in branches {
+        let backend = backend.clone();
+        lookups.spawn(async move { evaluate(&branch, &backend).await });
     }
 
-    let mut result_sets = Vec::new();
+    let mut book_ids = HashSet::new();
+    loop {
+        match tokio::time::timeout_at(deadline, lookups.join_next()).await {
+            Ok(Some(lookup)) => {
+                let matched = lookup.map_err(|e| AppError::Internal(format!("Query lookup failed: {}", e)))?;
+                book_ids.extend(matched.inspect_err(log_error)?);
+            }
+            Ok(None) => return Ok((book_ids, false)),
+            // Dropping `lookups` aborts the branches still running
+            Err(_) => return Ok((book_ids, true)),
+        }
+    }
+}
 
+/// Runs `future` to completion, or until `deadline` if there is one; `None`
+/// once the deadline passed, the future dropped.
+async fn before_deadline<T>(deadline: Option<Deadline>, future: impl Future<Output = T>) -> Option<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+async fn get_field_postings(word: &str, field: IndexField, backend: &Backend) -> HashSet<u32> {
+    backend
+        .get_books_for_word_in_field(word, field)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to get {} postings for '{}': {}", field.as_str(), word, e);
+            HashSet::new()
+        })
+}
+
+/// Books whose author field contains every word of the `author` filter, so
+/// `ann` doesn't match "Joanna". `None` when the filter has no indexable
+/// words or the author postings don't know them (e.g. a partial name), in
+/// which case the substring match in [`apply_filters`] decides alone.
+pub(crate) async fn get_author_matches(author: Option<&str>, backend: &Backend) -> Option<HashSet<u32>> {
+    let words = index_terms(author?);
+    let mut matches: Option<HashSet<u32>> = None;
+    for word in &words {
+        let books = get_field_postings(word, IndexField::Author, backend).await;
+        matches = Some(match matches {
+            Some(matches) => matches.intersection(&books).copied().collect(),
+            None => books,
+        });
+    }
+    matches.filter(|books| !books.is_empty())
+}
+
+/// Looks up the IDF and the title/author postings of each query word for
+/// relevance scoring, plus its full postings unless every result contains
+/// every word. A failed lookup only costs that word its field boost.
+pub(crate) async fn get_term_stats(words: &[String], all_required: bool, backend: &Backend) -> Vec<TermStats> {
+    let total_books = match backend.get_stats().await {
+        Ok((total_books, _)) => total_books,
+        Err(e) => {
+            error!("Failed to get index stats for scoring: {}", e);
+            0
+        }
+    };
+
+    let mut terms = Vec::with_capacity(words.len());
     for word in words {
-        match backend.search_word(word).await {
-            Ok(book_ids) => {
-                if book_ids.is_empty() {
-                    // If any word has no results, the intersection will be empty
-                    return Ok(HashSet::new());
-                }
-                result_sets.push(book_ids);
-            }
-            Err(e) => {
-                error!("Failed to search for word '{}': {}", word, e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        let (doc_freq, books) = if all_required {
+            let doc_freq = backend.get_word_doc_freq(word).await.unwrap_or_else(|e| {
+                error!("Failed to get document frequency of '{}': {}", word, e);
+                0
+            });
+            (doc_freq, None)
+        } else {
+            let books = get_field_postings(word, IndexField::Body, backend).await;
+            (books.len(), Some(books))
+        };
+        terms.push(TermStats {
+            idf: idf(total_books.max(doc_freq), doc_freq),
+            books,
+            title: get_field_postings(word, IndexField::Title, backend).await,
+            author: get_field_postings(word, IndexField::Author, backend).await,
+        });
+    }
+
+    terms
+}
+
+pub(crate) async fn get_book_metadata_batch(
+    book_ids: &HashSet<u32>,
+    backend: &Backend,
+    cache: &MetadataCache,
+) -> Vec<BookMetadata> {
+    let book_ids: Vec<u32> = book_ids.iter().copied().collect();
+
+    match cache.get_many(&book_ids, backend).await {
+        Ok(metadata_list) => {
+            if metadata_list.len() < book_ids.len() {
+                error!("No metadata found for {} books", book_ids.len() - metadata_list.len());
             }
+            metadata_list
+        }
+        Err(e) => {
+            error!("Failed to get metadata for {} books: {}", book_ids.len(), e);
+            Vec::new()
         }
     }
+}
+
+/// Looks up how many books contain each word of `q` (see [`query_words`])
+/// and each of their `synonyms`, and how many contain any of the words each
+/// pattern was expanded to, besides each of those words. Words the index
+/// never holds are marked filtered instead of looked up.
+async fn get_query_term_stats(
+    q: &str,
+    synonyms: Option<&SynonymExpander>,
+    expansions: &[Expansion],
+    backend: &Backend,
+) -> Result<BTreeMap<String, TermStat>, AppError> {
+    let words = query_words(q);
+    let mut stats = BTreeMap::new();
+    for word in &words {
+        let stat = match TokenizerConfig::configured().check_indexable(word) {
+            Some(not_indexed) => TermStat {
+                filtered: true,
+                reason: Some(not_indexed.reason()),
+                ..TermStat::default()
+            },
+            None => word_stat(word, None, backend).await?,
+        };
+        stats.insert(word.clone(), stat);
+    }
 
-    // Find intersection of all sets (books that contain ALL words)
-    if result_sets.is_empty() {
-        return Ok(HashSet::new());
+    if let Some(synonyms) = synonyms {
+        for word in &words {
+            for synonym in synonyms.lookup(word) {
+                if let Entry::Vacant(entry) = stats.entry(synonym) {
+                    let stat = word_stat(entry.key(), Some(word), backend).await?;
+                    entry.insert(stat);
+                }
+            }
+        }
     }
 
-    let mut intersection = result_sets[0].clone();
-    for set in result_sets.iter().skip(1) {
-        intersection = intersection.intersection(set).cloned().collect();
+    for expansion in expansions {
+        let words: Vec<String> = expansion.words.iter().map(|(word, _)| word.clone()).collect();
+        let doc_freq = if words.is_empty() {
+            0
+        } else if expansion.field.is_unscoped() {
+            let book_ids = backend.search_any_word(&words).await.inspect_err(|e| {
+                error!("Failed to look up the words of '{}': {}", expansion.pattern, e);
+            })?;
+            book_ids.len()
+        } else {
+            let mut book_ids = HashSet::new();
+            for word in &words {
+                book_ids.extend(get_field_postings(word, expansion.field, backend).await);
+            }
+            book_ids.len()
+        };
+        let pattern = expansion.pattern.to_string();
+        for (word, word_doc_freq) in &expansion.words {
+            if let Entry::Vacant(entry) = stats.entry(word.clone()) {
+                entry.insert(TermStat {
+                    doc_freq: *word_doc_freq,
+                    found: true,
+                    expansion_of: Some(pattern.clone()),
+                    ..TermStat::default()
+                });
+            }
+        }
+        stats.insert(
+            pattern,
+            TermStat {
+                doc_freq,
+                found: doc_freq > 0,
+                expansions: words,
+                truncated: expansion.truncated,
+                ..TermStat::default()
+            },
+        );
     }
+    Ok(stats)
+}
 
-    Ok(intersection)
+async fn word_stat(word: &str, synonym_of: Option<&str>, backend: &Backend) -> Result<TermStat, AppError> {
+    let doc_freq = backend.get_word_doc_freq(word).await.inspect_err(|e| {
+        error!("Failed to get document frequency of '{}': {}", word, e);
+    })?;
+    Ok(TermStat {
+        doc_freq,
+        found: doc_freq > 0,
+        synonym_of: synonym_of.map(str::to_string),
+        ..TermStat::default()
+    })
 }
 
-async fn get_book_metadata_batch(
+/// Collects, per book of `book_ids`, the chapters in which any of the query
+/// words occur.
+///
+/// Books indexed without chapter postings are simply absent from the map.
+async fn get_chapter_hits(
+    words: &[String],
     book_ids: &HashSet<u32>,
     backend: &Backend,
-) -> Vec<BookMetadata> {
-    let mut metadata_list = Vec::new();
+    metadata_cache: &MetadataCache,
+) -> HashMap<u32, BTreeSet<usize>> {
+    let mut hits: HashMap<u32, BTreeSet<usize>> = HashMap::new();
+    let chapter_counts: HashMap<u32, usize> = get_book_metadata_batch(book_ids, backend, metadata_cache)
+        .await
+        .into_iter()
+        .filter(|book| book.chapter_count > 0)
+        .map(|book| (book.book_id, book.chapter_count))
+        .collect();
+    if chapter_counts.is_empty() {
+        return hits;
+    }
 
-    for &book_id in book_ids {
-        match backend.get_book_metadata(book_id).await {
-            Ok(Some(metadata)) => metadata_list.push(metadata),
-            Ok(None) => {
-                error!("No metadata found for book {}", book_id);
+    for word in words {
+        match backend.search_word_chapters_in_books(word, &chapter_counts).await {
+            Ok(postings) => {
+                for (book_id, chapter_no) in postings {
+                    hits.entry(book_id).or_default().insert(chapter_no);
+                }
             }
             Err(e) => {
-                error!("Failed to get metadata for book {}: {}", book_id, e);
+                error!("Failed to get chapter postings for word '{}': {}", word, e);
             }
         }
     }
 
-    metadata_list
+    hits
 }
 
-fn apply_filters(
+/// Keeps the books matching every filter set in `params`.
+pub fn apply_filters(
     metadata_list: Vec<BookMetadata>,
     params: &SearchParams,
 ) -> Vec<BookMetadata> {
@@ -120,7 +505,7 @@ fn apply_filters(
 
             // Apply language filter
             if let Some(ref language_filter) = params.language {
-                if book.language != *language_filter {
+                if !book.language.eq_ignore_ascii_case(language_filter) {
                     return false;
                 }
             }
@@ -132,79 +517,521 @@ fn apply_filters(
                 }
             }
 
+            // Apply year range filter; an unknown year can't be in range
+            let (year_from, year_to) = params.year_range();
+            if year_from.is_some() || year_to.is_some() {
+                let in_range = book.year.is_some_and(|year| {
+                    year_from.is_none_or(|from| year >= from) && year_to.is_none_or(|to| year <= to)
+                });
+                if !in_range {
+                    return false;
+                }
+            }
+
+            // Apply word count range filter
+            if params.min_word_count.is_some_and(|min| book.word_count < min)
+                || params.max_word_count.is_some_and(|max| book.word_count > max)
+            {
+                return false;
+            }
+
+            // Apply reading level filter; an unknown level can't be at most the limit
+            if let Some(max_level) = params.max_reading_level {
+                if book.reading_level.is_none_or(|level| level > max_level) {
+                    return false;
+                }
+            }
+
+            // Apply subject filter (case-insensitive, any subject may match)
+            if let Some(ref subject_filter) = params.subject {
+                let subject_filter = subject_filter.to_lowercase();
+                if !book
+                    .subjects
+                    .iter()
+                    .any(|subject| subject.to_lowercase().contains(&subject_filter))
+                {
+                    return false;
+                }
+            }
+
             true
         })
         .collect()
 }
 
+/// Unhighlighted body snippets for the first [`MAX_SNIPPETS`] of
+/// `book_ids`, read off the async runtime. Books whose body isn't available
+/// are left out.
+async fn get_body_snippets(book_ids: Vec<u32>, query_words: &[String]) -> HashMap<u32, String> {
+    let terms = query_words.to_vec();
+    tokio::task::spawn_blocking(move || {
+        book_ids
+            .into_iter()
+            .take(MAX_SNIPPETS)
+            .filter_map(|book_id| extract_snippet(book_id, &terms).map(|snippet| (book_id, snippet)))
+            .collect()
+    })
+    .await
+    .unwrap_or_else(|e| {
+        error!("Snippet extraction failed: {}", e);
+        HashMap::new()
+    })
+}
+
+/// Builds the `highlights` map for a result: the title and author with
+/// matched terms marked, plus the body snippet when one was extracted.
+/// Fields without any match are omitted.
+pub(crate) fn build_highlights(
+    book: &BookResult,
+    query_words: &[String],
+    body_snippet: Option<&str>,
+) -> HashMap<String, Vec<String>> {
+    let mut highlights = HashMap::new();
+
+    for (field, text) in [("title", &book.title), ("author", &book.author)] {
+        if has_matches(text, query_words) {
+            highlights.insert(field.to_string(), vec![apply_highlights(text, query_words)]);
+        }
+    }
+
+    if let Some(snippet) = body_snippet {
+        highlights.insert("body".to_string(), vec![apply_highlights(snippet, query_words)]);
+    }
+
+    highlights
+}
 
 /// Main search handler for the Search Service.
 ///
-/// Tokenizes the query, retrieves matching books from the inverted index,
-/// applies filters, and returns structured search results.
+/// Parses the query (see [`crate::services::query`]), retrieves matching
+/// books from the inverted index, applies filters, sorts, and returns the
+/// requested page of results. Books are ordered by ID before sorting, so
+/// pages are stable for a given query. Malformed queries get a `400`.
+///
+/// Responses are served from the [`ResultCache`] when the same normalized
+/// query, filters and page were searched recently; `X-Cache` says `hit` or
+/// `miss`. Successful searches, cached or not, go to the [`QueryLogger`].
+///
+/// [`ResultCache`]: crate::services::result_cache::ResultCache
+/// [`QueryLogger`]: crate::services::query_log::QueryLogger
+///
+/// Results only change with the index, so responses carry an `ETag` over
+/// the book count and the last index change, and `Last-Modified` once the
+/// indexing service recorded one. A request whose `If-None-Match` still
+/// matches gets an empty `304`, which isn't logged.
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "One page of matching books, or every match with `export=true`; CSV or NDJSON rows when `Accept` asks for them", content(
+            ("application/json" = SearchResponse),
+            ("text/csv" = String),
+            ("application/x-ndjson" = BookResult),
+        )),
+        (status = 304, description = "Unchanged since the `If-None-Match` or `If-Modified-Since` validators"),
+        (status = 400, description = "Malformed parameters or an empty year range", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+        (status = 504, description = "Ran past `SEARCH_TIMEOUT_SECS`", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
 pub async fn search_books(
-    Query(params): Query<SearchParams>,
-    State(backend): State<Backend>,
-) -> Result<Json<SearchResponse>, StatusCode> {
+    params: Result<Query<SearchParams>, QueryRejection>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+
+    let format = ResultFormat::negotiate(&headers);
+    if format != ResultFormat::Json {
+        let response = execute_export(params, &state).await?;
+        return Ok(stream_results(format, response.results));
+    }
+
+    Ok(match execute_search(params, &headers, &state).await? {
+        SearchOutcome::NotModified(validators) => validators.not_modified(),
+        SearchOutcome::Found { body, cache_status, validators } => {
+            json_response(body, cache_status, validators.as_ref())
+        }
+    })
+}
+
+/// What a search produced, ready for the transport to send.
+pub enum SearchOutcome {
+    /// The request's `If-None-Match` or `If-Modified-Since` still holds.
+    NotModified(Validators),
+    /// The `SearchResponse` as JSON, and whether the [`ResultCache`] had
+    /// it (`"hit"` or `"miss"`).
+    ///
+    /// [`ResultCache`]: crate::services::result_cache::ResultCache
+    Found {
+        body: Bytes,
+        cache_status: &'static str,
+        validators: Option<Validators>,
+    },
+}
+
+/// Runs a search for any transport: validates `params`, answers from the
+/// [`ResultCache`] or the index, and logs it with the [`QueryLogger`].
+/// `GET /search` and the gRPC `Search` call both go through here, so they
+/// can't disagree; callers without conditional headers pass an empty map.
+///
+/// [`ResultCache`]: crate::services::result_cache::ResultCache
+/// [`QueryLogger`]: crate::services::query_log::QueryLogger
+pub async fn execute_search(
+    params: SearchParams,
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<SearchOutcome, AppError> {
+    let started = Instant::now();
+    let AppState { backend, result_cache, query_logger, synonyms, .. } = state;
+    let query = parse_search(&params, synonyms)?;
+
+    let key = result_cache_key(query.as_ref(), &params);
+    let mut log_entry = query_log_entry(query.as_ref(), &params);
+    // A cached response keeps the validators of the index it was computed
+    // from, so its ETag never vouches for newer results
+    if let Some(cached) = result_cache.get(&key) {
+        if let Some(validators) = cached.validators.as_ref().filter(|v| v.is_fresh(headers)) {
+            return Ok(SearchOutcome::NotModified(validators.clone()));
+        }
+        log_entry.result_count = cached.total_count;
+        log_entry.latency_ms = elapsed_ms(started);
+        query_logger.log(backend, log_entry);
+        let validators = cached.validators.clone();
+        return Ok(SearchOutcome::Found {
+            body: respell_query(cached, &params.q),
+            cache_status: "hit",
+            validators,
+        });
+    }
+
+    let validators = index_validators(backend).await;
+    if let Some(validators) = validators.as_ref().filter(|v| v.is_fresh(headers)) {
+        return Ok(SearchOutcome::NotModified(validators.clone()));
+    }
+
+    let spelling = params.q.clone();
+    let (response, book_ids) = run_search(params, query, state).await?;
+    let body = serde_json::to_vec(&response).map(Bytes::from).map_err(|e| {
+        error!("Failed to serialize search response: {}", e);
+        AppError::Internal(format!("Failed to serialize search response: {}", e))
+    })?;
+    // A partial response only reflects this request's luck with its budget
+    if !response.partial {
+        result_cache.insert(key, spelling, body.clone(), response.total_count, validators.clone(), book_ids);
+    }
+
+    log_entry.result_count = response.total_count;
+    log_entry.latency_ms = elapsed_ms(started);
+    query_logger.log(backend, log_entry);
+
+    Ok(SearchOutcome::Found {
+        body,
+        cache_status: "miss",
+        validators,
+    })
+}
+
+/// Runs a search for a CSV or NDJSON export, like [`execute_search`] but
+/// bypassing the result cache, which holds JSON bodies, and conditional
+/// requests. Still logged.
+pub async fn execute_export(params: SearchParams, state: &AppState) -> Result<SearchResponse, AppError> {
+    let started = Instant::now();
+    let query = parse_search(&params, &state.synonyms)?;
+    let mut log_entry = query_log_entry(query.as_ref(), &params);
+
+    let (response, _) = run_search(params, query, state).await?;
+
+    log_entry.result_count = response.total_count;
+    log_entry.latency_ms = elapsed_ms(started);
+    state.query_logger.log(&state.backend, log_entry);
+    Ok(response)
+}
+
+/// Checks the parameters and parses the boolean query, expanded with
+/// `synonyms` unless `params.synonyms` is off. Its patterns are left for
+/// [`expand_query`]; its positive terms then drive scoring and highlights.
+pub(crate) fn parse_search(params: &SearchParams, synonyms: &SynonymExpander) -> Result<Option<BooleanQuery>, AppError> {
     info!("Search query: {:?}", params);
 
-    // Tokenize the search query
-    let query_words = tokenize_query(&params.q);
+    if params.limit == Some(0) {
+        return Err(AppError::InvalidQuery("limit must be at least 1".to_string()));
+    }
+    params.check_year_names()?;
+    if let (Some(from), Some(to)) = params.year_range() {
+        if from > to {
+            return Err(AppError::InvalidQuery(format!("year_from ({}) is after year_to ({})", from, to)));
+        }
+    }
 
-    if query_words.is_empty() {
-        return Ok(Json(SearchResponse {
-            query: params.q.clone(),
-            filters: HashMap::new(),
-            count: 0,
-            results: Vec::new(),
-        }));
+    let parse = if params.regex { parse_regex_query } else { parse_query };
+    let query = parse(&params.q, params.mode).map_err(|e| AppError::InvalidQuery(e.to_string()))?;
+    Ok(match query {
+        Some(query) if params.synonyms => Some(synonyms.expand(&query)),
+        query => query,
+    })
+}
+
+/// Replaces the patterns of `query` by the words they match (see
+/// [`crate::services::wildcard`]).
+pub(crate) async fn expand_query(
+    query: Option<BooleanQuery>,
+    backend: &Backend,
+) -> Result<(Option<BooleanQuery>, Vec<Expansion>), AppError> {
+    match query {
+        Some(query) => {
+            let (query, expansions) = expand_patterns(query, backend).await?;
+            Ok((Some(query), expansions))
+        }
+        None => Ok((None, Vec::new())),
+    }
+}
+
+pub(crate) fn query_log_entry(query: Option<&BooleanQuery>, params: &SearchParams) -> QueryLogEntry {
+    QueryLogEntry {
+        query: logged_query(query, &params.q),
+        filters: build_filters_map(params),
+        result_count: 0,
+        latency_ms: 0.0,
+        created_at: Utc::now(),
     }
+}
 
-    // Find books that contain all the search words
-    let book_ids = get_book_ids_for_words(&query_words, &backend).await?;
+/// Validators for the current state of the index; `None` if it can't be
+/// read, in which case responses go out without them.
+async fn index_validators(backend: &Backend) -> Option<Validators> {
+    match backend.get_index_freshness().await {
+        Ok((total_books, last_updated)) => Some(Validators::new((total_books, last_updated), last_updated)),
+        Err(e) => {
+            error!("Failed to read index freshness: {}", e);
+            None
+        }
+    }
+}
 
-    if book_ids.is_empty() {
-        return Ok(Json(SearchResponse {
-            query: params.q.clone(),
-            filters: build_filters_map(&params),
-            count: 0,
-            results: Vec::new(),
-        }));
+/// The query as the query log records it: its parsed form, or the trimmed
+/// lowercase input when no searchable word was left in it.
+fn logged_query(query: Option<&BooleanQuery>, q: &str) -> String {
+    match query {
+        Some(query) => query.to_string(),
+        None => q.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase(),
     }
+}
+
+pub(crate) fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Runs a parsed search against the backend, returning the response and
+/// the books the query matched before filters.
+async fn run_search(
+    params: SearchParams,
+    query: Option<BooleanQuery>,
+    state: &AppState,
+) -> Result<(SearchResponse, HashSet<u32>), AppError> {
+    let AppState { backend, metadata_cache, synonyms, timeouts, .. } = state;
+    let (query, expansions) = expand_query(query, backend).await?;
+    let budget = (!params.export).then(|| timeouts.search_budget(params.timeout_ms));
+    let deadline = budget.map(|budget| Deadline::now() + budget);
+    let (mut limit, offset) = if params.export {
+        (usize::MAX, 0)
+    } else {
+        (params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT), params.offset.unwrap_or(0))
+    };
+    let query_words = query.as_ref().map(BooleanQuery::positive_terms).unwrap_or_default();
+
+    // Find the books the query matches
+    let (book_ids, mut partial) = get_book_ids_for_query(query.as_ref(), backend, deadline).await?;
 
     // Get metadata for all matching books
-    let all_metadata = get_book_metadata_batch(&book_ids, &backend).await;
+    let mut all_metadata = get_book_metadata_batch(&book_ids, backend, metadata_cache).await;
+
+    // Indexed author words are exact, so they take precedence over the
+    // substring match when they know the name
+    if let Some(author_matches) = get_author_matches(params.author.as_deref(), backend).await {
+        all_metadata.retain(|book| author_matches.contains(&book.book_id));
+    }
 
     // Apply filters
     let filtered_metadata = apply_filters(all_metadata, &params);
 
-    // Convert to response format
-    let mut results: Vec<BookResult> = filtered_metadata
+    // Field postings are only needed to score by relevance
+    let terms = if params.sort == SortOrder::Relevance && !filtered_metadata.is_empty() {
+        let all_required = query.as_ref().is_some_and(BooleanQuery::requires_all_terms);
+        before_deadline(deadline, get_term_stats(&query_words, all_required, backend))
+            .await
+            .unwrap_or_else(|| {
+                partial = true;
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+
+    // Counts and facets cover the editions left after deduplication
+    let editions = if params.dedupe {
+        dedupe_editions(filtered_metadata, |book_id| relevance_score(book_id, &terms))
+    } else {
+        filtered_metadata.into_iter().map(|book| (book, Vec::new())).collect()
+    };
+    let facets = (!params.facets.is_empty())
+        .then(|| count_facets(&params.facets, editions.iter().map(|(book, _)| book)));
+
+    let mut results: Vec<ScoredBookResult> = editions
         .into_iter()
-        .map(|book| BookResult {
-            book_id: book.book_id,
-            title: book.title.clone(),
-            author: book.author.clone(),
-            language: book.language.clone(),
-            year: book.year,
+        .map(|(book, also_available_as)| ScoredBookResult {
+            score: relevance_score(book.book_id, &terms),
+            book: BookResult {
+                also_available_as,
+                ..BookResult::from(book)
+            },
         })
         .collect();
 
-    // Sort results by book_id for consistency
-    results.sort_by_key(|book| book.book_id);
+    // Order by book_id first so ties keep a consistent order
+    results.sort_by_key(|result| result.book.book_id);
+    sort_results(&mut results, params.sort);
+    let total_count = results.len();
+    if params.export {
+        limit = total_count;
+    }
 
-    let filters = build_filters_map(&params);
+    // Highlights and chapter hits are only worked out for the page returned
+    let page: Vec<BookResult> = results
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|result| BookResult { score: result.score, ..result.book })
+        .collect();
+    let page_ids: HashSet<u32> = page.iter().map(|book| book.book_id).collect();
+    let mut chapter_hits = if page_ids.is_empty() || query_words.is_empty() {
+        HashMap::new()
+    } else {
+        before_deadline(deadline, get_chapter_hits(&query_words, &page_ids, backend, metadata_cache))
+            .await
+            .unwrap_or_else(|| {
+                partial = true;
+                HashMap::new()
+            })
+    };
+    let body_snippets = if (params.snippets || params.highlight_body) && !query_words.is_empty() {
+        get_body_snippets(page.iter().map(|book| book.book_id).collect(), &query_words).await
+    } else {
+        HashMap::new()
+    };
+    let results: Vec<BookResult> = page
+        .into_iter()
+        .map(|mut book| {
+            let body_snippet = body_snippets.get(&book.book_id).map(String::as_str);
+            let highlighted_body = body_snippet.filter(|_| params.highlight_body);
+            book.highlights = build_highlights(&book, &query_words, highlighted_body);
+            if params.snippets {
+                book.snippet = body_snippet.map(|snippet| wrap_matches(snippet, &query_words, "em"));
+            }
+            book.chapters = chapter_hits
+                .remove(&book.book_id)
+                .map(|chapters| chapters.into_iter().collect());
+            book
+        })
+        .collect();
+
+    // A regex query has no plain words, only patterns
+    let q = if params.regex { "" } else { params.q.as_str() };
+    let term_stats = get_query_term_stats(q, params.synonyms.then_some(synonyms), &expansions, backend).await?;
+    let suggestions = match &query {
+        _ if total_count > 0 || !params.suggest => None,
+        Some(query) => Some(suggest(query, backend).await),
+        None => Some(Vec::new()),
+    };
+
+    let timed_out_after_ms = budget.filter(|_| partial).map(|budget| budget.as_millis() as u64);
+    if let Some(ms) = timed_out_after_ms {
+        warn!("Search for {:?} ran out of its {} ms budget; returning partial results", params.q, ms);
+        metrics().record_partial_response();
+    }
 
-    Ok(Json(SearchResponse {
+    let response = SearchResponse {
+        parsed_query: query.map(|query| query.to_string()).unwrap_or_default(),
+        filters: build_filters_map(&params),
         query: params.q,
-        filters,
         count: results.len(),
+        total_count,
+        limit,
+        offset,
         results,
-    }))
+        facets,
+        suggestions,
+        term_stats,
+        partial,
+        timed_out_after_ms,
+    };
+    Ok((response, book_ids))
+}
+
+/// Identifies a search by everything that shapes its response: the
+/// normalized query, the filters and the page.
+fn result_cache_key(query: Option<&BooleanQuery>, params: &SearchParams) -> String {
+    let parsed = query.map(ToString::to_string).unwrap_or_default();
+    format!(
+        "{}|mode={}|sort={}|author={:?}|language={:?}|year={:?}|years={:?}..{:?}|words={:?}..{:?}|reading_level<={:?}|subject={:?}|dedupe={}|regex={}|facets={:?}|snippets={}|highlight_body={}|suggest={}|export={}|limit={}|offset={}",
+        parsed,
+        params.mode.as_str(),
+        params.sort.as_str(),
+        params.author,
+        params.language,
+        params.year,
+        params.year_range().0,
+        params.year_range().1,
+        params.min_word_count,
+        params.max_word_count,
+        params.max_reading_level,
+        params.subject,
+        params.dedupe,
+        params.regex,
+        params.facets,
+        params.snippets,
+        params.highlight_body,
+        params.suggest,
+        params.export,
+        params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        params.offset.unwrap_or(0),
+    )
+}
+
+/// The cached body, with `query` echoing this request's spelling of it.
+fn respell_query(cached: CachedSearch, q: &str) -> Bytes {
+    if cached.query == q {
+        return cached.body;
+    }
+    let Ok(mut body) = serde_json::from_slice::<serde_json::Value>(&cached.body) else {
+        return cached.body;
+    };
+    body["query"] = q.into();
+    serde_json::to_vec(&body).map_or(cached.body, Bytes::from)
+}
+
+fn json_response(body: Bytes, cache_status: &'static str, validators: Option<&Validators>) -> Response {
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("application/json")),
+            (HeaderName::from_static("x-cache"), HeaderValue::from_static(cache_status)),
+        ],
+        body,
+    )
+        .into_response();
+    if let Some(validators) = validators {
+        validators.apply(response.headers_mut());
+    }
+    response
 }
 
 fn build_filters_map(params: &SearchParams) -> HashMap<String, String> {
     let mut filters = HashMap::new();
+    filters.insert("mode".to_string(), params.mode.as_str().to_string());
+    filters.insert("sort".to_string(), params.sort.as_str().to_string());
 
     if let Some(ref author) = params.author {
         filters.insert("author".to_string(), author.clone());
@@ -215,6 +1042,964 @@ fn build_filters_map(params: &SearchParams) -> HashMap<String, String> {
     if let Some(year) = params.year {
         filters.insert("year".to_string(), year.to_string());
     }
+    let (year_from, year_to) = params.year_range();
+    if let Some(year_from) = year_from {
+        filters.insert("year_from".to_string(), year_from.to_string());
+    }
+    if let Some(year_to) = year_to {
+        filters.insert("year_to".to_string(), year_to.to_string());
+    }
+    if params.min_word_count.is_some() || params.max_word_count.is_some() {
+        let bound = |value: Option<usize>| value.map_or("*".to_string(), |n| n.to_string());
+        filters.insert(
+            "word_count_range".to_string(),
+            format!("{}-{}", bound(params.min_word_count), bound(params.max_word_count)),
+        );
+    }
+    if let Some(max_reading_level) = params.max_reading_level {
+        filters.insert("max_reading_level".to_string(), max_reading_level.to_string());
+    }
+    if let Some(ref subject) = params.subject {
+        filters.insert("subject".to_string(), subject.clone());
+    }
+    if params.dedupe {
+        filters.insert("dedupe".to_string(), "true".to_string());
+    }
+    if params.regex {
+        filters.insert("regex".to_string(), "true".to_string());
+    }
 
     filters
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{IndexField, MemoryBackend, StorageBackend};
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn book(book_id: u32, year: Option<u32>) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: format!("Book {}", book_id),
+            author: "Anonymous".to_string(),
+            language: "en".to_string(),
+            year,
+            ..Default::default()
+        }
+    }
+
+    fn params(year_from: Option<u32>, year_to: Option<u32>) -> SearchParams {
+        SearchParams {
+            q: "book".to_string(),
+            author: None,
+            language: None,
+            year: None,
+            year_from,
+            year_to,
+            year_min: None,
+            year_max: None,
+            min_word_count: None,
+            max_word_count: None,
+            max_reading_level: None,
+            subject: None,
+            highlight_body: false,
+            snippets: false,
+            mode: MatchMode::default(),
+            sort: SortOrder::default(),
+            facets: Vec::new(),
+            limit: None,
+            offset: None,
+            suggest: true,
+            synonyms: true,
+            dedupe: false,
+            regex: false,
+            timeout_ms: None,
+            export: false,
+        }
+    }
+
+    fn filtered_ids(params: &SearchParams) -> Vec<u32> {
+        let books = vec![book(1, Some(1813)), book(2, Some(1818)), book(3, None)];
+        apply_filters(books, params).iter().map(|book| book.book_id).collect()
+    }
+
+    #[test]
+    fn year_range_bounds_are_inclusive() {
+        assert_eq!(filtered_ids(&params(Some(1815), None)), [2]);
+        assert_eq!(filtered_ids(&params(None, Some(1813))), [1]);
+        assert_eq!(filtered_ids(&params(Some(1813), Some(1818))), [1, 2]);
+        assert!(filtered_ids(&params(Some(1819), Some(1820))).is_empty());
+    }
+
+    #[test]
+    fn unknown_years_only_pass_without_a_range() {
+        assert_eq!(filtered_ids(&params(None, None)), [1, 2, 3]);
+    }
+
+    #[test]
+    fn author_filter_matches_substrings_case_insensitively() {
+        let mut books = vec![book(1, None), book(2, None)];
+        books[0].author = "Jane Austen".to_string();
+        books[1].author = "Mary Wollstonecraft Shelley".to_string();
+        let params = SearchParams {
+            author: Some("AUSTEN".to_string()),
+            ..params(None, None)
+        };
+
+        let ids: Vec<u32> = apply_filters(books, &params).iter().map(|b| b.book_id).collect();
+        assert_eq!(ids, [1]);
+    }
+
+    #[test]
+    fn filters_are_echoed_normalized() {
+        let uri: axum::http::Uri = "/search?q=love&author=%20Jane%20AUSTEN%20&language=EN&year_from=1800&year_to=1850"
+            .parse()
+            .unwrap();
+        let Query(params) = Query::<SearchParams>::try_from_uri(&uri).unwrap();
+        let filters = build_filters_map(&params);
+
+        assert_eq!(filters["author"], "jane austen");
+        assert_eq!(filters["language"], "en");
+        assert_eq!(filters["year_from"], "1800");
+        assert_eq!(filters["year_to"], "1850");
+
+        // The older names are echoed under the current ones
+        let uri: axum::http::Uri = "/search?q=love&year_min=1800&year_to=1850".parse().unwrap();
+        let Query(params) = Query::<SearchParams>::try_from_uri(&uri).unwrap();
+        let filters = build_filters_map(&params);
+        assert_eq!(filters["year_from"], "1800");
+        assert!(!filters.contains_key("year_min"));
+    }
+
+    #[test]
+    fn blank_text_filters_are_ignored() {
+        let uri: axum::http::Uri = "/search?q=love&author=&language=%20&subject=".parse().unwrap();
+        let Query(params) = Query::<SearchParams>::try_from_uri(&uri).unwrap();
+
+        assert_eq!(params.author, None);
+        assert_eq!(params.language, None);
+        assert_eq!(params.subject, None);
+        let mut keys: Vec<String> = build_filters_map(&params).into_keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["mode", "sort"]);
+    }
+
+    fn word_count_params(min: Option<usize>, max: Option<usize>) -> SearchParams {
+        SearchParams {
+            min_word_count: min,
+            max_word_count: max,
+            ..params(None, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn word_count_range_keeps_books_within_bounds() {
+        let backend: Backend = Arc::new(MemoryBackend::new());
+        for (book_id, word_count) in [(1, 4_000), (2, 10_000), (3, 50_000), (4, 120_000)] {
+            let metadata = BookMetadata { word_count, ..book(book_id, None) };
+            backend.store_book_metadata(&metadata).await.unwrap();
+        }
+        let cache = MetadataCache::new(10, std::time::Duration::from_secs(60));
+        let books = get_book_metadata_batch(&HashSet::from([1, 2, 3, 4]), &backend, &cache).await;
+        let ids = |params: SearchParams| {
+            let mut ids: Vec<u32> = apply_filters(books.clone(), &params).iter().map(|b| b.book_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        assert_eq!(ids(word_count_params(None, Some(9_999))), [1]);
+        assert_eq!(ids(word_count_params(Some(50_001), None)), [4]);
+        assert_eq!(ids(word_count_params(Some(10_000), Some(50_000))), [2, 3]);
+        assert!(ids(word_count_params(Some(60_000), Some(100_000))).is_empty());
+        assert_eq!(ids(word_count_params(None, None)), [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn max_reading_level_excludes_harder_and_unscored_books() {
+        let backend: Backend = Arc::new(MemoryBackend::new());
+        for (book_id, reading_level) in [(1, Some(4.2)), (2, Some(6.0)), (3, Some(11.5)), (4, None)] {
+            let metadata = BookMetadata { reading_level, ..book(book_id, None) };
+            backend.store_book_metadata(&metadata).await.unwrap();
+        }
+        let cache = MetadataCache::new(10, std::time::Duration::from_secs(60));
+        let books = get_book_metadata_batch(&HashSet::from([1, 2, 3, 4]), &backend, &cache).await;
+        let params = SearchParams { max_reading_level: Some(6.0), ..params(None, None) };
+
+        let mut ids: Vec<u32> = apply_filters(books, &params).iter().map(|b| b.book_id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(build_filters_map(&params)["max_reading_level"], "6");
+    }
+
+    #[test]
+    fn word_count_range_is_reported_in_filters() {
+        let filters = |min, max| build_filters_map(&word_count_params(min, max));
+
+        assert_eq!(filters(Some(10_000), Some(50_000))["word_count_range"], "10000-50000");
+        assert_eq!(filters(None, Some(10_000))["word_count_range"], "*-10000");
+        assert_eq!(filters(Some(50_000), None)["word_count_range"], "50000-*");
+        assert!(!filters(None, None).contains_key("word_count_range"));
+    }
+
+    #[test]
+    fn range_is_reported_in_filters() {
+        let filters = build_filters_map(&params(Some(1800), Some(1850)));
+        assert_eq!(filters["year_from"], "1800");
+        assert_eq!(filters["year_to"], "1850");
+    }
+
+    /// A backend holding `count` books that all contain "whale".
+    async fn whale_books(count: u32) -> Backend {
+        let backend = MemoryBackend::new();
+        for book_id in 1..=count {
+            backend.store_book_metadata(&book(book_id, Some(1800 + book_id))).await.unwrap();
+            backend.add_word_to_index("whale", book_id, IndexField::Body).await.unwrap();
+        }
+        Arc::new(backend)
+    }
+
+    async fn get_json(backend: Backend, uri: &str) -> (StatusCode, Value) {
+        let response = crate::app(backend)
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn result_ids(body: &Value) -> Vec<u64> {
+        body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["book_id"].as_u64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pages_partition_the_full_result_set() {
+        let backend = whale_books(5).await;
+
+        let (status, first) = get_json(backend.clone(), "/search?q=whale&limit=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first["total_count"], 5);
+        assert_eq!(first["count"], 2);
+        assert_eq!(first["limit"], 2);
+        assert_eq!(first["offset"], 0);
+
+        let mut seen = result_ids(&first);
+        for offset in [2, 4] {
+            let (_, page) = get_json(backend.clone(), &format!("/search?q=whale&limit=2&offset={}", offset)).await;
+            assert_eq!(page["total_count"], 5);
+            seen.extend(result_ids(&page));
+        }
+        assert_eq!(seen, [1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn pages_follow_the_requested_sort() {
+        let (_, page) = get_json(whale_books(5).await, "/search?q=whale&sort=year_desc&limit=2&offset=1").await;
+        assert_eq!(result_ids(&page), [4, 3]);
+    }
+
+    /// Books whose titles differ in case and diacritics, some without a year.
+    async fn french_books() -> Backend {
+        let backend = MemoryBackend::new();
+        let books = [
+            (1, "Zadig", Some(1747)),
+            (2, "émile", None),
+            (3, "Candide", Some(1759)),
+            (4, "Emma", Some(1815)),
+            (5, "Ève future", None),
+            (6, "eugénie Grandet", Some(1833)),
+        ];
+        for (book_id, title, year) in books {
+            let metadata = BookMetadata {
+                title: title.to_string(),
+                ..book(book_id, year)
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            backend.add_word_to_index("roman", book_id, IndexField::Body).await.unwrap();
+        }
+        Arc::new(backend)
+    }
+
+    /// Every result for `uri`, fetched two at a time.
+    async fn all_pages(backend: &Backend, uri: &str) -> Vec<u64> {
+        let mut ids = Vec::new();
+        for offset in (0..6).step_by(2) {
+            let (_, page) = get_json(backend.clone(), &format!("{}&limit=2&offset={}", uri, offset)).await;
+            ids.extend(result_ids(&page));
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn sorts_apply_before_pagination() {
+        let backend = french_books().await;
+        let cases: [(&str, &[u64]); 4] = [
+            ("title", &[3, 2, 4, 6, 5, 1]),
+            ("year_asc", &[1, 3, 4, 6, 2, 5]),
+            ("year_desc", &[6, 4, 3, 1, 2, 5]),
+            ("relevance", &[1, 2, 3, 4, 5, 6]),
+        ];
+
+        for (sort, expected) in cases {
+            let uri = format!("/search?q=roman&sort={}", sort);
+            let (status, full) = get_json(backend.clone(), &format!("{}&limit=10", uri)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(result_ids(&full), expected, "sort={}", sort);
+            assert_eq!(all_pages(&backend, &uri).await, expected, "paged sort={}", sort);
+        }
+    }
+
+    #[tokio::test]
+    async fn identical_requests_return_identical_order() {
+        let backend = french_books().await;
+        for sort in ["relevance", "title", "year_desc"] {
+            let uri = format!("/search?q=roman&sort={}", sort);
+            let (_, first) = get_json(backend.clone(), &uri).await;
+            for _ in 0..5 {
+                let (_, again) = get_json(backend.clone(), &uri).await;
+                assert_eq!(result_ids(&again), result_ids(&first), "sort={}", sort);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn effective_sort_is_echoed() {
+        let backend = french_books().await;
+
+        let (_, body) = get_json(backend.clone(), "/search?q=roman").await;
+        assert_eq!(body["filters"]["sort"], "relevance");
+        let (_, body) = get_json(backend, "/search?q=roman&sort=title").await;
+        assert_eq!(body["filters"]["sort"], "title_asc");
+    }
+
+    #[tokio::test]
+    async fn unknown_sort_lists_the_allowed_options() {
+        let (status, body) = get_json(french_books().await, "/search?q=roman&sort=newest").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let message = body["message"].as_str().unwrap();
+        for option in ["relevance", "year_asc", "year_desc", "title", "title_asc", "author_asc"] {
+            assert!(message.contains(&format!("`{}`", option)), "{}", message);
+        }
+    }
+
+    #[tokio::test]
+    async fn snippets_are_omitted_without_a_body() {
+        // None of these books is in the datalake
+        let (status, body) = get_json(whale_books(3).await, "/search?q=whale&snippets=true").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 3);
+        assert!(body["results"].as_array().unwrap().iter().all(|result| result.get("snippet").is_none()));
+    }
+
+    #[tokio::test]
+    async fn limit_defaults_and_is_capped() {
+        let backend = whale_books(3).await;
+
+        let (_, page) = get_json(backend.clone(), "/search?q=whale").await;
+        assert_eq!(page["limit"], DEFAULT_LIMIT);
+        assert_eq!(page["count"], 3);
+
+        let (_, page) = get_json(backend, "/search?q=whale&limit=1000").await;
+        assert_eq!(page["limit"], MAX_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn responses_are_gzipped_when_the_client_accepts_it() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let backend = whale_books(MAX_LIMIT as u32).await;
+        let uri = format!("/search?q=whale&limit={}", MAX_LIMIT);
+        let plain = to_bytes(
+            crate::app(backend.clone())
+                .oneshot(Request::get(&uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .into_body(),
+            usize::MAX,
+        )
+        .await
+        .unwrap();
+
+        let response = crate::app(backend)
+            .oneshot(Request::get(&uri).header("accept-encoding", "gzip").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        let gzipped = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        let mut json = Vec::new();
+        GzDecoder::new(&gzipped[..]).read_to_end(&mut json).unwrap();
+        let page: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(page["count"], MAX_LIMIT);
+        assert_eq!(json.len(), plain.len());
+        assert!(gzipped.len() * 4 < plain.len(), "{} bytes gzipped to {}", plain.len(), gzipped.len());
+    }
+
+    #[tokio::test]
+    async fn offset_past_the_end_returns_an_empty_page() {
+        let (status, page) = get_json(whale_books(3).await, "/search?q=whale&offset=10").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total_count"], 3);
+        assert_eq!(page["count"], 0);
+        assert!(result_ids(&page).is_empty());
+    }
+
+    async fn get_export(backend: Backend, uri: &str, accept: &str) -> (StatusCode, String, String) {
+        let response = crate::app(backend)
+            .oneshot(Request::get(uri).header(header::ACCEPT, accept).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response.headers()[header::CONTENT_TYPE].to_str().unwrap().to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn csv_and_ndjson_exports_follow_the_accept_header() {
+        let backend = whale_books(30).await;
+
+        // Paged like JSON unless exporting
+        let (status, content_type, csv) = get_export(backend.clone(), "/search?q=whale", "text/csv").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "text/csv; charset=utf-8");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "book_id,title,author,language,year,score");
+        assert_eq!(lines.len(), 1 + DEFAULT_LIMIT);
+
+        let (_, _, csv) = get_export(backend.clone(), "/search?q=whale&export=true&limit=5&offset=10", "text/csv").await;
+        assert_eq!(csv.lines().count(), 1 + 30);
+        assert!(csv.ends_with("\r\n"));
+
+        let (status, content_type, ndjson) =
+            get_export(backend.clone(), "/search?q=whale&export=true", "application/x-ndjson").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/x-ndjson");
+        let books: Vec<Value> = ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(books.len(), 30);
+        assert!(books.iter().all(|book| book["book_id"].is_u64() && book["score"].is_f64()));
+
+        // JSON stays the default, and export=true returns every match there too
+        let (_, body) = get_json(backend.clone(), "/search?q=whale&export=true").await;
+        assert_eq!(result_ids(&body).len(), 30);
+        assert_eq!(body["limit"], 30);
+
+        // Errors keep their JSON shape
+        let (status, content_type, _) =
+            get_export(backend, "/search?q=whale&year_from=1900&year_to=1800", "text/csv").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, "application/json");
+    }
+
+    #[tokio::test]
+    async fn misspelled_queries_suggest_the_indexed_word() {
+        let backend = whale_books(3).await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=whael").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_count"], 0);
+        assert_eq!(body["suggestions"], json!(["whale"]));
+
+        let (_, body) = get_json(backend.clone(), "/search?q=whael&suggest=false").await;
+        assert!(body.get("suggestions").is_none());
+        let (_, body) = get_json(backend, "/search?q=whale").await;
+        assert!(body.get("suggestions").is_none());
+    }
+
+    #[tokio::test]
+    async fn nonsense_queries_suggest_nothing() {
+        let (status, body) = get_json(whale_books(3).await, "/search?q=xqzvbnk").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["suggestions"], json!([]));
+    }
+
+    /// "pride" is in books 1 and 2, "prejudice" in books 2 and 3.
+    async fn pride_and_prejudice_books() -> Backend {
+        let backend = MemoryBackend::new();
+        for (book_id, words) in [(1, &["pride"][..]), (2, &["pride", "prejudice"]), (3, &["prejudice"])] {
+            backend.store_book_metadata(&book(book_id, None)).await.unwrap();
+            for word in words {
+                backend.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        Arc::new(backend)
+    }
+
+    #[tokio::test]
+    async fn term_stats_show_each_word_found_unfound_or_filtered() {
+        let backend = pride_and_prejudice_books().await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=Pride%20prejudice%20zanzibar%20of%20x86").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_count"], 0);
+        assert_eq!(
+            body["term_stats"],
+            json!({
+                "pride": {"doc_freq": 2, "found": true},
+                "prejudice": {"doc_freq": 2, "found": true},
+                "zanzibar": {"doc_freq": 0, "found": false},
+                "of": {"doc_freq": 0, "found": false, "filtered": true, "reason": "shorter than 3 letters"},
+                "x86": {
+                    "doc_freq": 0,
+                    "found": false,
+                    "filtered": true,
+                    "reason": "contains non-alphabetic characters"
+                },
+            })
+        );
+
+        // Operators aren't words, negated words still are
+        let (_, body) = get_json(backend, "/search?q=pride%20AND%20NOT%20prejudice").await;
+        let words: Vec<&String> = body["term_stats"].as_object().unwrap().keys().collect();
+        assert_eq!(words, ["prejudice", "pride"]);
+    }
+
+    #[tokio::test]
+    async fn mode_all_requires_every_word() {
+        let backend = pride_and_prejudice_books().await;
+
+        for uri in ["/search?q=pride%20prejudice", "/search?q=pride%20prejudice&mode=all"] {
+            let (status, body) = get_json(backend.clone(), uri).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(result_ids(&body), [2]);
+            assert_eq!(body["filters"]["mode"], "all");
+        }
+    }
+
+    #[tokio::test]
+    async fn mode_any_matches_either_word_ranking_both_first() {
+        let (_, body) = get_json(pride_and_prejudice_books().await, "/search?q=pride%20prejudice&mode=any").await;
+
+        assert_eq!(body["filters"]["mode"], "any");
+        assert_eq!(body["total_count"], 3);
+        assert_eq!(result_ids(&body)[0], 2);
+    }
+
+    #[tokio::test]
+    async fn boolean_queries_are_evaluated_and_echoed() {
+        let backend = pride_and_prejudice_books().await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=pride%20NOT%20prejudice").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result_ids(&body), [1]);
+        assert_eq!(body["parsed_query"], "pride AND NOT prejudice");
+
+        let (_, body) = get_json(backend, "/search?q=(pride%20OR%20prejudice)%20AND%20of").await;
+        assert_eq!(body["total_count"], 3);
+        assert_eq!(body["parsed_query"], "pride OR prejudice");
+    }
+
+    #[tokio::test]
+    async fn field_scoped_terms_mix_with_body_terms() {
+        let backend = MemoryBackend::new();
+        let postings: [(u32, &[(&str, IndexField)]); 3] = [
+            (1, &[("pride", IndexField::Title), ("austen", IndexField::Author), ("whale", IndexField::Body)]),
+            (2, &[("pride", IndexField::Title), ("austen", IndexField::Author)]),
+            (3, &[("pride", IndexField::Body), ("austen", IndexField::Author), ("whale", IndexField::Body)]),
+        ];
+        for (book_id, words) in postings {
+            backend.store_book_metadata(&book(book_id, None)).await.unwrap();
+            for &(word, field) in words {
+                backend.add_word_to_index(word, book_id, field).await.unwrap();
+            }
+        }
+
+        let (status, body) = get_json(Arc::new(backend), "/search?q=title:pride%20author:Austen%20whale").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result_ids(&body), [1]);
+        assert_eq!(body["parsed_query"], "title:pride AND author:austen AND whale");
+        let words: Vec<&String> = body["term_stats"].as_object().unwrap().keys().collect();
+        assert_eq!(words, ["austen", "pride", "whale"]);
+    }
+
+    #[tokio::test]
+    async fn unknown_query_fields_are_rejected() {
+        let (status, body) = get_json(pride_and_prejudice_books().await, "/search?q=pride%20isbn:foo").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+        assert_eq!(body["message"], "unknown field 'isbn' (expected title, author or body) at position 6");
+    }
+
+    #[tokio::test]
+    async fn malformed_boolean_queries_are_rejected() {
+        let (status, body) = get_json(pride_and_prejudice_books().await, "/search?q=pride%20AND%20(prejudice").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+        assert_eq!(body["message"], "unclosed '(' at position 10");
+    }
+
+    #[tokio::test]
+    async fn wildcards_match_every_expanded_word_and_list_them() {
+        let backend = pride_and_prejudice_books().await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=pr*").await;
+
+        assert_eq!(status, StatusCode::OK);
+        // Book 2 holds both words, so it ranks first
+        assert_eq!(result_ids(&body), [2, 1, 3]);
+        assert_eq!(body["parsed_query"], "prejudice OR pride");
+        assert_eq!(
+            body["term_stats"],
+            json!({
+                "pr*": {"doc_freq": 3, "found": true, "expansions": ["prejudice", "pride"]},
+                "prejudice": {"doc_freq": 2, "found": true, "expansion_of": "pr*"},
+                "pride": {"doc_freq": 2, "found": true, "expansion_of": "pr*"},
+            })
+        );
+
+        // The expansion fills the pattern's slot in the query
+        let (_, body) = get_json(backend.clone(), "/search?q=pre*%20NOT%20pride").await;
+        assert_eq!(result_ids(&body), [3]);
+
+        let (status, body) = get_json(backend, "/search?q=p*").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+        assert_eq!(body["message"], "wildcard 'p*' needs at least 2 letters before '*' at position 0");
+    }
+
+    #[tokio::test]
+    async fn regex_terms_match_whole_words_and_reject_malformed_patterns() {
+        let backend = pride_and_prejudice_books().await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=pri.e&regex=true").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result_ids(&body), [1, 2]);
+        assert_eq!(body["filters"]["regex"], "true");
+        assert_eq!(body["term_stats"]["pri.e"]["expansions"], json!(["pride"]));
+        // Without regex=true the dot is just punctuation
+        let (_, body) = get_json(backend.clone(), "/search?q=pri.e").await;
+        assert!(body["term_stats"].get("pri.e").is_none());
+
+        let (status, body) = get_json(backend, "/search?q=pri%5Bde&regex=true").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+        assert_eq!(body["message"], "invalid regex 'pri[de': unclosed character class at position 0");
+    }
+
+    #[tokio::test]
+    async fn unknown_mode_is_rejected() {
+        let (status, body) = get_json(pride_and_prejudice_books().await, "/search?q=pride&mode=some").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn malformed_paging_parameters_are_rejected() {
+        for uri in ["/search?q=whale&limit=-1", "/search?q=whale&limit=0", "/search?q=whale&offset=abc"] {
+            let (status, body) = get_json(whale_books(1).await, uri).await;
+
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+            assert_eq!(body["error_code"], "INVALID_QUERY");
+        }
+    }
+
+    /// Books by different authors, years and languages, all containing "love".
+    async fn love_books() -> Backend {
+        let backend = MemoryBackend::new();
+        let books = [
+            (1342, "Jane Austen", "en", Some(1813)),
+            (158, "Jane Austen", "en", Some(1815)),
+            (17797, "Jane Austen", "fr", Some(1822)),
+            (84, "Mary Wollstonecraft Shelley", "en", Some(1818)),
+            (3268, "Ann Radcliffe", "en", Some(1794)),
+            (4276, "Joanna Baillie", "en", Some(1798)),
+            (9999, "Jane Austen", "en", None),
+        ];
+        for (book_id, author, language, year) in books {
+            let metadata = BookMetadata {
+                author: author.to_string(),
+                language: language.to_string(),
+                ..book(book_id, year)
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            backend.add_word_to_index("love", book_id, IndexField::Body).await.unwrap();
+            for word in index_terms(author) {
+                backend.add_word_to_index(&word, book_id, IndexField::Author).await.unwrap();
+            }
+        }
+        Arc::new(backend)
+    }
+
+    #[tokio::test]
+    async fn author_year_range_and_language_filters_combine() {
+        let backend = love_books().await;
+        let uri = |filters: &str| format!("/search?q=love&sort=year_asc&{}", filters);
+
+        let (status, body) = get_json(backend.clone(), &uri("author=austen&year_from=1810&year_to=1820")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result_ids(&body), [1342, 158]);
+
+        let (_, body) = get_json(backend.clone(), &uri("author=AUSTEN&year_from=1810&language=fr")).await;
+        assert_eq!(result_ids(&body), [17797]);
+        assert_eq!(body["filters"]["author"], "austen");
+        assert_eq!(body["filters"]["language"], "fr");
+        assert_eq!(body["filters"]["year_from"], "1810");
+
+        // An unknown year never falls in a range
+        let (_, body) = get_json(backend.clone(), &uri("author=austen&year_to=1900")).await;
+        assert!(!result_ids(&body).contains(&9999));
+
+        let (_, body) = get_json(backend, &uri("year_from=1790&year_to=1799&language=en")).await;
+        assert_eq!(result_ids(&body), [3268, 4276]);
+    }
+
+    #[tokio::test]
+    async fn author_prefers_whole_indexed_words() {
+        let backend = love_books().await;
+
+        // "ann" is an indexed author word, so "Joanna" no longer matches
+        let (_, body) = get_json(backend.clone(), "/search?q=love&author=ann").await;
+        assert_eq!(result_ids(&body), [3268]);
+
+        // A partial name isn't indexed and falls back to the substring match
+        let (_, body) = get_json(backend, "/search?q=love&author=wollstone").await;
+        assert_eq!(result_ids(&body), [84]);
+    }
+
+    #[tokio::test]
+    async fn empty_year_range_is_rejected() {
+        let (status, body) = get_json(love_books().await, "/search?q=love&year_from=1850&year_to=1800").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+        assert_eq!(body["message"], "year_from (1850) is after year_to (1800)");
+
+        let (_, body) = get_json(love_books().await, "/search?q=love&year_min=1850&year_max=1800").await;
+        assert_eq!(body["message"], "year_from (1850) is after year_to (1800)");
+    }
+
+    #[tokio::test]
+    async fn a_year_bound_under_both_names_is_rejected() {
+        let (status, body) = get_json(love_books().await, "/search?q=love&year_from=1810&year_min=1800").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+        assert_eq!(body["message"], "year_from and year_min are the same filter; send only year_from");
+    }
+
+    #[tokio::test]
+    async fn facets_count_every_matching_book() {
+        let backend = love_books().await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=love&facets=language,author,decade&limit=2").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["facets"]["language"], json!({ "en": 6, "fr": 1 }));
+        assert_eq!(body["facets"]["author"]["Jane Austen"], 4);
+        assert_eq!(
+            body["facets"]["decade"],
+            json!({ "1790s": 2, "1810s": 3, "1820s": 1, "unknown": 1 })
+        );
+
+        // Facets follow the filters, and are left out unless requested
+        let (_, body) = get_json(backend.clone(), "/search?q=love&language=fr&facets=decade").await;
+        assert_eq!(body["facets"], json!({ "decade": { "1820s": 1 } }));
+        let (_, body) = get_json(backend, "/search?q=love").await;
+        assert!(body.get("facets").is_none());
+    }
+
+    /// Two editions of Moby Dick, the first with a title match, and a
+    /// third book by the same author.
+    async fn moby_dick_editions() -> Backend {
+        let backend = MemoryBackend::new();
+        let books = [(2701, "Moby Dick; Or, The Whale"), (15, "MOBY DICK, OR THE WHALE"), (2489, "Typee")];
+        for (book_id, title) in books {
+            let metadata = BookMetadata {
+                title: title.to_string(),
+                author: "Herman Melville".to_string(),
+                ..book(book_id, Some(1851))
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            backend.add_word_to_index("whale", book_id, IndexField::Body).await.unwrap();
+        }
+        backend.add_word_to_index("whale", 2701, IndexField::Title).await.unwrap();
+        Arc::new(backend)
+    }
+
+    #[tokio::test]
+    async fn dedupe_collapses_editions_into_the_best_scored_one() {
+        let backend = moby_dick_editions().await;
+
+        let (status, body) = get_json(backend.clone(), "/search?q=whale&dedupe=true&facets=decade").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_count"], 2);
+        assert_eq!(result_ids(&body), [2701, 2489]);
+        assert_eq!(body["results"][0]["also_available_as"], json!([15]));
+        assert!(body["results"][1].get("also_available_as").is_none());
+        assert_eq!(body["facets"]["decade"], json!({ "1850s": 2 }));
+        assert_eq!(body["filters"]["dedupe"], "true");
+
+        // Without scores the lowest ID stands for the edition
+        let (_, body) = get_json(backend.clone(), "/search?q=whale&dedupe=true&sort=title_asc").await;
+        assert_eq!(result_ids(&body), [15, 2489]);
+        assert_eq!(body["results"][0]["also_available_as"], json!([2701]));
+
+        let (_, body) = get_json(backend, "/search?q=whale&facets=decade").await;
+        assert_eq!(body["total_count"], 3);
+        assert_eq!(body["facets"]["decade"], json!({ "1850s": 3 }));
+        assert!(body["results"].as_array().unwrap().iter().all(|book| book.get("also_available_as").is_none()));
+    }
+
+    #[tokio::test]
+    async fn unknown_facets_are_rejected() {
+        let (status, body) = get_json(love_books().await, "/search?q=love&facets=genre").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+    }
+
+    /// Sends `request` to `app`, returning the `X-Cache` header and the body.
+    async fn send(app: &axum::Router, request: Request<Body>) -> (Option<String>, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let cache = response
+            .headers()
+            .get("x-cache")
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (cache, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn search(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn repeated_searches_hit_the_cache_until_invalidated() {
+        let backend = whale_books(3).await;
+        let app = crate::app(backend.clone());
+
+        let (cache, first) = send(&app, search("/search?q=whale")).await;
+        assert_eq!(cache.as_deref(), Some("miss"));
+        assert_eq!(first["total_count"], 3);
+
+        // A newly indexed book isn't seen while the response is cached; the
+        // cached body still echoes each request's spelling of the query
+        backend.store_book_metadata(&book(4, Some(1804))).await.unwrap();
+        backend.add_word_to_index("whale", 4, IndexField::Body).await.unwrap();
+        let (cache, second) = send(&app, search("/search?q=%20Whale")).await;
+        assert_eq!(cache.as_deref(), Some("hit"));
+        assert_eq!(second["total_count"], 3);
+        assert_eq!(second["query"], " Whale");
+
+        // Another page is another entry
+        let (cache, _) = send(&app, search("/search?q=whale&limit=2")).await;
+        assert_eq!(cache.as_deref(), Some("miss"));
+
+        let (_, invalidated) = send(&app, Request::post("/cache/invalidate").body(Body::empty()).unwrap()).await;
+        assert_eq!(invalidated, json!({"status": "invalidated"}));
+
+        let (cache, third) = send(&app, search("/search?q=whale")).await;
+        assert_eq!(cache.as_deref(), Some("miss"));
+        assert_eq!(third["total_count"], 4);
+    }
+
+    #[tokio::test]
+    async fn invalid_searches_are_not_cached() {
+        let app = crate::app(whale_books(1).await);
+
+        let (cache, body) = send(&app, search("/search?q=whale&year_from=1900&year_to=1800")).await;
+        assert_eq!(cache, None);
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn searches_out_of_time_return_the_books_found_so_far() {
+        let memory = MemoryBackend::new();
+        for (book_id, word) in [(1, "pride"), (2, "prejudice")] {
+            memory.store_book_metadata(&book(book_id, None)).await.unwrap();
+            memory.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+        }
+        memory.slow_down_word("prejudice", Duration::from_secs(30));
+        let app = crate::app(Arc::new(memory));
+
+        let started = Instant::now();
+        let (cache, body) = send(&app, search("/search?q=pride%20OR%20prejudice&timeout_ms=100")).await;
+
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+        assert_eq!(body["partial"], true);
+        assert_eq!(body["timed_out_after_ms"], 100);
+        assert_eq!(result_ids(&body), [1]);
+        // Partial responses are never served from the cache
+        assert_eq!(cache.as_deref(), Some("miss"));
+        let (cache, _) = send(&app, search("/search?q=pride%20OR%20prejudice&timeout_ms=100")).await;
+        assert_eq!(cache.as_deref(), Some("miss"));
+
+        let (_, body) = send(&app, search("/search?q=pride&timeout_ms=100")).await;
+        assert!(body.get("partial").is_none());
+        assert!(body.get("timed_out_after_ms").is_none());
+    }
+
+    #[tokio::test]
+    async fn chapter_hits_count_against_the_budget() {
+        let memory = MemoryBackend::new();
+        for book_id in [1, 2] {
+            memory.store_book_metadata(&BookMetadata { chapter_count: 3, ..book(book_id, None) }).await.unwrap();
+            memory.add_word_to_index("whale", book_id, IndexField::Body).await.unwrap();
+            memory.add_word_to_chapter_index("whale", book_id, 2).await.unwrap();
+        }
+        let app = crate::app(Arc::new(memory.clone()));
+
+        // Only the page's books get their chapters
+        let (_, body) = send(&app, search("/search?q=whale&sort=year_asc&limit=1")).await;
+        assert_eq!(body["results"][0]["chapters"], json!([2]));
+        assert_eq!(body["results"].as_array().unwrap().len(), 1);
+
+        // Finding the books fits the budget, their chapters no longer do
+        memory.slow_down_word("whale", Duration::from_millis(400));
+        let (_, body) = send(&app, search("/search?q=whale&sort=year_asc&timeout_ms=600")).await;
+        assert_eq!(body["partial"], true);
+        assert_eq!(result_ids(&body), [1, 2]);
+        assert!(body["results"][0].get("chapters").is_none());
+    }
+
+    #[tokio::test]
+    async fn unchanged_results_are_not_modified() {
+        let backend = whale_books(3).await;
+        let app = crate::app(backend.clone());
+        let revalidate = |etag: &str| Request::get("/search?q=whale").header("if-none-match", etag).body(Body::empty()).unwrap();
+
+        let first = app.clone().oneshot(search("/search?q=whale")).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert!(first.headers().contains_key("last-modified"));
+        let etag = first.headers()["etag"].to_str().unwrap().to_string();
+
+        let second = app.clone().oneshot(revalidate(&etag)).await.unwrap();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(second.headers()["etag"], etag.as_str());
+        assert!(to_bytes(second.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        // The cached response still matches; once it's gone the new book shows
+        backend.store_book_metadata(&book(4, Some(1804))).await.unwrap();
+        backend.add_word_to_index("whale", 4, IndexField::Body).await.unwrap();
+        let cached = app.clone().oneshot(revalidate(&etag)).await.unwrap();
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+        send(&app, Request::post("/cache/invalidate").body(Body::empty()).unwrap()).await;
+        let changed = app.clone().oneshot(revalidate(&etag)).await.unwrap();
+        assert_eq!(changed.status(), StatusCode::OK);
+        assert_ne!(changed.headers()["etag"], etag.as_str());
+        let body: Value = serde_json::from_slice(&to_bytes(changed.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["total_count"], 4);
+    }
+}
@@ -0,0 +1,166 @@
+//! Snapshot Endpoint
+//!
+//! **POST /snapshot/refresh**
+//! → Reloads the in-memory index snapshot from the backend now rather than
+//! at the next interval (see [`crate::models::storage::snapshot`]), e.g.
+//! right after a batch of books was indexed. Answers `unchanged` when the
+//! index version hasn't moved; otherwise the result cache is emptied, as its
+//! entries came from the old snapshot. Without `SEARCH_SNAPSHOT=on` there is no
+//! snapshot, a `400`; a backend that can't be read is a `503`, keeping the
+//! snapshot in use. A corpus above `SNAPSHOT_MAX_WORDS` is a `500` that drops
+//! the snapshot and the result cache, so queries go to the backend.
+
+use crate::error::AppError;
+use crate::models::responses::SnapshotRefreshResponse;
+use crate::models::storage::snapshot::{SnapshotBackend, SnapshotError};
+use crate::services::result_cache::ResultCache;
+use axum::{extract::State, response::Json};
+use std::sync::Arc;
+use tracing::info;
+
+/// Reloads the in-memory index snapshot.
+#[utoipa::path(
+    post,
+    path = "/snapshot/refresh",
+    tag = "snapshot",
+    responses(
+        (status = 200, description = "The snapshot matches the index", body = SnapshotRefreshResponse),
+        (status = 400, description = "`SEARCH_SNAPSHOT` is not on", body = ErrorResponse),
+        (status = 500, description = "The index is above `SNAPSHOT_MAX_WORDS`; the old snapshot is dropped", body = ErrorResponse),
+        (status = 503, description = "The backend can't be read; the old snapshot stays", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn refresh_snapshot(
+    State(snapshot): State<Option<SnapshotBackend>>,
+    State(result_cache): State<ResultCache>,
+) -> Result<Json<SnapshotRefreshResponse>, AppError> {
+    let snapshot = snapshot.ok_or_else(|| AppError::InvalidRequest(SnapshotError::NotEnabled.to_string()))?;
+    let previous = snapshot.current();
+    let loaded = match snapshot.refresh().await {
+        Ok(loaded) => loaded,
+        Err(e @ SnapshotError::TooLarge { .. }) => {
+            // The snapshot the cached results came from is gone
+            result_cache.invalidate_all();
+            return Err(AppError::Internal(e.to_string()));
+        }
+        Err(SnapshotError::Storage(e)) => return Err(e.into()),
+        Err(e) => return Err(AppError::Internal(e.to_string())),
+    };
+
+    let unchanged = previous.is_some_and(|previous| Arc::ptr_eq(&previous, &loaded));
+    if !unchanged {
+        result_cache.invalidate_all();
+        info!("Refreshed index snapshot: {} books, {} words", loaded.books(), loaded.words());
+    }
+    Ok(Json(SnapshotRefreshResponse {
+        status: if unchanged { "unchanged" } else { "refreshed" }.to_string(),
+        snapshot: snapshot.status(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::snapshot::SnapshotConfig;
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use crate::state::AppState;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::Router;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn refresh() -> Request<Body> {
+        Request::post("/snapshot/refresh").body(Body::empty()).unwrap()
+    }
+
+    async fn add_book(memory: &MemoryBackend, book_id: u32, word: &str) {
+        let metadata = BookMetadata {
+            book_id,
+            title: format!("Book {}", book_id),
+            author: "Anonymous".to_string(),
+            language: "en".to_string(),
+            word_count: 1,
+            unique_words: 1,
+            ..Default::default()
+        };
+        memory.store_book_metadata(&metadata).await.unwrap();
+        memory.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+    }
+
+    fn ids(body: &Value) -> Vec<u64> {
+        body["results"].as_array().unwrap().iter().map(|r| r["book_id"].as_u64().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn searches_see_new_books_once_the_snapshot_is_refreshed() {
+        let memory = MemoryBackend::new();
+        add_book(&memory, 1, "whale").await;
+        let app = crate::app_with_state(AppState::new(Arc::new(memory.clone())).with_snapshot(SnapshotConfig::default()));
+
+        let (status, body) = send(&app, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["snapshot"]["loaded"], false);
+
+        let (status, body) = send(&app, refresh()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "refreshed");
+        assert_eq!(body["snapshot"]["words"], 1);
+
+        add_book(&memory, 2, "whale").await;
+        let search = || Request::get("/search?q=whale").body(Body::empty()).unwrap();
+        assert_eq!(ids(&send(&app, search()).await.1), [1]);
+
+        let (_, body) = send(&app, refresh()).await;
+        assert_eq!(body["status"], "refreshed");
+        assert_eq!(body["snapshot"]["books"], 2);
+        assert_eq!(send(&app, search()).await.1["total_count"], 2);
+        assert_eq!(send(&app, refresh()).await.1["status"], "unchanged");
+
+        let (_, body) = send(&app, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert_eq!(body["snapshot"]["loaded"], true);
+        assert!(body["snapshot"]["memory_bytes"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn outgrowing_the_word_limit_drops_the_snapshot_and_cached_results() {
+        let memory = MemoryBackend::new();
+        add_book(&memory, 1, "whale").await;
+        let config = SnapshotConfig {
+            max_words: 1,
+            ..SnapshotConfig::default()
+        };
+        let app = crate::app_with_state(AppState::new(Arc::new(memory.clone())).with_snapshot(config));
+        assert_eq!(send(&app, refresh()).await.1["status"], "refreshed");
+        let search = || Request::get("/search?q=whale").body(Body::empty()).unwrap();
+        assert_eq!(ids(&send(&app, search()).await.1), [1]);
+
+        add_book(&memory, 2, "whale").await;
+        add_book(&memory, 2, "ship").await;
+        let (status, _) = send(&app, refresh()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        let (_, body) = send(&app, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert_eq!(body["snapshot"]["loaded"], false);
+        assert_eq!(send(&app, search()).await.1["total_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn refreshing_without_snapshot_mode_is_a_bad_request() {
+        let app = crate::app(Arc::new(MemoryBackend::new()));
+
+        let (status, body) = send(&app, refresh()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["message"], "SEARCH_SNAPSHOT is not on");
+        let (_, body) = send(&app, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert!(body.get("snapshot").is_none());
+    }
+}
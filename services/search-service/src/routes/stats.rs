@@ -0,0 +1,145 @@
+//! Search Statistics Endpoint
+//!
+//! **GET /search/stats?window_mins=&top=**
+//! → Summarizes the searches logged in the last `window_mins` minutes
+//!   (default 1440, at most 43200): the `top` most frequent queries
+//!   (default 10, at most 100), the most frequent queries that found
+//!   nothing, and latency percentiles. See [`crate::services::query_log`].
+
+use crate::models::responses::SearchStatsResponse;
+use crate::error::AppError;
+use crate::services::query_log::{summarize_queries, QueryLogger};
+use crate::Backend;
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::Json,
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use tracing::error;
+use utoipa::IntoParams;
+
+const DEFAULT_WINDOW_MINS: u64 = 24 * 60;
+/// Thirty days.
+const MAX_WINDOW_MINS: u64 = 30 * 24 * 60;
+const DEFAULT_TOP: usize = 10;
+const MAX_TOP: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchStatsParams {
+    /// Minutes of the query log to summarize.
+    #[param(default = json!(DEFAULT_WINDOW_MINS), minimum = 1, maximum = 43200)]
+    pub window_mins: Option<u64>,
+    /// Queries listed in `top_queries` and `zero_result_queries`.
+    #[param(default = json!(DEFAULT_TOP), minimum = 1, maximum = 100)]
+    pub top: Option<usize>,
+}
+
+/// Summarizes the query log over the requested window.
+#[utoipa::path(
+    get,
+    path = "/search/stats",
+    tag = "search",
+    params(SearchStatsParams),
+    responses(
+        (status = 200, description = "Popular queries and latencies", body = SearchStatsResponse),
+        (status = 400, description = "Malformed parameters", body = ErrorResponse),
+        (status = 503, description = "Storage backend unavailable", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn search_stats(
+    params: Result<Query<SearchStatsParams>, QueryRejection>,
+    State(backend): State<Backend>,
+    State(query_logger): State<QueryLogger>,
+) -> Result<Json<SearchStatsResponse>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let window_mins = params.window_mins.unwrap_or(DEFAULT_WINDOW_MINS).clamp(1, MAX_WINDOW_MINS);
+    let top = params.top.unwrap_or(DEFAULT_TOP).clamp(1, MAX_TOP);
+
+    let since = Utc::now() - Duration::minutes(window_mins as i64);
+    let entries = backend.get_query_log(since).await.inspect_err(|e| {
+        error!("Failed to read the query log: {}", e);
+    })?;
+    let summary = summarize_queries(&entries, top);
+
+    Ok(Json(SearchStatsResponse {
+        window_mins,
+        logging_enabled: query_logger.is_enabled(),
+        total_queries: summary.total_queries,
+        top_queries: summary.top_queries,
+        zero_result_queries: summary.zero_result_queries,
+        latency_ms: summary.latency_ms,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use crate::Backend;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    async fn get_json(backend: &Backend, uri: &str) -> (StatusCode, Value) {
+        let response = crate::app(backend.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn stats_reflect_the_searches_made() {
+        let memory = MemoryBackend::new();
+        memory
+            .store_book_metadata(&BookMetadata {
+                book_id: 2701,
+                title: "Moby Dick".to_string(),
+                author: "Herman Melville".to_string(),
+                language: "en".to_string(),
+                year: Some(1851),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        memory.add_word_to_index("whale", 2701, IndexField::Body).await.unwrap();
+        let backend: Backend = Arc::new(memory.clone());
+
+        for uri in [
+            "/search?q=whale",
+            "/search?q=Whale",
+            "/search?q=whale&language=en",
+            "/search?q=zanzibar",
+            "/search?q=whale%20AND",
+        ] {
+            get_json(&backend, uri).await;
+        }
+
+        // Logging runs in the background; the malformed query isn't logged
+        for _ in 0..100 {
+            if memory.get_query_log(chrono::DateTime::UNIX_EPOCH).await.unwrap().len() == 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let (status, stats) = get_json(&backend, "/search/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(stats["logging_enabled"], true);
+        assert_eq!(stats["total_queries"], 4);
+        assert_eq!(stats["top_queries"][0], json!({"query": "whale", "count": 3}));
+        assert_eq!(stats["zero_result_queries"], json!([{"query": "zanzibar", "count": 1}]));
+        assert!(stats["latency_ms"]["max"].as_f64().unwrap() >= stats["latency_ms"]["p50"].as_f64().unwrap());
+
+        let (_, stats) = get_json(&backend, "/search/stats?top=1&window_mins=0").await;
+        assert_eq!(stats["window_mins"], 1);
+        assert_eq!(stats["top_queries"].as_array().unwrap().len(), 1);
+    }
+}
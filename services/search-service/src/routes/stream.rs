@@ -0,0 +1,339 @@
+//! Streaming Search Endpoint
+//!
+//! **GET /search/stream?q=...&author=&language=&year=&year_from=&year_to=&max_reading_level=&subject=**
+//! → Sends the books matching the query and filters of `GET /search` as
+//!   server-sent events while they are found, instead of one response once
+//!   every match is known. Each `result` event holds one [`BookResult`];
+//!   a final `summary` event holds the [`StreamSummary`], or an `error`
+//!   event an [`ErrorResponse`] if the backend fails midway.
+//!
+//! Results are **unordered**. The branches of a top-level `OR` are looked
+//! up one at a time and each branch's new books go out before the next
+//! branch is read; other queries are resolved first. Either way, books are
+//! hydrated, filtered and sent [`STREAM_BATCH`] at a time in ascending ID
+//! order. `score` is the relevance score `sort=relevance` would rank by, so
+//! clients can order what they have; paging, other sorts, facets, snippets,
+//! suggestions and `dedupe` don't apply. Malformed parameters are a `400`
+//! before the stream starts.
+//!
+//! At most [`STREAM_BUFFER`] events wait for a slow client before the
+//! search pauses, and a client hanging up ends the search at its next
+//! event. Streams aren't bound by `SEARCH_TIMEOUT_SECS` or `timeout_ms`.
+
+use crate::error::AppError;
+use crate::models::responses::{BookResult, ErrorResponse, StreamSummary};
+use crate::routes::search::{
+    apply_filters, build_highlights, elapsed_ms, expand_query, get_author_matches, get_book_metadata_batch,
+    get_term_stats, parse_search, query_log_entry, SearchParams,
+};
+use crate::services::query::{evaluate, Query as BooleanQuery};
+use crate::services::search::{relevance_score, SortOrder};
+use crate::state::AppState;
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, Instrument};
+
+/// Books hydrated and filtered per metadata lookup.
+pub const STREAM_BATCH: usize = 50;
+/// Events buffered for a client before the search waits for it to read.
+pub const STREAM_BUFFER: usize = 32;
+
+type EventSender = mpsc::Sender<Result<Event, Infallible>>;
+
+/// Why a stream ended before its summary.
+enum Stopped {
+    /// The client hung up.
+    Disconnected,
+    Failed(AppError),
+}
+
+impl From<AppError> for Stopped {
+    fn from(error: AppError) -> Self {
+        Stopped::Failed(error)
+    }
+}
+
+/// Streams matching books as server-sent events (see the module docs).
+#[utoipa::path(
+    get,
+    path = "/search/stream",
+    tag = "search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "`result` events holding a `BookResult` each, unordered, then a `summary` event with a `StreamSummary` (or an `error` event with an `ErrorResponse`)", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Malformed parameters or an empty year range", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn stream_search(
+    params: Result<Query<SearchParams>, QueryRejection>,
+    State(state): State<AppState>,
+) -> Result<Sse<ReceiverStream<Result<Event, Infallible>>>, AppError> {
+    let Query(params) = params.map_err(|rejection| AppError::InvalidQuery(rejection.body_text()))?;
+    let query = parse_search(&params, &state.synonyms)?;
+    let (query, _) = expand_query(query, &state.backend).await?;
+
+    let (events, receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(run_stream(params, query, state, events).in_current_span());
+
+    Ok(Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()))
+}
+
+/// Sends the results and then the summary, or the error that cut them
+/// short, and logs the search once it completed.
+async fn run_stream(params: SearchParams, query: Option<BooleanQuery>, state: AppState, events: EventSender) {
+    let started = Instant::now();
+    let mut log_entry = query_log_entry(query.as_ref(), &params);
+
+    let stopped = match send_results(&params, query.as_ref(), &state, &events).await {
+        Ok(total_count) => {
+            let summary = StreamSummary {
+                total_count,
+                took_ms: elapsed_ms(started),
+            };
+            log_entry.result_count = total_count;
+            log_entry.latency_ms = summary.took_ms;
+            state.query_logger.log(&state.backend, log_entry);
+            match json_event("summary", &summary) {
+                Ok(event) => send(&events, event).await.err(),
+                Err(e) => Some(Stopped::Failed(e)),
+            }
+        }
+        Err(stopped) => Some(stopped),
+    };
+
+    match stopped {
+        None => {}
+        Some(Stopped::Disconnected) => debug!("Client left a streamed search for {:?}", params.q),
+        Some(Stopped::Failed(e)) => {
+            error!("Streamed search for {:?} failed: {}", params.q, e);
+            let body = ErrorResponse::new(e.error_code(), e.to_string());
+            if let Ok(event) = json_event("error", &body) {
+                let _ = send(&events, event).await;
+            }
+        }
+    }
+}
+
+/// Sends a `result` event for every book matching `query` and the filters,
+/// returning how many were sent.
+async fn send_results(
+    params: &SearchParams,
+    query: Option<&BooleanQuery>,
+    state: &AppState,
+    events: &EventSender,
+) -> Result<usize, Stopped> {
+    let Some(query) = query else {
+        return Ok(0);
+    };
+    let backend = &state.backend;
+    let words = query.positive_terms();
+    let author_matches = get_author_matches(params.author.as_deref(), backend).await;
+    let terms = if params.sort == SortOrder::Relevance {
+        get_term_stats(&words, query.requires_all_terms(), backend).await
+    } else {
+        Vec::new()
+    };
+
+    let branches = match query {
+        BooleanQuery::Or(children) => children.iter().collect(),
+        query => vec![query],
+    };
+    let mut seen = HashSet::new();
+    let mut sent = 0;
+    for branch in branches {
+        let matched = evaluate(branch, backend).await.map_err(AppError::from)?;
+        let mut book_ids: Vec<u32> = matched.into_iter().filter(|book_id| seen.insert(*book_id)).collect();
+        book_ids.sort_unstable();
+
+        for batch in book_ids.chunks(STREAM_BATCH) {
+            let batch: HashSet<u32> = batch.iter().copied().collect();
+            let mut metadata = get_book_metadata_batch(&batch, backend, &state.metadata_cache).await;
+            if let Some(author_matches) = &author_matches {
+                metadata.retain(|book| author_matches.contains(&book.book_id));
+            }
+            let mut books = apply_filters(metadata, params);
+            books.sort_by_key(|book| book.book_id);
+
+            for book in books {
+                let mut result = BookResult {
+                    score: relevance_score(book.book_id, &terms),
+                    ..BookResult::from(book)
+                };
+                result.highlights = build_highlights(&result, &words, None);
+                send(events, json_event("result", &result)?).await?;
+                sent += 1;
+            }
+        }
+    }
+
+    Ok(sent)
+}
+
+fn json_event(name: &str, data: &impl serde::Serialize) -> Result<Event, AppError> {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize {} event: {}", name, e)))
+}
+
+/// Waits for room in the client's buffer; fails once the client is gone.
+async fn send(events: &EventSender, event: Event) -> Result<(), Stopped> {
+    events.send(Ok(event)).await.map_err(|_| Stopped::Disconnected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend};
+    use crate::services::query::parse_query;
+    use crate::services::search::MatchMode;
+    use crate::Backend;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request, StatusCode};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// 120 books titled "Voyage", so matches span several batches: every
+    /// book has "ship", even ones "whale", multiples of three "harpoon".
+    async fn fleet() -> Backend {
+        let backend: Backend = Arc::new(MemoryBackend::new());
+        for book_id in 1..=120 {
+            let metadata = BookMetadata {
+                book_id,
+                title: format!("Voyage {}", book_id),
+                author: "Herman Melville".to_string(),
+                language: if book_id % 4 == 0 { "fr" } else { "en" }.to_string(),
+                year: Some(1800 + book_id),
+                word_count: 1_000,
+                unique_words: 100,
+                ..Default::default()
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            backend.add_word_to_index("voyage", book_id, IndexField::Title).await.unwrap();
+            backend.add_word_to_index("ship", book_id, IndexField::Body).await.unwrap();
+            if book_id % 2 == 0 {
+                backend.add_word_to_index("whale", book_id, IndexField::Body).await.unwrap();
+            }
+            if book_id % 3 == 0 {
+                backend.add_word_to_index("harpoon", book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        backend
+    }
+
+    async fn get(backend: &Backend, uri: &str) -> (StatusCode, String, String) {
+        let response = crate::app(backend.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response.headers()[header::CONTENT_TYPE].to_str().unwrap().to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, content_type, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    /// The `(event, data)` pairs of an SSE body.
+    fn events(body: &str) -> Vec<(String, Value)> {
+        body.split("\n\n")
+            .filter(|block| !block.trim().is_empty())
+            .map(|block| {
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .unwrap_or_else(|| panic!("no {} in {:?}", name, block))
+                        .to_string()
+                };
+                (field("event: "), serde_json::from_str(&field("data: ")).unwrap())
+            })
+            .collect()
+    }
+
+    fn sorted_ids<'a>(results: impl IntoIterator<Item = &'a Value>) -> Vec<u64> {
+        let mut ids: Vec<u64> = results.into_iter().map(|result| result["book_id"].as_u64().unwrap()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[tokio::test]
+    async fn streams_the_same_books_as_search() {
+        let backend = fleet().await;
+
+        for query in ["whale", "whale%20OR%20harpoon", "ship%20AND%20NOT%20whale&language=en", "kraken"] {
+            let (status, content_type, body) = get(&backend, &format!("/search/stream?q={}", query)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(content_type.starts_with("text/event-stream"), "{}", content_type);
+
+            let events = events(&body);
+            let (summary, results) = events.split_last().unwrap();
+            assert_eq!(summary.0, "summary", "{}", query);
+            assert!(results.iter().all(|(name, _)| name == "result"));
+            assert_eq!(summary.1["total_count"], results.len());
+
+            let (_, _, blocking) = get(&backend, &format!("/search?q={}&export=true", query)).await;
+            let blocking: Value = serde_json::from_str(&blocking).unwrap();
+            let expected = sorted_ids(blocking["results"].as_array().unwrap());
+            let streamed = sorted_ids(results.iter().map(|(_, result)| result));
+            assert_eq!(streamed, expected, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn or_branches_are_sent_as_they_are_resolved() {
+        let (_, _, body) = get(&fleet().await, "/search/stream?q=harpoon%20OR%20whale").await;
+        let ids: Vec<u64> = events(&body)
+            .iter()
+            .filter(|(name, _)| name == "result")
+            .map(|(_, result)| result["book_id"].as_u64().unwrap())
+            .collect();
+
+        // Every harpoon book first, then the whale books not yet sent
+        let harpoon: Vec<u64> = (1..=120).filter(|id| id % 3 == 0).collect();
+        let whale_only: Vec<u64> = (1..=120).filter(|id| id % 2 == 0 && id % 3 != 0).collect();
+        assert_eq!(ids, [harpoon, whale_only].concat());
+    }
+
+    #[tokio::test]
+    async fn results_carry_relevance_scores_and_highlights() {
+        let (_, _, body) = get(&fleet().await, "/search/stream?q=voyage&year=1842").await;
+        let events = events(&body);
+
+        assert_eq!(events.len(), 2);
+        let result = &events[0].1;
+        assert_eq!(result["book_id"], 42);
+        assert!(result["score"].as_f64().unwrap() > 0.0);
+        assert_eq!(result["highlights"]["title"][0], "<mark>Voyage</mark> 42");
+    }
+
+    #[tokio::test]
+    async fn malformed_parameters_are_rejected_before_streaming() {
+        let (status, content_type, body) = get(&fleet().await, "/search/stream?q=whale&year_from=1900&year_to=1800").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, "application/json");
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error_code"], "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn a_client_hanging_up_stops_the_search() {
+        let state = AppState::new(fleet().await);
+        let params: SearchParams =
+            serde_json::from_value(serde_json::json!({ "q": "ship", "sort": "year_asc" })).unwrap();
+        let query = parse_query(&params.q, MatchMode::All).unwrap();
+        let (events, receiver) = mpsc::channel(1);
+        drop(receiver);
+
+        let sent = send_results(&params, query.as_ref(), &state, &events).await;
+        assert!(matches!(sent, Err(Stopped::Disconnected)));
+    }
+}
@@ -0,0 +1,139 @@
+//! Synonyms Endpoint
+//!
+//! **POST /synonyms/reload**
+//! → Reads the `SYNONYMS_FILE` file again (see
+//! [`crate::services::synonyms`]), so edits apply without a restart. A
+//! file that can't be read or parsed is a `500` and the synonyms in use
+//! stay; without a synonyms file there is nothing to reload, a `400`.
+//! Cached responses don't need dropping: their key holds the expanded
+//! query.
+
+use crate::error::AppError;
+use crate::models::responses::SynonymsReloadResponse;
+use crate::services::synonyms::{SynonymExpander, SynonymsError};
+use axum::{extract::State, response::Json};
+use tracing::info;
+
+/// Reloads the synonyms file.
+#[utoipa::path(
+    post,
+    path = "/synonyms/reload",
+    tag = "synonyms",
+    responses(
+        (status = 200, description = "Synonyms replaced by the file's", body = SynonymsReloadResponse),
+        (status = 400, description = "No synonyms file is configured", body = ErrorResponse),
+        (status = 500, description = "The file can't be read or parsed; the old synonyms stay", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+pub async fn reload_synonyms(State(synonyms): State<SynonymExpander>) -> Result<Json<SynonymsReloadResponse>, AppError> {
+    let words = tokio::task::spawn_blocking(move || synonyms.reload())
+        .await
+        .map_err(|e| AppError::Internal(format!("Reloading synonyms failed: {}", e)))?
+        .map_err(|e| match e {
+            SynonymsError::NotConfigured => AppError::InvalidRequest(e.to_string()),
+            e => AppError::Internal(e.to_string()),
+        })?;
+    info!("Reloaded synonyms for {} words", words);
+
+    Ok(Json(SynonymsReloadResponse {
+        status: "reloaded".to_string(),
+        words,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend};
+    use crate::services::synonyms::SynonymExpander;
+    use crate::state::AppState;
+    use crate::Backend;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use axum::Router;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn search(app: &Router, query: &str) -> Value {
+        let (status, body) = send(app, Request::get(format!("/search?{}", query)).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        body
+    }
+
+    fn ids(body: &Value) -> Vec<u64> {
+        let mut ids: Vec<u64> = body["results"].as_array().unwrap().iter().map(|r| r["book_id"].as_u64().unwrap()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Red books 1–3 with a car, an automobile and a motorcar; book 4 has
+    /// an automobile that isn't red.
+    async fn garage() -> Backend {
+        let backend: Backend = Arc::new(MemoryBackend::new());
+        for (book_id, words) in [(1, &["red", "car"]), (2, &["red", "automobile"]), (3, &["red", "motorcar"]), (4, &["blue", "automobile"])] {
+            let metadata = BookMetadata {
+                book_id,
+                title: format!("Book {}", book_id),
+                author: "Anonymous".to_string(),
+                language: "en".to_string(),
+                ..Default::default()
+            };
+            backend.store_book_metadata(&metadata).await.unwrap();
+            for word in words {
+                backend.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        backend
+    }
+
+    #[tokio::test]
+    async fn searches_expand_with_the_synonyms_file_and_its_reloads() {
+        let path = std::env::temp_dir().join(format!("synonyms-e2e-{}.txt", std::process::id()));
+        std::fs::write(&path, "# test synonyms\ncar: automobile\n").unwrap();
+        let mut state = AppState::new(garage().await);
+        state.synonyms = SynonymExpander::from_file(&path).unwrap();
+        let app = crate::app_with_state(state);
+
+        let body = search(&app, "q=red+car").await;
+        assert_eq!(ids(&body), [1, 2]);
+        assert_eq!(body["parsed_query"], "red AND (car OR automobile)");
+        assert_eq!(body["term_stats"]["automobile"]["synonym_of"], "car");
+        assert_eq!(body["term_stats"]["automobile"]["doc_freq"], 2);
+        assert!(body["term_stats"]["car"].get("synonym_of").is_none());
+        assert_eq!(ids(&search(&app, "q=red+car&expand_synonyms=false").await), [1]);
+        assert_eq!(ids(&search(&app, "q=red+car&synonyms=false").await), [1]);
+        assert_eq!(ids(&search(&app, "q=red+car&mode=any").await), [1, 2, 3, 4]);
+
+        std::fs::write(&path, "car: motorcar, automobile\n").unwrap();
+        let (status, body) = send(&app, Request::post("/synonyms/reload").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["words"], 1);
+        assert_eq!(ids(&search(&app, "q=red+car").await), [1, 2, 3]);
+
+        std::fs::write(&path, "car motorcar\n").unwrap();
+        let (status, body) = send(&app, Request::post("/synonyms/reload").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body["message"].as_str().unwrap().starts_with("Line 1 of"), "{}", body);
+        assert_eq!(ids(&search(&app, "q=red+car").await), [1, 2, 3]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reloading_without_a_synonyms_file_is_a_bad_request() {
+        let mut state = AppState::new(garage().await);
+        state.synonyms = SynonymExpander::default();
+        let app = crate::app_with_state(state);
+
+        let (status, body) = send(&app, Request::post("/synonyms/reload").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["message"], "SYNONYMS_FILE is not set");
+    }
+}
@@ -0,0 +1,111 @@
+//! Backend Connection Monitor
+//!
+//! A Redis connection can break when the server restarts or the network
+//! flaps, and a cluster connection never recovers on its own. A background
+//! task tests the backend every [`CHECK_INTERVAL`], warning on each failure,
+//! and after [`RECONNECT_AFTER`] failures in a row replaces its connections
+//! (see [`StorageBackend::reconnect`]), then again after every further
+//! [`RECONNECT_AFTER`]. `GET /status` reports the current run of failures as
+//! `backend_consecutive_failures`.
+//!
+//! [`StorageBackend::reconnect`]: crate::models::storage::StorageBackend::reconnect
+
+use crate::routes::health::HEALTH_CHECK_TIMEOUT;
+use crate::Backend;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Failed checks in a row before reconnecting.
+pub const RECONNECT_AFTER: u32 = 3;
+
+/// Number of connection checks in a row that failed. Clones share the
+/// count, so the monitor can update it for `/status`.
+#[derive(Clone, Default)]
+pub struct BackendHealth {
+    consecutive_failures: Arc<AtomicU32>,
+}
+
+impl BackendHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Acquire)
+    }
+
+    /// Tests `backend` once, reconnecting it after every
+    /// [`RECONNECT_AFTER`] failures in a row. Returns whether it answered.
+    pub async fn check(&self, backend: &Backend) -> bool {
+        let result = match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, backend.test_connection()).await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err(format!("no answer within {:?}", HEALTH_CHECK_TIMEOUT)),
+        };
+
+        let reason = match result {
+            Ok(()) => {
+                let failures = self.consecutive_failures.swap(0, Ordering::AcqRel);
+                if failures > 0 {
+                    info!("Storage backend answering again after {} failed check(s)", failures);
+                }
+                return true;
+            }
+            Err(reason) => reason,
+        };
+
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::AcqRel) + 1;
+        warn!("Storage backend connection check failed ({} in a row): {}", failures, reason);
+        if failures.is_multiple_of(RECONNECT_AFTER) {
+            match backend.reconnect().await {
+                Ok(()) => info!("Reconnected to the storage backend"),
+                Err(e) => error!("Failed to reconnect to the storage backend: {}", e),
+            }
+        }
+        false
+    }
+
+    /// Checks `backend` every `interval` for as long as the runtime runs.
+    pub fn spawn(&self, backend: Backend, interval: Duration) {
+        let health = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick is immediate, and startup has just tested it
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                health.check(&backend).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+
+    #[tokio::test]
+    async fn reconnects_after_three_failed_checks_in_a_row() {
+        let memory = MemoryBackend::new();
+        let backend: Backend = Arc::new(memory.clone());
+        let health = BackendHealth::new();
+        assert!(health.check(&backend).await);
+
+        memory.drop_connection();
+        for failures in 1..RECONNECT_AFTER {
+            assert!(!health.check(&backend).await);
+            assert_eq!(health.consecutive_failures(), failures);
+            assert!(backend.test_connection().await.is_err(), "reconnected too early");
+        }
+
+        // The third failure reconnects, so the next check passes
+        assert!(!health.check(&backend).await);
+        assert_eq!(health.consecutive_failures(), RECONNECT_AFTER);
+        assert!(health.check(&backend).await);
+        assert_eq!(health.consecutive_failures(), 0);
+    }
+}
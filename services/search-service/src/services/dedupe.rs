@@ -0,0 +1,119 @@
+//! Duplicate Editions
+//!
+//! Project Gutenberg sometimes holds the same text under several IDs, which
+//! fill a page of results with near-identical rows. With `?dedupe=true`,
+//! `GET /search` keeps one book per edition, the best scored (lowest ID on
+//! ties), and lists the others' IDs in `also_available_as`.
+//!
+//! Ingestion records no content hash, so editions are recognized by title
+//! and author, compared ignoring case, diacritics and punctuation. Books
+//! missing either are never collapsed.
+
+use crate::models::storage::BookMetadata;
+use crate::utils::text::fold_diacritics;
+use std::collections::HashMap;
+
+/// `text` folded, with punctuation dropped and spaces collapsed.
+fn normalize(text: &str) -> String {
+    fold_diacritics(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// What identifies `book`'s edition, or `None` without a title and author.
+pub fn edition_key(book: &BookMetadata) -> Option<(String, String)> {
+    let title = normalize(&book.title);
+    let author = normalize(&book.author);
+    (!title.is_empty() && !author.is_empty()).then_some((title, author))
+}
+
+/// Collapses `books` to one per edition, each with the sorted IDs of the
+/// books it stands for. Editions keep the position of their first book.
+pub fn dedupe_editions(books: Vec<BookMetadata>, score: impl Fn(u32) -> f64) -> Vec<(BookMetadata, Vec<u32>)> {
+    let mut editions: Vec<(BookMetadata, Vec<u32>)> = Vec::with_capacity(books.len());
+    let mut positions: HashMap<(String, String), usize> = HashMap::new();
+
+    for book in books {
+        let Some(key) = edition_key(&book) else {
+            editions.push((book, Vec::new()));
+            continue;
+        };
+        let Some(&position) = positions.get(&key) else {
+            positions.insert(key, editions.len());
+            editions.push((book, Vec::new()));
+            continue;
+        };
+
+        let (kept, others) = &mut editions[position];
+        let (new_score, kept_score) = (score(book.book_id), score(kept.book_id));
+        let better = new_score > kept_score || (new_score == kept_score && book.book_id < kept.book_id);
+        let dropped = if better { std::mem::replace(kept, book) } else { book };
+        others.push(dropped.book_id);
+    }
+
+    for (_, others) in &mut editions {
+        others.sort_unstable();
+    }
+    editions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(book_id: u32, title: &str, author: &str) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: title.to_string(),
+            author: author.to_string(),
+            language: "en".to_string(),
+            word_count: 1000,
+            unique_words: 100,
+            ..Default::default()
+        }
+    }
+
+    fn collapsed(editions: &[(BookMetadata, Vec<u32>)]) -> Vec<(u32, Vec<u32>)> {
+        editions.iter().map(|(book, others)| (book.book_id, others.clone())).collect()
+    }
+
+    #[test]
+    fn titles_and_authors_match_ignoring_case_accents_and_punctuation() {
+        assert_eq!(
+            edition_key(&book(1, "Les Misérables", "Victor Hugo")),
+            edition_key(&book(2, "LES MISERABLES.", "victor  HUGO"))
+        );
+        assert_ne!(
+            edition_key(&book(1, "Les Misérables", "Victor Hugo")),
+            edition_key(&book(3, "Les Misérables, Volume 2", "Victor Hugo"))
+        );
+        assert_eq!(edition_key(&book(4, "Anonymous Tales", "")), None);
+        assert_eq!(edition_key(&book(5, " -- ", "Someone")), None);
+    }
+
+    #[test]
+    fn keeps_the_best_scored_edition_and_lists_the_rest() {
+        let books = vec![
+            book(1342, "Pride and Prejudice", "Jane Austen"),
+            book(84, "Frankenstein", "Mary Shelley"),
+            book(42671, "Pride and Prejudice", "Jane Austen"),
+            book(20686, "PRIDE AND PREJUDICE?", "jane austen"),
+        ];
+        let scores = HashMap::from([(1342, 1.0), (42671, 2.5), (20686, 2.5), (84, 1.0)]);
+
+        let editions = dedupe_editions(books, |id| scores[&id]);
+
+        assert_eq!(collapsed(&editions), [(20686, vec![1342, 42671]), (84, vec![])]);
+    }
+
+    #[test]
+    fn books_without_a_title_or_author_stay_apart() {
+        let books = vec![book(1, "", "Unknown"), book(2, "", "Unknown"), book(3, "Untitled", "")];
+
+        let editions = dedupe_editions(books, |_| 0.0);
+
+        assert_eq!(collapsed(&editions), [(1, vec![]), (2, vec![]), (3, vec![])]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Facet Counts
+//!
+//! Counts the books of a search per language, author or publication decade,
+//! set with `?facets=language,author,decade`. Counts cover every book that
+//! matched the query and filters, not just the page returned, and each facet
+//! lists at most [`MAX_FACET_VALUES`] values, the most frequent first.
+
+use crate::models::storage::BookMetadata;
+use std::collections::{BTreeMap, HashMap};
+
+/// Distinct values reported per facet.
+pub const MAX_FACET_VALUES: usize = 20;
+
+/// Value reported for books without a known year.
+const UNKNOWN_DECADE: &str = "unknown";
+
+/// A metadata field search results can be counted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Facet {
+    Language,
+    Author,
+    /// Publication decade, e.g. `1810s`.
+    Decade,
+}
+
+impl Facet {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Facet::Language => "language",
+            Facet::Author => "author",
+            Facet::Decade => "decade",
+        }
+    }
+
+    fn value_of(&self, book: &BookMetadata) -> String {
+        match self {
+            Facet::Language => book.language.clone(),
+            Facet::Author => book.author.clone(),
+            Facet::Decade => book
+                .year
+                .map_or_else(|| UNKNOWN_DECADE.to_string(), |year| format!("{}s", year / 10 * 10)),
+        }
+    }
+}
+
+/// Parses a comma-separated facet list, ignoring blanks and repeats.
+pub fn parse_facets(list: &str) -> Result<Vec<Facet>, String> {
+    let mut facets = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let facet = match name.to_lowercase().as_str() {
+            "language" => Facet::Language,
+            "author" => Facet::Author,
+            "decade" => Facet::Decade,
+            _ => return Err(format!("unknown facet '{}', expected language, author or decade", name)),
+        };
+        if !facets.contains(&facet) {
+            facets.push(facet);
+        }
+    }
+    Ok(facets)
+}
+
+/// Counts `books` by each facet, keeping the [`MAX_FACET_VALUES`] most
+/// frequent values; ties go to the alphabetically first value.
+pub fn count_facets<'a>(
+    facets: &[Facet],
+    books: impl IntoIterator<Item = &'a BookMetadata> + Clone,
+) -> HashMap<String, BTreeMap<String, usize>> {
+    facets
+        .iter()
+        .map(|facet| {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for book in books.clone() {
+                *counts.entry(facet.value_of(book)).or_default() += 1;
+            }
+
+            let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+            counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+            counts.truncate(MAX_FACET_VALUES);
+            (facet.as_str().to_string(), counts.into_iter().collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(book_id: u32, author: &str, language: &str, year: Option<u32>) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: format!("Book {}", book_id),
+            author: author.to_string(),
+            language: language.to_string(),
+            year,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_facet_lists() {
+        assert_eq!(
+            parse_facets("decade, Language,,decade").unwrap(),
+            [Facet::Decade, Facet::Language]
+        );
+        assert!(parse_facets("").unwrap().is_empty());
+        assert_eq!(
+            parse_facets("language,genre").unwrap_err(),
+            "unknown facet 'genre', expected language, author or decade"
+        );
+    }
+
+    #[test]
+    fn counts_books_per_facet_value() {
+        let books = [
+            book(1342, "Jane Austen", "en", Some(1813)),
+            book(158, "Jane Austen", "en", Some(1815)),
+            book(17797, "Jules Verne", "fr", Some(1865)),
+            book(84, "Mary Shelley", "en", None),
+        ];
+
+        let facets = count_facets(&[Facet::Language, Facet::Author, Facet::Decade], &books);
+
+        assert_eq!(facets["language"], BTreeMap::from([("en".to_string(), 3), ("fr".to_string(), 1)]));
+        assert_eq!(facets["author"]["Jane Austen"], 2);
+        assert_eq!(
+            facets["decade"],
+            BTreeMap::from([
+                ("1810s".to_string(), 2),
+                ("1860s".to_string(), 1),
+                ("unknown".to_string(), 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_most_frequent_values() {
+        // One author with two books, then one book each for many more
+        let mut books = vec![book(1, "Author 99", "en", None), book(2, "Author 99", "en", None)];
+        books.extend((0..30).map(|i| book(100 + i, &format!("Author {:02}", i), "en", None)));
+
+        let authors = &count_facets(&[Facet::Author], &books)["author"];
+
+        assert_eq!(authors.len(), MAX_FACET_VALUES);
+        assert_eq!(authors["Author 99"], 2);
+        assert!(authors.contains_key("Author 00"));
+        assert!(!authors.contains_key("Author 29"));
+    }
+}
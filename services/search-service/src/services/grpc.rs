@@ -0,0 +1,340 @@
+//! gRPC Server
+//!
+//! Serves `proto/search.proto` next to the HTTP API, for internal callers.
+//! Each RPC runs the same code as its HTTP route:
+//!
+//! - `Search` → `GET /search` (through [`execute_search`], sharing the
+//!   result cache, query log and `SEARCH_TIMEOUT_SECS`)
+//! - `GetBook` → `GET /search/book/:book_id` (through [`fetch_book`])
+//!
+//! Errors map onto gRPC codes: `INVALID_QUERY` and `INVALID_REQUEST` →
+//! `INVALID_ARGUMENT`, `BOOK_NOT_FOUND` → `NOT_FOUND`, `BACKEND_UNAVAILABLE`
+//! → `UNAVAILABLE`, `GATEWAY_TIMEOUT` → `DEADLINE_EXCEEDED`, anything else →
+//! `INTERNAL`.
+//!
+//! ## Environment Variables
+//! - `GRPC_PORT`: Port of the gRPC server (default: `7013`)
+
+use crate::error::AppError;
+use crate::models::responses;
+use crate::routes::browse::fetch_book;
+use crate::routes::search::{execute_search, SearchOutcome, SearchParams};
+use crate::services::shutdown::Shutdown;
+use crate::state::AppState;
+use axum::http::HeaderMap;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Code, Request, Response, Status};
+use tracing::{error, info};
+
+/// Types and stubs generated from `proto/search.proto`.
+pub mod pb {
+    tonic::include_proto!("search");
+}
+
+use pb::search_service_server::{SearchService, SearchServiceServer};
+
+pub const DEFAULT_GRPC_PORT: u16 = 7013;
+
+/// Reads `GRPC_PORT`, falling back to [`DEFAULT_GRPC_PORT`].
+pub fn grpc_port() -> u16 {
+    std::env::var("GRPC_PORT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_GRPC_PORT)
+}
+
+/// The gRPC service, sharing the HTTP server's state.
+#[derive(Clone)]
+pub struct SearchGrpc {
+    state: AppState,
+}
+
+impl SearchGrpc {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl SearchService for SearchGrpc {
+    #[tracing::instrument(name = "grpc_search", skip_all, err(level = "warn"))]
+    async fn search(
+        &self,
+        request: Request<pb::SearchRequest>,
+    ) -> Result<Response<pb::SearchResponse>, Status> {
+        let params = search_params(request.into_inner())?;
+        info!("Search query (gRPC): {:?}", params.q);
+
+        let headers = HeaderMap::new();
+        let search = execute_search(params, &headers, &self.state);
+        let outcome = tokio::time::timeout(self.state.timeouts.search, search)
+            .await
+            .map_err(|_| AppError::Timeout)??;
+
+        let SearchOutcome::Found { body, .. } = outcome else {
+            // Only conditional headers make a search unchanged
+            return Err(Status::internal("Search answered without a body"));
+        };
+        let response: responses::SearchResponse = serde_json::from_slice(&body).map_err(|e| {
+            error!("Failed to decode search response: {}", e);
+            Status::internal("Failed to decode search response")
+        })?;
+
+        Ok(Response::new(response.into()))
+    }
+
+    #[tracing::instrument(name = "grpc_get_book", skip_all, fields(book_id), err(level = "warn"))]
+    async fn get_book(&self, request: Request<pb::BookRequest>) -> Result<Response<pb::Book>, Status> {
+        let book_id = request.into_inner().book_id;
+        tracing::Span::current().record("book_id", book_id);
+
+        let book = fetch_book(book_id, &self.state.backend).await?;
+        Ok(Response::new(book.into()))
+    }
+}
+
+/// Reads a request into the parameters of `GET /search`, through the same
+/// deserializers, so blank filters, case and unknown modes, sorts and facets
+/// are treated alike. Unset fields take the route's defaults.
+fn search_params(request: pb::SearchRequest) -> Result<SearchParams, AppError> {
+    let facets = (!request.facets.is_empty()).then(|| request.facets.join(","));
+    let mut params = json!({
+        "q": request.q,
+        "author": request.author,
+        "language": request.language,
+        "year": request.year,
+        "year_from": request.year_from,
+        "year_to": request.year_to,
+        "min_word_count": request.min_word_count,
+        "max_word_count": request.max_word_count,
+        "max_reading_level": request.max_reading_level,
+        "subject": request.subject,
+        "highlight_body": request.highlight_body,
+        "snippets": request.snippets,
+        "mode": request.mode,
+        "sort": request.sort,
+        "facets": facets,
+        "limit": request.limit,
+        "offset": request.offset,
+        "suggest": request.suggest,
+        "synonyms": request.synonyms,
+        "dedupe": request.dedupe,
+        "regex": request.regex,
+        "timeout_ms": request.timeout_ms,
+        "export": request.export,
+    });
+    if let Value::Object(fields) = &mut params {
+        fields.retain(|_, value| !value.is_null());
+    }
+
+    serde_json::from_value(params).map_err(|e| AppError::InvalidQuery(format!("Invalid search request: {}", e)))
+}
+
+impl From<AppError> for Status {
+    fn from(error: AppError) -> Self {
+        let code = match error {
+            AppError::InvalidQuery(_) | AppError::InvalidRequest(_) => Code::InvalidArgument,
+            AppError::BookNotFound(_) => Code::NotFound,
+            AppError::BackendUnavailable(_) => Code::Unavailable,
+            AppError::Timeout => Code::DeadlineExceeded,
+            AppError::Internal(_) => Code::Internal,
+        };
+        Status::new(code, error.to_string())
+    }
+}
+
+/// Serves the gRPC API on `listener` until `shutdown` is triggered.
+pub async fn serve(listener: TcpListener, state: AppState, shutdown: Shutdown) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(SearchServiceServer::new(SearchGrpc::new(state)))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move { shutdown.wait().await })
+        .await
+}
+
+impl From<responses::BookResult> for pb::BookResult {
+    fn from(book: responses::BookResult) -> Self {
+        Self {
+            book_id: book.book_id,
+            title: book.title,
+            author: book.author,
+            language: book.language,
+            year: book.year,
+            subjects: book.subjects,
+            highlights: book
+                .highlights
+                .into_iter()
+                .map(|(field, snippets)| (field, pb::Snippets { snippets }))
+                .collect(),
+            chapters: book.chapters.unwrap_or_default().into_iter().map(|chapter| chapter as u64).collect(),
+            snippet: book.snippet,
+            score: book.score,
+            reading_level: book.reading_level,
+            also_available_as: book.also_available_as,
+        }
+    }
+}
+
+impl From<responses::SearchResponse> for pb::SearchResponse {
+    fn from(response: responses::SearchResponse) -> Self {
+        Self {
+            query: response.query,
+            parsed_query: response.parsed_query,
+            filters: response.filters,
+            count: response.count as u64,
+            total_count: response.total_count as u64,
+            limit: response.limit as u64,
+            offset: response.offset as u64,
+            results: response.results.into_iter().map(Into::into).collect(),
+            facets: response
+                .facets
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(facet, counts)| {
+                    let counts = counts.into_iter().map(|(value, count)| (value, count as u64)).collect();
+                    (facet, pb::FacetCounts { counts })
+                })
+                .collect(),
+            suggestions: response.suggestions.unwrap_or_default(),
+            term_stats: response
+                .term_stats
+                .into_iter()
+                .map(|(word, stat)| (word, stat.into()))
+                .collect(),
+            partial: response.partial,
+            timed_out_after_ms: response.timed_out_after_ms,
+        }
+    }
+}
+
+impl From<responses::TermStat> for pb::TermStat {
+    fn from(stat: responses::TermStat) -> Self {
+        Self {
+            doc_freq: stat.doc_freq as u64,
+            found: stat.found,
+            filtered: stat.filtered,
+            reason: stat.reason,
+            synonym_of: stat.synonym_of,
+            expansion_of: stat.expansion_of,
+            expansions: stat.expansions,
+            truncated: stat.truncated,
+        }
+    }
+}
+
+impl From<responses::BookDetailResponse> for pb::Book {
+    fn from(detail: responses::BookDetailResponse) -> Self {
+        let book = detail.book;
+        Self {
+            book_id: book.book_id,
+            title: book.title,
+            author: book.author,
+            language: book.language,
+            year: book.year,
+            word_count: book.word_count as u64,
+            unique_words: book.unique_words as u64,
+            chapter_count: book.chapter_count as u64,
+            subjects: book.subjects,
+            related_words_count: detail.related_words_count as u64,
+            reading_level: book.reading_level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use crate::Backend;
+    use pb::search_service_client::SearchServiceClient;
+    use std::sync::Arc;
+    use tonic::transport::Channel;
+
+    fn book(book_id: u32, title: &str, year: u32) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: title.to_string(),
+            author: "Herman Melville".to_string(),
+            language: "en".to_string(),
+            year: Some(year),
+            word_count: 1_000,
+            unique_words: 100,
+            ..Default::default()
+        }
+    }
+
+    /// Starts the gRPC server on a free port and connects a client to it.
+    async fn client(backend: Backend, shutdown: Shutdown) -> SearchServiceClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, AppState::new(backend), shutdown));
+        SearchServiceClient::connect(format!("http://{}", addr)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn search_and_get_book_over_grpc() {
+        let backend = MemoryBackend::new();
+        for (book_id, title, year) in [(2701, "Moby Dick", 1851), (15, "Omoo", 1847)] {
+            backend.store_book_metadata(&book(book_id, title, year)).await.unwrap();
+            backend.add_word_to_index("whale", book_id, IndexField::Body).await.unwrap();
+        }
+        backend.add_word_to_index("moby", 2701, IndexField::Title).await.unwrap();
+        let shutdown = Shutdown::new();
+        let mut client = client(Arc::new(backend), shutdown.clone()).await;
+
+        let response = client
+            .search(pb::SearchRequest {
+                q: "Whale".to_string(),
+                sort: Some("year_asc".to_string()),
+                facets: vec!["decade".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.parsed_query, "whale");
+        assert_eq!(response.total_count, 2);
+        let ids: Vec<u32> = response.results.iter().map(|book| book.book_id).collect();
+        assert_eq!(ids, [15, 2701]);
+        assert_eq!(response.filters["sort"], "year_asc");
+        assert_eq!(response.facets["decade"].counts["1840s"], 1);
+
+        let book = client.get_book(pb::BookRequest { book_id: 2701 }).await.unwrap().into_inner();
+        assert_eq!(book.title, "Moby Dick");
+        assert_eq!(book.year, Some(1851));
+
+        let err = client.get_book(pb::BookRequest { book_id: 1 }).await.unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        shutdown.trigger();
+    }
+
+    #[tokio::test]
+    async fn invalid_searches_are_invalid_arguments() {
+        let shutdown = Shutdown::new();
+        let mut client = client(Arc::new(MemoryBackend::new()), shutdown.clone()).await;
+
+        let err = client
+            .search(pb::SearchRequest {
+                q: "whale".to_string(),
+                year_from: Some(1900),
+                year_to: Some(1800),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        assert!(err.message().contains("year_from (1900) is after year_to (1800)"), "{}", err.message());
+
+        let err = client
+            .search(pb::SearchRequest {
+                q: "whale".to_string(),
+                mode: Some("sometimes".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        shutdown.trigger();
+    }
+}
@@ -0,0 +1,125 @@
+//! Book Metadata Cache
+//!
+//! Every search hydrates the metadata of all matching books, which is the
+//! same handful of popular books request after request. The cache keeps
+//! recently used metadata in memory so only books it hasn't seen lately
+//! reach the backend, in a single batched call.
+//!
+//! Entries expire `METADATA_CACHE_TTL_SECS` after they were fetched
+//! (default: `300`), so a re-indexed book's new metadata shows up within
+//! that time. At most `METADATA_CACHE_MAX_ENTRIES` books are kept (default:
+//! `10000`), evicting the least recently used. Books that aren't indexed
+//! are never cached.
+
+use crate::models::storage::{BookMetadata, StorageError};
+use crate::Backend;
+use moka::sync::Cache;
+use std::time::Duration;
+
+const DEFAULT_TTL_SECS: u64 = 300;
+const DEFAULT_MAX_ENTRIES: u64 = 10_000;
+
+/// Shared in-memory cache of [`BookMetadata`] by book ID; clones share
+/// entries.
+#[derive(Clone)]
+pub struct MetadataCache {
+    books: Cache<u32, BookMetadata>,
+}
+
+impl MetadataCache {
+    pub fn new(max_entries: u64, ttl: Duration) -> Self {
+        Self {
+            books: Cache::builder().max_capacity(max_entries).time_to_live(ttl).build(),
+        }
+    }
+
+    /// Reads `METADATA_CACHE_TTL_SECS` and `METADATA_CACHE_MAX_ENTRIES`.
+    pub fn from_env() -> Self {
+        let env_or = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            env_or("METADATA_CACHE_MAX_ENTRIES", DEFAULT_MAX_ENTRIES),
+            Duration::from_secs(env_or("METADATA_CACHE_TTL_SECS", DEFAULT_TTL_SECS)),
+        )
+    }
+
+    /// Metadata of `book_ids`, fetching the ones not cached from `backend`
+    /// in one call. Books that aren't indexed are left out.
+    pub async fn get_many(&self, book_ids: &[u32], backend: &Backend) -> Result<Vec<BookMetadata>, StorageError> {
+        let mut books = Vec::with_capacity(book_ids.len());
+        let mut misses = Vec::new();
+        for &book_id in book_ids {
+            match self.books.get(&book_id) {
+                Some(metadata) => books.push(metadata),
+                None => misses.push(book_id),
+            }
+        }
+
+        if !misses.is_empty() {
+            for metadata in backend.get_book_metadatas(&misses).await? {
+                self.books.insert(metadata.book_id, metadata.clone());
+                books.push(metadata);
+            }
+        }
+
+        Ok(books)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{MemoryBackend, StorageBackend};
+    use std::sync::Arc;
+
+    fn book(book_id: u32, title: &str) -> BookMetadata {
+        BookMetadata {
+            book_id,
+            title: title.to_string(),
+            author: "Jane Austen".to_string(),
+            language: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn titles(mut books: Vec<BookMetadata>) -> Vec<String> {
+        books.sort_by_key(|book| book.book_id);
+        books.into_iter().map(|book| book.title).collect()
+    }
+
+    #[tokio::test]
+    async fn serves_cached_books_without_the_backend() {
+        let memory = MemoryBackend::new();
+        memory.store_book_metadata(&book(158, "Emma")).await.unwrap();
+        let backend: Backend = Arc::new(memory.clone());
+        let cache = MetadataCache::new(100, Duration::from_secs(60));
+
+        assert_eq!(titles(cache.get_many(&[158, 1342], &backend).await.unwrap()), ["Emma"]);
+
+        // A cached book keeps its metadata; a book missing before is looked up again
+        memory.store_book_metadata(&book(158, "Emma (revised)")).await.unwrap();
+        memory.store_book_metadata(&book(1342, "Pride and Prejudice")).await.unwrap();
+        assert_eq!(
+            titles(cache.get_many(&[158, 1342], &backend).await.unwrap()),
+            ["Emma", "Pride and Prejudice"]
+        );
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_the_ttl() {
+        let memory = MemoryBackend::new();
+        memory.store_book_metadata(&book(158, "Emma")).await.unwrap();
+        let backend: Backend = Arc::new(memory.clone());
+        let cache = MetadataCache::new(100, Duration::from_millis(50));
+
+        cache.get_many(&[158], &backend).await.unwrap();
+        memory.store_book_metadata(&book(158, "Emma (revised)")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(titles(cache.get_many(&[158], &backend).await.unwrap()), ["Emma (revised)"]);
+    }
+}
@@ -0,0 +1,100 @@
+//! Search Metrics
+//!
+//! Prometheus instrumentation for the search service, exposed on
+//! `GET /metrics`:
+//!
+//! - `search_cache_hits_total` — searches answered from the result cache
+//! - `search_cache_misses_total` — searches that ran against the backend
+//! - `search_partial_responses_total` — searches answered with partial
+//!   results after running out of their time budget
+//!
+//! The cache hit rate is `hits / (hits + misses)`.
+
+use prometheus::{Encoder, IntCounter, Registry, TextEncoder};
+use std::sync::OnceLock;
+
+pub struct SearchMetrics {
+    registry: Registry,
+    cache_hits: IntCounter,
+    cache_misses: IntCounter,
+    partial_responses: IntCounter,
+}
+
+impl SearchMetrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let cache_hits = IntCounter::new(
+            "search_cache_hits_total",
+            "Searches answered from the result cache",
+        )
+        .unwrap();
+        let cache_misses = IntCounter::new(
+            "search_cache_misses_total",
+            "Searches that missed the result cache and ran against the backend",
+        )
+        .unwrap();
+        let partial_responses = IntCounter::new(
+            "search_partial_responses_total",
+            "Searches answered with partial results after running out of their time budget",
+        )
+        .unwrap();
+
+        registry.register(Box::new(cache_hits.clone())).unwrap();
+        registry.register(Box::new(cache_misses.clone())).unwrap();
+        registry.register(Box::new(partial_responses.clone())).unwrap();
+
+        Self {
+            registry,
+            cache_hits,
+            cache_misses,
+            partial_responses,
+        }
+    }
+
+    pub fn record_cache_hit(&self) {
+        self.cache_hits.inc();
+    }
+
+    pub fn record_cache_miss(&self) {
+        self.cache_misses.inc();
+    }
+
+    pub fn record_partial_response(&self) {
+        self.partial_responses.inc();
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Process-wide metrics registry.
+pub fn metrics() -> &'static SearchMetrics {
+    static METRICS: OnceLock<SearchMetrics> = OnceLock::new();
+    METRICS.get_or_init(SearchMetrics::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cache_counters() {
+        let metrics = SearchMetrics::new();
+        metrics.record_cache_miss();
+        metrics.record_cache_hit();
+        metrics.record_cache_hit();
+        metrics.record_partial_response();
+
+        let text = metrics.render();
+        assert!(text.contains("search_cache_hits_total 2"));
+        assert!(text.contains("search_cache_misses_total 1"));
+        assert!(text.contains("search_partial_responses_total 1"));
+    }
+}
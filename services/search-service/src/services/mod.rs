@@ -0,0 +1,17 @@
+pub mod backend_monitor;
+pub mod dedupe;
+pub mod facets;
+pub mod grpc;
+pub mod metadata_cache;
+pub mod metrics;
+pub mod query;
+pub mod query_log;
+pub mod related;
+pub mod result_cache;
+pub mod search;
+pub mod shutdown;
+pub mod startup;
+pub mod suggest;
+pub mod synonyms;
+pub mod timeout;
+pub mod wildcard;
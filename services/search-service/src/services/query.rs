@@ -0,0 +1,886 @@
+//! Boolean Queries
+//!
+//! Parses `q` into a small AST and evaluates it against the index's posting
+//! sets. Supported syntax, from loosest to tightest binding:
+//!
+//! - `a OR b` — books containing either
+//! - `a AND b`, or just `a b` — books containing both
+//! - `NOT a`, also infix as `a NOT b` (`a AND NOT b`)
+//! - parentheses for grouping: `whale AND (ship OR boat)`
+//! - `title:pride`, `author:austen` — the word must be in that field;
+//!   `body:whale` is the same as `whale`. Quotes keep several words under
+//!   one field, `title:"pride and prejudice"`, each of them required there
+//! - `whal*` — any indexed word starting with `whal`; with
+//!   [`parse_regex_query`], every term is a regular expression instead,
+//!   quoted if it holds spaces or parentheses. Both are expanded to the
+//!   words they match by [`crate::services::wildcard`]
+//!
+//! Quotes also stop words inside them being read as operators. Any other
+//! `name:` prefix of letters is rejected as an unknown field.
+//!
+//! Operators must be uppercase; a lowercase `and` is an ordinary word. Words
+//! are normalized with the indexer's rules, so words under three letters
+//! drop out of the query instead of matching nothing.
+//!
+//! `mode=any` only changes queries without explicit operators or
+//! parentheses, turning `pride prejudice` into `pride OR prejudice`.
+
+use crate::models::storage::{IndexField, StorageError};
+use crate::services::search::MatchMode;
+use crate::services::wildcard::{build_regex, MIN_PREFIX_LEN};
+use crate::utils::text::{index_terms, TokenizerConfig};
+use crate::Backend;
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A parsed query. `And` and `Or` hold at least two children, none of the
+/// same kind as their parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Term(String),
+    /// A term that must be in the title or author; `body:` terms are plain
+    /// [`Query::Term`]s.
+    Field(IndexField, String),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    /// A pattern in a field (`Body` when unscoped), matching nothing until
+    /// it is replaced by the words it stands for.
+    Pattern(IndexField, TermPattern),
+}
+
+/// A term standing for the indexed words it matches.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TermPattern {
+    /// `whal*`: words starting with the folded, lowercase prefix.
+    Prefix(String),
+    /// Words the whole expression matches, ignoring case.
+    Regex(String),
+}
+
+impl fmt::Display for TermPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TermPattern::Prefix(prefix) => write!(f, "{}*", prefix),
+            TermPattern::Regex(pattern) if pattern.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') => {
+                write!(f, "\"{}\"", pattern)
+            }
+            TermPattern::Regex(pattern) => write!(f, "{}", pattern),
+        }
+    }
+}
+
+/// Why a query could not be parsed. `position` is the byte offset of the
+/// offending token in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Word(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match &self.kind {
+            TokenKind::Word(word) => format!("'{}'", word),
+            TokenKind::And => "'AND'".to_string(),
+            TokenKind::Or => "'OR'".to_string(),
+            TokenKind::Not => "'NOT'".to_string(),
+            TokenKind::Open => "'('".to_string(),
+            TokenKind::Close => "')'".to_string(),
+        }
+    }
+
+    fn starts_operand(&self) -> bool {
+        matches!(self.kind, TokenKind::Word(_) | TokenKind::Not | TokenKind::Open)
+    }
+}
+
+/// Splits the input on whitespace, with parentheses as tokens of their own.
+/// A double quote inside a word runs it on to the next quote, spaces and
+/// parentheses included.
+fn lex(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word_start = None;
+    let mut quoted = false;
+
+    let end_word = |tokens: &mut Vec<Token>, start: Option<usize>, end: usize| {
+        if let Some(start) = start {
+            let kind = match &input[start..end] {
+                "AND" => TokenKind::And,
+                "OR" => TokenKind::Or,
+                "NOT" => TokenKind::Not,
+                word => TokenKind::Word(word.to_string()),
+            };
+            tokens.push(Token { kind, position: start });
+        }
+    };
+
+    for (i, c) in input.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+            word_start.get_or_insert(i);
+        } else if quoted {
+            continue;
+        } else if c.is_whitespace() || c == '(' || c == ')' {
+            end_word(&mut tokens, word_start.take(), i);
+            match c {
+                '(' => tokens.push(Token { kind: TokenKind::Open, position: i }),
+                ')' => tokens.push(Token { kind: TokenKind::Close, position: i }),
+                _ => {}
+            }
+        } else if word_start.is_none() {
+            word_start = Some(i);
+        }
+    }
+    end_word(&mut tokens, word_start, input.len());
+
+    tokens
+}
+
+/// Splits a `name:` prefix off `word`. Returns `None` for the field when
+/// there is no prefix of letters followed by a term; `Err` with the name
+/// when the field is unknown.
+fn split_field(word: &str) -> Result<(Option<IndexField>, &str), &str> {
+    let Some((name, term)) = word.split_once(':') else {
+        return Ok((None, word));
+    };
+    if name.is_empty() || term.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Ok((None, word));
+    }
+
+    let field = match name.to_ascii_lowercase().as_str() {
+        "title" => IndexField::Title,
+        "author" => IndexField::Author,
+        "body" => IndexField::Body,
+        _ => return Err(name),
+    };
+    Ok((Some(field), term))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Join {
+    And,
+    Or,
+}
+
+/// Joins `parts` with `join`, dropping empty operands and merging children
+/// of the same kind.
+fn combine(parts: Vec<Option<Query>>, join: Join) -> Option<Query> {
+    let mut children = Vec::new();
+    for part in parts.into_iter().flatten() {
+        match (join, part) {
+            (Join::And, Query::And(inner)) | (Join::Or, Query::Or(inner)) => children.extend(inner),
+            (_, part) => children.push(part),
+        }
+    }
+
+    match children.len() {
+        0 => None,
+        1 => children.pop(),
+        _ => Some(match join {
+            Join::And => Query::And(children),
+            Join::Or => Query::Or(children),
+        }),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    next: usize,
+    input_len: usize,
+    /// Whether the input used any operator or parenthesis.
+    explicit: bool,
+    /// Whether every term is a regular expression.
+    regex: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.next);
+        self.next += 1;
+        token
+    }
+
+    /// `or := and ("OR" and)*`. `after` names what the first operand
+    /// follows, for error messages.
+    fn parse_or(&mut self, after: &str) -> Result<Option<Query>, ParseError> {
+        let mut parts = vec![self.parse_and(after)?];
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.explicit = true;
+            self.advance();
+            parts.push(self.parse_and("'OR'")?);
+        }
+        Ok(combine(parts, Join::Or))
+    }
+
+    /// `and := unary (["AND"] unary)*`
+    fn parse_and(&mut self, after: &str) -> Result<Option<Query>, ParseError> {
+        let mut parts = vec![self.parse_unary(after)?];
+        loop {
+            match self.peek() {
+                Some(token) if token.kind == TokenKind::And => {
+                    self.explicit = true;
+                    self.advance();
+                    parts.push(self.parse_unary("'AND'")?);
+                }
+                Some(token) if token.starts_operand() => parts.push(self.parse_unary("a term")?),
+                _ => break,
+            }
+        }
+        Ok(combine(parts, Join::And))
+    }
+
+    /// A regular expression, with quotes dropped, or a single word ending
+    /// in `*`.
+    fn parse_pattern(&self, word: &str, position: usize) -> Result<TermPattern, ParseError> {
+        let error = |message: String| ParseError { position, message };
+        if self.regex {
+            let pattern = word.replace('"', "");
+            build_regex(&pattern).map_err(|reason| error(format!("invalid regex '{}': {}", pattern, reason)))?;
+            return Ok(TermPattern::Regex(pattern));
+        }
+
+        let prefix = word
+            .strip_suffix('*')
+            .filter(|prefix| !prefix.contains('*'))
+            .ok_or_else(|| error(format!("'*' is only allowed at the end of a word, in '{}'", word)))?;
+        let prefix = TokenizerConfig::configured().fold(prefix).to_lowercase();
+        if !prefix.chars().all(char::is_alphanumeric) {
+            return Err(error(format!("wildcard '{}' must be a single word", word)));
+        }
+        if prefix.chars().count() < MIN_PREFIX_LEN {
+            return Err(error(format!("wildcard '{}' needs at least {} letters before '*'", word, MIN_PREFIX_LEN)));
+        }
+        Ok(TermPattern::Prefix(prefix))
+    }
+
+    /// `unary := "NOT" unary | word | "(" or ")"`. `after` names what the
+    /// operand follows, for error messages.
+    fn parse_unary(&mut self, after: &str) -> Result<Option<Query>, ParseError> {
+        let Some(token) = self.advance() else {
+            return Err(ParseError {
+                position: self.input_len,
+                message: format!("expected a term after {}", after),
+            });
+        };
+
+        match &token.kind {
+            TokenKind::Word(word) => {
+                let (field, word) = split_field(word).map_err(|name| ParseError {
+                    position: token.position,
+                    message: format!("unknown field '{}' (expected title, author or body)", name),
+                })?;
+                if self.regex || word.contains('*') {
+                    let pattern = self.parse_pattern(word, token.position)?;
+                    return Ok(Some(Query::Pattern(field.unwrap_or(IndexField::Body), pattern)));
+                }
+                let terms = index_terms(word)
+                    .into_iter()
+                    .map(|term| {
+                        Some(match field {
+                            Some(field) if !field.is_body() => Query::Field(field, term),
+                            _ => Query::Term(term),
+                        })
+                    })
+                    .collect();
+                Ok(combine(terms, Join::And))
+            }
+            TokenKind::Not => {
+                self.explicit = true;
+                Ok(self.parse_unary("'NOT'")?.map(|query| Query::Not(Box::new(query))))
+            }
+            TokenKind::Open => {
+                self.explicit = true;
+                let open = token.position;
+                let inner = self.parse_or("'('")?;
+                match self.advance() {
+                    Some(token) if token.kind == TokenKind::Close => Ok(inner),
+                    Some(token) => Err(ParseError {
+                        position: token.position,
+                        message: format!("unexpected {}", token.describe()),
+                    }),
+                    None => Err(ParseError {
+                        position: open,
+                        message: "unclosed '('".to_string(),
+                    }),
+                }
+            }
+            TokenKind::And | TokenKind::Or | TokenKind::Close => Err(ParseError {
+                position: token.position,
+                message: format!("unexpected {}", token.describe()),
+            }),
+        }
+    }
+}
+
+/// Parses `input`, returning `None` when nothing searchable is left (an
+/// empty query, or only words under three letters). Bare words are ANDed, or ORed with
+/// [`MatchMode::Any`] if the query has no operators at all.
+pub fn parse_query(input: &str, mode: MatchMode) -> Result<Option<Query>, ParseError> {
+    parse(input, mode, false)
+}
+
+/// Like [`parse_query`], reading every term as a regular expression.
+pub fn parse_regex_query(input: &str, mode: MatchMode) -> Result<Option<Query>, ParseError> {
+    parse(input, mode, true)
+}
+
+fn parse(input: &str, mode: MatchMode, regex: bool) -> Result<Option<Query>, ParseError> {
+    let tokens = lex(input);
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parser = Parser {
+        tokens: &tokens,
+        next: 0,
+        input_len: input.len(),
+        explicit: false,
+        regex,
+    };
+    let query = parser.parse_or("the start of the query")?;
+    if let Some(token) = parser.peek() {
+        return Err(ParseError {
+            position: token.position,
+            message: format!("unexpected {}", token.describe()),
+        });
+    }
+
+    Ok(match query {
+        Some(Query::And(terms)) if mode == MatchMode::Any && !parser.explicit => Some(Query::Or(terms)),
+        query => query,
+    })
+}
+
+/// Every word of `input` apart from the operators, field names and
+/// wildcards, lowercased and folded like the index's words, in order and
+/// without repeats. Words are split at the characters that separate words
+/// for the tokenizer, but unlike the parser this keeps the ones the index
+/// never holds, such as words under three letters.
+pub fn query_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for token in lex(input) {
+        let TokenKind::Word(word) = token.kind else {
+            continue;
+        };
+        if word.contains('*') {
+            continue;
+        }
+        let word = split_field(&word).map_or(word.as_str(), |(_, term)| term);
+        for part in word.split(|c: char| !(c.is_alphanumeric() || c == '_')).filter(|part| !part.is_empty()) {
+            let part = TokenizerConfig::configured().fold(part).to_lowercase();
+            if !words.contains(&part) {
+                words.push(part);
+            }
+        }
+    }
+    words
+}
+
+impl Query {
+    /// Terms a matching book may contain, i.e. those not under a `NOT`,
+    /// whatever field they are scoped to.
+    pub fn positive_terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        self.collect_positive_terms(&mut terms);
+        terms
+    }
+
+    fn collect_positive_terms(&self, terms: &mut Vec<String>) {
+        match self {
+            Query::Term(term) | Query::Field(_, term) if !terms.contains(term) => terms.push(term.clone()),
+            Query::Term(_) | Query::Field(..) | Query::Not(_) | Query::Pattern(..) => {}
+            Query::And(children) | Query::Or(children) => {
+                children.iter().for_each(|child| child.collect_positive_terms(terms))
+            }
+        }
+    }
+
+    /// This query with every occurrence of the term `from` replaced by `to`.
+    pub fn replace_term(&self, from: &str, to: &str) -> Query {
+        match self {
+            Query::Term(term) if term == from => Query::Term(to.to_string()),
+            Query::Field(field, term) if term == from => Query::Field(*field, to.to_string()),
+            Query::Term(_) | Query::Field(..) | Query::Pattern(..) => self.clone(),
+            Query::And(children) => Query::And(children.iter().map(|child| child.replace_term(from, to)).collect()),
+            Query::Or(children) => Query::Or(children.iter().map(|child| child.replace_term(from, to)).collect()),
+            Query::Not(inner) => Query::Not(Box::new(inner.replace_term(from, to))),
+        }
+    }
+
+    /// This query with each term replaced by an `OR` of itself and its
+    /// `alternatives`, in the same field, so an expanded term still counts
+    /// as one operand of an `AND`. Inside an `OR` the alternatives join the
+    /// parent instead of nesting, without repeats.
+    pub fn expand_terms(&self, alternatives: &impl Fn(&str) -> Vec<String>) -> Query {
+        let expand = |term: &str, as_query: &dyn Fn(String) -> Query| {
+            let others = alternatives(term);
+            if others.is_empty() {
+                return as_query(term.to_string());
+            }
+            let mut group = vec![as_query(term.to_string())];
+            for other in others {
+                let other = as_query(other);
+                if !group.contains(&other) {
+                    group.push(other);
+                }
+            }
+            Query::Or(group)
+        };
+
+        match self {
+            Query::Term(term) => expand(term, &Query::Term),
+            Query::Field(field, term) => expand(term, &|term| Query::Field(*field, term)),
+            Query::Pattern(..) => self.clone(),
+            Query::And(children) => Query::And(children.iter().map(|child| child.expand_terms(alternatives)).collect()),
+            Query::Or(children) => {
+                let mut expanded = Vec::new();
+                for child in children {
+                    let flattened = match child.expand_terms(alternatives) {
+                        Query::Or(group) => group,
+                        child => vec![child],
+                    };
+                    for child in flattened {
+                        if !expanded.contains(&child) {
+                            expanded.push(child);
+                        }
+                    }
+                }
+                Query::Or(expanded)
+            }
+            Query::Not(inner) => Query::Not(Box::new(inner.expand_terms(alternatives))),
+        }
+    }
+
+    /// The patterns in this query with the field each is matched in, in
+    /// order and without repeats.
+    pub fn patterns(&self) -> Vec<(IndexField, TermPattern)> {
+        let mut patterns = Vec::new();
+        self.collect_patterns(&mut patterns);
+        patterns
+    }
+
+    fn collect_patterns(&self, patterns: &mut Vec<(IndexField, TermPattern)>) {
+        match self {
+            Query::Pattern(field, pattern) if !patterns.contains(&(*field, pattern.clone())) => {
+                patterns.push((*field, pattern.clone()))
+            }
+            Query::Term(_) | Query::Field(..) | Query::Pattern(..) => {}
+            Query::Not(inner) => inner.collect_patterns(patterns),
+            Query::And(children) | Query::Or(children) => {
+                children.iter().for_each(|child| child.collect_patterns(patterns))
+            }
+        }
+    }
+
+    /// This query with each pattern replaced by an `OR` of the `words` it
+    /// matches, in its field, like [`Query::expand_terms`] does with
+    /// synonyms. Patterns without words are kept, and match nothing.
+    pub fn expand_patterns<'a>(&self, words: &impl Fn(IndexField, &TermPattern) -> Option<&'a [String]>) -> Query {
+        match self {
+            Query::Pattern(field, pattern) => {
+                let Some(words) = words(*field, pattern).filter(|words| !words.is_empty()) else {
+                    return self.clone();
+                };
+                let as_query = |word: &String| match field {
+                    IndexField::Body => Query::Term(word.clone()),
+                    field => Query::Field(*field, word.clone()),
+                };
+                match words {
+                    [word] => as_query(word),
+                    words => Query::Or(words.iter().map(as_query).collect()),
+                }
+            }
+            Query::Term(_) | Query::Field(..) => self.clone(),
+            Query::And(children) => Query::And(children.iter().map(|child| child.expand_patterns(words)).collect()),
+            Query::Or(children) => {
+                let mut expanded = Vec::new();
+                for child in children {
+                    let flattened = match child.expand_patterns(words) {
+                        Query::Or(group) => group,
+                        child => vec![child],
+                    };
+                    for child in flattened {
+                        if !expanded.contains(&child) {
+                            expanded.push(child);
+                        }
+                    }
+                }
+                match expanded.len() {
+                    1 => expanded.remove(0),
+                    _ => Query::Or(expanded),
+                }
+            }
+            Query::Not(inner) => Query::Not(Box::new(inner.expand_patterns(words))),
+        }
+    }
+
+    /// Whether every match contains every positive term: a single term, or
+    /// terms ANDed together, possibly with negations.
+    pub fn requires_all_terms(&self) -> bool {
+        match self {
+            Query::Term(_) | Query::Field(..) | Query::Pattern(..) => true,
+            Query::And(children) => children
+                .iter()
+                .all(|child| matches!(child, Query::Term(_) | Query::Field(..) | Query::Pattern(..) | Query::Not(_))),
+            Query::Or(_) | Query::Not(_) => false,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parent_binds_tighter: bool) -> fmt::Result {
+        if parent_binds_tighter {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+/// Normalized form: lowercase terms, `field:` prefixes only for title and
+/// author, explicit uppercase operators and only the parentheses precedence
+/// requires.
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Term(term) => write!(f, "{}", term),
+            Query::Field(field, term) => write!(f, "{}:{}", field.as_str(), term),
+            Query::Pattern(IndexField::Body, pattern) => write!(f, "{}", pattern),
+            Query::Pattern(field, pattern) => write!(f, "{}:{}", field.as_str(), pattern),
+            Query::Not(inner) => {
+                write!(f, "NOT ")?;
+                inner.fmt_operand(f, matches!(**inner, Query::And(_) | Query::Or(_)))
+            }
+            Query::And(children) => {
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    child.fmt_operand(f, matches!(child, Query::Or(_)))?;
+                }
+                Ok(())
+            }
+            Query::Or(children) => {
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " OR ")?;
+                    }
+                    child.fmt_operand(f, false)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+type BookSetFuture<'a> = Pin<Box<dyn Future<Output = Result<HashSet<u32>, StorageError>> + Send + 'a>>;
+
+/// Returns the books matching `query`. Plain conjunctions and disjunctions
+/// of terms are resolved by the backend in one call; `NOT` without a
+/// positive operand beside it is taken against every indexed book.
+pub fn evaluate<'a>(query: &'a Query, backend: &'a Backend) -> BookSetFuture<'a> {
+    Box::pin(async move {
+        match query {
+            Query::Term(term) => backend.search_word(term).await,
+            Query::Field(field, term) => backend.get_books_for_word_in_field(term, *field).await,
+            Query::Pattern(..) => Ok(HashSet::new()),
+            Query::Not(inner) => {
+                let mut books = backend.get_indexed_books().await?;
+                let excluded = evaluate(inner, backend).await?;
+                books.retain(|book_id| !excluded.contains(book_id));
+                Ok(books)
+            }
+            Query::Or(children) => {
+                if let Some(terms) = as_terms(children) {
+                    return backend.search_any_word(&terms).await;
+                }
+                let mut books = HashSet::new();
+                for child in children {
+                    books.extend(evaluate(child, backend).await?);
+                }
+                Ok(books)
+            }
+            Query::And(children) => {
+                let (negated, positive): (Vec<&Query>, Vec<&Query>) =
+                    children.iter().partition(|child| matches!(child, Query::Not(_)));
+
+                let mut books = if positive.is_empty() {
+                    backend.get_indexed_books().await?
+                } else if let Some(terms) = as_terms(positive.iter().copied()) {
+                    backend.search_all_words(&terms).await?
+                } else {
+                    let mut books: Option<HashSet<u32>> = None;
+                    for child in positive {
+                        let matched = evaluate(child, backend).await?;
+                        let narrowed = match books {
+                            Some(mut books) => {
+                                books.retain(|book_id| matched.contains(book_id));
+                                books
+                            }
+                            None => matched,
+                        };
+                        if narrowed.is_empty() {
+                            return Ok(narrowed);
+                        }
+                        books = Some(narrowed);
+                    }
+                    books.unwrap_or_default()
+                };
+
+                for child in negated {
+                    if books.is_empty() {
+                        break;
+                    }
+                    let Query::Not(inner) = child else { unreachable!() };
+                    let excluded = evaluate(inner, backend).await?;
+                    books.retain(|book_id| !excluded.contains(book_id));
+                }
+                Ok(books)
+            }
+        }
+    })
+}
+
+/// The terms of `queries`, if every one of them is a bare term.
+fn as_terms<'a>(queries: impl IntoIterator<Item = &'a Query>) -> Option<Vec<String>> {
+    queries
+        .into_iter()
+        .map(|query| match query {
+            Query::Term(term) => Some(term.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{BookMetadata, IndexField, MemoryBackend, StorageBackend};
+    use std::sync::Arc;
+
+    fn parse(input: &str) -> String {
+        parse_query(input, MatchMode::All).unwrap().map(|query| query.to_string()).unwrap_or_default()
+    }
+
+    fn parse_error(input: &str) -> ParseError {
+        parse_query(input, MatchMode::All).unwrap_err()
+    }
+
+    #[test]
+    fn bare_words_are_anded() {
+        assert_eq!(parse("Whale ship"), "whale AND ship");
+        assert_eq!(parse_query("whale ship", MatchMode::Any).unwrap().unwrap().to_string(), "whale OR ship");
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse_query("whale OR ship AND boat", MatchMode::All).unwrap(),
+            Some(Query::Or(vec![
+                Query::Term("whale".to_string()),
+                Query::And(vec![Query::Term("ship".to_string()), Query::Term("boat".to_string())]),
+            ]))
+        );
+        assert_eq!(parse("(whale OR ship) AND boat"), "(whale OR ship) AND boat");
+    }
+
+    #[test]
+    fn not_works_as_prefix_and_infix() {
+        assert_eq!(parse("whale AND ship NOT pequod"), "whale AND ship AND NOT pequod");
+        assert_eq!(parse("NOT (whale OR ship)"), "NOT (whale OR ship)");
+        assert_eq!(parse("NOT NOT whale"), "NOT NOT whale");
+    }
+
+    #[test]
+    fn explicit_operators_ignore_mode_any() {
+        let query = parse_query("whale ship OR boat", MatchMode::Any).unwrap().unwrap();
+        assert_eq!(query.to_string(), "whale AND ship OR boat");
+    }
+
+    #[test]
+    fn short_words_drop_out() {
+        assert_eq!(parse("a whale AND of the sea"), "whale AND the AND sea");
+        assert_eq!(parse("whale OR (a AND of)"), "whale");
+        assert_eq!(parse_query("of", MatchMode::All).unwrap(), None);
+        assert_eq!(parse_query("   ", MatchMode::All).unwrap(), None);
+    }
+
+    #[test]
+    fn query_words_keep_the_words_the_parser_drops() {
+        assert_eq!(query_words("The whale AND (it OR whale's)"), ["the", "whale", "it", "s"]);
+        assert_eq!(query_words("NOT café x86"), ["café", "x86"]);
+        assert!(query_words("( AND )").is_empty());
+    }
+
+    #[test]
+    fn malformed_queries_point_at_the_offending_token() {
+        assert_eq!(parse_error("whale AND").to_string(), "expected a term after 'AND' at position 9");
+        assert_eq!(parse_error("OR whale").to_string(), "unexpected 'OR' at position 0");
+        assert_eq!(parse_error("whale )").to_string(), "unexpected ')' at position 6");
+        assert_eq!(parse_error("ship (whale OR boat").to_string(), "unclosed '(' at position 5");
+        assert_eq!(parse_error("whale AND OR ship").to_string(), "unexpected 'OR' at position 10");
+        assert_eq!(parse_error("NOT").to_string(), "expected a term after 'NOT' at position 3");
+        assert_eq!(parse_error("whale OR").to_string(), "expected a term after 'OR' at position 8");
+        assert_eq!(parse_error("()").to_string(), "unexpected ')' at position 1");
+    }
+
+    #[test]
+    fn trailing_stars_make_prefix_patterns() {
+        assert_eq!(parse("Whal* ship"), "whal* AND ship");
+        assert_eq!(parse("title:Pri* OR NOT café*"), "title:pri* OR NOT café*");
+        assert_eq!(
+            parse_query("author:aus*", MatchMode::All).unwrap(),
+            Some(Query::Pattern(IndexField::Author, TermPattern::Prefix("aus".to_string())))
+        );
+        assert!(query_words("whal* ship").iter().eq(["ship"]));
+
+        assert_eq!(parse_error("w*").to_string(), "wildcard 'w*' needs at least 2 letters before '*' at position 0");
+        assert_eq!(parse_error("ship wh*le").to_string(), "'*' is only allowed at the end of a word, in 'wh*le' at position 5");
+        assert_eq!(parse_error("whale's*").to_string(), "wildcard 'whale's*' must be a single word at position 0");
+    }
+
+    #[test]
+    fn regex_queries_keep_operators_and_quote_parentheses() {
+        let parse_regex = |input: &str| parse_regex_query(input, MatchMode::All).unwrap().unwrap().to_string();
+        assert_eq!(parse_regex("colou?r OR title:Wh.le"), "colou?r OR title:Wh.le");
+        assert_eq!(parse_regex("\"(whale|ship)\" NOT boat"), "\"(whale|ship)\" AND NOT boat");
+
+        let error = parse_regex_query("ship colo[ur", MatchMode::All).unwrap_err();
+        assert_eq!(error.position, 5);
+        assert!(error.message.starts_with("invalid regex 'colo[ur': "), "{}", error.message);
+    }
+
+    #[test]
+    fn field_prefixes_scope_terms() {
+        assert_eq!(parse("title:Pride author:austen whale"), "title:pride AND author:austen AND whale");
+        assert_eq!(parse("body:whale OR Title:ship"), "whale OR title:ship");
+        assert_eq!(parse("title:\"pride and prejudice\" NOT author:bronte"), "title:pride AND title:and AND title:prejudice AND NOT author:bronte");
+        assert_eq!(parse("\"whale OR ship\""), "whale AND ship");
+        assert_eq!(parse("10:30 whale"), "whale");
+        assert_eq!(query_words("title:pride author:\"jane austen\""), ["pride", "jane", "austen"]);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert_eq!(
+            parse_error("whale isbn:foo").to_string(),
+            "unknown field 'isbn' (expected title, author or body) at position 6"
+        );
+    }
+
+    #[test]
+    fn positive_terms_skip_negations() {
+        let query = parse_query("whale OR ship NOT pequod", MatchMode::All).unwrap().unwrap();
+        assert_eq!(query.positive_terms(), ["whale", "ship"]);
+        assert!(!query.requires_all_terms());
+        assert!(parse_query("whale NOT pequod", MatchMode::All).unwrap().unwrap().requires_all_terms());
+    }
+
+    #[test]
+    fn expanded_terms_form_one_operand() {
+        let synonyms = |term: &str| match term {
+            "car" => vec!["automobile".to_string(), "motorcar".to_string()],
+            "automobile" => vec!["car".to_string()],
+            _ => Vec::new(),
+        };
+        let expand = |input: &str, mode: MatchMode| {
+            parse_query(input, mode).unwrap().unwrap().expand_terms(&synonyms).to_string()
+        };
+
+        assert_eq!(expand("red car", MatchMode::All), "red AND (car OR automobile OR motorcar)");
+        assert_eq!(expand("red car", MatchMode::Any), "red OR car OR automobile OR motorcar");
+        assert_eq!(expand("car OR automobile", MatchMode::All), "car OR automobile OR motorcar");
+        assert_eq!(expand("title:car NOT automobile", MatchMode::All), "(title:car OR title:automobile OR title:motorcar) AND NOT (automobile OR car)");
+        assert_eq!(expand("whale", MatchMode::All), "whale");
+        assert!(!parse_query("red car", MatchMode::All).unwrap().unwrap().expand_terms(&synonyms).requires_all_terms());
+    }
+
+    /// Books 1–3: 1 has whale and ship, 2 whale and pequod, 3 ship only.
+    async fn backend() -> Backend {
+        let backend = MemoryBackend::new();
+        for (book_id, words) in [(1, &["whale", "ship"][..]), (2, &["whale", "pequod"]), (3, &["ship"])] {
+            backend
+                .store_book_metadata(&BookMetadata {
+                    book_id,
+                    language: "en".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            for word in words {
+                backend.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        Arc::new(backend)
+    }
+
+    async fn matches(backend: &Backend, input: &str) -> Vec<u32> {
+        let query = parse_query(input, MatchMode::All).unwrap().unwrap();
+        let mut ids: Vec<u32> = evaluate(&query, backend).await.unwrap().into_iter().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[tokio::test]
+    async fn evaluates_against_postings() {
+        let backend = backend().await;
+
+        assert_eq!(matches(&backend, "whale ship").await, [1]);
+        assert_eq!(matches(&backend, "whale OR ship").await, [1, 2, 3]);
+        assert_eq!(matches(&backend, "whale NOT pequod").await, [1]);
+        assert_eq!(matches(&backend, "NOT whale").await, [3]);
+        assert_eq!(matches(&backend, "pequod OR ship AND whale").await, [1, 2]);
+        assert_eq!(matches(&backend, "(pequod OR ship) AND whale").await, [1, 2]);
+        assert_eq!(matches(&backend, "(pequod OR ship) NOT whale").await, [3]);
+    }
+
+    #[tokio::test]
+    async fn scoped_terms_use_field_postings() {
+        let backend = backend().await;
+        // Book 3 has "ship" in its title as well as its body
+        backend.add_word_to_index("ship", 3, IndexField::Title).await.unwrap();
+
+        assert_eq!(matches(&backend, "title:ship").await, [3]);
+        assert_eq!(matches(&backend, "body:ship").await, [1, 3]);
+        assert_eq!(matches(&backend, "title:ship OR pequod").await, [2, 3]);
+        assert!(matches(&backend, "author:ship").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn negating_everything_matches_nothing() {
+        let backend = backend().await;
+
+        assert!(matches(&backend, "NOT (whale OR ship)").await.is_empty());
+        assert!(matches(&backend, "whale NOT whale").await.is_empty());
+        assert!(matches(&backend, "NOT whale NOT ship").await.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+//! Query Log
+//!
+//! Every search is appended to the backend's query log (see
+//! [`StorageBackend::record_query`]) with its normalized query, filters,
+//! number of results and latency, so `GET /search/stats` can report what
+//! people search for, which searches find nothing, and how fast they are.
+//!
+//! Logging is fire-and-forget: the entry is written from a spawned task and
+//! a failed write is only traced, so it never delays or fails a search.
+//! `SEARCH_LOGGING=off` turns it off, and queries longer than
+//! [`MAX_LOGGED_QUERY_CHARS`] are truncated before they are stored.
+//!
+//! [`StorageBackend::record_query`]: crate::models::storage::StorageBackend::record_query
+
+use crate::models::responses::{LatencyPercentiles, QueryCount};
+use crate::models::storage::QueryLogEntry;
+use crate::Backend;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Longer queries are cut to this many characters before they are logged.
+pub const MAX_LOGGED_QUERY_CHARS: usize = 200;
+
+/// Writes searches to the query log unless logging is switched off.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryLogger {
+    enabled: bool,
+}
+
+impl QueryLogger {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    /// Reads `SEARCH_LOGGING`; `off`, `false` or `0` disable logging.
+    pub fn from_env() -> Self {
+        let setting = std::env::var("SEARCH_LOGGING").unwrap_or_default();
+        Self::new(!matches!(setting.trim().to_lowercase().as_str(), "off" | "false" | "0"))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Stores `entry` in the background. Must be called within a Tokio
+    /// runtime.
+    pub fn log(&self, backend: &Backend, mut entry: QueryLogEntry) {
+        if !self.enabled {
+            return;
+        }
+        entry.query = truncate_chars(&entry.query, MAX_LOGGED_QUERY_CHARS);
+
+        let backend = backend.clone();
+        tokio::spawn(async move {
+            if let Err(e) = backend.record_query(&entry).await {
+                warn!("Failed to log query '{}': {}", entry.query, e);
+            }
+        });
+    }
+}
+
+/// The first `max` characters of `text`, with `…` appended when cut.
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// What a window of the query log adds up to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuerySummary {
+    pub total_queries: usize,
+    pub top_queries: Vec<QueryCount>,
+    pub zero_result_queries: Vec<QueryCount>,
+    pub latency_ms: LatencyPercentiles,
+}
+
+/// Counts searches per query, keeping the `top` most frequent overall and
+/// among those that found nothing. Ties go to the alphabetically first
+/// query.
+pub fn summarize_queries(entries: &[QueryLogEntry], top: usize) -> QuerySummary {
+    let mut all: HashMap<&str, usize> = HashMap::new();
+    let mut zero_result: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        *all.entry(&entry.query).or_insert(0) += 1;
+        if entry.result_count == 0 {
+            *zero_result.entry(&entry.query).or_insert(0) += 1;
+        }
+    }
+
+    let mut latencies: Vec<f64> = entries.iter().map(|entry| entry.latency_ms).collect();
+    latencies.sort_by(f64::total_cmp);
+
+    QuerySummary {
+        total_queries: entries.len(),
+        top_queries: most_frequent(all, top),
+        zero_result_queries: most_frequent(zero_result, top),
+        latency_ms: LatencyPercentiles {
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p99: percentile(&latencies, 99.0),
+            max: latencies.last().copied().unwrap_or(0.0),
+        },
+    }
+}
+
+fn most_frequent(counts: HashMap<&str, usize>, top: usize) -> Vec<QueryCount> {
+    let mut counts: Vec<QueryCount> = counts
+        .into_iter()
+        .map(|(query, count)| QueryCount {
+            query: query.to_string(),
+            count,
+        })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.query.cmp(&b.query)));
+    counts.truncate(top);
+    counts
+}
+
+/// Nearest-rank percentile of sorted `values`; `0` when there are none.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(query: &str, result_count: usize, latency_ms: f64) -> QueryLogEntry {
+        QueryLogEntry {
+            query: query.to_string(),
+            filters: HashMap::new(),
+            result_count,
+            latency_ms,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn ranks_queries_and_zero_result_queries() {
+        let entries = [
+            entry("whale", 3, 1.0),
+            entry("love", 5, 1.0),
+            entry("whale", 3, 1.0),
+            entry("zanzibar", 0, 1.0),
+            entry("quixotic", 0, 1.0),
+            entry("zanzibar", 0, 1.0),
+        ];
+        let summary = summarize_queries(&entries, 2);
+
+        assert_eq!(summary.total_queries, 6);
+        let top: Vec<(&str, usize)> = summary.top_queries.iter().map(|q| (q.query.as_str(), q.count)).collect();
+        assert_eq!(top, [("whale", 2), ("zanzibar", 2)]);
+        let zero: Vec<(&str, usize)> = summary.zero_result_queries.iter().map(|q| (q.query.as_str(), q.count)).collect();
+        assert_eq!(zero, [("zanzibar", 2), ("quixotic", 1)]);
+    }
+
+    #[test]
+    fn latency_percentiles_use_the_nearest_rank() {
+        let entries: Vec<QueryLogEntry> = (1..=100).rev().map(|ms| entry("whale", 1, ms as f64)).collect();
+        let latency = summarize_queries(&entries, 10).latency_ms;
+
+        assert_eq!((latency.p50, latency.p90, latency.p99, latency.max), (50.0, 90.0, 99.0, 100.0));
+        assert_eq!(summarize_queries(&[], 10).latency_ms.p99, 0.0);
+    }
+
+    #[test]
+    fn long_queries_are_truncated_on_a_char_boundary() {
+        assert_eq!(truncate_chars("whale", 10), "whale");
+        assert_eq!(truncate_chars("ééééé", 3), "ééé…");
+    }
+}
@@ -0,0 +1,85 @@
+//! Related Books
+//!
+//! Ranks books by how many indexed words they share with a given book,
+//! using the Jaccard similarity of their word sets:
+//! `|A ∩ B| / |A ∪ B|`, from `0.0` (nothing shared) to `1.0` (same words).
+
+use std::collections::HashSet;
+
+/// Jaccard similarity of two word sets; `0.0` when both are empty.
+pub fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    // Walk the smaller set for the intersection
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let shared = small.iter().filter(|word| large.contains(*word)).count();
+    let union = a.len() + b.len() - shared;
+
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Scores every candidate against `words` and returns the `n` most similar
+/// as `(book_id, similarity)`, highest first and by book ID on ties. Books
+/// sharing no words are left out.
+pub fn rank_related(
+    words: &HashSet<String>,
+    candidates: impl IntoIterator<Item = (u32, HashSet<String>)>,
+    n: usize,
+) -> Vec<(u32, f64)> {
+    let mut ranked: Vec<(u32, f64)> = candidates
+        .into_iter()
+        .map(|(book_id, other)| (book_id, jaccard(words, &other)))
+        .filter(|&(_, similarity)| similarity > 0.0)
+        .collect();
+
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked.truncate(n);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> HashSet<String> {
+        list.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn jaccard_divides_shared_by_combined_words() {
+        let a = words(&["whale", "ship", "sea"]);
+        let b = words(&["ship", "sea", "storm", "captain"]);
+
+        assert_eq!(jaccard(&a, &b), 2.0 / 5.0);
+        assert_eq!(jaccard(&a, &a), 1.0);
+        assert_eq!(jaccard(&a, &words(&["ballroom"])), 0.0);
+        assert_eq!(jaccard(&words(&[]), &words(&[])), 0.0);
+    }
+
+    #[test]
+    fn ranks_most_similar_first_and_drops_unrelated() {
+        let target = words(&["whale", "ship", "sea", "captain"]);
+        let candidates = vec![
+            (3, words(&["ship", "sea"])),
+            (1, words(&["whale", "ship", "sea"])),
+            (2, words(&["ballroom", "dance"])),
+            (4, words(&["sea", "ship", "storm"])),
+        ];
+
+        let ranked = rank_related(&target, candidates, 5);
+        let ids: Vec<u32> = ranked.iter().map(|&(book_id, _)| book_id).collect();
+        assert_eq!(ids, [1, 3, 4]);
+        assert_eq!(ranked[0].1, 0.75);
+    }
+
+    #[test]
+    fn keeps_only_top_n() {
+        let target = words(&["sea"]);
+        let candidates = (1..=10).map(|book_id| (book_id, words(&["sea"])));
+
+        let ranked = rank_related(&target, candidates, 3);
+        assert_eq!(ranked, [(1, 1.0), (2, 1.0), (3, 1.0)]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Search Result Cache
+//!
+//! Popular queries run the same posting lookups over and over. The cache
+//! keeps the serialized `SearchResponse` of recent searches, keyed by the
+//! normalized query, filters and page, so a repeat is answered without
+//! touching the backend.
+//!
+//! Entries expire `SEARCH_CACHE_TTL_SECS` after they were stored (default:
+//! `60`), and at most `SEARCH_CACHE_MAX_ENTRIES` are kept (default: `1000`),
+//! evicting the least recently used. `POST /cache/invalidate` drops entries
+//! early: all of them, or with `?book_id=` only those whose query matched
+//! that book. A book new to the index can match any query, so callers
+//! invalidate everything after indexing new books.
+//!
+//! Hits and misses are counted in [`crate::services::metrics`].
+
+use crate::services::metrics::metrics;
+use crate::utils::conditional::Validators;
+use axum::body::Bytes;
+use moka::sync::Cache;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_TTL_SECS: u64 = 60;
+const DEFAULT_MAX_ENTRIES: u64 = 1000;
+
+/// A cached search response.
+#[derive(Clone)]
+pub struct CachedSearch {
+    /// The query as spelled by the request that filled the entry; other
+    /// spellings normalize to the same key.
+    pub query: String,
+    /// The serialized `SearchResponse`.
+    pub body: Bytes,
+    /// Results across all pages.
+    pub total_count: usize,
+    /// `ETag` and `Last-Modified` of the index state the response was
+    /// computed from, if it could be read.
+    pub validators: Option<Validators>,
+    /// Books the query matched before filters were applied.
+    book_ids: Arc<HashSet<u32>>,
+}
+
+/// Shared cache of search responses; clones share entries.
+#[derive(Clone)]
+pub struct ResultCache {
+    entries: Cache<String, CachedSearch>,
+}
+
+impl ResultCache {
+    pub fn new(max_entries: u64, ttl: Duration) -> Self {
+        Self {
+            entries: Cache::builder()
+                .max_capacity(max_entries)
+                .time_to_live(ttl)
+                .support_invalidation_closures()
+                .build(),
+        }
+    }
+
+    /// Reads `SEARCH_CACHE_TTL_SECS` and `SEARCH_CACHE_MAX_ENTRIES`.
+    pub fn from_env() -> Self {
+        let env_or = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self::new(
+            env_or("SEARCH_CACHE_MAX_ENTRIES", DEFAULT_MAX_ENTRIES),
+            Duration::from_secs(env_or("SEARCH_CACHE_TTL_SECS", DEFAULT_TTL_SECS)),
+        )
+    }
+
+    /// The cached response for `key`, counting the lookup as a hit or miss.
+    pub fn get(&self, key: &str) -> Option<CachedSearch> {
+        let cached = self.entries.get(key);
+        match cached {
+            Some(_) => metrics().record_cache_hit(),
+            None => metrics().record_cache_miss(),
+        }
+        cached
+    }
+
+    /// Stores the response `body` of `query`, with `total_count` results
+    /// after filters, out of the `book_ids` the query matched.
+    pub fn insert(
+        &self,
+        key: String,
+        query: String,
+        body: Bytes,
+        total_count: usize,
+        validators: Option<Validators>,
+        book_ids: HashSet<u32>,
+    ) {
+        self.entries.insert(
+            key,
+            CachedSearch {
+                query,
+                body,
+                total_count,
+                validators,
+                book_ids: Arc::new(book_ids),
+            },
+        );
+    }
+
+    pub fn invalidate_all(&self) {
+        self.entries.invalidate_all();
+    }
+
+    /// Drops the responses whose query matched `book_id`.
+    pub fn invalidate_book(&self, book_id: u32) {
+        self.entries
+            .invalidate_entries_if(move |_, cached| cached.book_ids.contains(&book_id))
+            .expect("invalidation closures are enabled");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn book_invalidation_only_drops_matching_entries() {
+        let cache = ResultCache::new(100, Duration::from_secs(60));
+        cache.insert("whale".into(), "whale".into(), Bytes::from_static(b"{}"), 1, None, HashSet::from([2701]));
+        cache.insert("love".into(), "love".into(), Bytes::from_static(b"{}"), 2, None, HashSet::from([1342, 158]));
+
+        cache.invalidate_book(1342);
+
+        assert!(cache.get("whale").is_some());
+        assert!(cache.get("love").is_none());
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let cache = ResultCache::new(100, Duration::from_millis(50));
+        cache.insert("whale".into(), "whale".into(), Bytes::from_static(b"{}"), 0, None, HashSet::new());
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert!(cache.get("whale").is_none());
+    }
+}
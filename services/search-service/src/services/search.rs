@@ -0,0 +1,247 @@
+//! Result Scoring and Ordering
+//!
+//! Scores search results for relevance and sorts them in the order requested
+//! with `?sort=`.
+//!
+//! The index only records whether a word occurs in a book, not how often, so
+//! relevance is TF-IDF with a term frequency of one: each query term adds its
+//! inverse document frequency, weighted by the field it was found in, so
+//! books matching in their title or author rank above books matching only in
+//! their body. With `mode=any`, a book only scores for the terms it contains.
+
+use crate::models::responses::BookResult;
+use crate::utils::text::fold_diacritics;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+/// Order of the results returned by `GET /search`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Highest relevance score first.
+    #[default]
+    Relevance,
+    /// Oldest first; books without a year last.
+    YearAsc,
+    /// Newest first; books without a year last.
+    YearDesc,
+    /// Alphabetical, ignoring case and diacritics. Also accepted as `title`.
+    #[serde(alias = "title")]
+    TitleAsc,
+    AuthorAsc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "relevance",
+            SortOrder::YearAsc => "year_asc",
+            SortOrder::YearDesc => "year_desc",
+            SortOrder::TitleAsc => "title_asc",
+            SortOrder::AuthorAsc => "author_asc",
+        }
+    }
+}
+
+/// How the words of a multi-word query combine, set with `?mode=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// Books containing every word.
+    #[default]
+    All,
+    /// Books containing at least one word.
+    Any,
+}
+
+impl MatchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchMode::All => "all",
+            MatchMode::Any => "any",
+        }
+    }
+}
+
+pub const TITLE_WEIGHT: f64 = 3.0;
+pub const AUTHOR_WEIGHT: f64 = 2.0;
+pub const BODY_WEIGHT: f64 = 1.0;
+
+/// Index statistics for one query term.
+#[derive(Debug, Default)]
+pub struct TermStats {
+    pub idf: f64,
+    /// Books containing the term; `None` when every result does
+    /// (`mode=all`), which saves fetching the posting list.
+    pub books: Option<HashSet<u32>>,
+    /// Books with the term in their title.
+    pub title: HashSet<u32>,
+    /// Books with the term in their author.
+    pub author: HashSet<u32>,
+}
+
+/// Smoothed inverse document frequency, `ln(1 + total / doc_freq)`.
+pub fn idf(total_books: usize, doc_freq: usize) -> f64 {
+    (1.0 + total_books as f64 / doc_freq.max(1) as f64).ln()
+}
+
+/// Sums the IDF of each term the book contains, weighted by the best field
+/// the book matched it in.
+pub fn relevance_score(book_id: u32, terms: &[TermStats]) -> f64 {
+    terms
+        .iter()
+        .filter(|term| term.books.as_ref().is_none_or(|books| books.contains(&book_id)))
+        .map(|term| {
+            let weight = if term.title.contains(&book_id) {
+                TITLE_WEIGHT
+            } else if term.author.contains(&book_id) {
+                AUTHOR_WEIGHT
+            } else {
+                BODY_WEIGHT
+            };
+            term.idf * weight
+        })
+        .sum()
+}
+
+/// A search result together with its relevance score.
+#[derive(Debug)]
+pub struct ScoredBookResult {
+    pub score: f64,
+    pub book: BookResult,
+}
+
+/// Orders known years by `cmp`, with `None` after every known year.
+fn cmp_year_none_last(a: Option<u32>, b: Option<u32>, cmp: fn(&u32, &u32) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(&a, &b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sorts `results` in place. Every order is a stable sort, so results that compare
+/// equal (e.g. two books from the same year) keep their incoming order.
+pub fn sort_results(results: &mut [ScoredBookResult], order: SortOrder) {
+    match order {
+        SortOrder::Relevance => results.sort_by(|a, b| b.score.total_cmp(&a.score)),
+        SortOrder::YearAsc => results.sort_by(|a, b| cmp_year_none_last(a.book.year, b.book.year, u32::cmp)),
+        SortOrder::YearDesc => {
+            results.sort_by(|a, b| cmp_year_none_last(a.book.year, b.book.year, |a, b| b.cmp(a)))
+        }
+        // Fold each name once rather than on every comparison
+        SortOrder::TitleAsc => results.sort_by_cached_key(|r| fold_diacritics(&r.book.title)),
+        SortOrder::AuthorAsc => results.sort_by_cached_key(|r| r.book.author.to_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn result(book_id: u32, title: &str, year: Option<u32>, score: f64) -> ScoredBookResult {
+        ScoredBookResult {
+            score,
+            book: BookResult {
+                book_id,
+                title: title.to_string(),
+                author: format!("Author {}", book_id),
+                language: "en".to_string(),
+                year,
+                subjects: Vec::new(),
+                reading_level: None,
+                score,
+                highlights: HashMap::new(),
+                chapters: None,
+                snippet: None,
+                also_available_as: Vec::new(),
+            },
+        }
+    }
+
+    fn ids(results: &[ScoredBookResult]) -> Vec<u32> {
+        results.iter().map(|r| r.book.book_id).collect()
+    }
+
+    fn sample() -> Vec<ScoredBookResult> {
+        vec![
+            result(1, "Zadig", Some(1813), 1.0),
+            result(2, "émile", None, 3.0),
+            result(3, "Candide", Some(1759), 2.0),
+            result(4, "Emma", Some(1813), 0.5),
+        ]
+    }
+
+    #[test]
+    fn relevance_puts_highest_score_first() {
+        let mut results = sample();
+        sort_results(&mut results, SortOrder::Relevance);
+        assert_eq!(ids(&results), [2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn unknown_years_sort_last_both_ways() {
+        let mut results = sample();
+        sort_results(&mut results, SortOrder::YearAsc);
+        assert_eq!(ids(&results), [3, 1, 4, 2]);
+
+        sort_results(&mut results, SortOrder::YearDesc);
+        assert_eq!(ids(&results), [1, 4, 3, 2]);
+    }
+
+    #[test]
+    fn books_from_the_same_year_keep_their_order() {
+        let mut results = sample();
+        results.swap(0, 3);
+        sort_results(&mut results, SortOrder::YearAsc);
+        assert_eq!(ids(&results), [3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn titles_compare_ignoring_case_and_diacritics() {
+        let mut results = sample();
+        sort_results(&mut results, SortOrder::TitleAsc);
+        assert_eq!(ids(&results), [3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn title_and_author_matches_outscore_body_matches() {
+        let terms = [TermStats {
+            idf: idf(100, 10),
+            books: None,
+            title: HashSet::from([1]),
+            author: HashSet::from([2]),
+        }];
+        let title = relevance_score(1, &terms);
+        let author = relevance_score(2, &terms);
+        let body = relevance_score(3, &terms);
+        assert!(title > author && author > body);
+        assert!(idf(100, 1) > idf(100, 50));
+    }
+
+    #[test]
+    fn any_mode_only_scores_contained_terms() {
+        let term = |books: [u32; 2]| TermStats {
+            idf: idf(100, 10),
+            books: Some(HashSet::from(books)),
+            ..TermStats::default()
+        };
+        let terms = [term([1, 2]), term([1, 3])];
+
+        assert_eq!(relevance_score(1, &terms), 2.0 * relevance_score(2, &terms));
+        assert_eq!(relevance_score(4, &terms), 0.0);
+    }
+
+    #[test]
+    fn parses_sort_parameter() {
+        let order: SortOrder = serde_json::from_str("\"year_desc\"").unwrap();
+        assert_eq!(order, SortOrder::YearDesc);
+        let order: SortOrder = serde_json::from_str("\"title\"").unwrap();
+        assert_eq!(order, SortOrder::TitleAsc);
+        assert!(serde_json::from_str::<SortOrder>("\"newest\"").is_err());
+    }
+}
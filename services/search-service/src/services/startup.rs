@@ -0,0 +1,71 @@
+//! Startup Probe
+//!
+//! A cloneable flag raised the first time the storage backend answers. The
+//! service starts serving before the backend is reachable, so `/startup`
+//! keeps failing until this is set, and never fails again afterwards.
+//!
+//! Once the backend answers, [`check_diacritic_folding`] compares the
+//! service's `FOLD_DIACRITICS` with the setting the index was built with.
+
+use crate::Backend;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{error, warn};
+
+#[derive(Clone, Default)]
+pub struct StartupProbe {
+    completed: Arc<AtomicBool>,
+}
+
+impl StartupProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn complete(&self) {
+        self.completed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed.load(Ordering::SeqCst)
+    }
+}
+
+/// Warns when `fold_diacritics`, this service's setting, differs from the
+/// one the index was built with: queries would then be spelled differently
+/// from the stored words, so accented words would stop matching. Returns
+/// whether they agree; an index that never recorded the setting agrees with
+/// either.
+pub async fn check_diacritic_folding(backend: &Backend, fold_diacritics: bool) -> bool {
+    match backend.get_diacritic_folding().await {
+        Ok(Some(indexed)) if indexed != fold_diacritics => {
+            warn!(
+                "FOLD_DIACRITICS is {} but the index was built with it {}; set it to match, or rebuild the index (POST /index/rebuild) with the new setting",
+                fold_diacritics, indexed
+            );
+            false
+        }
+        Ok(_) => true,
+        Err(e) => {
+            error!("Failed to read the index's diacritic folding setting: {}", e);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::MemoryBackend;
+
+    #[tokio::test]
+    async fn folding_must_match_the_index() {
+        let memory = MemoryBackend::new();
+        let backend: Backend = Arc::new(memory.clone());
+        assert!(check_diacritic_folding(&backend, true).await);
+
+        memory.set_diacritic_folding(true);
+        assert!(check_diacritic_folding(&backend, true).await);
+        assert!(!check_diacritic_folding(&backend, false).await);
+    }
+}
@@ -0,0 +1,162 @@
+//! Query Suggestions
+//!
+//! "Did you mean" for searches that find nothing: each query term missing
+//! from the vocabulary is swapped for the most common indexed word one edit
+//! away (a letter deleted, inserted or replaced, or two neighbours swapped),
+//! and the alternative queries that would find books are suggested.
+//!
+//! Candidates are generated from the term instead of scanning the
+//! vocabulary, so a term costs one [`filter_known_words`] lookup of at most
+//! `54n + 25` words plus a document frequency per known candidate. All of it
+//! runs within [`SUGGESTION_BUDGET`]; a search that runs out of time gets no
+//! suggestions.
+//!
+//! [`filter_known_words`]: crate::models::storage::StorageBackend::filter_known_words
+
+use crate::models::storage::StorageError;
+use crate::services::query::{evaluate, Query};
+use crate::utils::text::TokenizerConfig;
+use crate::Backend;
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::warn;
+
+/// Most alternative queries suggested for one search.
+pub const MAX_SUGGESTIONS: usize = 3;
+/// Time a search may spend working out suggestions.
+pub const SUGGESTION_BUDGET: Duration = Duration::from_millis(50);
+/// Misspelled terms corrected per query.
+const MAX_CORRECTED_TERMS: usize = 4;
+/// Longer terms are left alone rather than generate hundreds of edits.
+const MAX_TERM_LEN: usize = 20;
+/// Known words per term whose document frequency is looked up.
+const MAX_CANDIDATES: usize = 10;
+/// Alternative queries evaluated before giving up.
+const MAX_ALTERNATIVES: usize = 3 * MAX_SUGGESTIONS;
+
+/// Indexable words one edit away from `word`, without `word` itself.
+pub fn edits1(word: &str) -> Vec<String> {
+    const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+    let bytes = word.as_bytes();
+    let mut edits = HashSet::new();
+
+    for i in 0..=bytes.len() {
+        let (head, tail) = bytes.split_at(i);
+        if !tail.is_empty() {
+            edits.insert([head, &tail[1..]].concat());
+            for &letter in LETTERS {
+                edits.insert([head, &[letter], &tail[1..]].concat());
+            }
+        }
+        if tail.len() > 1 {
+            edits.insert([head, &[tail[1], tail[0]], &tail[2..]].concat());
+        }
+        for &letter in LETTERS {
+            edits.insert([head, &[letter], tail].concat());
+        }
+    }
+
+    let mut edits: Vec<String> = edits
+        .into_iter()
+        .filter_map(|edit| String::from_utf8(edit).ok())
+        .filter(|edit| edit != word && TokenizerConfig::configured().check_indexable(edit).is_none())
+        .collect();
+    edits.sort_unstable();
+    edits
+}
+
+/// Up to [`MAX_SUGGESTIONS`] normalized queries like `query` with misspelled
+/// terms corrected, each matching at least one book before filters. Empty
+/// when nothing close is indexed, on a backend error or once
+/// [`SUGGESTION_BUDGET`] runs out.
+pub async fn suggest(query: &Query, backend: &Backend) -> Vec<String> {
+    match tokio::time::timeout(SUGGESTION_BUDGET, find_suggestions(query, backend)).await {
+        Ok(Ok(suggestions)) => suggestions,
+        Ok(Err(e)) => {
+            warn!("Failed to find suggestions for '{}': {}", query, e);
+            Vec::new()
+        }
+        Err(_) => {
+            warn!("Gave up on suggestions for '{}' after {:?}", query, SUGGESTION_BUDGET);
+            Vec::new()
+        }
+    }
+}
+
+async fn find_suggestions(query: &Query, backend: &Backend) -> Result<Vec<String>, StorageError> {
+    let terms = query.positive_terms();
+    let known: HashSet<String> = backend.filter_known_words(&terms).await?.into_iter().collect();
+
+    let mut corrections: Vec<(String, Vec<String>)> = Vec::new();
+    for term in terms.iter().filter(|term| !known.contains(*term)).take(MAX_CORRECTED_TERMS) {
+        let words = closest_words(term, backend).await?;
+        if !words.is_empty() {
+            corrections.push((term.clone(), words));
+        }
+    }
+    if corrections.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Every term at its best correction first, then one term at a time,
+    // best corrections before the rest
+    let mut alternatives = vec![corrections
+        .iter()
+        .fold(query.clone(), |alternative, (term, words)| alternative.replace_term(term, &words[0]))];
+    for rank in 0..MAX_CANDIDATES {
+        for (term, words) in &corrections {
+            if let Some(word) = words.get(rank) {
+                alternatives.push(query.replace_term(term, word));
+            }
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    let mut tried = HashSet::new();
+    for alternative in alternatives {
+        if suggestions.len() == MAX_SUGGESTIONS || tried.len() == MAX_ALTERNATIVES {
+            break;
+        }
+        let suggestion = alternative.to_string();
+        if !tried.insert(suggestion.clone()) {
+            continue;
+        }
+        if !evaluate(&alternative, backend).await?.is_empty() {
+            suggestions.push(suggestion);
+        }
+    }
+    Ok(suggestions)
+}
+
+/// Indexed words one edit away from `term`, in the most books first, then
+/// alphabetically.
+async fn closest_words(term: &str, backend: &Backend) -> Result<Vec<String>, StorageError> {
+    if term.len() > MAX_TERM_LEN {
+        return Ok(Vec::new());
+    }
+
+    let mut ranked = Vec::new();
+    for word in backend.filter_known_words(&edits1(term)).await?.into_iter().take(MAX_CANDIDATES) {
+        ranked.push((backend.get_word_doc_freq(&word).await?, word));
+    }
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(ranked.into_iter().map(|(_, word)| word).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_cover_every_single_letter_change() {
+        let edits = edits1("whael");
+
+        assert!(edits.contains(&"whale".to_string()), "swap");
+        assert!(edits.contains(&"whal".to_string()), "delete");
+        assert!(edits.contains(&"wheel".to_string()), "replace");
+        assert!(edits.contains(&"whaeel".to_string()), "insert");
+        assert!(!edits.contains(&"whael".to_string()));
+        // Too short: never indexed, never suggested
+        assert!(!edits1("thee").iter().any(|edit| edit.len() < 3));
+    }
+}
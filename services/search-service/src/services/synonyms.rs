@@ -0,0 +1,344 @@
+//! Query-Time SynonymExpander
+//!
+//! `SYNONYMS_FILE` names the synonyms file, loaded at startup; without it a
+//! `synonyms.json` in the working directory is used if there is one. A
+//! `.json` file is an object of each word's synonym list
+//! (`{"automobile": ["car", "motorcar"]}`), anything else has a
+//! `word: synonym, synonym` line per word, with blank lines and lines
+//! starting with `#` ignored. Searches expand each query term to itself or
+//! its synonyms (see [`Query::expand_terms`]) unless `expand_synonyms=false`,
+//! so under `mode=all` a term and its synonyms count as one required term.
+//!
+//! Entries work one way: `car: automobile` lets `car` find "automobile" but
+//! not the reverse. Words and synonyms are tokenized like the index's words,
+//! so case and, with `FOLD_DIACRITICS`, accents don't matter; synonyms that
+//! aren't a single searchable word are skipped. Terms are looked up by their
+//! English stem, so `automobiles` finds the entry for `automobile` and also
+//! matches `automobile` itself; entries whose words share a stem are merged.
+//!
+//! `POST /synonyms/reload` reads the file again. A file that can't be read
+//! or parsed leaves the synonyms in use unchanged.
+
+use crate::services::query::Query;
+use crate::utils::text::index_terms;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Used when neither `SYNONYMS_FILE` nor `SYNONYMS_PATH` is set, if it
+/// exists.
+pub const DEFAULT_SYNONYMS_FILE: &str = "synonyms.json";
+
+#[derive(Debug, Error)]
+pub enum SynonymsError {
+    #[error("SYNONYMS_FILE is not set")]
+    NotConfigured,
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Line {line} of {path} is not `word: synonym, ...`")]
+    Malformed { path: String, line: usize },
+    #[error("{path} is not a JSON object of synonym lists: {reason}")]
+    Json { path: String, reason: String },
+    #[error("{word:?} in {path} is not one searchable word")]
+    NotAWord { path: String, word: String },
+}
+
+/// Parses `word: synonym, synonym` lines into each word's synonyms. `path`
+/// only names the file in errors.
+pub fn parse_synonyms(text: &str, path: &str) -> Result<HashMap<String, Vec<String>>, SynonymsError> {
+    let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let malformed = || SynonymsError::Malformed {
+            path: path.to_string(),
+            line: number + 1,
+        };
+        let (word, list) = line.split_once(':').ok_or_else(malformed)?;
+        let word = single_term(word).ok_or_else(malformed)?;
+        add_synonyms(&mut synonyms, word, list.split(','), path);
+    }
+    synonyms.retain(|_, list| !list.is_empty());
+    Ok(synonyms)
+}
+
+/// Parses a JSON object of each word's synonym list. `path` only names the
+/// file in errors.
+pub fn parse_synonyms_json(text: &str, path: &str) -> Result<HashMap<String, Vec<String>>, SynonymsError> {
+    let entries: BTreeMap<String, Vec<String>> = serde_json::from_str(text).map_err(|e| SynonymsError::Json {
+        path: path.to_string(),
+        reason: e.to_string(),
+    })?;
+    let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+    for (word, list) in &entries {
+        let term = single_term(word).ok_or_else(|| SynonymsError::NotAWord {
+            path: path.to_string(),
+            word: word.clone(),
+        })?;
+        add_synonyms(&mut synonyms, term, list.iter().map(String::as_str), path);
+    }
+    synonyms.retain(|_, list| !list.is_empty());
+    Ok(synonyms)
+}
+
+/// `text` as an index term, if it is exactly one.
+fn single_term(text: &str) -> Option<String> {
+    <[String; 1]>::try_from(index_terms(text)).ok().map(|[term]| term)
+}
+
+fn add_synonyms<'a>(synonyms: &mut HashMap<String, Vec<String>>, word: String, list: impl Iterator<Item = &'a str>, path: &str) {
+    let entry = synonyms.entry(word.clone()).or_default();
+    for synonym in list {
+        match single_term(synonym) {
+            Some(synonym) if synonym != word && !entry.contains(&synonym) => entry.push(synonym),
+            Some(_) => {}
+            None => warn!("Skipping synonym {:?} of {:?} in {}: not one searchable word", synonym.trim(), word, path),
+        }
+    }
+}
+
+/// The English stem synonyms are looked up by.
+fn stem(word: &str) -> String {
+    static STEMMER: OnceLock<Stemmer> = OnceLock::new();
+    STEMMER.get_or_init(|| Stemmer::create(Algorithm::English)).stem(word).into_owned()
+}
+
+/// Each stem's words: the words with that stem, then their synonyms.
+fn by_stem(words: HashMap<String, Vec<String>>) -> HashMap<String, Vec<String>> {
+    let mut words: Vec<_> = words.into_iter().collect();
+    words.sort();
+    let mut stems: HashMap<String, Vec<String>> = HashMap::new();
+    for (word, _) in &words {
+        stems.entry(stem(word)).or_default().push(word.clone());
+    }
+    for (word, synonyms) in words {
+        let entry = stems.get_mut(&stem(&word)).expect("every word's stem was added");
+        for synonym in synonyms {
+            if !entry.contains(&synonym) {
+                entry.push(synonym);
+            }
+        }
+    }
+    stems
+}
+
+/// Reads the file at `path`, as JSON if it ends in `.json`. Returns each
+/// word's synonyms and how many words have some.
+fn read_synonyms(path: &Path) -> Result<(HashMap<String, Vec<String>>, usize), SynonymsError> {
+    let name = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|source| SynonymsError::Read {
+        path: name.clone(),
+        source,
+    })?;
+    let words = if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+        parse_synonyms_json(&text, &name)?
+    } else {
+        parse_synonyms(&text, &name)?
+    };
+    let count = words.len();
+    Ok((by_stem(words), count))
+}
+
+/// The synonyms searches expand terms with, keyed by stem; clones share
+/// them, so a reload is seen by every handler.
+#[derive(Clone, Default)]
+pub struct SynonymExpander {
+    path: Option<PathBuf>,
+    words: Arc<RwLock<HashMap<String, Vec<String>>>>,
+}
+
+impl SynonymExpander {
+    /// Fixed synonyms, without a file to reload.
+    pub fn new(words: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            path: None,
+            words: Arc::new(RwLock::new(by_stem(words))),
+        }
+    }
+
+    /// SynonymExpander from the file at `path`, which later reloads read again.
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, SynonymsError> {
+        let path = path.into();
+        let (words, _) = read_synonyms(&path)?;
+        Ok(Self {
+            path: Some(path),
+            words: Arc::new(RwLock::new(words)),
+        })
+    }
+
+    /// Reads `SYNONYMS_FILE` (or its older name `SYNONYMS_PATH`), else
+    /// [`DEFAULT_SYNONYMS_FILE`] if it exists. A file that can't be loaded
+    /// is logged and leaves no synonyms until a reload succeeds.
+    pub fn from_env() -> Self {
+        let configured = ["SYNONYMS_FILE", "SYNONYMS_PATH"]
+            .into_iter()
+            .find_map(|name| std::env::var_os(name).filter(|path| !path.is_empty()))
+            .map(PathBuf::from);
+        let Some(path) = configured.or_else(|| {
+            let default = PathBuf::from(DEFAULT_SYNONYMS_FILE);
+            default.is_file().then_some(default)
+        }) else {
+            return Self::default();
+        };
+        let synonyms = Self {
+            path: Some(path),
+            ..Self::default()
+        };
+        match synonyms.reload() {
+            Ok(words) => info!("Loaded synonyms for {} words", words),
+            Err(e) => warn!("No synonyms loaded: {}", e),
+        }
+        synonyms
+    }
+
+    /// Reads the file again, replacing the synonyms only if it loads.
+    /// Returns how many words now have synonyms.
+    pub fn reload(&self) -> Result<usize, SynonymsError> {
+        let path = self.path.as_ref().ok_or(SynonymsError::NotConfigured)?;
+        let (words, count) = read_synonyms(path)?;
+        *self.words.write().unwrap_or_else(|e| e.into_inner()) = words;
+        Ok(count)
+    }
+
+    /// The synonyms of `word`, an index term; empty if it has none.
+    pub fn lookup(&self, word: &str) -> Vec<String> {
+        let words = self.words.read().unwrap_or_else(|e| e.into_inner());
+        lookup(&words, word)
+    }
+
+    /// `query` with every term ORed with its synonyms.
+    pub fn expand(&self, query: &Query) -> Query {
+        let words = self.words.read().unwrap_or_else(|e| e.into_inner());
+        if words.is_empty() {
+            return query.clone();
+        }
+        query.expand_terms(&|term| lookup(&words, term))
+    }
+}
+
+fn lookup(words: &HashMap<String, Vec<String>>, word: &str) -> Vec<String> {
+    let Some(synonyms) = words.get(&stem(word)) else {
+        return Vec::new();
+    };
+    synonyms.iter().filter(|synonym| *synonym != word).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::query::parse_query;
+    use crate::services::search::MatchMode;
+
+    #[test]
+    fn parses_word_lines_like_index_terms() {
+        let text = "# vehicles\n\nCar: automobile, Motorcar,  automobile\nship: vessel\nship: boat, ship\n";
+        let synonyms = parse_synonyms(text, "synonyms.txt").unwrap();
+
+        assert_eq!(synonyms["car"], ["automobile", "motorcar"]);
+        assert_eq!(synonyms["ship"], ["vessel", "boat"]);
+        assert_eq!(synonyms.len(), 2);
+    }
+
+    #[test]
+    fn skips_synonyms_that_are_not_one_word() {
+        let synonyms = parse_synonyms("car: of, horseless carriage, auto\nboat: of", "synonyms.txt").unwrap();
+
+        assert_eq!(synonyms["car"], ["auto"]);
+        assert!(!synonyms.contains_key("boat"));
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_with_their_number() {
+        for text in ["car: automobile\nvessel", "of: a, an", "red car: automobile"] {
+            let error = parse_synonyms(text, "synonyms.txt").unwrap_err().to_string();
+            assert!(error.starts_with("Line "), "{}", error);
+        }
+        assert_eq!(
+            parse_synonyms("car: automobile\n\nvessel", "synonyms.txt").unwrap_err().to_string(),
+            "Line 3 of synonyms.txt is not `word: synonym, ...`"
+        );
+    }
+
+    #[test]
+    fn parses_json_objects_like_word_lines() {
+        let json = r#"{"Automobile": ["car", "Motorcar", "horseless carriage", "car"], "boat": []}"#;
+        let synonyms = parse_synonyms_json(json, "synonyms.json").unwrap();
+
+        assert_eq!(synonyms["automobile"], ["car", "motorcar"]);
+        assert_eq!(synonyms.len(), 1);
+        assert!(matches!(parse_synonyms_json("[]", "synonyms.json"), Err(SynonymsError::Json { .. })));
+        assert_eq!(
+            parse_synonyms_json(r#"{"red car": ["automobile"]}"#, "synonyms.json").unwrap_err().to_string(),
+            "\"red car\" in synonyms.json is not one searchable word"
+        );
+    }
+
+    #[test]
+    fn the_sample_synonyms_file_parses() {
+        let synonyms = parse_synonyms_json(include_str!("../../config/synonyms.json"), "synonyms.json").unwrap();
+
+        assert_eq!(synonyms["automobile"], ["car", "motorcar", "vehicle"]);
+    }
+
+    #[test]
+    fn looks_words_up_by_their_stem() {
+        let synonyms = SynonymExpander::new(HashMap::from([(
+            "automobile".to_string(),
+            vec!["car".to_string(), "motorcar".to_string()],
+        )]));
+        let query = parse_query("automobiles", MatchMode::All).unwrap().unwrap();
+
+        assert_eq!(synonyms.lookup("automobiles"), ["automobile", "car", "motorcar"]);
+        assert_eq!(synonyms.lookup("automobile"), ["car", "motorcar"]);
+        assert_eq!(synonyms.lookup("car"), Vec::<String>::new());
+        assert_eq!(synonyms.expand(&query).to_string(), "automobiles OR automobile OR car OR motorcar");
+    }
+
+    #[test]
+    fn expands_query_terms_with_their_synonyms() {
+        let synonyms = SynonymExpander::new(HashMap::from([("car".to_string(), vec!["automobile".to_string()])]));
+        let query = parse_query("red car", MatchMode::All).unwrap().unwrap();
+
+        assert_eq!(synonyms.expand(&query).to_string(), "red AND (car OR automobile)");
+        assert_eq!(SynonymExpander::default().expand(&query), query);
+        assert_eq!(synonyms.lookup("red"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reload_keeps_the_old_synonyms_when_the_file_is_bad() {
+        let path = std::env::temp_dir().join(format!("synonyms-{}.txt", std::process::id()));
+        std::fs::write(&path, "car: automobile\n").unwrap();
+        let synonyms = SynonymExpander::from_file(&path).unwrap();
+
+        std::fs::write(&path, "car: automobile, motorcar\nship: vessel\n").unwrap();
+        assert_eq!(synonyms.clone().reload().unwrap(), 2);
+        assert_eq!(synonyms.lookup("car"), ["automobile", "motorcar"]);
+
+        std::fs::write(&path, "not a synonym line\n").unwrap();
+        assert!(synonyms.reload().is_err());
+        assert_eq!(synonyms.lookup("ship"), ["vessel"]);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(synonyms.reload(), Err(SynonymsError::Read { .. })));
+        assert!(matches!(SynonymExpander::default().reload(), Err(SynonymsError::NotConfigured)));
+    }
+
+    #[test]
+    fn json_files_are_read_as_json() {
+        let path = std::env::temp_dir().join(format!("synonyms-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"ship": ["vessel"], "boat": ["dinghy"]}"#).unwrap();
+        let synonyms = SynonymExpander::from_file(&path).unwrap();
+
+        assert_eq!(synonyms.reload().unwrap(), 2);
+        assert_eq!(synonyms.lookup("ships"), ["ship", "vessel"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Request Timeouts
+//!
+//! Bounds how long a search may run: a search still working when its timeout
+//! expires is dropped and answered with `504 Gateway Timeout` and a
+//! `GATEWAY_TIMEOUT` error, so a pathological query can't hold a worker.
+//!
+//! Well before that, a search runs out of its time budget for fetching
+//! postings and scoring, and answers with the books found so far, marked
+//! `partial` (see [`crate::routes::search`]). A request can ask for another
+//! budget with `timeout_ms`, up to the timeout.
+//!
+//! ## Environment Variables
+//! - `SEARCH_TIMEOUT_SECS`: `GET /search` and the gRPC `Search` call (default: `5`)
+//! - `SEARCH_TIMEOUT_MS`: their time budget before answering with partial
+//!   results (default: `2000`)
+
+use crate::error::AppError;
+use axum::{error_handling::HandleErrorLayer, routing::MethodRouter, BoxError};
+use std::time::Duration;
+use tower::{timeout::error::Elapsed, timeout::TimeoutLayer, ServiceBuilder};
+
+const DEFAULT_SEARCH_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_SEARCH_BUDGET_MS: u64 = 2000;
+
+/// Timeouts of the routes that have one.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeouts {
+    pub search: Duration,
+    /// Default time budget of a search, never above `search`.
+    pub search_budget: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            search: Duration::from_secs(DEFAULT_SEARCH_TIMEOUT_SECS),
+            search_budget: Duration::from_millis(DEFAULT_SEARCH_BUDGET_MS),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Reads `SEARCH_TIMEOUT_SECS` and `SEARCH_TIMEOUT_MS`; zero or
+    /// unparsable values keep the defaults.
+    pub fn from_env() -> Self {
+        let positive = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&value| value > 0)
+                .unwrap_or(default)
+        };
+        let search = Duration::from_secs(positive("SEARCH_TIMEOUT_SECS", DEFAULT_SEARCH_TIMEOUT_SECS));
+        let search_budget = Duration::from_millis(positive("SEARCH_TIMEOUT_MS", DEFAULT_SEARCH_BUDGET_MS));
+
+        Self {
+            search,
+            search_budget: search_budget.min(search),
+        }
+    }
+
+    /// The budget of a search asking for `timeout_ms`, if anything, capped
+    /// at the search timeout.
+    pub fn search_budget(&self, timeout_ms: Option<u64>) -> Duration {
+        timeout_ms
+            .map_or(self.search_budget, Duration::from_millis)
+            .min(self.search)
+    }
+}
+
+/// `route`, answering with a `504` once it has run for `timeout`.
+pub fn with_timeout<S>(route: MethodRouter<S>, timeout: Duration) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    route.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(timeout_error))
+            .layer(TimeoutLayer::new(timeout)),
+    )
+}
+
+async fn timeout_error(error: BoxError) -> AppError {
+    if error.is::<Elapsed>() {
+        AppError::Timeout
+    } else {
+        AppError::Internal(error.to_string())
+    }
+}
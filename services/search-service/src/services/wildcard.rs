@@ -0,0 +1,290 @@
+//! Wildcard and Regex Terms
+//!
+//! `whal*` in a query stands for the indexed words starting with `whal`, and
+//! with `regex=true` every term is a regular expression matched against whole
+//! words, ignoring case (`colou?r`). Before the query is evaluated, each
+//! pattern is replaced by an `OR` of up to [`MAX_EXPANSIONS`] of the words it
+//! matches, those in the most books, so `whal* ship` with `mode=all` needs
+//! one of the `whal` words and `ship`. A pattern matching no word matches no
+//! book.
+//!
+//! Prefixes are read in order from the backend's vocabulary index, so they
+//! stay cheap however large the vocabulary grows; regexes walk the whole
+//! vocabulary. `author:` patterns walk the author vocabulary instead, since
+//! author words are only indexed in their field. Every word a pattern matches
+//! is ranked, so a pattern matching more than [`MAX_CANDIDATES`] words is
+//! rejected with `400` instead of being ranked on part of its words. Every
+//! pattern of a query must be expanded within [`EXPANSION_BUDGET`]. The
+//! `regex` crate never backtracks, so no pattern takes exponential time, and
+//! patterns that compile too large are rejected with `400` like malformed
+//! ones.
+
+use crate::error::AppError;
+use crate::models::storage::IndexField;
+use crate::services::query::{Query, TermPattern};
+use crate::Backend;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Most words a pattern is replaced by.
+pub const MAX_EXPANSIONS: usize = 50;
+/// Most words a pattern may match, all ranked by document frequency.
+pub const MAX_CANDIDATES: usize = 200;
+/// Time a query may spend expanding its patterns.
+pub const EXPANSION_BUDGET: Duration = Duration::from_secs(1);
+/// Letters a wildcard needs before its `*`.
+pub const MIN_PREFIX_LEN: usize = 2;
+/// Longest regular expression accepted, in characters.
+pub const MAX_PATTERN_LEN: usize = 100;
+/// Compiled size a regular expression may reach, in bytes.
+const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// The words a pattern was replaced by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    /// The field the pattern is matched in.
+    pub field: IndexField,
+    pub pattern: TermPattern,
+    /// Each word with its number of books, in the most books first.
+    pub words: Vec<(String, usize)>,
+    /// Whether the pattern matched more words than it was replaced by.
+    pub truncated: bool,
+}
+
+/// Compiles `pattern` to match whole words ignoring case, or says why it
+/// can't be used.
+pub fn build_regex(pattern: &str) -> Result<Regex, String> {
+    if pattern.chars().count() > MAX_PATTERN_LEN {
+        return Err(format!("longer than {} characters", MAX_PATTERN_LEN));
+    }
+    let build = |pattern: &str| {
+        RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| match e {
+                regex::Error::CompiledTooBig(_) => "too complex to match against the vocabulary".to_string(),
+                regex::Error::Syntax(message) => {
+                    let reason = message.lines().last().unwrap_or_default();
+                    reason.trim().trim_start_matches("error: ").to_string()
+                }
+                e => e.to_string(),
+            })
+    };
+    // Compiled alone first, so `a)|(b` can't escape the anchors
+    build(pattern)?;
+    build(&format!("^(?:{})$", pattern))
+}
+
+/// `query` with its patterns replaced by the words they match, and what each
+/// was replaced by. Fails with `INVALID_QUERY` once [`EXPANSION_BUDGET`]
+/// runs out.
+pub async fn expand_patterns(query: Query, backend: &Backend) -> Result<(Query, Vec<Expansion>), AppError> {
+    let patterns = query.patterns();
+    if patterns.is_empty() {
+        return Ok((query, Vec::new()));
+    }
+
+    let expansions = tokio::time::timeout(EXPANSION_BUDGET, async {
+        let mut expansions = Vec::with_capacity(patterns.len());
+        for (field, pattern) in &patterns {
+            expansions.push(expand(*field, pattern, backend).await?);
+        }
+        Ok::<_, AppError>(expansions)
+    })
+    .await
+    .map_err(|_| {
+        let patterns: Vec<String> = patterns.iter().map(|(_, pattern)| pattern.to_string()).collect();
+        AppError::InvalidQuery(format!(
+            "matching {} against the vocabulary took longer than {:?}; make the pattern more specific",
+            patterns.join(", "),
+            EXPANSION_BUDGET
+        ))
+    })??;
+
+    let words: HashMap<(IndexField, TermPattern), Vec<String>> = expansions
+        .iter()
+        .map(|expansion| {
+            let words = expansion.words.iter().map(|(word, _)| word.clone()).collect();
+            ((expansion.field, expansion.pattern.clone()), words)
+        })
+        .collect();
+    let expanded = query.expand_patterns(&|field, pattern| words.get(&(field, pattern.clone())).map(Vec::as_slice));
+    Ok((expanded, expansions))
+}
+
+/// The words in the most books that `pattern` matches in `field`; ties go
+/// alphabetically. Fails with `INVALID_QUERY` when it matches more than
+/// [`MAX_CANDIDATES`] words.
+async fn expand(field: IndexField, pattern: &TermPattern, backend: &Backend) -> Result<Expansion, AppError> {
+    // One more than allowed, to tell a full window from an overflowing one
+    let limit = MAX_CANDIDATES + 1;
+    let candidates = match pattern {
+        TermPattern::Prefix(prefix) if field.is_unscoped() => backend.words_with_prefix(prefix, limit).await?,
+        TermPattern::Prefix(prefix) => {
+            let regex = Regex::new(&format!("^{}", regex::escape(prefix))).expect("an escaped prefix is valid");
+            backend.words_matching(field, &regex, limit).await?
+        }
+        TermPattern::Regex(source) => {
+            let regex = build_regex(source)
+                .map_err(|reason| AppError::InvalidQuery(format!("invalid regex '{}': {}", source, reason)))?;
+            let vocabulary = if field.is_unscoped() { IndexField::Body } else { field };
+            backend.words_matching(vocabulary, &regex, limit).await?
+        }
+    };
+    if candidates.len() > MAX_CANDIDATES {
+        return Err(AppError::InvalidQuery(format!(
+            "'{}' matches more than {} words; make the pattern more specific",
+            pattern, MAX_CANDIDATES
+        )));
+    }
+
+    let mut words = Vec::with_capacity(candidates.len());
+    for word in candidates {
+        let doc_freq = match field.is_unscoped() {
+            true => backend.get_word_doc_freq(&word).await?,
+            false => backend.get_books_for_word_in_field(&word, field).await?.len(),
+        };
+        words.push((doc_freq, word));
+    }
+    words.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    let truncated = words.len() > MAX_EXPANSIONS;
+    words.truncate(MAX_EXPANSIONS);
+
+    Ok(Expansion {
+        field,
+        pattern: pattern.clone(),
+        words: words.into_iter().map(|(doc_freq, word)| (word, doc_freq)).collect(),
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{IndexField, MemoryBackend, StorageBackend};
+    use crate::services::query::{parse_query, parse_regex_query};
+    use crate::services::search::MatchMode;
+    use std::sync::Arc;
+
+    /// `whale` in books 1–3, `whaling` in 1–2, `whaler` in 1, `ship` in 1
+    /// and `color`/`colour` in 4 and 5.
+    async fn backend() -> Backend {
+        let memory = MemoryBackend::new();
+        for (word, books) in [
+            ("whale", &[1, 2, 3][..]),
+            ("whaling", &[1, 2]),
+            ("whaler", &[1]),
+            ("ship", &[1]),
+            ("color", &[4]),
+            ("colour", &[5]),
+        ] {
+            for &book_id in books {
+                memory.add_word_to_index(word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        Arc::new(memory)
+    }
+
+    async fn expand_query(q: &str, regex: bool, backend: &Backend) -> (String, Vec<Expansion>) {
+        let parse = if regex { parse_regex_query } else { parse_query };
+        let query = parse(q, MatchMode::All).unwrap().unwrap();
+        let (query, expansions) = expand_patterns(query, backend).await.unwrap();
+        (query.to_string(), expansions)
+    }
+
+    #[tokio::test]
+    async fn prefixes_expand_to_the_most_common_words_within_their_slot() {
+        let backend = backend().await;
+
+        let (query, expansions) = expand_query("whal* ship", false, &backend).await;
+
+        assert_eq!(query, "(whale OR whaling OR whaler) AND ship");
+        assert_eq!(
+            expansions[0].words,
+            [("whale".to_string(), 3), ("whaling".to_string(), 2), ("whaler".to_string(), 1)]
+        );
+        assert!(!expansions[0].truncated);
+        assert_eq!(expand_query("title:whal*", false, &backend).await.0, "title:whale OR title:whaling OR title:whaler");
+        // Nothing matches: the pattern stays and finds no book
+        assert_eq!(expand_query("zebr*", false, &backend).await.0, "zebr*");
+    }
+
+    #[tokio::test]
+    async fn expansions_are_capped() {
+        let memory = MemoryBackend::new();
+        for i in 0..MAX_EXPANSIONS + 10 {
+            let word = format!("whale{:03}", i);
+            // Later words are in more books, so they are the ones kept
+            for book_id in 0..=i as u32 {
+                memory.add_word_to_index(&word, book_id, IndexField::Body).await.unwrap();
+            }
+        }
+        let backend: Backend = Arc::new(memory);
+
+        let (_, expansions) = expand_query("whale*", false, &backend).await;
+
+        assert!(expansions[0].truncated);
+        assert_eq!(expansions[0].words.len(), MAX_EXPANSIONS);
+        assert_eq!(expansions[0].words[0], ("whale059".to_string(), 60));
+        assert!(expansions[0].words.iter().all(|(word, _)| word.as_str() >= "whale010"));
+    }
+
+    #[tokio::test]
+    async fn patterns_matching_too_many_words_are_rejected() {
+        let memory = MemoryBackend::new();
+        for i in 0..=MAX_CANDIDATES {
+            memory.add_word_to_index(&format!("whale{:03}", i), 1, IndexField::Body).await.unwrap();
+        }
+        // The most common word sorts after every other candidate
+        for book_id in 2..10 {
+            memory.add_word_to_index(&format!("whale{:03}", MAX_CANDIDATES), book_id, IndexField::Body).await.unwrap();
+        }
+        let backend: Backend = Arc::new(memory);
+
+        for (q, regex) in [("whale*", false), ("whale.*", true)] {
+            let parse = if regex { parse_regex_query } else { parse_query };
+            let query = parse(q, MatchMode::All).unwrap().unwrap();
+            match expand_patterns(query, &backend).await {
+                Err(AppError::InvalidQuery(message)) => assert!(message.contains("more than 200 words"), "{}", message),
+                other => panic!("{:?} expanded to {:?}", q, other.map(|(query, _)| query.to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn author_patterns_expand_from_the_author_vocabulary() {
+        let memory = MemoryBackend::new();
+        memory.add_word_to_index("austen", 1342, IndexField::Author).await.unwrap();
+        memory.add_word_to_index("austere", 2, IndexField::Body).await.unwrap();
+        let backend: Backend = Arc::new(memory);
+
+        let (query, expansions) = expand_query("author:aus*", false, &backend).await;
+        assert_eq!(query, "author:austen");
+        assert_eq!(expansions[0].words, [("austen".to_string(), 1)]);
+        assert_eq!(expand_query("aus*", false, &backend).await.0, "austere");
+        assert_eq!(expand_query("author:\"aus.*\"", true, &backend).await.0, "author:austen");
+    }
+
+    #[tokio::test]
+    async fn regexes_match_whole_words_ignoring_case() {
+        let backend = backend().await;
+
+        assert_eq!(expand_query("COLOU?R", true, &backend).await.0, "color OR colour");
+        // `whal` alone matches no whole word
+        assert_eq!(expand_query("whal", true, &backend).await.0, "whal");
+        assert_eq!(expand_query("\"(whale|ship)\" NOT whaler", true, &backend).await.0, "(whale OR ship) AND NOT whaler");
+    }
+
+    #[test]
+    fn malformed_and_oversized_regexes_are_rejected() {
+        assert_eq!(build_regex("colo(u?r").unwrap_err(), "unclosed group");
+        assert_eq!(build_regex("a{1000}{1000}").unwrap_err(), "too complex to match against the vocabulary");
+        assert!(build_regex(&"a".repeat(MAX_PATTERN_LEN + 1)).unwrap_err().starts_with("longer than"));
+        assert!(build_regex("a)|(b").is_err());
+        assert!(build_regex(r"\w+ing").unwrap().is_match("WHALING"));
+        assert!(!build_regex("whal").unwrap().is_match("whale"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Shared Application State
+//!
+//! Everything the handlers share. Handlers extract only the part they need
+//! (`State<Backend>`, `State<MetadataCache>`, `State<ResultCache>`,
+//! `State<QueryLogger>`, `State<Arc<Instant>>`, `State<StartupProbe>`,
+//! `State<BackendHealth>`, `State<SynonymExpander>`,
+//! `State<Option<SnapshotBackend>>`)
+//! through [`FromRef`].
+
+use crate::models::storage::snapshot::{SnapshotBackend, SnapshotConfig};
+use crate::services::backend_monitor::BackendHealth;
+use crate::services::metadata_cache::MetadataCache;
+use crate::services::query_log::QueryLogger;
+use crate::services::result_cache::ResultCache;
+use crate::services::startup::StartupProbe;
+use crate::services::synonyms::SynonymExpander;
+use crate::services::timeout::RequestTimeouts;
+use crate::Backend;
+use axum::extract::FromRef;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub backend: Backend,
+    pub metadata_cache: MetadataCache,
+    pub result_cache: ResultCache,
+    pub query_logger: QueryLogger,
+    /// When the service started, for the uptime in `/status`.
+    pub started_at: Arc<Instant>,
+    pub startup: StartupProbe,
+    /// Kept up to date by the connection monitor, once spawned.
+    pub backend_health: BackendHealth,
+    pub synonyms: SynonymExpander,
+    /// Also `backend`, when queries are answered from an in-memory snapshot.
+    pub snapshot: Option<SnapshotBackend>,
+    /// Read when building the router, not by handlers.
+    pub timeouts: RequestTimeouts,
+}
+
+impl AppState {
+    /// State around `backend`, with metadata and result caches, query
+    /// logging, synonyms and request timeouts configured from the
+    /// environment.
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            metadata_cache: MetadataCache::from_env(),
+            result_cache: ResultCache::from_env(),
+            query_logger: QueryLogger::from_env(),
+            started_at: Arc::new(Instant::now()),
+            startup: StartupProbe::new(),
+            backend_health: BackendHealth::new(),
+            synonyms: SynonymExpander::from_env(),
+            snapshot: None,
+            timeouts: RequestTimeouts::from_env(),
+        }
+    }
+
+    /// The state with queries answered from an in-memory snapshot of the
+    /// current backend, empty until its first refresh.
+    pub fn with_snapshot(self, config: SnapshotConfig) -> Self {
+        let snapshot = SnapshotBackend::new(self.backend.clone(), config);
+        Self {
+            backend: Arc::new(snapshot.clone()),
+            snapshot: Some(snapshot),
+            ..self
+        }
+    }
+}
+
+impl FromRef<AppState> for Backend {
+    fn from_ref(state: &AppState) -> Self {
+        state.backend.clone()
+    }
+}
+
+impl FromRef<AppState> for MetadataCache {
+    fn from_ref(state: &AppState) -> Self {
+        state.metadata_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for ResultCache {
+    fn from_ref(state: &AppState) -> Self {
+        state.result_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for QueryLogger {
+    fn from_ref(state: &AppState) -> Self {
+        state.query_logger
+    }
+}
+
+impl FromRef<AppState> for Arc<Instant> {
+    fn from_ref(state: &AppState) -> Self {
+        state.started_at.clone()
+    }
+}
+
+impl FromRef<AppState> for StartupProbe {
+    fn from_ref(state: &AppState) -> Self {
+        state.startup.clone()
+    }
+}
+
+impl FromRef<AppState> for BackendHealth {
+    fn from_ref(state: &AppState) -> Self {
+        state.backend_health.clone()
+    }
+}
+
+impl FromRef<AppState> for SynonymExpander {
+    fn from_ref(state: &AppState) -> Self {
+        state.synonyms.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<SnapshotBackend> {
+    fn from_ref(state: &AppState) -> Self {
+        state.snapshot.clone()
+    }
+}
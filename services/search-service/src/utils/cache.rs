@@ -0,0 +1,109 @@
+//! In-Process Caching
+//!
+//! Small caches for responses that are expensive to compute but change
+//! rarely, such as aggregations over every indexed book.
+//!
+//! - [`TtlCache`] — entries expire after a fixed time
+//! - [`VersionedCache`] — a single value kept until the index version changes
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Thread-safe map whose entries expire `ttl` after insertion.
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a clone of the cached value, if present and not expired.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((inserted, value)) if inserted.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+/// Holds one value tagged with the index version it was computed from.
+pub struct VersionedCache<V> {
+    entry: Mutex<Option<(String, V)>>,
+}
+
+impl<V: Clone> VersionedCache<V> {
+    pub const fn new() -> Self {
+        Self {
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached value if it was computed from `version`.
+    pub fn get(&self, version: &str) -> Option<V> {
+        let entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        match entry.as_ref() {
+            Some((cached_version, value)) if cached_version == version => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, version: String, value: V) {
+        *self.entry.lock().unwrap_or_else(|e| e.into_inner()) = Some((version, value));
+    }
+}
+
+impl<V: Clone> Default for VersionedCache<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_fresh_entries() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        cache.insert("authors", vec![1342]);
+
+        assert_eq!(cache.get(&"authors"), Some(vec![1342]));
+        assert_eq!(cache.get(&"languages"), None);
+    }
+
+    #[test]
+    fn expires_entries_after_ttl() {
+        let cache = TtlCache::new(Duration::ZERO);
+        cache.insert("authors", vec![1342]);
+
+        assert_eq!(cache.get(&"authors"), None);
+    }
+
+    #[test]
+    fn versioned_cache_invalidates_on_new_version() {
+        let cache = VersionedCache::new();
+        cache.insert("1".to_string(), 450);
+
+        assert_eq!(cache.get("1"), Some(450));
+        assert_eq!(cache.get("2"), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Chapter Matches
+//!
+//! Chapter postings only say which chapters of a book hold a word, so the
+//! chapters' titles and the number of matches in each are read from the
+//! book's body in the datalake, split by `common_text::chapter`, the same
+//! detection the indexing service numbered the chapters with.
+
+use crate::utils::file::find_body_file;
+use crate::utils::text::tokenize_sequence;
+use common_text::chapter::detect_chapters;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/// A chapter's heading and the number of query words found in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterMatches {
+    pub title: Option<String>,
+    pub occurrences: usize,
+}
+
+/// Matches of `words` in the given chapters of a book's body, by chapter
+/// ID; empty when the body isn't in the datalake. Reads the whole file, so
+/// call it off the async runtime.
+pub fn chapter_matches(book_id: u32, chapter_ids: &[usize], words: &[String]) -> HashMap<usize, ChapterMatches> {
+    let Some(body) = find_body_file(book_id).and_then(|path| fs::read_to_string(path).ok()) else {
+        return HashMap::new();
+    };
+    count_chapter_matches(&body, chapter_ids, words)
+}
+
+/// Matches of `words` in the given chapters of `body`, by chapter ID.
+/// Chapters the body doesn't have are left out.
+pub fn count_chapter_matches(body: &str, chapter_ids: &[usize], words: &[String]) -> HashMap<usize, ChapterMatches> {
+    let words: HashSet<&str> = words.iter().map(String::as_str).collect();
+    detect_chapters(body)
+        .into_iter()
+        .filter(|chapter| chapter_ids.contains(&chapter.id))
+        .map(|chapter| {
+            let occurrences = tokenize_sequence(chapter.text(body))
+                .iter()
+                .filter(|word| words.contains(word.as_str()))
+                .count();
+            (
+                chapter.id,
+                ChapterMatches {
+                    title: chapter.title,
+                    occurrences,
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_every_query_word_in_each_chapter() {
+        let body = "PREFACE\nOf love.\nCHAPTER I\nLove, love and pride.\nCHAPTER II. Balls\nNo love here, only loving pride.\n";
+        let words = ["love".to_string(), "pride".to_string()];
+        let matches = count_chapter_matches(body, &[1, 2, 9], &words);
+
+        assert_eq!(matches.len(), 2);
+        assert_eq!(
+            matches[&1],
+            ChapterMatches {
+                title: Some("CHAPTER I".to_string()),
+                occurrences: 3,
+            }
+        );
+        assert_eq!(matches[&2].title.as_deref(), Some("CHAPTER II. Balls"));
+        assert_eq!(matches[&2].occurrences, 2);
+    }
+}
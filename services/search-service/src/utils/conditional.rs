@@ -0,0 +1,88 @@
+//! Conditional Requests
+//!
+//! `ETag` and `Last-Modified` validators for responses that only change
+//! when the index does, so polling clients can revalidate with
+//! `If-None-Match` (or `If-Modified-Since`) and get an empty `304` back
+//! instead of the same body again.
+//!
+//! ETags are weak (`W/"…"`): the body may be gzipped or not, but means the
+//! same either way.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The validators of one version of a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validators {
+    etag: String,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validators {
+    /// Validators for a response determined by `state`, last changed at
+    /// `last_modified`. `DefaultHasher` is deterministic within a build, so
+    /// replicas of the same build agree on the ETag.
+    pub fn new(state: impl Hash, last_modified: Option<DateTime<Utc>>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        Self {
+            etag: format!("W/\"{:016x}\"", hasher.finish()),
+            last_modified,
+        }
+    }
+
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    /// Whether the client's cached copy, described by `If-None-Match` or,
+    /// without it, `If-Modified-Since`, is still current.
+    pub fn is_fresh(&self, request: &HeaderMap) -> bool {
+        if let Some(if_none_match) = request.get(header::IF_NONE_MATCH) {
+            let Ok(if_none_match) = if_none_match.to_str() else {
+                return false;
+            };
+            return if_none_match.split(',').map(str::trim).any(|tag| tag == "*" || weak_eq(tag, &self.etag));
+        }
+
+        let since = request
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+        match (since, self.last_modified) {
+            // HTTP dates have whole seconds
+            (Some(since), Some(last_modified)) => last_modified.timestamp() <= since.timestamp(),
+            _ => false,
+        }
+    }
+
+    /// An empty `304 Not Modified` carrying the validators.
+    pub fn not_modified(&self) -> Response {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        self.apply(response.headers_mut());
+        response
+    }
+
+    /// Adds `ETag` and, when the time is known, `Last-Modified` to `headers`.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            let date = last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            if let Ok(date) = HeaderValue::from_str(&date) {
+                headers.insert(header::LAST_MODIFIED, date);
+            }
+        }
+    }
+}
+
+/// Weak comparison: the tags match ignoring any `W/` prefix.
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
@@ -0,0 +1,164 @@
+//! Search Result Exports
+//!
+//! `GET /search` answers in the format the `Accept` header asks for: CSV
+//! (`text/csv`) with one row per book, NDJSON (`application/x-ndjson`) with
+//! one `BookResult` object per line, or the usual JSON response. Exports are
+//! streamed, each row serialized as the body is sent, so a large result set
+//! is never held as one serialized buffer.
+
+use crate::models::responses::BookResult;
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use std::borrow::Cow;
+use std::convert::Infallible;
+use tracing::error;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Columns of a CSV export, in order.
+pub const CSV_COLUMNS: &[&str] = &["book_id", "title", "author", "language", "year", "score"];
+
+/// Representation of a search response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl ResultFormat {
+    /// The first format in `Accept` that the service can produce; JSON when
+    /// nothing else is listed. Quality values are not weighed.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+            return ResultFormat::Json;
+        };
+
+        accept
+            .split(',')
+            .filter_map(|range| range.split(';').next())
+            .find_map(|media_type| match media_type.trim().to_ascii_lowercase().as_str() {
+                "text/csv" => Some(ResultFormat::Csv),
+                "application/x-ndjson" => Some(ResultFormat::Ndjson),
+                "application/json" | "application/*" | "*/*" => Some(ResultFormat::Json),
+                _ => None,
+            })
+            .unwrap_or(ResultFormat::Json)
+    }
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break, doubling
+/// embedded quotes (RFC 4180).
+pub fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// One CSV line for `book`, in [`CSV_COLUMNS`] order. An unknown year is
+/// left empty.
+pub fn csv_row(book: &BookResult) -> String {
+    format!(
+        "{},{},{},{},{},{}\r\n",
+        book.book_id,
+        csv_field(&book.title),
+        csv_field(&book.author),
+        csv_field(&book.language),
+        book.year.map(|year| year.to_string()).unwrap_or_default(),
+        book.score,
+    )
+}
+
+fn ndjson_line(book: &BookResult) -> Bytes {
+    let mut line = serde_json::to_vec(book).unwrap_or_else(|e| {
+        error!("Failed to serialize book {} for export: {}", book.book_id, e);
+        Vec::new()
+    });
+    line.push(b'\n');
+    Bytes::from(line)
+}
+
+/// Streams `results` as CSV, with a header row, or as NDJSON.
+pub fn stream_results(format: ResultFormat, results: Vec<BookResult>) -> Response {
+    let (content_type, lines): (&str, Box<dyn Iterator<Item = Bytes> + Send>) = match format {
+        ResultFormat::Csv => {
+            let header = Bytes::from(format!("{}\r\n", CSV_COLUMNS.join(",")));
+            let rows = results.into_iter().map(|book| Bytes::from(csv_row(&book)));
+            (CSV_CONTENT_TYPE, Box::new(std::iter::once(header).chain(rows)))
+        }
+        ResultFormat::Ndjson | ResultFormat::Json => {
+            (NDJSON_CONTENT_TYPE, Box::new(results.into_iter().map(|book| ndjson_line(&book))))
+        }
+    };
+
+    let body = Body::from_stream(tokio_stream::iter(lines.map(Ok::<_, Infallible>)));
+    ([(header::CONTENT_TYPE, HeaderValue::from_static(content_type))], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn book(title: &str, author: &str, year: Option<u32>) -> BookResult {
+        BookResult {
+            book_id: 1342,
+            title: title.to_string(),
+            author: author.to_string(),
+            language: "en".to_string(),
+            year,
+            subjects: Vec::new(),
+            reading_level: None,
+            score: 1.5,
+            highlights: HashMap::new(),
+            chapters: None,
+            snippet: None,
+            also_available_as: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn plain_fields_are_left_alone() {
+        assert_eq!(csv_field("Pride and Prejudice"), "Pride and Prejudice");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn commas_quotes_and_line_breaks_are_quoted() {
+        assert_eq!(csv_field("Austen, Jane"), "\"Austen, Jane\"");
+        assert_eq!(csv_field("The \"Hound\""), "\"The \"\"Hound\"\"\"");
+        assert_eq!(csv_field("Line one\nline two"), "\"Line one\nline two\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+    }
+
+    #[test]
+    fn rows_follow_the_columns() {
+        assert_eq!(
+            csv_row(&book("Pride and Prejudice", "Austen, Jane", Some(1813))),
+            "1342,Pride and Prejudice,\"Austen, Jane\",en,1813,1.5\r\n"
+        );
+        assert_eq!(csv_row(&book("Untitled", "Anonymous", None)), "1342,Untitled,Anonymous,en,,1.5\r\n");
+    }
+
+    #[test]
+    fn accept_picks_the_first_known_format() {
+        let accept = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+            ResultFormat::negotiate(&headers)
+        };
+
+        assert_eq!(ResultFormat::negotiate(&HeaderMap::new()), ResultFormat::Json);
+        assert_eq!(accept("text/csv"), ResultFormat::Csv);
+        assert_eq!(accept("application/x-ndjson; charset=utf-8"), ResultFormat::Ndjson);
+        assert_eq!(accept("text/html, TEXT/CSV;q=0.9, */*;q=0.1"), ResultFormat::Csv);
+        assert_eq!(accept("application/json, text/csv"), ResultFormat::Json);
+        assert_eq!(accept("text/html"), ResultFormat::Json);
+    }
+}
@@ -0,0 +1,30 @@
+//! File Utilities
+//!
+//! Read-only helpers for locating book files in the shared **datalake**.
+//! The search service mounts the same volume as the ingestion and indexing
+//! services, using the same `<date>/<hour>/body_<id>.txt` layout.
+
+use std::fs;
+use std::path::PathBuf;
+
+pub const DATALAKE_PATH: &str = "/app/datalake";
+
+/// Locates the body file of a book, if it exists anywhere in the datalake.
+pub fn find_body_file(book_id: u32) -> Option<PathBuf> {
+    let entries = fs::read_dir(DATALAKE_PATH).ok()?;
+
+    for date_entry in entries.flatten() {
+        if !date_entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        if let Ok(subdir_entries) = fs::read_dir(date_entry.path()) {
+            for subdir_entry in subdir_entries.flatten() {
+                let body_path = subdir_entry.path().join(format!("body_{}.txt", book_id));
+                if body_path.exists() {
+                    return Some(body_path);
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,201 @@
+//! Highlighting Utilities
+//!
+//! Wraps occurrences of query terms in `<mark>` tags so clients can show
+//! *where* a result matched. Matching is case-insensitive and overlapping
+//! or adjacent matches are merged into a single highlighted span.
+//!
+//! The output is meant to be rendered as HTML, so everything outside the
+//! inserted tags is HTML-escaped: a title like `<script>` comes back as
+//! `&lt;script&gt;` rather than markup.
+
+use regex::Regex;
+
+/// Returns `text`, HTML-escaped, with every case-insensitive occurrence of
+/// any term wrapped in `<mark>…</mark>`.
+///
+/// Overlapping matches (`"pri"` and `"ride"` in `"Pride"`) produce one
+/// span covering both, so the output never contains nested tags.
+pub fn apply_highlights(text: &str, terms: &[String]) -> String {
+    wrap_matches(text, terms, "mark")
+}
+
+/// Like [`apply_highlights`], wrapping matches in `<tag>…</tag>`.
+pub fn wrap_matches(text: &str, terms: &[String], tag: &str) -> String {
+    let ranges = merge_ranges(find_match_ranges(text, terms));
+
+    let mut highlighted = String::with_capacity(text.len() + ranges.len() * (2 * tag.len() + 5));
+    let mut cursor = 0;
+    for (start, end) in ranges {
+        push_escaped(&mut highlighted, &text[cursor..start]);
+        highlighted.push_str(&format!("<{}>", tag));
+        push_escaped(&mut highlighted, &text[start..end]);
+        highlighted.push_str(&format!("</{}>", tag));
+        cursor = end;
+    }
+    push_escaped(&mut highlighted, &text[cursor..]);
+
+    highlighted
+}
+
+/// Whether any term occurs in `text`, i.e. whether highlighting it would
+/// mark anything.
+pub fn has_matches(text: &str, terms: &[String]) -> bool {
+    !find_match_ranges(text, terms).is_empty()
+}
+
+/// Appends `text` to `out` with the HTML special characters escaped.
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Collects the byte ranges of all (possibly overlapping) term matches.
+fn find_match_ranges(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+
+    for term in terms.iter().filter(|t| !t.is_empty()) {
+        let re = match Regex::new(&format!("(?i){}", regex::escape(term))) {
+            Ok(re) => re,
+            Err(_) => continue,
+        };
+
+        // Restart one character after each match start so repeated
+        // occurrences of the same term can overlap ("ana" in "banana").
+        let mut pos = 0;
+        while let Some(m) = re.find_at(text, pos) {
+            ranges.push((m.start(), m.end()));
+            pos = m.start() + text[m.start()..].chars().next().map_or(1, char::len_utf8);
+            if pos >= text.len() {
+                break;
+            }
+        }
+    }
+
+    ranges
+}
+
+/// Sorts ranges and merges those that overlap or touch.
+fn merge_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn highlights_case_insensitively() {
+        assert_eq!(
+            apply_highlights("Jane Austen", &terms(&["JANE"])),
+            "<mark>Jane</mark> Austen"
+        );
+    }
+
+    #[test]
+    fn highlights_multiple_terms() {
+        assert_eq!(
+            apply_highlights("Pride and Prejudice", &terms(&["pride", "prejudice"])),
+            "<mark>Pride</mark> and <mark>Prejudice</mark>"
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_terms() {
+        assert_eq!(
+            apply_highlights("Pride and Prejudice", &terms(&["pri", "ride"])),
+            "<mark>Pride</mark> and Prejudice"
+        );
+        assert_eq!(
+            apply_highlights("Prejudice", &terms(&["prej", "judice"])),
+            "<mark>Prejudice</mark>"
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_occurrences_of_same_term() {
+        assert_eq!(
+            apply_highlights("banana", &terms(&["ana"])),
+            "b<mark>anana</mark>"
+        );
+    }
+
+    #[test]
+    fn merges_adjacent_matches() {
+        assert_eq!(
+            apply_highlights("whalebone", &terms(&["whale", "bone"])),
+            "<mark>whalebone</mark>"
+        );
+    }
+
+    #[test]
+    fn leaves_text_untouched_without_matches() {
+        assert_eq!(apply_highlights("Frankenstein", &terms(&["austen"])), "Frankenstein");
+        assert_eq!(apply_highlights("Frankenstein", &terms(&[""])), "Frankenstein");
+        assert_eq!(apply_highlights("Frankenstein", &[]), "Frankenstein");
+    }
+
+    #[test]
+    fn escapes_regex_metacharacters() {
+        assert_eq!(
+            apply_highlights("Is it (really) over?", &terms(&["(really)", "?"])),
+            "Is it <mark>(really)</mark> over<mark>?</mark>"
+        );
+    }
+
+    #[test]
+    fn handles_multibyte_text() {
+        assert_eq!(
+            apply_highlights("Les Misérables", &terms(&["misérables"])),
+            "Les <mark>Misérables</mark>"
+        );
+    }
+
+    #[test]
+    fn escapes_html_around_and_inside_matches() {
+        assert_eq!(
+            apply_highlights("<script>alert('x')</script> & Co", &terms(&["alert"])),
+            "&lt;script&gt;<mark>alert</mark>(&#39;x&#39;)&lt;/script&gt; &amp; Co"
+        );
+        assert_eq!(
+            apply_highlights("Tom & \"Jerry\"", &terms(&["&"])),
+            "Tom <mark>&amp;</mark> &quot;Jerry&quot;"
+        );
+        assert_eq!(apply_highlights("<b>Emma</b>", &[]), "&lt;b&gt;Emma&lt;/b&gt;");
+    }
+
+    #[test]
+    fn reports_whether_anything_matches() {
+        assert!(has_matches("Jane Austen", &terms(&["austen"])));
+        assert!(!has_matches("Jane & Austen", &terms(&["emma"])));
+    }
+
+    #[test]
+    fn wraps_matches_in_any_tag() {
+        assert_eq!(
+            wrap_matches("Call me Ishmael", &terms(&["ishmael"]), "em"),
+            "Call me <em>Ishmael</em>"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Language Utilities
+//!
+//! Book headers record the language either as an ISO 639-1 code (`en`) or
+//! as a name (`English`). These helpers map both forms onto a single code
+//! so per-language statistics don't split one language across two rows.
+
+use crate::models::responses::LanguageEntry;
+use std::collections::HashMap;
+
+/// ISO 639-1 codes and English names of the languages common in Project Gutenberg.
+pub static LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("ca", "Catalan"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("eo", "Esperanto"),
+    ("es", "Spanish"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hu", "Hungarian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("la", "Latin"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("tl", "Tagalog"),
+    ("zh", "Chinese"),
+];
+
+/// Maps a stored language value to `(code, name)`.
+///
+/// Unknown languages keep their original spelling as the name and its
+/// lowercase form as the code.
+pub fn normalize_language(raw: &str) -> (String, String) {
+    let raw = raw.trim();
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(code, name)| raw.eq_ignore_ascii_case(code) || raw.eq_ignore_ascii_case(name))
+        .map(|(code, name)| (code.to_string(), name.to_string()))
+        .unwrap_or_else(|| (raw.to_lowercase(), raw.to_string()))
+}
+
+/// Merges a raw `language -> book count` distribution by language code,
+/// sorted by book count (descending), then code.
+pub fn summarize_languages(distribution: &HashMap<String, usize>) -> Vec<LanguageEntry> {
+    let mut by_code: HashMap<String, LanguageEntry> = HashMap::new();
+
+    for (raw, &count) in distribution {
+        let (code, name) = normalize_language(raw);
+        by_code
+            .entry(code.clone())
+            .or_insert(LanguageEntry {
+                code,
+                name,
+                book_count: 0,
+            })
+            .book_count += count;
+    }
+
+    let mut languages: Vec<LanguageEntry> = by_code.into_values().collect();
+    languages.sort_by(|a, b| b.book_count.cmp(&a.book_count).then_with(|| a.code.cmp(&b.code)));
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_codes_and_names() {
+        assert_eq!(normalize_language("English"), ("en".to_string(), "English".to_string()));
+        assert_eq!(normalize_language("FR"), ("fr".to_string(), "French".to_string()));
+        assert_eq!(normalize_language("Klingon"), ("klingon".to_string(), "Klingon".to_string()));
+    }
+
+    #[test]
+    fn merges_codes_and_names_of_same_language() {
+        let distribution = HashMap::from([("English".to_string(), 3), ("en".to_string(), 2)]);
+        let languages = summarize_languages(&distribution);
+
+        assert_eq!(languages.len(), 1);
+        assert_eq!(languages[0].book_count, 5);
+    }
+
+    #[test]
+    fn french_book_changes_distribution() {
+        let mut distribution = HashMap::from([("English".to_string(), 2)]);
+        assert!(summarize_languages(&distribution).iter().all(|l| l.code != "fr"));
+
+        *distribution.entry("French".to_string()).or_insert(0) += 1;
+        let languages = summarize_languages(&distribution);
+
+        assert_eq!(languages[0].code, "en");
+        assert_eq!(languages[1].code, "fr");
+        assert_eq!(languages[1].name, "French");
+        assert_eq!(languages[1].book_count, 1);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod cache;
+pub mod chapter;
+pub mod conditional;
+pub mod export;
+pub mod file;
+pub mod highlight;
+pub mod language;
+pub mod passage;
+pub mod snippet;
+pub mod text;
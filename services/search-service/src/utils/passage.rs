@@ -0,0 +1,46 @@
+//! Passage Text
+//!
+//! The passage index only stores `(book_id, sentence_id)` pairs, so the text
+//! of a matching sentence is read back from the book's body in the datalake
+//! and split with `common_text::split_sentences`, the same splitter the
+//! indexing service numbered the sentences with.
+
+use crate::utils::file::find_body_file;
+use crate::utils::text::split_sentences;
+use std::collections::HashMap;
+use std::fs;
+
+/// Text of the given sentences of a book's body, by sentence ID. Sentences
+/// past the end of the body, or every sentence when the body isn't in the
+/// datalake, are left out. Reads the whole file, so call it off the async
+/// runtime.
+pub fn sentence_texts(book_id: u32, sentence_ids: &[usize]) -> HashMap<usize, String> {
+    let Some(body) = find_body_file(book_id).and_then(|path| fs::read_to_string(path).ok()) else {
+        return HashMap::new();
+    };
+    select_sentences(&body, sentence_ids)
+}
+
+/// The given sentences of `body`, by sentence ID.
+pub fn select_sentences(body: &str, sentence_ids: &[usize]) -> HashMap<usize, String> {
+    let mut sentences: Vec<Option<String>> = split_sentences(body).into_iter().map(Some).collect();
+    sentence_ids
+        .iter()
+        .filter_map(|&id| Some((id, sentences.get_mut(id)?.take()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_sentences_by_position() {
+        let body = "Call me Ishmael. Some years ago, having little\nmoney, I went to sea.\n\nThe whale was white.";
+        let texts = select_sentences(body, &[1, 2, 7]);
+
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts[&1], "Some years ago, having little money, I went to sea.");
+        assert_eq!(texts[&2], "The whale was white.");
+    }
+}
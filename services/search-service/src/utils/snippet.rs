@@ -0,0 +1,212 @@
+//! Snippet Extraction
+//!
+//! Builds short context snippets from a book's body text so search results
+//! can show where the query matched inside the book.
+//!
+//! Bodies can run to megabytes, so they are streamed line by line, stopping
+//! once every term has been found or after [`MAX_SCAN_BYTES`]. The context
+//! found for each `(book, term)` pair is cached, so repeating a query, or
+//! sharing a term with an earlier one, doesn't read the body again.
+
+use crate::utils::file::find_body_file;
+use crate::utils::text::tokenize_sequence;
+use moka::sync::Cache;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Number of words kept on each side of the first match.
+const CONTEXT_WORDS: usize = 15;
+
+/// Most bytes of a body read while looking for the terms.
+pub const MAX_SCAN_BYTES: u64 = 4 * 1024 * 1024;
+
+const CACHE_MAX_ENTRIES: u64 = 10_000;
+const CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// The words around the first occurrence of a term in a body.
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    /// Position of the occurrence among the body's words.
+    position: usize,
+    text: String,
+}
+
+/// Windows by `(book_id, term)`; `None` when the term isn't in the scanned
+/// part of the body or the body isn't available.
+fn window_cache() -> &'static Cache<(u32, String), Option<Window>> {
+    static CACHE: OnceLock<Cache<(u32, String), Option<Window>>> = OnceLock::new();
+    CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(CACHE_MAX_ENTRIES)
+            .time_to_live(CACHE_TTL)
+            .build()
+    })
+}
+
+/// Extracts `±CONTEXT_WORDS` words around the first occurrence of any term
+/// in the body of `book_id`, unhighlighted.
+///
+/// Returns `None` when the body is not available in the datalake or none
+/// of the terms occur in it. Reads the file, so call it off the async
+/// runtime.
+pub fn extract_snippet(book_id: u32, terms: &[String]) -> Option<String> {
+    let cache = window_cache();
+    let mut windows = Vec::with_capacity(terms.len());
+    let mut missing = Vec::new();
+    for term in terms {
+        match cache.get(&(book_id, term.clone())) {
+            Some(window) => windows.extend(window),
+            None => missing.push(term.clone()),
+        }
+    }
+
+    if !missing.is_empty() {
+        let mut found = find_body_file(book_id)
+            .and_then(|path| File::open(path).ok())
+            .map(|file| scan_windows(BufReader::new(file), &missing, MAX_SCAN_BYTES))
+            .unwrap_or_else(|| vec![None; missing.len()]);
+        for (term, window) in missing.into_iter().zip(found.iter_mut()) {
+            cache.insert((book_id, term), window.clone());
+            windows.extend(window.take());
+        }
+    }
+
+    windows
+        .into_iter()
+        .min_by_key(|window| window.position)
+        .map(|window| window.text)
+}
+
+/// A window still collecting the words after its occurrence.
+struct OpenWindow {
+    term: usize,
+    position: usize,
+    words: Vec<String>,
+}
+
+/// Streams `reader` for the first word equal (case-insensitively) to each of
+/// `terms`, reading at most `max_bytes`, and returns each term's window in
+/// the order of `terms`.
+fn scan_windows(reader: impl BufRead, terms: &[String], max_bytes: u64) -> Vec<Option<Window>> {
+    // A single line may be huge, so the cap applies within lines too
+    let mut reader = reader.take(max_bytes);
+    let mut windows: Vec<Option<Window>> = vec![None; terms.len()];
+    let mut open: Vec<OpenWindow> = Vec::new();
+    let mut before: VecDeque<String> = VecDeque::with_capacity(CONTEXT_WORDS);
+    let mut position = 0;
+    let mut line = String::new();
+
+    let close = |window: OpenWindow, windows: &mut Vec<Option<Window>>| {
+        windows[window.term] = Some(Window {
+            position: window.position,
+            text: window.words.join(" "),
+        });
+    };
+
+    loop {
+        line.clear();
+        // A cap falling inside a multi-byte character ends the scan too
+        if !matches!(reader.read_line(&mut line), Ok(n) if n > 0) {
+            break;
+        }
+
+        for word in line.split_whitespace() {
+            for window in &mut open {
+                window.words.push(word.to_string());
+            }
+            let (done, still_open): (Vec<_>, Vec<_>) = open
+                .drain(..)
+                .partition(|window| window.words.len() > window.position.min(CONTEXT_WORDS) + CONTEXT_WORDS);
+            open = still_open;
+            for window in done {
+                close(window, &mut windows);
+            }
+
+            // Tokenized like the index, so `whale's` and `Whale,` match `whale`
+            let tokens = tokenize_sequence(word);
+            for (term, _) in terms.iter().enumerate().filter(|(_, t)| tokens.contains(t)) {
+                if windows[term].is_none() && !open.iter().any(|window| window.term == term) {
+                    let mut words: Vec<String> = before.iter().cloned().collect();
+                    words.push(word.to_string());
+                    open.push(OpenWindow { term, position, words });
+                }
+            }
+
+            if before.len() == CONTEXT_WORDS {
+                before.pop_front();
+            }
+            before.push_back(word.to_string());
+            position += 1;
+        }
+
+        if open.is_empty() && windows.iter().all(Option::is_some) {
+            break;
+        }
+    }
+
+    // The body or the scan ended before these windows filled up
+    for window in open {
+        close(window, &mut windows);
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn terms(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    fn numbered(count: usize, marker: (usize, &str)) -> String {
+        (0..count)
+            .map(|i| if i == marker.0 { marker.1.to_string() } else { format!("w{}", i) })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn keeps_context_on_both_sides() {
+        let body = numbered(100, (50, "Whale,"));
+        let windows = scan_windows(Cursor::new(body), &terms(&["whale"]), MAX_SCAN_BYTES);
+
+        let window = windows[0].as_ref().unwrap();
+        assert_eq!(window.position, 50);
+        let words: Vec<&str> = window.text.split(' ').collect();
+        assert_eq!(words.len(), 2 * CONTEXT_WORDS + 1);
+        assert_eq!((words[0], words[15], words[30]), ("w35", "Whale,", "w65"));
+    }
+
+    #[test]
+    fn clips_context_at_the_edges() {
+        let body = "Call me Ishmael. Some years ago";
+        let windows = scan_windows(Cursor::new(body), &terms(&["call", "ago", "pequod"]), MAX_SCAN_BYTES);
+
+        assert_eq!(windows[0].as_ref().unwrap().text, body);
+        assert_eq!(windows[1].as_ref().unwrap().position, 5);
+        assert_eq!(windows[2], None);
+    }
+
+    #[test]
+    fn matches_words_as_the_index_tokenizes_them() {
+        let body = "the whale's jaw and the Harpoon—line";
+        let windows = scan_windows(Cursor::new(body), &terms(&["whale", "line"]), MAX_SCAN_BYTES);
+
+        assert_eq!(windows[0].as_ref().unwrap().position, 1);
+        assert_eq!(windows[1].as_ref().unwrap().position, 5);
+    }
+
+    #[test]
+    fn spans_lines_and_stops_at_the_byte_cap() {
+        let body = format!("{}\n{}\n", numbered(10, (9, "ship")), numbered(1000, (999, "whale")));
+        let windows = scan_windows(Cursor::new(&body), &terms(&["ship", "whale"]), 100);
+
+        assert_eq!(windows[0].as_ref().unwrap().text.split(' ').count(), 10 + CONTEXT_WORDS);
+        assert_eq!(windows[1], None);
+    }
+}
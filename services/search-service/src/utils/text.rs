@@ -0,0 +1,45 @@
+//! Text Utilities
+//!
+//! Queries are split into words by the shared `common-text` crate, the same
+//! tokenizer the indexing service runs on books, so every term looked up can
+//! exist in the postings.
+
+pub use common_text::{index_terms, split_sentences, tokenize_sequence, TokenizerConfig, MIN_WORD_LEN};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Lowercases `text` and strips its diacritics (`Émile` → `emile`), for
+/// comparisons that shouldn't depend on either.
+pub fn fold_diacritics(text: &str) -> String {
+    text.nfd().filter(|c| !is_combining_mark(*c)).collect::<String>().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_like_the_indexer() {
+        assert_eq!(index_terms("Netherfield, Longbourn!"), ["netherfield", "longbourn"]);
+        assert_eq!(index_terms("the whale's jaw"), ["the", "whale", "jaw"]);
+        assert_eq!(index_terms("Mr. Darcy—proud"), ["darcy", "proud"]);
+    }
+
+    #[test]
+    fn drops_words_the_index_never_holds() {
+        assert!(index_terms("it, is, of x86").is_empty());
+        assert_eq!(index_terms("café society"), ["society"]);
+        assert!(index_terms("\u{212A}elvin").is_empty());
+    }
+
+    #[test]
+    fn keeps_first_occurrence_order_without_duplicates() {
+        assert_eq!(index_terms("Whale WHALE ocean whale"), ["whale", "ocean"]);
+    }
+
+    #[test]
+    fn folds_case_and_diacritics() {
+        assert_eq!(fold_diacritics("Émile, ou De l'éducation"), "emile, ou de l'education");
+        assert_eq!(fold_diacritics("Noël"), "noel");
+        assert_eq!(fold_diacritics("Moby Dick"), "moby dick");
+    }
+}
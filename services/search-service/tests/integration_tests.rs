@@ -13,7 +13,9 @@ async fn test_health_check() {
     assert_eq!(response.status(), 200);
 
     let body: Value = response.json().await.expect("Failed to parse JSON");
-    assert_eq!(body["status"], "running");
+    // Degraded until books are indexed
+    assert!(body["status"] == "running" || body["status"] == "degraded");
+    assert_eq!(body["checks"]["backend_reachable"], true);
     assert_eq!(body["service"], "search-service");
 }
 
@@ -29,4 +31,121 @@ async fn test_basic_search() {
     assert_eq!(body["query"], "test");
     assert!(body["results"].is_array());
     assert!(body["count"].is_number());
-}
\ No newline at end of file
+    assert!(body["total_count"].as_u64() >= body["count"].as_u64());
+}
+
+#[tokio::test]
+async fn test_streamed_search_matches_blocking_search() {
+    let stream = reqwest::get("http://0.0.0.0:7003/search/stream?q=love")
+        .await
+        .expect("Failed to make request");
+    assert_eq!(stream.status(), 200);
+    let body = stream.text().await.expect("Failed to read stream");
+
+    let mut streamed = Vec::new();
+    let mut summary = None;
+    for event in body.split("\n\n").filter(|event| !event.trim().is_empty()) {
+        let field = |name: &str| event.lines().find_map(|line| line.strip_prefix(name));
+        let data: Value = serde_json::from_str(field("data: ").expect("event without data")).unwrap();
+        match field("event: ") {
+            Some("result") => streamed.push(data["book_id"].as_u64().unwrap()),
+            Some("summary") => summary = Some(data),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    let summary = summary.expect("stream ended without a summary");
+    assert_eq!(summary["total_count"].as_u64(), Some(streamed.len() as u64));
+
+    let blocking: Value = reqwest::get("http://0.0.0.0:7003/search?q=love&export=true")
+        .await
+        .expect("Failed to make request")
+        .json()
+        .await
+        .expect("Failed to parse JSON");
+    let mut expected: Vec<u64> = blocking["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["book_id"].as_u64().unwrap())
+        .collect();
+    streamed.sort_unstable();
+    expected.sort_unstable();
+    assert_eq!(streamed, expected);
+}
+
+#[tokio::test]
+async fn test_authors_sorted_alphabetically() {
+    let response = reqwest::get("http://0.0.0.0:7003/search/authors?per_page=200")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let names: Vec<String> = body["authors"]
+        .as_array()
+        .expect("authors should be an array")
+        .iter()
+        .map(|author| author["name"].as_str().unwrap().to_lowercase())
+        .collect();
+
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+}
+
+#[tokio::test]
+async fn test_authors_prefix_filter() {
+    let response = reqwest::get("http://0.0.0.0:7003/search/authors?prefix=jane")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    for author in body["authors"].as_array().expect("authors should be an array") {
+        let name = author["name"].as_str().unwrap().to_lowercase();
+        assert!(name.starts_with("jane"), "unexpected author {}", name);
+        assert_eq!(
+            author["book_count"].as_u64().unwrap() as usize,
+            author["book_ids"].as_array().unwrap().len()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_languages_sorted_by_book_count() {
+    let response = reqwest::get("http://0.0.0.0:7003/search/languages")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let counts: Vec<u64> = body["languages"]
+        .as_array()
+        .expect("languages should be an array")
+        .iter()
+        .map(|language| language["book_count"].as_u64().unwrap())
+        .collect();
+
+    assert!(counts.windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+#[tokio::test]
+async fn test_boolean_query() {
+    let response = reqwest::get("http://0.0.0.0:7003/search?q=whale%20OR%20ship%20NOT%20pequod")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 200);
+
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["parsed_query"], "whale OR ship AND NOT pequod");
+
+    let response = reqwest::get("http://0.0.0.0:7003/search?q=whale%20AND")
+        .await
+        .expect("Failed to make request");
+
+    assert_eq!(response.status(), 400);
+}
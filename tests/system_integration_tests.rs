@@ -15,20 +15,280 @@ async fn test_all_services_health() {
     let client = reqwest::Client::new();
 
     // Test ingestion service
-    let response = client.get(&format!("{}/status", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
+    let response = client.get(format!("{}/status", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
     assert_eq!(response.status(), 200);
     let body: Value = response.json().await.expect("Failed to parse JSON");
     assert_eq!(body["service"], "ingestion-service");
 
     // Test indexing service
-    let response = client.get(&format!("{}/status", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    let response = client.get(format!("{}/status", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
     assert_eq!(response.status(), 200);
     let body: Value = response.json().await.expect("Failed to parse JSON");
     assert_eq!(body["service"], "indexing-service");
 
     // Test search service
-    let response = client.get(&format!("{}/status", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    let response = client.get(format!("{}/status", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
     assert_eq!(response.status(), 200);
     let body: Value = response.json().await.expect("Failed to parse JSON");
     assert_eq!(body["service"], "search-service");
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_year_distribution_covers_indexed_books() {
+    let client = reqwest::Client::new();
+
+    // Frankenstein and Pride and Prejudice
+    let mut decades = Vec::new();
+    for (book_id, query) in [(84, "frankenstein"), (1342, "prejudice")] {
+        let response = client.post(format!("{}/ingest/{}", INGESTION_BASE_URL, book_id)).send().await.expect("Failed to reach ingestion service");
+        assert!(response.status().is_success());
+
+        let response = client.post(format!("{}/index/update/{}", INDEXING_BASE_URL, book_id)).send().await.expect("Failed to reach indexing service");
+        assert_eq!(response.status(), 200);
+
+        let response = client.get(format!("{}/search?q={}", SEARCH_BASE_URL, query)).send().await.expect("Failed to reach search service");
+        let body: Value = response.json().await.expect("Failed to parse JSON");
+        let book = body["results"]
+            .as_array()
+            .and_then(|results| results.iter().find(|r| r["book_id"] == book_id))
+            .expect("Indexed book should be searchable");
+        decades.push(book["year"].as_u64().map(|year| (year / 10) * 10));
+    }
+
+    let response = client.get(format!("{}/search/years", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let buckets = body["decades"].as_array().expect("decades should be an array");
+
+    for decade in decades {
+        assert!(
+            buckets.iter().any(|bucket| bucket["decade"].as_u64() == decade && bucket["book_count"].as_u64() > Some(0)),
+            "decade {:?} missing from distribution",
+            decade
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_year_range_filter() {
+    let client = reqwest::Client::new();
+
+    // Pride and Prejudice (1813) and Frankenstein (1818)
+    for book_id in [1342, 84] {
+        let response = client.post(format!("{}/ingest/{}", INGESTION_BASE_URL, book_id)).send().await.expect("Failed to reach ingestion service");
+        assert!(response.status().is_success());
+
+        let response = client.post(format!("{}/index/update/{}", INDEXING_BASE_URL, book_id)).send().await.expect("Failed to reach indexing service");
+        assert_eq!(response.status(), 200);
+    }
+
+    // Both books contain "letter"; only Frankenstein is from 1815 or later
+    let response = client.get(format!("{}/search?q=letter&year_from=1815", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["filters"]["year_from"], "1815");
+
+    let book_ids: Vec<u64> = body["results"]
+        .as_array()
+        .expect("results should be an array")
+        .iter()
+        .filter_map(|result| result["book_id"].as_u64())
+        .collect();
+    assert!(book_ids.contains(&84), "Frankenstein (1818) should match");
+    assert!(!book_ids.contains(&1342), "Pride and Prejudice (1813) should be filtered out");
+    for result in body["results"].as_array().unwrap() {
+        assert!(result["year"].as_u64().is_some_and(|year| year >= 1815));
+    }
+}
+
+#[tokio::test]
+async fn test_search_matches_body_words() {
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/ingest/1342", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
+    assert!(response.status().is_success());
+    let response = client.post(format!("{}/index/update/1342", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    assert_eq!(response.status(), 200);
+
+    // Netherfield only occurs in the text of Pride and Prejudice, never in
+    // its title or author; the trailing comma must not matter either
+    let response = client.get(format!("{}/search?q=Netherfield,", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let results = body["results"].as_array().expect("results should be an array");
+    assert!(results.iter().any(|result| result["book_id"] == 1342), "book 1342 should match a body-only word");
+}
+
+#[tokio::test]
+async fn test_author_filter() {
+    let client = reqwest::Client::new();
+
+    // Frankenstein (Shelley) and Pride and Prejudice (Austen)
+    for book_id in [84, 1342] {
+        let response = client.post(format!("{}/ingest/{}", INGESTION_BASE_URL, book_id)).send().await.expect("Failed to reach ingestion service");
+        assert!(response.status().is_success());
+
+        let response = client.post(format!("{}/index/update/{}", INDEXING_BASE_URL, book_id)).send().await.expect("Failed to reach indexing service");
+        assert_eq!(response.status(), 200);
+    }
+
+    let response = client.get(format!("{}/search?q=love&author=austen", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["filters"]["author"], "austen");
+    let results = body["results"].as_array().expect("results should be an array");
+    assert!(results.iter().any(|result| result["book_id"] == 1342));
+    for result in results {
+        assert!(result["author"].as_str().unwrap().to_lowercase().contains("austen"));
+    }
+
+    // An empty author is no filter at all
+    let response = client.get(format!("{}/search?q=love&author=", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert!(body["filters"].get("author").is_none());
+    let results = body["results"].as_array().expect("results should be an array");
+    assert!(results.iter().any(|result| result["book_id"] == 84));
+    assert!(results.iter().any(|result| result["book_id"] == 1342));
+}
+
+#[tokio::test]
+async fn test_book_lookup() {
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/ingest/1342", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
+    assert!(response.status().is_success());
+    let response = client.post(format!("{}/index/update/1342", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    assert_eq!(response.status(), 200);
+
+    let response = client.get(format!("{}/search/book/1342", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(body["book_id"], 1342);
+    assert!(body["title"].as_str().unwrap().contains("Pride"));
+    assert!(body["related_words_count"].as_u64() > Some(0));
+
+    let response = client.get(format!("{}/search/book/4294967295", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_relevance_feedback() {
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/ingest/1342", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
+    assert!(response.status().is_success());
+    let response = client.post(format!("{}/index/update/1342", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    assert_eq!(response.status(), 200);
+
+    // Feedback accumulates across runs, so compare against the current totals
+    let stats_url = format!("{}/search/feedback/stats?query=love", SEARCH_BASE_URL);
+    let before: Value = client.get(&stats_url).send().await.expect("Failed to reach search service").json().await.expect("Failed to parse JSON");
+
+    let feedback = serde_json::json!({ "query": "love", "book_id": 1342, "relevant": true });
+    let response = client.post(format!("{}/search/feedback", SEARCH_BASE_URL)).json(&feedback).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 201);
+
+    let response = client.get(&stats_url).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let after: Value = response.json().await.expect("Failed to parse JSON");
+    assert_eq!(after["query"], "love");
+    assert_eq!(after["positive"].as_u64(), before["positive"].as_u64().map(|positive| positive + 1));
+    assert_eq!(after["negative"], before["negative"]);
+    let top = after["top_relevant_books"].as_array().expect("top_relevant_books should be an array");
+    assert!(top.iter().any(|book| book["book_id"] == 1342));
+}
+
+#[tokio::test]
+async fn test_index_size_is_reported() {
+    let client = reqwest::Client::new();
+
+    // Pride and Prejudice, Frankenstein and Alice's Adventures in Wonderland
+    for book_id in [1342, 84, 11] {
+        let response = client.post(format!("{}/ingest/{}", INGESTION_BASE_URL, book_id)).send().await.expect("Failed to reach ingestion service");
+        assert!(response.status().is_success());
+        let response = client.post(format!("{}/index/update/{}", INDEXING_BASE_URL, book_id)).send().await.expect("Failed to reach indexing service");
+        assert_eq!(response.status(), 200);
+    }
+
+    let response = client.get(format!("{}/index/status", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    assert!(body["index_size_mb"].as_f64().expect("index_size_mb should be a number") > 0.0);
+}
+
+#[tokio::test]
+async fn test_search_facets() {
+    let client = reqwest::Client::new();
+
+    for book_id in [1342, 84] {
+        let response = client.post(format!("{}/ingest/{}", INGESTION_BASE_URL, book_id)).send().await.expect("Failed to reach ingestion service");
+        assert!(response.status().is_success());
+        let response = client.post(format!("{}/index/update/{}", INDEXING_BASE_URL, book_id)).send().await.expect("Failed to reach indexing service");
+        assert_eq!(response.status(), 200);
+    }
+
+    let response = client.get(format!("{}/search?q=love&facets=language,decade&limit=1", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+
+    // Facets count every match, not just the single result returned
+    let total: u64 = body["facets"]["language"].as_object().expect("language facet should be an object").values().filter_map(Value::as_u64).sum();
+    assert_eq!(Some(total), body["total_count"].as_u64());
+    assert!(body["facets"]["decade"].is_object());
+}
+
+#[tokio::test]
+async fn test_search_cache_invalidation() {
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/ingest/1342", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
+    assert!(response.status().is_success());
+    let response = client.post(format!("{}/index/update/1342", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    assert_eq!(response.status(), 200);
+
+    let response = client.post(format!("{}/cache/invalidate?book_id=1342", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+
+    let url = format!("{}/search?q=pemberley&limit=3", SEARCH_BASE_URL);
+    let first = client.get(&url).send().await.expect("Failed to reach search service");
+    assert_eq!(first.headers()["x-cache"], "miss");
+    let second = client.get(&url).send().await.expect("Failed to reach search service");
+    assert_eq!(second.headers()["x-cache"], "hit");
+
+    let response = client.post(format!("{}/cache/invalidate", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let third = client.get(&url).send().await.expect("Failed to reach search service");
+    assert_eq!(third.headers()["x-cache"], "miss");
+
+    let metrics = client.get(format!("{}/metrics", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert!(metrics.text().await.expect("Failed to read metrics").contains("search_cache_hits_total"));
+}
+
+/// Runs against whichever backend the services were started with; run the
+/// suite once with `BACKEND_TYPE=redis` and once with `BACKEND_TYPE=postgres`.
+#[tokio::test]
+async fn test_pipeline_on_configured_backend() {
+    let client = reqwest::Client::new();
+
+    // Search must read the store indexing writes to
+    let mut backend_types = Vec::new();
+    for base_url in [INDEXING_BASE_URL, SEARCH_BASE_URL] {
+        let response = client.get(format!("{}/status", base_url)).send().await.expect("Failed to reach service");
+        let body: Value = response.json().await.expect("Failed to parse JSON");
+        assert_eq!(body["backend_connected"], true, "{} can't reach its backend", base_url);
+        backend_types.push(body["backend_type"].as_str().expect("backend_type should be a string").to_string());
+    }
+    assert_eq!(backend_types[0], backend_types[1], "indexing and search use different backends");
+    let backend_type = &backend_types[0];
+
+    let response = client.post(format!("{}/ingest/84", INGESTION_BASE_URL)).send().await.expect("Failed to reach ingestion service");
+    assert!(response.status().is_success());
+    let response = client.post(format!("{}/index/update/84", INDEXING_BASE_URL)).send().await.expect("Failed to reach indexing service");
+    assert_eq!(response.status(), 200);
+
+    let response = client.get(format!("{}/search?q=frankenstein", SEARCH_BASE_URL)).send().await.expect("Failed to reach search service");
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.expect("Failed to parse JSON");
+    let results = body["results"].as_array().expect("results should be an array");
+    assert!(results.iter().any(|result| result["book_id"] == 84), "book 84 should be searchable on {}", backend_type);
+}
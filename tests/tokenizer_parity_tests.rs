@@ -0,0 +1,93 @@
+// ============================================================
+//  Tokenizer parity tests
+//
+//  A query term can only match if the indexing service stored
+//  the word spelled the same way. These tests run tricky text
+//  through both services' tokenizers and expect the same words.
+// ============================================================
+
+use indexing_service::utils::text::{tokenize_body, tokenize_text, tokenize_text_fast, TokenizerConfig};
+use search_service::services::query::parse_query;
+use search_service::services::search::MatchMode;
+use search_service::utils::text::index_terms;
+use std::collections::HashSet;
+
+const TRICKY_INPUTS: &[&str] = &[
+    // Punctuation
+    "Whale, whale; WHALE! whale? (whale) [ship] {ocean}",
+    "Mr. Darcy—proud, and Elizabeth…prejudiced",
+    "well-known sea-faring men / either-or",
+    "“Quoted,” said he; «guillemets» and 'single'",
+    // Apostrophes
+    "the whale's jaw, the whales' jaws",
+    "Don’t won't shan't o'clock rock'n'roll",
+    // Accents and other scripts
+    "café naïve résumé Émile façade",
+    "Zürich São Paulo Ærøskøbing İstanbul",
+    "Москва and 東京 beside London",
+    "\u{212A}elvin Kelvin",
+    // Mixed case
+    "ALLCAPS lowercase CamelCase mIxEd",
+    "Moby DICK moby dick MoBy DiCk",
+    // Digits, underscores and common words
+    "x86 10pm 3rd snake_case under_score the and with",
+    "",
+];
+
+/// What the indexing service stores for a book whose body is `text`.
+fn indexed(text: &str) -> HashSet<String> {
+    let words = tokenize_body(text);
+    assert_eq!(words, tokenize_text(text), "fast and regex tokenizers disagree on {text:?}");
+    assert_eq!(words, tokenize_text_fast(text));
+    words
+}
+
+/// The terms the search service looks up when `text` is the query.
+fn queried(text: &str) -> HashSet<String> {
+    parse_query(text, MatchMode::Any)
+        .unwrap_or_else(|e| panic!("{text:?} failed to parse: {e}"))
+        .map(|query| query.positive_terms().into_iter().collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn indexing_and_query_parsing_produce_the_same_tokens() {
+    for text in TRICKY_INPUTS {
+        let indexed = indexed(text);
+        assert_eq!(queried(text), indexed, "query tokens differ for {text:?}");
+        assert_eq!(index_terms(text).into_iter().collect::<HashSet<_>>(), indexed, "{text:?}");
+    }
+}
+
+#[test]
+fn every_indexed_word_is_found_by_a_query_for_itself() {
+    let words: HashSet<String> = TRICKY_INPUTS.iter().flat_map(|text| indexed(text)).collect();
+    assert!(words.len() > 20);
+    for word in words {
+        assert_eq!(queried(&word), HashSet::from([word.clone()]));
+    }
+}
+
+#[test]
+fn tricky_inputs_tokenize_as_expected() {
+    let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect::<HashSet<_>>();
+
+    assert_eq!(indexed("the whale's jaw, the whales' jaws"), words(&["the", "whale", "jaw", "whales", "jaws"]));
+    assert_eq!(indexed("café naïve résumé Émile façade"), words(&[]));
+    assert_eq!(indexed("\u{212A}elvin Kelvin"), words(&["kelvin"]));
+    assert_eq!(indexed("Moby DICK moby dick MoBy DiCk"), words(&["moby", "dick"]));
+}
+
+#[test]
+fn folded_indexing_and_queries_agree() {
+    let folding = TokenizerConfig {
+        fold_diacritics: true,
+        ..TokenizerConfig::DEFAULT
+    };
+    for text in TRICKY_INPUTS {
+        let indexed = folding.tokenize_text_fast(text);
+        assert_eq!(indexed, folding.tokenize_text(text), "{text:?}");
+        assert_eq!(folding.index_terms(text).into_iter().collect::<HashSet<_>>(), indexed, "{text:?}");
+    }
+    assert!(folding.tokenize_text_fast("café naïve résumé Émile façade").contains("facade"));
+}